@@ -0,0 +1,134 @@
+// Events per second of the core engine under representative workloads.
+// Run with `cargo bench -p dscale`, compare against a baseline with `--save-baseline` / `--baseline`.
+
+use criterion::{Criterion, Throughput, criterion_group, criterion_main};
+use dscale::*;
+
+const BROADCAST_INTERVAL: Jiffies = Jiffies(10);
+const TICK_INTERVAL: Jiffies = Jiffies(1);
+
+type Workload = fn() -> RunDigest;
+
+struct Payload;
+
+impl Message for Payload {
+    fn virtual_size(&self) -> usize {
+        100
+    }
+}
+
+// Every process periodically broadcasts to all others: network queue dominates
+#[derive(Default)]
+struct Broadcaster;
+
+impl ProcessHandle for Broadcaster {
+    fn start(&mut self) {
+        schedule_timer_after(BROADCAST_INTERVAL);
+    }
+
+    fn on_message(&mut self, _from: ProcessId, _message: MessagePtr) {}
+
+    fn on_timer(&mut self, _id: TimerId) {
+        broadcast(Payload);
+        schedule_timer_after(BROADCAST_INTERVAL);
+    }
+}
+
+// Many processes with many short timers: timer queue dominates
+#[derive(Default)]
+struct Ticker;
+
+impl ProcessHandle for Ticker {
+    fn start(&mut self) {
+        (0..10).for_each(|i| {
+            schedule_timer_after(TICK_INTERVAL + Jiffies(i));
+        });
+    }
+
+    fn on_message(&mut self, _from: ProcessId, _message: MessagePtr) {}
+
+    fn on_timer(&mut self, _id: TimerId) {
+        schedule_timer_after(TICK_INTERVAL);
+    }
+}
+
+// Senders flood a single receiver over bounded NICs: bandwidth buffers dominate
+#[derive(Default)]
+struct Flooder;
+
+impl ProcessHandle for Flooder {
+    fn start(&mut self) {
+        if list_pool("Receivers").contains(&rank()) {
+            return;
+        }
+        schedule_timer_after(TICK_INTERVAL);
+    }
+
+    fn on_message(&mut self, _from: ProcessId, _message: MessagePtr) {}
+
+    fn on_timer(&mut self, _id: TimerId) {
+        send_random_from_pool("Receivers", Payload);
+        schedule_timer_after(TICK_INTERVAL);
+    }
+}
+
+fn broadcast_heavy() -> RunDigest {
+    let mut sim = SimulationBuilder::default()
+        .add_pool::<Broadcaster>("Broadcasters", 50)
+        .latency_topology(&[LatencyDescription::WithinPool(
+            "Broadcasters",
+            Distributions::Uniform(Jiffies(1), Jiffies(10)),
+        )])
+        .time_budget(Jiffies(2_000))
+        .seed(1)
+        .build();
+    sim.run();
+    sim.digest()
+}
+
+fn timer_heavy() -> RunDigest {
+    let mut sim = SimulationBuilder::default()
+        .add_pool::<Ticker>("Tickers", 1_000)
+        .time_budget(Jiffies(100))
+        .seed(1)
+        .build();
+    sim.run();
+    sim.digest()
+}
+
+fn bounded_bandwidth() -> RunDigest {
+    let mut sim = SimulationBuilder::default()
+        .add_pool::<Flooder>("Senders", 20)
+        .add_pool::<Flooder>("Receivers", 2)
+        .nic_bandwidth(BandwidthDescription::Bounded(1_000))
+        .latency_topology(&[LatencyDescription::BetweenPools(
+            "Senders",
+            "Receivers",
+            Distributions::Uniform(Jiffies(1), Jiffies(10)),
+        )])
+        .time_budget(Jiffies(5_000))
+        .seed(1)
+        .build();
+    sim.run();
+    sim.digest()
+}
+
+fn engine(c: &mut Criterion) {
+    let workloads: [(&str, Workload); 3] = [
+        ("broadcast_heavy", broadcast_heavy),
+        ("timer_heavy", timer_heavy),
+        ("bounded_bandwidth", bounded_bandwidth),
+    ];
+
+    let mut group = c.benchmark_group("engine");
+    group.sample_size(10);
+    for (name, workload) in workloads {
+        // Runs are deterministic, so every iteration executes the same number of events
+        group.throughput(Throughput::Elements(workload().events as u64));
+        group.bench_function(name, |b| b.iter(workload));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, engine);
+criterion_main!(benches);
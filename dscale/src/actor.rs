@@ -1,16 +1,141 @@
+//! Extension point for user-defined simulation actors.
+//!
+//! The engine is driven by actors: every actor owns a queue of future events and
+//! the simulation repeatedly steps the actor with the earliest one. The network,
+//! timers and scenario are actors themselves. Custom actors added with
+//! [`SimulationBuilder::add_actor`] take part in the same event loop, so global
+//! entities such as an oracle, a feed of external events or a background chaos
+//! agent do not have to be disguised as processes.
+//!
+//! [`SimulationBuilder::add_actor`]: crate::SimulationBuilder::add_actor
+
 use std::{cell::RefCell, rc::Rc};
 
-use crate::time::Jiffies;
+use crate::{
+    ProcessId,
+    global::{self, tracing},
+    time::Jiffies,
+};
 
 pub(crate) type SharedActor = Rc<RefCell<dyn SimulationActor>>;
 
-pub(crate) trait SimulationActor {
+/// A source of events driven by the simulation engine.
+///
+/// On every step the engine asks all actors for their closest event with
+/// [`peek_closest`], advances the clock to the earliest one and calls [`step`]
+/// of its actor. Events of the same jiffy are ordered by the tie breaker of the
+/// simulation, so custom actors stay deterministic. No actor starves the others
+/// at one jiffy: see [`SimulationBuilder::actor_event_budget`].
+///
+/// Actors are not processes: they never crash and receive no messages. To act on
+/// behalf of a process, e.g. send a message from it, wrap the call in
+/// [`as_process`].
+///
+/// # Examples
+///
+/// ```rust
+/// use std::{cell::RefCell, rc::Rc};
+/// use dscale::{SimulationBuilder, SimulationActor, Jiffies, now, global::anykv};
+///
+/// // Counts time passing in steps of 100 jiffies
+/// struct Metronome {
+///     next: Jiffies,
+/// }
+///
+/// impl SimulationActor for Metronome {
+///     fn start(&mut self) {
+///         self.next = now() + Jiffies(100);
+///     }
+///
+///     fn step(&mut self) {
+///         anykv::modify::<usize>("ticks", |ticks| *ticks += 1);
+///         self.next += Jiffies(100);
+///     }
+///
+///     fn peek_closest(&self) -> Option<Jiffies> {
+///         Some(self.next)
+///     }
+/// }
+///
+/// anykv::set::<usize>("ticks", 0);
+///
+/// let mut simulation = SimulationBuilder::default()
+///     .add_actor(Rc::new(RefCell::new(Metronome { next: Jiffies(0) })))
+///     .time_budget(Jiffies(10_000))
+///     .build();
+///
+/// simulation.step_until(Jiffies(1_000));
+/// assert_eq!(anykv::get::<usize>("ticks"), 10);
+/// ```
+///
+/// [`peek_closest`]: SimulationActor::peek_closest
+/// [`step`]: SimulationActor::step
+/// [`SimulationBuilder::actor_event_budget`]: crate::SimulationBuilder::actor_event_budget
+pub trait SimulationActor {
+    /// Called once before the first step, after all processes are started.
     fn start(&mut self);
+
+    /// Executes the event returned by [`peek_closest`]. The simulation clock is
+    /// already at its time.
+    ///
+    /// [`peek_closest`]: SimulationActor::peek_closest
     fn step(&mut self);
+
+    /// Returns the time of the closest event, or `None` if there is nothing to do.
+    ///
+    /// Returning a time in the past makes the event execute at the current time.
     fn peek_closest(&self) -> Option<Jiffies>;
 }
 
-pub(crate) trait EventSubmitter {
+/// An actor accepting batches of events from outside of the event loop.
+///
+/// The network and timers receive messages and timers of processes through this
+/// trait. Custom actors may implement it for their own events, e.g. to let the
+/// caller feed a schedule between [`Simulation::step_until`] calls.
+///
+/// [`Simulation::step_until`]: crate::Simulation::step_until
+pub trait EventSubmitter {
+    /// Type of events accepted by the actor.
     type Event;
+
+    /// Takes submitted events, leaving `events` empty.
     fn submit(&mut self, events: &mut Vec<Self::Event>);
 }
+
+/// Runs `f` on behalf of process `id`.
+///
+/// Meant for [`SimulationActor`] implementations: messages sent and timers
+/// scheduled inside `f` belong to process `id`, and [`rank`] returns `id`. The
+/// previous process context is restored afterwards.
+///
+/// # Examples
+///
+/// ```rust
+/// use dscale::{actor::as_process, rank};
+/// # use dscale::{SimulationBuilder, ProcessHandle, ProcessId, MessagePtr, TimerId, Jiffies};
+/// # #[derive(Default)]
+/// # struct Idle;
+/// # impl ProcessHandle for Idle {
+/// #     fn start(&mut self) {}
+/// #     fn on_message(&mut self, from: ProcessId, message: MessagePtr) {}
+/// #     fn on_timer(&mut self, id: TimerId) {}
+/// # }
+/// # let mut simulation = SimulationBuilder::default().add_pool::<Idle>("nodes", 2).build();
+///
+/// assert_eq!(as_process(2, rank), 2);
+/// ```
+///
+/// # Panics
+///
+/// Panics if the process is crashed, or if called outside of a simulation.
+///
+/// [`rank`]: crate::rank
+pub fn as_process<T>(id: ProcessId, f: impl FnOnce() -> T) -> T {
+    assert!(!global::is_crashed(id), "P{id} is crashed");
+    let previous = global::rank();
+    global::set_process(id);
+    tracing::on_step();
+    let result = f();
+    global::set_process(previous);
+    result
+}
@@ -0,0 +1,190 @@
+//! Self-contained bundles of run artifacts.
+//!
+//! A result is only as good as the ability to reproduce and share it. With
+//! [`SimulationBuilder::artifacts_dir`] (or the `--artifacts-dir <dir>` command
+//! line argument of any simulation binary) every [`Simulation::run`] writes a
+//! bundle into `<dir>/seed-<seed>/`:
+//!
+//! - `config.txt`: snapshot of the builder configuration.
+//! - `seed.txt`, `digest.txt`: seed and [`RunDigest`] of the run.
+//! - `processes.csv`: per-process summary (pool, crash state, pending events,
+//!   filtered messages, memory usage).
+//! - `metrics.csv`: values recorded with [`metric`].
+//! - `trace.txt`: causal message trace, if tracing is enabled.
+//! - `attachments/`: files attached with [`attach`], e.g. DAG or graph dumps.
+//! - `failure.txt`: only for failed runs: the panic or deadlock, pending events,
+//!   stuck quorums and captured logs of the failing process.
+//! - `interrupted.txt`: only for runs stopped with Ctrl-C (`interrupt` feature):
+//!   the time reached and pending events, as in `failure.txt`.
+//!
+//! Times are given in jiffies. With [`SimulationBuilder::jiffy_duration`] set
+//! they are also given in real-world units: `metrics.csv` fills its `seconds`
+//! column, `trace.txt` starts with the duration of a jiffy and reports annotate
+//! times, e.g. `Jiffies(1500) [1.5s]`.
+//!
+//! [`SimulationBuilder::artifacts_dir`]: crate::SimulationBuilder::artifacts_dir
+//! [`SimulationBuilder::jiffy_duration`]: crate::SimulationBuilder::jiffy_duration
+//! [`Simulation::run`]: crate::Simulation::run
+//! [`RunDigest`]: crate::RunDigest
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::BTreeMap,
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{Jiffies, ProcessId, global, now, random::Seed};
+
+const ARGUMENT: &str = "--artifacts-dir";
+
+#[derive(Default)]
+struct Collected {
+    metrics: Vec<(Jiffies, ProcessId, String, f64)>,
+    attachments: BTreeMap<String, String>,
+}
+
+thread_local! {
+    static ENABLED: Cell<bool> = const { Cell::new(false) }; // Nothing is collected without a bundle
+    static COLLECTED: RefCell<Collected> = RefCell::new(Collected::default());
+}
+
+pub(crate) fn setup_artifacts(enabled: bool) {
+    ENABLED.set(enabled);
+}
+
+pub(crate) fn drop_artifacts() {
+    ENABLED.take();
+    COLLECTED.take();
+}
+
+/// Records the value of a metric at the current time into `metrics.csv`.
+///
+/// Within a process the value is attributed to it, values recorded by the
+/// experiment driver between steps are attributed to process 0. Does nothing
+/// unless the simulation writes artifacts.
+///
+/// # Examples
+///
+/// ```rust
+/// use dscale::{ProcessHandle, ProcessId, MessagePtr, TimerId};
+/// use dscale::artifacts;
+///
+/// #[derive(Default)]
+/// struct Replica {
+///     log: Vec<MessagePtr>,
+/// }
+///
+/// impl ProcessHandle for Replica {
+///     fn start(&mut self) {}
+///
+///     fn on_message(&mut self, from: ProcessId, message: MessagePtr) {
+///         self.log.push(message);
+///         artifacts::metric("log_length", self.log.len() as f64);
+///     }
+///
+///     fn on_timer(&mut self, id: TimerId) {}
+/// }
+/// ```
+pub fn metric(name: &str, value: f64) {
+    if !ENABLED.get() {
+        return;
+    }
+    let process = global::try_rank().unwrap_or(0);
+    COLLECTED.with_borrow_mut(|collected| {
+        collected
+            .metrics
+            .push((now(), process, name.to_string(), value))
+    });
+}
+
+/// Attaches a file named `name` with `contents` to the bundle.
+///
+/// Meant for dumps which only make sense to the protocol, e.g. a DAG in DOT
+/// format. A later attachment with the same name replaces the earlier one.
+/// Does nothing unless the simulation writes artifacts.
+pub fn attach(name: &str, contents: impl Into<String>) {
+    if !ENABLED.get() {
+        return;
+    }
+    COLLECTED.with_borrow_mut(|collected| {
+        collected
+            .attachments
+            .insert(name.to_string(), contents.into())
+    });
+}
+
+// Directory given with --artifacts-dir <dir> or --artifacts-dir=<dir>
+pub(crate) fn dir_from_args() -> Option<PathBuf> {
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == ARGUMENT {
+            return args.next().map(PathBuf::from);
+        }
+        if let Some(dir) = arg
+            .strip_prefix(ARGUMENT)
+            .and_then(|arg| arg.strip_prefix('='))
+        {
+            return Some(PathBuf::from(dir));
+        }
+    }
+    None
+}
+
+// Everything of a run which goes into its bundle
+pub(crate) struct Bundle {
+    dir: PathBuf,
+    seed: Seed,
+    config: String,
+}
+
+impl Bundle {
+    pub(crate) fn new(root: impl AsRef<Path>, seed: Seed, config: String) -> Self {
+        Self {
+            dir: root.as_ref().join(format!("seed-{seed}")),
+            seed,
+            config,
+        }
+    }
+
+    // Files computed by the simulation, collected values are added here
+    pub(crate) fn write(&self, files: Vec<(&str, String)>) {
+        fs::create_dir_all(&self.dir).expect("Unable to create artifacts directory");
+        let write = |name: &str, contents: &str| {
+            fs::write(self.dir.join(name), contents).expect("Unable to write artifact");
+        };
+        write("config.txt", &self.config);
+        write("seed.txt", &format!("{}\n", self.seed));
+        files
+            .iter()
+            .for_each(|(name, contents)| write(name, contents));
+
+        COLLECTED.with_borrow(|collected| {
+            let mut metrics = String::from("time,process,metric,value,seconds\n");
+            collected
+                .metrics
+                .iter()
+                .for_each(|(time, process, name, value)| {
+                    let seconds = time
+                        .to_duration()
+                        .map_or(String::new(), |time| time.as_secs_f64().to_string());
+                    let _ = writeln!(metrics, "{},{process},{name},{value},{seconds}", time.0);
+                });
+            write("metrics.csv", &metrics);
+
+            if !collected.attachments.is_empty() {
+                fs::create_dir_all(self.dir.join("attachments"))
+                    .expect("Unable to create artifacts directory");
+            }
+            collected
+                .attachments
+                .iter()
+                .for_each(|(name, contents)| write(&format!("attachments/{name}"), contents));
+        });
+    }
+
+    pub(crate) fn dir(&self) -> &Path {
+        &self.dir
+    }
+}
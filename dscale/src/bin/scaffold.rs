@@ -0,0 +1,94 @@
+// Creates a new protocol crate wired up to DScale: a process, its messages, a
+// checker of invariants and a main running the simulation.
+//
+// Usage: cargo run -p dscale --bin scaffold -- <name> [directory]
+//
+// The crate is created in <directory>/<name> (systems/<name> by default, which
+// the workspace picks up on its own). Templates are in templates/protocol,
+// where {{name}} stands for the crate name and {{Name}} for its CamelCase form.
+// Crates created elsewhere need their dscale dependency pointed at crates.io.
+
+use std::{fs, path::Path, process::exit};
+
+const TEMPLATE: &[(&str, &str)] = &[
+    (
+        "Cargo.toml",
+        include_str!("../../templates/protocol/Cargo.toml.template"),
+    ),
+    (
+        "src/lib.rs",
+        include_str!("../../templates/protocol/src/lib.rs"),
+    ),
+    (
+        "src/main.rs",
+        include_str!("../../templates/protocol/src/main.rs"),
+    ),
+    (
+        "src/messages.rs",
+        include_str!("../../templates/protocol/src/messages.rs"),
+    ),
+    (
+        "src/process.rs",
+        include_str!("../../templates/protocol/src/process.rs"),
+    ),
+    (
+        "src/checker.rs",
+        include_str!("../../templates/protocol/src/checker.rs"),
+    ),
+];
+
+fn fail(message: &str) -> ! {
+    eprintln!("scaffold: {message}");
+    exit(1)
+}
+
+// Crate names double as module paths, so only snake_case is accepted
+fn is_valid(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_lowercase())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+}
+
+fn camel_case(name: &str) -> String {
+    name.split('_')
+        .flat_map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|first| first.to_ascii_uppercase())
+                .into_iter()
+                .chain(chars)
+        })
+        .collect()
+}
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let Some(name) = args.next() else {
+        fail("usage: scaffold <name> [directory]");
+    };
+    if !is_valid(&name) {
+        fail(&format!(
+            "'{name}' is not a snake_case crate name (e.g. two_phase_commit)"
+        ));
+    }
+    let root = Path::new(&args.next().unwrap_or("systems".to_string())).join(&name);
+    if root.exists() {
+        fail(&format!("{} already exists", root.display()));
+    }
+
+    let camel = camel_case(&name);
+    for (path, template) in TEMPLATE {
+        let path = root.join(path);
+        let contents = template
+            .replace("{{name}}", &name)
+            .replace("{{Name}}", &camel);
+        fs::create_dir_all(path.parent().expect("Template files are in the crate"))
+            .and_then(|_| fs::write(&path, contents))
+            .unwrap_or_else(|error| fail(&format!("can not write {}: {error}", path.display())));
+    }
+
+    println!("Created {}", root.display());
+    println!("Run it with: cargo run --release -p {name}");
+}
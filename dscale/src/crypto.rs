@@ -0,0 +1,215 @@
+//! Sizes of cryptographic material in messages.
+//!
+//! Protocols do not compute real signatures in simulation, yet signatures,
+//! digests and certificates dominate the size of many consensus messages. This
+//! module provides the [`SizeModel`] every protocol computes such sizes from,
+//! configured once per simulation with [`SimulationBuilder::size_model`], so
+//! switching e.g. from Ed25519 multi-signatures to BLS aggregates needs no
+//! protocol edits.
+//!
+//! It also provides [`VrfProof`], a simulated verifiable random function for
+//! leader election and sortition.
+//!
+//! [`SimulationBuilder::size_model`]: crate::SimulationBuilder::size_model
+
+use crate::{
+    ProcessId,
+    global::{anykv, configuration},
+    rank,
+};
+
+pub(crate) const SIZE_MODEL_KEY: &str = "crypto/size_model";
+
+/// How many bytes an aggregate of signatures of several signers takes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Aggregation {
+    /// Signatures are not aggregated, an aggregate carries every signature
+    /// (e.g. Ed25519 multi-signatures).
+    Concatenated,
+    /// The aggregate has the same size regardless of the number of signers
+    /// (e.g. BLS signatures).
+    Constant(usize),
+}
+
+/// Sizes of signatures, digests and certificates in bytes.
+///
+/// The default model describes Ed25519 signatures (64 bytes), SHA-256 digests
+/// (32 bytes), BLS12-381 aggregates for certificates (96 bytes) and ECVRF proofs
+/// (80 bytes).
+///
+/// # Examples
+///
+/// ```rust
+/// use dscale::SimulationBuilder;
+/// use dscale::crypto::{Aggregation, SizeModel};
+///
+/// // Secp256k1 signatures without aggregation
+/// let model = SizeModel {
+///     signature: 65,
+///     aggregation: Aggregation::Concatenated,
+///     ..SizeModel::default()
+/// };
+/// assert_eq!(model.aggregated_signature(3), 3 * 65);
+///
+/// // Quorum certificate of 67 signers out of 100 processes:
+/// // digest + 67 signatures + bitmap of signers
+/// assert_eq!(model.certificate(67, 100), 32 + 67 * 65 + 13);
+///
+/// let builder = SimulationBuilder::default().size_model(model);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SizeModel {
+    /// Bytes of a single signature.
+    pub signature: usize,
+    /// Bytes of a digest (hash) of a message.
+    pub digest: usize,
+    /// Size of aggregated signatures.
+    pub aggregation: Aggregation,
+    /// Bytes of a VRF proof, which carries its output.
+    pub vrf_proof: usize,
+}
+
+impl Default for SizeModel {
+    fn default() -> Self {
+        Self {
+            signature: 64,
+            digest: 32,
+            aggregation: Aggregation::Constant(96),
+            vrf_proof: 80,
+        }
+    }
+}
+
+impl SizeModel {
+    /// Bytes of an aggregate of signatures of `signers` processes.
+    pub fn aggregated_signature(&self, signers: usize) -> usize {
+        match self.aggregation {
+            Aggregation::Concatenated => self.signature * signers,
+            Aggregation::Constant(size) => size,
+        }
+    }
+
+    /// Bytes of a certificate: a digest of the certified message signed by
+    /// `signers` out of `n` processes, together with a bitmap of the signers.
+    pub fn certificate(&self, signers: usize, n: usize) -> usize {
+        self.digest + self.aggregated_signature(signers) + n.div_ceil(8)
+    }
+}
+
+/// Returns the size model of the current simulation.
+///
+/// The model is configured with [`SimulationBuilder::size_model`], the default
+/// one is returned otherwise.
+///
+/// [`SimulationBuilder::size_model`]: crate::SimulationBuilder::size_model
+pub fn size_model() -> SizeModel {
+    anykv::try_get::<SizeModel>(SIZE_MODEL_KEY).unwrap_or_default()
+}
+
+// Separates VRF keys from generators seeded with the plain process seed
+const VRF_KEY_STREAM: u64 = 0x7672_665f_6b65_7973;
+
+/// Output of a simulated verifiable random function together with its proof.
+///
+/// Every process holds a VRF key derived from its seed. Evaluating the VRF on an
+/// input gives a pseudorandom output which nobody can predict without the key,
+/// while anybody can check with [`verify`] that the output belongs to the prover
+/// and the input. Outputs are deterministic in the seed of the simulation.
+///
+/// No real cryptography is performed: proofs are checked by recomputing the
+/// output, and their bytes are [`SizeModel::vrf_proof`]. Protocols model the time
+/// of verification like other checks, with [`configuration::cpu_time`].
+///
+/// Fields are public, so Byzantine processes can forge proofs, which fail to
+/// verify.
+///
+/// # Examples
+///
+/// ```rust
+/// use dscale::crypto::VrfProof;
+/// # use dscale::{SimulationBuilder, ProcessHandle, ProcessId, MessagePtr, TimerId};
+/// # #[derive(Default)]
+/// # struct Validator;
+/// # impl ProcessHandle for Validator {
+/// #     fn start(&mut self) {}
+/// #     fn on_message(&mut self, from: ProcessId, message: MessagePtr) {}
+/// #     fn on_timer(&mut self, id: TimerId) {}
+/// # }
+/// # use dscale::Jiffies;
+/// # let mut simulation = SimulationBuilder::default().add_pool::<Validator>("validators", 4).build();
+/// # simulation.step_until(Jiffies(1));
+///
+/// // Leader of round 7: the lowest output among the validators
+/// let round = 7;
+/// let leader = (1..=4)
+///     .map(|validator| VrfProof::of(validator, round))
+///     .min_by_key(|proof| proof.output)
+///     .unwrap();
+/// assert!(leader.verify());
+///
+/// // A leader claiming a better output is caught
+/// let forged = VrfProof { output: 0, ..leader };
+/// assert!(!forged.verify());
+/// ```
+///
+/// [`verify`]: VrfProof::verify
+/// [`configuration::cpu_time`]: crate::global::configuration::cpu_time
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct VrfProof {
+    /// Process whose key produced the output.
+    pub prover: ProcessId,
+    /// Input the VRF was evaluated on, e.g. a round number.
+    pub input: u64,
+    /// Pseudorandom output.
+    pub output: u64,
+}
+
+impl VrfProof {
+    /// Evaluates the VRF of the current process on `input`.
+    ///
+    /// Should be called from process context.
+    pub fn prove(input: u64) -> Self {
+        Self::of(rank(), input)
+    }
+
+    /// Evaluates the VRF of process `prover` on `input`.
+    ///
+    /// Processes should call [`prove`] for themselves and verify proofs of
+    /// others. This function stands for a proof `prover` would publish, e.g. when
+    /// all processes are assumed to have exchanged their proofs, or for analysis
+    /// outside of processes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the simulation has not started yet, keys are generated along
+    /// with process seeds.
+    ///
+    /// [`prove`]: VrfProof::prove
+    pub fn of(prover: ProcessId, input: u64) -> Self {
+        let key = configuration::seed_of(prover) ^ VRF_KEY_STREAM;
+        Self {
+            prover,
+            input,
+            output: mix(mix(key) ^ input),
+        }
+    }
+
+    /// Whether the output was produced by the key of the prover on the input.
+    pub fn verify(&self) -> bool {
+        *self == Self::of(self.prover, self.input)
+    }
+
+    /// Output as a uniform fraction in `[0, 1)`, e.g. for sortition with
+    /// probability proportional to stake.
+    pub fn fraction(&self) -> f64 {
+        (self.output >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+// SplitMix64 finalizer
+fn mix(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
@@ -3,4 +3,5 @@ use crate::ProcessId;
 pub enum Destination {
     BroadcastWithinPool(&'static str),
     To(ProcessId),
+    Members(Vec<ProcessId>),
 }
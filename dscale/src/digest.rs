@@ -0,0 +1,69 @@
+//! Compact fingerprint of a simulation run.
+//!
+//! Every process step (start, message delivery, timer firing) is folded into
+//! an order-sensitive hash, so two runs have equal digests only if they executed
+//! the same steps at the same times in the same order.
+
+use crate::{Jiffies, ProcessId, dscale_message::DScaleMessage};
+
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/// Summary of all process steps executed by a [`Simulation`].
+///
+/// Obtained with [`Simulation::digest`]. The hash is computed with FNV-1a over
+/// plain integers, so it is stable across platforms and compiler versions and
+/// can be stored in golden files (see [`helpers::Golden`]).
+///
+/// Message contents are not hashed, only their [`Message::virtual_size`].
+///
+/// [`Simulation`]: crate::Simulation
+/// [`Simulation::digest`]: crate::Simulation::digest
+/// [`helpers::Golden`]: crate::helpers::Golden
+/// [`Message::virtual_size`]: crate::Message::virtual_size
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RunDigest {
+    /// Number of executed process steps.
+    pub events: usize,
+    /// Order-sensitive hash of (time, source, destination, event) of every step.
+    pub trace_hash: u64,
+}
+
+impl Default for RunDigest {
+    fn default() -> Self {
+        Self {
+            events: 0,
+            trace_hash: FNV_OFFSET,
+        }
+    }
+}
+
+impl RunDigest {
+    pub(crate) fn record_start(&mut self, now: Jiffies, id: ProcessId) {
+        self.record(&[now.0, id, id, 0, 0]);
+    }
+
+    pub(crate) fn record_step(
+        &mut self,
+        now: Jiffies,
+        from: ProcessId,
+        to: ProcessId,
+        event: &DScaleMessage,
+    ) {
+        match event {
+            DScaleMessage::NetworkMessage(_, size) => self.record(&[now.0, from, to, 1, *size]),
+            DScaleMessage::Timer(id) => self.record(&[now.0, from, to, 2, *id]),
+        }
+    }
+
+    fn record(&mut self, words: &[usize]) {
+        self.events += 1;
+        words
+            .iter()
+            .flat_map(|word| (*word as u64).to_le_bytes())
+            .for_each(|byte| {
+                self.trace_hash ^= byte as u64;
+                self.trace_hash = self.trace_hash.wrapping_mul(FNV_PRIME);
+            });
+    }
+}
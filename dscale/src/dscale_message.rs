@@ -1,6 +1,6 @@
 use crate::{MessagePtr, TimerId};
 
 pub(crate) enum DScaleMessage {
-    NetworkMessage(MessagePtr),
+    NetworkMessage(MessagePtr, usize), // usize - virtual size
     Timer(TimerId),
 }
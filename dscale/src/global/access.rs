@@ -7,29 +7,38 @@ use crate::{
     Message, ProcessId,
     actor::EventSubmitter,
     debug_process,
-    network::NetworkActor,
+    global::{configuration, errors, tracing},
+    message::{Shared, SharedMessage},
+    message_type,
+    network::{NetworkActor, SharedReachability},
+    nursery::Nursery,
     random::Randomizer,
     time::{
         Jiffies,
         timer_manager::{TimerId, TimerManagerActor, next_timer_id},
     },
     topology::{GLOBAL_POOL, Topology},
+    transport,
 };
 
 pub struct SimulationAccess {
     process_on_execution: ProcessId,
-    pub(crate) scheduled_messages: Vec<(ProcessId, Destination, Rc<dyn Message>)>,
+    pub(crate) scheduled_messages: Vec<(ProcessId, Destination, SharedMessage, Jiffies)>,
     pub(crate) scheduled_timers: Vec<(ProcessId, TimerId, Jiffies)>,
     topology: Rc<Topology>,
     random: Randomizer,
     network: NetworkActor,
     timers: TimerManagerActor,
+    nursery: Rc<Nursery>,
+    reachability: SharedReachability,
 }
 
 impl SimulationAccess {
     pub(crate) fn new(
         network: NetworkActor,
         timers: TimerManagerActor,
+        nursery: Rc<Nursery>,
+        reachability: SharedReachability,
         topology: Rc<Topology>,
         random: Randomizer,
     ) -> Self {
@@ -40,6 +49,8 @@ impl SimulationAccess {
             topology,
             network,
             timers,
+            nursery,
+            reachability,
             random,
         }
     }
@@ -56,25 +67,93 @@ impl SimulationAccess {
         self.topology.list_pool(name)
     }
 
+    fn list_reachable_pool(&self, name: &str) -> Vec<ProcessId> {
+        self.reachability
+            .borrow()
+            .reachable(self.process_on_execution, self.topology.list_pool(name))
+    }
+
     fn choose_from_pool(&mut self, name: &str) -> ProcessId {
-        self.random
-            .choose_from_slice(&self.topology.list_pool(name))
+        let chosen = self
+            .random
+            .choose_from_slice(&self.topology.list_pool(name));
+        self.on_draw(|| format!("choose_from_pool {name}"), &[chosen]);
+        chosen
+    }
+
+    fn choose_nearest_from_pool(&mut self, name: &str) -> ProcessId {
+        let nearest = self
+            .topology
+            .nearest_in_pool(self.process_on_execution, name);
+        let chosen = self.random.choose_from_slice(&nearest);
+        self.on_draw(|| format!("choose_nearest_from_pool {name}"), &[chosen]);
+        chosen
+    }
+
+    fn on_draw(&self, context: impl FnOnce() -> String, values: &[ProcessId]) {
+        tracing::on_draw("access", Some(self.process_on_execution), context, values);
+    }
+
+    fn region_of(&self, id: ProcessId) -> Option<&'static str> {
+        self.topology.region_of(id)
+    }
+
+    fn expected_latency(&self, to: ProcessId) -> Option<f64> {
+        self.topology
+            .expected_latency(self.process_on_execution, to)
+    }
+
+    fn pool_member(&self, pool: &str, index: usize) -> Option<ProcessId> {
+        self.topology.pool_member(pool, index)
+    }
+
+    fn pool_of(&self, id: ProcessId) -> Option<(String, usize)> {
+        self.topology.pool_of(id)
     }
 
     fn broadcast_within_pool(&mut self, pool_name: &'static str, message: impl Message + 'static) {
-        self.scheduled_messages.push((
-            self.process_on_execution,
-            Destination::BroadcastWithinPool(pool_name),
-            Rc::new(message),
-        ));
+        self.schedule_message(Destination::BroadcastWithinPool(pool_name), message, now());
     }
 
     fn send_to(&mut self, to: ProcessId, message: impl Message + 'static) {
-        self.scheduled_messages.push((
+        self.schedule_message(Destination::To(to), message, now());
+    }
+
+    fn schedule_message<M: Message + 'static>(
+        &mut self,
+        destination: Destination,
+        message: M,
+        departure: Jiffies,
+    ) {
+        self.schedule_shared(destination, Shared::new(message), departure);
+    }
+
+    fn schedule_shared(
+        &mut self,
+        destination: Destination,
+        message: SharedMessage,
+        departure: Jiffies,
+    ) {
+        tracing::on_send(
             self.process_on_execution,
-            Destination::To(to),
-            Rc::new(message),
-        ));
+            &destination,
+            &message,
+            message_type::of(message.as_ref()).name,
+        );
+        self.scheduled_messages
+            .push((self.process_on_execution, destination, message, departure));
+    }
+
+    fn send_to_pool_quorum(&mut self, pool: &str, message: impl Message + 'static, k: usize) {
+        let members = self.topology.list_pool(pool);
+        assert!(
+            k <= members.len(),
+            "Quorum of {k} exceeds {} members of pool {pool}",
+            members.len()
+        );
+        let quorum = self.random.choose_multiple_from_slice(members, k);
+        self.on_draw(|| format!("send_to_pool_quorum {pool}"), &quorum);
+        self.schedule_message(Destination::Members(quorum), message, now());
     }
 
     fn send_random_from_pool(&mut self, pool: &str, message: impl Message + 'static) {
@@ -116,11 +195,20 @@ pub(crate) fn drop_access() {
 pub(crate) fn setup_access(
     network: NetworkActor,
     timers: TimerManagerActor,
+    nursery: Rc<Nursery>,
+    reachability: SharedReachability,
     topology: Rc<Topology>,
     random: Randomizer,
 ) {
     ACCESS_HANDLE.with_borrow_mut(|access| {
-        *access = Some(SimulationAccess::new(network, timers, topology, random))
+        *access = Some(SimulationAccess::new(
+            network,
+            timers,
+            nursery,
+            reachability,
+            topology,
+            random,
+        ))
     });
 }
 
@@ -131,6 +219,11 @@ where
     ACCESS_HANDLE.with_borrow_mut(|access| f(access.as_mut().expect("Out of simulation context")))
 }
 
+// Ground truth for oracles such as the perfect failure detector
+pub(crate) fn is_crashed(id: ProcessId) -> bool {
+    with_access(|access| access.nursery.is_crashed(id))
+}
+
 pub(crate) fn set_process(id: ProcessId) {
     with_access(|access| access.set_process(id));
 }
@@ -141,22 +234,83 @@ pub(crate) fn schedule() {
 
 pub fn schedule_timer_after(after: Jiffies) -> TimerId {
     debug_process!("Access: scheduling timer after {after}");
+    if let Some(hosted) = transport::hosted() {
+        return hosted.borrow_mut().schedule_timer_after(after);
+    }
     with_access(|access| access.schedule_timer_after(after))
 }
 
 pub fn broadcast(message: impl Message + 'static) {
     debug_process!("Access: broadcasting globally");
+    if let Some(hosted) = transport::hosted() {
+        return hosted
+            .borrow_mut()
+            .broadcast_within_pool(GLOBAL_POOL, Shared::new(message));
+    }
     with_access(|access| access.broadcast_within_pool(GLOBAL_POOL, message));
 }
 
+// Sends to every member of the pool. The sender does not have to be a member
+// (e.g. a client addressing replicas); if it is, it gets the message too.
 pub fn broadcast_within_pool(pool: &'static str, message: impl Message + 'static) {
     debug_process!("Access: broadcasting within: {pool}");
+    if let Some(hosted) = transport::hosted() {
+        return hosted
+            .borrow_mut()
+            .broadcast_within_pool(pool, Shared::new(message));
+    }
     with_access(|access| access.broadcast_within_pool(pool, message));
 }
 
 pub fn send_to(to: ProcessId, message: impl Message + 'static) {
     debug_process!("Access: send to: {to}");
-    with_access(|access| access.send_to(to, message));
+    if let Some(hosted) = transport::hosted() {
+        return hosted.borrow_mut().send_to(to, Shared::new(message));
+    }
+    if exists(to) {
+        with_access(|access| access.send_to(to, message));
+    }
+}
+
+// Sends a message received or created earlier without wrapping it again
+pub(crate) fn send_shared(to: ProcessId, message: SharedMessage) {
+    debug_process!("Access: send to: {to}");
+    if let Some(hosted) = transport::hosted() {
+        return hosted.borrow_mut().send_to(to, message);
+    }
+    if exists(to) {
+        with_access(|access| access.schedule_shared(Destination::To(to), message, now()));
+    }
+}
+
+// Message leaves the sender at `at` as if send_to() was called then.
+// Pending sends are dropped if the sender crashes or restarts before that.
+pub fn send_to_at(to: ProcessId, message: impl Message + 'static, at: Jiffies) {
+    debug_process!("Access: send to: {to} at {at}");
+    assert!(at >= now(), "Can not send a message in the past");
+    if exists(to) {
+        with_access(|access| access.schedule_message(Destination::To(to), message, at));
+    }
+}
+
+// Messages to unknown processes are protocol errors and get dropped
+fn exists(to: ProcessId) -> bool {
+    let exists = (1..=configuration::process_number()).contains(&to);
+    if !exists {
+        errors::report(format_args!("Message sent to unknown P{to}"));
+    }
+    exists
+}
+
+pub fn broadcast_after(message: impl Message + 'static, delay: Jiffies) {
+    debug_process!("Access: broadcasting globally after {delay}");
+    with_access(|access| {
+        access.schedule_message(
+            Destination::BroadcastWithinPool(GLOBAL_POOL),
+            message,
+            now() + delay,
+        )
+    });
 }
 
 pub fn send_random(message: impl Message + 'static) {
@@ -164,21 +318,78 @@ pub fn send_random(message: impl Message + 'static) {
     with_access(|access| access.send_random_from_pool(GLOBAL_POOL, message));
 }
 
+// Sends to `k` distinct members of the pool chosen at random, e.g. for quorum reads
+// without a full broadcast. Membership of the sender is treated as in broadcast_within_pool.
+pub fn send_to_pool_quorum(pool: &str, message: impl Message + 'static, k: usize) {
+    debug_process!("Access: sending to {k} random members of pool: {pool}");
+    with_access(|access| access.send_to_pool_quorum(pool, message, k));
+}
+
 pub fn send_random_from_pool(pool: &'static str, message: impl Message + 'static) {
     debug_process!("Access: sending random from pool: {pool}");
     with_access(|access| access.send_random_from_pool(pool, message));
 }
 
+// Does not panic outside of simulation context or while access is borrowed
+pub(crate) fn try_rank() -> Option<ProcessId> {
+    ACCESS_HANDLE
+        .try_with(|access| Some(access.try_borrow().ok()?.as_ref()?.rank()))
+        .ok()
+        .flatten()
+}
+
 pub fn rank() -> ProcessId {
+    if let Some(hosted) = transport::hosted() {
+        return hosted.borrow().rank();
+    }
     with_access(|access| access.rank())
 }
 
 pub fn list_pool(name: &str) -> Vec<ProcessId> {
     debug_process!("Access: listing pool: {name}");
+    if let Some(hosted) = transport::hosted() {
+        return hosted.borrow().list_pool(name);
+    }
     with_access(|access| access.list_pool(name).to_vec())
 }
 
+// Members of the pool the current process can reach: not crashed and, under a
+// partition, on its side. Unlike list_pool it sees faults, as they were
+// SimulationBuilder::reachability_staleness ago (now by default).
+pub fn list_reachable_pool(name: &str) -> Vec<ProcessId> {
+    debug_process!("Access: listing reachable pool: {name}");
+    with_access(|access| access.list_reachable_pool(name))
+}
+
 pub fn choose_from_pool(name: &str) -> ProcessId {
     debug_process!("Access: choosing random from pool: {name}");
     with_access(|access| access.choose_from_pool(name))
 }
+
+pub fn choose_nearest_from_pool(name: &str) -> ProcessId {
+    debug_process!("Access: choosing nearest from pool: {name}");
+    with_access(|access| access.choose_nearest_from_pool(name))
+}
+
+pub fn region_of(id: ProcessId) -> Option<&'static str> {
+    with_access(|access| access.region_of(id))
+}
+
+// Mean latency of the link from the current process to `to` (in jiffies), as configured
+// by the latency topology. None if there is no such link.
+pub fn expected_latency(to: ProcessId) -> Option<f64> {
+    with_access(|access| access.expected_latency(to))
+}
+
+// Process at `index` (from zero, in order of addition) of the pool, None if the
+// pool does not exist or is smaller. Stays valid when other pools are added or
+// reordered in the builder, unlike hard-coded ids.
+pub fn pool_member(pool: &str, index: usize) -> Option<ProcessId> {
+    debug_process!("Access: resolving member {index} of pool: {pool}");
+    with_access(|access| access.pool_member(pool, index))
+}
+
+// Reverse of pool_member: the pool the process was added with and its index there
+pub fn pool_of(id: ProcessId) -> Option<(String, usize)> {
+    with_access(|access| access.pool_of(id))
+}
@@ -7,13 +7,165 @@
 //! The storage is thread-local and persists throughout the simulation lifetime.
 //! All functions operate on a per-simulation basis and are reset when a new
 //! simulation starts.
+//!
+//! Keys registered with [`track`] keep a history of their modifications (time,
+//! process and the new value), which can be queried with [`history`] after the
+//! run, e.g. to find out which process reset a counter in the middle of a run.
 
 use std::any::Any;
 use std::cell::RefCell;
 use std::collections::HashMap;
+use std::fmt::{self, Debug, Display};
+
+use crate::{Jiffies, ProcessId, global, now};
+
+// Debug representation of a value of the tracked type
+type Formatter = fn(&dyn Any) -> Option<String>;
+
+struct Tracked {
+    format: Formatter,
+    changes: Vec<Change>,
+}
 
 thread_local! {
     pub(crate) static ANY_KV: RefCell<HashMap<String, Box<dyn Any>>> = RefCell::new(HashMap::new());
+    static HISTORY: RefCell<HashMap<String, Tracked>> = RefCell::new(HashMap::new());
+}
+
+/// Kind of a modification recorded in the [`history`] of a key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operation {
+    /// The value was replaced with [`set`].
+    Set,
+    /// The value was changed in place with [`modify`].
+    Modify,
+}
+
+/// A modification of a tracked key, see [`history`].
+///
+/// Displays as a log line, e.g. `[1200] P3 modify pongs = 0`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Change {
+    /// Simulation time of the modification.
+    pub time: Jiffies,
+    /// Process which modified the value, 0 outside of processes (e.g. the
+    /// experiment driver before the run).
+    pub process: ProcessId,
+    /// How the value was modified.
+    pub operation: Operation,
+    /// Debug representation of the value after the modification.
+    pub value: String,
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let operation = match self.operation {
+            Operation::Set => "set",
+            Operation::Modify => "modify",
+        };
+        write!(
+            f,
+            "[{}] P{} {operation} = {}",
+            self.time.0, self.process, self.value
+        )
+    }
+}
+
+fn record(key: &str, operation: Operation, value: &dyn Any) {
+    HISTORY.with_borrow_mut(|history| {
+        if let Some(tracked) = history.get_mut(key) {
+            tracked.changes.push(Change {
+                time: now(),
+                process: global::try_rank().unwrap_or(0),
+                operation,
+                value: (tracked.format)(value).unwrap_or_else(|| "<other type>".to_string()),
+            });
+        }
+    });
+}
+
+/// Starts recording every modification of `key` into its [`history`].
+///
+/// Values are recorded with their [`Debug`] representation, so the history
+/// shows what a value was changed to, not only when and by whom. Tracking
+/// an already tracked key keeps its history.
+///
+/// # Examples
+///
+/// ```rust
+/// use dscale::global::anykv;
+///
+/// anykv::track::<u32>("pongs");
+/// anykv::set("pongs", 0u32);
+/// anykv::modify("pongs", |pongs: &mut u32| *pongs += 1);
+///
+/// let history = anykv::history("pongs");
+/// assert_eq!(history.len(), 2);
+/// assert_eq!(history[1].value, "1");
+/// assert_eq!(history[1].operation, anykv::Operation::Modify);
+/// ```
+pub fn track<T: Debug + 'static>(key: &str) {
+    HISTORY.with_borrow_mut(|history| {
+        history.entry(key.to_string()).or_insert_with(|| Tracked {
+            format: |value| value.downcast_ref::<T>().map(|value| format!("{value:?}")),
+            changes: Vec::new(),
+        });
+    });
+}
+
+/// Returns the recorded modifications of `key` in the order they happened.
+///
+/// Empty if the key is not tracked with [`track`]. Like the values themselves,
+/// the history is available until the simulation is dropped.
+///
+/// # Examples
+///
+/// ```rust
+/// use dscale::{SimulationBuilder, Jiffies, rank, schedule_timer_after};
+/// use dscale::global::anykv;
+///
+/// #[derive(Default)]
+/// struct Counter;
+///
+/// impl dscale::ProcessHandle for Counter {
+///     fn start(&mut self) {
+///         schedule_timer_after(Jiffies(10 * rank()));
+///     }
+///
+///     fn on_message(&mut self, from: dscale::ProcessId, message: dscale::MessagePtr) {}
+///
+///     fn on_timer(&mut self, id: dscale::TimerId) {
+///         if rank() == 3 {
+///             anykv::set("pongs", 0usize); // Bug: resets the shared counter
+///         } else {
+///             anykv::modify::<usize>("pongs", |pongs| *pongs += 1);
+///         }
+///     }
+/// }
+///
+/// anykv::track::<usize>("pongs");
+/// anykv::set("pongs", 0usize);
+/// let mut simulation = SimulationBuilder::default()
+///     .add_pool::<Counter>("counters", 4)
+///     .time_budget(Jiffies(100))
+///     .build();
+/// simulation.step_until(Jiffies(100));
+///
+/// // Who set pongs to 0 mid-run?
+/// let reset = anykv::history("pongs")
+///     .into_iter()
+///     .find(|change| change.time > Jiffies(0) && change.value == "0")
+///     .expect("Counter was reset");
+/// assert_eq!((reset.process, reset.time), (3, Jiffies(30)));
+/// assert_eq!(reset.to_string(), "[30] P3 set = 0");
+/// ```
+pub fn history(key: &str) -> Vec<Change> {
+    HISTORY.with_borrow(|history| {
+        history
+            .get(key)
+            .map(|tracked| tracked.changes.clone())
+            .unwrap_or_default()
+    })
 }
 
 /// Stores a value of any type in the global key-value store.
@@ -46,6 +198,7 @@ thread_local! {
 ///
 /// This function does not panic under normal circumstances.
 pub fn set<T: 'static>(key: &str, value: T) {
+    record(key, Operation::Set, &value);
     ANY_KV.with(|m| {
         m.borrow_mut().insert(key.to_string(), Box::new(value));
     });
@@ -100,6 +253,46 @@ pub fn get<T: 'static + Clone>(key: &str) -> T {
     })
 }
 
+/// Retrieves a cloned copy of a value if it is present in the global key-value store.
+///
+/// Unlike [`get`], this function does not panic on a missing key, which makes it
+/// suitable for optional configuration with a fallback default.
+///
+/// # Examples
+///
+/// ```rust
+/// use dscale::global::anykv;
+///
+/// assert_eq!(anykv::try_get::<u32>("missing"), None);
+///
+/// anykv::set("timeout", 500u32);
+/// let timeout = anykv::try_get::<u32>("timeout").unwrap_or(1000);
+/// assert_eq!(timeout, 500);
+/// ```
+///
+/// # Panics
+///
+/// This function panics if the key exists but the stored value cannot be
+/// downcast to type `T`.
+pub fn try_get<T: 'static + Clone>(key: &str) -> Option<T> {
+    ANY_KV.with(|m| {
+        m.borrow()
+            .get(key)
+            .map(|value| value.downcast_ref::<T>().cloned().expect("Wrong type cast"))
+    })
+}
+
+// Like try_get, but None while the store is borrowed by modify: reports written
+// by a failed assertion within modify must not fail themselves
+pub(crate) fn peek<T: 'static + Clone>(key: &str) -> Option<T> {
+    ANY_KV.with(|m| {
+        m.try_borrow()
+            .ok()?
+            .get(key)
+            .map(|value| value.downcast_ref::<T>().cloned().expect("Wrong type cast"))
+    })
+}
+
 /// Modifies a value in the global key-value store in-place.
 ///
 /// This function allows you to modify a stored value without retrieving and
@@ -144,14 +337,18 @@ pub fn get<T: 'static + Clone>(key: &str) -> T {
 /// * The stored value cannot be downcast to type `T`
 pub fn modify<T: 'static>(key: &str, f: impl FnOnce(&mut T)) {
     ANY_KV.with(|m| {
-        f(m.borrow_mut()
+        let mut m = m.borrow_mut();
+        let value = m
             .get_mut(key)
             .expect("No key")
             .downcast_mut::<T>()
-            .expect("Wrong type cast"));
+            .expect("Wrong type cast");
+        f(value);
+        record(key, Operation::Modify, value);
     });
 }
 
 pub fn drop_anykv() {
     ANY_KV.take();
+    HISTORY.take();
 }
@@ -0,0 +1,165 @@
+//! Common starting point of all processes.
+//!
+//! Protocols often need every process to begin from the same initial state (genesis
+//! blocks, the first DAG round, an initial configuration) and to act only once the
+//! others are up. Distributing such state with messages costs a round trip before
+//! the protocol even starts and makes the first round a special case.
+//!
+//! [`genesis`] hands every process the very same instance of the initial state,
+//! checking that each of them would have built an identical one on its own.
+//! [`await_all_started`] is a barrier: its timer fires once every process of the
+//! simulation has executed its `start`.
+//!
+//! Both are simulation-only facilities.
+
+use std::{
+    any::Any,
+    cell::RefCell,
+    collections::HashMap,
+    hash::{DefaultHasher, Hash, Hasher},
+    rc::Rc,
+};
+
+use crate::{
+    Jiffies, ProcessId, TimerId, global::configuration, now, rank, schedule_timer_after, sim_assert,
+};
+
+struct Genesis {
+    value: Rc<dyn Any>,
+    digest: u64,
+    creator: ProcessId,
+}
+
+thread_local! {
+    static GENESIS: RefCell<HashMap<String, Genesis>> = RefCell::new(HashMap::new());
+}
+
+pub(crate) fn drop_genesis() {
+    GENESIS.take();
+}
+
+fn digest_of(value: &impl Hash) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Returns initial state shared by all processes under `key`.
+///
+/// Every calling process builds its own candidate with `create`. The candidate of
+/// the first caller becomes the genesis, every later caller (restarted processes
+/// included) gets a clone of it after its own candidate is checked to hash
+/// identically. A mismatch means the initial state depends on something local to
+/// the process (its id, clock or seed) and fails as [`sim_assert!`].
+///
+/// Sharing the instance matters for state with identity: for example, `Rc`
+/// pointers built by one process compare equal everywhere. The genesis is dropped
+/// together with the simulation.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::rc::Rc;
+/// use dscale::{ProcessHandle, ProcessId, MessagePtr, TimerId};
+/// use dscale::global::{bootstrap, configuration};
+///
+/// #[derive(Default)]
+/// struct Node {
+///     blocks: Vec<Rc<(usize, ProcessId)>>, // Round and author
+/// }
+///
+/// impl ProcessHandle for Node {
+///     fn start(&mut self) {
+///         let n = configuration::process_number();
+///         self.blocks = bootstrap::genesis("genesis", || {
+///             (1..=n).map(|author| Rc::new((0, author))).collect()
+///         });
+///     }
+///
+///     fn on_message(&mut self, from: ProcessId, message: MessagePtr) {}
+///     fn on_timer(&mut self, id: TimerId) {}
+/// }
+/// ```
+///
+/// [`sim_assert!`]: crate::sim_assert
+pub fn genesis<T: Clone + Hash + 'static>(key: &str, create: impl FnOnce() -> T) -> T {
+    let candidate = create();
+    let digest = digest_of(&candidate);
+
+    let shared = GENESIS.with_borrow(|genesis| {
+        genesis
+            .get(key)
+            .map(|g| (g.value.clone(), g.digest, g.creator))
+    });
+
+    match shared {
+        None => {
+            GENESIS.with_borrow_mut(|genesis| {
+                genesis.insert(
+                    key.to_string(),
+                    Genesis {
+                        value: Rc::new(candidate.clone()),
+                        digest,
+                        creator: rank(),
+                    },
+                )
+            });
+            candidate
+        }
+        Some((value, shared_digest, creator)) => {
+            sim_assert!(
+                digest == shared_digest,
+                "Genesis {key} of P{} differs from the one of P{creator}",
+                rank()
+            );
+            value
+                .downcast_ref::<T>()
+                .expect("Genesis requested with a different type")
+                .clone()
+        }
+    }
+}
+
+/// Schedules a timer of the current process which fires once every process has started.
+///
+/// All processes are started at the beginning of the simulation, before any message
+/// or timer is delivered, so the timer fires right after the last `start` returns.
+/// With [`SimulationBuilder::startup_jitter`] it fires once the last delayed process
+/// has started. A process restarted later gets it at once, since the others are up
+/// already.
+///
+/// # Examples
+///
+/// ```rust
+/// use dscale::{ProcessHandle, ProcessId, MessagePtr, TimerId, Message, broadcast};
+/// use dscale::global::bootstrap;
+///
+/// struct Hello;
+/// impl Message for Hello {}
+///
+/// #[derive(Default)]
+/// struct Node {
+///     barrier: TimerId,
+/// }
+///
+/// impl ProcessHandle for Node {
+///     fn start(&mut self) {
+///         self.barrier = bootstrap::await_all_started();
+///     }
+///
+///     fn on_message(&mut self, from: ProcessId, message: MessagePtr) {}
+///
+///     fn on_timer(&mut self, id: TimerId) {
+///         if id == self.barrier {
+///             broadcast(Hello); // Everyone is listening
+///         }
+///     }
+/// }
+/// ```
+///
+/// [`SimulationBuilder::startup_jitter`]: crate::SimulationBuilder::startup_jitter
+pub fn await_all_started() -> TimerId {
+    schedule_timer_after(Jiffies(
+        configuration::last_start().0.saturating_sub(now().0),
+    ))
+}
@@ -7,17 +7,58 @@
 //! The configuration system uses the global key-value store internally and provides
 //! type-safe access to commonly used configuration parameters.
 
-use crate::{ProcessId, global::anykv, random::Seed, rank};
+use std::time::Duration;
+
+use crate::{Jiffies, ProcessId, global::anykv, now, random::Seed, rank};
 
 pub(crate) fn setup_global_configuration(proc_num: usize) {
     anykv::set::<usize>("proc_num", proc_num)
 }
 
+pub(crate) fn setup_jiffy_duration(duration: Duration) {
+    anykv::set::<Duration>("jiffy_duration", duration)
+}
+
 pub(crate) fn setup_local_configuration(id: ProcessId, base_seed: Seed) {
     // Prevent resonance between procs by changing seed a little bit
     anykv::set::<u64>(&format!("seeds/{}", id), base_seed + id as u64)
 }
 
+pub(crate) fn seed_of(id: ProcessId) -> Seed {
+    anykv::get::<u64>(&format!("seeds/{}", id))
+}
+
+pub(crate) fn setup_last_start(at: Jiffies) {
+    anykv::set::<Jiffies>("last_start", at)
+}
+
+// When the last process starts, see SimulationBuilder::startup_jitter
+pub(crate) fn last_start() -> Jiffies {
+    anykv::try_get::<Jiffies>("last_start").unwrap_or_default()
+}
+
+pub(crate) fn setup_cpu_speed(id: ProcessId, speed: f64) {
+    anykv::set::<f64>(&format!("cpu_speeds/{}", id), speed)
+}
+
+pub(crate) fn setup_clock_drift(id: ProcessId, drift: f64) {
+    anykv::set::<f64>(&format!("clock_drifts/{}", id), drift)
+}
+
+fn drift_of(id: ProcessId) -> f64 {
+    anykv::try_get::<f64>(&format!("clock_drifts/{}", id)).unwrap_or(0.0)
+}
+
+fn offset_of(id: ProcessId) -> f64 {
+    anykv::try_get::<f64>(&format!("clock_offsets/{}", id)).unwrap_or(0.0)
+}
+
+// Steps the local clock of the process to simulation time plus `error`
+pub(crate) fn set_clock_error(id: ProcessId, error: f64) {
+    let offset = error - now().0 as f64 * drift_of(id);
+    anykv::set::<f64>(&format!("clock_offsets/{}", id), offset)
+}
+
 /// Returns the random seed for the currently executing process.
 ///
 /// Each process in the simulation receives a unique random seed derived from
@@ -102,3 +143,205 @@ pub fn seed() -> Seed {
 pub fn process_number() -> usize {
     anykv::get::<usize>("proc_num")
 }
+
+/// Returns the real-world time represented by one jiffy.
+///
+/// The duration is configured with [`SimulationBuilder::jiffy_duration`]. It
+/// does not change how the simulation runs, but lets processes and reports
+/// translate jiffies (and bytes per jiffy) into real-world units.
+///
+/// # Context
+///
+/// This function can be called from any context within the simulation.
+///
+/// [`SimulationBuilder::jiffy_duration`]: crate::SimulationBuilder::jiffy_duration
+///
+/// # Returns
+///
+/// The duration of one jiffy, `None` unless configured.
+pub fn jiffy_duration() -> Option<Duration> {
+    anykv::peek::<Duration>("jiffy_duration")
+}
+
+/// Returns the CPU speed factor of the currently executing process.
+///
+/// Speed factors are configured with [`SimulationBuilder::cpu_speed`] and
+/// [`SimulationBuilder::process_cpu_speed`]. A process with factor `0.5` needs
+/// twice as much time for the same work as a process with the default factor `1.0`.
+///
+/// # Context
+///
+/// This function must be called from within a process context (i.e., during
+/// the execution of [`ProcessHandle`] methods).
+///
+/// [`SimulationBuilder::cpu_speed`]: crate::SimulationBuilder::cpu_speed
+/// [`SimulationBuilder::process_cpu_speed`]: crate::SimulationBuilder::process_cpu_speed
+/// [`ProcessHandle`]: crate::ProcessHandle
+///
+/// # Returns
+///
+/// The speed factor of the current process, `1.0` unless configured.
+pub fn cpu_speed() -> f64 {
+    anykv::try_get::<f64>(&format!("cpu_speeds/{}", rank())).unwrap_or(1.0)
+}
+
+/// Returns how long `nominal` CPU work takes on the currently executing process.
+///
+/// Processes simulating handler costs (signature checks, execution of requests)
+/// should pass nominal costs through this function, so the costs scale with the
+/// [`cpu_speed`] of the process. The result is rounded up to whole jiffies.
+///
+/// # Context
+///
+/// This function must be called from within a process context (i.e., during
+/// the execution of [`ProcessHandle`] methods).
+///
+/// [`ProcessHandle`]: crate::ProcessHandle
+///
+/// # Examples
+///
+/// ```rust
+/// use dscale::{ProcessHandle, ProcessId, MessagePtr, TimerId, Jiffies, schedule_timer_after};
+/// use dscale::global::configuration;
+///
+/// const EXECUTION_COST: Jiffies = Jiffies(10);
+///
+/// struct Executor;
+///
+/// impl ProcessHandle for Executor {
+///     fn start(&mut self) {}
+///
+///     fn on_message(&mut self, from: ProcessId, message: MessagePtr) {
+///         // Respond once the request is executed
+///         schedule_timer_after(configuration::cpu_time(EXECUTION_COST));
+///     }
+///
+///     fn on_timer(&mut self, id: TimerId) {}
+/// }
+/// ```
+///
+/// # Returns
+///
+/// `nominal` divided by the speed factor of the current process.
+pub fn cpu_time(nominal: Jiffies) -> Jiffies {
+    Jiffies((nominal.0 as f64 / cpu_speed()).ceil() as usize)
+}
+
+/// Returns the clock drift of the currently executing process.
+///
+/// Drifts are configured with [`SimulationBuilder::clock_drift`] and
+/// [`SimulationBuilder::process_clock_drift`]. The local clock of a process with
+/// drift `0.01` runs 1% faster than simulation time, with drift `-0.01` 1% slower.
+///
+/// # Context
+///
+/// This function must be called from within a process context (i.e., during
+/// the execution of [`ProcessHandle`] methods).
+///
+/// [`SimulationBuilder::clock_drift`]: crate::SimulationBuilder::clock_drift
+/// [`SimulationBuilder::process_clock_drift`]: crate::SimulationBuilder::process_clock_drift
+/// [`ProcessHandle`]: crate::ProcessHandle
+///
+/// # Returns
+///
+/// The drift of the current process, `0.0` unless configured.
+pub fn clock_drift() -> f64 {
+    drift_of(rank())
+}
+
+/// Returns the reading of the local clock of the currently executing process.
+///
+/// Unlike [`now`], which is the same for every process, local clocks drift
+/// apart according to [`clock_drift`], unless pulled back by [`ClockSync`].
+/// Protocols relying on timing assumptions
+/// (leases, timeouts bounding the validity of a promise) should measure time
+/// with local clocks, so their safety under drift can be tested.
+///
+/// # Context
+///
+/// This function must be called from within a process context (i.e., during
+/// the execution of [`ProcessHandle`] methods).
+///
+/// [`now`]: crate::now
+/// [`ClockSync`]: crate::ClockSync
+/// [`ProcessHandle`]: crate::ProcessHandle
+///
+/// # Examples
+///
+/// ```rust
+/// use dscale::{ProcessHandle, ProcessId, MessagePtr, TimerId, Jiffies, schedule_timer_after};
+/// use dscale::global::configuration;
+///
+/// const LEASE: Jiffies = Jiffies(1_000);
+///
+/// #[derive(Default)]
+/// struct Holder {
+///     lease_until: Jiffies, // Local time
+/// }
+///
+/// impl ProcessHandle for Holder {
+///     fn start(&mut self) {
+///         self.lease_until = configuration::local_now() + LEASE;
+///         // Fires when the local clock reaches the end of the lease
+///         schedule_timer_after(configuration::local_duration(LEASE));
+///     }
+///
+///     fn on_message(&mut self, from: ProcessId, message: MessagePtr) {}
+///
+///     fn on_timer(&mut self, id: TimerId) {
+///         assert!(configuration::local_now() >= self.lease_until);
+///     }
+/// }
+/// ```
+///
+/// # Returns
+///
+/// Simulation time scaled by `1 + drift` and corrected by the last clock
+/// synchronization, rounded down to whole jiffies.
+pub fn local_now() -> Jiffies {
+    Jiffies(local_time(rank()).max(0.0).floor() as usize)
+}
+
+fn local_time(id: ProcessId) -> f64 {
+    now().0 as f64 * (1.0 + drift_of(id)) + offset_of(id)
+}
+
+/// Returns how far the local clock of the currently executing process is
+/// ahead of simulation time (negative if behind).
+///
+/// # Context
+///
+/// This function must be called from within a process context (i.e., during
+/// the execution of [`ProcessHandle`] methods).
+///
+/// [`ProcessHandle`]: crate::ProcessHandle
+///
+/// # Returns
+///
+/// [`local_now`] minus [`now`] in jiffies, without rounding.
+///
+/// [`now`]: crate::now
+pub fn clock_skew() -> f64 {
+    local_time(rank()) - now().0 as f64
+}
+
+/// Returns how much simulation time passes while the local clock of the
+/// currently executing process advances by `local`.
+///
+/// Pass local durations through this function before scheduling timers, so the
+/// timers fire when the local clock says so. The result is rounded up to whole
+/// jiffies.
+///
+/// # Context
+///
+/// This function must be called from within a process context (i.e., during
+/// the execution of [`ProcessHandle`] methods).
+///
+/// [`ProcessHandle`]: crate::ProcessHandle
+///
+/// # Returns
+///
+/// `local` divided by `1 + drift` of the current process.
+pub fn local_duration(local: Jiffies) -> Jiffies {
+    Jiffies((local.0 as f64 / (1.0 + clock_drift())).ceil() as usize)
+}
@@ -0,0 +1,217 @@
+//! Simulated disk I/O.
+//!
+//! Every process owns a disk configured with [`SimulationBuilder::disk`]. Writes
+//! land in the page cache and are free, but they become durable only after an
+//! [`fsync`], which consumes simulated time: the disk flushes all dirty bytes at its
+//! throughput and pays a fixed latency per flush. Protocols which must persist
+//! before acknowledging (log replication, checkpoints) wait for the timer returned
+//! by [`fsync`] and pay a realistic durability cost.
+//!
+//! Each disk serves one flush at a time, so flushes issued back to back queue up.
+//!
+//! [`SimulationBuilder::disk`]: crate::SimulationBuilder::disk
+
+use std::cell::RefCell;
+
+use crate::{Jiffies, ProcessId, TimerId, debug_process, now, rank, schedule_timer_after};
+
+/// What survives in the [`wal`] of a process when it crashes (see [`Simulation::restart`]).
+///
+/// [`wal`]: crate::global::wal
+/// [`Simulation::restart`]: crate::Simulation::restart
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CrashTruncation {
+    /// Only entries covered by a completed sync survive (default).
+    #[default]
+    DropUnsynced,
+    /// Synced entries survive together with a random prefix of the rest,
+    /// modeling writes which were partially flushed at the time of the crash.
+    TornTail,
+    /// Every entry survives: the crash lost process memory, but not the page cache.
+    KeepAll,
+}
+
+/// Describes the disk of every process in the simulation.
+///
+/// The default disk is instant: [`fsync`] completes at the current time, but
+/// still asynchronously through a timer.
+///
+/// # Examples
+///
+/// ```rust
+/// use dscale::{SimulationBuilder, DiskDescription, CrashTruncation, Jiffies};
+///
+/// let simulation = SimulationBuilder::default()
+///     .add_pool::<MyProcess>("replicas", 3)
+///     .disk(DiskDescription {
+///         fsync_latency: Jiffies(50),  // Fixed cost of every flush
+///         throughput: Some(100),       // 100 bytes per jiffy
+///         crash_truncation: CrashTruncation::TornTail,
+///     })
+///     .build();
+/// # #[derive(Default)]
+/// # struct MyProcess;
+/// # impl dscale::ProcessHandle for MyProcess {
+/// #     fn start(&mut self) {}
+/// #     fn on_message(&mut self, from: dscale::ProcessId, message: dscale::MessagePtr) {}
+/// #     fn on_timer(&mut self, id: dscale::TimerId) {}
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DiskDescription {
+    /// Time spent by every flush regardless of the amount of flushed bytes.
+    pub fsync_latency: Jiffies,
+    /// Bytes written to stable storage per jiffy, `None` for unlimited throughput.
+    pub throughput: Option<usize>,
+    /// Which [`wal`] entries survive a crash.
+    ///
+    /// [`wal`]: crate::global::wal
+    pub crash_truncation: CrashTruncation,
+}
+
+#[derive(Clone, Copy, Default)]
+struct DiskState {
+    dirty: usize,
+    busy_until: Jiffies,
+    written: usize,
+    fsyncs: usize,
+}
+
+struct Disks {
+    description: DiskDescription,
+    states: Vec<DiskState>,
+}
+
+thread_local! {
+    static DISKS: RefCell<Option<Disks>> = const { RefCell::new(None) };
+}
+
+pub(crate) fn setup_disks(description: DiskDescription, proc_num: usize) {
+    DISKS.set(Some(Disks {
+        description,
+        states: vec![DiskState::default(); proc_num + 1],
+    }));
+}
+
+pub(crate) fn drop_disks() {
+    DISKS.take();
+}
+
+pub(crate) fn description() -> DiskDescription {
+    DISKS.with_borrow(|disks| {
+        disks
+            .as_ref()
+            .expect("Out of simulation context")
+            .description
+    })
+}
+
+// Page cache is lost, the device itself keeps flushing what it was asked to
+pub(crate) fn on_restart(id: ProcessId) {
+    DISKS.with_borrow_mut(|disks| {
+        if let Some(disks) = disks.as_mut() {
+            disks.states[id].dirty = 0;
+        }
+    });
+}
+
+fn with_disk<T>(f: impl FnOnce(&DiskDescription, &mut DiskState) -> T) -> T {
+    let id: ProcessId = rank();
+    DISKS.with_borrow_mut(|disks| {
+        let disks = disks.as_mut().expect("Out of simulation context");
+        f(&disks.description, &mut disks.states[id])
+    })
+}
+
+/// Writes `bytes` to the page cache of the current process.
+///
+/// Written bytes are not durable until the next [`fsync`] completes.
+pub fn write(bytes: usize) {
+    debug_process!("Disk: writing {bytes} bytes");
+    with_disk(|_, disk| {
+        disk.dirty += bytes;
+        disk.written += bytes;
+    });
+}
+
+/// Flushes all bytes written so far to stable storage.
+///
+/// Returns a timer which fires in the current process once the flush completes:
+/// after all previously issued flushes, the fixed [`fsync_latency`] and the time
+/// needed to write dirty bytes at the disk [`throughput`].
+///
+/// # Examples
+///
+/// ```rust
+/// use dscale::{ProcessHandle, ProcessId, MessagePtr, TimerId, Message, send_to};
+/// use dscale::global::disk;
+///
+/// struct Append { entry: Vec<u8> }
+/// impl Message for Append {}
+///
+/// struct Ack;
+/// impl Message for Ack {}
+///
+/// #[derive(Default)]
+/// struct LogReplica {
+///     persisting: Vec<(TimerId, ProcessId)>,
+/// }
+///
+/// impl ProcessHandle for LogReplica {
+///     fn start(&mut self) {}
+///
+///     fn on_message(&mut self, from: ProcessId, message: MessagePtr) {
+///         let append = message.as_type::<Append>();
+///         disk::write(append.entry.len());
+///         self.persisting.push((disk::fsync(), from)); // Ack only after entry is durable
+///     }
+///
+///     fn on_timer(&mut self, id: TimerId) {
+///         if let Some(position) = self.persisting.iter().position(|(timer, _)| *timer == id) {
+///             let (_, leader) = self.persisting.remove(position);
+///             send_to(leader, Ack);
+///         }
+///     }
+/// }
+/// ```
+///
+/// [`fsync_latency`]: DiskDescription::fsync_latency
+/// [`throughput`]: DiskDescription::throughput
+pub fn fsync() -> TimerId {
+    let after = flush() - now();
+    debug_process!("Disk: fsync completes after {after}");
+    schedule_timer_after(after)
+}
+
+// Returns time when everything written so far becomes durable
+pub(crate) fn flush() -> Jiffies {
+    with_disk(|description, disk| {
+        let transfer = match description.throughput {
+            None => 0,
+            Some(throughput) => disk.dirty.div_ceil(throughput),
+        };
+        disk.busy_until =
+            disk.busy_until.max(now()) + description.fsync_latency + Jiffies(transfer);
+        disk.dirty = 0;
+        disk.fsyncs += 1;
+        disk.busy_until
+    })
+}
+
+/// Number of bytes written by the current process which are not covered by any [`fsync`] yet.
+pub fn dirty_bytes() -> usize {
+    with_disk(|_, disk| disk.dirty)
+}
+
+/// Total number of bytes written and flushes issued by the process.
+///
+/// Can be called outside of process context, for example after the simulation has run.
+pub fn usage(id: ProcessId) -> (usize, usize) {
+    DISKS.with_borrow(|disks| {
+        disks
+            .as_ref()
+            .and_then(|disks| disks.states.get(id))
+            .map(|disk| (disk.written, disk.fsyncs))
+            .unwrap_or_default()
+    })
+}
@@ -0,0 +1,108 @@
+//! Protocol errors reported to the framework instead of panicking.
+//!
+//! Handlers reaching a state their protocol rules out (a response to a request
+//! never made, a message of an unexpected type, a send to a process which does
+//! not exist) usually `unwrap` or `expect` and take the whole run down. Fault
+//! injection makes such states reachable on purpose, and one incidental panic
+//! then hides everything else the run would have shown. Reporting them with
+//! [`protocol_error!`] instead lets the configured [`ErrorPolicy`] decide: abort
+//! the run like [`sim_assert!`] (default), or record a [`ProtocolError`] and
+//! carry on, see [`SimulationBuilder::protocol_errors`] and
+//! [`Simulation::protocol_errors`].
+//!
+//! [`protocol_error!`]: crate::protocol_error
+//! [`sim_assert!`]: crate::sim_assert
+//! [`SimulationBuilder::protocol_errors`]: crate::SimulationBuilder::protocol_errors
+//! [`Simulation::protocol_errors`]: crate::Simulation::protocol_errors
+
+use std::{
+    cell::RefCell,
+    fmt::{self, Arguments},
+};
+
+use log::warn;
+
+use crate::{Jiffies, ProcessId, helpers::assertion, now, rank};
+
+/// What happens when a process reports a protocol error.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ErrorPolicy {
+    /// The run fails at once with the context of the process, like a failed
+    /// [`sim_assert!`] (default).
+    ///
+    /// [`sim_assert!`]: crate::sim_assert
+    #[default]
+    Abort,
+    /// The error is logged as a warning and recorded, the process continues
+    /// from the point of the report.
+    Record,
+}
+
+/// Protocol error recorded under [`ErrorPolicy::Record`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProtocolError {
+    /// Simulation time of the report.
+    pub at: Jiffies,
+    /// Process which reported the error.
+    pub process: ProcessId,
+    /// What went wrong.
+    pub message: String,
+}
+
+impl fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "P{} at {}", self.process, self.at.0)?;
+        if let Some(at) = self.at.to_duration() {
+            write!(f, " [{at:?}]")?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+struct Errors {
+    policy: ErrorPolicy,
+    recorded: Vec<ProtocolError>,
+}
+
+thread_local! {
+    static ERRORS: RefCell<Option<Errors>> = const { RefCell::new(None) };
+}
+
+pub(crate) fn setup_errors(policy: ErrorPolicy) {
+    ERRORS.set(Some(Errors {
+        policy,
+        recorded: Vec::new(),
+    }));
+}
+
+pub(crate) fn drop_errors() {
+    ERRORS.take();
+}
+
+fn with_errors<T>(f: impl FnOnce(&mut Errors) -> T) -> T {
+    ERRORS.with_borrow_mut(|errors| f(errors.as_mut().expect("Out of simulation context")))
+}
+
+pub(crate) fn recorded() -> Vec<ProtocolError> {
+    with_errors(|errors| errors.recorded.clone())
+}
+
+/// Reports a protocol error of the current process. Used by [`protocol_error!`].
+///
+/// [`protocol_error!`]: crate::protocol_error
+#[doc(hidden)]
+#[track_caller]
+pub fn report(message: Arguments) {
+    match with_errors(|errors| errors.policy) {
+        ErrorPolicy::Abort => assertion::fail(format_args!("protocol error: {message}")),
+        ErrorPolicy::Record => {
+            let error = ProtocolError {
+                at: now(),
+                process: rank(),
+                message: message.to_string(),
+            };
+            warn!("Protocol error of {error}");
+            with_errors(|errors| errors.recorded.push(error));
+        }
+    }
+}
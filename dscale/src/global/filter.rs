@@ -0,0 +1,117 @@
+//! Inbound message filtering.
+//!
+//! A process can install a filter with [`set_message_filter`] to model
+//! protocol-level firewalls: blacklisting peers caught equivocating, ignoring
+//! clients over quota and alike. Messages rejected by the filter are dropped
+//! before [`ProcessHandle::on_message`] and counted, see
+//! [`Simulation::filtered_messages`]. Timers are never filtered.
+//!
+//! The filter belongs to the process instance: a restarted process starts
+//! without one.
+//!
+//! [`ProcessHandle::on_message`]: crate::ProcessHandle::on_message
+//! [`Simulation::filtered_messages`]: crate::Simulation::filtered_messages
+
+use std::{cell::RefCell, rc::Rc};
+
+use log::debug;
+
+use crate::{MessagePtr, ProcessId, rank};
+
+type MessageFilter = Rc<dyn Fn(ProcessId, &MessagePtr) -> bool>;
+
+struct Filters {
+    filters: Vec<Option<MessageFilter>>,
+    filtered: Vec<usize>,
+}
+
+thread_local! {
+    static FILTERS: RefCell<Option<Filters>> = const { RefCell::new(None) };
+}
+
+pub(crate) fn setup_filters(proc_num: usize) {
+    FILTERS.set(Some(Filters {
+        filters: vec![None; proc_num + 1],
+        filtered: vec![0; proc_num + 1],
+    }));
+}
+
+pub(crate) fn drop_filters() {
+    FILTERS.take();
+}
+
+fn with_filters<T>(f: impl FnOnce(&mut Filters) -> T) -> T {
+    FILTERS.with_borrow_mut(|filters| f(filters.as_mut().expect("Out of simulation context")))
+}
+
+/// Installs an inbound message filter for the current process.
+///
+/// Every message delivered to the process is first passed to `filter` together
+/// with its sender; the message reaches [`ProcessHandle::on_message`] only if
+/// the filter returns `true`. The filter runs in the context of the receiving
+/// process, so [`rank`] and [`now`] can be used inside it. A new filter
+/// replaces the previous one.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::{cell::RefCell, collections::HashSet, rc::Rc};
+/// use dscale::{ProcessHandle, ProcessId, MessagePtr, TimerId, set_message_filter};
+///
+/// #[derive(Default)]
+/// struct Replica {
+///     blacklist: Rc<RefCell<HashSet<ProcessId>>>,
+/// }
+///
+/// impl ProcessHandle for Replica {
+///     fn start(&mut self) {
+///         let blacklist = self.blacklist.clone();
+///         set_message_filter(move |from, _message| !blacklist.borrow().contains(&from));
+///     }
+///
+///     fn on_message(&mut self, from: ProcessId, message: MessagePtr) {
+///         // Equivocation detected: ignore the peer from now on
+///         self.blacklist.borrow_mut().insert(from);
+///     }
+///
+///     fn on_timer(&mut self, id: TimerId) {}
+/// }
+/// ```
+///
+/// [`ProcessHandle::on_message`]: crate::ProcessHandle::on_message
+/// [`rank`]: crate::rank
+/// [`now`]: crate::now
+pub fn set_message_filter(filter: impl Fn(ProcessId, &MessagePtr) -> bool + 'static) {
+    let id = rank();
+    with_filters(|filters| filters.filters[id] = Some(Rc::new(filter)));
+}
+
+/// Removes the filter of the current process, all messages are delivered again.
+pub fn clear_message_filter() {
+    let id = rank();
+    with_filters(|filters| filters.filters[id] = None);
+}
+
+// Must be called in the context of the receiving process
+pub(crate) fn admits(from: ProcessId, to: ProcessId, message: &MessagePtr) -> bool {
+    let filter = FILTERS.with_borrow(|filters| {
+        filters
+            .as_ref()
+            .and_then(|filters| filters.filters.get(to).cloned().flatten())
+    });
+    // Filter is called without borrowing FILTERS, it may replace itself
+    if filter.is_none_or(|filter| filter(from, message)) {
+        return true;
+    }
+    debug!("Message from P{from} filtered out by P{to}");
+    with_filters(|filters| filters.filtered[to] += 1);
+    false
+}
+
+pub(crate) fn filtered(id: ProcessId) -> usize {
+    with_filters(|filters| filters.filtered.get(id).copied().unwrap_or(0))
+}
+
+pub(crate) fn on_restart(id: ProcessId) {
+    with_filters(|filters| filters.filters[id] = None);
+}
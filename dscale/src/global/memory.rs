@@ -0,0 +1,201 @@
+//! Simulated memory budgets of processes.
+//!
+//! Protocol state of real validators lives in bounded memory: a storm of
+//! buffered vertices waiting for their parents can exhaust it long before the
+//! protocol makes progress again. Processes report the approximate footprint of
+//! their structures with [`report`], and a process whose total footprint
+//! exceeds the budget configured with [`SimulationBuilder::memory_limit`] is
+//! crashed or stops accepting messages, depending on the [`MemoryPolicy`].
+//!
+//! Footprints belong to the process instance: a restarted process starts empty.
+//!
+//! [`SimulationBuilder::memory_limit`]: crate::SimulationBuilder::memory_limit
+
+use std::{cell::RefCell, collections::BTreeMap};
+
+use log::debug;
+
+use crate::{Jiffies, ProcessId, now, rank};
+
+/// What happens to a process whose footprint exceeds its budget.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MemoryPolicy {
+    /// The process crashes right after the handler which exceeded the budget,
+    /// like a validator killed by the OOM killer (default).
+    #[default]
+    Crash,
+    /// Messages delivered to the process are refused while it is over budget,
+    /// timers keep firing so it can release memory.
+    Backpressure,
+}
+
+/// Memory budget of a process.
+///
+/// # Examples
+///
+/// ```rust
+/// use dscale::{SimulationBuilder, MemoryLimit, MemoryPolicy};
+///
+/// let simulation = SimulationBuilder::default()
+///     .add_pool::<MyProcess>("validators", 4)
+///     .memory_limit("validators", MemoryLimit {
+///         budget: 64 * 1024 * 1024, // Reported bytes
+///         policy: MemoryPolicy::Crash,
+///     })
+///     .build();
+/// # #[derive(Default)]
+/// # struct MyProcess;
+/// # impl dscale::ProcessHandle for MyProcess {
+/// #     fn start(&mut self) {}
+/// #     fn on_message(&mut self, from: dscale::ProcessId, message: dscale::MessagePtr) {}
+/// #     fn on_timer(&mut self, id: dscale::TimerId) {}
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MemoryLimit {
+    /// Largest total footprint in bytes the process may report.
+    pub budget: usize,
+    /// What happens once the footprint exceeds the budget.
+    pub policy: MemoryPolicy,
+}
+
+/// Memory usage of a process, see [`Simulation::memory_stats`].
+///
+/// [`Simulation::memory_stats`]: crate::Simulation::memory_stats
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MemoryStats {
+    /// Footprint of the current process instance.
+    pub footprint: usize,
+    /// Largest footprint over all instances of the process.
+    pub peak: usize,
+    /// Messages refused under [`MemoryPolicy::Backpressure`].
+    pub refused: usize,
+    /// Times the process crashed under [`MemoryPolicy::Crash`].
+    pub out_of_memory: Vec<Jiffies>,
+}
+
+#[derive(Default)]
+struct ProcessMemory {
+    limit: Option<MemoryLimit>,
+    components: BTreeMap<&'static str, usize>,
+    stats: MemoryStats,
+}
+
+thread_local! {
+    static MEMORY: RefCell<Option<Vec<ProcessMemory>>> = const { RefCell::new(None) };
+}
+
+pub(crate) fn setup_memory(limits: BTreeMap<ProcessId, MemoryLimit>, proc_num: usize) {
+    MEMORY.set(Some(
+        (0..=proc_num)
+            .map(|id| ProcessMemory {
+                limit: limits.get(&id).copied(),
+                ..Default::default()
+            })
+            .collect(),
+    ));
+}
+
+pub(crate) fn drop_memory() {
+    MEMORY.take();
+}
+
+fn with_memory<T>(id: ProcessId, f: impl FnOnce(&mut ProcessMemory) -> T) -> T {
+    MEMORY.with_borrow_mut(|memory| f(&mut memory.as_mut().expect("Out of simulation context")[id]))
+}
+
+/// Sets the footprint of a component of the current process to `bytes`.
+///
+/// The footprint of a process is the sum of its components, so independent
+/// structures (a DAG, a mempool, a cache) report themselves under their own
+/// names without knowing about each other.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::collections::BTreeMap;
+/// use dscale::{ProcessHandle, ProcessId, MessagePtr, TimerId};
+/// use dscale::global::memory;
+///
+/// struct Block { payload: Vec<u8> }
+/// impl dscale::Message for Block {}
+///
+/// #[derive(Default)]
+/// struct Replica {
+///     pending: BTreeMap<ProcessId, Vec<usize>>,
+///     bytes: usize,
+/// }
+///
+/// impl ProcessHandle for Replica {
+///     fn start(&mut self) {}
+///
+///     fn on_message(&mut self, from: ProcessId, message: MessagePtr) {
+///         let block = message.as_type::<Block>();
+///         self.pending.entry(from).or_default().push(block.payload.len());
+///         self.bytes += block.payload.len();
+///         memory::report("pending", self.bytes);
+///     }
+///
+///     fn on_timer(&mut self, id: TimerId) {}
+/// }
+/// ```
+pub fn report(component: &'static str, bytes: usize) {
+    let id = rank();
+    with_memory(id, |memory| {
+        memory.components.insert(component, bytes);
+        let footprint = memory.components.values().sum();
+        memory.stats.footprint = footprint;
+        memory.stats.peak = memory.stats.peak.max(footprint);
+    });
+}
+
+/// Total footprint of the current process in bytes.
+pub fn footprint() -> usize {
+    with_memory(rank(), |memory| memory.stats.footprint)
+}
+
+/// Memory budget of the current process, `None` if unlimited.
+pub fn budget() -> Option<usize> {
+    with_memory(rank(), |memory| memory.limit.map(|limit| limit.budget))
+}
+
+/// Returns `true` if the footprint of the current process exceeds its budget.
+///
+/// Lets protocols shed load (stop buffering, drop caches) before the policy
+/// of the budget kicks in on the next message.
+pub fn under_pressure() -> bool {
+    exceeded(rank()).is_some()
+}
+
+// Policy of the budget if the footprint of the process exceeds it
+pub(crate) fn exceeded(id: ProcessId) -> Option<MemoryPolicy> {
+    MEMORY.with_borrow(|memory| {
+        let memory = &memory.as_ref()?[id];
+        memory
+            .limit
+            .filter(|limit| memory.stats.footprint > limit.budget)
+            .map(|limit| limit.policy)
+    })
+}
+
+pub(crate) fn record_refused(id: ProcessId) {
+    debug!("Message to P{id} refused: over memory budget");
+    with_memory(id, |memory| memory.stats.refused += 1);
+}
+
+pub(crate) fn record_out_of_memory(id: ProcessId) {
+    debug!("P{id} is out of memory");
+    with_memory(id, |memory| memory.stats.out_of_memory.push(now()));
+}
+
+pub(crate) fn stats(id: ProcessId) -> MemoryStats {
+    with_memory(id, |memory| memory.stats.clone())
+}
+
+// Structures of the crashed instance are gone
+pub(crate) fn on_restart(id: ProcessId) {
+    with_memory(id, |memory| {
+        memory.components.clear();
+        memory.stats.footprint = 0;
+    });
+}
@@ -1,26 +1,52 @@
 mod access;
 pub mod anykv;
+pub mod bootstrap;
 pub(crate) mod clock;
 pub mod configuration;
+pub mod disk;
+pub mod errors;
+pub mod filter;
+pub mod memory;
+pub mod named_timer;
+pub mod tracing;
 pub mod tso;
+pub mod wal;
 
 pub use tso::global_unique_id;
 
 pub use clock::now;
 
+pub use filter::clear_message_filter;
+pub use filter::set_message_filter;
+
+pub use named_timer::schedule_named_timer;
+pub use named_timer::timer_kind;
+
 pub use access::broadcast;
+pub use access::broadcast_after;
 pub use access::broadcast_within_pool;
 pub use access::choose_from_pool;
+pub use access::choose_nearest_from_pool;
+pub use access::expected_latency;
 pub use access::list_pool;
+pub use access::list_reachable_pool;
+pub use access::pool_member;
+pub use access::pool_of;
 pub use access::rank;
+pub use access::region_of;
 pub use access::schedule_timer_after;
 pub use access::send_random;
 pub use access::send_random_from_pool;
 pub use access::send_to;
+pub use access::send_to_at;
+pub use access::send_to_pool_quorum;
 
+pub(crate) use access::is_crashed;
 pub(crate) use access::schedule;
+pub(crate) use access::send_shared;
 pub(crate) use access::set_process;
 pub(crate) use access::setup_access;
+pub(crate) use access::try_rank;
 
 pub(crate) use clock::fast_forward_clock;
 
@@ -28,5 +54,18 @@ pub(crate) fn drop_all() {
     clock::drop_clock();
     tso::drop_tso();
     anykv::drop_anykv();
+    bootstrap::drop_genesis();
     access::drop_access();
+    tracing::drop_tracing();
+    disk::drop_disks();
+    errors::drop_errors();
+    filter::drop_filters();
+    memory::drop_memory();
+    named_timer::drop_named_timers();
+    wal::drop_wals();
+    crate::helpers::assertion::drop_trails();
+    crate::helpers::log_capture::drop_capture();
+    crate::helpers::quorum::drop_quorums();
+    crate::artifacts::drop_artifacts();
+    crate::message_type::drop_registry();
 }
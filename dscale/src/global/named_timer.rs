@@ -0,0 +1,99 @@
+//! Timers carrying a payload.
+//!
+//! A process with several timer purposes usually keeps a [`TimerId`] field per
+//! purpose and compares every fired id against all of them. Named timers attach
+//! the purpose to the timer itself: schedule with [`schedule_named_timer`] and
+//! read it back in [`ProcessHandle::on_timer`] with [`timer_kind`].
+//!
+//! A payload lives until its timer fires or is dropped together with a crashed
+//! process.
+//!
+//! [`TimerId`]: crate::TimerId
+//! [`ProcessHandle::on_timer`]: crate::ProcessHandle::on_timer
+
+use std::{any::Any, cell::RefCell, collections::HashMap, rc::Rc};
+
+use crate::{Jiffies, TimerId, schedule_timer_after};
+
+thread_local! {
+    static NAMED_TIMERS: RefCell<HashMap<TimerId, Rc<dyn Any>>> = RefCell::new(HashMap::new());
+}
+
+pub(crate) fn drop_named_timers() {
+    NAMED_TIMERS.take();
+}
+
+// Called once the timer fired or was dropped
+pub(crate) fn forget(id: TimerId) {
+    NAMED_TIMERS.with_borrow_mut(|timers| timers.remove(&id));
+}
+
+/// Schedules a timer for the current process carrying `kind` as its payload.
+///
+/// Behaves exactly like [`schedule_timer_after`]; the payload can be read back
+/// with [`timer_kind`] when the timer fires. Any `Clone` type works as a
+/// payload, typically an enum of all timer purposes of the process.
+///
+/// # Examples
+///
+/// ```rust
+/// use dscale::{ProcessHandle, ProcessId, MessagePtr, TimerId, Jiffies};
+/// use dscale::{schedule_named_timer, timer_kind};
+///
+/// #[derive(Clone)]
+/// enum TimerKind {
+///     Heartbeat,
+///     RoundTimeout(usize),
+/// }
+///
+/// #[derive(Default)]
+/// struct Replica {
+///     round: usize,
+/// }
+///
+/// impl ProcessHandle for Replica {
+///     fn start(&mut self) {
+///         schedule_named_timer(Jiffies(100), TimerKind::Heartbeat);
+///         schedule_named_timer(Jiffies(1_000), TimerKind::RoundTimeout(self.round));
+///     }
+///
+///     fn on_message(&mut self, from: ProcessId, message: MessagePtr) {}
+///
+///     fn on_timer(&mut self, id: TimerId) {
+///         match timer_kind::<TimerKind>(id) {
+///             Some(TimerKind::Heartbeat) => {
+///                 schedule_named_timer(Jiffies(100), TimerKind::Heartbeat);
+///             }
+///             Some(TimerKind::RoundTimeout(round)) if round == self.round => {
+///                 self.round += 1; // No progress in time, move on
+///                 schedule_named_timer(Jiffies(1_000), TimerKind::RoundTimeout(self.round));
+///             }
+///             _ => {} // Timeout of an already finished round
+///         }
+///     }
+/// }
+/// ```
+///
+/// # Returns
+///
+/// The [`TimerId`] of the scheduled timer.
+///
+/// [`schedule_timer_after`]: crate::schedule_timer_after
+/// [`TimerId`]: crate::TimerId
+pub fn schedule_named_timer<K: Clone + 'static>(after: Jiffies, kind: K) -> TimerId {
+    let id = schedule_timer_after(after);
+    NAMED_TIMERS.with_borrow_mut(|timers| timers.insert(id, Rc::new(kind)));
+    id
+}
+
+/// Returns the payload of a timer scheduled with [`schedule_named_timer`].
+///
+/// Meant to be called from [`ProcessHandle::on_timer`]. Returns `None` if the
+/// timer was scheduled with plain [`schedule_timer_after`] or carries a payload
+/// of another type.
+///
+/// [`ProcessHandle::on_timer`]: crate::ProcessHandle::on_timer
+/// [`schedule_timer_after`]: crate::schedule_timer_after
+pub fn timer_kind<K: Clone + 'static>(id: TimerId) -> Option<K> {
+    NAMED_TIMERS.with_borrow(|timers| timers.get(&id)?.downcast_ref::<K>().cloned())
+}
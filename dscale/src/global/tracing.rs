@@ -0,0 +1,506 @@
+//! Causal message tracing.
+//!
+//! When enabled with [`SimulationBuilder::trace_messages`], every sent message gets
+//! a [`MessageId`] and belongs to a trace. A message starts a new trace unless the
+//! sender explicitly links it to a message it was caused by, using [`in_reply_to`]
+//! or [`caused_by`]. Linked messages form a causal tree which can be dumped with
+//! [`dump`], showing when every hop was sent and delivered.
+//!
+//! Linking is explicit because protocols often send messages caused by something
+//! other than the message currently being handled (for example, the last of a quorum
+//! of votes), and only the protocol knows the real cause.
+//!
+//! The tracer also logs every random draw of the simulation (message latencies,
+//! results of [`choose_from_pool`] and alike) with the process and request it was
+//! made for, so a comparison of two runs can tell whether they diverged because
+//! the random values differed or because the protocol acted differently.
+//!
+//! The tracer is thread-local and is reset when the simulation is dropped. Use
+//! [`record`] to keep all messages and draws of a run, e.g. to compare two runs
+//! with [`TraceDiff`].
+//!
+//! [`SimulationBuilder::trace_messages`]: crate::SimulationBuilder::trace_messages
+//! [`TraceDiff`]: crate::helpers::TraceDiff
+//! [`choose_from_pool`]: crate::choose_from_pool
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    fmt::Write,
+    fs,
+    path::Path,
+    time::Duration,
+};
+
+use crate::{
+    Jiffies, ProcessId,
+    destination::Destination,
+    global::configuration,
+    message::{Shared, SharedMessage},
+    now,
+};
+
+const JIFFY_DURATION: &str = "# jiffy duration: "; // Header of saved recordings
+const DRAW: &str = "rng"; // First field of draw lines in saved recordings
+
+/// Identifier of a single send (one broadcast is one message with many deliveries).
+pub type MessageId = usize;
+
+/// Identifier of a causal tree, equal to the [`MessageId`] of its root message.
+pub type TraceId = MessageId;
+
+struct Record {
+    trace: TraceId,
+    type_name: &'static str,
+    from: ProcessId,
+    destination: String,
+    sent_at: Jiffies,
+    deliveries: Vec<(ProcessId, Jiffies)>,
+    children: Vec<MessageId>,
+}
+
+#[derive(Default)]
+struct Tracer {
+    next_id: MessageId,
+    records: HashMap<MessageId, Record>,
+    in_flight: HashMap<*const (), MessageId>, // Same Rc is delivered to every receiver
+    current: Option<MessageId>,
+    cause: Option<MessageId>,
+    draws: Vec<RecordedDraw>,
+}
+
+thread_local! {
+    static ENABLED: Cell<bool> = const { Cell::new(false) }; // Keeps hot path cheap when disabled
+    static TRACER: RefCell<Tracer> = RefCell::new(Tracer::default());
+}
+
+pub(crate) fn setup_tracing(enabled: bool) {
+    ENABLED.set(enabled);
+}
+
+pub(crate) fn is_enabled() -> bool {
+    ENABLED.get()
+}
+
+pub(crate) fn drop_tracing() {
+    ENABLED.take();
+    TRACER.take();
+}
+
+pub(crate) fn on_send(
+    from: ProcessId,
+    destination: &Destination,
+    message: &SharedMessage,
+    type_name: &'static str,
+) {
+    if !ENABLED.get() {
+        return;
+    }
+
+    TRACER.with_borrow_mut(|tracer| {
+        tracer.next_id += 1;
+        let id = tracer.next_id;
+        let trace = match tracer.cause {
+            Some(cause) => {
+                let parent = tracer.records.get_mut(&cause).expect("Unknown cause");
+                parent.children.push(id);
+                parent.trace
+            }
+            None => id,
+        };
+
+        tracer.records.insert(
+            id,
+            Record {
+                trace,
+                type_name,
+                from,
+                destination: match destination {
+                    Destination::To(to) => format!("P{to}"),
+                    Destination::BroadcastWithinPool(pool) => format!("pool {pool}"),
+                    Destination::Members(members) => format!("P{members:?}"),
+                },
+                sent_at: now(),
+                deliveries: Vec::new(),
+                children: Vec::new(),
+            },
+        );
+        tracer
+            .in_flight
+            .insert(Shared::as_ptr(message) as *const (), id);
+    });
+}
+
+pub(crate) fn on_deliver(to: ProcessId, message: &SharedMessage) {
+    if !ENABLED.get() {
+        return;
+    }
+
+    TRACER.with_borrow_mut(|tracer| {
+        let id = *tracer
+            .in_flight
+            .get(&(Shared::as_ptr(message) as *const ()))
+            .expect("Delivering untraced message");
+        tracer
+            .records
+            .get_mut(&id)
+            .expect("Unknown message")
+            .deliveries
+            .push((to, now()));
+        tracer.current = Some(id);
+        tracer.cause = None;
+    });
+}
+
+// Values drawn from `stream` for `process` (None for the engine itself)
+pub(crate) fn on_draw(
+    stream: &'static str,
+    process: Option<ProcessId>,
+    context: impl FnOnce() -> String,
+    values: &[usize],
+) {
+    if !ENABLED.get() {
+        return;
+    }
+
+    TRACER.with_borrow_mut(|tracer| {
+        tracer.draws.push(RecordedDraw {
+            stream: stream.to_string(),
+            at: now(),
+            process,
+            context: context(),
+            values: values.to_vec(),
+        })
+    });
+}
+
+// Starts and timers are not caused by any message
+pub(crate) fn on_step() {
+    if !ENABLED.get() {
+        return;
+    }
+
+    TRACER.with_borrow_mut(|tracer| {
+        tracer.cause = None;
+        tracer.current = None;
+    });
+}
+
+/// Returns id of the message being handled by the current process step.
+///
+/// Returns `None` inside [`ProcessHandle::start`], [`ProcessHandle::on_timer`],
+/// or if tracing is disabled.
+///
+/// [`ProcessHandle::start`]: crate::ProcessHandle::start
+/// [`ProcessHandle::on_timer`]: crate::ProcessHandle::on_timer
+pub fn current() -> Option<MessageId> {
+    TRACER.with_borrow(|tracer| tracer.current)
+}
+
+/// Marks all messages sent during the rest of the current step as caused by
+/// the message being handled.
+///
+/// Does nothing outside of [`ProcessHandle::on_message`] or if tracing is disabled.
+///
+/// # Examples
+///
+/// ```rust
+/// use dscale::{MessagePtr, ProcessHandle, ProcessId, TimerId, Message, send_to};
+/// use dscale::global::tracing;
+///
+/// struct Ping;
+/// struct Pong;
+/// impl Message for Ping {}
+/// impl Message for Pong {}
+///
+/// #[derive(Default)]
+/// struct Responder;
+///
+/// impl ProcessHandle for Responder {
+///     fn start(&mut self) {}
+///
+///     fn on_message(&mut self, from: ProcessId, message: MessagePtr) {
+///         if message.is::<Ping>() {
+///             tracing::in_reply_to(); // Pong joins the trace of Ping
+///             send_to(from, Pong);
+///         }
+///     }
+///
+///     fn on_timer(&mut self, id: TimerId) {}
+/// }
+/// ```
+///
+/// [`ProcessHandle::on_message`]: crate::ProcessHandle::on_message
+pub fn in_reply_to() {
+    if let Some(current) = current() {
+        caused_by(current);
+    }
+}
+
+/// Marks all messages sent during the rest of the current step as caused by
+/// `cause`, which may be any message observed earlier (see [`current`]).
+///
+/// # Panics
+///
+/// Panics on the next send if tracing is enabled and `cause` is not a known message.
+pub fn caused_by(cause: MessageId) {
+    TRACER.with_borrow_mut(|tracer| tracer.cause = Some(cause));
+}
+
+/// Returns the trace the message belongs to.
+pub fn trace_of(message: MessageId) -> Option<TraceId> {
+    TRACER.with_borrow(|tracer| tracer.records.get(&message).map(|record| record.trace))
+}
+
+/// Renders the causal tree of a trace: every message with its sender, destination,
+/// send time and deliveries, children indented below their cause.
+///
+/// Should be called before the simulation is dropped.
+///
+/// # Examples
+///
+/// ```text
+/// #1 Ping P1 -> P2 sent at Jiffies(0)
+///     delivered to P2 at Jiffies(7) (+7)
+///   #2 Pong P2 -> P1 sent at Jiffies(7)
+///       delivered to P1 at Jiffies(12) (+5)
+/// ```
+pub fn dump(trace: TraceId) -> String {
+    TRACER.with_borrow(|tracer| {
+        let mut out = String::new();
+        if tracer
+            .records
+            .get(&trace)
+            .is_some_and(|root| root.trace == trace)
+        {
+            render(tracer, trace, 0, &mut out);
+        }
+        out
+    })
+}
+
+fn render(tracer: &Tracer, id: MessageId, depth: usize, out: &mut String) {
+    let record = &tracer.records[&id];
+    let indent = "  ".repeat(depth);
+    let _ = writeln!(
+        out,
+        "{indent}#{id} {} P{} -> {} sent at {}",
+        record.type_name, record.from, record.destination, record.sent_at
+    );
+    record.deliveries.iter().for_each(|(to, at)| {
+        let _ = writeln!(
+            out,
+            "{indent}    delivered to P{to} at {at} (+{})",
+            (*at - record.sent_at).0
+        );
+    });
+    record
+        .children
+        .iter()
+        .for_each(|child| render(tracer, *child, depth + 1, out));
+}
+
+/// A sent message of a [`RecordedTrace`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RecordedMessage {
+    /// Id of the message within its run.
+    pub id: MessageId,
+    /// Trace the message belongs to.
+    pub trace: TraceId,
+    /// Type of the message without its module path.
+    pub type_name: String,
+    /// Sender.
+    pub from: ProcessId,
+    /// Rendered destination, e.g. `P2` or `pool Replicas`.
+    pub destination: String,
+    /// Time the message was sent.
+    pub sent_at: Jiffies,
+    /// Receivers with delivery times, in order of delivery.
+    pub deliveries: Vec<(ProcessId, Jiffies)>,
+}
+
+/// A random draw of a [`RecordedTrace`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RecordedDraw {
+    /// Random stream the values were drawn from: `network` (latencies),
+    /// `access` (choices of processes), `traffic`, `clock_sync` or `wal`.
+    pub stream: String,
+    /// Time of the draw.
+    pub at: Jiffies,
+    /// Process the values were drawn for, e.g. the sender of a message or the
+    /// caller of [`choose_from_pool`]. `None` for draws of the simulation itself.
+    ///
+    /// [`choose_from_pool`]: crate::choose_from_pool
+    pub process: Option<ProcessId>,
+    /// What the values were drawn for, e.g. `latency to P2` or
+    /// `choose_from_pool Replicas`.
+    pub context: String,
+    /// Drawn values: jiffies for latencies and delays, process ids for choices.
+    pub values: Vec<usize>,
+}
+
+/// All traced messages of a run in order of sending.
+///
+/// Obtained with [`record`]. Unlike the tracer, a recording outlives the
+/// simulation and can be stored with [`RecordedTrace::save`], so runs of
+/// different binaries or configurations can be compared later with
+/// [`TraceDiff`].
+///
+/// # File Format
+///
+/// One message per line with tab-separated id, trace, type, sender,
+/// destination, send time and space-separated `receiver@time` deliveries,
+/// followed by one draw per line with tab-separated `rng`, stream, time,
+/// process (`-` if none), context and space-separated values. Times are in
+/// jiffies; recordings of simulations with a configured jiffy duration start
+/// with a `# jiffy duration: <nanoseconds>ns` line.
+///
+/// [`TraceDiff`]: crate::helpers::TraceDiff
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RecordedTrace {
+    /// Messages in order of sending.
+    pub messages: Vec<RecordedMessage>,
+    /// Random draws in order of drawing.
+    pub draws: Vec<RecordedDraw>,
+    /// Real-world duration of one jiffy, see [`SimulationBuilder::jiffy_duration`].
+    ///
+    /// [`SimulationBuilder::jiffy_duration`]: crate::SimulationBuilder::jiffy_duration
+    pub jiffy_duration: Option<Duration>,
+}
+
+impl RecordedTrace {
+    /// Writes the recording into `path`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the file cannot be written.
+    pub fn save(&self, path: impl AsRef<Path>) {
+        let mut out = String::new();
+        if let Some(duration) = self.jiffy_duration {
+            let _ = writeln!(out, "{JIFFY_DURATION}{}ns", duration.as_nanos());
+        }
+        self.messages.iter().for_each(|message| {
+            let deliveries: Vec<String> = message
+                .deliveries
+                .iter()
+                .map(|(to, at)| format!("{to}@{}", at.0))
+                .collect();
+            let _ = writeln!(
+                out,
+                "{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                message.id,
+                message.trace,
+                message.type_name,
+                message.from,
+                message.destination,
+                message.sent_at.0,
+                deliveries.join(" ")
+            );
+        });
+        self.draws.iter().for_each(|draw| {
+            let values: Vec<String> = draw.values.iter().map(usize::to_string).collect();
+            let _ = writeln!(
+                out,
+                "{DRAW}\t{}\t{}\t{}\t{}\t{}",
+                draw.stream,
+                draw.at.0,
+                draw.process.map_or("-".to_string(), |id| id.to_string()),
+                draw.context,
+                values.join(" ")
+            );
+        });
+        fs::write(path, out).expect("Unable to write trace file");
+    }
+
+    /// Reads a recording written by [`RecordedTrace::save`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the file cannot be read or is malformed.
+    pub fn load(path: impl AsRef<Path>) -> Self {
+        let content = fs::read_to_string(path).expect("Unable to read trace file");
+        let number = |field: &str| field.parse::<usize>().expect("Malformed trace file");
+        let jiffy_duration = content
+            .lines()
+            .next()
+            .and_then(|line| line.strip_prefix(JIFFY_DURATION))
+            .map(|nanos| {
+                let nanos = nanos.strip_suffix("ns").expect("Malformed trace file");
+                Duration::from_nanos(nanos.parse().expect("Malformed trace file"))
+            });
+        let (draws, messages): (Vec<&str>, Vec<&str>) = content
+            .lines()
+            .skip(usize::from(jiffy_duration.is_some()))
+            .partition(|line| line.starts_with(&format!("{DRAW}\t")));
+        let messages = messages
+            .into_iter()
+            .map(|line| {
+                let fields: Vec<&str> = line.split('\t').collect();
+                assert_eq!(fields.len(), 7, "Malformed trace line: {line}");
+                RecordedMessage {
+                    id: number(fields[0]),
+                    trace: number(fields[1]),
+                    type_name: fields[2].to_string(),
+                    from: number(fields[3]),
+                    destination: fields[4].to_string(),
+                    sent_at: Jiffies(number(fields[5])),
+                    deliveries: fields[6]
+                        .split_whitespace()
+                        .map(|delivery| {
+                            let (to, at) = delivery.split_once('@').expect("Malformed trace file");
+                            (number(to), Jiffies(number(at)))
+                        })
+                        .collect(),
+                }
+            })
+            .collect();
+        let draws = draws
+            .into_iter()
+            .map(|line| {
+                let fields: Vec<&str> = line.split('\t').collect();
+                assert_eq!(fields.len(), 6, "Malformed trace line: {line}");
+                RecordedDraw {
+                    stream: fields[1].to_string(),
+                    at: Jiffies(number(fields[2])),
+                    process: (fields[3] != "-").then(|| number(fields[3])),
+                    context: fields[4].to_string(),
+                    values: fields[5].split_whitespace().map(number).collect(),
+                }
+            })
+            .collect();
+        Self {
+            messages,
+            draws,
+            jiffy_duration,
+        }
+    }
+}
+
+/// Returns all messages and random draws traced so far in the current run.
+///
+/// Should be called before the simulation is dropped. Empty if tracing is disabled.
+pub fn record() -> RecordedTrace {
+    TRACER.with_borrow(|tracer| {
+        let mut ids: Vec<&MessageId> = tracer.records.keys().collect();
+        ids.sort();
+        RecordedTrace {
+            messages: ids
+                .into_iter()
+                .map(|id| {
+                    let record = &tracer.records[id];
+                    RecordedMessage {
+                        id: *id,
+                        trace: record.trace,
+                        type_name: record.type_name.to_string(),
+                        from: record.from,
+                        destination: record.destination.clone(),
+                        sent_at: record.sent_at,
+                        deliveries: record.deliveries.clone(),
+                    }
+                })
+                .collect(),
+            draws: tracer.draws.clone(),
+            jiffy_duration: configuration::jiffy_duration(),
+        }
+    })
+}
@@ -0,0 +1,200 @@
+//! Write-ahead log on top of the simulated disk.
+//!
+//! Every process owns a log of entries which outlives the process itself: when the
+//! process is restarted with [`Simulation::restart`], the fresh instance reads back
+//! whatever survived the crash. Appends go through the page cache of the process
+//! [`disk`] and become durable only after a [`sync`] completes; at crash time the
+//! unsynced tail is truncated according to [`DiskDescription::crash_truncation`].
+//! This makes recovery paths of replication protocols deal with lost and partially
+//! written tails, as they would in a real deployment.
+//!
+//! [`Simulation::restart`]: crate::Simulation::restart
+//! [`disk`]: crate::global::disk
+//! [`DiskDescription::crash_truncation`]: crate::DiskDescription::crash_truncation
+
+use std::cell::RefCell;
+
+use log::debug;
+
+use crate::{
+    Jiffies, Message, MessagePtr, ProcessId, TimerId, debug_process,
+    global::{
+        disk::{self, CrashTruncation},
+        tracing,
+    },
+    message::{Shared, SharedMessage},
+    now,
+    random::{Distributions, Randomizer, Seed},
+    rank, schedule_timer_after,
+};
+
+/// Position of an entry in the log, starting from zero.
+pub type Lsn = usize;
+
+struct Entry {
+    payload: SharedMessage,
+    durable_at: Option<Jiffies>, // Set once covered by a sync, not decreasing along the log
+}
+
+#[derive(Default)]
+struct Log {
+    entries: Vec<Entry>,
+    unsynced_from: Lsn,
+}
+
+struct Logs {
+    logs: Vec<Log>,
+    random: Randomizer,
+}
+
+thread_local! {
+    static LOGS: RefCell<Option<Logs>> = const { RefCell::new(None) };
+}
+
+pub(crate) fn setup_wals(seed: Seed, proc_num: usize) {
+    LOGS.set(Some(Logs {
+        logs: (0..=proc_num).map(|_| Log::default()).collect(),
+        random: Randomizer::new(seed),
+    }));
+}
+
+pub(crate) fn drop_wals() {
+    LOGS.take();
+}
+
+fn with_log<T>(f: impl FnOnce(&mut Log) -> T) -> T {
+    let id: ProcessId = rank();
+    LOGS.with_borrow_mut(|logs| f(&mut logs.as_mut().expect("Out of simulation context").logs[id]))
+}
+
+/// Appends an entry to the log of the current process and returns its position.
+///
+/// The entry is written to the page cache of the process disk, its size is taken
+/// from [`Message::virtual_size`]. It is visible to [`entries`] immediately, but
+/// may be lost on crash until a [`sync`] covering it completes.
+pub fn append(entry: impl Message + 'static) -> Lsn {
+    disk::write(entry.virtual_size());
+    with_log(|log| {
+        log.entries.push(Entry {
+            payload: Shared::new(entry),
+            durable_at: None,
+        });
+        log.entries.len() - 1
+    })
+}
+
+/// Makes all entries appended so far durable.
+///
+/// Returns a timer which fires in the current process once the entries are
+/// durable, same as [`disk::fsync`].
+///
+/// # Examples
+///
+/// ```rust
+/// use dscale::{ProcessHandle, ProcessId, MessagePtr, TimerId, Message, send_to};
+/// use dscale::global::wal;
+///
+/// struct Vote { term: usize }
+/// impl Message for Vote { fn virtual_size(&self) -> usize { 16 } }
+///
+/// #[derive(Default)]
+/// struct Acceptor {
+///     term: usize,
+///     persisting: Option<TimerId>,
+/// }
+///
+/// impl ProcessHandle for Acceptor {
+///     fn start(&mut self) {
+///         // Recover after restart: the last durable vote wins
+///         if let Some(last) = wal::entries().pop() {
+///             self.term = last.as_type::<Vote>().term;
+///         }
+///     }
+///
+///     fn on_message(&mut self, from: ProcessId, message: MessagePtr) {
+///         self.term += 1;
+///         wal::append(Vote { term: self.term });
+///         self.persisting = Some(wal::sync());
+///     }
+///
+///     fn on_timer(&mut self, id: TimerId) {
+///         if self.persisting == Some(id) {
+///             // Vote is durable, safe to reply
+///         }
+///     }
+/// }
+/// ```
+///
+/// [`disk::fsync`]: crate::global::disk::fsync
+pub fn sync() -> TimerId {
+    let durable_at = disk::flush();
+    with_log(|log| {
+        log.entries[log.unsynced_from..]
+            .iter_mut()
+            .for_each(|entry| entry.durable_at = Some(durable_at));
+        log.unsynced_from = log.entries.len();
+    });
+    debug_process!("WAL: synced entries durable at {durable_at}");
+    schedule_timer_after(durable_at - now())
+}
+
+/// Returns all entries of the log of the current process in append order.
+pub fn entries() -> Vec<MessagePtr> {
+    with_log(|log| {
+        log.entries
+            .iter()
+            .map(|entry| MessagePtr(entry.payload.clone()))
+            .collect()
+    })
+}
+
+/// Number of entries in the log of the current process.
+pub fn len() -> usize {
+    with_log(|log| log.entries.len())
+}
+
+/// Removes entries starting from `from`, for example a conflicting suffix.
+///
+/// Truncation is treated as durable immediately.
+pub fn truncate(from: Lsn) {
+    with_log(|log| {
+        log.entries.truncate(from);
+        log.unsynced_from = log.unsynced_from.min(from);
+    });
+}
+
+// Applies crash truncation policy to the log of the crashed process
+pub(crate) fn on_crash(id: ProcessId) {
+    let policy = disk::description().crash_truncation;
+    LOGS.with_borrow_mut(|logs| {
+        let logs = logs.as_mut().expect("Out of simulation context");
+        let log = &mut logs.logs[id];
+        let durable = log
+            .entries
+            .iter()
+            .position(|entry| entry.durable_at.is_none_or(|at| at > now()))
+            .unwrap_or(log.entries.len());
+        let survived = match policy {
+            CrashTruncation::KeepAll => log.entries.len(),
+            CrashTruncation::DropUnsynced => durable,
+            CrashTruncation::TornTail => {
+                let torn = log.entries.len() - durable;
+                let kept = logs
+                    .random
+                    .random_usize(Distributions::Uniform(Jiffies(0), Jiffies(torn)));
+                tracing::on_draw("wal", Some(id), || "torn tail".to_string(), &[kept]);
+                durable + kept
+            }
+        };
+        debug!(
+            "WAL of P{id}: {} of {} entries survived the crash",
+            survived,
+            log.entries.len()
+        );
+        log.entries.truncate(survived);
+        log.entries
+            .iter_mut()
+            .for_each(|entry| entry.durable_at = Some(now())); // Whatever survived is on disk
+        log.unsynced_from = log.entries.len();
+    });
+}
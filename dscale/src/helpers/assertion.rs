@@ -0,0 +1,143 @@
+//! In-simulation assertions with context capture.
+//!
+//! When enabled with [`SimulationBuilder::record_trails`], the engine keeps a short
+//! trail of recently delivered events and the set of pending timers for every
+//! process. When [`sim_assert!`] fails, this context is printed together with the
+//! current simulation time and process id, which is usually enough to understand
+//! why the assertion failed deep inside a handler. Without trails only the time
+//! and process id are printed.
+//!
+//! [`SimulationBuilder::record_trails`]: crate::SimulationBuilder::record_trails
+//! [`sim_assert!`]: crate::sim_assert
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::{BTreeMap, HashMap, VecDeque},
+    fmt::Arguments,
+};
+
+use crate::{Jiffies, ProcessId, TimerId, global, now};
+
+/// Number of last delivered events remembered for every process.
+pub const RECENT_EVENTS: usize = 16;
+
+#[derive(Clone, Copy)]
+enum Event {
+    Message { from: ProcessId, size: usize },
+    Timer(TimerId),
+}
+
+#[derive(Default)]
+struct ProcessTrail {
+    recent: VecDeque<(Jiffies, Event)>,
+    pending_timers: BTreeMap<TimerId, Jiffies>,
+}
+
+thread_local! {
+    static ENABLED: Cell<bool> = const { Cell::new(false) }; // Keeps hot path cheap when disabled
+    static TRAILS: RefCell<HashMap<ProcessId, ProcessTrail>> = RefCell::new(HashMap::new());
+}
+
+pub(crate) fn setup_trails(enabled: bool) {
+    ENABLED.set(enabled);
+}
+
+fn with_trail(id: ProcessId, f: impl FnOnce(&mut ProcessTrail)) {
+    if !ENABLED.get() {
+        return;
+    }
+    TRAILS.with_borrow_mut(|trails| f(trails.entry(id).or_default()));
+}
+
+fn push_recent(trail: &mut ProcessTrail, event: Event) {
+    if trail.recent.len() == RECENT_EVENTS {
+        trail.recent.pop_front();
+    }
+    trail.recent.push_back((now(), event));
+}
+
+pub(crate) fn record_message(from: ProcessId, to: ProcessId, size: usize) {
+    with_trail(to, |trail| {
+        push_recent(trail, Event::Message { from, size })
+    });
+}
+
+pub(crate) fn record_timer_fired(id: ProcessId, timer: TimerId) {
+    with_trail(id, |trail| {
+        trail.pending_timers.remove(&timer);
+        push_recent(trail, Event::Timer(timer));
+    });
+}
+
+pub(crate) fn record_timer_scheduled(id: ProcessId, timer: TimerId, fire_at: Jiffies) {
+    with_trail(id, |trail| {
+        trail.pending_timers.insert(timer, fire_at);
+    });
+}
+
+pub(crate) fn record_timer_dropped(id: ProcessId, timer: TimerId) {
+    with_trail(id, |trail| {
+        trail.pending_timers.remove(&timer);
+    });
+}
+
+// Timers of a crashed process or of its previous incarnation never fire
+pub(crate) fn forget_pending_timers(id: ProcessId) {
+    with_trail(id, |trail| trail.pending_timers.clear());
+}
+
+pub(crate) fn drop_trails() {
+    ENABLED.take();
+    TRAILS.take();
+}
+
+/// Prints context of the failed assertion and panics. Used by [`sim_assert!`].
+///
+/// [`sim_assert!`]: crate::sim_assert
+#[doc(hidden)]
+#[track_caller]
+pub fn fail(message: Arguments) -> ! {
+    let id = global::rank();
+    let mut report = format!(
+        "sim_assert failed at {} on P{id}: {message}\n",
+        now().display()
+    );
+
+    if ENABLED.get() {
+        TRAILS.with_borrow(|trails| push_trail(&mut report, id, trails.get(&id)));
+    } else {
+        report.push_str(
+            "Enable SimulationBuilder::record_trails to see recent events and pending timers\n",
+        );
+    }
+
+    eprint!("{report}");
+    panic!(
+        "sim_assert failed at {} on P{id}: {message}",
+        now().display()
+    );
+}
+
+fn push_trail(report: &mut String, id: ProcessId, trail: Option<&ProcessTrail>) {
+    report.push_str(&format!("Last delivered events of P{id} (oldest first):\n"));
+    trail
+        .iter()
+        .flat_map(|trail| trail.recent.iter())
+        .for_each(|(at, event)| {
+            let line = match event {
+                Event::Message { from, size } => {
+                    format!("  {}: message from P{from} ({size} bytes)\n", at.display())
+                }
+                Event::Timer(timer) => format!("  {}: timer {timer}\n", at.display()),
+            };
+            report.push_str(&line);
+        });
+
+    report.push_str(&format!("Pending timers of P{id}:\n"));
+    trail
+        .iter()
+        .flat_map(|trail| trail.pending_timers.iter())
+        .for_each(|(timer, fire_at)| {
+            report.push_str(&format!("  timer {timer} fires at {}\n", fire_at.display()));
+        });
+}
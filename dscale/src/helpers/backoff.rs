@@ -0,0 +1,239 @@
+//! Exponential backoff with jitter and a retry budget.
+//!
+//! This module provides the `Backoff` struct driving retry timers, so retry loops
+//! of clients, view synchronizers and recovery procedures follow the same policy
+//! and report the same [`BackoffStats`].
+
+use rand::{Rng, SeedableRng, rngs::StdRng};
+
+use crate::{
+    Jiffies, TimerId,
+    global::{configuration, schedule_timer_after},
+    random::Seed,
+};
+
+// Separates the jitter stream from generators seeded with the plain process seed
+const JITTER_STREAM: Seed = 0x6a69_7474;
+
+/// How a backoff delay is randomized.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Jitter {
+    /// Delays are exactly `min(cap, base * 2^retry)`.
+    None,
+
+    /// Delays are uniform in `[0, min(cap, base * 2^retry)]`.
+    Full,
+
+    /// Half of the delay is kept, the other half is uniform: delays are in
+    /// `[d / 2, d]` for `d = min(cap, base * 2^retry)`.
+    Equal,
+}
+
+/// Counters of a [`Backoff`], accumulated over its lifetime.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BackoffStats {
+    /// Delays handed out, i.e. retries scheduled.
+    pub retries: usize,
+
+    /// Operations given up because the retry budget ran out.
+    pub exhausted: usize,
+
+    /// Operations reported successful with [`Backoff::reset`].
+    pub successes: usize,
+
+    /// Sum of all delays handed out.
+    pub total_delay: Jiffies,
+
+    /// Largest number of retries of a single operation.
+    pub max_retries: usize,
+}
+
+impl BackoffStats {
+    /// Adds counters of `other`, e.g. to sum up backoffs of all clients.
+    pub fn merge(&mut self, other: &BackoffStats) {
+        self.retries += other.retries;
+        self.exhausted += other.exhausted;
+        self.successes += other.successes;
+        self.total_delay += other.total_delay;
+        self.max_retries = self.max_retries.max(other.max_retries);
+    }
+}
+
+/// Capped exponential backoff with jitter and an optional retry budget.
+///
+/// The `n`-th retry of an operation (counting from zero) waits up to
+/// `min(cap, base * 2^n)`, randomized according to [`Jitter`]. Once the budget
+/// of retries is spent, [`next_delay`] returns `None` and the backoff starts over
+/// for the next operation. [`reset`] starts over after a success.
+///
+/// Backoffs created with [`Backoff::new`] draw jitter from the seed of the current
+/// process, so they are usually created in [`ProcessHandle::start`].
+///
+/// # Examples
+///
+/// ## Retry Loop
+///
+/// ```rust
+/// use dscale::{ProcessHandle, ProcessId, MessagePtr, TimerId, Jiffies, Message, send_to};
+/// use dscale::helpers::{Backoff, backoff::Jitter};
+///
+/// struct Request;
+/// impl Message for Request {}
+///
+/// #[derive(Default)]
+/// struct Client {
+///     backoff: Option<Backoff>,
+/// }
+///
+/// impl ProcessHandle for Client {
+///     fn start(&mut self) {
+///         // 10, 20, 40, 80 jiffies with full jitter, then give up
+///         self.backoff = Some(
+///             Backoff::new(Jiffies(10), Jiffies(80))
+///                 .with_jitter(Jitter::Full)
+///                 .with_budget(4),
+///         );
+///         send_to(1, Request);
+///     }
+///
+///     fn on_message(&mut self, from: ProcessId, message: MessagePtr) {
+///         // Rejected: try again later, unless the budget is spent
+///         if self.backoff.as_mut().unwrap().schedule().is_none() {
+///             // Give up
+///         }
+///     }
+///
+///     fn on_timer(&mut self, id: TimerId) {
+///         send_to(1, Request);
+///     }
+/// }
+/// ```
+///
+/// ## Delays
+///
+/// ```rust
+/// use dscale::{Jiffies, helpers::Backoff};
+///
+/// let mut backoff = Backoff::seeded(Jiffies(10), Jiffies(50), 42).with_budget(4);
+/// let delays: Vec<_> = std::iter::from_fn(|| backoff.next_delay()).collect();
+/// assert_eq!(delays, [Jiffies(10), Jiffies(20), Jiffies(40), Jiffies(50)]);
+///
+/// let stats = backoff.stats();
+/// assert_eq!((stats.retries, stats.exhausted), (4, 1));
+/// assert_eq!(stats.total_delay, Jiffies(120));
+/// ```
+///
+/// # Panics
+///
+/// [`Backoff::new`] and [`Backoff::seeded`] panic if `base` is zero or `cap` is
+/// less than `base`.
+///
+/// [`next_delay`]: Backoff::next_delay
+/// [`reset`]: Backoff::reset
+/// [`ProcessHandle::start`]: crate::ProcessHandle::start
+#[derive(Clone, Debug)]
+pub struct Backoff {
+    base: Jiffies,
+    cap: Jiffies,
+    jitter: Jitter,
+    budget: Option<usize>,
+    retries: usize, // Of the current operation
+    rng: StdRng,
+    stats: BackoffStats,
+}
+
+impl Backoff {
+    /// Creates a backoff from `base` to `cap` without jitter and budget, seeded with the seed of the current process.
+    ///
+    /// Should be called from process context, e.g. in [`ProcessHandle::start`].
+    ///
+    /// [`ProcessHandle::start`]: crate::ProcessHandle::start
+    pub fn new(base: Jiffies, cap: Jiffies) -> Self {
+        Self::seeded(base, cap, configuration::seed() ^ JITTER_STREAM)
+    }
+
+    /// Creates a backoff from `base` to `cap` with an explicit seed.
+    pub fn seeded(base: Jiffies, cap: Jiffies, seed: Seed) -> Self {
+        assert!(base.0 > 0, "Backoff base should be positive");
+        assert!(cap >= base, "Backoff cap should not be less than base");
+        Self {
+            base,
+            cap,
+            jitter: Jitter::None,
+            budget: None,
+            retries: 0,
+            rng: StdRng::seed_from_u64(seed),
+            stats: BackoffStats::default(),
+        }
+    }
+
+    /// Randomizes delays according to `jitter`.
+    pub fn with_jitter(mut self, jitter: Jitter) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Allows at most `retries` retries per operation.
+    pub fn with_budget(mut self, retries: usize) -> Self {
+        self.budget = Some(retries);
+        self
+    }
+
+    /// Returns the delay before the next retry, or `None` if the budget is spent.
+    ///
+    /// Running out of budget is counted in [`BackoffStats::exhausted`] and starts
+    /// the backoff over, as [`reset`] does.
+    ///
+    /// [`reset`]: Backoff::reset
+    pub fn next_delay(&mut self) -> Option<Jiffies> {
+        if self.budget.is_some_and(|budget| self.retries >= budget) {
+            self.stats.exhausted += 1;
+            self.retries = 0;
+            return None;
+        }
+
+        let ceiling = self
+            .base
+            .0
+            .saturating_mul(
+                1usize
+                    .checked_shl(self.retries as u32)
+                    .unwrap_or(usize::MAX),
+            )
+            .min(self.cap.0);
+        let delay = match self.jitter {
+            Jitter::None => ceiling,
+            Jitter::Full => self.rng.random_range(0..=ceiling),
+            Jitter::Equal => ceiling - ceiling / 2 + self.rng.random_range(0..=ceiling / 2),
+        };
+
+        self.retries += 1;
+        self.stats.retries += 1;
+        self.stats.total_delay += Jiffies(delay);
+        self.stats.max_retries = self.stats.max_retries.max(self.retries);
+        Some(Jiffies(delay))
+    }
+
+    /// Schedules a timer after [`next_delay`], or returns `None` if the budget is spent.
+    ///
+    /// [`next_delay`]: Backoff::next_delay
+    pub fn schedule(&mut self) -> Option<TimerId> {
+        self.next_delay().map(schedule_timer_after)
+    }
+
+    /// Reports the operation successful and starts the backoff over.
+    pub fn reset(&mut self) {
+        self.stats.successes += 1;
+        self.retries = 0;
+    }
+
+    /// Number of retries of the current operation.
+    pub fn retries(&self) -> usize {
+        self.retries
+    }
+
+    /// Counters accumulated since creation.
+    pub fn stats(&self) -> BackoffStats {
+        self.stats
+    }
+}
@@ -0,0 +1,299 @@
+//! Batch scheduling of simulations within a wall-clock budget.
+//!
+//! Nightly CI jobs have a fixed amount of time, and the set of experiments worth
+//! running usually does not fit into it. This module provides the
+//! `BatchScheduler` struct, which runs experiments in priority order while their
+//! estimated wall-clock time fits into what is left of the total budget, and
+//! reports the results of the runs that fit together with the skipped ones.
+
+use std::{
+    fmt::{self, Debug},
+    fs,
+    panic::{self, AssertUnwindSafe},
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use log::info;
+
+struct Job<'a, T> {
+    name: String,
+    priority: u32,
+    budget: Duration,
+    experiment: Box<dyn FnOnce() -> T + 'a>,
+}
+
+/// What happened to a job of a batch.
+#[derive(Clone, Debug, PartialEq)]
+pub enum JobStatus<T> {
+    /// The experiment finished with a result.
+    Completed(T),
+    /// The experiment panicked with the message.
+    Failed(String),
+    /// The budget of the job did not fit into the rest of the total budget.
+    Skipped,
+}
+
+/// Outcome of a job, see [`BatchReport`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct JobOutcome<T> {
+    /// Name given to the job.
+    pub name: String,
+    /// Priority of the job, higher runs first.
+    pub priority: u32,
+    /// Estimated wall-clock time of the job.
+    pub budget: Duration,
+    /// Wall-clock time the job took, zero if skipped.
+    pub elapsed: Duration,
+    /// Result of the job.
+    pub status: JobStatus<T>,
+}
+
+impl<T> JobOutcome<T> {
+    /// Returns `true` if the job took longer than its budget.
+    pub fn overran(&self) -> bool {
+        self.elapsed > self.budget
+    }
+}
+
+/// Runs as many experiments as fit into a total wall-clock budget.
+///
+/// Every job has a priority and an estimated wall-clock budget. Jobs run one at
+/// a time in order of decreasing priority (jobs of equal priority in the order
+/// they were added). A job runs only if its budget fits into the time left, so
+/// a long job which does not fit is skipped while shorter jobs of lower
+/// priority may still run. Running jobs are never interrupted: a job exceeding
+/// its budget is marked as overran, and the jobs after it get less time.
+///
+/// Experiments build and run their own simulations, usually reading results
+/// from [`anykv`]. A panic fails the job without stopping the batch.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::time::Duration;
+/// use dscale::{SimulationBuilder, Jiffies, global::anykv};
+/// use dscale::helpers::batch::{BatchScheduler, JobStatus};
+///
+/// let ticks = |processes: usize| {
+///     move || {
+///         anykv::set::<usize>("ticks", 0);
+///         let mut simulation = SimulationBuilder::default()
+///             .add_pool::<Ticker>("tickers", processes)
+///             .time_budget(Jiffies(1_000))
+///             .build();
+///         simulation.run();
+///         anykv::get::<usize>("ticks")
+///     }
+/// };
+///
+/// let report = BatchScheduler::new(Duration::from_secs(60))
+///     .job("small", 10, Duration::from_secs(1), ticks(3))
+///     .job("huge", 5, Duration::from_secs(3_600), ticks(1_000))
+///     .job("medium", 1, Duration::from_secs(5), ticks(30))
+///     .run();
+///
+/// assert!(matches!(report.outcomes[0].status, JobStatus::Completed(_)));
+/// assert_eq!(report.outcomes[1].status, JobStatus::Skipped);
+/// assert_eq!(report.completed().count(), 2);
+/// println!("{report}");
+/// # #[derive(Default)]
+/// # struct Ticker;
+/// # impl dscale::ProcessHandle for Ticker {
+/// #     fn start(&mut self) { dscale::schedule_timer_after(Jiffies(10)); }
+/// #     fn on_message(&mut self, from: dscale::ProcessId, message: dscale::MessagePtr) {}
+/// #     fn on_timer(&mut self, id: dscale::TimerId) {
+/// #         anykv::modify::<usize>("ticks", |t| *t += 1);
+/// #         dscale::schedule_timer_after(Jiffies(10));
+/// #     }
+/// # }
+/// ```
+///
+/// [`anykv`]: crate::global::anykv
+pub struct BatchScheduler<'a, T> {
+    total: Duration,
+    jobs: Vec<Job<'a, T>>,
+}
+
+impl<'a, T> BatchScheduler<'a, T> {
+    /// Creates an empty batch which may take `total` wall-clock time.
+    pub fn new(total: Duration) -> Self {
+        Self {
+            total,
+            jobs: Vec::new(),
+        }
+    }
+
+    /// Adds an experiment expected to take `budget` wall-clock time.
+    pub fn job(
+        mut self,
+        name: impl Into<String>,
+        priority: u32,
+        budget: Duration,
+        experiment: impl FnOnce() -> T + 'a,
+    ) -> Self {
+        self.jobs.push(Job {
+            name: name.into(),
+            priority,
+            budget,
+            experiment: Box::new(experiment),
+        });
+        self
+    }
+
+    /// Runs the jobs which fit into the total budget.
+    pub fn run(mut self) -> BatchReport<T> {
+        // Stable: equal priorities keep the order of addition
+        self.jobs.sort_by_key(|job| std::cmp::Reverse(job.priority));
+
+        let start = Instant::now();
+        let outcomes = self
+            .jobs
+            .into_iter()
+            .map(|job| {
+                let left = self.total.saturating_sub(start.elapsed());
+                let mut outcome = JobOutcome {
+                    name: job.name,
+                    priority: job.priority,
+                    budget: job.budget,
+                    elapsed: Duration::ZERO,
+                    status: JobStatus::Skipped,
+                };
+                if job.budget > left {
+                    info!("Batch job '{}' skipped: {:?} left", outcome.name, left);
+                    return outcome;
+                }
+
+                let job_start = Instant::now();
+                outcome.status = match panic::catch_unwind(AssertUnwindSafe(job.experiment)) {
+                    Ok(result) => JobStatus::Completed(result),
+                    Err(panic) => JobStatus::Failed(panic_message(panic.as_ref())),
+                };
+                outcome.elapsed = job_start.elapsed();
+                info!(
+                    "Batch job '{}' finished in {:?}",
+                    outcome.name, outcome.elapsed
+                );
+                outcome
+            })
+            .collect();
+
+        BatchReport {
+            outcomes,
+            total: self.total,
+            elapsed: start.elapsed(),
+        }
+    }
+}
+
+fn panic_message(panic: &(dyn std::any::Any + Send)) -> String {
+    panic
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| panic.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "Unknown panic".to_string())
+}
+
+/// Results of a batch, in the order the jobs were considered.
+///
+/// Displays as a table with one line per job.
+#[derive(Clone, Debug)]
+pub struct BatchReport<T> {
+    /// Outcomes of all jobs, ordered by decreasing priority.
+    pub outcomes: Vec<JobOutcome<T>>,
+    /// Total budget of the batch.
+    pub total: Duration,
+    /// Wall-clock time the batch took.
+    pub elapsed: Duration,
+}
+
+impl<T> BatchReport<T> {
+    /// Names and results of completed jobs.
+    pub fn completed(&self) -> impl Iterator<Item = (&str, &T)> {
+        self.outcomes
+            .iter()
+            .filter_map(|outcome| match &outcome.status {
+                JobStatus::Completed(result) => Some((outcome.name.as_str(), result)),
+                _ => None,
+            })
+    }
+
+    /// Names of skipped jobs.
+    pub fn skipped(&self) -> impl Iterator<Item = &str> {
+        self.outcomes
+            .iter()
+            .filter(|outcome| matches!(outcome.status, JobStatus::Skipped))
+            .map(|outcome| outcome.name.as_str())
+    }
+
+    /// Names and panic messages of failed jobs.
+    pub fn failed(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.outcomes
+            .iter()
+            .filter_map(|outcome| match &outcome.status {
+                JobStatus::Failed(message) => Some((outcome.name.as_str(), message.as_str())),
+                _ => None,
+            })
+    }
+
+    /// Returns `true` if every job ran and none failed.
+    pub fn is_complete(&self) -> bool {
+        self.completed().count() == self.outcomes.len()
+    }
+}
+
+impl<T: Debug> BatchReport<T> {
+    /// Writes the report into a CSV file with columns
+    /// `name,priority,budget_ms,elapsed_ms,status,result`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the file cannot be written.
+    pub fn save(&self, path: impl AsRef<Path>) {
+        let mut out = String::from("name,priority,budget_ms,elapsed_ms,status,result\n");
+        self.outcomes.iter().for_each(|outcome| {
+            let (status, result) = match &outcome.status {
+                JobStatus::Completed(result) => ("completed", format!("{result:?}")),
+                JobStatus::Failed(message) => ("failed", message.clone()),
+                JobStatus::Skipped => ("skipped", String::new()),
+            };
+            out.push_str(&format!(
+                "{},{},{},{},{status},\"{}\"\n",
+                outcome.name,
+                outcome.priority,
+                outcome.budget.as_millis(),
+                outcome.elapsed.as_millis(),
+                result.replace('"', "\"\""),
+            ));
+        });
+        fs::write(path, out).expect("Unable to write batch report");
+    }
+}
+
+impl<T: Debug> fmt::Display for BatchReport<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Batch: {} of {} jobs completed in {:.1?} of {:.1?}",
+            self.completed().count(),
+            self.outcomes.len(),
+            self.elapsed,
+            self.total
+        )?;
+        self.outcomes.iter().try_for_each(|outcome| {
+            write!(
+                f,
+                "  [{:>3}] {:<24} {:>10.1?} / {:>10.1?}  ",
+                outcome.priority, outcome.name, outcome.elapsed, outcome.budget
+            )?;
+            match &outcome.status {
+                JobStatus::Completed(result) if outcome.overran() => {
+                    writeln!(f, "overran: {result:?}")
+                }
+                JobStatus::Completed(result) => writeln!(f, "{result:?}"),
+                JobStatus::Failed(message) => writeln!(f, "FAILED: {message}"),
+                JobStatus::Skipped => writeln!(f, "skipped"),
+            }
+        })
+    }
+}
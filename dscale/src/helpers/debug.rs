@@ -2,6 +2,134 @@
 #[macro_export]
 macro_rules! debug_process {
     ($($arg:tt)+) => {
-        log::debug!("[Now: {} | P{}] {}", now(), rank(), format_args!($($arg)+));
+        $crate::log_process!(log::Level::Debug, $($arg)+)
     }
 }
+
+#[macro_export]
+macro_rules! warn_process {
+    ($($arg:tt)+) => {
+        $crate::log_process!(log::Level::Warn, $($arg)+)
+    }
+}
+
+#[macro_export]
+macro_rules! error_process {
+    ($($arg:tt)+) => {
+        $crate::log_process!(log::Level::Error, $($arg)+)
+    }
+}
+
+// Arguments are evaluated once, for both the log and the capture
+#[doc(hidden)]
+#[macro_export]
+macro_rules! log_process {
+    ($level:expr, $($arg:tt)+) => {
+        match format_args!($($arg)+) {
+            message => {
+                log::log!($level, "[Now: {} | P{}] {}", now(), rank(), message);
+                $crate::helpers::log_capture::capture($level, message);
+            }
+        }
+    }
+}
+
+/// Asserts a condition inside a process step, capturing simulation context on failure.
+///
+/// Works like [`assert!`], but before panicking it prints the current simulation
+/// time and id of the executing process. With [`SimulationBuilder::record_trails`]
+/// it also prints the last [`RECENT_EVENTS`] events delivered to that process and
+/// its pending timers.
+///
+/// Must be called within the context of a running process step.
+///
+/// # Examples
+///
+/// ```rust
+/// use dscale::{MessagePtr, ProcessHandle, ProcessId, TimerId, sim_assert};
+///
+/// #[derive(Default)]
+/// struct Counter {
+///     received: usize,
+/// }
+///
+/// impl ProcessHandle for Counter {
+///     fn start(&mut self) {}
+///
+///     fn on_message(&mut self, from: ProcessId, message: MessagePtr) {
+///         self.received += 1;
+///         sim_assert!(self.received <= 10, "P{from} flooded us: {} messages", self.received);
+///     }
+///
+///     fn on_timer(&mut self, id: TimerId) {}
+/// }
+/// ```
+///
+/// [`SimulationBuilder::record_trails`]: crate::SimulationBuilder::record_trails
+/// [`RECENT_EVENTS`]: crate::helpers::assertion::RECENT_EVENTS
+#[macro_export]
+macro_rules! sim_assert {
+    ($cond:expr $(,)?) => {
+        $crate::sim_assert!($cond, "{}", stringify!($cond))
+    };
+    ($cond:expr, $($arg:tt)+) => {
+        if !$cond {
+            $crate::helpers::assertion::fail(format_args!($($arg)+));
+        }
+    };
+}
+
+/// Reports a protocol error of the current process instead of panicking.
+///
+/// Meant for states the protocol rules out but faults or malformed input can
+/// still reach, in place of `unwrap`, `expect` or `unreachable!`. Under the
+/// default [`ErrorPolicy::Abort`] it fails like [`sim_assert!`]; under
+/// [`ErrorPolicy::Record`] the error is recorded and the handler goes on, so it
+/// should bail out right after the report.
+///
+/// Must be called within the context of a running process step.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::collections::HashMap;
+/// use dscale::{MessagePtr, ProcessHandle, ProcessId, TimerId, protocol_error};
+///
+/// struct Response {
+///     request: usize,
+/// }
+///
+/// impl dscale::Message for Response {}
+///
+/// #[derive(Default)]
+/// struct Client {
+///     pending: HashMap<usize, String>, // Request -> operation
+/// }
+///
+/// impl ProcessHandle for Client {
+///     fn start(&mut self) {}
+///
+///     fn on_message(&mut self, from: ProcessId, message: MessagePtr) {
+///         let Some(response) = message.try_as::<Response>() else {
+///             protocol_error!("Unexpected message from P{from}");
+///             return;
+///         };
+///         let Some(operation) = self.pending.remove(&response.request) else {
+///             protocol_error!("P{from} answered unknown request {}", response.request);
+///             return;
+///         };
+///     }
+///
+///     fn on_timer(&mut self, id: TimerId) {}
+/// }
+/// ```
+///
+/// [`ErrorPolicy::Abort`]: crate::ErrorPolicy::Abort
+/// [`ErrorPolicy::Record`]: crate::ErrorPolicy::Record
+/// [`sim_assert!`]: crate::sim_assert
+#[macro_export]
+macro_rules! protocol_error {
+    ($($arg:tt)+) => {
+        $crate::global::errors::report(format_args!($($arg)+))
+    };
+}
@@ -0,0 +1,226 @@
+//! Bounded suppression of duplicate deliveries.
+//!
+//! This module provides the `DedupCache` struct for protocols which must
+//! remember what they have already seen (reliable broadcast, gossip) without
+//! keeping every identifier of a long run in memory.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    hash::Hash,
+};
+
+use crate::{Jiffies, global::now};
+
+/// Hit and miss counters of a [`DedupCache`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DedupStats {
+    /// Lookups and insertions of keys already in the cache.
+    pub hits: usize,
+    /// Lookups and insertions of keys not in the cache.
+    pub misses: usize,
+    /// Keys dropped because they outlived the time to live.
+    pub expired: usize,
+    /// Keys dropped because the cache was full.
+    pub evicted: usize,
+}
+
+impl DedupStats {
+    /// Share of hits among all lookups and insertions.
+    pub fn hit_rate(&self) -> f64 {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            return 0.0;
+        }
+        self.hits as f64 / total as f64
+    }
+}
+
+/// A set of recently seen keys with bounded memory.
+///
+/// Keys are forgotten `ttl` jiffies after their insertion, and the oldest keys
+/// are forgotten first once more than `capacity` keys are held. A forgotten key
+/// is treated as new again, so `ttl` and `capacity` should cover the window in
+/// which duplicates can still arrive, e.g. a few maximal network delays.
+///
+/// Every lookup and insertion is counted in [`DedupStats`], which helps sizing
+/// the cache: evictions together with hits on the same workload with a larger
+/// cache mean the cache is too small.
+///
+/// # Examples
+///
+/// ## Gossip
+///
+/// ```rust
+/// use dscale::{ProcessHandle, ProcessId, MessagePtr, TimerId, Jiffies, Message, broadcast};
+/// use dscale::helpers::DedupCache;
+///
+/// struct Rumor(usize);
+/// impl Message for Rumor {}
+///
+/// struct Gossiper {
+///     seen: DedupCache<usize>,
+/// }
+///
+/// impl Default for Gossiper {
+///     fn default() -> Self {
+///         Self {
+///             seen: DedupCache::new(10_000, Jiffies(5_000)),
+///         }
+///     }
+/// }
+///
+/// impl ProcessHandle for Gossiper {
+///     fn start(&mut self) {}
+///
+///     fn on_message(&mut self, from: ProcessId, message: MessagePtr) {
+///         let id = message.as_type::<Rumor>().0;
+///         // Relay every rumor once
+///         if self.seen.insert(id) {
+///             broadcast(Rumor(id));
+///         }
+///     }
+///
+///     fn on_timer(&mut self, id: TimerId) {}
+/// }
+/// ```
+///
+/// ## Eviction
+///
+/// ```rust
+/// use dscale::{Jiffies, helpers::DedupCache};
+///
+/// let mut seen = DedupCache::new(2, Jiffies(100));
+/// assert!(seen.insert("a"));
+/// assert!(seen.insert("b"));
+/// assert!(!seen.insert("a")); // Duplicate
+///
+/// assert!(seen.insert("c")); // Full, "a" is forgotten
+/// assert!(!seen.contains(&"a"));
+///
+/// let stats = seen.stats();
+/// assert_eq!((stats.hits, stats.misses, stats.evicted), (1, 4, 1));
+/// ```
+///
+/// # Panics
+///
+/// [`DedupCache::new`] panics if `capacity` or `ttl` is zero.
+#[derive(Clone, Debug)]
+pub struct DedupCache<K> {
+    capacity: usize,
+    ttl: Jiffies,
+    inserted: HashMap<K, Jiffies>,
+    order: VecDeque<(Jiffies, K)>, // Insertion order, may contain removed keys
+    stats: DedupStats,
+}
+
+impl<K: Eq + Hash + Clone> DedupCache<K> {
+    /// Creates an empty cache holding at most `capacity` keys for at most `ttl` jiffies each.
+    pub fn new(capacity: usize, ttl: Jiffies) -> Self {
+        assert!(capacity > 0, "DedupCache capacity should be positive");
+        assert!(ttl.0 > 0, "DedupCache time to live should be positive");
+        Self {
+            capacity,
+            ttl,
+            inserted: HashMap::new(),
+            order: VecDeque::new(),
+            stats: DedupStats::default(),
+        }
+    }
+
+    /// Remembers `key`.
+    ///
+    /// Returns `true` if the key is new, `false` for a duplicate. The time to
+    /// live of a duplicate is not extended.
+    pub fn insert(&mut self, key: K) -> bool {
+        self.expire();
+        if self.inserted.contains_key(&key) {
+            self.stats.hits += 1;
+            return false;
+        }
+        self.stats.misses += 1;
+
+        if self.inserted.len() == self.capacity {
+            self.pop_oldest();
+            self.stats.evicted += 1;
+        }
+        let present = now();
+        self.inserted.insert(key.clone(), present);
+        self.order.push_back((present, key));
+        self.compact();
+        true
+    }
+
+    /// Whether `key` was inserted and is not forgotten yet.
+    pub fn contains(&mut self, key: &K) -> bool {
+        self.expire();
+        let hit = self.inserted.contains_key(key);
+        if hit {
+            self.stats.hits += 1;
+        } else {
+            self.stats.misses += 1;
+        }
+        hit
+    }
+
+    /// Forgets `key`, returns whether it was in the cache.
+    ///
+    /// Counted as a lookup in [`DedupStats`].
+    pub fn remove(&mut self, key: &K) -> bool {
+        let hit = self.contains(key);
+        self.inserted.remove(key);
+        hit
+    }
+
+    /// Number of keys held.
+    pub fn len(&self) -> usize {
+        self.inserted.len()
+    }
+
+    /// Whether no keys are held.
+    pub fn is_empty(&self) -> bool {
+        self.inserted.is_empty()
+    }
+
+    /// Hit and miss counters since creation.
+    pub fn stats(&self) -> DedupStats {
+        self.stats
+    }
+}
+
+impl<K: Eq + Hash + Clone> DedupCache<K> {
+    fn is_live(&self, entry: &(Jiffies, K)) -> bool {
+        self.inserted.get(&entry.1) == Some(&entry.0)
+    }
+
+    fn expire(&mut self) {
+        let present = now();
+        while let Some(entry) = self.order.front() {
+            if !self.is_live(entry) {
+                self.order.pop_front();
+            } else if entry.0 + self.ttl <= present {
+                let (_, key) = self.order.pop_front().expect("Should not be empty");
+                self.inserted.remove(&key);
+                self.stats.expired += 1;
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn pop_oldest(&mut self) {
+        while let Some(entry) = self.order.pop_front() {
+            if self.is_live(&entry) {
+                self.inserted.remove(&entry.1);
+                return;
+            }
+        }
+    }
+
+    // Entries of removed keys would grow the queue without bound otherwise
+    fn compact(&mut self) {
+        if self.order.len() > 2 * self.capacity {
+            let inserted = &self.inserted;
+            self.order.retain(|(at, key)| inserted.get(key) == Some(at));
+        }
+    }
+}
@@ -0,0 +1,294 @@
+//! Service discovery through a directory process.
+//!
+//! [`list_pool`] answers instantly and is always consistent, which hides a
+//! whole class of bugs: processes starting before their peers are known,
+//! members leaving while others still contact them. This module models
+//! discovery the way deployments do it (DNS, Consul, ZooKeeper):
+//!
+//! - [`Directory`]: runs on a dedicated process. Members register and
+//!   deregister with it, updates become visible only after a propagation delay.
+//! - [`DiscoveryClient`]: runs on every other process. It resolves pools through
+//!   the directory and caches answers for a TTL, serving stale memberships
+//!   while a refresh is on its way.
+//!
+//! Like failure detectors, both live inside processes and share their messages
+//! and timers: the process offers every received message and fired timer to
+//! them first and handles it itself only if they did not.
+//!
+//! [`list_pool`]: crate::list_pool
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{
+    Jiffies, Message, MessagePtr, ProcessId, TimerId, now, pool_member, schedule_named_timer,
+    send_to, timer_kind,
+};
+
+// Messages exchanged by directories and clients
+enum Discovery {
+    Register(&'static str),
+    Deregister(&'static str),
+    Resolve(&'static str),
+    Resolved(&'static str, Vec<ProcessId>),
+}
+
+impl Message for Discovery {
+    fn virtual_size(&self) -> usize {
+        match self {
+            Discovery::Resolved(_, members) => 16 + 8 * members.len(),
+            _ => 16,
+        }
+    }
+}
+
+// Timer applying a pending update, recognized with timer_kind
+#[derive(Clone, Copy)]
+struct Propagation(usize);
+
+/// Membership of pools as known to the directory process.
+///
+/// Registrations and deregistrations take effect `propagation` jiffies after
+/// they reach the directory; resolutions are answered with the memberships in
+/// effect.
+///
+/// # Examples
+///
+/// ```rust
+/// use dscale::{ProcessHandle, ProcessId, MessagePtr, TimerId, Jiffies};
+/// use dscale::helpers::discovery::Directory;
+///
+/// struct Dns {
+///     directory: Directory,
+/// }
+///
+/// impl Default for Dns {
+///     fn default() -> Self {
+///         Self { directory: Directory::new(Jiffies(30)) }
+///     }
+/// }
+///
+/// impl ProcessHandle for Dns {
+///     fn start(&mut self) {}
+///
+///     fn on_message(&mut self, from: ProcessId, message: MessagePtr) {
+///         self.directory.on_message(from, &message);
+///     }
+///
+///     fn on_timer(&mut self, id: TimerId) {
+///         self.directory.on_timer(id);
+///     }
+/// }
+/// ```
+pub struct Directory {
+    propagation: Jiffies,
+    members: BTreeMap<&'static str, BTreeSet<ProcessId>>,
+    pending: BTreeMap<usize, (&'static str, ProcessId, bool)>, // Update -> (pool, member, joins)
+    next_update: usize,
+}
+
+impl Directory {
+    /// Creates an empty directory applying updates `propagation` jiffies after they arrive.
+    pub fn new(propagation: Jiffies) -> Self {
+        Self {
+            propagation,
+            members: BTreeMap::new(),
+            pending: BTreeMap::new(),
+            next_update: 0,
+        }
+    }
+
+    /// Handles a received message if it belongs to discovery.
+    ///
+    /// Returns `false` for messages of the protocol.
+    pub fn on_message(&mut self, from: ProcessId, message: &MessagePtr) -> bool {
+        let Some(request) = message.try_as::<Discovery>() else {
+            return false;
+        };
+        match request.as_ref() {
+            Discovery::Register(pool) => self.update(pool, from, true),
+            Discovery::Deregister(pool) => self.update(pool, from, false),
+            Discovery::Resolve(pool) => {
+                send_to(from, Discovery::Resolved(pool, self.members(pool)))
+            }
+            Discovery::Resolved(..) => {}
+        }
+        true
+    }
+
+    /// Handles a fired timer if it belongs to discovery.
+    ///
+    /// Returns `false` for timers of the protocol.
+    pub fn on_timer(&mut self, id: TimerId) -> bool {
+        let Some(Propagation(update)) = timer_kind::<Propagation>(id) else {
+            return false;
+        };
+        if let Some((pool, member, joins)) = self.pending.remove(&update) {
+            self.apply(pool, member, joins);
+        }
+        true
+    }
+
+    /// Members of the pool in effect at the directory.
+    pub fn members(&self, pool: &str) -> Vec<ProcessId> {
+        self.members
+            .get(pool)
+            .map(|members| members.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    fn update(&mut self, pool: &'static str, member: ProcessId, joins: bool) {
+        if self.propagation == Jiffies(0) {
+            return self.apply(pool, member, joins);
+        }
+        self.next_update += 1;
+        self.pending.insert(self.next_update, (pool, member, joins));
+        schedule_named_timer(self.propagation, Propagation(self.next_update));
+    }
+
+    fn apply(&mut self, pool: &'static str, member: ProcessId, joins: bool) {
+        let members = self.members.entry(pool).or_default();
+        if joins {
+            members.insert(member);
+        } else {
+            members.remove(&member);
+        }
+    }
+}
+
+/// Resolver of pool memberships with a TTL cache.
+///
+/// The directory is the first member of the `directory` pool. [`resolve`]
+/// returns the cached membership of a pool and queries the directory once the
+/// cache entry expired (or never existed), so answers may be stale by up to
+/// the TTL plus the propagation delay of the directory. A resolution arriving
+/// is reported by [`on_message`].
+///
+/// # Examples
+///
+/// ```rust
+/// use dscale::{ProcessHandle, ProcessId, MessagePtr, TimerId, Jiffies, SimulationBuilder};
+/// use dscale::{Distributions, LatencyDescription, schedule_timer_after, send_to};
+/// use dscale::helpers::discovery::{Directory, DiscoveryClient};
+///
+/// struct Gossip;
+/// impl dscale::Message for Gossip {}
+///
+/// struct Node {
+///     discovery: DiscoveryClient,
+/// }
+///
+/// impl Default for Node {
+///     fn default() -> Self {
+///         Self { discovery: DiscoveryClient::new("dns", Jiffies(100)) }
+///     }
+/// }
+///
+/// impl ProcessHandle for Node {
+///     fn start(&mut self) {
+///         self.discovery.register("nodes");
+///         schedule_timer_after(Jiffies(10));
+///     }
+///
+///     fn on_message(&mut self, from: ProcessId, message: MessagePtr) {
+///         if self.discovery.on_message(from, &message).is_some() {
+///             return;
+///         }
+///         // Gossip from peers
+///     }
+///
+///     fn on_timer(&mut self, id: TimerId) {
+///         // Empty until the first resolution, stale members are contacted too
+///         let peers = self.discovery.resolve("nodes").unwrap_or_default();
+///         peers.iter().for_each(|peer| send_to(*peer, Gossip));
+///         schedule_timer_after(Jiffies(10));
+///     }
+/// }
+///
+/// # struct Dns { directory: Directory }
+/// # impl Default for Dns {
+/// #     fn default() -> Self { Self { directory: Directory::new(Jiffies(30)) } }
+/// # }
+/// # impl ProcessHandle for Dns {
+/// #     fn start(&mut self) {}
+/// #     fn on_message(&mut self, from: ProcessId, message: MessagePtr) {
+/// #         self.directory.on_message(from, &message);
+/// #     }
+/// #     fn on_timer(&mut self, id: TimerId) { self.directory.on_timer(id); }
+/// # }
+/// let mut simulation = SimulationBuilder::default()
+///     .add_pool::<Dns>("dns", 1)
+///     .add_pool::<Node>("nodes", 4)
+///     .latency_topology(&[LatencyDescription::WithinPool(
+///         dscale::GLOBAL_POOL,
+///         Distributions::Uniform(Jiffies(5), Jiffies(10)),
+///     )])
+///     .time_budget(Jiffies(1_000))
+///     .build();
+/// simulation.run();
+/// ```
+///
+/// # Panics
+///
+/// Methods sending to the directory panic if the `directory` pool is empty.
+///
+/// [`resolve`]: DiscoveryClient::resolve
+/// [`on_message`]: DiscoveryClient::on_message
+pub struct DiscoveryClient {
+    directory: &'static str,
+    ttl: Jiffies,
+    cache: BTreeMap<&'static str, (Jiffies, Vec<ProcessId>)>, // Pool -> (expiry, members)
+    querying: BTreeSet<&'static str>,
+}
+
+impl DiscoveryClient {
+    /// Creates a client of the directory running in the `directory` pool, caching answers for `ttl` jiffies.
+    pub fn new(directory: &'static str, ttl: Jiffies) -> Self {
+        Self {
+            directory,
+            ttl,
+            cache: BTreeMap::new(),
+            querying: BTreeSet::new(),
+        }
+    }
+
+    /// Registers the current process as a member of the pool.
+    pub fn register(&self, pool: &'static str) {
+        send_to(self.directory(), Discovery::Register(pool));
+    }
+
+    /// Deregisters the current process from the pool.
+    pub fn deregister(&self, pool: &'static str) {
+        send_to(self.directory(), Discovery::Deregister(pool));
+    }
+
+    /// Cached members of the pool, `None` until the first resolution arrives.
+    ///
+    /// Queries the directory if the cache entry expired and no query is in flight.
+    pub fn resolve(&mut self, pool: &'static str) -> Option<Vec<ProcessId>> {
+        let expired = self
+            .cache
+            .get(pool)
+            .is_none_or(|(expiry, _)| *expiry <= now());
+        if expired && self.querying.insert(pool) {
+            send_to(self.directory(), Discovery::Resolve(pool));
+        }
+        self.cache.get(pool).map(|(_, members)| members.clone())
+    }
+
+    /// Handles a received message if it belongs to discovery.
+    ///
+    /// Returns the pool whose resolution arrived, `None` for messages of the protocol.
+    pub fn on_message(&mut self, _from: ProcessId, message: &MessagePtr) -> Option<&'static str> {
+        let response = message.try_as::<Discovery>()?;
+        let Discovery::Resolved(pool, members) = response.as_ref() else {
+            return None;
+        };
+        self.querying.remove(pool);
+        self.cache.insert(pool, (now() + self.ttl, members.clone()));
+        Some(pool)
+    }
+
+    fn directory(&self) -> ProcessId {
+        pool_member(self.directory, 0).expect("Directory pool should not be empty")
+    }
+}
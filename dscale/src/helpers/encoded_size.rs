@@ -0,0 +1,66 @@
+//! Message sizes grounded in real encodings.
+//!
+//! Available with the `serde` feature. Instead of estimating sizes field by field
+//! like [`impl_virtual_size!`], messages are encoded with bincode and their
+//! [`Message::virtual_size`] is the length of the encoding. The size is computed
+//! once per sent message, no matter how many processes receive it.
+//!
+//! [`Message::virtual_size`]: crate::Message::virtual_size
+//! [`impl_virtual_size!`]: crate::impl_virtual_size
+
+use serde::Serialize;
+
+/// Returns the length of the bincode encoding of `value` in bytes.
+///
+/// # Examples
+///
+/// ```rust
+/// use dscale::helpers::encoded_size;
+///
+/// assert_eq!(encoded_size(&42u64), 8);
+/// assert_eq!(encoded_size(&vec![1u32, 2, 3]), 8 + 3 * 4);
+/// ```
+///
+/// # Panics
+///
+/// Panics if `value` can not be encoded, for example if its `Serialize`
+/// implementation fails.
+pub fn encoded_size<T: Serialize + ?Sized>(value: &T) -> usize {
+    bincode::serialized_size(value).expect("Message can not be encoded") as usize
+}
+
+/// Implements [`Message`] for serializable types, sizing them by their encoding.
+///
+/// Requires the type to implement [`Serialize`], so messages can not silently
+/// fall back to guessed sizes.
+///
+/// # Examples
+///
+/// ```rust
+/// use dscale::{Message, encoded_message};
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct Vote {
+///     round: u64,
+///     signature: Vec<u8>,
+/// }
+///
+/// encoded_message!(Vote);
+///
+/// let vote = Vote { round: 1, signature: vec![0; 64] };
+/// assert_eq!(vote.virtual_size(), 8 + 8 + 64);
+/// ```
+///
+/// [`Message`]: crate::Message
+/// [`Serialize`]: serde::Serialize
+#[macro_export]
+macro_rules! encoded_message {
+    ($($type:ty),+ $(,)?) => {
+        $(impl $crate::Message for $type {
+            fn virtual_size(&self) -> usize {
+                $crate::helpers::encoded_size(self)
+            }
+        })+
+    };
+}
@@ -0,0 +1,103 @@
+use std::collections::{BTreeSet, HashMap};
+
+use crate::{
+    Jiffies, MessagePtr, ProcessId, TimerId,
+    helpers::failure_detector::{
+        Detection, FailureDetector, Probe, Tick, members_of, restore, suspect,
+    },
+    now, schedule_named_timer, send_to, timer_kind,
+};
+
+/// Heartbeat detector with timeouts growing on every mistake.
+///
+/// Every process sends a heartbeat to each member every `period` jiffies and
+/// suspects members it has not heard from for longer than their timeout, which
+/// starts at `timeout`. A heartbeat from a suspected member restores it and
+/// increases its timeout by `timeout`, so once timeouts exceed the actual delays
+/// nobody alive is suspected any more (eventual strong accuracy).
+///
+/// # Panics
+///
+/// [`EventuallyPerfectDetector::new`] panics if `period` or `timeout` is zero.
+pub struct EventuallyPerfectDetector {
+    pool: &'static str,
+    period: Jiffies,
+    initial_timeout: Jiffies,
+    members: Vec<ProcessId>,
+    last_heard: HashMap<ProcessId, Jiffies>,
+    timeouts: HashMap<ProcessId, Jiffies>,
+    suspected: BTreeSet<ProcessId>,
+}
+
+impl EventuallyPerfectDetector {
+    /// Creates a detector monitoring `pool` with heartbeats every `period` jiffies.
+    pub fn new(pool: &'static str, period: Jiffies, timeout: Jiffies) -> Self {
+        assert!(period.0 > 0, "Heartbeat period should be positive");
+        assert!(timeout.0 > 0, "Timeout should be positive");
+        Self {
+            pool,
+            period,
+            initial_timeout: timeout,
+            members: Vec::new(),
+            last_heard: HashMap::new(),
+            timeouts: HashMap::new(),
+            suspected: BTreeSet::new(),
+        }
+    }
+
+    /// Current timeout of a member.
+    pub fn timeout_of(&self, id: ProcessId) -> Jiffies {
+        self.timeouts
+            .get(&id)
+            .copied()
+            .unwrap_or(self.initial_timeout)
+    }
+}
+
+impl FailureDetector for EventuallyPerfectDetector {
+    fn start(&mut self) {
+        self.members = members_of(self.pool);
+        self.members.iter().for_each(|member| {
+            self.last_heard.insert(*member, now());
+        });
+        schedule_named_timer(self.period, Tick::Period);
+    }
+
+    fn on_message(&mut self, from: ProcessId, message: &MessagePtr) -> Option<Vec<Detection>> {
+        if !message.is::<Probe>() {
+            return None;
+        }
+        let mut detections = Vec::new();
+        self.last_heard.insert(from, now());
+        if self.suspected.contains(&from) {
+            let timeout = self.timeout_of(from) + self.initial_timeout;
+            self.timeouts.insert(from, timeout);
+            restore(&mut self.suspected, from, &mut detections);
+        }
+        Some(detections)
+    }
+
+    fn on_timer(&mut self, id: TimerId) -> Option<Vec<Detection>> {
+        timer_kind::<Tick>(id)?;
+        let present = now();
+        self.members
+            .iter()
+            .for_each(|member| send_to(*member, Probe::Heartbeat));
+        let silent: Vec<ProcessId> = self
+            .members
+            .iter()
+            .copied()
+            .filter(|member| present - self.last_heard[member] > self.timeout_of(*member))
+            .collect();
+        let mut detections = Vec::new();
+        silent
+            .into_iter()
+            .for_each(|member| suspect(&mut self.suspected, member, &mut detections));
+        schedule_named_timer(self.period, Tick::Period);
+        Some(detections)
+    }
+
+    fn suspected(&self) -> &BTreeSet<ProcessId> {
+        &self.suspected
+    }
+}
@@ -0,0 +1,170 @@
+//! Failure detection behind a common interface.
+//!
+//! This module provides the [`FailureDetector`] trait and three implementations
+//! with different guarantees, so a protocol (e.g. a view-change pacemaker) is
+//! written once and evaluated with each of them:
+//!
+//! - [`PerfectDetector`]: suspects exactly the crashed processes, using the
+//!   ground truth of the simulation. A baseline no real detector achieves.
+//! - [`EventuallyPerfectDetector`]: heartbeats with growing timeouts. May suspect
+//!   slow processes, but stops doing so after enough mistakes.
+//! - [`SwimDetector`]: SWIM-style probing of one member per period with indirect
+//!   probes through other members, which keeps the load constant per process.
+//!
+//! A detector lives inside the process and shares its messages and timers: the
+//! process offers every received message and fired timer to the detector first
+//! and handles them itself only if the detector returns `None`.
+
+mod eventually_perfect;
+mod perfect;
+mod swim;
+
+pub use eventually_perfect::EventuallyPerfectDetector;
+pub use perfect::PerfectDetector;
+pub use swim::SwimDetector;
+
+use std::collections::BTreeSet;
+
+use crate::{Message, MessagePtr, ProcessId, TimerId, list_pool, rank};
+
+/// A change of the opinion of a detector about a process.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Detection {
+    /// The process is now suspected to have crashed.
+    Suspect(ProcessId),
+    /// The process was suspected, but turned out to be alive.
+    Restore(ProcessId),
+}
+
+/// Monitors members of a pool and reports suspected crashes.
+///
+/// Detections are returned from [`FailureDetector::on_message`] and
+/// [`FailureDetector::on_timer`] as they happen, the current opinion is
+/// available from [`FailureDetector::suspected`].
+///
+/// # Examples
+///
+/// ```rust
+/// use dscale::{ProcessHandle, ProcessId, MessagePtr, TimerId, Jiffies};
+/// use dscale::helpers::failure_detector::{Detection, EventuallyPerfectDetector, FailureDetector};
+///
+/// struct Pacemaker {
+///     detector: Box<dyn FailureDetector>,
+///     leader: ProcessId,
+/// }
+///
+/// impl Default for Pacemaker {
+///     fn default() -> Self {
+///         Self {
+///             detector: Box::new(EventuallyPerfectDetector::new("replicas", Jiffies(50), Jiffies(200))),
+///             leader: 1,
+///         }
+///     }
+/// }
+///
+/// impl Pacemaker {
+///     fn on_detections(&mut self, detections: Vec<Detection>) {
+///         for detection in detections {
+///             if detection == Detection::Suspect(self.leader) {
+///                 self.leader += 1; // View change
+///             }
+///         }
+///     }
+/// }
+///
+/// impl ProcessHandle for Pacemaker {
+///     fn start(&mut self) {
+///         self.detector.start();
+///     }
+///
+///     fn on_message(&mut self, from: ProcessId, message: MessagePtr) {
+///         if let Some(detections) = self.detector.on_message(from, &message) {
+///             return self.on_detections(detections);
+///         }
+///         // Protocol messages
+///     }
+///
+///     fn on_timer(&mut self, id: TimerId) {
+///         if let Some(detections) = self.detector.on_timer(id) {
+///             return self.on_detections(detections);
+///         }
+///         // Protocol timers
+///     }
+/// }
+/// ```
+pub trait FailureDetector {
+    /// Starts monitoring. Called from [`ProcessHandle::start`].
+    ///
+    /// [`ProcessHandle::start`]: crate::ProcessHandle::start
+    fn start(&mut self);
+
+    /// Handles a received message if it belongs to the detector.
+    ///
+    /// Returns `None` for messages of the protocol.
+    fn on_message(&mut self, from: ProcessId, message: &MessagePtr) -> Option<Vec<Detection>>;
+
+    /// Handles a fired timer if it belongs to the detector.
+    ///
+    /// Returns `None` for timers of the protocol.
+    fn on_timer(&mut self, id: TimerId) -> Option<Vec<Detection>>;
+
+    /// Processes currently suspected.
+    fn suspected(&self) -> &BTreeSet<ProcessId>;
+
+    /// Whether the process is currently suspected.
+    fn is_suspected(&self, id: ProcessId) -> bool {
+        self.suspected().contains(&id)
+    }
+}
+
+// Messages exchanged by detectors
+#[derive(Clone, Copy)]
+enum Probe {
+    Heartbeat,
+    Ping {
+        seq: usize,
+        on_behalf: Option<ProcessId>,
+    },
+    PingRequest {
+        seq: usize,
+        target: ProcessId,
+    },
+    Ack {
+        seq: usize,
+        on_behalf: Option<ProcessId>,
+    },
+}
+
+impl Message for Probe {
+    fn virtual_size(&self) -> usize {
+        16
+    }
+}
+
+// Timers scheduled by detectors, recognized with timer_kind
+#[derive(Clone, Copy)]
+enum Tick {
+    Period,
+    AckTimeout(usize),
+}
+
+// Members of the pool except the current process
+fn members_of(pool: &str) -> Vec<ProcessId> {
+    let me = rank();
+    list_pool(pool)
+        .into_iter()
+        .filter(|member| *member != me)
+        .collect()
+}
+
+fn suspect(suspected: &mut BTreeSet<ProcessId>, id: ProcessId, detections: &mut Vec<Detection>) {
+    if suspected.insert(id) {
+        detections.push(Detection::Suspect(id));
+    }
+}
+
+fn restore(suspected: &mut BTreeSet<ProcessId>, id: ProcessId, detections: &mut Vec<Detection>) {
+    if suspected.remove(&id) {
+        detections.push(Detection::Restore(id));
+    }
+}
@@ -0,0 +1,66 @@
+use std::collections::BTreeSet;
+
+use crate::{
+    Jiffies, MessagePtr, ProcessId, TimerId, global,
+    helpers::failure_detector::{Detection, FailureDetector, Tick, members_of, restore, suspect},
+    schedule_named_timer, timer_kind,
+};
+
+/// Suspects exactly the crashed members of a pool.
+///
+/// Reads crashes and restarts from the simulation itself every `period`
+/// jiffies, so it never suspects a live process and detects every crash within
+/// one period. It sends no messages and is unaffected by partitions: it is the
+/// reference the other detectors are compared to.
+///
+/// # Panics
+///
+/// [`FailureDetector::start`] panics outside of simulation.
+pub struct PerfectDetector {
+    pool: &'static str,
+    period: Jiffies,
+    members: Vec<ProcessId>,
+    suspected: BTreeSet<ProcessId>,
+}
+
+impl PerfectDetector {
+    /// Creates a detector monitoring `pool`, checking for crashes every `period` jiffies.
+    pub fn new(pool: &'static str, period: Jiffies) -> Self {
+        assert!(period.0 > 0, "Detection period should be positive");
+        Self {
+            pool,
+            period,
+            members: Vec::new(),
+            suspected: BTreeSet::new(),
+        }
+    }
+}
+
+impl FailureDetector for PerfectDetector {
+    fn start(&mut self) {
+        self.members = members_of(self.pool);
+        schedule_named_timer(self.period, Tick::Period);
+    }
+
+    fn on_message(&mut self, _from: ProcessId, _message: &MessagePtr) -> Option<Vec<Detection>> {
+        None
+    }
+
+    fn on_timer(&mut self, id: TimerId) -> Option<Vec<Detection>> {
+        timer_kind::<Tick>(id)?;
+        let mut detections = Vec::new();
+        self.members.iter().for_each(|member| {
+            if global::is_crashed(*member) {
+                suspect(&mut self.suspected, *member, &mut detections);
+            } else {
+                restore(&mut self.suspected, *member, &mut detections);
+            }
+        });
+        schedule_named_timer(self.period, Tick::Period);
+        Some(detections)
+    }
+
+    fn suspected(&self) -> &BTreeSet<ProcessId> {
+        &self.suspected
+    }
+}
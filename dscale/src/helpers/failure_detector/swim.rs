@@ -0,0 +1,169 @@
+use std::collections::BTreeSet;
+
+use crate::{
+    Jiffies, MessagePtr, ProcessId, TimerId,
+    global::configuration,
+    helpers::failure_detector::{
+        Detection, FailureDetector, Probe, Tick, members_of, restore, suspect,
+    },
+    random::Randomizer,
+    schedule_named_timer, send_to, timer_kind,
+};
+
+/// SWIM-style detector: one probe per period, indirect probes on silence.
+///
+/// Every `period` jiffies the process pings the next member in round-robin
+/// order. Without an ack within `ack_timeout` it asks `indirect` random other
+/// members to ping the target on its behalf and relay the ack. A target which
+/// acked neither directly nor indirectly by the end of the period is suspected,
+/// and restored once it acks a later probe. Unlike heartbeats, the number of
+/// messages per period does not grow with the size of the pool.
+///
+/// Suspicions are local: the dissemination component of SWIM is not modeled.
+///
+/// # Panics
+///
+/// [`SwimDetector::new`] panics unless `0 < ack_timeout < period`.
+pub struct SwimDetector {
+    pool: &'static str,
+    period: Jiffies,
+    ack_timeout: Jiffies,
+    indirect: usize,
+    members: Vec<ProcessId>,
+    next_target: usize,
+    probe: Option<(usize, ProcessId, bool)>, // seq, target, acked
+    seq: usize,
+    randomizer: Option<Randomizer>,
+    suspected: BTreeSet<ProcessId>,
+}
+
+impl SwimDetector {
+    /// Creates a detector monitoring `pool`, probing one member every `period`
+    /// jiffies and asking `indirect` members for help after `ack_timeout` jiffies.
+    pub fn new(pool: &'static str, period: Jiffies, ack_timeout: Jiffies, indirect: usize) -> Self {
+        assert!(
+            ack_timeout.0 > 0 && ack_timeout < period,
+            "Ack timeout should be positive and shorter than the period"
+        );
+        Self {
+            pool,
+            period,
+            ack_timeout,
+            indirect,
+            members: Vec::new(),
+            next_target: 0,
+            probe: None,
+            seq: 0,
+            randomizer: None,
+            suspected: BTreeSet::new(),
+        }
+    }
+
+    fn start_period(&mut self, detections: &mut Vec<Detection>) {
+        if let Some((_, target, false)) = self.probe {
+            suspect(&mut self.suspected, target, detections);
+        }
+        schedule_named_timer(self.period, Tick::Period);
+        if self.members.is_empty() {
+            return;
+        }
+
+        let target = self.members[self.next_target % self.members.len()];
+        self.next_target += 1;
+        self.seq += 1;
+        self.probe = Some((self.seq, target, false));
+        send_to(
+            target,
+            Probe::Ping {
+                seq: self.seq,
+                on_behalf: None,
+            },
+        );
+        schedule_named_timer(self.ack_timeout, Tick::AckTimeout(self.seq));
+    }
+
+    fn ask_for_help(&mut self, seq: usize) {
+        let Some((current, target, false)) = self.probe else {
+            return;
+        };
+        if current != seq {
+            return;
+        }
+        let helpers: Vec<ProcessId> = self
+            .members
+            .iter()
+            .copied()
+            .filter(|member| *member != target)
+            .collect();
+        let amount = self.indirect.min(helpers.len());
+        self.randomizer
+            .as_mut()
+            .expect("Detector is not started")
+            .choose_multiple_from_slice(&helpers, amount)
+            .into_iter()
+            .for_each(|helper| send_to(helper, Probe::PingRequest { seq, target }));
+    }
+}
+
+impl FailureDetector for SwimDetector {
+    fn start(&mut self) {
+        self.members = members_of(self.pool);
+        self.randomizer = Some(Randomizer::new(configuration::seed()));
+        // Spread the first targets of different processes
+        self.next_target = configuration::seed() as usize % self.members.len().max(1);
+        schedule_named_timer(self.period, Tick::Period);
+    }
+
+    fn on_message(&mut self, from: ProcessId, message: &MessagePtr) -> Option<Vec<Detection>> {
+        if !message.is::<Probe>() {
+            return None;
+        }
+        let mut detections = Vec::new();
+        match *MessagePtr(message.0.clone()).as_type::<Probe>() {
+            Probe::Ping { seq, on_behalf } => send_to(from, Probe::Ack { seq, on_behalf }),
+            Probe::PingRequest { seq, target } => send_to(
+                target,
+                Probe::Ping {
+                    seq,
+                    on_behalf: Some(from),
+                },
+            ),
+            Probe::Ack {
+                seq,
+                on_behalf: Some(origin),
+            } => send_to(
+                origin,
+                Probe::Ack {
+                    seq,
+                    on_behalf: None,
+                },
+            ),
+            Probe::Ack {
+                seq,
+                on_behalf: None,
+            } => {
+                if let Some((current, target, acked)) = &mut self.probe
+                    && *current == seq
+                {
+                    *acked = true;
+                    restore(&mut self.suspected, *target, &mut detections);
+                }
+            }
+            Probe::Heartbeat => {}
+        }
+        Some(detections)
+    }
+
+    fn on_timer(&mut self, id: TimerId) -> Option<Vec<Detection>> {
+        let mut detections = Vec::new();
+        match timer_kind::<Tick>(id)? {
+            Tick::Period => self.start_period(&mut detections),
+            Tick::AckTimeout(seq) => self.ask_for_help(seq),
+        }
+        Some(detections)
+    }
+
+    fn suspected(&self) -> &BTreeSet<ProcessId> {
+        &self.suspected
+    }
+}
@@ -0,0 +1,141 @@
+//! Fuzzing of message handlers.
+//!
+//! Available with the `fuzz` feature. Full simulations only produce messages
+//! honest processes would send, so handlers rarely see a vote for a round far
+//! ahead, a certificate for a message never initiated or a timestamp at the end
+//! of its range. [`HandlerFuzzer`] decodes such messages from raw fuzzer input
+//! with [`arbitrary`] and hands them straight to the handlers of a built
+//! simulation, so panics in handlers (unwraps on missing quorum entries,
+//! overflows in round arithmetic) show up without running the protocol.
+
+use arbitrary::{Arbitrary, Unstructured};
+
+use crate::{
+    ProcessId, Simulation, SimulationBuilder, global::configuration, message::SharedMessage,
+};
+
+/// Feeds messages decoded from fuzzer input to process handlers.
+///
+/// Input is consumed as a sequence of steps. A step either executes the next
+/// pending event of the simulation, so replies and timers of handled messages
+/// take effect, or delivers a message decoded from an `I` to a process chosen by
+/// the input, as if sent by another chosen process. Delivered messages skip the
+/// network: no latency, bandwidth, filters or partitions apply, crashed
+/// processes do not get them.
+///
+/// Decoding is up to the caller, since protocol messages usually hold shared
+/// pointers and invariants (e.g. edges of DAG vertices) [`Arbitrary`] can not
+/// derive. Decoders may keep state between messages of one input, such as
+/// previously decoded vertices to point edges at.
+///
+/// Meant to be called from a `cargo fuzz` target with a fresh fuzzer per input:
+///
+/// ```rust,ignore
+/// fuzz_target!(|data: &[u8]| {
+///     HandlerFuzzer::new(builder()).fuzz(data, decode);
+/// });
+/// ```
+///
+/// # Examples
+///
+/// ```rust
+/// use arbitrary::Arbitrary;
+/// use dscale::{Message, MessagePtr, ProcessHandle, ProcessId, SimulationBuilder, TimerId};
+/// use dscale::{helpers::HandlerFuzzer, message::Shared};
+///
+/// #[derive(Arbitrary, Debug)]
+/// struct Vote {
+///     round: u8,
+/// }
+///
+/// impl Message for Vote {}
+///
+/// #[derive(Default)]
+/// struct Voter {
+///     round: u8,
+/// }
+///
+/// impl ProcessHandle for Voter {
+///     fn start(&mut self) {}
+///     fn on_message(&mut self, _from: ProcessId, message: MessagePtr) {
+///         // Panics on a round overflow
+///         self.round = self.round.max(message.as_type::<Vote>().round + 1);
+///     }
+///     fn on_timer(&mut self, _id: TimerId) {}
+/// }
+///
+/// let fuzz = |data: &[u8]| {
+///     let builder = SimulationBuilder::default().add_pool::<Voter>("voters", 3);
+///     HandlerFuzzer::new(builder).fuzz(data, |_from, vote: Vote| Some(Shared::new(vote)))
+/// };
+///
+/// assert_eq!(fuzz(&[1, 1, 1, 7]), 1);
+/// assert!(std::panic::catch_unwind(|| fuzz(&[1, 1, 1, 255])).is_err());
+/// ```
+pub struct HandlerFuzzer {
+    simulation: Simulation,
+}
+
+impl HandlerFuzzer {
+    /// Builds the simulation and starts its processes.
+    pub fn new(builder: SimulationBuilder) -> Self {
+        let mut simulation = builder.build();
+        simulation.step_n(0);
+        Self { simulation }
+    }
+
+    /// Delivers `message` from `from` to the handler of `to`.
+    ///
+    /// Useful to turn a crashing input into a plain regression test.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is no process `to`.
+    pub fn deliver(&mut self, from: ProcessId, to: ProcessId, message: SharedMessage) {
+        self.simulation.deliver(from, to, message);
+    }
+
+    /// Executes steps decoded from `data` until it runs out, returns the number
+    /// of delivered messages.
+    ///
+    /// `decode` turns an `I` claimed to be sent by the given process into a
+    /// message, or `None` to skip it.
+    pub fn fuzz<I: for<'a> Arbitrary<'a>>(
+        &mut self,
+        data: &[u8],
+        mut decode: impl FnMut(ProcessId, I) -> Option<SharedMessage>,
+    ) -> usize {
+        let processes = configuration::process_number();
+        let mut input = Unstructured::new(data);
+        let mut delivered = 0;
+        while !input.is_empty() {
+            let Ok(step) = self.step(&mut input, processes, &mut decode) else {
+                break;
+            };
+            delivered += usize::from(step);
+        }
+        delivered
+    }
+
+    // Returns whether a message was delivered
+    fn step<I: for<'a> Arbitrary<'a>>(
+        &mut self,
+        input: &mut Unstructured,
+        processes: usize,
+        decode: &mut impl FnMut(ProcessId, I) -> Option<SharedMessage>,
+    ) -> arbitrary::Result<bool> {
+        if input.ratio(1, 4)? {
+            self.simulation.step_n(1);
+            return Ok(false);
+        }
+        let from = input.int_in_range(1..=processes)?;
+        let to = input.int_in_range(1..=processes)?;
+        match decode(from, I::arbitrary(input)?) {
+            Some(message) => {
+                self.deliver(from, to, message);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+}
@@ -0,0 +1,143 @@
+//! Golden file regression checks.
+//!
+//! This module provides the `Golden` struct for recording a compact summary of
+//! a simulation run into a file and asserting that future runs still match it.
+//! It guards refactors of the engine (network, bandwidth queues, timers) against
+//! silently changing simulation semantics.
+
+use std::{collections::BTreeMap, fmt::Display, fs, path::Path};
+
+use crate::{Simulation, global};
+
+/// Environment variable which forces golden files to be rewritten.
+pub const UPDATE_GOLDEN_ENV: &str = "DSCALE_UPDATE_GOLDEN";
+
+/// A snapshot of a finished simulation run compared against a golden file.
+///
+/// The snapshot always contains the final simulation time, the number of executed
+/// process steps and the trace hash (see [`RunDigest`]). Any additional values
+/// (usually final metrics read from [`anykv`]) can be attached with [`Golden::metric`].
+///
+/// # Golden File Lifecycle
+///
+/// - If the golden file does not exist, [`Golden::assert_matches`] records it
+/// - If it exists, every recorded value must match the current run exactly
+/// - Intended changes of semantics are accepted by rerunning with
+///   `DSCALE_UPDATE_GOLDEN=1`, which rewrites the file
+///
+/// # File Format
+///
+/// Plain `key = value` lines sorted by key, so changes are easy to review in diffs.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use dscale::{SimulationBuilder, Jiffies, global::anykv, helpers::Golden};
+///
+/// let mut simulation = SimulationBuilder::default()
+///     .add_pool::<MyProcess>("workers", 3)
+///     .time_budget(Jiffies(10_000))
+///     .seed(42)
+///     .build();
+///
+/// anykv::set::<usize>("delivered", 0);
+/// simulation.run();
+///
+/// Golden::new(&simulation)
+///     .metric("delivered", anykv::get::<usize>("delivered"))
+///     .assert_matches("golden/workers.golden");
+/// # #[derive(Default)]
+/// # struct MyProcess;
+/// # impl dscale::ProcessHandle for MyProcess {
+/// #     fn start(&mut self) {}
+/// #     fn on_message(&mut self, from: dscale::ProcessId, message: dscale::MessagePtr) {}
+/// #     fn on_timer(&mut self, id: dscale::TimerId) {}
+/// # }
+/// ```
+///
+/// # Panics
+///
+/// [`Golden::assert_matches`] panics listing every mismatched value if the run
+/// diverged from the golden file, or if the file cannot be read or written.
+///
+/// [`RunDigest`]: crate::RunDigest
+/// [`anykv`]: crate::global::anykv
+pub struct Golden {
+    values: BTreeMap<String, String>,
+}
+
+impl Golden {
+    /// Captures digest of the simulation. Should be called after [`Simulation::run`].
+    ///
+    /// [`Simulation::run`]: crate::Simulation::run
+    pub fn new(simulation: &Simulation) -> Self {
+        let digest = simulation.digest();
+        Self {
+            values: BTreeMap::new(),
+        }
+        .metric("final_time", global::now())
+        .metric("events", digest.events)
+        .metric("trace_hash", format!("{:016x}", digest.trace_hash))
+    }
+
+    /// Attaches named value to the snapshot. Values are compared by their
+    /// `Display` representation.
+    pub fn metric(mut self, name: &str, value: impl Display) -> Self {
+        self.values.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    /// Records the snapshot into `path` if it does not exist yet (or
+    /// `DSCALE_UPDATE_GOLDEN` is set), otherwise asserts that it matches.
+    pub fn assert_matches(&self, path: impl AsRef<Path>) {
+        let path = path.as_ref();
+
+        if !path.exists() || std::env::var_os(UPDATE_GOLDEN_ENV).is_some() {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).expect("Unable to create golden directory");
+            }
+            fs::write(path, self.render()).expect("Unable to write golden file");
+            return;
+        }
+
+        let golden = fs::read_to_string(path).expect("Unable to read golden file");
+        let expected: BTreeMap<&str, &str> = golden
+            .lines()
+            .filter_map(|line| line.split_once(" = "))
+            .collect();
+
+        let mut keys: Vec<&str> = expected.keys().copied().collect();
+        keys.extend(self.values.keys().map(String::as_str));
+        keys.sort();
+        keys.dedup();
+
+        let mismatches: Vec<String> = keys
+            .into_iter()
+            .filter_map(|key| {
+                let actual = self.values.get(key).map(String::as_str);
+                let golden = expected.get(key).copied();
+                (actual != golden).then(|| {
+                    format!(
+                        "  {key}: golden {}, actual {}",
+                        golden.unwrap_or("<missing>"),
+                        actual.unwrap_or("<missing>")
+                    )
+                })
+            })
+            .collect();
+
+        assert!(
+            mismatches.is_empty(),
+            "Run diverged from golden file {}:\n{}\nRerun with {UPDATE_GOLDEN_ENV}=1 if the change is intended",
+            path.display(),
+            mismatches.join("\n")
+        );
+    }
+
+    fn render(&self) -> String {
+        self.values
+            .iter()
+            .map(|(key, value)| format!("{key} = {value}\n"))
+            .collect()
+    }
+}
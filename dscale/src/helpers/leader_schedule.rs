@@ -0,0 +1,108 @@
+//! Leader placement schedules.
+//!
+//! This module provides the `LeaderSchedule` struct which tells leader-based
+//! protocols which process leads at a given time. It is configured once with
+//! [`SimulationBuilder::leader_schedule`], so experiments like "leader in a far
+//! region" or "leadership moves away after 50k jiffies" need no protocol edits.
+//!
+//! [`SimulationBuilder::leader_schedule`]: crate::SimulationBuilder::leader_schedule
+
+use crate::{Jiffies, ProcessId, global::anykv};
+
+pub(crate) const LEADER_SCHEDULE_KEY: &str = "leader_schedule";
+
+/// Who leads during one period of a [`LeaderSchedule`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Leadership {
+    /// A single process leads every slot.
+    Pinned(ProcessId),
+    /// Leadership rotates among the processes slot by slot.
+    Rotating(Vec<ProcessId>),
+    /// Leadership rotates among processes of the region (see
+    /// [`SimulationBuilder::add_pool_in_region`]), resolved when the simulation is built.
+    ///
+    /// [`SimulationBuilder::add_pool_in_region`]: crate::SimulationBuilder::add_pool_in_region
+    RotatingInRegion(&'static str),
+}
+
+impl From<ProcessId> for Leadership {
+    fn from(id: ProcessId) -> Self {
+        Leadership::Pinned(id)
+    }
+}
+
+/// Leadership of processes over simulated time.
+///
+/// A schedule is a list of periods, each starting at some time and assigning
+/// [`Leadership`]. Protocols ask for the leader of a slot (round, view, term)
+/// with [`LeaderSchedule::leader`], providing the time the slot belongs to.
+///
+/// Protocols which need all processes to agree on the leader of a slot should
+/// derive that time from the slot itself rather than from [`now`], which differs
+/// between processes.
+///
+/// # Examples
+///
+/// ```rust
+/// use dscale::{SimulationBuilder, Jiffies, helpers::{LeaderSchedule, Leadership}};
+///
+/// let mut simulation = SimulationBuilder::default()
+///     .add_pool::<MyProcess>("validators", 8)
+///     .leader_schedule(&[
+///         (Jiffies(0), Leadership::Pinned(1)),
+///         (Jiffies(50_000), Leadership::Rotating(vec![6, 7, 8])),
+///     ])
+///     .build();
+///
+/// let schedule = LeaderSchedule::configured().unwrap();
+/// assert_eq!(schedule.leader(Jiffies(100), 3), 1);
+/// assert_eq!(schedule.leader(Jiffies(60_000), 3), 6);
+/// assert_eq!(schedule.leader(Jiffies(60_000), 4), 7);
+/// # #[derive(Default)]
+/// # struct MyProcess;
+/// # impl dscale::ProcessHandle for MyProcess {
+/// #     fn start(&mut self) {}
+/// #     fn on_message(&mut self, from: dscale::ProcessId, message: dscale::MessagePtr) {}
+/// #     fn on_timer(&mut self, id: dscale::TimerId) {}
+/// # }
+/// ```
+///
+/// [`now`]: crate::now
+#[derive(Clone, Debug)]
+pub struct LeaderSchedule {
+    periods: Vec<(Jiffies, Vec<ProcessId>)>, // Sorted by start, resolved to rotation lists
+}
+
+impl LeaderSchedule {
+    pub(crate) fn new(periods: Vec<(Jiffies, Vec<ProcessId>)>) -> Self {
+        assert!(!periods.is_empty(), "Leader schedule should not be empty");
+        assert!(
+            periods.iter().all(|(_, leaders)| !leaders.is_empty()),
+            "Every period of leader schedule should have leaders"
+        );
+        assert!(
+            periods.is_sorted_by_key(|(start, _)| *start),
+            "Leader schedule periods should be sorted by time"
+        );
+        Self { periods }
+    }
+
+    /// Returns the schedule configured for the current simulation, if any.
+    pub fn configured() -> Option<Self> {
+        anykv::try_get::<LeaderSchedule>(LEADER_SCHEDULE_KEY)
+    }
+
+    /// Leader of the `slot` which belongs to time `at`.
+    ///
+    /// Before the first period starts, the first period is in effect.
+    pub fn leader(&self, at: Jiffies, slot: usize) -> ProcessId {
+        let period = self.periods.partition_point(|(start, _)| *start <= at);
+        let leaders = &self.periods[period.saturating_sub(1)].1;
+        leaders[slot % leaders.len()]
+    }
+
+    /// Whether leadership ever changes hands by time.
+    pub fn is_time_dependent(&self) -> bool {
+        self.periods.len() > 1
+    }
+}
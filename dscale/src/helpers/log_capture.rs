@@ -0,0 +1,158 @@
+//! Per-process capture of process logs.
+//!
+//! With [`SimulationBuilder::capture_logs`] every process keeps its last log
+//! lines written with [`debug_process!`], [`warn_process!`] and
+//! [`error_process!`] in a ring buffer. When a process step panics (including
+//! failed [`sim_assert!`]), the lines of the panicking process are printed after
+//! the panic message, so its own recent history is not lost in the interleaved
+//! global log. Capture does not depend on `RUST_LOG`.
+//!
+//! [`SimulationBuilder::capture_logs`]: crate::SimulationBuilder::capture_logs
+//! [`debug_process!`]: crate::debug_process
+//! [`warn_process!`]: crate::warn_process
+//! [`error_process!`]: crate::error_process
+//! [`sim_assert!`]: crate::sim_assert
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::{HashMap, VecDeque},
+    fmt::Arguments,
+    sync::Once,
+};
+
+use log::Level;
+
+use crate::{Jiffies, ProcessId, global, now};
+
+/// A captured log line of a process.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LogLine {
+    pub at: Jiffies,
+    pub level: Level,
+    pub message: String,
+}
+
+struct Capture {
+    lines: usize,
+    level: Level,
+    buffers: HashMap<ProcessId, VecDeque<LogLine>>,
+}
+
+thread_local! {
+    static ENABLED: Cell<bool> = const { Cell::new(false) };
+    static CAPTURE: RefCell<Option<Capture>> = const { RefCell::new(None) };
+}
+
+static PANIC_HOOK: Once = Once::new();
+
+pub(crate) fn setup_capture(lines: usize, level: Level) {
+    CAPTURE.set(Some(Capture {
+        lines,
+        level,
+        buffers: HashMap::new(),
+    }));
+    ENABLED.set(true);
+    PANIC_HOOK.call_once(|| {
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            previous(info);
+            report_panicking_process();
+        }));
+    });
+}
+
+pub(crate) fn drop_capture() {
+    ENABLED.set(false);
+    CAPTURE.take();
+}
+
+/// Records a line of the current process. Used by [`debug_process!`] and friends.
+///
+/// [`debug_process!`]: crate::debug_process
+#[doc(hidden)]
+pub fn capture(level: Level, message: Arguments) {
+    if !ENABLED.get() {
+        return;
+    }
+    let Some(id) = global::try_rank() else {
+        return;
+    };
+    let at = now();
+    CAPTURE.with_borrow_mut(|capture| {
+        let Some(capture) = capture.as_mut().filter(|capture| level <= capture.level) else {
+            return;
+        };
+        let buffer = capture.buffers.entry(id).or_default();
+        if buffer.len() == capture.lines {
+            buffer.pop_front();
+        }
+        buffer.push_back(LogLine {
+            at,
+            level,
+            message: message.to_string(),
+        });
+    });
+}
+
+/// Returns captured log lines of process `id`, oldest first.
+///
+/// Empty if capture is disabled or the process logged nothing.
+///
+/// # Examples
+///
+/// ```rust
+/// use dscale::{SimulationBuilder, Jiffies, helpers::log_capture};
+/// # use dscale::{ProcessHandle, ProcessId, MessagePtr, TimerId, debug_process, now, rank};
+/// # #[derive(Default)]
+/// # struct Greeter;
+/// # impl ProcessHandle for Greeter {
+/// #     fn start(&mut self) { debug_process!("Hello"); }
+/// #     fn on_message(&mut self, from: ProcessId, message: MessagePtr) {}
+/// #     fn on_timer(&mut self, id: TimerId) {}
+/// # }
+///
+/// let mut simulation = SimulationBuilder::default()
+///     .add_pool::<Greeter>("greeters", 2)
+///     .capture_logs(8, log::Level::Debug)
+///     .build();
+///
+/// simulation.step_until(Jiffies(10));
+/// assert_eq!(log_capture::recent_logs(2)[0].message, "Hello");
+/// ```
+pub fn recent_logs(id: ProcessId) -> Vec<LogLine> {
+    CAPTURE.with_borrow(|capture| {
+        capture
+            .as_ref()
+            .and_then(|capture| capture.buffers.get(&id))
+            .map(|buffer| buffer.iter().cloned().collect())
+            .unwrap_or_default()
+    })
+}
+
+fn report_panicking_process() {
+    if !ENABLED.try_with(Cell::get).unwrap_or(false) {
+        return;
+    }
+    let Some(id) = global::try_rank() else {
+        return;
+    };
+    // The panic may come from within the capture itself
+    let Some(lines) = CAPTURE
+        .try_with(|capture| {
+            let capture = capture.try_borrow().ok()?;
+            capture.as_ref()?.buffers.get(&id).cloned()
+        })
+        .ok()
+        .flatten()
+    else {
+        return;
+    };
+    let mut report = format!("Last log lines of P{id} (oldest first):\n");
+    lines.iter().for_each(|line| {
+        report.push_str(&format!(
+            "  {} {:<5} {}\n",
+            line.at, line.level, line.message
+        ));
+    });
+    eprint!("{report}");
+}
@@ -0,0 +1,352 @@
+//! Minimization of failing runs.
+//!
+//! An invariant violated under some seed of a sweep usually fails in a large
+//! configuration: many processes, a long run, a busy fault schedule. This module
+//! provides the `Minimizer` struct, which searches nearby configurations (fewer
+//! processes, a shorter time budget, fewer scenario events, neighbouring seeds)
+//! that still fail, and returns the smallest one found as a repro case.
+
+use std::{
+    fmt,
+    panic::{self, AssertUnwindSafe},
+};
+
+use crate::{Jiffies, ProcessId, Scenario, scenario::ScenarioEvent};
+
+/// Configuration of a run explored by the [`Minimizer`].
+///
+/// What `processes` means is up to the check building the simulation, usually
+/// the size of the pool under test. Scenario events refer to process ids, so
+/// events involving processes beyond `processes` are dropped when it shrinks.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Repro {
+    /// Seed of the simulation.
+    pub seed: u64,
+    /// Number of processes.
+    pub processes: usize,
+    /// Time budget of the simulation.
+    pub time_budget: Jiffies,
+    /// Environmental events of the run.
+    pub scenario: Vec<(Jiffies, ScenarioEvent)>,
+}
+
+impl Repro {
+    /// Scenario to pass to [`SimulationBuilder::scenario`].
+    ///
+    /// [`SimulationBuilder::scenario`]: crate::SimulationBuilder::scenario
+    pub fn scenario(&self) -> Scenario {
+        self.scenario
+            .iter()
+            .cloned()
+            .fold(Scenario::new(), |scenario, (at, event)| {
+                scenario.at(at, event)
+            })
+    }
+
+    fn with_seed(&self, seed: u64) -> Self {
+        Self {
+            seed,
+            ..self.clone()
+        }
+    }
+
+    fn with_processes(&self, processes: usize) -> Self {
+        let exists = |id: &ProcessId| *id <= processes;
+        let scenario = self
+            .scenario
+            .iter()
+            .filter_map(|(at, event)| {
+                let event = match event {
+                    ScenarioEvent::Crash(id) | ScenarioEvent::Restart(id) if !exists(id) => {
+                        return None;
+                    }
+                    ScenarioEvent::Partition(groups) => ScenarioEvent::Partition(
+                        groups
+                            .iter()
+                            .map(|group| group.iter().copied().filter(exists).collect())
+                            .filter(|group: &Vec<ProcessId>| !group.is_empty())
+                            .collect(),
+                    ),
+                    event => event.clone(),
+                };
+                Some((*at, event))
+            })
+            .collect();
+        Self {
+            processes,
+            scenario,
+            ..self.clone()
+        }
+    }
+
+    fn with_time_budget(&self, time_budget: Jiffies) -> Self {
+        Self {
+            time_budget,
+            scenario: self
+                .scenario
+                .iter()
+                .filter(|(at, _)| *at < time_budget)
+                .cloned()
+                .collect(),
+            ..self.clone()
+        }
+    }
+
+    fn without_events(&self, from: usize, to: usize) -> Self {
+        let mut scenario = self.scenario.clone();
+        scenario.drain(from..to);
+        Self {
+            scenario,
+            ..self.clone()
+        }
+    }
+}
+
+impl fmt::Display for Repro {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "seed: {}", self.seed)?;
+        writeln!(f, "processes: {}", self.processes)?;
+        writeln!(f, "time budget: {}", self.time_budget)?;
+        write!(f, "scenario:")?;
+        if self.scenario.is_empty() {
+            write!(f, " empty")?;
+        }
+        self.scenario
+            .iter()
+            .try_for_each(|(at, event)| write!(f, "\n  {at}: {event:?}"))
+    }
+}
+
+/// Shrinks a failing run to a minimal repro case.
+///
+/// The check builds and runs a simulation from a [`Repro`] and returns whether
+/// the invariant held; a panic (e.g. a failed [`sim_assert!`]) counts as a
+/// failure too. [`sweep`] finds a failing seed, [`minimize`] then repeatedly
+/// shrinks the number of processes, the scenario and the time budget while the
+/// run keeps failing, until nothing shrinks anymore.
+///
+/// Smaller configurations often need another seed to hit the same
+/// interleaving: [`with_seed_attempts`] tries every candidate with several
+/// consecutive seeds.
+///
+/// Panic messages of the explored runs are silenced by replacing the panic hook
+/// of the whole program while the minimizer runs.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::collections::BTreeMap;
+/// use dscale::{ProcessHandle, ProcessId, MessagePtr, TimerId, Jiffies, SimulationBuilder};
+/// use dscale::{Distributions, LatencyDescription, broadcast, now, rank, schedule_timer_after};
+/// use dscale::global::configuration;
+/// use dscale::helpers::minimizer::{Minimizer, Repro};
+/// use dscale::scenario::{crash, restart};
+///
+/// struct Heartbeat;
+/// impl dscale::Message for Heartbeat {}
+///
+/// // Buggy: assumes that peers never stop sending heartbeats
+/// #[derive(Default)]
+/// struct Node {
+///     last_heard: BTreeMap<ProcessId, Jiffies>,
+/// }
+///
+/// impl ProcessHandle for Node {
+///     fn start(&mut self) {
+///         (1..=configuration::process_number())
+///             .filter(|id| *id != rank())
+///             .for_each(|id| {
+///                 self.last_heard.insert(id, now());
+///             });
+///         schedule_timer_after(Jiffies(10));
+///     }
+///
+///     fn on_message(&mut self, from: ProcessId, message: MessagePtr) {
+///         self.last_heard.insert(from, now());
+///     }
+///
+///     fn on_timer(&mut self, id: TimerId) {
+///         assert!(self.last_heard.values().all(|heard| now() - *heard <= Jiffies(50)));
+///         broadcast(Heartbeat);
+///         schedule_timer_after(Jiffies(10));
+///     }
+/// }
+///
+/// let check = |repro: &Repro| {
+///     SimulationBuilder::default()
+///         .seed(repro.seed)
+///         .add_pool::<Node>("nodes", repro.processes)
+///         .latency_topology(&[LatencyDescription::WithinPool(
+///             "nodes",
+///             Distributions::Uniform(Jiffies(1), Jiffies(5)),
+///         )])
+///         .scenario(repro.scenario())
+///         .time_budget(repro.time_budget)
+///         .build()
+///         .run();
+///     true
+/// };
+///
+/// let base = Repro {
+///     seed: 0,
+///     processes: 7,
+///     time_budget: Jiffies(1_000),
+///     scenario: vec![(Jiffies(100), crash(2)), (Jiffies(700), crash(5)), (Jiffies(900), restart(5))],
+/// };
+///
+/// let mut minimizer = Minimizer::new(check);
+/// let failing = minimizer.sweep(&base, 0..10).expect("Some seed fails");
+/// let minimal = minimizer.minimize(failing);
+/// assert_eq!(minimal.processes, 2);
+/// assert_eq!(minimal.scenario, vec![(Jiffies(100), crash(2))]);
+/// println!("{minimal}");
+/// ```
+///
+/// # Panics
+///
+/// [`Minimizer::minimize`] panics if the given repro does not fail.
+///
+/// [`sim_assert!`]: crate::sim_assert
+/// [`sweep`]: Minimizer::sweep
+/// [`minimize`]: Minimizer::minimize
+/// [`with_seed_attempts`]: Minimizer::with_seed_attempts
+pub struct Minimizer<F> {
+    check: F,
+    min_processes: usize,
+    seed_attempts: u64,
+    runs: usize,
+}
+
+impl<F: FnMut(&Repro) -> bool> Minimizer<F> {
+    /// Creates a minimizer of runs checked by `check`, which returns `false` (or panics) on failure.
+    pub fn new(check: F) -> Self {
+        Self {
+            check,
+            min_processes: 1,
+            seed_attempts: 1,
+            runs: 0,
+        }
+    }
+
+    /// Never shrinks below `processes` processes, e.g. the smallest valid cluster.
+    pub fn with_min_processes(mut self, processes: usize) -> Self {
+        self.min_processes = processes;
+        self
+    }
+
+    /// Tries every candidate configuration with `attempts` consecutive seeds.
+    pub fn with_seed_attempts(mut self, attempts: u64) -> Self {
+        assert!(attempts > 0, "Seed attempts should be positive");
+        self.seed_attempts = attempts;
+        self
+    }
+
+    /// Number of runs executed so far.
+    pub fn runs(&self) -> usize {
+        self.runs
+    }
+
+    /// Runs the repro once, returns `true` if it fails.
+    pub fn fails(&mut self, repro: &Repro) -> bool {
+        self.silenced(|minimizer| minimizer.run(repro))
+    }
+
+    /// Runs `base` with every seed in order, returns the first failing run.
+    pub fn sweep(&mut self, base: &Repro, seeds: impl IntoIterator<Item = u64>) -> Option<Repro> {
+        self.silenced(|minimizer| {
+            seeds
+                .into_iter()
+                .map(|seed| base.with_seed(seed))
+                .find(|repro| minimizer.run(repro))
+        })
+    }
+
+    /// Shrinks the failing repro until no nearby configuration fails.
+    pub fn minimize(&mut self, failing: Repro) -> Repro {
+        self.silenced(|minimizer| {
+            assert!(
+                minimizer.run(&failing),
+                "Repro should fail before minimization"
+            );
+            let mut best = failing;
+            loop {
+                let before = best.clone();
+                best = minimizer.shrink_processes(best);
+                best = minimizer.shrink_scenario(best);
+                best = minimizer.shrink_time_budget(best);
+                if best == before {
+                    return best;
+                }
+            }
+        })
+    }
+
+    fn run(&mut self, repro: &Repro) -> bool {
+        self.runs += 1;
+        let check = &mut self.check;
+        !panic::catch_unwind(AssertUnwindSafe(|| check(repro))).unwrap_or(false)
+    }
+
+    // Failing variant of the candidate with one of the following seeds
+    fn attempt(&mut self, candidate: Repro) -> Option<Repro> {
+        (0..self.seed_attempts)
+            .map(|offset| candidate.with_seed(candidate.seed.wrapping_add(offset)))
+            .find(|repro| self.run(repro))
+    }
+
+    // Binary search for the fewest processes, failures are not monotone so this is a heuristic
+    fn shrink_processes(&mut self, mut best: Repro) -> Repro {
+        let (mut low, mut high) = (self.min_processes, best.processes);
+        while low < high {
+            let middle = (low + high) / 2;
+            match self.attempt(best.with_processes(middle)) {
+                Some(repro) => {
+                    best = repro;
+                    high = middle;
+                }
+                None => low = middle + 1,
+            }
+        }
+        best
+    }
+
+    // Removes chunks of events, halving the chunk size down to single events
+    fn shrink_scenario(&mut self, mut best: Repro) -> Repro {
+        let mut chunk = best.scenario.len().div_ceil(2);
+        while chunk > 0 {
+            let mut from = 0;
+            while from < best.scenario.len() {
+                let to = (from + chunk).min(best.scenario.len());
+                match self.attempt(best.without_events(from, to)) {
+                    Some(repro) => best = repro,
+                    None => from = to,
+                }
+            }
+            chunk /= 2;
+        }
+        best
+    }
+
+    fn shrink_time_budget(&mut self, mut best: Repro) -> Repro {
+        let (mut low, mut high) = (1, best.time_budget.0);
+        while low < high {
+            let middle = (low + high) / 2;
+            match self.attempt(best.with_time_budget(Jiffies(middle))) {
+                Some(repro) => {
+                    best = repro;
+                    high = middle;
+                }
+                None => low = middle + 1,
+            }
+        }
+        best
+    }
+
+    fn silenced<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> T {
+        let hook = panic::take_hook();
+        panic::set_hook(Box::new(|_| {}));
+        let result = panic::catch_unwind(AssertUnwindSafe(|| f(self)));
+        panic::set_hook(hook);
+        result.unwrap_or_else(|panic| panic::resume_unwind(panic))
+    }
+}
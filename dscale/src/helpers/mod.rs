@@ -1,4 +1,47 @@
+pub mod assertion;
+pub mod backoff;
+pub mod batch;
 pub mod combiner;
 pub mod debug;
+pub mod dedup_cache;
+pub mod discovery;
+#[cfg(feature = "serde")]
+pub mod encoded_size;
+pub mod failure_detector;
+#[cfg(feature = "fuzz")]
+pub mod fuzz;
+pub mod golden;
+pub mod leader_schedule;
+pub mod log_capture;
+pub mod minimizer;
+pub mod quorum;
+pub mod rate_limiter;
+pub mod reliable;
+pub mod round_protocol;
+pub mod tie_break_audit;
+pub mod trace_diff;
+pub mod virtual_size;
 
+pub use backoff::Backoff;
+pub use backoff::BackoffStats;
+pub use batch::BatchScheduler;
 pub use combiner::Combiner;
+pub use dedup_cache::DedupCache;
+pub use dedup_cache::DedupStats;
+#[cfg(feature = "serde")]
+pub use encoded_size::encoded_size;
+pub use failure_detector::FailureDetector;
+#[cfg(feature = "fuzz")]
+pub use fuzz::HandlerFuzzer;
+pub use golden::Golden;
+pub use leader_schedule::LeaderSchedule;
+pub use leader_schedule::Leadership;
+pub use minimizer::Minimizer;
+pub use quorum::QuorumTracker;
+pub use rate_limiter::RateLimiter;
+pub use reliable::ReliableLayer;
+pub use round_protocol::RoundProtocol;
+pub use tie_break_audit::TieBreakAudit;
+pub use trace_diff::TraceDiff;
+pub use virtual_size::VirtualSize;
+pub use virtual_size::virtual_size_of;
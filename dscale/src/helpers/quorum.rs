@@ -0,0 +1,261 @@
+//! Quorum tracking with introspection of stuck quorums.
+//!
+//! A protocol waiting for a quorum that never forms usually just goes silent.
+//! This module provides the `QuorumTracker` struct, which collects votes of
+//! distinct processes like a [`Combiner`], but also registers itself with the
+//! simulation while pending. [`Simulation::pending_quorums`] lists every quorum
+//! still waiting together with its label, age and missing voters, so a stuck
+//! run explains itself in a report line: "P3 'commit 5': waiting on 1 more vote
+//! (2 of 3) from P9 for 40000 jiffies".
+//!
+//! [`Combiner`]: crate::helpers::Combiner
+//! [`Simulation::pending_quorums`]: crate::Simulation::pending_quorums
+
+use std::{
+    cell::RefCell,
+    collections::BTreeSet,
+    fmt::{self, Display},
+    rc::{Rc, Weak},
+};
+
+use crate::{Jiffies, ProcessId, now, rank};
+
+struct QuorumState {
+    owner: ProcessId,
+    label: String,
+    opened: Jiffies,
+    threshold: usize,
+    voters: BTreeSet<ProcessId>,
+    votes: BTreeSet<ProcessId>,
+}
+
+impl QuorumState {
+    fn is_reached(&self) -> bool {
+        self.votes.len() >= self.threshold
+    }
+}
+
+thread_local! {
+    static QUORUMS: RefCell<Vec<Weak<RefCell<QuorumState>>>> = const { RefCell::new(Vec::new()) };
+}
+
+fn register(state: &Rc<RefCell<QuorumState>>) {
+    QUORUMS.with_borrow_mut(|quorums| {
+        // Forget dropped trackers whenever the registry would grow
+        if quorums.len() == quorums.capacity() {
+            quorums.retain(|quorum| quorum.strong_count() > 0);
+        }
+        quorums.push(Rc::downgrade(state));
+    });
+}
+
+pub(crate) fn pending_quorums() -> Vec<PendingQuorum> {
+    QUORUMS.with_borrow(|quorums| {
+        quorums
+            .iter()
+            .filter_map(Weak::upgrade)
+            .filter(|state| !state.borrow().is_reached())
+            .map(|state| PendingQuorum::of(&state.borrow()))
+            .collect()
+    })
+}
+
+pub(crate) fn drop_quorums() {
+    QUORUMS.take();
+}
+
+/// Collects votes of distinct processes until a threshold is reached.
+///
+/// A tracker waits for `threshold` votes out of a known set of voters; votes of
+/// other processes and repeated votes are ignored. While the quorum is not
+/// reached, the tracker is listed by [`Simulation::pending_quorums`] as a
+/// [`PendingQuorum`]. Dropping the tracker (e.g. once the protocol moved on to
+/// the next round) removes it from the listing, as does a restart of the owner.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::collections::BTreeMap;
+/// use dscale::{ProcessHandle, ProcessId, MessagePtr, TimerId, Jiffies, SimulationBuilder};
+/// use dscale::{Distributions, LatencyDescription, list_pool, rank, send_to};
+/// use dscale::helpers::quorum::QuorumTracker;
+/// use dscale::scenario::crash;
+///
+/// struct Propose(usize);
+/// impl dscale::Message for Propose {}
+///
+/// struct Vote(usize);
+/// impl dscale::Message for Vote {}
+///
+/// #[derive(Default)]
+/// struct Replica {
+///     votes: BTreeMap<usize, QuorumTracker>, // Slot -> votes for the proposal
+/// }
+///
+/// impl ProcessHandle for Replica {
+///     fn start(&mut self) {
+///         if rank() == 1 {
+///             // Unanimity: every replica must acknowledge the proposal
+///             let replicas = list_pool("replicas");
+///             let votes = QuorumTracker::new("commit 1", replicas.clone(), replicas.len());
+///             self.votes.insert(1, votes);
+///             replicas.iter().for_each(|replica| send_to(*replica, Propose(1)));
+///         }
+///     }
+///
+///     fn on_message(&mut self, from: ProcessId, message: MessagePtr) {
+///         if let Some(proposal) = message.try_as::<Propose>() {
+///             send_to(from, Vote(proposal.0));
+///         }
+///         if let Some(vote) = message.try_as::<Vote>() {
+///             let votes = self.votes.get_mut(&vote.0).expect("Voting on known slot");
+///             if votes.vote(from) {
+///                 // Committed
+///             }
+///         }
+///     }
+///
+///     fn on_timer(&mut self, id: TimerId) {}
+/// }
+///
+/// let mut simulation = SimulationBuilder::default()
+///     .add_pool::<Replica>("replicas", 3)
+///     .latency_topology(&[LatencyDescription::WithinPool(
+///         "replicas",
+///         Distributions::Uniform(Jiffies(5), Jiffies(10)),
+///     )])
+///     .scenario(dscale::Scenario::new().at(Jiffies(0), crash(3)))
+///     .time_budget(Jiffies(1_000))
+///     .build();
+/// simulation.step_until(Jiffies(500));
+///
+/// let stuck = simulation.pending_quorums();
+/// assert_eq!(stuck.len(), 1);
+/// assert_eq!(stuck[0].label, "commit 1");
+/// assert_eq!(stuck[0].missing, vec![3]);
+/// assert!(stuck[0].to_string().starts_with("P1 'commit 1': waiting on 1 more vote (2 of 3) from P3 for"));
+/// ```
+///
+/// # Panics
+///
+/// [`QuorumTracker::new`] panics outside of a process context, with a zero
+/// threshold or with a threshold exceeding the number of voters.
+///
+/// [`Simulation::pending_quorums`]: crate::Simulation::pending_quorums
+pub struct QuorumTracker {
+    state: Rc<RefCell<QuorumState>>,
+}
+
+impl QuorumTracker {
+    /// Creates a tracker of the current process waiting for `threshold` votes out of `voters`.
+    pub fn new(
+        label: impl Into<String>,
+        voters: impl IntoIterator<Item = ProcessId>,
+        threshold: usize,
+    ) -> Self {
+        let voters: BTreeSet<ProcessId> = voters.into_iter().collect();
+        assert!(threshold > 0, "Quorum threshold should be positive");
+        assert!(
+            threshold <= voters.len(),
+            "Quorum threshold should not exceed the number of voters"
+        );
+        let state = Rc::new(RefCell::new(QuorumState {
+            owner: rank(),
+            label: label.into(),
+            opened: now(),
+            threshold,
+            voters,
+            votes: BTreeSet::new(),
+        }));
+        register(&state);
+        Self { state }
+    }
+
+    /// Counts the vote of `from`.
+    ///
+    /// Returns `true` exactly once: for the vote which reaches the threshold.
+    pub fn vote(&mut self, from: ProcessId) -> bool {
+        let mut state = self.state.borrow_mut();
+        if state.is_reached() || !state.voters.contains(&from) {
+            return false;
+        }
+        state.votes.insert(from) && state.is_reached()
+    }
+
+    /// Returns `true` once the threshold is reached.
+    pub fn is_reached(&self) -> bool {
+        self.state.borrow().is_reached()
+    }
+
+    /// Number of counted votes.
+    pub fn votes(&self) -> usize {
+        self.state.borrow().votes.len()
+    }
+
+    /// Voters which have not voted yet.
+    pub fn missing(&self) -> Vec<ProcessId> {
+        let state = self.state.borrow();
+        state.voters.difference(&state.votes).copied().collect()
+    }
+}
+
+/// A quorum still waiting for votes, see [`Simulation::pending_quorums`].
+///
+/// Displays as a report line naming the process, the label, the progress, the
+/// missing voters and the age of the quorum.
+///
+/// [`Simulation::pending_quorums`]: crate::Simulation::pending_quorums
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PendingQuorum {
+    /// Process waiting for the quorum.
+    pub process: ProcessId,
+    /// Label given to the [`QuorumTracker`].
+    pub label: String,
+    /// Counted votes.
+    pub votes: usize,
+    /// Votes needed to reach the quorum.
+    pub threshold: usize,
+    /// Voters which have not voted yet.
+    pub missing: Vec<ProcessId>,
+    /// Time since the tracker was created.
+    pub age: Jiffies,
+}
+
+impl PendingQuorum {
+    fn of(state: &QuorumState) -> Self {
+        Self {
+            process: state.owner,
+            label: state.label.clone(),
+            votes: state.votes.len(),
+            threshold: state.threshold,
+            missing: state.voters.difference(&state.votes).copied().collect(),
+            age: now() - state.opened,
+        }
+    }
+}
+
+impl Display for PendingQuorum {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let needed = self.threshold - self.votes;
+        let missing: Vec<String> = self.missing.iter().map(|id| format!("P{id}")).collect();
+        write!(
+            f,
+            "P{} '{}': waiting on {needed} more {} ({} of {}) from {}{} for {} jiffies{}",
+            self.process,
+            self.label,
+            if needed == 1 { "vote" } else { "votes" },
+            self.votes,
+            self.threshold,
+            if needed < self.missing.len() {
+                "any of "
+            } else {
+                ""
+            },
+            missing.join(", "),
+            self.age.0,
+            self.age
+                .to_duration()
+                .map_or(String::new(), |age| format!(" [{age:?}]")),
+        )
+    }
+}
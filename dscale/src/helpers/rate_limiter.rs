@@ -0,0 +1,156 @@
+//! Token bucket rate limiting over simulated time.
+//!
+//! This module provides the `RateLimiter` struct which processes consult before
+//! sending, so client-side pacing and leader batching policies are expressed the
+//! same way across systems.
+
+use crate::{Jiffies, global::now};
+
+/// A token bucket refilled with simulated time.
+///
+/// The bucket holds at most `burst` tokens and gains `rate` tokens every `per`
+/// jiffies. Refill is exact: credit is kept in fractions of a token, so slow
+/// rates such as 3 tokens per 10 jiffies do not drift due to rounding.
+///
+/// The bucket starts full at the time of construction, so it is usually created
+/// in [`ProcessHandle::start`] rather than in `Default`.
+///
+/// # Examples
+///
+/// ## Client-Side Pacing
+///
+/// ```rust
+/// use dscale::{ProcessHandle, ProcessId, MessagePtr, TimerId, Jiffies, Message, send_to, schedule_timer_after};
+/// use dscale::helpers::RateLimiter;
+///
+/// struct Request;
+/// impl Message for Request {}
+///
+/// #[derive(Default)]
+/// struct Client {
+///     limiter: Option<RateLimiter>,
+/// }
+///
+/// impl ProcessHandle for Client {
+///     fn start(&mut self) {
+///         // At most 5 requests per 100 jiffies, no bursts above 5
+///         self.limiter = Some(RateLimiter::new(5, Jiffies(100), 5));
+///         schedule_timer_after(Jiffies(1));
+///     }
+///
+///     fn on_message(&mut self, from: ProcessId, message: MessagePtr) {}
+///
+///     fn on_timer(&mut self, id: TimerId) {
+///         let limiter = self.limiter.as_mut().unwrap();
+///         if limiter.try_acquire(1) {
+///             send_to(1, Request);
+///             schedule_timer_after(Jiffies(1));
+///         } else {
+///             schedule_timer_after(limiter.wait_time(1));
+///         }
+///     }
+/// }
+/// ```
+///
+/// ## Leader Batching
+///
+/// ```rust
+/// use dscale::{Jiffies, helpers::RateLimiter};
+///
+/// // Propose at most 1000 transactions per 10 jiffies
+/// let mut limiter = RateLimiter::new(1000, Jiffies(10), 1000);
+/// let pending = 1500;
+///
+/// let batch = limiter.acquire_up_to(pending);
+/// assert_eq!(batch, 1000);
+/// assert_eq!(limiter.wait_time(pending - batch), Jiffies(5));
+/// ```
+///
+/// # Panics
+///
+/// [`RateLimiter::new`] panics if `rate`, `per` or `burst` is zero.
+///
+/// [`ProcessHandle::start`]: crate::ProcessHandle::start
+#[derive(Clone, Debug)]
+pub struct RateLimiter {
+    rate: usize,
+    per: usize,
+    capacity: usize, // burst * per
+    credit: usize,   // Tokens scaled by per
+    last_refill: Jiffies,
+}
+
+impl RateLimiter {
+    /// Creates a full bucket gaining `rate` tokens every `per` jiffies and holding at most `burst` tokens.
+    pub fn new(rate: usize, per: Jiffies, burst: usize) -> Self {
+        assert!(rate > 0, "RateLimiter rate should be positive");
+        assert!(per.0 > 0, "RateLimiter period should be positive");
+        assert!(burst > 0, "RateLimiter burst should be positive");
+        let capacity = burst * per.0;
+        Self {
+            rate,
+            per: per.0,
+            capacity,
+            credit: capacity,
+            last_refill: now(),
+        }
+    }
+
+    /// Number of whole tokens available right now.
+    pub fn available(&mut self) -> usize {
+        self.refill();
+        self.credit / self.per
+    }
+
+    /// Takes `tokens` from the bucket if all of them are available.
+    ///
+    /// Returns `false` and takes nothing otherwise.
+    pub fn try_acquire(&mut self, tokens: usize) -> bool {
+        self.refill();
+        let needed = tokens * self.per;
+        if needed > self.credit {
+            return false;
+        }
+        self.credit -= needed;
+        true
+    }
+
+    /// Takes as many tokens as available, but at most `tokens`, and returns their number.
+    pub fn acquire_up_to(&mut self, tokens: usize) -> usize {
+        let taken = self.available().min(tokens);
+        self.credit -= taken * self.per;
+        taken
+    }
+
+    /// Time after which [`try_acquire`] of `tokens` succeeds, if nothing else is taken meanwhile.
+    ///
+    /// Returns `Jiffies(0)` if tokens are available right now.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `tokens` exceeds the burst size, because such request never succeeds.
+    ///
+    /// [`try_acquire`]: RateLimiter::try_acquire
+    pub fn wait_time(&mut self, tokens: usize) -> Jiffies {
+        self.refill();
+        let needed = tokens * self.per;
+        assert!(
+            needed <= self.capacity,
+            "Requested {tokens} tokens, but burst is only {}",
+            self.capacity / self.per
+        );
+        Jiffies(needed.saturating_sub(self.credit).div_ceil(self.rate))
+    }
+}
+
+impl RateLimiter {
+    fn refill(&mut self) {
+        let present = now();
+        let elapsed = (present - self.last_refill).0;
+        self.credit = self
+            .credit
+            .saturating_add(elapsed.saturating_mul(self.rate))
+            .min(self.capacity);
+        self.last_refill = present;
+    }
+}
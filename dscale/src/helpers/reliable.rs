@@ -0,0 +1,376 @@
+//! Reliable delivery built at the protocol layer.
+//!
+//! The simulated network loses messages only on purpose (partitions, crashes,
+//! full inboxes), and protocols often assume it does not lose them at all. This
+//! module provides the [`ReliableLayer`], a bottom [`Layer`] of a [`Stack`]
+//! which acknowledges every message, retransmits unacknowledged ones with a
+//! doubling timeout and delivers retransmitted duplicates only once. Enabled
+//! between chosen pools only, it lets an experiment compare a protocol over a
+//! reliable layer with the same protocol assuming a reliable network, and its
+//! [`ReliableStats`] tell what the reliability costs.
+//!
+//! [`Stack`]: crate::stack::Stack
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::{
+    Jiffies, Message, MessagePtr, ProcessId, TimerId,
+    global::{self, anykv},
+    list_pool,
+    message::{Shared, SharedMessage},
+    now, rank, schedule_timer_after, send_to,
+    stack::{Layer, LayerContext},
+};
+
+/// Key of [`ReliableStats`] in [`anykv`], see [`stats`].
+///
+/// [`anykv`]: crate::global::anykv
+pub const RELIABLE_STATS_KEY: &str = "reliable_stats";
+
+/// Costs of reliable delivery, summed over all processes of the run.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ReliableStats {
+    /// Messages sent to a receiver for the first time over reliable links.
+    pub sent: usize,
+    /// Messages sent again because they were not acknowledged in time.
+    pub retransmitted: usize,
+    /// Acknowledgments sent by receivers.
+    pub acks: usize,
+    /// Received copies of messages delivered already.
+    pub duplicates: usize,
+    /// Messages dropped after running out of retransmissions.
+    pub given_up: usize,
+    /// Messages sent over links without reliability.
+    pub unreliable: usize,
+}
+
+impl ReliableStats {
+    /// Messages on the wire per message sent, 1.0 for a reliable network.
+    pub fn overhead(&self) -> f64 {
+        if self.sent == 0 {
+            return 0.0;
+        }
+        (self.sent + self.retransmitted + self.acks) as f64 / self.sent as f64
+    }
+}
+
+/// Returns [`ReliableStats`] of the current run, zeroes if no message was sent
+/// through a [`ReliableLayer`].
+pub fn stats() -> ReliableStats {
+    anykv::try_get::<ReliableStats>(RELIABLE_STATS_KEY).unwrap_or_default()
+}
+
+fn record(f: impl FnOnce(&mut ReliableStats)) {
+    if anykv::try_get::<ReliableStats>(RELIABLE_STATS_KEY).is_none() {
+        anykv::set::<ReliableStats>(RELIABLE_STATS_KEY, ReliableStats::default());
+    }
+    anykv::modify::<ReliableStats>(RELIABLE_STATS_KEY, f);
+}
+
+/// A message submitted to a [`ReliableLayer`] together with its receivers.
+pub struct Outgoing {
+    to: Vec<ProcessId>,
+    message: SharedMessage,
+}
+
+impl Outgoing {
+    /// Sends `message` to process `to`.
+    pub fn to(to: ProcessId, message: impl Message + 'static) -> Self {
+        Self::members(vec![to], message)
+    }
+
+    /// Sends `message` to every member of `pool` except the sender.
+    pub fn pool(pool: &str, message: impl Message + 'static) -> Self {
+        let me = rank();
+        let members = list_pool(pool)
+            .into_iter()
+            .filter(|member| *member != me)
+            .collect();
+        Self::members(members, message)
+    }
+
+    /// Sends `message` to every process in `to`.
+    pub fn members(to: Vec<ProcessId>, message: impl Message + 'static) -> Self {
+        Self {
+            to,
+            message: Shared::new(message),
+        }
+    }
+}
+
+impl Message for Outgoing {}
+
+// Sequence numbers start over when the sender restarts, so they are told apart
+// by the incarnation of the sender: the time its layer started.
+struct Data {
+    incarnation: Jiffies,
+    seq: u64,
+    message: SharedMessage,
+}
+
+impl Message for Data {
+    fn virtual_size(&self) -> usize {
+        16 + self.message.virtual_size()
+    }
+}
+
+struct Ack {
+    incarnation: Jiffies,
+    seq: u64,
+}
+
+impl Message for Ack {
+    fn virtual_size(&self) -> usize {
+        16
+    }
+}
+
+struct Pending {
+    message: SharedMessage,
+    retransmissions: usize,
+}
+
+// Sequence numbers delivered from one incarnation of a sender
+#[derive(Default)]
+struct Delivered {
+    incarnation: Jiffies,
+    below: u64,            // Every number below was delivered
+    beyond: BTreeSet<u64>, // Delivered out of order
+}
+
+impl Delivered {
+    // Returns true the first time the number is seen
+    fn insert(&mut self, seq: u64) -> bool {
+        if seq < self.below || !self.beyond.insert(seq) {
+            return false;
+        }
+        while self.beyond.remove(&self.below) {
+            self.below += 1;
+        }
+        true
+    }
+}
+
+/// Acknowledgments and retransmissions below the other layers of a [`Stack`].
+///
+/// Upper layers submit [`Outgoing`] messages. Messages between processes of the
+/// pool pairs given with [`between`] (or all messages, if no pair is given) are
+/// numbered, acknowledged by receivers and retransmitted every `timeout`
+/// jiffies, doubled after each retransmission up to `16 * timeout`, until
+/// acknowledged or [`max_retransmissions`] run out. Receivers deliver every
+/// message once, in the order of arrival. Other messages are sent as they are.
+///
+/// Both sides of a reliable link must run the layer. Pending messages are lost
+/// together with a crashed sender.
+///
+/// # Examples
+///
+/// ```rust
+/// use dscale::{Distributions, Jiffies, LatencyDescription, Message, MessagePtr, ProcessId, SimulationBuilder};
+/// use dscale::helpers::reliable::{self, Outgoing, ReliableLayer};
+/// use dscale::stack::{Layer, LayerContext, Stack, StackDefinition, Stacked};
+///
+/// struct Update(usize);
+/// impl Message for Update {}
+///
+/// #[derive(Default)]
+/// struct Replication;
+///
+/// impl Layer for Replication {
+///     fn start(&mut self, context: &mut LayerContext) {
+///         if dscale::rank() == 1 {
+///             context.submit(Outgoing::pool("replicas", Update(42)));
+///         }
+///     }
+///
+///     fn on_deliver(&mut self, from: ProcessId, message: MessagePtr, context: &mut LayerContext) {
+///         assert_eq!(message.as_type::<Update>().0, 42);
+///     }
+/// }
+///
+/// struct Replica;
+/// impl StackDefinition for Replica {
+///     fn stack() -> Stack {
+///         Stack::new()
+///             .layer(ReliableLayer::new(Jiffies(50)).between("replicas", "replicas"))
+///             .layer(Replication)
+///     }
+/// }
+///
+/// let mut simulation = SimulationBuilder::default()
+///     .add_pool::<Stacked<Replica>>("replicas", 3)
+///     .latency_topology(&[LatencyDescription::WithinPool(
+///         "replicas",
+///         Distributions::Uniform(Jiffies(1), Jiffies(10)),
+///     )])
+///     .build();
+/// simulation.step_until(Jiffies(1_000));
+///
+/// let stats = reliable::stats();
+/// assert_eq!((stats.sent, stats.acks, stats.retransmitted), (2, 2, 0));
+/// ```
+///
+/// [`Stack`]: crate::stack::Stack
+/// [`between`]: ReliableLayer::between
+/// [`max_retransmissions`]: ReliableLayer::max_retransmissions
+pub struct ReliableLayer {
+    timeout: Jiffies,
+    max_retransmissions: Option<usize>,
+    pairs: Vec<(&'static str, &'static str)>,
+    links: Vec<(BTreeSet<ProcessId>, BTreeSet<ProcessId>)>, // Members of the pairs, from start
+    incarnation: Jiffies,
+    next_seq: HashMap<ProcessId, u64>,
+    pending: BTreeMap<(ProcessId, u64), Pending>,
+    timers: HashMap<TimerId, (ProcessId, u64)>,
+    delivered: HashMap<ProcessId, Delivered>,
+}
+
+impl ReliableLayer {
+    /// Creates a layer retransmitting messages unacknowledged for `timeout` jiffies.
+    pub fn new(timeout: Jiffies) -> Self {
+        assert!(timeout.0 > 0, "Retransmission timeout should be positive");
+        Self {
+            timeout,
+            max_retransmissions: None,
+            pairs: Vec::new(),
+            links: Vec::new(),
+            incarnation: Jiffies(0),
+            next_seq: HashMap::new(),
+            pending: BTreeMap::new(),
+            timers: HashMap::new(),
+            delivered: HashMap::new(),
+        }
+    }
+
+    /// Makes messages between pools `a` and `b` (both ways) reliable. Without
+    /// any pair every message is.
+    pub fn between(mut self, a: &'static str, b: &'static str) -> Self {
+        self.pairs.push((a, b));
+        self
+    }
+
+    /// Gives up on a message after `retransmissions`, unlimited by default.
+    pub fn max_retransmissions(mut self, retransmissions: usize) -> Self {
+        self.max_retransmissions = Some(retransmissions);
+        self
+    }
+
+    fn is_reliable(&self, from: ProcessId, to: ProcessId) -> bool {
+        self.pairs.is_empty()
+            || self.links.iter().any(|(a, b)| {
+                (a.contains(&from) && b.contains(&to)) || (b.contains(&from) && a.contains(&to))
+            })
+    }
+
+    fn transmit(&mut self, to: ProcessId, seq: u64, after: Jiffies) {
+        let message = self.pending[&(to, seq)].message.clone();
+        send_to(
+            to,
+            Data {
+                incarnation: self.incarnation,
+                seq,
+                message,
+            },
+        );
+        self.timers.insert(schedule_timer_after(after), (to, seq));
+    }
+}
+
+impl Layer for ReliableLayer {
+    fn start(&mut self, _context: &mut LayerContext) {
+        self.incarnation = now();
+        self.links = self
+            .pairs
+            .iter()
+            .map(|(a, b)| {
+                (
+                    list_pool(a).into_iter().collect(),
+                    list_pool(b).into_iter().collect(),
+                )
+            })
+            .collect();
+    }
+
+    fn on_deliver(&mut self, from: ProcessId, message: MessagePtr, context: &mut LayerContext) {
+        if let Some(data) = message.try_as::<Data>() {
+            send_to(
+                from,
+                Ack {
+                    incarnation: data.incarnation,
+                    seq: data.seq,
+                },
+            );
+            record(|stats| stats.acks += 1);
+
+            let delivered = self.delivered.entry(from).or_default();
+            if delivered.incarnation != data.incarnation {
+                *delivered = Delivered {
+                    incarnation: data.incarnation,
+                    ..Default::default()
+                };
+            }
+            if delivered.insert(data.seq) {
+                context.deliver(from, MessagePtr(data.message.clone()));
+            } else {
+                record(|stats| stats.duplicates += 1);
+            }
+            return;
+        }
+
+        if let Some(ack) = message.try_as::<Ack>() {
+            if ack.incarnation == self.incarnation {
+                self.pending.remove(&(from, ack.seq));
+            }
+            return;
+        }
+
+        context.deliver(from, message);
+    }
+
+    fn on_submit(&mut self, message: MessagePtr, _context: &mut LayerContext) {
+        let outgoing = message.as_type::<Outgoing>();
+        let me = rank();
+        for to in outgoing.to.iter().copied() {
+            if !self.is_reliable(me, to) {
+                global::send_shared(to, outgoing.message.clone());
+                record(|stats| stats.unreliable += 1);
+                continue;
+            }
+
+            let next_seq = self.next_seq.entry(to).or_default();
+            let seq = *next_seq;
+            *next_seq += 1;
+            self.pending.insert(
+                (to, seq),
+                Pending {
+                    message: outgoing.message.clone(),
+                    retransmissions: 0,
+                },
+            );
+            self.transmit(to, seq, self.timeout);
+            record(|stats| stats.sent += 1);
+        }
+    }
+
+    fn on_timer(&mut self, id: TimerId, _context: &mut LayerContext) {
+        let Some((to, seq)) = self.timers.remove(&id) else {
+            return;
+        };
+        let Some(pending) = self.pending.get_mut(&(to, seq)) else {
+            return; // Acknowledged meanwhile
+        };
+        if self
+            .max_retransmissions
+            .is_some_and(|max| pending.retransmissions >= max)
+        {
+            self.pending.remove(&(to, seq));
+            record(|stats| stats.given_up += 1);
+            return;
+        }
+
+        pending.retransmissions += 1;
+        let backoff = 1 << pending.retransmissions.min(4);
+        self.transmit(to, seq, Jiffies(self.timeout.0 * backoff));
+        record(|stats| stats.retransmitted += 1);
+    }
+}
@@ -0,0 +1,188 @@
+//! Skeleton of round-based protocols.
+//!
+//! This module provides the `RoundProtocol` struct tracking the current round, the
+//! processes heard from in every round and the timer of the current round, so
+//! DAG protocols, view-based BFT and similar protocols share one round state machine
+//! instead of keeping counters, quorum checks and timers in sync by hand.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{Jiffies, ProcessId, TimerId, global::schedule_timer_after};
+
+/// Current round of a process with per-round quorum tracking and an optional round timeout.
+///
+/// Processes are counted into a round with [`record`], duplicates are ignored. A
+/// round is complete once a quorum of distinct processes was recorded in it.
+/// [`advance`] enters the next round if the current one is complete, [`catch_up`]
+/// jumps to a later complete round. Entering a round schedules its timer (if a
+/// timeout is set): once it fires, [`on_timer`] reports the timeout and
+/// [`timed_out`] holds until the next round is entered.
+///
+/// The protocol starts in round 0 without a timer. Records of rounds below the
+/// current one are dropped, they can not change its progress anymore.
+///
+/// # Examples
+///
+/// ```rust
+/// use dscale::{ProcessHandle, ProcessId, MessagePtr, TimerId, Jiffies, Message, broadcast};
+/// use dscale::global::configuration;
+/// use dscale::helpers::RoundProtocol;
+///
+/// struct Proposal {
+///     round: usize,
+/// }
+/// impl Message for Proposal {}
+///
+/// #[derive(Default)]
+/// struct Node {
+///     rounds: Option<RoundProtocol>,
+///     has_leader: bool,
+/// }
+///
+/// impl Node {
+///     fn rounds(&mut self) -> &mut RoundProtocol {
+///         self.rounds.as_mut().unwrap()
+///     }
+///
+///     // Leaves the round after a quorum, waiting for the leader until the timeout
+///     fn try_advance(&mut self) {
+///         if !self.has_leader && !self.rounds().timed_out() {
+///             return;
+///         }
+///         if let Some(round) = self.rounds().advance() {
+///             self.has_leader = false;
+///             broadcast(Proposal { round });
+///         }
+///     }
+/// }
+///
+/// impl ProcessHandle for Node {
+///     fn start(&mut self) {
+///         let quorum = configuration::process_number() / 2 + 1;
+///         self.rounds = Some(RoundProtocol::new(quorum).with_timeout(Jiffies(100)));
+///         broadcast(Proposal { round: 0 });
+///     }
+///
+///     fn on_message(&mut self, from: ProcessId, message: MessagePtr) {
+///         let round = message.as_type::<Proposal>().round;
+///         if round == self.rounds().round() && from == 1 {
+///             self.has_leader = true;
+///         }
+///         self.rounds().record(round, from);
+///         self.try_advance();
+///     }
+///
+///     fn on_timer(&mut self, id: TimerId) {
+///         if self.rounds().on_timer(id).is_some() {
+///             self.try_advance();
+///         }
+///     }
+/// }
+/// ```
+///
+/// # Panics
+///
+/// [`RoundProtocol::new`] panics if `quorum` is zero.
+///
+/// [`record`]: RoundProtocol::record
+/// [`advance`]: RoundProtocol::advance
+/// [`catch_up`]: RoundProtocol::catch_up
+/// [`on_timer`]: RoundProtocol::on_timer
+/// [`timed_out`]: RoundProtocol::timed_out
+#[derive(Clone, Debug)]
+pub struct RoundProtocol {
+    round: usize,
+    quorum: usize,
+    timeout: Option<Jiffies>,
+    timer: Option<TimerId>, // Of the current round
+    timed_out: bool,
+    heard: BTreeMap<usize, BTreeSet<ProcessId>>, // Round -> recorded processes
+}
+
+impl RoundProtocol {
+    /// Creates a protocol in round 0 whose rounds complete with `quorum` distinct processes.
+    pub fn new(quorum: usize) -> Self {
+        assert!(quorum > 0, "Round quorum should be positive");
+        Self {
+            round: 0,
+            quorum,
+            timeout: None,
+            timer: None,
+            timed_out: false,
+            heard: BTreeMap::new(),
+        }
+    }
+
+    /// Schedules a timer of `timeout` on entering every round after the first one.
+    pub fn with_timeout(mut self, timeout: Jiffies) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Current round.
+    pub fn round(&self) -> usize {
+        self.round
+    }
+
+    /// Number of distinct processes recorded in the round.
+    pub fn count(&self, round: usize) -> usize {
+        self.heard.get(&round).map_or(0, BTreeSet::len)
+    }
+
+    /// Returns `true` if a quorum of processes was recorded in the round.
+    pub fn quorum_reached(&self, round: usize) -> bool {
+        self.count(round) >= self.quorum
+    }
+
+    /// Counts `from` into the round, returns `true` if this record completed the quorum of the round.
+    pub fn record(&mut self, round: usize, from: ProcessId) -> bool {
+        if round < self.round {
+            return false;
+        }
+        let heard = self.heard.entry(round).or_default();
+        heard.insert(from) && heard.len() == self.quorum
+    }
+
+    /// Returns `true` if the timer of the current round has fired.
+    pub fn timed_out(&self) -> bool {
+        self.timed_out
+    }
+
+    /// Handles a timer, returns the round which timed out if `id` is the timer of the current round.
+    ///
+    /// Timers of rounds left already are not reported.
+    pub fn on_timer(&mut self, id: TimerId) -> Option<usize> {
+        if self.timer != Some(id) {
+            return None;
+        }
+        self.timer = None;
+        self.timed_out = true;
+        Some(self.round)
+    }
+
+    /// Enters the next round if the current one is complete and returns it.
+    pub fn advance(&mut self) -> Option<usize> {
+        if !self.quorum_reached(self.round) {
+            return None;
+        }
+        self.enter(self.round + 1);
+        Some(self.round)
+    }
+
+    /// Enters `round` if it is ahead of the current one and complete already, e.g. after
+    /// falling behind the others. Returns `true` if the round was entered.
+    pub fn catch_up(&mut self, round: usize) -> bool {
+        if round <= self.round || !self.quorum_reached(round) {
+            return false;
+        }
+        self.enter(round);
+        true
+    }
+
+    fn enter(&mut self, round: usize) {
+        self.round = round;
+        self.heard = self.heard.split_off(&round);
+        self.timed_out = false;
+        self.timer = self.timeout.map(schedule_timer_after);
+    }
+}
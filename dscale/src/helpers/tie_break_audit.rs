@@ -0,0 +1,116 @@
+//! Detection of protocols depending on the tie-break order of the engine.
+//!
+//! Events scheduled for the same jiffy are executed in a fixed order, by default
+//! in the order they were scheduled (see [`TieBreak::Fifo`]). This module reruns
+//! an experiment with shuffled tie-breaking (see [`SimulationBuilder::shuffle_ties`])
+//! and reports runs whose results differ.
+//!
+//! [`SimulationBuilder::shuffle_ties`]: crate::SimulationBuilder::shuffle_ties
+//! [`TieBreak::Fifo`]: crate::TieBreak::Fifo
+
+use std::fmt::Debug;
+
+use crate::SimulationBuilder;
+
+/// Results of an experiment rerun with different tie-break orders.
+///
+/// The experiment receives a [`SimulationBuilder`] to configure, builds and runs
+/// the simulation and returns its user-visible results (usually read from
+/// [`anykv`]). It is run once with the default tie-break order and once per salt
+/// with shuffled ties; everything else, including the seed, must stay the same.
+///
+/// [`RunDigest`] is not a good result here: it records the execution order, so it
+/// changes whenever ties are broken differently.
+///
+/// # Examples
+///
+/// ```rust
+/// use dscale::{SimulationBuilder, Jiffies, global::anykv, helpers::TieBreakAudit};
+///
+/// let audit = TieBreakAudit::run(8, |builder: SimulationBuilder| {
+///     anykv::set::<usize>("ticks", 0);
+///     let mut simulation = builder
+///         .add_pool::<Ticker>("tickers", 3)
+///         .time_budget(Jiffies(1_000))
+///         .seed(42)
+///         .build();
+///     simulation.run();
+///     anykv::get::<usize>("ticks")
+/// });
+///
+/// audit.assert_stable();
+/// # #[derive(Default)]
+/// # struct Ticker;
+/// # impl dscale::ProcessHandle for Ticker {
+/// #     fn start(&mut self) { dscale::schedule_timer_after(Jiffies(10)); }
+/// #     fn on_message(&mut self, from: dscale::ProcessId, message: dscale::MessagePtr) {}
+/// #     fn on_timer(&mut self, id: dscale::TimerId) {
+/// #         anykv::modify::<usize>("ticks", |t| *t += 1);
+/// #         dscale::schedule_timer_after(Jiffies(10));
+/// #     }
+/// # }
+/// ```
+///
+/// [`anykv`]: crate::global::anykv
+/// [`RunDigest`]: crate::RunDigest
+pub struct TieBreakAudit<T> {
+    baseline: T,
+    shuffled: Vec<(u64, T)>,
+}
+
+impl<T: PartialEq + Debug> TieBreakAudit<T> {
+    /// Runs `experiment` with the default tie-break order and then with salts
+    /// `1..=runs`.
+    pub fn run(runs: u64, experiment: impl Fn(SimulationBuilder) -> T) -> Self {
+        let baseline = experiment(SimulationBuilder::default());
+        let shuffled = (1..=runs)
+            .map(|salt| {
+                (
+                    salt,
+                    experiment(SimulationBuilder::default().shuffle_ties(salt)),
+                )
+            })
+            .collect();
+        Self { baseline, shuffled }
+    }
+
+    /// Returns result of the run with the default tie-break order.
+    pub fn baseline(&self) -> &T {
+        &self.baseline
+    }
+
+    /// Returns salts and results of the runs which differ from the baseline.
+    pub fn divergent(&self) -> impl Iterator<Item = (u64, &T)> {
+        self.shuffled
+            .iter()
+            .filter(|(_, result)| *result != self.baseline)
+            .map(|(salt, result)| (*salt, result))
+    }
+
+    /// Returns true if every shuffled run matched the baseline.
+    pub fn is_stable(&self) -> bool {
+        self.divergent().next().is_none()
+    }
+
+    /// Asserts that every shuffled run matched the baseline.
+    ///
+    /// # Panics
+    ///
+    /// Panics listing every divergent run with its salt, which reproduces it with
+    /// [`SimulationBuilder::shuffle_ties`].
+    pub fn assert_stable(&self) {
+        let divergent: Vec<String> = self
+            .divergent()
+            .map(|(salt, result)| format!("  salt {salt}: {result:?}"))
+            .collect();
+
+        assert!(
+            divergent.is_empty(),
+            "Results depend on tie-break order, baseline {:?}, {} of {} shuffled runs diverged:\n{}",
+            self.baseline,
+            divergent.len(),
+            self.shuffled.len(),
+            divergent.join("\n")
+        );
+    }
+}
@@ -0,0 +1,455 @@
+//! Comparison of two recorded runs.
+//!
+//! This module provides the `TraceDiff` struct which aligns messages of two
+//! recorded traces (see [`tracing::record`]) and reports where the runs started
+//! to differ. It helps attributing a change of results, e.g. after flipping a
+//! single configuration flag, to the protocol decision which caused it, and
+//! telling it apart from a change of the random values the run drew.
+//!
+//! [`tracing::record`]: crate::global::tracing::record
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::{self, Display},
+    time::Duration,
+};
+
+use crate::{
+    Jiffies, ProcessId,
+    global::tracing::{RecordedDraw, RecordedMessage, RecordedTrace},
+};
+
+/// The earliest difference between two runs.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Divergence {
+    /// The message was sent in both runs, but to other receivers or at other times.
+    Changed {
+        baseline: RecordedMessage,
+        other: RecordedMessage,
+    },
+    /// The message was sent in the baseline run only.
+    Missing(RecordedMessage),
+    /// The message was sent in the other run only.
+    Extra(RecordedMessage),
+}
+
+impl Divergence {
+    fn at(&self) -> Jiffies {
+        match self {
+            Divergence::Changed { baseline, other } => baseline.sent_at.min(other.sent_at),
+            Divergence::Missing(message) | Divergence::Extra(message) => message.sent_at,
+        }
+    }
+}
+
+/// The earliest difference between random draws of two runs.
+///
+/// Draws are aligned by position in their random stream: the k-th draw of a
+/// stream in one run is matched with the k-th draw of the same stream in the
+/// other run.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DrawDivergence {
+    /// The same request (time, process and context) got other values: the
+    /// random stream itself differs, e.g. with another seed or distribution.
+    Value {
+        baseline: RecordedDraw,
+        other: RecordedDraw,
+    },
+    /// Another request at the same position of the stream: the runs asked for
+    /// different draws, so every later value of the stream is shifted.
+    Request {
+        baseline: RecordedDraw,
+        other: RecordedDraw,
+    },
+    /// The baseline run drew more values from the stream.
+    Missing(RecordedDraw),
+    /// The other run drew more values from the stream.
+    Extra(RecordedDraw),
+}
+
+impl DrawDivergence {
+    fn at(&self) -> Jiffies {
+        match self {
+            DrawDivergence::Value { baseline, other }
+            | DrawDivergence::Request { baseline, other } => baseline.at.min(other.at),
+            DrawDivergence::Missing(draw) | DrawDivergence::Extra(draw) => draw.at,
+        }
+    }
+}
+
+/// Why two runs diverged, see [`TraceDiff::cause`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DivergenceCause {
+    /// A request drew other values no later than the messages diverged: the
+    /// runs got different randomness for the same behavior.
+    Randomness,
+    /// The messages diverged while every request drew the same values, or the
+    /// runs made different requests first: the behavior itself differs.
+    Logic,
+}
+
+/// Messages of one type in both runs.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TypeSummary {
+    /// Messages sent in the baseline run.
+    pub baseline: usize,
+    /// Messages sent in the other run.
+    pub other: usize,
+    /// Mean time from sending to delivery in the baseline run.
+    pub baseline_latency: f64,
+    /// Mean time from sending to delivery in the other run.
+    pub other_latency: f64,
+}
+
+/// Differences between a baseline run and another run.
+///
+/// Messages are aligned by logical event rather than by [`MessageId`]: the k-th
+/// message of a type sent by a process in one run is matched with the k-th
+/// message of the same type sent by the same process in the other run. Ids
+/// shift as soon as runs diverge, logical events stay comparable downstream.
+///
+/// Matched messages differ if they were sent at different times, to other
+/// destinations or delivered to other receivers or at other times. The
+/// earliest such difference is the [`Divergence`] of the runs; everything
+/// after it is summarized by counts and mean latencies per message type.
+///
+/// Displays as a human readable report.
+///
+/// # Examples
+///
+/// ```rust
+/// use dscale::{SimulationBuilder, Jiffies, Distributions, LatencyDescription};
+/// use dscale::global::tracing::{self, RecordedTrace};
+/// use dscale::helpers::TraceDiff;
+///
+/// fn run(latency: Distributions) -> RecordedTrace {
+///     let mut simulation = SimulationBuilder::default()
+///         .add_pool::<Pinger>("pingers", 2)
+///         .latency_topology(&[LatencyDescription::WithinPool("pingers", latency)])
+///         .trace_messages(true)
+///         .time_budget(Jiffies(100))
+///         .seed(42)
+///         .build();
+///     simulation.run();
+///     tracing::record()
+/// }
+///
+/// let baseline = run(Distributions::Uniform(Jiffies(5), Jiffies(5)));
+/// let slower = run(Distributions::Uniform(Jiffies(10), Jiffies(10)));
+///
+/// let diff = TraceDiff::between(&baseline, &slower);
+/// assert!(!diff.is_identical());
+/// println!("{diff}");
+/// # #[derive(Default)]
+/// # struct Pinger;
+/// # struct Ping;
+/// # impl dscale::Message for Ping {}
+/// # impl dscale::ProcessHandle for Pinger {
+/// #     fn start(&mut self) { dscale::send_to(3 - dscale::rank(), Ping); }
+/// #     fn on_message(&mut self, from: dscale::ProcessId, message: dscale::MessagePtr) {
+/// #         dscale::send_to(from, Ping);
+/// #     }
+/// #     fn on_timer(&mut self, id: dscale::TimerId) {}
+/// # }
+/// ```
+///
+/// [`MessageId`]: crate::global::tracing::MessageId
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TraceDiff {
+    /// Messages matched in both runs without differences.
+    pub identical: usize,
+    /// Messages matched in both runs with differences.
+    pub changed: usize,
+    /// Messages of the baseline run without a match.
+    pub missing: usize,
+    /// Messages of the other run without a match.
+    pub extra: usize,
+    /// The earliest difference, `None` if the runs are identical.
+    pub first_divergence: Option<Divergence>,
+    /// Summary of both runs by message type.
+    pub by_type: BTreeMap<String, TypeSummary>,
+    /// The earliest difference of random draws, `None` if both runs drew the
+    /// same values for the same requests.
+    pub first_draw_divergence: Option<DrawDivergence>,
+    /// Real-world duration of one jiffy in the baseline run, if configured.
+    pub jiffy_duration: Option<Duration>,
+}
+
+type LogicalEvent = (ProcessId, String, usize); // Sender, type, occurrence
+
+impl TraceDiff {
+    /// Aligns messages of both runs and collects their differences.
+    pub fn between(baseline: &RecordedTrace, other: &RecordedTrace) -> Self {
+        let mut diff = Self {
+            jiffy_duration: baseline.jiffy_duration,
+            ..Self::default()
+        };
+        let mut unmatched = logical_events(other);
+
+        logical_events(baseline)
+            .into_iter()
+            .for_each(|(event, message)| match unmatched.remove(&event) {
+                Some(counterpart) if same_outcome(message, counterpart) => diff.identical += 1,
+                Some(counterpart) => {
+                    diff.changed += 1;
+                    diff.diverge(Divergence::Changed {
+                        baseline: message.clone(),
+                        other: counterpart.clone(),
+                    });
+                }
+                None => {
+                    diff.missing += 1;
+                    diff.diverge(Divergence::Missing(message.clone()));
+                }
+            });
+        unmatched.into_values().for_each(|message| {
+            diff.extra += 1;
+            diff.diverge(Divergence::Extra(message.clone()));
+        });
+
+        diff.first_draw_divergence = first_draw_divergence(baseline, other);
+        diff.summarize(
+            baseline,
+            |summary| &mut summary.baseline,
+            |summary| &mut summary.baseline_latency,
+        );
+        diff.summarize(
+            other,
+            |summary| &mut summary.other,
+            |summary| &mut summary.other_latency,
+        );
+        diff
+    }
+
+    /// Whether both runs sent and delivered the same messages at the same times.
+    pub fn is_identical(&self) -> bool {
+        self.first_divergence.is_none()
+    }
+
+    /// Whether the runs diverged because of their random draws or their
+    /// logic, `None` if they are identical.
+    ///
+    /// Both recordings need their draws (see [`tracing::record`]); without
+    /// draws every divergence is attributed to logic.
+    ///
+    /// [`tracing::record`]: crate::global::tracing::record
+    pub fn cause(&self) -> Option<DivergenceCause> {
+        let first = self.first_divergence.as_ref()?;
+        match &self.first_draw_divergence {
+            Some(draw @ DrawDivergence::Value { .. }) if draw.at() <= first.at() => {
+                Some(DivergenceCause::Randomness)
+            }
+            _ => Some(DivergenceCause::Logic),
+        }
+    }
+}
+
+impl TraceDiff {
+    // Earliest in time, the first one found among simultaneous ones
+    fn diverge(&mut self, divergence: Divergence) {
+        let earlier = match &self.first_divergence {
+            None => true,
+            Some(first) => divergence.at() < first.at(),
+        };
+        if earlier {
+            self.first_divergence = Some(divergence);
+        }
+    }
+
+    fn summarize(
+        &mut self,
+        trace: &RecordedTrace,
+        count: fn(&mut TypeSummary) -> &mut usize,
+        latency: fn(&mut TypeSummary) -> &mut f64,
+    ) {
+        let mut latencies: HashMap<&str, (usize, usize)> = HashMap::new(); // Sum, deliveries
+        trace.messages.iter().for_each(|message| {
+            *count(self.by_type.entry(message.type_name.clone()).or_default()) += 1;
+            let entry = latencies.entry(&message.type_name).or_default();
+            message.deliveries.iter().for_each(|(_, at)| {
+                entry.0 += (*at - message.sent_at).0;
+                entry.1 += 1;
+            });
+        });
+        latencies
+            .into_iter()
+            .filter(|(_, (_, deliveries))| *deliveries > 0)
+            .for_each(|(type_name, (sum, deliveries))| {
+                *latency(self.by_type.get_mut(type_name).expect("Counted above")) =
+                    sum as f64 / deliveries as f64;
+            });
+    }
+}
+
+fn logical_events(trace: &RecordedTrace) -> BTreeMap<LogicalEvent, &RecordedMessage> {
+    let mut occurrences: HashMap<(ProcessId, &str), usize> = HashMap::new();
+    trace
+        .messages
+        .iter()
+        .map(|message| {
+            let occurrence = occurrences
+                .entry((message.from, &message.type_name))
+                .or_default();
+            *occurrence += 1;
+            (
+                (message.from, message.type_name.clone(), *occurrence),
+                message,
+            )
+        })
+        .collect()
+}
+
+// Earliest in time among streams, the first one found among simultaneous ones
+fn first_draw_divergence(
+    baseline: &RecordedTrace,
+    other: &RecordedTrace,
+) -> Option<DrawDivergence> {
+    let baseline = streams(baseline);
+    let mut other = streams(other);
+    let mut divergences: Vec<DrawDivergence> = baseline
+        .into_iter()
+        .filter_map(|(stream, draws)| {
+            stream_divergence(&draws, &other.remove(stream).unwrap_or_default())
+        })
+        .collect();
+    divergences.extend(
+        other
+            .into_values()
+            .filter_map(|draws| stream_divergence(&[], &draws)),
+    );
+    divergences.into_iter().reduce(|first, divergence| {
+        if divergence.at() < first.at() {
+            divergence
+        } else {
+            first
+        }
+    })
+}
+
+fn streams(trace: &RecordedTrace) -> BTreeMap<&str, Vec<&RecordedDraw>> {
+    let mut streams: BTreeMap<&str, Vec<&RecordedDraw>> = BTreeMap::new();
+    trace
+        .draws
+        .iter()
+        .for_each(|draw| streams.entry(&draw.stream).or_default().push(draw));
+    streams
+}
+
+fn stream_divergence(
+    baseline: &[&RecordedDraw],
+    other: &[&RecordedDraw],
+) -> Option<DrawDivergence> {
+    let same_request = |a: &RecordedDraw, b: &RecordedDraw| {
+        (a.at, a.process, &a.context) == (b.at, b.process, &b.context)
+    };
+    let matched = baseline.iter().zip(other).find_map(|(baseline, other)| {
+        let (baseline, other) = ((*baseline).clone(), (*other).clone());
+        if !same_request(&baseline, &other) {
+            Some(DrawDivergence::Request { baseline, other })
+        } else if baseline.values != other.values {
+            Some(DrawDivergence::Value { baseline, other })
+        } else {
+            None
+        }
+    });
+    let common = baseline.len().min(other.len());
+    matched
+        .or_else(|| {
+            baseline
+                .get(common)
+                .map(|draw| DrawDivergence::Missing((*draw).clone()))
+        })
+        .or_else(|| {
+            other
+                .get(common)
+                .map(|draw| DrawDivergence::Extra((*draw).clone()))
+        })
+}
+
+fn same_outcome(a: &RecordedMessage, b: &RecordedMessage) -> bool {
+    a.destination == b.destination && a.sent_at == b.sent_at && a.deliveries == b.deliveries
+}
+
+impl Display for TraceDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some(first) = &self.first_divergence else {
+            return writeln!(f, "Runs are identical: {} messages", self.identical);
+        };
+
+        writeln!(
+            f,
+            "First divergence at {}:",
+            first.at().display_in(self.jiffy_duration)
+        )?;
+        match first {
+            Divergence::Changed { baseline, other } => {
+                writeln!(f, "  baseline: {}", describe(baseline))?;
+                writeln!(f, "  other:    {}", describe(other))?;
+            }
+            Divergence::Missing(message) => {
+                writeln!(f, "  only in baseline: {}", describe(message))?
+            }
+            Divergence::Extra(message) => writeln!(f, "  only in other: {}", describe(message))?,
+        }
+        match &self.first_draw_divergence {
+            Some(draw) if draw.at() <= first.at() => {
+                let cause = match self.cause() {
+                    Some(DivergenceCause::Randomness) => "randomness",
+                    _ => "logic",
+                };
+                writeln!(f, "Caused by {cause}, first differing draw:")?;
+                describe_draw(f, draw)?;
+            }
+            _ => writeln!(f, "Caused by logic, draws were the same until then")?,
+        }
+        writeln!(
+            f,
+            "Messages: {} identical, {} changed, {} only in baseline, {} only in other",
+            self.identical, self.changed, self.missing, self.extra
+        )?;
+        self.by_type.iter().try_for_each(|(type_name, summary)| {
+            writeln!(
+                f,
+                "  {type_name}: sent {} -> {}, mean latency {:.1} -> {:.1}",
+                summary.baseline, summary.other, summary.baseline_latency, summary.other_latency
+            )
+        })
+    }
+}
+
+fn describe_draw(f: &mut fmt::Formatter<'_>, divergence: &DrawDivergence) -> fmt::Result {
+    match divergence {
+        DrawDivergence::Value { baseline, other } | DrawDivergence::Request { baseline, other } => {
+            writeln!(f, "  baseline: {}", draw(baseline))?;
+            writeln!(f, "  other:    {}", draw(other))
+        }
+        DrawDivergence::Missing(missing) => writeln!(f, "  only in baseline: {}", draw(missing)),
+        DrawDivergence::Extra(extra) => writeln!(f, "  only in other: {}", draw(extra)),
+    }
+}
+
+fn draw(draw: &RecordedDraw) -> String {
+    let process = draw
+        .process
+        .map_or(String::new(), |process| format!(" P{process}"));
+    format!(
+        "{}{process} {} at {}: {:?}",
+        draw.stream, draw.context, draw.at, draw.values
+    )
+}
+
+fn describe(message: &RecordedMessage) -> String {
+    let deliveries: Vec<String> = message
+        .deliveries
+        .iter()
+        .map(|(to, at)| format!("P{to} at {at}"))
+        .collect();
+    format!(
+        "{} P{} -> {} sent at {}, delivered to [{}]",
+        message.type_name,
+        message.from,
+        message.destination,
+        message.sent_at,
+        deliveries.join(", ")
+    )
+}
@@ -0,0 +1,259 @@
+//! Estimation of message sizes from their contents.
+//!
+//! This module provides the `VirtualSize` trait and the [`impl_virtual_size!`]
+//! macro, so [`Message::virtual_size`] can be derived from message fields instead
+//! of being hard-coded. Sizes follow a compact binary encoding: fixed-size values
+//! take their memory size, collections and strings are prefixed with an 8 byte
+//! length and enums with a 1 byte tag.
+//!
+//! [`Message::virtual_size`]: crate::Message::virtual_size
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
+    rc::Rc,
+};
+
+use crate::Jiffies;
+
+const LENGTH_PREFIX: usize = 8;
+
+/// Types whose size on the wire can be estimated.
+///
+/// Implemented for primitives, strings, common collections, tuples, arrays and
+/// smart pointers. Message types usually implement it with [`impl_virtual_size!`]
+/// and forward [`Message::virtual_size`] to [`virtual_size_of`].
+///
+/// # Examples
+///
+/// ```rust
+/// use dscale::{Message, impl_virtual_size};
+/// use dscale::helpers::virtual_size_of;
+///
+/// struct Batch {
+///     round: u32,
+///     transactions: Vec<u64>,
+/// }
+///
+/// impl_virtual_size!(struct Batch { round, transactions });
+///
+/// impl Message for Batch {
+///     fn virtual_size(&self) -> usize {
+///         virtual_size_of(self)
+///     }
+/// }
+///
+/// let batch = Batch { round: 1, transactions: vec![1, 2, 3] };
+/// assert_eq!(batch.virtual_size(), 4 + 8 + 3 * 8);
+/// ```
+///
+/// [`Message::virtual_size`]: crate::Message::virtual_size
+pub trait VirtualSize {
+    /// Returns the estimated size of the value in bytes.
+    fn estimated_size(&self) -> usize;
+}
+
+/// Returns the estimated size of `value` in bytes.
+///
+/// # Examples
+///
+/// ```rust
+/// use dscale::helpers::virtual_size_of;
+///
+/// assert_eq!(virtual_size_of(&42u64), 8);
+/// assert_eq!(virtual_size_of(&(1u32, Some(2u8))), 4 + 1 + 1);
+/// assert_eq!(virtual_size_of("hello"), 8 + 5);
+/// ```
+pub fn virtual_size_of<T: VirtualSize + ?Sized>(value: &T) -> usize {
+    value.estimated_size()
+}
+
+/// Implements [`VirtualSize`] for a struct or an enum by summing sizes of fields.
+///
+/// Structs list the fields to account for; omitted fields are not sent over
+/// the network (for example, local bookkeeping). Enums list their variants
+/// with bindings for tuple fields; unit variants are listed by name only.
+/// Enums with struct-like variants need a manual implementation.
+///
+/// # Examples
+///
+/// ```rust
+/// use dscale::impl_virtual_size;
+/// use dscale::helpers::virtual_size_of;
+///
+/// struct Vote {
+///     round: usize,
+///     signature: [u8; 64],
+/// }
+///
+/// enum Request {
+///     Put(usize, String),
+///     Get(usize),
+///     Ping,
+/// }
+///
+/// impl_virtual_size!(struct Vote { round, signature });
+/// impl_virtual_size!(enum Request { Put(key, value), Get(key), Ping });
+///
+/// assert_eq!(virtual_size_of(&Vote { round: 1, signature: [0; 64] }), 72);
+/// assert_eq!(virtual_size_of(&Request::Put(1, "v".to_string())), 1 + 8 + 8 + 1);
+/// assert_eq!(virtual_size_of(&Request::Ping), 1);
+/// ```
+///
+/// [`VirtualSize`]: crate::helpers::VirtualSize
+#[macro_export]
+macro_rules! impl_virtual_size {
+    (struct $type:ty { $($field:tt),* $(,)? }) => {
+        impl $crate::helpers::VirtualSize for $type {
+            fn estimated_size(&self) -> usize {
+                0 $(+ $crate::helpers::virtual_size_of(&self.$field))*
+            }
+        }
+    };
+    (enum $type:ty { $($variant:ident $(($($binding:ident),* $(,)?))?),* $(,)? }) => {
+        impl $crate::helpers::VirtualSize for $type {
+            #[allow(unused_variables)]
+            fn estimated_size(&self) -> usize {
+                1 + match self {
+                    $(Self::$variant $(($($binding),*))? => {
+                        0 $($(+ $crate::helpers::virtual_size_of($binding))*)?
+                    })*
+                }
+            }
+        }
+    };
+}
+
+macro_rules! fixed_size {
+    ($($type:ty),*) => {
+        $(impl VirtualSize for $type {
+            fn estimated_size(&self) -> usize {
+                size_of::<$type>()
+            }
+        })*
+    };
+}
+
+fixed_size!(u8, u16, u32, u64, u128, usize);
+fixed_size!(i8, i16, i32, i64, i128, isize);
+fixed_size!(f32, f64, bool, char, ());
+
+impl VirtualSize for Jiffies {
+    fn estimated_size(&self) -> usize {
+        size_of::<u64>()
+    }
+}
+
+impl VirtualSize for str {
+    fn estimated_size(&self) -> usize {
+        LENGTH_PREFIX + self.len()
+    }
+}
+
+impl VirtualSize for String {
+    fn estimated_size(&self) -> usize {
+        self.as_str().estimated_size()
+    }
+}
+
+impl<T: VirtualSize> VirtualSize for Option<T> {
+    fn estimated_size(&self) -> usize {
+        1 + self.as_ref().map_or(0, T::estimated_size)
+    }
+}
+
+impl<T: VirtualSize + ?Sized> VirtualSize for &T {
+    fn estimated_size(&self) -> usize {
+        (**self).estimated_size()
+    }
+}
+
+impl<T: VirtualSize + ?Sized> VirtualSize for Box<T> {
+    fn estimated_size(&self) -> usize {
+        (**self).estimated_size()
+    }
+}
+
+impl<T: VirtualSize + ?Sized> VirtualSize for Rc<T> {
+    fn estimated_size(&self) -> usize {
+        (**self).estimated_size()
+    }
+}
+
+impl<T: VirtualSize, const N: usize> VirtualSize for [T; N] {
+    fn estimated_size(&self) -> usize {
+        self.iter().map(T::estimated_size).sum()
+    }
+}
+
+fn sequence<'a, T: VirtualSize + 'a>(items: impl Iterator<Item = &'a T>) -> usize {
+    LENGTH_PREFIX + items.map(T::estimated_size).sum::<usize>()
+}
+
+impl<T: VirtualSize> VirtualSize for [T] {
+    fn estimated_size(&self) -> usize {
+        sequence(self.iter())
+    }
+}
+
+impl<T: VirtualSize> VirtualSize for Vec<T> {
+    fn estimated_size(&self) -> usize {
+        sequence(self.iter())
+    }
+}
+
+impl<T: VirtualSize> VirtualSize for VecDeque<T> {
+    fn estimated_size(&self) -> usize {
+        sequence(self.iter())
+    }
+}
+
+impl<T: VirtualSize, S> VirtualSize for HashSet<T, S> {
+    fn estimated_size(&self) -> usize {
+        sequence(self.iter())
+    }
+}
+
+impl<T: VirtualSize> VirtualSize for BTreeSet<T> {
+    fn estimated_size(&self) -> usize {
+        sequence(self.iter())
+    }
+}
+
+impl<K: VirtualSize, V: VirtualSize, S> VirtualSize for HashMap<K, V, S> {
+    fn estimated_size(&self) -> usize {
+        LENGTH_PREFIX
+            + self
+                .iter()
+                .map(|(k, v)| k.estimated_size() + v.estimated_size())
+                .sum::<usize>()
+    }
+}
+
+impl<K: VirtualSize, V: VirtualSize> VirtualSize for BTreeMap<K, V> {
+    fn estimated_size(&self) -> usize {
+        LENGTH_PREFIX
+            + self
+                .iter()
+                .map(|(k, v)| k.estimated_size() + v.estimated_size())
+                .sum::<usize>()
+    }
+}
+
+macro_rules! tuple_size {
+    ($($name:ident),+) => {
+        impl<$($name: VirtualSize),+> VirtualSize for ($($name,)+) {
+            #[allow(non_snake_case)]
+            fn estimated_size(&self) -> usize {
+                let ($($name,)+) = self;
+                0 $(+ $name.estimated_size())+
+            }
+        }
+    };
+}
+
+tuple_size!(A);
+tuple_size!(A, B);
+tuple_size!(A, B, C);
+tuple_size!(A, B, C, D);
+tuple_size!(A, B, C, D, E);
+tuple_size!(A, B, C, D, E, F);
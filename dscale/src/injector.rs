@@ -0,0 +1,167 @@
+//! Injection of external events into a running simulation.
+//!
+//! An [`Injector`] lets the experiment driver, which lives outside of processes,
+//! schedule messages and configuration changes at given simulated times, e.g. a
+//! workload ramp or a reconfiguration controlled by the test rather than encoded
+//! inside a process.
+
+use std::{cell::RefCell, collections::BTreeMap, rc::Rc};
+
+use crate::{
+    Message, ProcessId,
+    actor::{SimulationActor, as_process},
+    global::{self, now},
+    nursery::Nursery,
+    time::Jiffies,
+};
+
+type Injection = Box<dyn FnOnce()>;
+
+// Injections of the same jiffy execute in the order they were made
+type Injections = Rc<RefCell<BTreeMap<(Jiffies, usize), Injection>>>;
+
+/// Handle for injecting events into a running simulation.
+///
+/// Obtained with [`Simulation::injector`] and cheap to clone. Events can be
+/// injected before the run, between [`step_until`] calls, or by other injected
+/// events. Every event executes at its time in the event loop of the
+/// simulation, ordered with messages and timers of the same jiffy by the tie
+/// breaker, and events of the same jiffy execute in the order they were
+/// injected.
+///
+/// # Examples
+///
+/// ```rust
+/// use dscale::{SimulationBuilder, Jiffies, global::anykv};
+/// # use dscale::{ProcessHandle, ProcessId, MessagePtr, TimerId, schedule_timer_after};
+/// # #[derive(Default)]
+/// # struct Client;
+/// # impl ProcessHandle for Client {
+/// #     fn start(&mut self) { schedule_timer_after(Jiffies(10)); }
+/// #     fn on_message(&mut self, from: ProcessId, message: MessagePtr) {}
+/// #     fn on_timer(&mut self, id: TimerId) {
+/// #         let rate = anykv::get::<usize>("rate");
+/// #         anykv::modify::<usize>("requests", |requests| *requests += rate);
+/// #         schedule_timer_after(Jiffies(10));
+/// #     }
+/// # }
+///
+/// anykv::set::<usize>("rate", 1);
+/// anykv::set::<usize>("requests", 0);
+///
+/// let mut simulation = SimulationBuilder::default()
+///     .add_pool::<Client>("clients", 1)
+///     .time_budget(Jiffies(1_000))
+///     .build();
+///
+/// // Clients read the rate every 10 jiffies, the driver doubles it at 100
+/// simulation.injector().at(Jiffies(95), || anykv::set::<usize>("rate", 2));
+///
+/// simulation.step_until(Jiffies(200));
+/// assert_eq!(anykv::get::<usize>("requests"), 9 + 2 * 11);
+/// ```
+///
+/// [`Simulation::injector`]: crate::Simulation::injector
+/// [`step_until`]: crate::Simulation::step_until
+#[derive(Clone)]
+pub struct Injector {
+    injections: Injections,
+    sequence: Rc<RefCell<usize>>,
+}
+
+impl Injector {
+    /// Executes `f` at simulated time `at`.
+    ///
+    /// `f` runs outside of any process. It may change configuration (e.g. values
+    /// in [`anykv`]), inject further events or act on behalf of a process with
+    /// [`as_process`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `at` is in the past.
+    ///
+    /// [`anykv`]: crate::global::anykv
+    /// [`as_process`]: crate::actor::as_process
+    pub fn at(&self, at: Jiffies, f: impl FnOnce() + 'static) {
+        assert!(at >= now(), "Can not inject an event into the past");
+        let mut sequence = self.sequence.borrow_mut();
+        self.injections
+            .borrow_mut()
+            .insert((at, *sequence), Box::new(f));
+        *sequence += 1;
+    }
+
+    /// Sends `message` from process `from` to process `to` at simulated time `at`.
+    ///
+    /// The message leaves `from` at `at` and travels through the network like any
+    /// other message of `from`, so it is subject to latency, bandwidth and
+    /// partitions. If `from` is crashed at that time, the message is not sent.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `at` is in the past.
+    pub fn send_at(
+        &self,
+        at: Jiffies,
+        from: ProcessId,
+        to: ProcessId,
+        message: impl Message + 'static,
+    ) {
+        self.at(at, move || {
+            if !global::is_crashed(from) {
+                as_process(from, || global::send_to(to, message));
+            }
+        });
+    }
+
+    /// Returns the number of injected events waiting for their time.
+    pub fn pending(&self) -> usize {
+        self.injections.borrow().len()
+    }
+}
+
+pub(crate) struct InjectorActor {
+    injections: Injections,
+    nursery: Rc<Nursery>,
+}
+
+impl InjectorActor {
+    pub(crate) fn new(nursery: Rc<Nursery>) -> (Self, Injector) {
+        let injections = Injections::default();
+        let injector = Injector {
+            injections: injections.clone(),
+            sequence: Rc::default(),
+        };
+        (
+            Self {
+                injections,
+                nursery,
+            },
+            injector,
+        )
+    }
+}
+
+impl SimulationActor for InjectorActor {
+    fn start(&mut self) {
+        // Do nothing
+    }
+
+    fn peek_closest(&self) -> Option<Jiffies> {
+        self.injections
+            .borrow()
+            .first_key_value()
+            .map(|((at, _), _)| *at)
+    }
+
+    fn step(&mut self) {
+        // Released before execution, injections may inject more
+        let (_, injection) = self
+            .injections
+            .borrow_mut()
+            .pop_first()
+            .expect("Should not be empty");
+        self.nursery.observe(|| "Injected event".to_string());
+        injection();
+    }
+}
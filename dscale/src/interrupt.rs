@@ -0,0 +1,37 @@
+// Ctrl-C handling of the `interrupt` feature. The first Ctrl-C asks the running
+// simulation to stop after the current event, so it still reports what it has
+// got; the second one kills the process as usual in case the report hangs.
+
+use std::{
+    process::exit,
+    sync::{
+        Once,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use log::warn;
+
+static INSTALL: Once = Once::new();
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+
+// Takes over SIGINT for the rest of the process
+pub(crate) fn install() {
+    INSTALL.call_once(|| {
+        let installed = ctrlc::set_handler(|| {
+            if REQUESTED.swap(true, Ordering::Relaxed) {
+                exit(130);
+            }
+            eprintln!("\nInterrupted, stopping after the current event (Ctrl-C again to kill)");
+        });
+        if let Err(error) = installed {
+            warn!("Unable to install Ctrl-C handler: {error}");
+        }
+    });
+}
+
+// Stays set: later runs of the process stop right away too, so a batch of
+// simulations is cancelled as a whole
+pub(crate) fn requested() -> bool {
+    REQUESTED.load(Ordering::Relaxed)
+}
@@ -1,46 +1,109 @@
-mod actor;
+pub mod actor;
 mod alloc;
+pub mod artifacts;
+pub mod crypto;
 mod destination;
+mod digest;
 mod dscale_message;
 pub mod global;
 pub mod helpers;
+mod injector;
+#[cfg(feature = "interrupt")]
+mod interrupt;
 pub mod message;
+mod message_type;
 mod network;
 mod nursery;
+mod pending;
 mod process_handle;
+mod profile;
 mod progress;
 mod random;
+pub mod scenario;
 mod simulation;
 mod simulation_builder;
+pub mod stack;
 pub mod time;
 mod topology;
+pub mod transport;
+mod window;
+pub mod workload;
+
+pub use actor::EventSubmitter;
+pub use actor::SimulationActor;
 
 pub use message::Message;
 pub use message::MessagePtr;
 
 pub use process_handle::ProcessHandle;
 pub use process_handle::ProcessId;
+pub use profile::Profile;
 
+pub use digest::RunDigest;
+pub use pending::PendingEvents;
+pub use pending::PrunedEvents;
+pub use pending::QueueStats;
 pub use simulation::Simulation;
 pub use simulation_builder::SimulationBuilder;
 
+pub use injector::Injector;
+
+pub use global::disk::CrashTruncation;
+pub use global::disk::DiskDescription;
+pub use global::errors::ErrorPolicy;
+pub use global::errors::ProtocolError;
+pub use global::memory::MemoryLimit;
+pub use global::memory::MemoryPolicy;
+pub use global::memory::MemoryStats;
+
 pub use global::broadcast;
+pub use global::broadcast_after;
 pub use global::broadcast_within_pool;
 pub use global::choose_from_pool;
+pub use global::choose_nearest_from_pool;
+pub use global::clear_message_filter;
+pub use global::expected_latency;
 pub use global::global_unique_id;
 pub use global::list_pool;
+pub use global::list_reachable_pool;
 pub use global::now;
+pub use global::pool_member;
+pub use global::pool_of;
 pub use global::rank;
+pub use global::region_of;
+pub use global::schedule_named_timer;
 pub use global::schedule_timer_after;
 pub use global::send_random_from_pool;
 pub use global::send_to;
+pub use global::send_to_at;
+pub use global::send_to_pool_quorum;
+pub use global::set_message_filter;
+pub use global::timer_kind;
 
+pub use network::BackgroundTraffic;
+pub use network::Bandwidth;
 pub use network::BandwidthDescription;
+pub use network::ChannelOrdering;
+pub use network::CongestionNotification;
+pub use network::InboxDescription;
+pub use network::InboxStats;
+pub use network::LinkStats;
+pub use network::LinkUsage;
+pub use network::NetworkSchedule;
+pub use network::OverflowPolicy;
+pub use network::RegionLink;
+pub use network::RelayTree;
 
 pub use topology::GLOBAL_POOL;
 pub use topology::LatencyDescription;
 
 pub use random::Distributions;
+pub use random::TieBreak;
 
+pub use scenario::Scenario;
+
+pub use time::ClockSync;
 pub use time::Jiffies;
 pub use time::TimerId;
+
+pub use window::DebugWindow;
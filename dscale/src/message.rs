@@ -4,10 +4,35 @@
 //! processes in DScale simulations. It provides the `Message` trait that all
 //! message types must implement, as well as `MessagePtr` for type-safe message
 //! handling and routing infrastructure.
+//!
+//! # Thread Safety
+//!
+//! Messages are shared through [`Shared`], which is [`Rc`] by default. With the
+//! `sync` feature it becomes [`Arc`] and every [`Message`] must be [`Send`] and
+//! [`Sync`], so messages can cross threads once the engine executes in parallel.
+//! The feature only covers messages, the rest of the engine is still single
+//! threaded and keeps its `Rc` state:
+//!
+//! - Actors, process handles and transports live in `Rc<RefCell<..>>` and are
+//!   driven by the single event loop of [`Simulation`].
+//! - The topology, the nursery of processes and inbox statistics are `Rc` shared
+//!   between actors.
+//! - Per-process state of the `global` module (timers, filters, disks, memory
+//!   budgets, quorums) lives in thread locals of the simulating thread.
+//! - Causal pasts of messages on causal channels are `Rc` shared between copies
+//!   of a message within the network.
+//!
+//! [`Rc`]: std::rc::Rc
+//! [`Arc`]: std::sync::Arc
+//! [`Simulation`]: crate::Simulation
 
-use std::{any::Any, cmp::Reverse, collections::BinaryHeap, rc::Rc};
+use std::{
+    any::Any,
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+};
 
-use crate::{process_handle::ProcessId, time::Jiffies};
+use crate::{message_type::MessageType, process_handle::ProcessId, time::Jiffies};
 
 /// Core trait for all message types in DScale simulations.
 ///
@@ -125,7 +150,7 @@ use crate::{process_handle::ProcessId, time::Jiffies};
 /// [`virtual_size`]: Message::virtual_size
 /// [`MessagePtr`]: MessagePtr
 /// [`ProcessHandle::on_message`]: crate::ProcessHandle::on_message
-pub trait Message: Any {
+pub trait Message: Any + ThreadSafety {
     /// Returns the virtual size of this message in bytes for bandwidth simulation.
     ///
     /// This method defines how large the message appears to the network simulation
@@ -196,8 +221,77 @@ pub trait Message: Any {
     fn virtual_size(&self) -> usize {
         usize::default()
     }
+
+    /// Returns the name of the message type shown in debugging output,
+    /// such as the events logged in a [`DebugWindow`].
+    ///
+    /// The default implementation returns the type name without its module path
+    /// and rarely needs to be overridden.
+    ///
+    /// [`DebugWindow`]: crate::DebugWindow
+    fn type_name(&self) -> &'static str {
+        let name = std::any::type_name::<Self>();
+        name.rsplit("::").next().unwrap_or(name)
+    }
 }
 
+/// Reference-counted pointer sharing messages: [`Rc`] by default, [`Arc`] with the `sync` feature.
+///
+/// Both expose the same API, so code naming `Shared` instead of either pointer
+/// compiles in both modes.
+///
+/// [`Rc`]: std::rc::Rc
+/// [`Arc`]: std::sync::Arc
+#[cfg(not(feature = "sync"))]
+pub type Shared<T> = std::rc::Rc<T>;
+
+/// Reference-counted pointer sharing messages: [`Rc`] by default, [`Arc`] with the `sync` feature.
+///
+/// Both expose the same API, so code naming `Shared` instead of either pointer
+/// compiles in both modes.
+///
+/// [`Rc`]: std::rc::Rc
+/// [`Arc`]: std::sync::Arc
+#[cfg(feature = "sync")]
+pub type Shared<T> = std::sync::Arc<T>;
+
+/// Weak counterpart of [`Shared`]: [`rc::Weak`] by default, [`sync::Weak`] with the `sync` feature.
+///
+/// [`rc::Weak`]: std::rc::Weak
+/// [`sync::Weak`]: std::sync::Weak
+#[cfg(not(feature = "sync"))]
+pub type WeakShared<T> = std::rc::Weak<T>;
+
+/// Weak counterpart of [`Shared`]: [`rc::Weak`] by default, [`sync::Weak`] with the `sync` feature.
+///
+/// [`rc::Weak`]: std::rc::Weak
+/// [`sync::Weak`]: std::sync::Weak
+#[cfg(feature = "sync")]
+pub type WeakShared<T> = std::sync::Weak<T>;
+
+/// Type-erased message shared between its recipients.
+pub type SharedMessage = Shared<dyn Message>;
+
+#[cfg(not(feature = "sync"))]
+type SharedAny = Shared<dyn Any>;
+
+#[cfg(feature = "sync")]
+type SharedAny = Shared<dyn Any + Send + Sync>;
+
+/// Bounds messages need beyond [`Any`]: none by default, [`Send`] and [`Sync`] with the `sync` feature.
+#[cfg(not(feature = "sync"))]
+pub trait ThreadSafety {}
+
+#[cfg(not(feature = "sync"))]
+impl<T: ?Sized> ThreadSafety for T {}
+
+/// Bounds messages need beyond [`Any`]: none by default, [`Send`] and [`Sync`] with the `sync` feature.
+#[cfg(feature = "sync")]
+pub trait ThreadSafety: Send + Sync {}
+
+#[cfg(feature = "sync")]
+impl<T: Send + Sync + ?Sized> ThreadSafety for T {}
+
 /// A smart pointer for type-safe message handling in DScale simulations.
 ///
 /// `MessagePtr` is a reference-counted smart pointer that wraps message objects
@@ -281,13 +375,13 @@ pub trait Message: Any {
 /// ```
 ///
 /// [`ProcessHandle::on_message`]: crate::ProcessHandle::on_message
-pub struct MessagePtr(pub Rc<dyn Message>);
+pub struct MessagePtr(pub SharedMessage);
 
 impl MessagePtr {
     /// Attempts to safely cast the message to a specific type.
     ///
     /// This method provides safe runtime type checking and casting for messages.
-    /// It returns `Some(Rc<T>)` if the message is of the requested type `T`,
+    /// It returns `Some(Shared<T>)` if the message is of the requested type `T`,
     /// or `None` if the cast fails. This is the recommended way to handle
     /// messages as it cannot panic.
     ///
@@ -297,7 +391,7 @@ impl MessagePtr {
     ///
     /// # Returns
     ///
-    /// * `Some(Rc<T>)` - If the message is of type `T`
+    /// * `Some(Shared<T>)` - If the message is of type `T`
     /// * `None` - If the message is not of type `T`
     ///
     /// # Examples
@@ -323,8 +417,8 @@ impl MessagePtr {
     ///     }
     /// }
     /// ```
-    pub fn try_as<T: 'static>(&self) -> Option<Rc<T>> {
-        match (self.0.clone() as Rc<dyn Any>).downcast::<T>() {
+    pub fn try_as<T: Any + ThreadSafety>(&self) -> Option<Shared<T>> {
+        match (self.0.clone() as SharedAny).downcast::<T>() {
             Err(_) => None,
             Ok(m) => Some(m),
         }
@@ -367,7 +461,7 @@ impl MessagePtr {
     /// }
     /// ```
     pub fn is<T: 'static>(&self) -> bool {
-        (self.0.clone() as Rc<dyn Any>).is::<T>()
+        (self.0.clone() as SharedAny).is::<T>()
     }
 
     /// Casts the message to a specific type, panicking if the cast fails.
@@ -385,7 +479,7 @@ impl MessagePtr {
     ///
     /// # Returns
     ///
-    /// `Rc<T>` - The message cast to the target type.
+    /// `Shared<T>` - The message cast to the target type.
     ///
     /// # Panics
     ///
@@ -414,8 +508,8 @@ impl MessagePtr {
     ///
     /// [`is`]: MessagePtr::is
     /// [`try_as`]: MessagePtr::try_as
-    pub fn as_type<T: 'static>(self) -> Rc<T> {
-        (self.0 as Rc<dyn Any>).downcast::<T>().unwrap()
+    pub fn as_type<T: Any + ThreadSafety>(self) -> Shared<T> {
+        (self.0 as SharedAny).downcast::<T>().unwrap()
     }
 }
 
@@ -423,18 +517,30 @@ impl MessagePtr {
 pub struct ProcessStep {
     pub(crate) source: ProcessId,
     pub(crate) dest: ProcessId,
-    pub(crate) message: Rc<dyn Message>,
+    pub(crate) message: SharedMessage,
+    pub(crate) message_type: MessageType,
+    pub(crate) size: usize, // Virtual size, computed once per sent message
+    pub(crate) causal_past: Option<std::rc::Rc<HashMap<ProcessId, Jiffies>>>, // Only with causal channels
+    pub(crate) relay: Option<crate::network::Relay>, // Hop of a broadcast over a relay tree
 }
 
 #[derive(Clone)]
 pub struct RoutedMessage {
     pub(crate) arrival_time: Jiffies,
+    pub(crate) tie: u64, // Orders messages arriving at the same time, see TieBreak
+    pub(crate) seq: u64, // Submission order, orders messages with the same tie
     pub(crate) step: ProcessStep,
 }
 
+impl RoutedMessage {
+    fn key(&self) -> (Jiffies, u64, u64) {
+        (self.arrival_time, self.tie, self.seq)
+    }
+}
+
 impl PartialEq for RoutedMessage {
     fn eq(&self, other: &Self) -> bool {
-        self.arrival_time.eq(&other.arrival_time)
+        self.key().eq(&other.key())
     }
 }
 
@@ -442,25 +548,25 @@ impl Eq for RoutedMessage {}
 
 impl PartialOrd for RoutedMessage {
     fn ge(&self, other: &Self) -> bool {
-        self.arrival_time.ge(&other.arrival_time)
+        self.key().ge(&other.key())
     }
     fn le(&self, other: &Self) -> bool {
-        self.arrival_time.le(&other.arrival_time)
+        self.key().le(&other.key())
     }
     fn gt(&self, other: &Self) -> bool {
-        self.arrival_time.gt(&other.arrival_time)
+        self.key().gt(&other.key())
     }
     fn lt(&self, other: &Self) -> bool {
-        self.arrival_time.lt(&other.arrival_time)
+        self.key().lt(&other.key())
     }
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        self.arrival_time.partial_cmp(&other.arrival_time)
+        self.key().partial_cmp(&other.key())
     }
 }
 
 impl Ord for RoutedMessage {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.arrival_time.cmp(&other.arrival_time)
+        self.key().cmp(&other.key())
     }
 }
 
@@ -0,0 +1,59 @@
+// Metadata of message types, computed once per type and carried by every routed
+// message, so the network, tracing and debugging output do not repeat downcasts
+// and name lookups per delivery.
+
+use std::{
+    any::{Any, TypeId},
+    cell::RefCell,
+    collections::HashMap,
+};
+
+use crate::{
+    Message,
+    network::{CongestionNotification, traffic::CrossTraffic},
+};
+
+// Messages the engine sends itself are told apart from protocol messages
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum MessageKind {
+    Protocol,
+    CrossTraffic,
+    CongestionNotification,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct MessageType {
+    pub(crate) id: TypeId,
+    pub(crate) name: &'static str, // Message::type_name, assumed not to depend on the value
+    pub(crate) kind: MessageKind,
+}
+
+thread_local! {
+    static REGISTRY: RefCell<HashMap<TypeId, MessageType>> = RefCell::new(HashMap::new());
+}
+
+// Registers the type of the message on first use
+pub(crate) fn of(message: &dyn Message) -> MessageType {
+    let id = (message as &dyn Any).type_id();
+    REGISTRY.with_borrow_mut(|registry| {
+        *registry.entry(id).or_insert_with(|| MessageType {
+            id,
+            name: message.type_name(),
+            kind: kind_of(id),
+        })
+    })
+}
+
+fn kind_of(id: TypeId) -> MessageKind {
+    if id == TypeId::of::<CrossTraffic>() {
+        MessageKind::CrossTraffic
+    } else if id == TypeId::of::<CongestionNotification>() {
+        MessageKind::CongestionNotification
+    } else {
+        MessageKind::Protocol
+    }
+}
+
+pub(crate) fn drop_registry() {
+    REGISTRY.take();
+}
@@ -6,20 +6,28 @@
 //! interface limitations.
 
 use std::collections::BinaryHeap;
+use std::rc::Rc;
+use std::time::Duration;
 
 use log::debug;
 
 use crate::{
     message::{RoutedMessage, TimePriorityMessageQueue},
-    network::LatencyQueue,
+    network::{
+        CongestionMonitor, CongestionNotification, Inboxes, LatencyQueue, NetworkSchedule,
+        SharedInboxStats,
+    },
     now,
+    process_handle::ProcessId,
     time::Jiffies,
+    topology::Topology,
 };
 
 /// Describes bandwidth constraints for network interfaces in the simulation.
 ///
 /// `BandwidthDescription` defines how network bandwidth limitations are applied
-/// to each process in the simulation. Bandwidth constraints affect message
+/// to each host in the simulation (every process is a separate host unless
+/// colocated with [`SimulationBuilder::colocate`]). Bandwidth constraints affect message
 /// transmission rates and can create realistic network bottlenecks that impact
 /// the behavior of distributed systems.
 ///
@@ -43,7 +51,9 @@ use crate::{
 ///
 /// Bandwidth is measured in bytes per [`Jiffy`], where a Jiffy is the basic
 /// unit of simulation time. The actual real-world time represented by a Jiffy
-/// depends on your simulation's context and interpretation.
+/// depends on your simulation's context and interpretation. To configure
+/// bandwidth in real-world units instead, use [`BandwidthDescription::Rate`]
+/// together with [`SimulationBuilder::jiffy_duration`].
 ///
 /// # Examples
 ///
@@ -138,7 +148,9 @@ use crate::{
 ///
 /// [`Message::virtual_size`]: crate::Message::virtual_size
 /// [`Jiffy`]: crate::Jiffies
-#[derive(Clone, Copy)]
+/// [`SimulationBuilder::colocate`]: crate::SimulationBuilder::colocate
+/// [`SimulationBuilder::jiffy_duration`]: crate::SimulationBuilder::jiffy_duration
+#[derive(Clone, Copy, Debug)]
 pub enum BandwidthDescription {
     /// No bandwidth limitations - messages transmit instantly.
     ///
@@ -240,39 +252,186 @@ pub enum BandwidthDescription {
     /// // - LargeMessage with 2500 bytes: takes 3 jiffies (⌈2500/1000⌉)
     /// ```
     Bounded(usize), // Bytes per Jiffy
+
+    /// Limited bandwidth given in real-world units.
+    ///
+    /// The rate is converted into bytes per jiffy (rounded down) with the
+    /// duration of a jiffy configured by [`SimulationBuilder::jiffy_duration`],
+    /// after that it behaves exactly like [`BandwidthDescription::Bounded`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::time::Duration;
+    /// use dscale::{SimulationBuilder, Bandwidth, BandwidthDescription};
+    ///
+    /// let simulation = SimulationBuilder::default()
+    ///     .jiffy_duration(Duration::from_millis(1))
+    ///     .nic_bandwidth(BandwidthDescription::Rate(Bandwidth::mbps(100))) // 12_500 bytes per jiffy
+    ///     .build();
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Building the simulation panics if the jiffy duration is not configured or
+    /// if the rate is less than one byte per jiffy.
+    ///
+    /// [`SimulationBuilder::jiffy_duration`]: crate::SimulationBuilder::jiffy_duration
+    Rate(Bandwidth),
+}
+
+impl BandwidthDescription {
+    // Replaces a rate with the bytes it transmits per jiffy
+    pub(crate) fn resolve(self, jiffy: Option<Duration>) -> Self {
+        match self {
+            BandwidthDescription::Rate(rate) => {
+                let jiffy =
+                    jiffy.expect("Jiffy duration should be configured to use bandwidth rates");
+                let bytes = rate.bytes_per_jiffy(jiffy);
+                assert!(
+                    bytes > 0,
+                    "Bandwidth rate should be at least one byte per jiffy"
+                );
+                BandwidthDescription::Bounded(bytes)
+            }
+            bandwidth => bandwidth,
+        }
+    }
+}
+
+/// Network bandwidth in real-world units.
+///
+/// Rates use decimal prefixes as network equipment does: `Bandwidth::mbps(100)`
+/// is 100 000 000 bits per second.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::time::Duration;
+/// use dscale::Bandwidth;
+///
+/// let ethernet = Bandwidth::gbps(1);
+/// assert_eq!(ethernet.bytes_per_jiffy(Duration::from_millis(1)), 125_000);
+/// assert_eq!(Bandwidth::kbps(8).bytes_per_jiffy(Duration::from_secs(1)), 1_000);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Bandwidth {
+    bits_per_second: u64,
+}
+
+impl Bandwidth {
+    /// Bandwidth of `bits` per second.
+    pub const fn bps(bits: u64) -> Self {
+        Self {
+            bits_per_second: bits,
+        }
+    }
+
+    /// Bandwidth of `kilobits` per second.
+    pub const fn kbps(kilobits: u64) -> Self {
+        Self::bps(kilobits * 1_000)
+    }
+
+    /// Bandwidth of `megabits` per second.
+    pub const fn mbps(megabits: u64) -> Self {
+        Self::bps(megabits * 1_000_000)
+    }
+
+    /// Bandwidth of `gigabits` per second.
+    pub const fn gbps(gigabits: u64) -> Self {
+        Self::bps(gigabits * 1_000_000_000)
+    }
+
+    /// Bits transmitted per second.
+    pub fn bits_per_second(&self) -> u64 {
+        self.bits_per_second
+    }
+
+    /// Whole bytes transmitted during one jiffy lasting `jiffy`.
+    pub fn bytes_per_jiffy(&self, jiffy: Duration) -> usize {
+        (self.bits_per_second as u128 * jiffy.as_nanos() / (8 * 1_000_000_000)) as usize
+    }
 }
 
 pub(crate) struct BandwidthQueue {
     bandwidth: usize,
     global_queue: LatencyQueue,
     total_pased: Vec<usize>,
+    uplink_busy: Vec<Jiffies>, // Until when NICs send copies of relayed broadcasts
     merged_fifo_buffers: TimePriorityMessageQueue,
+    inboxes: Inboxes,
+    congestion: CongestionMonitor,
+    topology: Rc<Topology>,
 }
 
 impl BandwidthQueue {
     pub(crate) fn new(
         bandwidth_type: BandwidthDescription,
-        proc_num: usize,
+        inboxes: Inboxes,
         global_queue: LatencyQueue,
+        congestion_threshold: Option<usize>,
+        topology: Rc<Topology>,
     ) -> Self {
         let bandwidth = match bandwidth_type {
             BandwidthDescription::Unbounded => usize::MAX,
             BandwidthDescription::Bounded(bound) => bound,
+            BandwidthDescription::Rate(_) => {
+                unreachable!("Bandwidth rates are resolved while building the simulation")
+            }
         };
 
         Self {
             bandwidth,
             global_queue,
-            total_pased: vec![0; proc_num + 1],
+            total_pased: vec![0; inboxes.size() + 1],
+            uplink_busy: vec![Jiffies(0); inboxes.size() + 1],
             merged_fifo_buffers: BinaryHeap::new(),
+            congestion: CongestionMonitor::new(congestion_threshold, inboxes.size()),
+            inboxes,
+            topology,
         }
     }
 
+    pub(crate) fn inbox_stats(&self) -> SharedInboxStats {
+        self.inboxes.stats()
+    }
+
+    pub(crate) fn recorded_schedule(&self) -> NetworkSchedule {
+        self.global_queue.recorded_schedule()
+    }
+
+    // Notifications to echo back to senders of messages buffered at congested NICs
+    pub(crate) fn take_congestion_notifications(
+        &mut self,
+    ) -> Vec<(ProcessId, CongestionNotification)> {
+        self.congestion.take()
+    }
+
     pub(crate) fn push(&mut self, message: RoutedMessage) {
         debug!("Submitted message with base time: {}", message.arrival_time);
         self.global_queue.push(message);
     }
 
+    pub(crate) fn discard(&mut self, message: RoutedMessage) {
+        self.global_queue.discard(message);
+    }
+
+    pub(crate) fn on_deliver(&mut self, message: &RoutedMessage) {
+        self.global_queue.on_deliver(message);
+    }
+
+    // Copies sent along a relay tree leave the NIC of the sender one after
+    // another, returns when the copy of `size` bytes is out
+    pub(crate) fn upload(&mut self, source: ProcessId, dest: ProcessId, size: usize) -> Jiffies {
+        if self.bandwidth == usize::MAX || self.topology.same_host(source, dest) {
+            return now();
+        }
+        let nic = self.topology.nic_of(source);
+        let start = self.uplink_busy[nic].max(now());
+        self.uplink_busy[nic] = start + Jiffies(size.div_ceil(self.bandwidth));
+        self.uplink_busy[nic]
+    }
+
     pub(crate) fn pop(&mut self) -> Option<RoutedMessage> {
         let closest_arriving_message = self.global_queue.peek();
         let closest_squeezing_message = self.merged_fifo_buffers.peek();
@@ -291,6 +450,36 @@ impl BandwidthQueue {
         }
     }
 
+    // Removes undeliverable messages from the latency queue and the buffers,
+    // returns how many of them were not dropped from inboxes already
+    pub(crate) fn prune(&mut self, undeliverable: impl Fn(&RoutedMessage) -> bool) -> usize {
+        let mut pruned = self.global_queue.prune(&undeliverable);
+        let (buffered, dropped): (Vec<_>, Vec<_>) = std::mem::take(&mut self.merged_fifo_buffers)
+            .into_iter()
+            .partition(|message| !undeliverable(&message.0));
+        self.merged_fifo_buffers = buffered.into();
+        dropped.into_iter().for_each(|message| {
+            if self.uses_nic(&message.0) {
+                self.congestion
+                    .dequeue(self.topology.nic_of(message.0.step.dest));
+            }
+            if self.inboxes.release(&message.0) {
+                pruned += 1;
+            }
+        });
+        pruned
+    }
+
+    // Messages in the latency queue and in the buffers, except dropped from inboxes
+    pub(crate) fn in_flight(&self) -> impl Iterator<Item = &RoutedMessage> {
+        self.global_queue.iter().chain(
+            self.merged_fifo_buffers
+                .iter()
+                .map(|message| &message.0)
+                .filter(|message| !self.inboxes.is_cancelled(message)),
+        )
+    }
+
     pub(crate) fn peek_closest(&self) -> Option<Jiffies> {
         let closest_arriving_message = self.global_queue.peek();
         let closest_squeezing_message = self.merged_fifo_buffers.peek();
@@ -318,11 +507,26 @@ impl BandwidthQueue {
             .pop()
             .expect("Global queue should not be empty");
 
-        // Only for bounded bandwidth - unbounded case is handled directly in deliver_from_latency_queue
-        let new_total = self.total_pased[message.step.dest] + message.step.message.virtual_size();
+        if !self.inboxes.admit(&message) {
+            debug!("Message dropped by inbox of P{}", message.step.dest);
+            return;
+        }
+
+        // Messages within a host do not go through its NIC
+        if self.uses_nic(&message) {
+            // Only for bounded bandwidth - unbounded case is handled directly in deliver_from_latency_queue
+            let nic = self.topology.nic_of(message.step.dest);
+            let new_total = self.total_pased[nic] + message.step.size;
 
-        if new_total > now().0 * self.bandwidth {
-            message.arrival_time = Jiffies(new_total / self.bandwidth); // > now()
+            if new_total > now().0 * self.bandwidth {
+                message.arrival_time = Jiffies(new_total / self.bandwidth); // > now()
+            }
+            self.congestion.enqueue(
+                nic,
+                message.step.source,
+                message.step.dest,
+                message.step.message_type.kind,
+            );
         }
 
         self.merged_fifo_buffers.push(std::cmp::Reverse(message));
@@ -334,10 +538,32 @@ impl BandwidthQueue {
             .pop()
             .expect("All buffers should not be empty")
             .0;
-        self.total_pased[message.step.dest] += message.step.message.virtual_size();
+
+        if self.uses_nic(&message) {
+            self.congestion
+                .dequeue(self.topology.nic_of(message.step.dest));
+        }
+
+        if !self.inboxes.release(&message) {
+            debug!(
+                "Skipping message dropped from inbox of P{}",
+                message.step.dest
+            );
+            return None;
+        }
+
+        if self.uses_nic(&message) {
+            self.total_pased[self.topology.nic_of(message.step.dest)] += message.step.size;
+        }
         Some(message)
     }
 
+    fn uses_nic(&self, message: &RoutedMessage) -> bool {
+        !self
+            .topology
+            .same_host(message.step.source, message.step.dest)
+    }
+
     fn deliver_from_latency_queue(&mut self) -> Option<RoutedMessage> {
         if self.bandwidth == usize::MAX {
             // For unbounded bandwidth, deliver directly from latency queue
@@ -0,0 +1,118 @@
+//! Ordering guarantees of channels between processes.
+//!
+//! Latencies are drawn per message, so messages may overtake each other. For
+//! channels configured as FIFO or causal, this module postpones arrivals which
+//! would violate the ordering: a message arrives strictly after the last one on
+//! its channel, or after everything its sender has causally seen being sent to
+//! the same destination.
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::{ProcessId, message::RoutedMessage, time::Jiffies};
+
+/// Ordering guarantees of channels between processes.
+///
+/// Latency of every message is drawn independently, so by default messages
+/// overtake each other freely. Stronger orderings delay messages that would
+/// otherwise arrive too early. Orderings are configured per pair of pools with
+/// [`SimulationBuilder::channel_ordering`], which makes it easy to check whether
+/// a protocol silently depends on FIFO channels.
+///
+/// # Examples
+///
+/// ```rust
+/// use dscale::{SimulationBuilder, ChannelOrdering};
+///
+/// let builder = SimulationBuilder::default()
+///     .add_pool::<MyProcess>("replicas", 3)
+///     .add_pool::<MyProcess>("clients", 2)
+///     .channel_ordering("replicas", "replicas", ChannelOrdering::Fifo)
+///     .channel_ordering("clients", "replicas", ChannelOrdering::Causal);
+/// # #[derive(Default)]
+/// # struct MyProcess;
+/// # impl dscale::ProcessHandle for MyProcess {
+/// #     fn start(&mut self) {}
+/// #     fn on_message(&mut self, from: dscale::ProcessId, message: dscale::MessagePtr) {}
+/// #     fn on_timer(&mut self, id: dscale::TimerId) {}
+/// # }
+/// ```
+///
+/// [`SimulationBuilder::channel_ordering`]: crate::SimulationBuilder::channel_ordering
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ChannelOrdering {
+    /// Messages may be delivered in any order (default).
+    #[default]
+    Random,
+
+    /// Messages from one process to another are delivered in the order they
+    /// were sent. A message never arrives together with or before a message
+    /// sent earlier on the same channel.
+    Fifo,
+
+    /// Messages are delivered after every message to the same destination
+    /// that causally precedes them, including messages sent by other
+    /// processes. Implies FIFO.
+    Causal,
+}
+
+pub(crate) type ChannelOrderings = HashMap<(ProcessId, ProcessId), ChannelOrdering>;
+
+// Latest arrival time to each destination known to a process
+type CausalPast = Rc<HashMap<ProcessId, Jiffies>>;
+
+pub(crate) struct Channels {
+    orderings: ChannelOrderings,
+    last_arrival: HashMap<(ProcessId, ProcessId), Jiffies>,
+    track_causality: bool,
+    causal_past: HashMap<ProcessId, CausalPast>,
+}
+
+impl Channels {
+    pub(crate) fn new(orderings: ChannelOrderings) -> Self {
+        Self {
+            track_causality: orderings.values().any(|o| *o == ChannelOrdering::Causal),
+            orderings,
+            last_arrival: HashMap::new(),
+            causal_past: HashMap::new(),
+        }
+    }
+
+    // Called once message latency is known
+    pub(crate) fn order(&mut self, message: &mut RoutedMessage) {
+        let channel = (message.step.source, message.step.dest);
+        match self.orderings.get(&channel).copied().unwrap_or_default() {
+            ChannelOrdering::Random => {}
+            ChannelOrdering::Fifo => {
+                if let Some(last) = self.last_arrival.get(&channel) {
+                    message.arrival_time = message.arrival_time.max(*last + Jiffies(1));
+                }
+                self.last_arrival.insert(channel, message.arrival_time);
+            }
+            ChannelOrdering::Causal => {
+                let past = self.causal_past.entry(channel.0).or_default();
+                if let Some(last) = past.get(&channel.1) {
+                    message.arrival_time = message.arrival_time.max(*last + Jiffies(1));
+                }
+            }
+        }
+
+        if self.track_causality {
+            let past = self.causal_past.entry(channel.0).or_default();
+            let known = Rc::make_mut(past).entry(channel.1).or_default();
+            *known = (*known).max(message.arrival_time);
+            message.step.causal_past = Some(past.clone());
+        }
+    }
+
+    pub(crate) fn on_deliver(&mut self, message: &RoutedMessage) {
+        let Some(sender_past) = &message.step.causal_past else {
+            return;
+        };
+        let past = Rc::make_mut(self.causal_past.entry(message.step.dest).or_default());
+        sender_past.iter().for_each(|(dest, arrival)| {
+            let known = past.entry(*dest).or_default();
+            *known = (*known).max(*arrival);
+        });
+    }
+}
@@ -0,0 +1,154 @@
+//! Explicit congestion notification of senders.
+//!
+//! With bounded bandwidth, messages arriving faster than a NIC can receive them
+//! wait in its buffer. Once the buffer of a receiving NIC holds a configured
+//! number of messages, senders of further messages are notified, so protocols
+//! can pace themselves (e.g. batch more, propose less often) before inboxes
+//! overflow.
+
+use std::collections::BTreeSet;
+
+use crate::{Message, ProcessId, message_type::MessageKind};
+
+/// Notification that a message of the receiving process was sent to a congested NIC.
+///
+/// Enabled with [`SimulationBuilder::congestion_notification`]. Once the buffer
+/// of messages waiting for the bandwidth of a receiving NIC reaches the
+/// threshold, the network echoes a notification back to the sender of every
+/// newly buffered message, like ECN marks echoed by a receiver. Every sender is
+/// notified once per congestion episode of a NIC: again only after the buffer
+/// drained below the threshold in between.
+///
+/// The notification is delivered through [`ProcessHandle::on_message`] as a
+/// message from the congested process, so it travels back over the network with
+/// latency and is lost to partitions like any other message. Background
+/// traffic senders are never notified.
+///
+/// # Examples
+///
+/// ```rust
+/// use dscale::{ProcessHandle, ProcessId, MessagePtr, TimerId, Jiffies, CongestionNotification};
+/// use dscale::{SimulationBuilder, BandwidthDescription, Distributions, LatencyDescription};
+/// use dscale::{broadcast, schedule_timer_after};
+/// use dscale::global::anykv;
+///
+/// struct Batch;
+/// impl dscale::Message for Batch {
+///     fn virtual_size(&self) -> usize { 10_000 }
+/// }
+///
+/// struct Proposer {
+///     interval: Jiffies,
+/// }
+///
+/// impl Default for Proposer {
+///     fn default() -> Self {
+///         Self { interval: Jiffies(10) }
+///     }
+/// }
+///
+/// impl ProcessHandle for Proposer {
+///     fn start(&mut self) {
+///         schedule_timer_after(self.interval);
+///     }
+///
+///     fn on_message(&mut self, from: ProcessId, message: MessagePtr) {
+///         if message.is::<CongestionNotification>() {
+///             // Multiplicative decrease of the sending rate
+///             self.interval = Jiffies(self.interval.0 * 2);
+///             anykv::modify::<usize>("notifications", |n| *n += 1);
+///         }
+///     }
+///
+///     fn on_timer(&mut self, id: TimerId) {
+///         broadcast(Batch);
+///         schedule_timer_after(self.interval);
+///     }
+/// }
+///
+/// let mut simulation = SimulationBuilder::default()
+///     .add_pool::<Proposer>("proposers", 4)
+///     .latency_topology(&[LatencyDescription::WithinPool(
+///         "proposers",
+///         Distributions::Uniform(Jiffies(5), Jiffies(10)),
+///     )])
+///     .nic_bandwidth(BandwidthDescription::Bounded(1_000))
+///     .congestion_notification(8)
+///     .time_budget(Jiffies(5_000))
+///     .build();
+/// anykv::set::<usize>("notifications", 0);
+/// simulation.run();
+/// assert!(anykv::get::<usize>("notifications") > 0);
+/// ```
+///
+/// [`SimulationBuilder::congestion_notification`]: crate::SimulationBuilder::congestion_notification
+/// [`ProcessHandle::on_message`]: crate::ProcessHandle::on_message
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CongestionNotification {
+    /// Receiver whose NIC is congested.
+    pub congested: ProcessId,
+    /// Messages waiting in the buffer of its NIC when the message of the sender joined them.
+    pub queued: usize,
+}
+
+impl Message for CongestionNotification {}
+
+// Tracks buffer lengths of NICs and collects notifications to echo back
+pub(crate) struct CongestionMonitor {
+    threshold: Option<usize>,
+    queued: Vec<usize>,                                // NIC -> buffered messages
+    notified: Vec<BTreeSet<ProcessId>>, // NIC -> senders notified in the current episode
+    pending: Vec<(ProcessId, CongestionNotification)>, // Sender -> notification
+}
+
+impl CongestionMonitor {
+    pub(crate) fn new(threshold: Option<usize>, nics: usize) -> Self {
+        Self {
+            threshold,
+            queued: vec![0; nics + 1],
+            notified: vec![BTreeSet::new(); nics + 1],
+            pending: Vec::new(),
+        }
+    }
+
+    pub(crate) fn enqueue(
+        &mut self,
+        nic: ProcessId,
+        source: ProcessId,
+        dest: ProcessId,
+        kind: MessageKind,
+    ) {
+        self.queued[nic] += 1;
+        let Some(threshold) = self.threshold else {
+            return;
+        };
+        if self.queued[nic] < threshold
+            || kind != MessageKind::Protocol
+            || !self.notified[nic].insert(source)
+        {
+            return;
+        }
+        self.pending.push((
+            source,
+            CongestionNotification {
+                congested: dest,
+                queued: self.queued[nic],
+            },
+        ));
+    }
+
+    pub(crate) fn dequeue(&mut self, nic: ProcessId) {
+        self.queued[nic] -= 1;
+        if self
+            .threshold
+            .is_some_and(|threshold| self.queued[nic] < threshold)
+        {
+            // Drained: the next episode notifies everyone again
+            self.notified[nic].clear();
+        }
+    }
+
+    pub(crate) fn take(&mut self) -> Vec<(ProcessId, CongestionNotification)> {
+        std::mem::take(&mut self.pending)
+    }
+}
@@ -0,0 +1,50 @@
+use std::collections::HashMap;
+
+use crate::{
+    ProcessId, now,
+    scenario::{Scenario, ScenarioEvent},
+    time::Jiffies,
+};
+
+// Ends of faults scheduled by the scenario. Nothing sees past them: a message
+// held back by a fault is undeliverable only if it arrives before the fault
+// may end.
+pub(crate) struct Horizon {
+    restarts: HashMap<ProcessId, Vec<Jiffies>>, // Sorted
+    partition_changes: Vec<Jiffies>,            // Partitions and heals, sorted
+}
+
+impl Horizon {
+    pub(crate) fn new(scenario: &Scenario) -> Self {
+        let mut restarts: HashMap<ProcessId, Vec<Jiffies>> = HashMap::new();
+        let mut partition_changes = Vec::new();
+        scenario
+            .events()
+            .into_iter()
+            .for_each(|(at, event)| match event {
+                ScenarioEvent::Restart(id) => restarts.entry(id).or_default().push(at),
+                ScenarioEvent::Partition(_) | ScenarioEvent::Heal => partition_changes.push(at),
+                ScenarioEvent::Crash(_) => {}
+            });
+        Self {
+            restarts,
+            partition_changes,
+        }
+    }
+
+    // None if the crashed process never comes back
+    pub(crate) fn restart(&self, id: ProcessId) -> Option<Jiffies> {
+        next(self.restarts.get(&id)?)
+    }
+
+    // None if the current partition stays forever
+    pub(crate) fn partition_change(&self) -> Option<Jiffies> {
+        next(&self.partition_changes)
+    }
+}
+
+// Events of the current jiffy may still be ahead
+fn next(times: &[Jiffies]) -> Option<Jiffies> {
+    let index = times.partition_point(|at| *at < now());
+    times.get(index).copied()
+}
@@ -0,0 +1,218 @@
+//! Per-process inbox limits and overload behavior.
+//!
+//! The inbox of a process holds messages which already reached its network
+//! interface but wait for bandwidth to be delivered. Without a limit it grows
+//! without bound under overload; a bounded inbox applies an [`OverflowPolicy`]
+//! instead and accounts every drop in [`InboxStats`].
+
+use std::{
+    cell::RefCell,
+    collections::{HashSet, VecDeque},
+    rc::Rc,
+};
+
+use log::debug;
+
+use crate::{
+    Jiffies, ProcessId,
+    message::{RoutedMessage, Shared},
+    now,
+    nursery::Nursery,
+};
+
+/// What happens to a message arriving at a full inbox.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// The arriving message is dropped.
+    DropNewest,
+    /// The message waiting in the inbox for the longest time is dropped to make room.
+    DropOldest,
+    /// The receiving process crashes: it never executes again and all messages
+    /// addressed to it are discarded.
+    Crash,
+}
+
+/// Describes inbox limits for every process in the simulation.
+///
+/// Inboxes only fill up when network bandwidth is bounded (see
+/// [`BandwidthDescription::Bounded`]): with unbounded bandwidth messages are
+/// delivered as soon as their latency elapses.
+///
+/// # Examples
+///
+/// ```rust
+/// use dscale::{SimulationBuilder, BandwidthDescription, InboxDescription, OverflowPolicy};
+///
+/// let simulation = SimulationBuilder::default()
+///     .add_pool::<MyProcess>("nodes", 3)
+///     .nic_bandwidth(BandwidthDescription::Bounded(1000))
+///     .inbox(InboxDescription::Bounded(64, OverflowPolicy::DropOldest))
+///     .build();
+/// # #[derive(Default)]
+/// # struct MyProcess;
+/// # impl dscale::ProcessHandle for MyProcess {
+/// #     fn start(&mut self) {}
+/// #     fn on_message(&mut self, from: dscale::ProcessId, message: dscale::MessagePtr) {}
+/// #     fn on_timer(&mut self, id: dscale::TimerId) {}
+/// # }
+/// ```
+///
+/// [`BandwidthDescription::Bounded`]: crate::BandwidthDescription::Bounded
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InboxDescription {
+    /// Inboxes grow without bound (default).
+    Unbounded,
+    /// At most this many messages wait in every inbox.
+    Bounded(usize, OverflowPolicy),
+}
+
+/// Overload statistics collected by bounded inboxes.
+///
+/// Obtained with [`Simulation::inbox_stats`].
+///
+/// [`Simulation::inbox_stats`]: crate::Simulation::inbox_stats
+#[derive(Clone, Debug, Default)]
+pub struct InboxStats {
+    dropped: Vec<usize>,
+    peak: Vec<usize>,
+    crashed: Vec<(ProcessId, Jiffies)>,
+}
+
+impl InboxStats {
+    fn new(proc_num: usize) -> Self {
+        Self {
+            dropped: vec![0; proc_num + 1],
+            peak: vec![0; proc_num + 1],
+            crashed: Vec::new(),
+        }
+    }
+
+    /// Number of messages addressed to the process which were dropped on overflow.
+    pub fn dropped(&self, id: ProcessId) -> usize {
+        self.dropped.get(id).copied().unwrap_or(0)
+    }
+
+    /// Number of dropped messages across all processes.
+    pub fn total_dropped(&self) -> usize {
+        self.dropped.iter().sum()
+    }
+
+    /// The largest number of messages which waited in the inbox of the process at once.
+    pub fn peak_len(&self, id: ProcessId) -> usize {
+        self.peak.get(id).copied().unwrap_or(0)
+    }
+
+    /// Processes crashed by [`OverflowPolicy::Crash`] together with the time of the crash.
+    pub fn crashed(&self) -> &[(ProcessId, Jiffies)] {
+        &self.crashed
+    }
+}
+
+pub(crate) type SharedInboxStats = Rc<RefCell<InboxStats>>;
+
+// Messages are identified by (pointer, destination): broadcast shares one pointer
+// between destinations, but every destination gets it at most once.
+type Key = (*const (), ProcessId);
+
+fn key(message: &RoutedMessage) -> Key {
+    (
+        Shared::as_ptr(&message.step.message) as *const (),
+        message.step.dest,
+    )
+}
+
+pub(crate) struct Inboxes {
+    limit: Option<(usize, OverflowPolicy)>,
+    queued: Vec<VecDeque<Key>>,
+    cancelled: HashSet<Key>, // Dropped oldest messages are still in the shared buffer heap
+    stats: SharedInboxStats,
+    nursery: Rc<Nursery>,
+}
+
+impl Inboxes {
+    pub(crate) fn new(description: InboxDescription, nursery: Rc<Nursery>) -> Self {
+        let limit = match description {
+            InboxDescription::Unbounded => None,
+            InboxDescription::Bounded(capacity, policy) => {
+                assert!(capacity > 0, "Inbox capacity should be positive");
+                Some((capacity, policy))
+            }
+        };
+        Self {
+            limit,
+            queued: vec![VecDeque::new(); nursery.size() + 1],
+            cancelled: HashSet::new(),
+            stats: Rc::new(RefCell::new(InboxStats::new(nursery.size()))),
+            nursery,
+        }
+    }
+
+    pub(crate) fn size(&self) -> usize {
+        self.queued.len() - 1
+    }
+
+    pub(crate) fn stats(&self) -> SharedInboxStats {
+        self.stats.clone()
+    }
+
+    // Returns whether the message should be buffered
+    pub(crate) fn admit(&mut self, message: &RoutedMessage) -> bool {
+        let Some((capacity, policy)) = self.limit else {
+            return true;
+        };
+        let dest = message.step.dest;
+
+        if self.nursery.is_crashed(dest) {
+            return false;
+        }
+
+        if self.queued[dest].len() == capacity {
+            debug!("Inbox of P{dest} is full, applying {policy:?}");
+            match policy {
+                OverflowPolicy::DropNewest => {
+                    self.stats.borrow_mut().dropped[dest] += 1;
+                    return false;
+                }
+                OverflowPolicy::DropOldest => {
+                    let oldest = self.queued[dest].pop_front().expect("Inbox is full");
+                    self.cancelled.insert(oldest);
+                    self.stats.borrow_mut().dropped[dest] += 1;
+                }
+                OverflowPolicy::Crash => {
+                    self.nursery.crash(dest);
+                    let mut stats = self.stats.borrow_mut();
+                    stats.crashed.push((dest, now()));
+                    stats.dropped[dest] += 1;
+                    return false;
+                }
+            }
+        }
+
+        self.queued[dest].push_back(key(message));
+        let mut stats = self.stats.borrow_mut();
+        stats.peak[dest] = stats.peak[dest].max(self.queued[dest].len());
+        true
+    }
+
+    pub(crate) fn is_cancelled(&self, message: &RoutedMessage) -> bool {
+        self.cancelled.contains(&key(message))
+    }
+
+    // Returns whether the message leaving buffer should be delivered
+    pub(crate) fn release(&mut self, message: &RoutedMessage) -> bool {
+        if self.limit.is_none() {
+            return true;
+        }
+
+        let key = key(message);
+        if self.cancelled.remove(&key) {
+            return false;
+        }
+
+        let inbox = &mut self.queued[message.step.dest];
+        if let Some(position) = inbox.iter().position(|queued| *queued == key) {
+            inbox.remove(position);
+        }
+        true
+    }
+}
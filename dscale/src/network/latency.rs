@@ -3,38 +3,106 @@ use std::rc::Rc;
 
 use log::debug;
 
+use crate::global::tracing;
 use crate::message::{RoutedMessage, TimePriorityMessageQueue};
+use crate::network::{Channels, NetworkSchedule, ScheduleMode};
 use crate::random::Randomizer;
+use crate::time::Jiffies;
 use crate::topology::Topology;
+use crate::{ProcessId, now};
 
 pub(crate) struct LatencyQueue {
     topology: Rc<Topology>,
     randomizer: Randomizer,
+    channels: Channels,
+    schedule: ScheduleMode,
     queue: TimePriorityMessageQueue,
 }
 impl LatencyQueue {
-    pub(crate) fn new(randomizer: Randomizer, topology: Rc<Topology>) -> Self {
+    pub(crate) fn new(
+        randomizer: Randomizer,
+        topology: Rc<Topology>,
+        schedule: ScheduleMode,
+    ) -> Self {
         Self {
             randomizer,
+            channels: Channels::new(topology.channel_orderings()),
             topology,
+            schedule,
             queue: BinaryHeap::new(),
         }
     }
 
+    // Drawn from the latency topology, unless replayed
+    fn link_latency(&mut self, source: ProcessId, dest: ProcessId) -> Jiffies {
+        if let ScheduleMode::Replay(schedule) = &self.schedule
+            && let Some(latency) = schedule.latency(now(), source, dest)
+        {
+            return latency;
+        }
+
+        let latency = Jiffies(
+            self.randomizer
+                .random_usize(self.topology.get_distribution(source, dest)),
+        );
+        tracing::on_draw(
+            "network",
+            Some(source),
+            || format!("latency to P{dest}"),
+            &[latency.0],
+        );
+        if let ScheduleMode::Record(schedule) = &mut self.schedule {
+            schedule.insert(now(), source, dest, latency);
+        }
+        latency
+    }
+
+    pub(crate) fn recorded_schedule(&self) -> NetworkSchedule {
+        match &self.schedule {
+            ScheduleMode::Record(schedule) => schedule.clone(),
+            _ => panic!(
+                "Network schedule is not recorded, see SimulationBuilder::record_network_schedule"
+            ),
+        }
+    }
+
     pub(crate) fn push(&mut self, mut message: RoutedMessage) {
+        self.schedule(&mut message);
+        self.queue.push(std::cmp::Reverse(message));
+    }
+
+    // Draws the latency of a message which is never delivered, so later
+    // messages get the same latencies as if it was
+    pub(crate) fn discard(&mut self, mut message: RoutedMessage) {
+        self.schedule(&mut message);
+    }
+
+    fn schedule(&mut self, message: &mut RoutedMessage) {
         debug!(
             "Arrival time before adding latency: {}",
             message.arrival_time
         );
-        message.arrival_time += self.randomizer.random_usize(
-            self.topology
-                .get_distribution(message.step.source, message.step.dest),
-        );
+        message.arrival_time += self.link_latency(message.step.source, message.step.dest);
+        for extra in self.topology.message_latency(&message.step) {
+            let extra = self.randomizer.random_usize(extra);
+            let (source, dest) = (message.step.source, message.step.dest);
+            tracing::on_draw(
+                "network",
+                Some(source),
+                || format!("extra latency to P{dest}"),
+                &[extra],
+            );
+            message.arrival_time += extra;
+        }
+        self.channels.order(message);
         debug!(
             "Arrival time after adding random latency: {}",
             message.arrival_time
         );
-        self.queue.push(std::cmp::Reverse(message));
+    }
+
+    pub(crate) fn on_deliver(&mut self, message: &RoutedMessage) {
+        self.channels.on_deliver(message);
     }
 
     pub(crate) fn pop(&mut self) -> Option<RoutedMessage> {
@@ -44,4 +112,15 @@ impl LatencyQueue {
     pub(crate) fn peek(&self) -> Option<&RoutedMessage> {
         Some(&self.queue.peek()?.0)
     }
+
+    // Returns the number of removed messages
+    pub(crate) fn prune(&mut self, undeliverable: impl Fn(&RoutedMessage) -> bool) -> usize {
+        let before = self.queue.len();
+        self.queue.retain(|message| !undeliverable(&message.0));
+        before - self.queue.len()
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &RoutedMessage> {
+        self.queue.iter().map(|message| &message.0)
+    }
 }
@@ -0,0 +1,225 @@
+//! Links between regions: capacity limits and accounting of the traffic
+//! crossing them.
+//!
+//! NIC bandwidth (see [`BandwidthDescription`]) limits every host on its own,
+//! while the WAN between datacenters is a shared resource and, in the cloud, the
+//! part of the bill growing with the traffic of the protocol. [`RegionLink`]
+//! configured with [`SimulationBuilder::region_link`] caps the link between two
+//! regions and prices its bytes; [`LinkStats`] accounts every link between
+//! regions, so protocol comparisons can include bandwidth cost next to latency
+//! and throughput.
+//!
+//! [`BandwidthDescription`]: crate::BandwidthDescription
+//! [`SimulationBuilder::region_link`]: crate::SimulationBuilder::region_link
+
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap},
+    fmt,
+    rc::Rc,
+    time::Duration,
+};
+
+use log::debug;
+
+use crate::{
+    BandwidthDescription, Jiffies, ProcessId, message::ProcessStep, message_type::MessageKind,
+    topology::Topology,
+};
+
+const BYTES_PER_GB: f64 = 1e9;
+
+/// Capacity and price of the link between two regions.
+///
+/// Every direction of the link carries up to `bandwidth` on its own, shared by
+/// all processes of the regions: messages leave in the order they are sent and
+/// wait for the copies ahead of them, on top of NIC bandwidth and latency. Every
+/// byte crossing the link costs `cost_per_gb / 10^9`, e.g. the WAN egress price
+/// of a cloud provider in $/GB.
+///
+/// Background traffic (see [`BackgroundTraffic`]) occupies the link but is not
+/// billed to the protocol. Messages between colocated processes never cross a
+/// link.
+///
+/// # Examples
+///
+/// ```rust
+/// use dscale::{Bandwidth, BandwidthDescription, RegionLink, SimulationBuilder};
+/// use std::time::Duration;
+///
+/// let builder = SimulationBuilder::default()
+///     .add_pool_in_region::<MyProcess>("replicas", "eu", 3)
+///     .add_pool_in_region::<MyProcess>("replicas", "us", 3)
+///     .jiffy_duration(Duration::from_millis(1))
+///     .region_link("eu", "us", RegionLink {
+///         bandwidth: BandwidthDescription::Rate(Bandwidth::gbps(1)),
+///         cost_per_gb: 0.02,
+///     });
+/// # #[derive(Default)]
+/// # struct MyProcess;
+/// # impl dscale::ProcessHandle for MyProcess {
+/// #     fn start(&mut self) {}
+/// #     fn on_message(&mut self, from: dscale::ProcessId, message: dscale::MessagePtr) {}
+/// #     fn on_timer(&mut self, id: dscale::TimerId) {}
+/// # }
+/// ```
+///
+/// [`BackgroundTraffic`]: crate::BackgroundTraffic
+#[derive(Clone, Copy, Debug)]
+pub struct RegionLink {
+    /// Capacity of every direction of the link.
+    pub bandwidth: BandwidthDescription,
+    /// Price of 10^9 bytes crossing the link.
+    pub cost_per_gb: f64,
+}
+
+impl Default for RegionLink {
+    /// Unbounded and free, like links which are not configured.
+    fn default() -> Self {
+        Self {
+            bandwidth: BandwidthDescription::Unbounded,
+            cost_per_gb: 0.0,
+        }
+    }
+}
+
+/// Traffic which crossed one direction of a link between regions.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct LinkUsage {
+    /// Messages sent over the link.
+    pub messages: usize,
+    /// Sum of virtual sizes of the messages.
+    pub bytes: usize,
+    /// Price of the bytes, see [`RegionLink::cost_per_gb`].
+    pub cost: f64,
+}
+
+/// Protocol traffic between regions, per direction of every link.
+///
+/// Messages are accounted when they leave the sender, whether or not they reach
+/// the receiver afterwards, like cloud egress. Both processes need a region
+/// (see [`SimulationBuilder::add_pool_in_region`]); links within a region are
+/// accounted too. Obtained with [`Simulation::link_stats`].
+///
+/// [`SimulationBuilder::add_pool_in_region`]: crate::SimulationBuilder::add_pool_in_region
+/// [`Simulation::link_stats`]: crate::Simulation::link_stats
+#[derive(Clone, Debug, Default)]
+pub struct LinkStats {
+    links: BTreeMap<(&'static str, &'static str), LinkUsage>,
+}
+
+impl LinkStats {
+    /// Traffic sent from region `from` to region `to`.
+    pub fn link(&self, from: &str, to: &str) -> LinkUsage {
+        self.links.get(&(from, to)).copied().unwrap_or_default()
+    }
+
+    /// Every used direction of a link as `(from, to, usage)`, ordered by regions.
+    pub fn links(&self) -> impl Iterator<Item = (&'static str, &'static str, LinkUsage)> + '_ {
+        self.links
+            .iter()
+            .map(|((from, to), usage)| (*from, *to, *usage))
+    }
+
+    /// Bytes sent between different regions.
+    pub fn cross_region_bytes(&self) -> usize {
+        self.links()
+            .filter(|(from, to, _)| from != to)
+            .map(|(_, _, usage)| usage.bytes)
+            .sum()
+    }
+
+    /// Price of all accounted traffic.
+    pub fn total_cost(&self) -> f64 {
+        self.links().map(|(_, _, usage)| usage.cost).sum()
+    }
+}
+
+impl fmt::Display for LinkStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "from,to,messages,bytes,cost")?;
+        self.links().try_for_each(|(from, to, usage)| {
+            writeln!(
+                f,
+                "{from},{to},{},{},{}",
+                usage.messages, usage.bytes, usage.cost
+            )
+        })
+    }
+}
+
+pub(crate) type RegionLinks = HashMap<(&'static str, &'static str), RegionLink>;
+pub(crate) type SharedLinkStats = Rc<RefCell<LinkStats>>;
+
+pub(crate) fn resolve(links: RegionLinks, jiffy: Option<Duration>) -> RegionLinks {
+    links
+        .into_iter()
+        .map(|(regions, link)| {
+            let link = RegionLink {
+                bandwidth: link.bandwidth.resolve(jiffy),
+                ..link
+            };
+            (regions, link)
+        })
+        .collect()
+}
+
+pub(crate) struct Links {
+    links: RegionLinks,
+    busy: HashMap<(&'static str, &'static str), Jiffies>, // Until when directions are sending
+    stats: SharedLinkStats,
+    topology: Rc<Topology>,
+}
+
+impl Links {
+    pub(crate) fn new(links: RegionLinks, topology: Rc<Topology>) -> Self {
+        Self {
+            links,
+            busy: HashMap::new(),
+            stats: Rc::new(RefCell::new(LinkStats::default())),
+            topology,
+        }
+    }
+
+    pub(crate) fn stats(&self) -> SharedLinkStats {
+        self.stats.clone()
+    }
+
+    // Accounts the copy of `step` sent from `source` to `target` once it is out
+    // of the sender at `departure`, returns when it has crossed the link
+    pub(crate) fn cross(
+        &mut self,
+        step: &ProcessStep,
+        source: ProcessId,
+        target: ProcessId,
+        departure: Jiffies,
+    ) -> Jiffies {
+        if self.topology.same_host(source, target) {
+            return departure;
+        }
+        let (Some(from), Some(to)) = (
+            self.topology.region_of(source),
+            self.topology.region_of(target),
+        ) else {
+            return departure;
+        };
+        let link = self.links.get(&(from, to)).copied().unwrap_or_default();
+
+        if step.message_type.kind != MessageKind::CrossTraffic {
+            let mut stats = self.stats.borrow_mut();
+            let usage = stats.links.entry((from, to)).or_default();
+            usage.messages += 1;
+            usage.bytes += step.size;
+            usage.cost += step.size as f64 * link.cost_per_gb / BYTES_PER_GB;
+        }
+
+        let BandwidthDescription::Bounded(bandwidth) = link.bandwidth else {
+            return departure;
+        };
+        let busy = self.busy.entry((from, to)).or_default();
+        let start = (*busy).max(departure);
+        *busy = start + Jiffies(step.size.div_ceil(bandwidth));
+        debug!("Message from P{source} to P{target} crosses {from} -> {to} until {busy}");
+        *busy
+    }
+}
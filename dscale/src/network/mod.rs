@@ -1,15 +1,52 @@
 mod bandwidth;
+mod channels;
+mod congestion;
+mod horizon;
+mod inbox;
 mod latency;
+pub(crate) mod links;
+mod reachability;
+mod relay;
+mod schedule;
+pub(crate) mod traffic;
 
 use std::cell::RefCell;
+use std::collections::BTreeMap;
 use std::rc::Rc;
 
+pub use bandwidth::Bandwidth;
 pub use bandwidth::BandwidthDescription;
 pub(crate) use bandwidth::BandwidthQueue;
+pub use channels::ChannelOrdering;
+pub(crate) use channels::ChannelOrderings;
+pub(crate) use channels::Channels;
+pub(crate) use congestion::CongestionMonitor;
+pub use congestion::CongestionNotification;
+pub(crate) use horizon::Horizon;
+pub use inbox::InboxDescription;
+pub use inbox::InboxStats;
+pub(crate) use inbox::Inboxes;
+pub use inbox::OverflowPolicy;
+pub(crate) use inbox::SharedInboxStats;
 pub(crate) use latency::LatencyQueue;
+pub use links::LinkStats;
+pub use links::LinkUsage;
+pub(crate) use links::Links;
+pub use links::RegionLink;
+pub(crate) use links::RegionLinks;
+pub(crate) use links::SharedLinkStats;
 use log::debug;
+pub(crate) use reachability::Reachability;
+pub(crate) use reachability::SharedReachability;
+pub(crate) use relay::Relay;
+pub use relay::RelayTree;
+pub(crate) use relay::RelayTrees;
+pub use schedule::NetworkSchedule;
+pub(crate) use schedule::ScheduleMode;
+pub use traffic::BackgroundTraffic;
+pub(crate) use traffic::Flow;
+pub(crate) use traffic::TrafficGenerator;
 
-use crate::Message;
 use crate::MessagePtr;
 use crate::ProcessId;
 use crate::actor::EventSubmitter;
@@ -19,66 +56,313 @@ use crate::dscale_message::DScaleMessage;
 use crate::global::configuration;
 use crate::message::ProcessStep;
 use crate::message::RoutedMessage;
+use crate::message::Shared;
+use crate::message::SharedMessage;
+use crate::message_type::{self, MessageKind};
 use crate::now;
 use crate::nursery::Nursery;
+use crate::pending::{PendingEvents, PrunedEvents};
 use crate::random::Randomizer;
 use crate::random::Seed;
+use crate::random::{TieBreak, TieBreaker};
 use crate::time::Jiffies;
 use crate::topology::Topology;
 
 pub(crate) type NetworkActor = Rc<RefCell<Network>>;
 
+fn separated(partition: &[usize], a: ProcessId, b: ProcessId) -> bool {
+    !partition.is_empty() && partition[a] != partition[b]
+}
+
+// Sends scheduled for the future, keyed by (departure, tie, submission order)
+type DeferredSends = BTreeMap<(Jiffies, u64, usize), DeferredSend>;
+
+struct DeferredSend {
+    source: ProcessId,
+    incarnation: usize,
+    destination: Destination,
+    message: SharedMessage,
+}
+
 pub(crate) struct Network {
     seed: Seed,
     bandwidth_queue: BandwidthQueue,
+    links: Links,
+    deferred: DeferredSends,
+    deferred_seq: usize,
+    tie_breaker: TieBreaker,
+    partition: Vec<usize>, // Group of every process, empty if there is no partition
+    horizon: Option<Horizon>, // Set if undeliverable messages are pruned
+    pruned: PrunedEvents,
+    reachability: SharedReachability,
     topology: Rc<Topology>,
     nursery: Rc<Nursery>,
 }
 
 impl Network {
-    fn submit_single_message(
+    pub(crate) fn submit_single_message(
         &mut self,
-        message: Rc<dyn Message>,
+        message: SharedMessage,
         source: ProcessId,
         destination: Destination,
     ) {
-        let targets = match destination {
-            Destination::BroadcastWithinPool(pool_name) => self.topology.list_pool(pool_name),
-            Destination::To(to) => &[to],
+        let step = ProcessStep {
+            source,
+            dest: source, // Set per target
+            message_type: message_type::of(message.as_ref()),
+            size: message.virtual_size(),
+            message,
+            causal_past: None,
+            relay: None,
+        };
+
+        let topology = self.topology.clone();
+        let targets = match &destination {
+            Destination::BroadcastWithinPool(pool) if topology.relay_tree(pool).is_some() => {
+                return self.submit_to_relay_tree(step, pool);
+            }
+            Destination::BroadcastWithinPool(pool_name) => topology.list_pool(pool_name),
+            Destination::To(to) => std::slice::from_ref(to),
+            Destination::Members(members) => members.as_slice(),
         };
 
         debug!("Submitting message from {source}, targets of the message: {targets:?}",);
 
-        targets.into_iter().copied().for_each(|target| {
-            let routed_message = RoutedMessage {
-                arrival_time: now() + Jiffies(1), // Without any latency message will arrive on next timepoint;
-                step: ProcessStep {
-                    source,
-                    dest: target,
-                    message: message.clone(),
-                },
-            };
-            self.bandwidth_queue.push(routed_message);
+        targets
+            .iter()
+            .for_each(|target| self.route(&step, step.source, *target));
+    }
+
+    // The sender reaches itself directly and its children in the tree, which
+    // forward the message on arrival
+    fn submit_to_relay_tree(&mut self, step: ProcessStep, pool: &'static str) {
+        let source = step.source;
+        let topology = self.topology.clone();
+        let members = topology.list_pool(pool);
+        let tree = topology.relay_tree(pool).expect("Relay tree is configured");
+        let children = tree.children(members, source, source);
+        debug!("Submitting message from {source} to relays {children:?} of pool {pool}");
+
+        if members.contains(&source) {
+            self.route(&step, source, source);
+        }
+        let step = ProcessStep {
+            relay: Some(Relay { pool, root: source }),
+            ..step
+        };
+        children
+            .into_iter()
+            .for_each(|child| self.route(&step, source, child));
+    }
+
+    // Passes a message arriving at a relay on to the relay's children
+    fn forward(&mut self, step: &ProcessStep) {
+        let Some(relay) = step.relay else {
+            return;
+        };
+        if self.nursery.is_crashed(step.dest) {
+            return; // The subtree is cut off
+        }
+        let topology = self.topology.clone();
+        let members = topology.list_pool(relay.pool);
+        let tree = topology
+            .relay_tree(relay.pool)
+            .expect("Relay tree is configured");
+        let children = tree.children(members, relay.root, step.dest);
+        debug!(
+            "P{} relays message of P{} to {children:?}",
+            step.dest, relay.root
+        );
+        children
+            .into_iter()
+            .for_each(|child| self.route(step, step.dest, child));
+    }
+
+    fn route(&mut self, step: &ProcessStep, source: ProcessId, target: ProcessId) {
+        if separated(&self.partition, source, target) {
+            debug!("Dropping message from P{source} to partitioned P{target}");
+            return;
+        }
+        let departure = match step.relay {
+            Some(_) => self.bandwidth_queue.upload(source, target, step.size),
+            None => now(),
+        };
+        let departure = self.links.cross(step, source, target, departure);
+        let routed_message = RoutedMessage {
+            arrival_time: departure.max(now() + Jiffies(1)), // Without any latency message will arrive on next timepoint;
+            tie: self.tie_breaker.next(),
+            seq: self.tie_breaker.seq(),
+            step: ProcessStep {
+                source,
+                dest: target,
+                causal_past: None,
+                ..step.clone()
+            },
+        };
+        if self.down_for_good(target) {
+            debug!("Pruning message from P{source} to P{target} crashed for good");
+            self.pruned.in_flight += 1;
+            self.bandwidth_queue.discard(routed_message);
+            return;
+        }
+        self.bandwidth_queue.push(routed_message);
+    }
+
+    fn defer(
+        &mut self,
+        message: SharedMessage,
+        source: ProcessId,
+        destination: Destination,
+        departure: Jiffies,
+    ) {
+        debug!("Deferring message from {source} until {departure}");
+        self.deferred_seq += 1;
+        self.deferred.insert(
+            (departure, self.tie_breaker.next(), self.deferred_seq),
+            DeferredSend {
+                source,
+                incarnation: self.nursery.incarnation(source),
+                destination,
+                message,
+            },
+        );
+    }
+
+    fn depart(&mut self, send: DeferredSend) {
+        if self.nursery.is_crashed(send.source)
+            || self.nursery.incarnation(send.source) != send.incarnation
+        {
+            debug!("Dropping deferred message of crashed P{}", send.source);
+            self.nursery.observe(|| {
+                format!(
+                    "P{}: scheduled {} dropped, sender crashed",
+                    send.source,
+                    message_type::of(send.message.as_ref()).name
+                )
+            });
+            return;
+        }
+        self.submit_single_message(send.message, send.source, send.destination);
+    }
+
+    fn next_departure(&self) -> Option<Jiffies> {
+        self.deferred
+            .keys()
+            .next()
+            .map(|(departure, ..)| *departure)
+    }
+
+    // Unlisted processes stay in group 0
+    pub(crate) fn partition(&mut self, groups: &[Vec<ProcessId>]) {
+        self.partition = vec![0; self.nursery.size() + 1];
+        groups.iter().enumerate().for_each(|(group, members)| {
+            members
+                .iter()
+                .for_each(|id| self.partition[*id] = group + 1)
+        });
+        self.reachability
+            .borrow_mut()
+            .record_partition(&self.partition);
+
+        // Messages across the partition arriving before it may change are lost
+        if let Some(horizon) = &self.horizon {
+            let until = horizon.partition_change();
+            let partition = &self.partition;
+            let pruned = self.bandwidth_queue.prune(|message| {
+                separated(partition, message.step.source, message.step.dest)
+                    && until.is_none_or(|until| message.arrival_time < until)
+            });
+            debug!("Pruned {pruned} messages across the partition");
+            self.pruned.in_flight += pruned;
+        }
+    }
+
+    // Without a scheduled restart, postponed starts aside
+    fn down_for_good(&self, id: ProcessId) -> bool {
+        self.horizon.as_ref().is_some_and(|horizon| {
+            self.nursery.is_crashed(id)
+                && !self.nursery.is_postponed(id)
+                && horizon.restart(id).is_none()
+        })
+    }
+
+    // Scheduled sends of crashed and restarted processes never depart, messages
+    // to crashed ones are lost until they restart
+    pub(crate) fn prune_retired(&mut self, retired: &[ProcessId]) {
+        let before = self.deferred.len();
+        let nursery = &self.nursery;
+        self.deferred.retain(|_, send| {
+            !retired.contains(&send.source)
+                || (!nursery.is_crashed(send.source)
+                    && nursery.incarnation(send.source) == send.incarnation)
         });
+        self.pruned.scheduled += before - self.deferred.len();
+
+        let Some(horizon) = &self.horizon else {
+            return;
+        };
+        retired
+            .iter()
+            .filter(|id| self.nursery.is_crashed(**id))
+            .for_each(|id| {
+                let until = horizon.restart(*id);
+                let pruned = self.bandwidth_queue.prune(|message| {
+                    message.step.dest == *id
+                        && until.is_none_or(|until| message.arrival_time < until)
+                });
+                debug!("Pruned {pruned} messages to crashed P{id}");
+                self.pruned.in_flight += pruned;
+            });
+    }
+
+    pub(crate) fn pruned(&self) -> PrunedEvents {
+        self.pruned
+    }
+
+    pub(crate) fn heal(&mut self) {
+        self.partition.clear();
+        self.reachability
+            .borrow_mut()
+            .record_partition(&self.partition);
+    }
+
+    // Indexed by process id, crashed processes are accounted by the caller
+    pub(crate) fn count_pending(&self, pending: &mut [PendingEvents]) {
+        self.bandwidth_queue
+            .in_flight()
+            .filter(|message| message.step.message_type.kind != MessageKind::CrossTraffic)
+            .for_each(|message| pending[message.step.dest].in_flight += 1);
+        self.deferred
+            .values()
+            .filter(|send| self.nursery.incarnation(send.source) == send.incarnation)
+            .for_each(|send| pending[send.source].scheduled += 1);
     }
 
     fn execute_process_step(&mut self, step: ProcessStep) {
-        let source = step.source;
+        let source = step.relay.map_or(step.source, |relay| relay.root); // Relays are transparent
         let dest = step.dest;
         let message = step.message;
 
         self.nursery.deliver(
             source,
             dest,
-            DScaleMessage::NetworkMessage(MessagePtr(message)),
+            DScaleMessage::NetworkMessage(MessagePtr(message), step.size),
         );
     }
 }
 
 impl Network {
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn new(
         seed: Seed,
+        tie_break: TieBreak,
         bandwidth_type: BandwidthDescription,
+        links: RegionLinks,
+        inbox: InboxDescription,
+        schedule: ScheduleMode,
+        congestion_threshold: Option<usize>,
+        horizon: Option<Horizon>,
+        reachability: SharedReachability,
         topology: Rc<Topology>,
         nursery: Rc<Nursery>,
     ) -> Self {
@@ -86,45 +370,138 @@ impl Network {
             seed,
             bandwidth_queue: BandwidthQueue::new(
                 bandwidth_type,
-                nursery.size(),
-                LatencyQueue::new(Randomizer::new(seed), topology.clone()),
+                Inboxes::new(inbox, nursery.clone()),
+                LatencyQueue::new(Randomizer::new(seed), topology.clone(), schedule),
+                congestion_threshold,
+                topology.clone(),
             ),
+            links: Links::new(links, topology.clone()),
+            deferred: BTreeMap::new(),
+            deferred_seq: 0,
+            tie_breaker: TieBreaker::new(tie_break),
+            partition: Vec::new(),
+            horizon,
+            pruned: PrunedEvents::default(),
+            reachability,
             topology,
             nursery,
         }
     }
 }
 
+impl Network {
+    pub(crate) fn inbox_stats(&self) -> SharedInboxStats {
+        self.bandwidth_queue.inbox_stats()
+    }
+
+    pub(crate) fn recorded_schedule(&self) -> NetworkSchedule {
+        self.bandwidth_queue.recorded_schedule()
+    }
+
+    pub(crate) fn link_stats(&self) -> SharedLinkStats {
+        self.links.stats()
+    }
+}
+
 impl SimulationActor for Network {
     fn start(&mut self) {
-        self.nursery.keys().for_each(|id| {
+        self.nursery.ids().for_each(|id| {
             configuration::setup_local_configuration(*id, self.seed);
-            self.nursery.start_single(*id);
+            self.nursery.start_or_postpone(*id);
         });
     }
 
     fn step(&mut self) {
+        // Processes start before messages and sends of the same jiffy
+        if let Some(start) = self.nursery.next_start()
+            && Some(start) == self.peek_closest()
+        {
+            self.nursery.start_postponed();
+            return;
+        }
+
+        if let Some(departure) = self.next_departure()
+            && self
+                .bandwidth_queue
+                .peek_closest()
+                .is_none_or(|arrival| departure <= arrival)
+        {
+            let (_, send) = self.deferred.pop_first().expect("Deferred send exists");
+            self.depart(send);
+            return;
+        }
+
         let next_event = self.bandwidth_queue.pop();
 
         match next_event {
             None => {}
+            Some(message) if separated(&self.partition, message.step.source, message.step.dest) => {
+                debug!(
+                    "Dropping in flight message from P{} to partitioned P{}",
+                    message.step.source, message.step.dest
+                );
+                self.nursery.observe(|| {
+                    format!(
+                        "P{} -> P{}: {} dropped by partition",
+                        message.step.source, message.step.dest, message.step.message_type.name
+                    )
+                });
+            }
+            Some(message) if message.step.message_type.kind == MessageKind::CrossTraffic => {
+                debug!(
+                    "Background traffic from P{} reached P{}",
+                    message.step.source, message.step.dest
+                );
+                self.bandwidth_queue.on_deliver(&message);
+            }
             Some(message) => {
+                self.bandwidth_queue.on_deliver(&message);
+                self.forward(&message.step);
                 self.execute_process_step(message.step);
             }
         }
+
+        // Echoed by the congested receiver back to the sender
+        self.bandwidth_queue
+            .take_congestion_notifications()
+            .into_iter()
+            .for_each(|(sender, notification)| {
+                debug!(
+                    "Notifying P{sender} of congestion at P{}",
+                    notification.congested
+                );
+                self.submit_single_message(
+                    Shared::new(notification),
+                    notification.congested,
+                    Destination::To(sender),
+                );
+            });
     }
 
     fn peek_closest(&self) -> Option<Jiffies> {
-        self.bandwidth_queue.peek_closest()
+        [
+            self.bandwidth_queue.peek_closest(),
+            self.next_departure(),
+            self.nursery.next_start(),
+        ]
+        .into_iter()
+        .flatten()
+        .min()
     }
 }
 
 impl EventSubmitter for Network {
-    type Event = (ProcessId, Destination, Rc<dyn Message>);
+    type Event = (ProcessId, Destination, SharedMessage, Jiffies); // Jiffies - departure time
 
     fn submit(&mut self, events: &mut Vec<Self::Event>) {
-        events.drain(..).for_each(|(from, destination, message)| {
-            self.submit_single_message(message, from, destination);
-        });
+        events
+            .drain(..)
+            .for_each(|(from, destination, message, departure)| {
+                if departure > now() {
+                    self.defer(message, from, destination, departure);
+                } else {
+                    self.submit_single_message(message, from, destination);
+                }
+            });
     }
 }
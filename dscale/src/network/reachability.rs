@@ -0,0 +1,70 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{ProcessId, now, time::Jiffies};
+
+pub(crate) type SharedReachability = Rc<RefCell<Reachability>>;
+
+// Connectivity over time, so processes can see it the way it was a while ago:
+// every partition and heal, every crash, restart and postponed start. Changes
+// superseded before `staleness` ago can not be seen anymore and are dropped
+pub(crate) struct Reachability {
+    staleness: Jiffies,
+    partitions: Vec<(Jiffies, Vec<usize>)>, // Group of every process since, empty if healed
+    down: Vec<Vec<(Jiffies, bool)>>,        // Whether the process is down since, per process
+}
+
+impl Reachability {
+    pub(crate) fn new_shared(proc_num: usize, staleness: Jiffies) -> SharedReachability {
+        Rc::new(RefCell::new(Self {
+            staleness,
+            partitions: Vec::new(),
+            down: vec![Vec::new(); proc_num + 1],
+        }))
+    }
+
+    pub(crate) fn record_partition(&mut self, partition: &[usize]) {
+        let horizon = self.horizon();
+        self.partitions.push((now(), partition.to_vec()));
+        forget_before(&mut self.partitions, horizon);
+    }
+
+    pub(crate) fn record_down(&mut self, id: ProcessId, down: bool) {
+        let horizon = self.horizon();
+        self.down[id].push((now(), down));
+        forget_before(&mut self.down[id], horizon);
+    }
+
+    // Members `from` could reach `staleness` ago, itself included
+    pub(crate) fn reachable(&self, from: ProcessId, members: &[ProcessId]) -> Vec<ProcessId> {
+        let at = self.horizon();
+        let partition = as_of(&self.partitions, at).map_or(&[][..], Vec::as_slice);
+        members
+            .iter()
+            .copied()
+            .filter(|id| {
+                *id == from
+                    || (!as_of(&self.down[*id], at).is_some_and(|down| *down)
+                        && (partition.is_empty() || partition[*id] == partition[from]))
+            })
+            .collect()
+    }
+
+    // Earliest time queried from now on, the clock never goes back
+    fn horizon(&self) -> Jiffies {
+        Jiffies(now().0.saturating_sub(self.staleness.0))
+    }
+}
+
+// Drops changes superseded before `at`, keeping the one in effect at `at`
+fn forget_before<T>(changes: &mut Vec<(Jiffies, T)>, at: Jiffies) {
+    let index = changes.partition_point(|(since, _)| *since <= at);
+    if index > 1 {
+        changes.drain(..index - 1);
+    }
+}
+
+// Last change at or before `at`, changes are recorded in order of time
+fn as_of<T>(changes: &[(Jiffies, T)], at: Jiffies) -> Option<&T> {
+    let index = changes.partition_point(|(since, _)| *since <= at);
+    index.checked_sub(1).map(|index| &changes[index].1)
+}
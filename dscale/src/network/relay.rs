@@ -0,0 +1,120 @@
+use std::collections::HashMap;
+
+use crate::ProcessId;
+
+/// Shape of the relay tree realizing broadcasts within a pool.
+///
+/// By default a broadcast leaves the sender as one message per member and only
+/// receiving NICs are limited by bandwidth, so the fanout of the sender is free.
+/// With a relay tree configured by [`SimulationBuilder::broadcast_tree`], the
+/// sender sends to its children only and every member forwards the message to
+/// its own children when it arrives. Copies sent along the tree leave the NIC of
+/// their sender one after another, so relays spend their own bandwidth and large
+/// committees pay for broadcasts with extra hops of latency instead of a free
+/// fanout. [`RelayTree::Depth`] of one is a direct broadcast paying for the
+/// fanout.
+///
+/// The tree is rooted at the sender. Other members follow in the order of the
+/// pool, starting after the sender, so relays differ between senders. Crashed
+/// and partitioned relays cut their subtrees off. Receivers see the original
+/// sender as the source of the message.
+///
+/// # Examples
+///
+/// ```rust
+/// use dscale::{SimulationBuilder, RelayTree};
+///
+/// let builder = SimulationBuilder::default()
+///     .add_pool::<MyProcess>("committee", 200)
+///     .broadcast_tree("committee", RelayTree::Fanout(8));
+/// # #[derive(Default)]
+/// # struct MyProcess;
+/// # impl dscale::ProcessHandle for MyProcess {
+/// #     fn start(&mut self) {}
+/// #     fn on_message(&mut self, from: dscale::ProcessId, message: dscale::MessagePtr) {}
+/// #     fn on_timer(&mut self, id: dscale::TimerId) {}
+/// # }
+/// ```
+///
+/// [`SimulationBuilder::broadcast_tree`]: crate::SimulationBuilder::broadcast_tree
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RelayTree {
+    /// Every process forwards to at most this many children; the depth follows
+    /// from the size of the pool.
+    Fanout(usize),
+
+    /// Members are at most this many hops away from the sender; the fanout is
+    /// the smallest one reaching the whole pool.
+    Depth(usize),
+}
+
+impl RelayTree {
+    // Fanout of a tree over `size` processes, the root included
+    fn fanout(&self, size: usize) -> usize {
+        match *self {
+            RelayTree::Fanout(fanout) => fanout,
+            RelayTree::Depth(depth) => {
+                let reached = |fanout: usize| {
+                    (1..=depth)
+                        .map(|level| fanout.saturating_pow(level as u32))
+                        .fold(0usize, usize::saturating_add)
+                };
+                (1..size.max(2))
+                    .find(|fanout| reached(*fanout) >= size - 1)
+                    .unwrap_or(1)
+            }
+        }
+    }
+
+    pub(crate) fn validate(&self) {
+        match self {
+            RelayTree::Fanout(0) => panic!("Relay tree fanout should be positive"),
+            RelayTree::Depth(0) => panic!("Relay tree depth should be positive"),
+            _ => {}
+        }
+    }
+
+    // Children of `node` in the tree of a broadcast from `root` to `members`
+    pub(crate) fn children(
+        &self,
+        members: &[ProcessId],
+        root: ProcessId,
+        node: ProcessId,
+    ) -> Vec<ProcessId> {
+        // Position 0 is the root, others map to members
+        let root_index = members.iter().position(|member| *member == root);
+        let size = members.len() + usize::from(root_index.is_none());
+        let member_at = |position: usize| match root_index {
+            Some(index) => members[(index + position) % members.len()],
+            None => members[position - 1],
+        };
+        let position = match node == root {
+            true => 0,
+            false => {
+                let index = members
+                    .iter()
+                    .position(|member| *member == node)
+                    .expect("Relay is a member of the pool");
+                match root_index {
+                    Some(root_index) => (index + members.len() - root_index) % members.len(),
+                    None => index + 1,
+                }
+            }
+        };
+
+        let fanout = self.fanout(size);
+        let first = position.saturating_mul(fanout).saturating_add(1);
+        (first..first.saturating_add(fanout).min(size))
+            .map(member_at)
+            .collect()
+    }
+}
+
+pub(crate) type RelayTrees = HashMap<String, RelayTree>;
+
+// Broadcast carried by a relay tree
+#[derive(Clone, Copy)]
+pub(crate) struct Relay {
+    pub(crate) pool: &'static str,
+    pub(crate) root: ProcessId,
+}
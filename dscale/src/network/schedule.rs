@@ -0,0 +1,164 @@
+//! Realized link latencies of a run, recorded and replayed.
+//!
+//! Two runs with the same latency distributions still see different network
+//! conditions once the protocols send different messages: every message draws
+//! its own latency. A [`NetworkSchedule`] recorded from one run fixes the drawn
+//! latencies of every link over time, so another run (of another protocol) can
+//! be replayed on exactly the same network.
+
+use std::{collections::BTreeMap, fmt::Write, fs, path::Path};
+
+use crate::{Jiffies, ProcessId};
+
+/// Latency of every link over time, in the style of ns-3 delay schedules.
+///
+/// A schedule holds samples `(time, latency)` per directed link. Replaying it, a
+/// message sent over a link at time `t` takes the latency of the last sample of
+/// the link at or before `t` (the first sample if there is none yet). Links
+/// without samples fall back to the latency topology of the replaying run.
+///
+/// Only link latencies are scheduled. Extra latencies of message types (see
+/// [`SimulationBuilder::message_latency`]) and bandwidth queueing depend on the
+/// traffic of the protocol and are computed by the replaying run itself.
+///
+/// # File Format
+///
+/// One sample per line with space-separated time, sender, receiver and latency
+/// in jiffies, ordered by time. Lines starting with `#` are comments, so
+/// schedules can be written by hand or generated by other tools.
+///
+/// ```text
+/// # time source dest latency
+/// 0 1 2 48
+/// 500 1 2 120
+/// ```
+///
+/// # Examples
+///
+/// ```rust
+/// use dscale::{SimulationBuilder, Distributions, Jiffies, LatencyDescription, NetworkSchedule};
+///
+/// let builder = || {
+///     SimulationBuilder::default()
+///         .add_pool::<Node>("nodes", 3)
+///         .latency_topology(&[LatencyDescription::WithinPool(
+///             "nodes",
+///             Distributions::Normal(Jiffies(50), Jiffies(10)),
+///         )])
+///         .time_budget(Jiffies(1_000))
+/// };
+///
+/// let mut recorded = builder().record_network_schedule(true).build();
+/// recorded.run();
+/// let schedule = recorded.network_schedule();
+/// assert!(!schedule.is_empty());
+///
+/// // Another protocol (or configuration) on the same realized network
+/// let mut replayed = builder().replay_network_schedule(schedule).build();
+/// replayed.run();
+/// # #[derive(Default)]
+/// # struct Node;
+/// # impl dscale::ProcessHandle for Node {
+/// #     fn start(&mut self) { dscale::schedule_timer_after(Jiffies(10)); }
+/// #     fn on_message(&mut self, from: dscale::ProcessId, message: dscale::MessagePtr) {}
+/// #     fn on_timer(&mut self, id: dscale::TimerId) {
+/// #         dscale::broadcast(Ping);
+/// #         dscale::schedule_timer_after(Jiffies(10));
+/// #     }
+/// # }
+/// # struct Ping;
+/// # impl dscale::Message for Ping {}
+/// ```
+///
+/// [`SimulationBuilder::message_latency`]: crate::SimulationBuilder::message_latency
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct NetworkSchedule {
+    links: BTreeMap<(ProcessId, ProcessId), Vec<(Jiffies, Jiffies)>>, // Samples ordered by time
+}
+
+impl NetworkSchedule {
+    /// Adds a sample: from `time` on, messages from `source` to `dest` take `latency`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `time` precedes the last sample of the link.
+    pub fn insert(&mut self, time: Jiffies, source: ProcessId, dest: ProcessId, latency: Jiffies) {
+        let samples = self.links.entry((source, dest)).or_default();
+        assert!(
+            samples.last().is_none_or(|(last, _)| *last <= time),
+            "Samples of link P{source} -> P{dest} should be ordered by time"
+        );
+        samples.push((time, latency));
+    }
+
+    /// Latency of a message sent from `source` to `dest` at `time`, `None` if the link has no samples.
+    pub fn latency(&self, time: Jiffies, source: ProcessId, dest: ProcessId) -> Option<Jiffies> {
+        let samples = self.links.get(&(source, dest))?;
+        let after = samples.partition_point(|(at, _)| *at <= time);
+        Some(samples[after.saturating_sub(1)].1)
+    }
+
+    /// Total number of samples.
+    pub fn len(&self) -> usize {
+        self.links.values().map(Vec::len).sum()
+    }
+
+    /// Returns `true` if there are no samples.
+    pub fn is_empty(&self) -> bool {
+        self.links.is_empty()
+    }
+
+    /// Writes the schedule into `path`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the file cannot be written.
+    pub fn save(&self, path: impl AsRef<Path>) {
+        let mut samples: Vec<(Jiffies, ProcessId, ProcessId, Jiffies)> = self
+            .links
+            .iter()
+            .flat_map(|((source, dest), samples)| {
+                samples
+                    .iter()
+                    .map(|(time, latency)| (*time, *source, *dest, *latency))
+            })
+            .collect();
+        samples.sort();
+
+        let mut out = String::from("# time source dest latency\n");
+        samples.iter().for_each(|(time, source, dest, latency)| {
+            let _ = writeln!(out, "{} {source} {dest} {}", time.0, latency.0);
+        });
+        fs::write(path, out).expect("Unable to write network schedule");
+    }
+
+    /// Reads a schedule written by [`NetworkSchedule::save`] or by hand.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the file cannot be read or is malformed.
+    pub fn load(path: impl AsRef<Path>) -> Self {
+        let content = fs::read_to_string(path).expect("Unable to read network schedule");
+        let mut schedule = Self::default();
+        content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .for_each(|line| {
+                let fields: Vec<usize> = line
+                    .split_whitespace()
+                    .map(|field| field.parse().expect("Malformed network schedule"))
+                    .collect();
+                assert_eq!(fields.len(), 4, "Malformed network schedule line: {line}");
+                schedule.insert(Jiffies(fields[0]), fields[1], fields[2], Jiffies(fields[3]));
+            });
+        schedule
+    }
+}
+
+// What the latency queue does with link latencies
+pub(crate) enum ScheduleMode {
+    Off,
+    Record(NetworkSchedule),
+    Replay(NetworkSchedule),
+}
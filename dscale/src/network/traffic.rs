@@ -0,0 +1,209 @@
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+use log::debug;
+
+use crate::{
+    Distributions, Message, ProcessId,
+    actor::SimulationActor,
+    destination::Destination,
+    global::tracing,
+    message::Shared,
+    network::NetworkActor,
+    now,
+    random::{Randomizer, Seed},
+    time::Jiffies,
+};
+
+/// Background cross-traffic between two pools following an on/off burst model.
+///
+/// Consensus protocols behave very differently on an idle network and under
+/// contention. A background flow makes every process of the `from` pool send
+/// messages of `message_size` bytes to random processes of the `to` pool every
+/// `interval` jiffies during a burst. Bursts and silences alternate, their
+/// lengths are sampled from `on` and `off` distributions; the flow starts with a
+/// silence.
+///
+/// Cross-traffic goes through the network like any other message: it gets
+/// latency, occupies bandwidth of receiving NICs (see [`BandwidthDescription`])
+/// and space in bounded inboxes (see [`InboxDescription`]), and is lost to
+/// partitions. It is consumed by the network on arrival and never reaches
+/// [`ProcessHandle::on_message`], so protocols need no changes.
+///
+/// Bounded bandwidth is accounted since the start of the run: silences leave
+/// unused capacity for the following bursts. Bursts delay other messages once
+/// the cross-traffic received by a NIC outweighs its capacity so far.
+///
+/// Configured with [`SimulationBuilder::background_traffic`].
+///
+/// # Examples
+///
+/// ```rust
+/// use dscale::{SimulationBuilder, BackgroundTraffic, BandwidthDescription, Distributions, Jiffies};
+///
+/// let builder = SimulationBuilder::default()
+///     .add_pool::<Replica>("replicas", 4)
+///     .nic_bandwidth(BandwidthDescription::Bounded(1_000))
+///     .background_traffic(BackgroundTraffic {
+///         from: "replicas",
+///         to: "replicas",
+///         message_size: 4_000,
+///         interval: Jiffies(2),
+///         // Bursts of ~50 jiffies every ~200 jiffies
+///         on: Distributions::Uniform(Jiffies(40), Jiffies(60)),
+///         off: Distributions::Normal(Jiffies(200), Jiffies(50)),
+///     });
+/// # #[derive(Default)]
+/// # struct Replica;
+/// # impl dscale::ProcessHandle for Replica {
+/// #     fn start(&mut self) {}
+/// #     fn on_message(&mut self, from: dscale::ProcessId, message: dscale::MessagePtr) {}
+/// #     fn on_timer(&mut self, id: dscale::TimerId) {}
+/// # }
+/// ```
+///
+/// [`BandwidthDescription`]: crate::BandwidthDescription
+/// [`InboxDescription`]: crate::InboxDescription
+/// [`ProcessHandle::on_message`]: crate::ProcessHandle::on_message
+/// [`SimulationBuilder::background_traffic`]: crate::SimulationBuilder::background_traffic
+#[derive(Clone, Copy)]
+pub struct BackgroundTraffic {
+    /// Pool of senders.
+    pub from: &'static str,
+    /// Pool of receivers, may be the same as `from`.
+    pub to: &'static str,
+    /// Bytes of every message.
+    pub message_size: usize,
+    /// Time between two messages of a sender during a burst.
+    pub interval: Jiffies,
+    /// Length of a burst, at least one jiffy.
+    pub on: Distributions,
+    /// Length of a silence between bursts.
+    pub off: Distributions,
+}
+
+// Filler consumed by the network on arrival
+pub(crate) struct CrossTraffic(usize);
+
+impl Message for CrossTraffic {
+    fn virtual_size(&self) -> usize {
+        self.0
+    }
+}
+
+pub(crate) struct Flow {
+    traffic: BackgroundTraffic,
+    senders: Vec<ProcessId>,
+    receivers: Vec<ProcessId>,
+    burst_end: Jiffies,
+}
+
+impl Flow {
+    pub(crate) fn new(
+        traffic: BackgroundTraffic,
+        senders: Vec<ProcessId>,
+        receivers: Vec<ProcessId>,
+    ) -> Self {
+        assert!(
+            traffic.interval > Jiffies(0),
+            "Interval of background traffic should be positive"
+        );
+        Self {
+            traffic,
+            senders,
+            receivers,
+            burst_end: Jiffies(0),
+        }
+    }
+}
+
+pub(crate) struct TrafficGenerator {
+    flows: Vec<Flow>,
+    emissions: BinaryHeap<Reverse<(Jiffies, usize)>>, // usize - index of the flow
+    randomizer: Randomizer,
+    network: NetworkActor,
+}
+
+impl TrafficGenerator {
+    pub(crate) fn new(flows: Vec<Flow>, seed: Seed, network: NetworkActor) -> Self {
+        Self {
+            flows,
+            emissions: BinaryHeap::new(),
+            randomizer: Randomizer::new(seed),
+            network,
+        }
+    }
+
+    // Silence after the burst, then the next burst starts
+    fn schedule_burst(&mut self, flow: usize) {
+        let off = self.randomizer.random_usize(self.flows[flow].traffic.off);
+        let on = self
+            .randomizer
+            .random_usize(self.flows[flow].traffic.on)
+            .max(1);
+        tracing::on_draw(
+            "traffic",
+            None,
+            || format!("burst of flow {flow}"),
+            &[off, on],
+        );
+        let start = self.flows[flow].burst_end + Jiffies(off);
+        self.flows[flow].burst_end = start + Jiffies(on);
+        self.emissions.push(Reverse((start, flow)));
+    }
+
+    fn emit(&mut self, flow: usize) {
+        let Flow {
+            traffic,
+            senders,
+            receivers,
+            ..
+        } = &self.flows[flow];
+        for sender in senders {
+            let targets: Vec<ProcessId> = receivers
+                .iter()
+                .copied()
+                .filter(|receiver| receiver != sender)
+                .collect();
+            if targets.is_empty() {
+                continue;
+            }
+            let target = self.randomizer.choose_from_slice(&targets);
+            tracing::on_draw(
+                "traffic",
+                Some(*sender),
+                || format!("target of flow {flow}"),
+                &[target],
+            );
+            self.network.borrow_mut().submit_single_message(
+                Shared::new(CrossTraffic(traffic.message_size)),
+                *sender,
+                Destination::To(target),
+            );
+        }
+    }
+}
+
+impl SimulationActor for TrafficGenerator {
+    fn start(&mut self) {
+        (0..self.flows.len()).for_each(|flow| {
+            self.flows[flow].burst_end = now();
+            self.schedule_burst(flow);
+        });
+    }
+
+    fn peek_closest(&self) -> Option<Jiffies> {
+        self.emissions.peek().map(|entry| entry.0.0)
+    }
+
+    fn step(&mut self) {
+        let (at, flow) = self.emissions.pop().expect("Should not be empty").0;
+        debug!("Emitting background traffic of flow {flow}");
+        self.emit(flow);
+        let next = at + self.flows[flow].traffic.interval;
+        if next < self.flows[flow].burst_end {
+            self.emissions.push(Reverse((next, flow)));
+        } else {
+            self.schedule_burst(flow);
+        }
+    }
+}
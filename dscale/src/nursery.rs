@@ -1,51 +1,260 @@
 use std::{
-    collections::{BTreeMap, btree_map::Keys},
+    any::Any,
+    cell::{Cell, RefCell},
+    collections::{BTreeMap, BTreeSet},
     rc::Rc,
 };
 
 use log::debug;
 
 use crate::{
-    ProcessId, dscale_message::DScaleMessage, global::set_process,
-    process_handle::MutableProcessHandle,
+    Jiffies, ProcessId,
+    digest::RunDigest,
+    dscale_message::DScaleMessage,
+    global::{
+        disk, filter,
+        memory::{self, MemoryPolicy},
+        now, set_process, tracing, wal,
+    },
+    helpers::assertion,
+    message_type,
+    network::SharedReachability,
+    process_handle::{MutableProcessHandle, ProcessFactory, Spawned},
+    window::DebugWindow,
 };
 
-pub(crate) type HandlerMap = BTreeMap<ProcessId, MutableProcessHandle>; // btree for deterministic iterators
+pub(crate) type FactoryMap = BTreeMap<ProcessId, ProcessFactory>; // btree for deterministic iterators
 
 pub(crate) struct Nursery {
-    procs: HandlerMap,
+    factories: FactoryMap,
+    procs: RefCell<BTreeMap<ProcessId, Spawned>>,
+    digest: Cell<RunDigest>,
+    crashed: Vec<Cell<bool>>,
+    incarnations: Vec<Cell<usize>>,
+    start_delays: BTreeMap<ProcessId, Jiffies>,
+    postponed: RefCell<BTreeSet<(Jiffies, ProcessId)>>, // Down until their start
+    retired: RefCell<Vec<ProcessId>>, // Crashed or restarted since the last take_retired
+    reachability: SharedReachability,
+    window: Option<DebugWindow>,
 }
 
 impl Nursery {
-    pub(crate) fn new(procs: HandlerMap) -> Rc<Self> {
-        Rc::new(Self { procs })
+    pub(crate) fn new(
+        factories: FactoryMap,
+        start_delays: BTreeMap<ProcessId, Jiffies>,
+        reachability: SharedReachability,
+        window: Option<DebugWindow>,
+    ) -> Rc<Self> {
+        let procs = factories
+            .iter()
+            .map(|(id, factory)| (*id, factory()))
+            .collect();
+        let crashed = (0..=factories.len()).map(|_| Cell::new(false)).collect();
+        let incarnations = (0..=factories.len()).map(|_| Cell::new(0)).collect();
+        Rc::new(Self {
+            factories,
+            procs: RefCell::new(procs),
+            digest: Cell::new(RunDigest::default()),
+            crashed,
+            incarnations,
+            start_delays,
+            postponed: RefCell::new(BTreeSet::new()),
+            retired: RefCell::new(Vec::new()),
+            reachability,
+            window,
+        })
     }
 
-    pub(crate) fn start_single(&self, id: ProcessId) {
-        set_process(id);
-        debug!("Starting P{id}");
+    fn handle(&self, id: ProcessId) -> MutableProcessHandle {
         self.procs
+            .borrow()
             .get(&id)
             .expect("Invalid ProcessId")
+            .handle
+            .clone()
+    }
+
+    // State of the current incarnation, kept after a crash
+    pub(crate) fn state(&self, id: ProcessId) -> Option<Rc<dyn Any>> {
+        Some(self.procs.borrow().get(&id)?.state.clone())
+    }
+
+    // A process with a start delay is down, as if crashed, until start_postponed
+    pub(crate) fn start_or_postpone(&self, id: ProcessId) {
+        match self.start_delays.get(&id) {
+            Some(delay) if delay.0 > 0 => {
+                debug!("Postponing start of P{id} by {delay}");
+                self.set_crashed(id, true);
+                self.postponed.borrow_mut().insert((now() + *delay, id));
+            }
+            _ => self.start_single(id),
+        }
+    }
+
+    fn set_crashed(&self, id: ProcessId, crashed: bool) {
+        self.crashed[id].set(crashed);
+        self.reachability.borrow_mut().record_down(id, crashed);
+    }
+
+    pub(crate) fn is_postponed(&self, id: ProcessId) -> bool {
+        self.postponed
+            .borrow()
+            .iter()
+            .any(|(_, postponed)| *postponed == id)
+    }
+
+    pub(crate) fn next_start(&self) -> Option<Jiffies> {
+        self.postponed.borrow().first().map(|(at, _)| *at)
+    }
+
+    pub(crate) fn start_postponed(&self) {
+        let (_, id) = self
+            .postponed
+            .borrow_mut()
+            .pop_first()
+            .expect("Postponed start exists");
+        self.set_crashed(id, false);
+        self.start_single(id);
+    }
+
+    // Crashes and restarts before the postponed start replace it
+    fn cancel_start(&self, id: ProcessId) {
+        self.postponed
             .borrow_mut()
-            .start();
+            .retain(|(_, postponed)| *postponed != id);
+    }
+
+    pub(crate) fn start_single(&self, id: ProcessId) {
+        set_process(id);
+        debug!("Starting P{id}");
+        self.observe(|| format!("P{id} starts"));
+        tracing::on_step();
+        let mut digest = self.digest.get();
+        digest.record_start(now(), id);
+        self.digest.set(digest);
+        self.handle(id).borrow_mut().start();
+        self.enforce_memory_budget(id);
     }
 
     pub(crate) fn deliver(&self, from: ProcessId, to: ProcessId, m: DScaleMessage) {
-        let mut handle = self.procs.get(&to).expect("Invalid ProcessId").borrow_mut();
+        if self.is_crashed(to) {
+            debug!("Skipping step for crashed P{to}");
+            self.observe(|| format!("{} dropped, P{to} is crashed", describe(from, to, &m)));
+            return;
+        }
         set_process(to);
+        if let DScaleMessage::NetworkMessage(..) = &m
+            && memory::exceeded(to) == Some(MemoryPolicy::Backpressure)
+        {
+            self.observe(|| format!("{} refused, P{to} is out of memory", describe(from, to, &m)));
+            memory::record_refused(to);
+            return;
+        }
+        if let DScaleMessage::NetworkMessage(ptr, _) = &m
+            && !filter::admits(from, to, ptr)
+        {
+            self.observe(|| format!("{} filtered out", describe(from, to, &m)));
+            return;
+        }
+        self.observe(|| format!("{} delivered", describe(from, to, &m)));
+        let handle = self.handle(to);
+        let mut handle = handle.borrow_mut();
         debug!("Executing step for From: P{} | To: P{}", to, from);
+        let mut digest = self.digest.get();
+        digest.record_step(now(), from, to, &m);
+        self.digest.set(digest);
         match m {
-            DScaleMessage::NetworkMessage(ptr) => handle.on_message(from, ptr),
-            DScaleMessage::Timer(id) => handle.on_timer(id),
+            DScaleMessage::NetworkMessage(ptr, size) => {
+                assertion::record_message(from, to, size);
+                tracing::on_deliver(to, &ptr.0);
+                handle.on_message(from, ptr)
+            }
+            DScaleMessage::Timer(id) => {
+                assertion::record_timer_fired(to, id);
+                tracing::on_step();
+                handle.on_timer(id)
+            }
+        }
+        self.enforce_memory_budget(to);
+    }
+
+    // Replaces process state with a fresh one and starts it again
+    pub(crate) fn restart(&self, id: ProcessId) {
+        self.observe(|| format!("P{id} restarts"));
+        wal::on_crash(id);
+        disk::on_restart(id);
+        filter::on_restart(id);
+        memory::on_restart(id);
+        self.cancel_start(id);
+        let factory = self.factories.get(&id).expect("Invalid ProcessId");
+        self.procs.borrow_mut().insert(id, factory());
+        self.set_crashed(id, false);
+        self.incarnations[id].set(self.incarnations[id].get() + 1);
+        self.retired.borrow_mut().push(id);
+        assertion::forget_pending_timers(id);
+        self.start_single(id);
+    }
+
+    // Timers scheduled by previous incarnations never fire
+    pub(crate) fn incarnation(&self, id: ProcessId) -> usize {
+        self.incarnations[id].get()
+    }
+
+    // Processes whose timers and scheduled sends will never fire
+    pub(crate) fn take_retired(&self) -> Vec<ProcessId> {
+        self.retired.take()
+    }
+
+    pub(crate) fn ids(&self) -> impl Iterator<Item = &ProcessId> {
+        self.factories.keys()
+    }
+
+    pub(crate) fn digest(&self) -> RunDigest {
+        self.digest.get()
+    }
+
+    pub(crate) fn crash(&self, id: ProcessId) {
+        debug!("Crashing P{id}");
+        self.observe(|| format!("P{id} crashes"));
+        self.cancel_start(id);
+        self.set_crashed(id, true);
+        self.retired.borrow_mut().push(id);
+        assertion::forget_pending_timers(id);
+    }
+
+    // Out of memory kills the process once the handler which exceeded the budget returns
+    fn enforce_memory_budget(&self, id: ProcessId) {
+        if memory::exceeded(id) == Some(MemoryPolicy::Crash) {
+            memory::record_out_of_memory(id);
+            wal::on_crash(id);
+            self.crash(id);
         }
     }
 
-    pub(crate) fn keys(&self) -> Keys<'_, ProcessId, MutableProcessHandle> {
-        self.procs.keys()
+    pub(crate) fn is_crashed(&self, id: ProcessId) -> bool {
+        self.crashed[id].get()
     }
 
     pub(crate) fn size(&self) -> usize {
-        self.procs.len()
+        self.factories.len()
+    }
+
+    // Engine events are reported through the nursery, which every actor has
+    pub(crate) fn observe(&self, event: impl FnOnce() -> String) {
+        if let Some(window) = &self.window {
+            window.observe(event);
+        }
+    }
+}
+
+fn describe(from: ProcessId, to: ProcessId, m: &DScaleMessage) -> String {
+    match m {
+        DScaleMessage::NetworkMessage(ptr, size) => {
+            format!(
+                "P{from} -> P{to}: {} ({size} bytes)",
+                message_type::of(ptr.0.as_ref()).name
+            )
+        }
+        DScaleMessage::Timer(id) => format!("P{to}: timer {id}"),
     }
 }
@@ -0,0 +1,163 @@
+//! Introspection of events waiting in the simulation queues.
+//!
+//! Invariant checkers and debugging code can ask the [`Simulation`] what is
+//! still ahead of a process instead of guessing from silence: see
+//! [`Simulation::pending_events_for`] and [`Simulation::queue_stats`].
+//!
+//! [`Simulation`]: crate::Simulation
+//! [`Simulation::pending_events_for`]: crate::Simulation::pending_events_for
+//! [`Simulation::queue_stats`]: crate::Simulation::queue_stats
+//!
+//! Events which can never happen are removed from the queues as soon as that
+//! is known, see [`Simulation::pruned_events`].
+//!
+//! [`Simulation::pruned_events`]: crate::Simulation::pruned_events
+
+use std::fmt::{self, Display};
+
+/// Events of a single process waiting in the simulation queues.
+///
+/// Only events which can still reach the process are counted: a crashed process
+/// has nothing pending, timers of previous incarnations of a restarted process
+/// and messages dropped from a bounded inbox are not counted. Messages in flight
+/// may still be lost on the way, e.g. to a partition.
+///
+/// Obtained with [`Simulation::pending_events_for`]. Displays as a short human
+/// readable summary.
+///
+/// [`Simulation::pending_events_for`]: crate::Simulation::pending_events_for
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PendingEvents {
+    /// Messages addressed to the process which are in the network.
+    pub in_flight: usize,
+    /// Sends of the process scheduled for the future with [`send_to_at`] or [`broadcast_after`].
+    ///
+    /// [`send_to_at`]: crate::send_to_at
+    /// [`broadcast_after`]: crate::broadcast_after
+    pub scheduled: usize,
+    /// Timers of the process which have not fired yet.
+    pub timers: usize,
+}
+
+impl PendingEvents {
+    /// Number of all pending events.
+    pub fn total(&self) -> usize {
+        self.in_flight + self.scheduled + self.timers
+    }
+
+    /// Whether nothing is pending: the process will not run again unless
+    /// somebody else sends it a message.
+    pub fn is_empty(&self) -> bool {
+        self.total() == 0
+    }
+}
+
+impl Display for PendingEvents {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} in-flight {}, {} scheduled {}, {} {} pending",
+            self.in_flight,
+            plural(self.in_flight, "message", "messages"),
+            self.scheduled,
+            plural(self.scheduled, "send", "sends"),
+            self.timers,
+            plural(self.timers, "timer", "timers"),
+        )
+    }
+}
+
+/// Events of all processes waiting in the simulation queues.
+///
+/// Counts follow the rules of [`PendingEvents`], summed over all processes.
+/// Obtained with [`Simulation::queue_stats`].
+///
+/// [`Simulation::queue_stats`]: crate::Simulation::queue_stats
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct QueueStats {
+    /// Messages in the network.
+    pub in_flight: usize,
+    /// Sends scheduled for the future.
+    pub scheduled: usize,
+    /// Timers which have not fired yet.
+    pub timers: usize,
+    /// Events of the [`Scenario`] which have not happened yet.
+    ///
+    /// [`Scenario`]: crate::Scenario
+    pub scenario_events: usize,
+}
+
+impl QueueStats {
+    pub(crate) fn add(&mut self, pending: &PendingEvents) {
+        self.in_flight += pending.in_flight;
+        self.scheduled += pending.scheduled;
+        self.timers += pending.timers;
+    }
+
+    /// Number of all pending events.
+    pub fn total(&self) -> usize {
+        self.in_flight + self.scheduled + self.timers + self.scenario_events
+    }
+}
+
+impl Display for QueueStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} in-flight {}, {} scheduled {}, {} {}, {} scenario {} pending",
+            self.in_flight,
+            plural(self.in_flight, "message", "messages"),
+            self.scheduled,
+            plural(self.scheduled, "send", "sends"),
+            self.timers,
+            plural(self.timers, "timer", "timers"),
+            self.scenario_events,
+            plural(self.scenario_events, "event", "events"),
+        )
+    }
+}
+
+/// Events removed from the simulation queues because they could never happen.
+///
+/// Timers and scheduled sends of a process are pruned when it crashes or
+/// restarts, since they never fire for a later incarnation. Messages in flight
+/// are pruned only with [`SimulationBuilder::prune_undeliverable`]. Obtained
+/// with [`Simulation::pruned_events`].
+///
+/// [`SimulationBuilder::prune_undeliverable`]: crate::SimulationBuilder::prune_undeliverable
+/// [`Simulation::pruned_events`]: crate::Simulation::pruned_events
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PrunedEvents {
+    /// Messages removed from the network, or never sent into it.
+    pub in_flight: usize,
+    /// Scheduled sends of processes which went down.
+    pub scheduled: usize,
+    /// Timers of processes which went down.
+    pub timers: usize,
+}
+
+impl PrunedEvents {
+    /// Number of all pruned events.
+    pub fn total(&self) -> usize {
+        self.in_flight + self.scheduled + self.timers
+    }
+}
+
+impl Display for PrunedEvents {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} in-flight {}, {} scheduled {}, {} {} pruned",
+            self.in_flight,
+            plural(self.in_flight, "message", "messages"),
+            self.scheduled,
+            plural(self.scheduled, "send", "sends"),
+            self.timers,
+            plural(self.timers, "timer", "timers"),
+        )
+    }
+}
+
+fn plural(count: usize, one: &'static str, many: &'static str) -> &'static str {
+    if count == 1 { one } else { many }
+}
@@ -4,7 +4,7 @@
 //! by all processes in DScale simulations, as well as the `ProcessId` type used
 //! for process identification throughout the system.
 
-use std::{cell::RefCell, rc::Rc};
+use std::{any::Any, cell::RefCell, rc::Rc};
 
 use crate::{MessagePtr, time::timer_manager::TimerId};
 
@@ -60,6 +60,24 @@ pub type ProcessId = usize;
 
 pub(crate) type MutableProcessHandle = Rc<RefCell<dyn ProcessHandle>>;
 
+// Creates fresh process state, on build and on every restart
+pub(crate) type ProcessFactory = fn() -> Spawned;
+
+// The same process behind the trait and as its concrete type, see Simulation::process
+#[derive(Clone)]
+pub(crate) struct Spawned {
+    pub(crate) handle: MutableProcessHandle,
+    pub(crate) state: Rc<dyn Any>,
+}
+
+pub(crate) fn spawn<P: ProcessHandle + Default + 'static>() -> Spawned {
+    let process = Rc::new(RefCell::new(P::default()));
+    Spawned {
+        handle: process.clone(),
+        state: process,
+    }
+}
+
 /// Core trait that defines the behavior of a process in DScale simulations.
 ///
 /// `ProcessHandle` is the fundamental interface that all processes must implement
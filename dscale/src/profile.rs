@@ -0,0 +1,171 @@
+//! Preset environments for common kinds of experiments.
+//!
+//! Every example main used to configure latencies, bandwidth and faults with
+//! its own magic numbers. A [`Profile`] bundles a sensible environment, so an
+//! experiment starts from it in one line with [`SimulationBuilder::profile`]
+//! and only overrides what it studies.
+//!
+//! [`SimulationBuilder::profile`]: crate::SimulationBuilder::profile
+
+use std::time::Duration;
+
+use crate::{
+    Bandwidth, BandwidthDescription, ChannelOrdering, Distributions, Jiffies, LatencyDescription,
+    ProcessHandle, SimulationBuilder, TieBreak,
+    scenario::{heal, inject_partition, restart},
+};
+
+/// A preset environment of a pool of processes.
+///
+/// Applied with [`SimulationBuilder::profile`], which adds the pool and
+/// configures it. All profiles set the [jiffy duration] to one millisecond, so
+/// latencies below are given in milliseconds. Bandwidth and tie-break order are
+/// settings of the whole simulation, so the last applied profile wins; every
+/// setting can be overridden by calling the builder method after the profile.
+///
+/// # Examples
+///
+/// ```rust
+/// use dscale::{SimulationBuilder, Jiffies, Profile};
+///
+/// let simulation = SimulationBuilder::default()
+///     .profile::<MyProcess>("replicas", Profile::Wan3Regions(9))
+///     .time_budget(Jiffies(10_000))
+///     .build();
+/// # #[derive(Default)]
+/// # struct MyProcess;
+/// # impl dscale::ProcessHandle for MyProcess {
+/// #     fn start(&mut self) {}
+/// #     fn on_message(&mut self, from: dscale::ProcessId, message: dscale::MessagePtr) {}
+/// #     fn on_timer(&mut self, id: dscale::TimerId) {}
+/// # }
+/// ```
+///
+/// [`SimulationBuilder::profile`]: crate::SimulationBuilder::profile
+/// [jiffy duration]: crate::SimulationBuilder::jiffy_duration
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Profile {
+    /// `n` processes in one datacenter: 0-1 ms latency, 10 Gbit/s NICs and
+    /// FIFO channels, like TCP connections. No faults.
+    LanCluster(usize),
+
+    /// `n` processes spread evenly over the regions of [`Profile::WAN_REGIONS`]
+    /// (earlier regions get the remainder): 1-2 ms latency within a region,
+    /// 40 ms between `us-east` and `eu-west`, 80 ms between `eu-west` and
+    /// `ap-southeast`, 110 ms between `us-east` and `ap-southeast` (one way,
+    /// with jitter), 1 Gbit/s NICs and FIFO channels. No faults.
+    Wan3Regions(usize),
+
+    /// `n` processes on a hostile asynchronous network, for checking safety
+    /// rather than measuring performance: 1-300 ms latency with arbitrary
+    /// reordering, unbounded bandwidth, [`TieBreak::Lifo`] order of simultaneous
+    /// events and clocks drifting by ±5% in turns. Faults: a minority of
+    /// `(n - 1) / 3` processes is partitioned away between 1 000 and 3 000 ms,
+    /// losing messages in flight, and the last process restarts at 5 000 ms.
+    AdversarialAsync(usize),
+}
+
+impl Profile {
+    /// Regions of [`Profile::Wan3Regions`].
+    pub const WAN_REGIONS: [&'static str; 3] = ["us-east", "eu-west", "ap-southeast"];
+
+    /// Number of processes added by the profile.
+    pub fn size(&self) -> usize {
+        match *self {
+            Profile::LanCluster(n) | Profile::Wan3Regions(n) | Profile::AdversarialAsync(n) => n,
+        }
+    }
+
+    pub(crate) fn apply<P: ProcessHandle + Default + 'static>(
+        self,
+        builder: SimulationBuilder,
+        pool: &'static str,
+    ) -> SimulationBuilder {
+        let builder = builder.jiffy_duration(Duration::from_millis(1));
+        match self {
+            Profile::LanCluster(n) => builder
+                .add_pool::<P>(pool, n)
+                .latency_topology(&[LatencyDescription::WithinPool(
+                    pool,
+                    Distributions::Uniform(Jiffies(0), Jiffies(1)),
+                )])
+                .nic_bandwidth(BandwidthDescription::Rate(Bandwidth::gbps(10)))
+                .channel_ordering(pool, pool, ChannelOrdering::Fifo),
+            Profile::Wan3Regions(n) => wan_3_regions::<P>(builder, pool, n),
+            Profile::AdversarialAsync(n) => adversarial_async::<P>(builder, pool, n),
+        }
+    }
+}
+
+fn wan_3_regions<P: ProcessHandle + Default + 'static>(
+    mut builder: SimulationBuilder,
+    pool: &'static str,
+    n: usize,
+) -> SimulationBuilder {
+    let regions = Profile::WAN_REGIONS.len();
+    let mut populated = Vec::new();
+    for (index, region) in Profile::WAN_REGIONS.into_iter().enumerate() {
+        let size = n / regions + usize::from(index < n % regions);
+        if size > 0 {
+            builder = builder.add_pool_in_region::<P>(pool, region, size);
+            populated.push(region);
+        }
+    }
+
+    // One-way latencies of the regions, links to empty regions are left out
+    let [us, eu, ap] = Profile::WAN_REGIONS;
+    let links = [
+        (us, us, Distributions::Uniform(Jiffies(1), Jiffies(2))),
+        (eu, eu, Distributions::Uniform(Jiffies(1), Jiffies(2))),
+        (ap, ap, Distributions::Uniform(Jiffies(1), Jiffies(2))),
+        (us, eu, Distributions::Normal(Jiffies(40), Jiffies(2))),
+        (eu, ap, Distributions::Normal(Jiffies(80), Jiffies(4))),
+        (us, ap, Distributions::Normal(Jiffies(110), Jiffies(5))),
+    ];
+    let latencies: Vec<LatencyDescription> = links
+        .into_iter()
+        .filter(|(a, b, _)| populated.contains(a) && populated.contains(b))
+        .map(|(a, b, distribution)| match a == b {
+            true => LatencyDescription::WithinRegion(a, distribution),
+            false => LatencyDescription::BetweenRegions(a, b, distribution),
+        })
+        .collect();
+
+    builder
+        .latency_topology(&latencies)
+        .nic_bandwidth(BandwidthDescription::Rate(Bandwidth::gbps(1)))
+        .channel_ordering(pool, pool, ChannelOrdering::Fifo)
+}
+
+fn adversarial_async<P: ProcessHandle + Default + 'static>(
+    builder: SimulationBuilder,
+    pool: &'static str,
+    n: usize,
+) -> SimulationBuilder {
+    let mut builder = builder
+        .add_pool::<P>(pool, n)
+        .latency_topology(&[LatencyDescription::WithinPool(
+            pool,
+            Distributions::Uniform(Jiffies(1), Jiffies(300)),
+        )])
+        .nic_bandwidth(BandwidthDescription::Unbounded)
+        .tie_break(TieBreak::Lifo);
+
+    let members = builder.pool_members(pool);
+    let added = &members[members.len() - n..]; // The pool may have existed before
+    for (index, id) in added.iter().enumerate() {
+        let drift = if index % 2 == 0 { 0.05 } else { -0.05 };
+        builder = builder.process_clock_drift(*id, drift);
+    }
+
+    let minority = &added[..n.saturating_sub(1) / 3];
+    if !minority.is_empty() {
+        builder = builder
+            .schedule(Jiffies(1_000), inject_partition(&[minority]))
+            .schedule(Jiffies(3_000), heal());
+    }
+    match added.last() {
+        Some(last) if n > 1 => builder.schedule(Jiffies(5_000), restart(*last)),
+        _ => builder,
+    }
+}
@@ -1,23 +1,40 @@
-use indicatif::{ProgressBar, ProgressStyle};
+use std::time::{Duration, Instant};
+
+use indicatif::{HumanDuration, ProgressBar, ProgressStyle};
 use log::log_enabled;
 
 use crate::time::Jiffies;
 
 const K_PROGRESS_TIMES: usize = 100;
+const K_PROGRESS_SCALE: u64 = 1000;
+// Refresh at least this often even if the clock is stuck on a dense jiffy
+const K_EVENTS_PER_REFRESH: usize = 1 << 16;
+// Weight of the latest window in the moving averages
+const K_SMOOTHING: f64 = 0.3;
 
+// Progress is measured in events, not jiffies: the remaining part of the time
+// budget is converted into expected events using the recently observed density,
+// so dense and sparse phases of the run move the bar at the same pace.
 pub(crate) struct Bar {
     bar: ProgressBar,
-    prev_log: usize,
+    enabled: bool,
+    total: Jiffies,
     delta: usize,
+    events: usize,
+    window_events: usize,
+    window_start: (Jiffies, Instant),
+    events_per_jiffy: Option<f64>,
+    events_per_sec: Option<f64>,
 }
 
 impl Bar {
     pub(crate) fn new(total: Jiffies) -> Self {
-        let bar = if log_enabled!(log::Level::Info) {
-            let bar = ProgressBar::new(total.0 as u64);
+        let enabled = log_enabled!(log::Level::Info);
+        let bar = if enabled {
+            let bar = ProgressBar::new(K_PROGRESS_SCALE);
             bar.set_style(
                 ProgressStyle::default_bar()
-                    .template("[{bar:60.green}] {pos}/{len} Jiffies")
+                    .template("[{bar:60.green}] {percent}% {msg}")
                     .unwrap(),
             );
             bar.set_position(0);
@@ -28,20 +45,82 @@ impl Bar {
 
         Self {
             bar: bar,
-            prev_log: 0,
-            delta: total.0 / K_PROGRESS_TIMES,
+            enabled,
+            total,
+            delta: (total.0 / K_PROGRESS_TIMES).max(1),
+            events: 0,
+            window_events: 0,
+            window_start: (Jiffies(0), Instant::now()),
+            events_per_jiffy: None,
+            events_per_sec: None,
         }
     }
 
+    // Called once per processed event
     pub(crate) fn make_progress(&mut self, time: Jiffies) {
-        let d = time.0 / self.delta;
-        if d > self.prev_log {
-            self.prev_log = d;
-            self.bar.set_position(time.0 as u64);
+        if !self.enabled {
+            return;
+        }
+        self.events += 1;
+        self.window_events += 1;
+        if time.0 - self.window_start.0.0 >= self.delta
+            || self.window_events >= K_EVENTS_PER_REFRESH
+        {
+            self.refresh(time);
         }
     }
 
     pub(crate) fn finish(&mut self) {
+        if self.enabled {
+            self.bar.set_position(K_PROGRESS_SCALE);
+        }
         self.bar.finish();
     }
+
+    fn refresh(&mut self, time: Jiffies) {
+        let (start_time, start_instant) = self.window_start;
+        let jiffies = time.0 - start_time.0;
+        let wall = start_instant.elapsed().as_secs_f64();
+        let events = self.window_events as f64;
+
+        if jiffies > 0 {
+            self.events_per_jiffy = Some(smooth(self.events_per_jiffy, events / jiffies as f64));
+        }
+        if wall > 0.0 {
+            self.events_per_sec = Some(smooth(self.events_per_sec, events / wall));
+        }
+        self.window_events = 0;
+        self.window_start = (time, Instant::now());
+
+        let remaining_jiffies = self.total.0.saturating_sub(time.0) as f64;
+        let (fraction, remaining_events) = match self.events_per_jiffy {
+            Some(density) => {
+                let remaining = remaining_jiffies * density;
+                let done = self.events as f64;
+                (done / (done + remaining), remaining)
+            }
+            // Nothing but a single jiffy observed yet: fall back to time
+            None => (time.0 as f64 / self.total.0.max(1) as f64, 0.0),
+        };
+        self.bar
+            .set_position((fraction.min(1.0) * K_PROGRESS_SCALE as f64) as u64);
+
+        let rate = self.events_per_sec.unwrap_or(0.0);
+        let eta = if rate > 0.0 && self.events_per_jiffy.is_some() {
+            HumanDuration(Duration::from_secs_f64(remaining_events / rate)).to_string()
+        } else {
+            "?".to_string()
+        };
+        self.bar.set_message(format!(
+            "{}/{} Jiffies, {:.0} events/s, ETA {}",
+            time.0, self.total.0, rate, eta
+        ));
+    }
+}
+
+fn smooth(prev: Option<f64>, sample: f64) -> f64 {
+    match prev {
+        Some(prev) => prev + K_SMOOTHING * (sample - prev),
+        None => sample,
+    }
 }
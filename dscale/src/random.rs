@@ -69,13 +69,23 @@ pub type Seed = u64;
 ///
 /// [`Jiffies`]: crate::Jiffies
 /// [`LatencyDescription`]: crate::LatencyDescription
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug)]
 pub enum Distributions {
     Uniform(Jiffies, Jiffies),
     Bernoulli(f64, Jiffies),
     Normal(Jiffies, Jiffies),
 }
 
+impl Distributions {
+    pub(crate) fn mean(&self) -> f64 {
+        match *self {
+            Distributions::Uniform(Jiffies(from), Jiffies(to)) => (from + to) as f64 / 2.0,
+            Distributions::Bernoulli(p, Jiffies(val)) => p * val as f64,
+            Distributions::Normal(Jiffies(mean), _) => mean as f64,
+        }
+    }
+}
+
 pub struct Randomizer {
     rnd: rand::rngs::StdRng,
 }
@@ -109,4 +119,75 @@ impl Randomizer {
             .copied()
             .expect("Chose from empty slice")
     }
+
+    // Distinct elements in random order
+    pub fn choose_multiple_from_slice<T: Copy>(&mut self, from: &[T], amount: usize) -> Vec<T> {
+        from.choose_multiple(&mut self.rnd, amount)
+            .copied()
+            .collect()
+    }
+}
+
+/// Order of events scheduled for the same jiffy, see [`SimulationBuilder::tie_break`].
+///
+/// [`SimulationBuilder::tie_break`]: crate::SimulationBuilder::tie_break
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TieBreak {
+    /// Messages are delivered in the order they were sent and timers fire in the
+    /// order they were scheduled. The order does not depend on the layout of
+    /// engine queues, so runs reproduce across versions.
+    #[default]
+    Fifo,
+    /// The most recently sent messages and scheduled timers go first.
+    Lifo,
+    /// Order shuffled with the salt, see [`SimulationBuilder::shuffle_ties`].
+    ///
+    /// [`SimulationBuilder::shuffle_ties`]: crate::SimulationBuilder::shuffle_ties
+    Shuffled(Seed),
+}
+
+impl TieBreak {
+    // Same policy with an independent salt for another kind of events
+    pub(crate) fn derive(self, offset: Seed) -> Self {
+        match self {
+            TieBreak::Shuffled(salt) => TieBreak::Shuffled(salt.wrapping_add(offset)),
+            policy => policy,
+        }
+    }
+}
+
+// Orders events scheduled for the same jiffy. Events are ordered by the key
+// first and by the order of scheduling after that, so with FIFO every event
+// gets the same key.
+pub(crate) struct TieBreaker {
+    policy: TieBreak,
+    rnd: Option<rand::rngs::StdRng>,
+    seq: u64,
+}
+
+impl TieBreaker {
+    pub(crate) fn new(policy: TieBreak) -> Self {
+        Self {
+            policy,
+            rnd: match policy {
+                TieBreak::Shuffled(salt) => Some(rand::rngs::StdRng::seed_from_u64(salt)),
+                _ => None,
+            },
+            seq: 0,
+        }
+    }
+
+    pub(crate) fn next(&mut self) -> u64 {
+        self.seq += 1;
+        match self.policy {
+            TieBreak::Fifo => 0,
+            TieBreak::Lifo => u64::MAX - self.seq,
+            TieBreak::Shuffled(_) => self.rnd.as_mut().map_or(0, |rnd| rnd.random()),
+        }
+    }
+
+    // Monotone sequence number of the last event, the secondary key
+    pub(crate) fn seq(&self) -> u64 {
+        self.seq
+    }
 }
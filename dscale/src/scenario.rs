@@ -0,0 +1,198 @@
+//! Scripted environmental events of a simulation run.
+//!
+//! This module provides the [`Scenario`] timeline: partitions, crashes, restarts
+//! and heals declared in one place and executed at given simulation times by a
+//! dedicated actor of the simulation engine.
+
+use std::{collections::VecDeque, rc::Rc};
+
+use log::info;
+
+use crate::{
+    ProcessId, actor::SimulationActor, global, network::NetworkActor, nursery::Nursery,
+    time::Jiffies,
+};
+
+/// An environmental event of a [`Scenario`].
+///
+/// Events are usually created with the functions of this module:
+/// [`inject_partition`], [`heal`], [`crash`] and [`restart`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ScenarioEvent {
+    /// Splits processes into groups which can not communicate with each other.
+    Partition(Vec<Vec<ProcessId>>),
+
+    /// Removes the partition, all processes can communicate again.
+    Heal,
+
+    /// Stops the process until it is restarted.
+    Crash(ProcessId),
+
+    /// Replaces the process with a fresh instance, see [`Simulation::restart`].
+    ///
+    /// [`Simulation::restart`]: crate::Simulation::restart
+    Restart(ProcessId),
+}
+
+/// Partitions the network into `groups`.
+///
+/// Messages between processes of different groups are lost: both messages sent
+/// while the partition is in place and messages in flight when it appears.
+/// Processes not listed in any group form one more group together. A new
+/// partition replaces the previous one.
+///
+/// # Examples
+///
+/// ```rust
+/// use dscale::{Jiffies, Scenario, scenario::inject_partition};
+///
+/// // Minority {1, 2} is cut off from {3, 4, 5}
+/// let scenario = Scenario::new().at(Jiffies(1_000), inject_partition(&[&[1, 2], &[3, 4, 5]]));
+/// ```
+pub fn inject_partition(groups: &[&[ProcessId]]) -> ScenarioEvent {
+    ScenarioEvent::Partition(groups.iter().map(|group| group.to_vec()).collect())
+}
+
+/// Removes the partition injected with [`inject_partition`].
+pub fn heal() -> ScenarioEvent {
+    ScenarioEvent::Heal
+}
+
+/// Crashes the process: it stops receiving messages and timers until it is
+/// restarted. Its [`wal`] is truncated according to [`DiskDescription::crash_truncation`].
+///
+/// [`wal`]: crate::global::wal
+/// [`DiskDescription::crash_truncation`]: crate::DiskDescription::crash_truncation
+pub fn crash(id: ProcessId) -> ScenarioEvent {
+    ScenarioEvent::Crash(id)
+}
+
+/// Starts a fresh instance of the process, crashing it first if it is running.
+pub fn restart(id: ProcessId) -> ScenarioEvent {
+    ScenarioEvent::Restart(id)
+}
+
+/// A timeline of environmental events of a simulation run.
+///
+/// `Scenario` gives a single readable place where network partitions, crashes
+/// and restarts of a run are declared. It is passed to
+/// [`SimulationBuilder::scenario`] and executed by the simulation itself, so the
+/// run can be driven with plain [`Simulation::run`]. Events scheduled for the
+/// same time are executed in declaration order, before messages and timers of
+/// that time.
+///
+/// # Examples
+///
+/// ```rust
+/// use dscale::{SimulationBuilder, Jiffies, Scenario};
+/// use dscale::scenario::{crash, heal, inject_partition, restart};
+///
+/// let scenario = Scenario::new()
+///     .at(Jiffies(1_000), inject_partition(&[&[1, 2], &[3, 4, 5]]))
+///     .at(Jiffies(5_000), crash(3))
+///     .at(Jiffies(8_000), heal())
+///     .at(Jiffies(9_000), restart(3));
+///
+/// let mut simulation = SimulationBuilder::default()
+///     .add_pool::<Replica>("replicas", 5)
+///     .scenario(scenario)
+///     .time_budget(Jiffies(10_000))
+///     .build();
+///
+/// simulation.run();
+/// # #[derive(Default)]
+/// # struct Replica;
+/// # impl dscale::ProcessHandle for Replica {
+/// #     fn start(&mut self) { dscale::schedule_timer_after(Jiffies(100)); }
+/// #     fn on_message(&mut self, from: dscale::ProcessId, message: dscale::MessagePtr) {}
+/// #     fn on_timer(&mut self, id: dscale::TimerId) { dscale::schedule_timer_after(Jiffies(100)); }
+/// # }
+/// ```
+///
+/// [`SimulationBuilder::scenario`]: crate::SimulationBuilder::scenario
+/// [`Simulation::run`]: crate::Simulation::run
+#[derive(Clone, Debug, Default)]
+pub struct Scenario {
+    events: Vec<(Jiffies, ScenarioEvent)>,
+}
+
+impl Scenario {
+    /// Creates an empty scenario.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Schedules `event` at simulation time `at`.
+    pub fn at(mut self, at: Jiffies, event: ScenarioEvent) -> Self {
+        self.events.push((at, event));
+        self
+    }
+
+    /// Returns all events ordered by time.
+    pub fn events(&self) -> Vec<(Jiffies, ScenarioEvent)> {
+        let mut events = self.events.clone();
+        events.sort_by_key(|(at, _)| *at); // Stable: declaration order within the same time
+        events
+    }
+}
+
+pub(crate) struct ScenarioActor {
+    events: VecDeque<(Jiffies, ScenarioEvent)>,
+    network: NetworkActor,
+    nursery: Rc<Nursery>,
+}
+
+impl ScenarioActor {
+    pub(crate) fn new(scenario: Scenario, network: NetworkActor, nursery: Rc<Nursery>) -> Self {
+        let events = scenario.events();
+        events.iter().for_each(|(_, event)| match event {
+            ScenarioEvent::Partition(groups) => groups
+                .iter()
+                .flatten()
+                .for_each(|id| assert_process(&nursery, *id)),
+            ScenarioEvent::Crash(id) | ScenarioEvent::Restart(id) => assert_process(&nursery, *id),
+            ScenarioEvent::Heal => {}
+        });
+        Self {
+            events: events.into(),
+            network,
+            nursery,
+        }
+    }
+
+    pub(crate) fn remaining(&self) -> usize {
+        self.events.len()
+    }
+}
+
+fn assert_process(nursery: &Nursery, id: ProcessId) {
+    assert!(
+        (1..=nursery.size()).contains(&id),
+        "Scenario refers to unknown P{id}"
+    );
+}
+
+impl SimulationActor for ScenarioActor {
+    fn start(&mut self) {
+        // Do nothing
+    }
+
+    fn peek_closest(&self) -> Option<Jiffies> {
+        self.events.front().map(|(at, _)| *at)
+    }
+
+    fn step(&mut self) {
+        let (_, event) = self.events.pop_front().expect("Should not be empty");
+        info!("Scenario: {event:?} at {}", global::now());
+        self.nursery.observe(|| format!("Scenario: {event:?}"));
+        match event {
+            ScenarioEvent::Partition(groups) => self.network.borrow_mut().partition(&groups),
+            ScenarioEvent::Heal => self.network.borrow_mut().heal(),
+            ScenarioEvent::Crash(id) => {
+                global::wal::on_crash(id);
+                self.nursery.crash(id);
+            }
+            ScenarioEvent::Restart(id) => self.nursery.restart(id),
+        }
+    }
+}
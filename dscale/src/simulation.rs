@@ -5,19 +5,49 @@
 //! struct orchestrates all simulation actors including network, timers, and
 //! process execution in a deterministic, single-threaded environment.
 
-use std::{cell::RefCell, process::exit, rc::Rc, usize};
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    fmt::Write,
+    panic::{self, AssertUnwindSafe},
+    process::exit,
+    rc::Rc,
+    usize,
+};
 
-use log::{error, info};
+use log::{error, info, warn};
 
 use crate::{
+    ProcessHandle, ProcessId, ProtocolError,
     actor::SharedActor,
-    global,
-    network::{BandwidthDescription, Network},
-    nursery::{HandlerMap, Nursery},
+    artifacts::{self, Bundle},
+    digest::RunDigest,
+    global::{
+        self,
+        disk::DiskDescription,
+        memory::{MemoryLimit, MemoryStats},
+    },
+    helpers::{
+        log_capture,
+        quorum::{self, PendingQuorum},
+    },
+    injector::{Injector, InjectorActor},
+    network::{
+        BandwidthDescription, Flow, Horizon, InboxDescription, InboxStats, LinkStats, Network,
+        NetworkActor, NetworkSchedule, Reachability, RegionLinks, ScheduleMode, SharedInboxStats,
+        SharedLinkStats, TrafficGenerator,
+    },
+    nursery::{FactoryMap, Nursery},
+    pending::{PendingEvents, PrunedEvents, QueueStats},
     progress::Bar,
-    random::{self, Randomizer},
-    time::{Jiffies, timer_manager::TimerManager},
-    topology::{LatencyTopology, PoolListing, Topology},
+    random::{self, Randomizer, TieBreak, TieBreaker},
+    scenario::{Scenario, ScenarioActor},
+    time::{
+        Jiffies,
+        timer_manager::{TimerManager, TimerManagerActor},
+    },
+    topology::Topology,
+    window::DebugWindow,
 };
 
 /// The main simulation engine that executes distributed system simulations.
@@ -82,45 +112,141 @@ use crate::{
 /// [`SimulationBuilder`]: crate::SimulationBuilder
 pub struct Simulation {
     actors: Vec<SharedActor>,
+    network: NetworkActor,
+    timers: TimerManagerActor,
+    scenario: Rc<RefCell<ScenarioActor>>,
+    injector: Injector,
+    nursery: Rc<Nursery>,
+    inbox_stats: SharedInboxStats,
+    link_stats: SharedLinkStats,
+    started: bool,
+    interrupted: bool,
     time_budget: Jiffies,
+    tie_breaker: TieBreaker,
+    event_budget: usize,
+    spent: Vec<usize>, // Actor -> events executed at spent_at
+    spent_at: Jiffies,
+    candidates: Vec<(Jiffies, u64, usize)>, // Reused by peek_closest: (time, tie, actor)
     progress_bar: Bar,
+    artifacts: Option<Bundle>,
 }
 
 impl Simulation {
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn new(
         seed: random::Seed,
+        tie_break: TieBreak,
         time_budget: Jiffies,
+        event_budget: usize,
         bandwidth: BandwidthDescription,
-        latency_topology: LatencyTopology,
-        pool_listing: PoolListing,
-        procs: HandlerMap,
+        links: RegionLinks,
+        inbox: InboxDescription,
+        network_schedule: ScheduleMode,
+        congestion_threshold: Option<usize>,
+        prune_undeliverable: bool,
+        reachability_staleness: Jiffies,
+        disk: DiskDescription,
+        memory_limits: BTreeMap<ProcessId, MemoryLimit>,
+        topology: Rc<Topology>,
+        procs: FactoryMap,
+        start_delays: BTreeMap<ProcessId, Jiffies>,
+        scenario: Scenario,
+        traffic: Vec<Flow>,
+        custom_actors: Vec<SharedActor>,
+        trace_messages: bool,
+        record_trails: bool,
+        debug_window: Option<DebugWindow>,
+        artifacts: Option<Bundle>,
     ) -> Self {
-        let topology = Topology::new_shared(pool_listing.clone(), latency_topology);
-        let nursery = Nursery::new(procs);
+        let reachability = Reachability::new_shared(procs.len(), reachability_staleness);
+        let nursery = Nursery::new(procs, start_delays, reachability.clone(), debug_window);
 
         let network_actor = Rc::new(RefCell::new(Network::new(
             seed,
+            tie_break,
             bandwidth,
+            links,
+            inbox,
+            network_schedule,
+            congestion_threshold,
+            prune_undeliverable.then(|| Horizon::new(&scenario)),
+            reachability.clone(),
             topology.clone(),
             nursery.clone(),
         )));
 
-        let timers_actor = Rc::new(RefCell::new(TimerManager::new(nursery.clone())));
+        let inbox_stats = network_actor.borrow().inbox_stats();
+        let link_stats = network_actor.borrow().link_stats();
+        let timers_actor = Rc::new(RefCell::new(TimerManager::new(
+            nursery.clone(),
+            tie_break.derive(1),
+        )));
 
         global::configuration::setup_global_configuration(nursery.size());
         global::setup_access(
             network_actor.clone(),
             timers_actor.clone(),
+            nursery.clone(),
+            reachability,
             topology,
             Randomizer::new(seed),
         );
+        global::tracing::setup_tracing(trace_messages);
+        crate::helpers::assertion::setup_trails(record_trails);
+        artifacts::setup_artifacts(artifacts.is_some());
+        global::disk::setup_disks(disk, nursery.size());
+        global::filter::setup_filters(nursery.size());
+        global::memory::setup_memory(memory_limits, nursery.size());
+        global::wal::setup_wals(seed, nursery.size());
+
+        let scenario_actor = Rc::new(RefCell::new(ScenarioActor::new(
+            scenario,
+            network_actor.clone(),
+            nursery.clone(),
+        )));
+
+        // Scenario goes first to precede messages and timers of the same jiffy
+        let traffic_actor = Rc::new(RefCell::new(TrafficGenerator::new(
+            traffic,
+            seed,
+            network_actor.clone(),
+        )));
+
+        let (injector_actor, injector) = InjectorActor::new(nursery.clone());
 
-        let actors: Vec<SharedActor> = vec![network_actor, timers_actor];
+        // Custom actors start after processes
+        let mut actors: Vec<SharedActor> = vec![
+            scenario_actor.clone(),
+            network_actor.clone(),
+            timers_actor.clone(),
+            traffic_actor,
+            Rc::new(RefCell::new(injector_actor)),
+        ];
+        actors.extend(custom_actors);
 
         Self {
+            spent: vec![0; actors.len()],
             actors,
+            network: network_actor,
+            timers: timers_actor,
+            scenario: scenario_actor,
+            injector,
+            nursery,
+            inbox_stats,
+            link_stats,
+            started: false,
+            interrupted: false,
             time_budget,
+            // Only shuffling reorders actors, other policies keep their fixed order
+            tie_breaker: TieBreaker::new(match tie_break {
+                TieBreak::Shuffled(_) => tie_break.derive(2),
+                _ => TieBreak::Fifo,
+            }),
+            event_budget,
+            spent_at: Jiffies(0),
+            candidates: Vec::new(),
             progress_bar: Bar::new(time_budget),
+            artifacts,
         }
     }
 
@@ -181,21 +307,878 @@ impl Simulation {
     /// This method will cause the program to exit with an error code if a deadlock
     /// is detected. Use `RUST_LOG=debug` for detailed information about the
     /// deadlock condition.
+    ///
+    /// # Artifacts
+    ///
+    /// If the simulation writes artifacts (see [`SimulationBuilder::artifacts_dir`]),
+    /// the bundle is written once the run finishes, panics or deadlocks.
+    ///
+    /// # Interruption
+    ///
+    /// With the `interrupt` feature the first Ctrl-C stops the run after the
+    /// current event instead of killing the process: the partial report (time
+    /// reached, pending events, stuck quorums) goes into `interrupted.txt` of the
+    /// artifacts bundle, or to stderr without one, and `run` returns normally, so
+    /// the caller still prints and saves the results gathered so far. Check
+    /// [`interrupted`] before treating them as final. The second Ctrl-C kills the
+    /// process.
+    ///
+    /// [`SimulationBuilder::artifacts_dir`]: crate::SimulationBuilder::artifacts_dir
+    /// [`interrupted`]: Simulation::interrupted
     pub fn run(&mut self) {
-        self.start();
+        #[cfg(feature = "interrupt")]
+        crate::interrupt::install();
+
+        if self.artifacts.is_none() {
+            self.run_to_budget();
+            if self.interrupted {
+                eprint!("{}", self.report(self.interruption()));
+            }
+            return;
+        }
+        match panic::catch_unwind(AssertUnwindSafe(|| self.run_to_budget())) {
+            Ok(()) if self.interrupted => {
+                self.write_artifacts(Some(("interrupted.txt", self.report(self.interruption()))))
+            }
+            Ok(()) => self.write_artifacts(None),
+            Err(panic) => {
+                let message = panic
+                    .downcast_ref::<String>()
+                    .cloned()
+                    .or_else(|| panic.downcast_ref::<&str>().map(|m| m.to_string()))
+                    .unwrap_or_else(|| "unknown panic".to_string());
+                let process = global::try_rank().map_or(String::new(), |id| format!(" on P{id}"));
+                self.write_artifacts(Some((
+                    "failure.txt",
+                    self.report(format!(
+                        "Panicked at {}{process}: {message}",
+                        global::now().display()
+                    )),
+                )));
+                panic::resume_unwind(panic)
+            }
+        }
+    }
+
+    /// Executes all events scheduled up to `deadline` and returns control to the caller.
+    ///
+    /// Processes are started on the first call. After the call the simulation clock
+    /// is at `deadline` (or at the time budget if it is earlier), so [`now`] observed
+    /// by the caller is predictable. Stepping can be freely mixed with [`step_n`]
+    /// and finished with [`run`], which continues from the current state. Events
+    /// execute in the same order as with a single [`run`], shuffled tie-breaks included.
+    ///
+    /// Unlike [`run`], running out of events is not treated as a deadlock: the call
+    /// simply returns.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dscale::{SimulationBuilder, Jiffies, now, global::anykv};
+    ///
+    /// anykv::set::<usize>("ticks", 0);
+    ///
+    /// let mut simulation = SimulationBuilder::default()
+    ///     .add_pool::<Ticker>("tickers", 1)
+    ///     .time_budget(Jiffies(1_000))
+    ///     .build();
+    ///
+    /// simulation.step_until(Jiffies(100));
+    /// assert_eq!(now(), Jiffies(100));
+    /// assert_eq!(anykv::get::<usize>("ticks"), 10);
+    ///
+    /// simulation.run(); // Finish the rest of the time budget
+    /// assert_eq!(anykv::get::<usize>("ticks"), 100);
+    /// # #[derive(Default)]
+    /// # struct Ticker;
+    /// # impl dscale::ProcessHandle for Ticker {
+    /// #     fn start(&mut self) { dscale::schedule_timer_after(Jiffies(10)); }
+    /// #     fn on_message(&mut self, from: dscale::ProcessId, message: dscale::MessagePtr) {}
+    /// #     fn on_timer(&mut self, id: dscale::TimerId) {
+    /// #         anykv::modify::<usize>("ticks", |t| *t += 1);
+    /// #         dscale::schedule_timer_after(Jiffies(10));
+    /// #     }
+    /// # }
+    /// ```
+    ///
+    /// [`now`]: crate::now
+    /// [`step_n`]: Simulation::step_n
+    /// [`run`]: Simulation::run
+    pub fn step_until(&mut self, deadline: Jiffies) {
+        self.ensure_started();
 
         while global::now() < self.time_budget {
+            match self.closest_time() {
+                Some(future) if future <= deadline => self.step(),
+                _ => break,
+            }
+        }
+
+        let target = deadline.min(self.time_budget);
+        if global::now() < target {
+            global::fast_forward_clock(target);
+        }
+    }
+
+    /// Executes at most `events` process steps and returns control to the caller.
+    ///
+    /// Processes are started on the first call (starting is not counted as a step).
+    /// Returns the number of executed steps, which is less than `events` only if the
+    /// time budget was exhausted or no events remain.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dscale::{SimulationBuilder, Jiffies, now};
+    ///
+    /// let mut simulation = SimulationBuilder::default()
+    ///     .add_pool::<Ticker>("tickers", 1)
+    ///     .time_budget(Jiffies(1_000))
+    ///     .build();
+    ///
+    /// assert_eq!(simulation.step_n(3), 3);
+    /// assert_eq!(now(), Jiffies(30));
+    /// # #[derive(Default)]
+    /// # struct Ticker;
+    /// # impl dscale::ProcessHandle for Ticker {
+    /// #     fn start(&mut self) { dscale::schedule_timer_after(Jiffies(10)); }
+    /// #     fn on_message(&mut self, from: dscale::ProcessId, message: dscale::MessagePtr) {}
+    /// #     fn on_timer(&mut self, id: dscale::TimerId) { dscale::schedule_timer_after(Jiffies(10)); }
+    /// # }
+    /// ```
+    pub fn step_n(&mut self, events: usize) -> usize {
+        self.ensure_started();
+
+        let mut executed = 0;
+        while executed < events && global::now() < self.time_budget && self.closest_time().is_some()
+        {
             self.step();
+            executed += 1;
         }
+        executed
+    }
 
-        // For small simulations progress bar is not fullfilling
-        self.progress_bar.finish();
+    /// Returns fingerprint of all process steps executed so far.
+    ///
+    /// Two runs of the same simulation with the same seed always produce equal
+    /// digests. Any change of scheduling semantics (latencies, bandwidth queues,
+    /// timers ordering) is very likely to change it, which makes the digest
+    /// suitable for regression checks against golden files (see [`helpers::Golden`]).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dscale::{SimulationBuilder, Jiffies};
+    ///
+    /// let run = || {
+    ///     let mut simulation = SimulationBuilder::default()
+    ///         .add_pool::<MyProcess>("workers", 3)
+    ///         .time_budget(Jiffies(1_000))
+    ///         .seed(7)
+    ///         .build();
+    ///     simulation.run();
+    ///     simulation.digest()
+    /// };
+    ///
+    /// assert_eq!(run(), run());
+    /// # #[derive(Default)]
+    /// # struct MyProcess;
+    /// # impl dscale::ProcessHandle for MyProcess {
+    /// #     fn start(&mut self) { dscale::schedule_timer_after(Jiffies(10)); }
+    /// #     fn on_message(&mut self, from: dscale::ProcessId, message: dscale::MessagePtr) {}
+    /// #     fn on_timer(&mut self, id: dscale::TimerId) { dscale::schedule_timer_after(Jiffies(10)); }
+    /// # }
+    /// ```
+    ///
+    /// [`helpers::Golden`]: crate::helpers::Golden
+    pub fn digest(&self) -> RunDigest {
+        self.nursery.digest()
+    }
 
-        info!("Looks good! ヽ('ー`)ノ");
+    /// Returns the process with the given id as its concrete type.
+    ///
+    /// Lets tests assert on the internal state of a protocol after (or between
+    /// steps of) a run instead of exporting everything through [`anykv`]. The
+    /// state is the one of the current incarnation: a crashed process keeps the
+    /// state it crashed with, a restarted one has the state built since the
+    /// restart. The process must not be borrowed while the simulation steps.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dscale::{SimulationBuilder, Jiffies, ProcessHandle, ProcessId, MessagePtr, TimerId};
+    /// use dscale::schedule_timer_after;
+    ///
+    /// #[derive(Default)]
+    /// struct Ticker {
+    ///     ticks: usize,
+    /// }
+    ///
+    /// impl ProcessHandle for Ticker {
+    ///     fn start(&mut self) {
+    ///         schedule_timer_after(Jiffies(100));
+    ///     }
+    ///     fn on_message(&mut self, from: ProcessId, message: MessagePtr) {}
+    ///     fn on_timer(&mut self, id: TimerId) {
+    ///         self.ticks += 1;
+    ///         schedule_timer_after(Jiffies(100));
+    ///     }
+    /// }
+    ///
+    /// let mut simulation = SimulationBuilder::default()
+    ///     .add_pool::<Ticker>("tickers", 2)
+    ///     .build();
+    /// simulation.step_until(Jiffies(1_000));
+    ///
+    /// assert_eq!(simulation.process::<Ticker>(2).borrow().ticks, 10);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is no process with the id or it is not a `P`.
+    ///
+    /// [`anykv`]: crate::global::anykv
+    pub fn process<P: ProcessHandle + 'static>(&self, id: ProcessId) -> Rc<RefCell<P>> {
+        self.nursery
+            .state(id)
+            .unwrap_or_else(|| panic!("No process P{id}"))
+            .downcast::<RefCell<P>>()
+            .unwrap_or_else(|_| panic!("P{id} is not a {}", std::any::type_name::<P>()))
+    }
+
+    /// Returns `true` if [`run`] was stopped by Ctrl-C before the time budget.
+    ///
+    /// Always `false` without the `interrupt` feature. Results of an interrupted
+    /// run cover only the simulated time reached, [`now`] tells how much of it.
+    ///
+    /// [`run`]: Simulation::run
+    /// [`now`]: crate::now
+    pub fn interrupted(&self) -> bool {
+        self.interrupted
+    }
+
+    /// Crashes the process, it stays down until [`restart`].
+    ///
+    /// Messages and timers addressed to the crashed process are dropped, and the
+    /// unsynced tail of its [`wal`] is truncated according to
+    /// [`DiskDescription::crash_truncation`]. Unlike a crash in a [`Scenario`], the
+    /// crash time may depend on the state of the run, e.g. crash whichever
+    /// process leads at the moment.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dscale::{SimulationBuilder, Jiffies, global::anykv};
+    ///
+    /// anykv::set::<usize>("ticks", 0);
+    ///
+    /// let mut simulation = SimulationBuilder::default()
+    ///     .add_pool::<Ticker>("tickers", 1)
+    ///     .time_budget(Jiffies(1_000))
+    ///     .build();
+    ///
+    /// simulation.step_until(Jiffies(100));
+    /// simulation.crash(1);
+    /// simulation.step_until(Jiffies(1_000));
+    /// assert_eq!(anykv::get::<usize>("ticks"), 10);
+    /// # #[derive(Default)]
+    /// # struct Ticker;
+    /// # impl dscale::ProcessHandle for Ticker {
+    /// #     fn start(&mut self) { dscale::schedule_timer_after(Jiffies(10)); }
+    /// #     fn on_message(&mut self, from: dscale::ProcessId, message: dscale::MessagePtr) {}
+    /// #     fn on_timer(&mut self, id: dscale::TimerId) {
+    /// #         anykv::modify::<usize>("ticks", |t| *t += 1);
+    /// #         dscale::schedule_timer_after(Jiffies(10));
+    /// #     }
+    /// # }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is no process with such id.
+    ///
+    /// [`restart`]: Simulation::restart
+    /// [`wal`]: crate::global::wal
+    /// [`DiskDescription::crash_truncation`]: crate::DiskDescription::crash_truncation
+    /// [`Scenario`]: crate::Scenario
+    pub fn crash(&mut self, id: ProcessId) {
+        self.ensure_started();
+        info!("Crashing P{id} at {}", global::now());
+        global::wal::on_crash(id);
+        self.nursery.crash(id);
+        self.prune();
+    }
+
+    /// Crashes the process and immediately starts a fresh instance of it.
+    ///
+    /// The process state is dropped and replaced with `Default`, then
+    /// [`ProcessHandle::start`] is called again at the current simulation time.
+    /// Timers scheduled by the crashed instance never fire, messages already in
+    /// flight are delivered to the new instance. Only the [`wal`] of the process
+    /// survives the crash, truncated according to [`DiskDescription::crash_truncation`].
+    ///
+    /// Crash time is controlled by stepping the simulation, see [`step_until`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dscale::{SimulationBuilder, Jiffies, global::{anykv, wal}};
+    ///
+    /// anykv::set::<usize>("recovered", 0);
+    ///
+    /// let mut simulation = SimulationBuilder::default()
+    ///     .add_pool::<Counter>("counters", 1)
+    ///     .time_budget(Jiffies(1_000))
+    ///     .build();
+    ///
+    /// simulation.step_until(Jiffies(100));
+    /// simulation.restart(1);
+    /// assert!(anykv::get::<usize>("recovered") > 0);
+    /// # struct Tick;
+    /// # impl dscale::Message for Tick {}
+    /// # #[derive(Default)]
+    /// # struct Counter { persisting: Option<dscale::TimerId> }
+    /// # impl dscale::ProcessHandle for Counter {
+    /// #     fn start(&mut self) {
+    /// #         anykv::set::<usize>("recovered", wal::len());
+    /// #         dscale::schedule_timer_after(Jiffies(10));
+    /// #     }
+    /// #     fn on_message(&mut self, from: dscale::ProcessId, message: dscale::MessagePtr) {}
+    /// #     fn on_timer(&mut self, id: dscale::TimerId) {
+    /// #         if self.persisting == Some(id) { return; }
+    /// #         wal::append(Tick);
+    /// #         self.persisting = Some(wal::sync());
+    /// #         dscale::schedule_timer_after(Jiffies(10));
+    /// #     }
+    /// # }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is no process with such id.
+    ///
+    /// [`ProcessHandle::start`]: crate::ProcessHandle::start
+    /// [`wal`]: crate::global::wal
+    /// [`DiskDescription::crash_truncation`]: crate::DiskDescription::crash_truncation
+    /// [`step_until`]: Simulation::step_until
+    pub fn restart(&mut self, id: ProcessId) {
+        self.ensure_started();
+        info!("Restarting P{id} at {}", global::now());
+        self.nursery.restart(id);
+        global::schedule();
+        self.prune();
+    }
+
+    /// Returns overload statistics of process inboxes collected so far.
+    ///
+    /// Statistics are only collected for inboxes bounded with
+    /// [`SimulationBuilder::inbox`]; with unbounded inboxes nothing is ever dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dscale::{SimulationBuilder, BandwidthDescription, InboxDescription, OverflowPolicy, Jiffies};
+    ///
+    /// let mut simulation = SimulationBuilder::default()
+    ///     .add_pool::<MyProcess>("nodes", 2)
+    ///     .nic_bandwidth(BandwidthDescription::Bounded(100))
+    ///     .inbox(InboxDescription::Bounded(8, OverflowPolicy::DropNewest))
+    ///     .time_budget(Jiffies(1_000))
+    ///     .build();
+    ///
+    /// simulation.run();
+    /// let stats = simulation.inbox_stats();
+    /// assert_eq!(stats.total_dropped(), 0); // Nothing was sent
+    /// assert!(stats.crashed().is_empty());
+    /// # #[derive(Default)]
+    /// # struct MyProcess;
+    /// # impl dscale::ProcessHandle for MyProcess {
+    /// #     fn start(&mut self) { dscale::schedule_timer_after(Jiffies(10)); }
+    /// #     fn on_message(&mut self, from: dscale::ProcessId, message: dscale::MessagePtr) {}
+    /// #     fn on_timer(&mut self, id: dscale::TimerId) { dscale::schedule_timer_after(Jiffies(10)); }
+    /// # }
+    /// ```
+    ///
+    /// [`SimulationBuilder::inbox`]: crate::SimulationBuilder::inbox
+    pub fn inbox_stats(&self) -> InboxStats {
+        self.inbox_stats.borrow().clone()
+    }
+
+    /// Returns the traffic sent between regions so far and its price.
+    ///
+    /// Links are priced and capped with [`SimulationBuilder::region_link`],
+    /// links which are not configured are accounted for free. See [`LinkStats`]
+    /// for what is accounted.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dscale::{Distributions, Jiffies, LatencyDescription, RegionLink, SimulationBuilder};
+    ///
+    /// let mut simulation = SimulationBuilder::default()
+    ///     .add_pool_in_region::<Sender>("nodes", "eu", 1)
+    ///     .add_pool_in_region::<Sender>("nodes", "us", 1)
+    ///     .latency_topology(&[LatencyDescription::WithinPool(
+    ///         "nodes",
+    ///         Distributions::Uniform(Jiffies(40), Jiffies(50)),
+    ///     )])
+    ///     .region_link("eu", "us", RegionLink { cost_per_gb: 0.02, ..Default::default() })
+    ///     .build();
+    /// simulation.step_until(Jiffies(100));
+    ///
+    /// let stats = simulation.link_stats();
+    /// assert_eq!(stats.link("eu", "us").bytes, 2_000_000_000);
+    /// assert_eq!(stats.link("us", "us").bytes, 0); // Sends to itself never cross a link
+    /// assert_eq!(stats.cross_region_bytes(), 2_000_000_000);
+    /// assert!((stats.total_cost() - 0.04).abs() < 1e-9);
+    /// # struct Blob;
+    /// # impl dscale::Message for Blob { fn virtual_size(&self) -> usize { 2_000_000_000 } }
+    /// # #[derive(Default)]
+    /// # struct Sender;
+    /// # impl dscale::ProcessHandle for Sender {
+    /// #     fn start(&mut self) { dscale::send_to(2, Blob); }
+    /// #     fn on_message(&mut self, from: dscale::ProcessId, message: dscale::MessagePtr) {}
+    /// #     fn on_timer(&mut self, id: dscale::TimerId) {}
+    /// # }
+    /// ```
+    ///
+    /// [`SimulationBuilder::region_link`]: crate::SimulationBuilder::region_link
+    pub fn link_stats(&self) -> LinkStats {
+        self.link_stats.borrow().clone()
+    }
+
+    /// Returns link latencies drawn so far, for replaying them in another run.
+    ///
+    /// See [`NetworkSchedule`] for an example.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the schedule is not recorded, see [`SimulationBuilder::record_network_schedule`].
+    ///
+    /// [`SimulationBuilder::record_network_schedule`]: crate::SimulationBuilder::record_network_schedule
+    pub fn network_schedule(&self) -> NetworkSchedule {
+        self.network.borrow().recorded_schedule()
+    }
+
+    /// Returns the number of messages dropped by the message filter of the process.
+    ///
+    /// Filters are installed by processes with [`set_message_filter`]. The count
+    /// survives restarts of the process, while the filter itself does not.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dscale::{SimulationBuilder, Distributions, Jiffies, LatencyDescription};
+    ///
+    /// let mut simulation = SimulationBuilder::default()
+    ///     .add_pool::<Deaf>("nodes", 2)
+    ///     .latency_topology(&[LatencyDescription::WithinPool(
+    ///         "nodes",
+    ///         Distributions::Uniform(Jiffies(1), Jiffies(5)),
+    ///     )])
+    ///     .time_budget(Jiffies(100))
+    ///     .build();
+    ///
+    /// simulation.step_until(Jiffies(100));
+    /// assert_eq!(simulation.filtered_messages(1), 2); // Pings of both processes
+    /// assert_eq!(simulation.filtered_messages(2), 2);
+    /// # struct Ping;
+    /// # impl dscale::Message for Ping {}
+    /// # #[derive(Default)]
+    /// # struct Deaf;
+    /// # impl dscale::ProcessHandle for Deaf {
+    /// #     fn start(&mut self) {
+    /// #         dscale::set_message_filter(|_, _| false);
+    /// #         dscale::broadcast(Ping);
+    /// #     }
+    /// #     fn on_message(&mut self, from: dscale::ProcessId, message: dscale::MessagePtr) {}
+    /// #     fn on_timer(&mut self, id: dscale::TimerId) {}
+    /// # }
+    /// ```
+    ///
+    /// [`set_message_filter`]: crate::set_message_filter
+    pub fn filtered_messages(&self, id: ProcessId) -> usize {
+        global::filter::filtered(id)
+    }
+
+    /// Returns protocol errors recorded so far, oldest first.
+    ///
+    /// Errors are recorded only under [`ErrorPolicy::Record`], see
+    /// [`SimulationBuilder::protocol_errors`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dscale::{ErrorPolicy, Jiffies, SimulationBuilder};
+    ///
+    /// let mut simulation = SimulationBuilder::default()
+    ///     .add_pool::<Lost>("nodes", 2)
+    ///     .protocol_errors(ErrorPolicy::Record)
+    ///     .build();
+    /// simulation.step_until(Jiffies(10));
+    ///
+    /// let errors = simulation.protocol_errors();
+    /// assert_eq!(errors.len(), 2);
+    /// assert_eq!(errors[0].to_string(), "P1 at 0: Message sent to unknown P7");
+    /// # struct Ping;
+    /// # impl dscale::Message for Ping {}
+    /// # #[derive(Default)]
+    /// # struct Lost;
+    /// # impl dscale::ProcessHandle for Lost {
+    /// #     fn start(&mut self) { dscale::send_to(7, Ping); }
+    /// #     fn on_message(&mut self, from: dscale::ProcessId, message: dscale::MessagePtr) {}
+    /// #     fn on_timer(&mut self, id: dscale::TimerId) {}
+    /// # }
+    /// ```
+    ///
+    /// [`ErrorPolicy::Record`]: crate::ErrorPolicy::Record
+    /// [`SimulationBuilder::protocol_errors`]: crate::SimulationBuilder::protocol_errors
+    pub fn protocol_errors(&self) -> Vec<ProtocolError> {
+        global::errors::recorded()
+    }
+
+    /// Returns the memory usage of the process.
+    ///
+    /// Footprints are reported by processes with [`memory::report`], budgets are
+    /// configured with [`SimulationBuilder::memory_limit`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dscale::{SimulationBuilder, Jiffies, MemoryLimit, MemoryPolicy};
+    ///
+    /// let mut simulation = SimulationBuilder::default()
+    ///     .add_pool::<Hoarder>("nodes", 2)
+    ///     .process_memory_limit(1, MemoryLimit {
+    ///         budget: 1_000,
+    ///         policy: MemoryPolicy::Crash,
+    ///     })
+    ///     .time_budget(Jiffies(1_000))
+    ///     .build();
+    ///
+    /// simulation.run();
+    /// let stats = simulation.memory_stats(1);
+    /// assert_eq!(stats.out_of_memory, vec![Jiffies(110)]); // 1100 bytes after 11 timers
+    /// assert_eq!(simulation.memory_stats(2).peak, 10_000);
+    /// # use dscale::global::memory;
+    /// # #[derive(Default)]
+    /// # struct Hoarder { bytes: usize }
+    /// # impl dscale::ProcessHandle for Hoarder {
+    /// #     fn start(&mut self) { dscale::schedule_timer_after(Jiffies(10)); }
+    /// #     fn on_message(&mut self, from: dscale::ProcessId, message: dscale::MessagePtr) {}
+    /// #     fn on_timer(&mut self, id: dscale::TimerId) {
+    /// #         self.bytes += 100;
+    /// #         memory::report("hoard", self.bytes);
+    /// #         dscale::schedule_timer_after(Jiffies(10));
+    /// #     }
+    /// # }
+    /// ```
+    ///
+    /// [`memory::report`]: crate::global::memory::report
+    /// [`SimulationBuilder::memory_limit`]: crate::SimulationBuilder::memory_limit
+    pub fn memory_stats(&self, id: ProcessId) -> MemoryStats {
+        global::memory::stats(id)
+    }
+
+    /// Returns events of the process waiting in the simulation queues.
+    ///
+    /// Useful for invariant checkers and for explaining a stuck run: instead of
+    /// silence one gets "P5: 3 in-flight messages, 0 scheduled sends, 1 timer
+    /// pending". See [`PendingEvents`] for what is counted. Counting walks all
+    /// queues, so the call is meant for debugging rather than for every step.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dscale::{SimulationBuilder, Distributions, Jiffies, LatencyDescription};
+    ///
+    /// let mut simulation = SimulationBuilder::default()
+    ///     .add_pool::<Pinger>("nodes", 2)
+    ///     .latency_topology(&[LatencyDescription::WithinPool(
+    ///         "nodes",
+    ///         Distributions::Uniform(Jiffies(10), Jiffies(10)),
+    ///     )])
+    ///     .time_budget(Jiffies(1_000))
+    ///     .build();
+    ///
+    /// simulation.step_until(Jiffies(5));
+    /// let pending = simulation.pending_events_for(2);
+    /// assert_eq!(pending.in_flight, 1); // Ping from P1 is still on the way
+    /// assert_eq!(pending.timers, 1);
+    /// println!("P2: {pending}");
+    /// # struct Ping;
+    /// # impl dscale::Message for Ping {}
+    /// # #[derive(Default)]
+    /// # struct Pinger;
+    /// # impl dscale::ProcessHandle for Pinger {
+    /// #     fn start(&mut self) {
+    /// #         if dscale::rank() == 1 { dscale::send_to(2, Ping); }
+    /// #         dscale::schedule_timer_after(Jiffies(100));
+    /// #     }
+    /// #     fn on_message(&mut self, from: dscale::ProcessId, message: dscale::MessagePtr) {}
+    /// #     fn on_timer(&mut self, id: dscale::TimerId) {}
+    /// # }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is no process with the given id.
+    pub fn pending_events_for(&self, id: ProcessId) -> PendingEvents {
+        assert!(
+            (1..=self.nursery.size()).contains(&id),
+            "No process with id {id}"
+        );
+        self.count_pending()[id]
+    }
+
+    /// Returns quorums of live processes which are still waiting for votes.
+    ///
+    /// Every [`QuorumTracker`] which has not reached its threshold is listed,
+    /// oldest first, with its label, age and missing voters. Displaying an entry
+    /// gives a ready report line, see [`QuorumTracker`] for an example.
+    ///
+    /// [`QuorumTracker`]: crate::helpers::quorum::QuorumTracker
+    pub fn pending_quorums(&self) -> Vec<PendingQuorum> {
+        quorum::pending_quorums()
+            .into_iter()
+            .filter(|pending| !self.nursery.is_crashed(pending.process))
+            .collect()
+    }
+
+    /// Returns the [`Injector`] of the simulation.
+    ///
+    /// The experiment driver uses it to inject messages and configuration changes
+    /// at given simulated times, see [`Injector`] for an example.
+    ///
+    /// [`Injector`]: crate::Injector
+    pub fn injector(&self) -> Injector {
+        self.injector.clone()
+    }
+
+    /// Returns the number of events removed from the simulation queues so far
+    /// because they could never happen.
+    ///
+    /// See [`PrunedEvents`] for what is pruned and when.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dscale::{Distributions, Jiffies, LatencyDescription, Scenario, SimulationBuilder};
+    /// use dscale::scenario::crash;
+    ///
+    /// let mut simulation = SimulationBuilder::default()
+    ///     .add_pool::<Ticker>("tickers", 3)
+    ///     .latency_topology(&[LatencyDescription::WithinPool(
+    ///         "tickers",
+    ///         Distributions::Uniform(Jiffies(1), Jiffies(2)),
+    ///     )])
+    ///     .scenario(Scenario::new().at(Jiffies(15), crash(3)))
+    ///     .prune_undeliverable(true)
+    ///     .build();
+    ///
+    /// simulation.step_until(Jiffies(100));
+    /// let pruned = simulation.pruned_events();
+    /// assert_eq!(pruned.timers, 1); // The timer P3 would never see
+    /// assert!(pruned.in_flight > 0); // Pings sent to P3 after the crash
+    /// # #[derive(Default)]
+    /// # struct Ticker;
+    /// # struct Ping;
+    /// # impl dscale::Message for Ping {}
+    /// # impl dscale::ProcessHandle for Ticker {
+    /// #     fn start(&mut self) { dscale::schedule_timer_after(Jiffies(10)); }
+    /// #     fn on_message(&mut self, from: dscale::ProcessId, message: dscale::MessagePtr) {}
+    /// #     fn on_timer(&mut self, id: dscale::TimerId) {
+    /// #         dscale::broadcast(Ping);
+    /// #         dscale::schedule_timer_after(Jiffies(10));
+    /// #     }
+    /// # }
+    /// ```
+    pub fn pruned_events(&self) -> PrunedEvents {
+        PrunedEvents {
+            timers: self.timers.borrow().pruned(),
+            ..self.network.borrow().pruned()
+        }
+    }
+
+    /// Returns statistics of all events waiting in the simulation queues.
+    ///
+    /// Counts are [`PendingEvents`] of all processes summed up, plus the
+    /// remaining events of the [`Scenario`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dscale::{SimulationBuilder, Jiffies};
+    ///
+    /// let mut simulation = SimulationBuilder::default()
+    ///     .add_pool::<Ticker>("tickers", 3)
+    ///     .time_budget(Jiffies(1_000))
+    ///     .build();
+    ///
+    /// simulation.step_until(Jiffies(50));
+    /// let stats = simulation.queue_stats();
+    /// assert_eq!(stats.timers, 3);
+    /// assert_eq!(stats.total(), 3);
+    /// # #[derive(Default)]
+    /// # struct Ticker;
+    /// # impl dscale::ProcessHandle for Ticker {
+    /// #     fn start(&mut self) { dscale::schedule_timer_after(Jiffies(10)); }
+    /// #     fn on_message(&mut self, from: dscale::ProcessId, message: dscale::MessagePtr) {}
+    /// #     fn on_timer(&mut self, id: dscale::TimerId) { dscale::schedule_timer_after(Jiffies(10)); }
+    /// # }
+    /// ```
+    ///
+    /// [`Scenario`]: crate::Scenario
+    pub fn queue_stats(&self) -> QueueStats {
+        let mut stats = QueueStats {
+            scenario_events: self.scenario.borrow().remaining(),
+            ..QueueStats::default()
+        };
+        self.count_pending()
+            .iter()
+            .for_each(|pending| stats.add(pending));
+        stats
     }
 }
 
 impl Simulation {
+    fn run_to_budget(&mut self) {
+        self.ensure_started();
+
+        while global::now() < self.time_budget {
+            if self.interrupt_requested() {
+                break;
+            }
+            self.step();
+        }
+
+        // For small simulations progress bar is not fullfilling
+        self.progress_bar.finish();
+
+        if self.interrupted {
+            warn!("{}", self.interruption());
+        } else {
+            info!("Looks good! ヽ('ー`)ノ");
+        }
+    }
+
+    // Always false without the interrupt feature
+    fn interrupt_requested(&mut self) -> bool {
+        #[cfg(feature = "interrupt")]
+        {
+            self.interrupted = crate::interrupt::requested();
+        }
+        self.interrupted
+    }
+
+    fn interruption(&self) -> String {
+        format!(
+            "Interrupted at {} of {} after {} events",
+            global::now().display(),
+            self.time_budget.display(),
+            self.digest().events
+        )
+    }
+
+    // Report of a run which did not finish: pending events, stuck quorums and
+    // captured logs of the process executing at the moment
+    fn report(&self, headline: String) -> String {
+        let mut report = format!("{headline}\n\nPending: {}\n", self.queue_stats());
+        self.pending_quorums().iter().for_each(|quorum| {
+            let _ = writeln!(report, "{quorum}");
+        });
+        if let Some(id) = global::try_rank() {
+            let logs = log_capture::recent_logs(id);
+            if !logs.is_empty() {
+                let _ = writeln!(report, "\nRecent logs of P{id}:");
+            }
+            logs.iter().for_each(|line| {
+                let _ = writeln!(
+                    report,
+                    "[{} {}] {}",
+                    line.at.display(),
+                    line.level,
+                    line.message
+                );
+            });
+        }
+        report
+    }
+
+    // Report is the name and contents of the file explaining why the run stopped
+    fn write_artifacts(&self, report: Option<(&str, String)>) {
+        let Some(bundle) = &self.artifacts else {
+            return;
+        };
+        let pending = self.count_pending();
+        let mut processes = String::from(
+            "process,pool,crashed,in_flight,scheduled,timers,filtered,memory_peak,memory_refused,out_of_memory\n",
+        );
+        self.nursery.ids().for_each(|id| {
+            let pool = global::pool_of(*id).map_or(String::new(), |(pool, _)| pool);
+            let memory = global::memory::stats(*id);
+            let _ = writeln!(
+                processes,
+                "{id},{pool},{},{},{},{},{},{},{},{}",
+                self.nursery.is_crashed(*id),
+                pending[*id].in_flight,
+                pending[*id].scheduled,
+                pending[*id].timers,
+                global::filter::filtered(*id),
+                memory.peak,
+                memory.refused,
+                memory.out_of_memory.len(),
+            );
+        });
+
+        let digest = self.digest();
+        let mut files = vec![
+            ("processes.csv", processes),
+            (
+                "digest.txt",
+                format!(
+                    "events: {}\ntrace hash: {:016x}\n",
+                    digest.events, digest.trace_hash
+                ),
+            ),
+        ];
+        let links = self.link_stats();
+        if links.links().next().is_some() {
+            files.push(("links.csv", links.to_string()));
+        }
+        files.extend(report);
+        bundle.write(files);
+        if global::tracing::is_enabled() {
+            global::tracing::record().save(bundle.dir().join("trace.txt"));
+        }
+        info!("Artifacts written to {}", bundle.dir().display());
+    }
+
+    // Hands a message straight to the handler of `to`, bypassing the network
+    #[cfg(feature = "fuzz")]
+    pub(crate) fn deliver(
+        &mut self,
+        from: ProcessId,
+        to: ProcessId,
+        message: crate::message::SharedMessage,
+    ) {
+        self.ensure_started();
+        let size = message.virtual_size();
+        self.nursery.deliver(
+            from,
+            to,
+            crate::dscale_message::DScaleMessage::NetworkMessage(crate::MessagePtr(message), size),
+        );
+        global::schedule();
+    }
+
+    fn ensure_started(&mut self) {
+        if !self.started {
+            self.started = true;
+            self.start();
+        }
+    }
+
     fn start(&mut self) {
         self.actors.iter_mut().for_each(|actor| {
             actor.borrow_mut().start();
@@ -207,31 +1190,91 @@ impl Simulation {
         match self.peek_closest() {
             None => {
                 error!("DEADLOCK! (ﾉಥ益ಥ）ﾉ ┻━┻ Try with RUST_LOG=debug");
+                self.write_artifacts(Some((
+                    "failure.txt",
+                    self.report(format!("Deadlock at {}", global::now().display())),
+                )));
                 exit(1)
             }
-            Some((future, actor)) => {
+            Some((future, index)) => {
                 global::fast_forward_clock(future);
+                if self.spent_at != future {
+                    self.spent_at = future;
+                    self.spent.fill(0);
+                }
+                self.spent[index] += 1;
+                let actor = self.actors[index].clone();
                 actor.borrow_mut().step();
                 global::schedule(); // Only after step() to avoid double borrow_mut() of SharedActor
+                self.prune();
                 self.progress_bar
                     .make_progress(future.min(self.time_budget));
             }
         }
     }
 
-    fn peek_closest(&mut self) -> Option<(Jiffies, SharedActor)> {
-        let mut min_time = Jiffies(usize::MAX);
-        let mut sha: Option<SharedActor> = None;
-        for actor in self.actors.iter() {
-            actor.borrow().peek_closest().map(|time| {
-                if time < min_time {
-                    min_time = time;
-                    sha = Some(actor.clone())
-                }
-            });
+    // Drops events of processes which went down during the last step
+    fn prune(&mut self) {
+        let retired = self.nursery.take_retired();
+        if retired.is_empty() {
+            return;
         }
+        self.timers.borrow_mut().prune(&retired);
+        self.network.borrow_mut().prune_retired(&retired);
+    }
+
+    // Indexed by process id
+    fn count_pending(&self) -> Vec<PendingEvents> {
+        let mut pending = vec![PendingEvents::default(); self.nursery.size() + 1];
+        self.network.borrow().count_pending(&mut pending);
+        self.timers.borrow().count_pending(&mut pending);
+        self.nursery
+            .ids()
+            .filter(|id| self.nursery.is_crashed(**id))
+            .for_each(|id| pending[*id] = PendingEvents::default());
+        pending
+    }
+
+    // Time of the closest event. Unlike peek_closest it draws no tie-break values,
+    // so stepping consumes the same tie-break sequence as run()
+    fn closest_time(&self) -> Option<Jiffies> {
+        self.actors
+            .iter()
+            .filter_map(|actor| actor.borrow().peek_closest())
+            .min()
+    }
+
+    // Closest event and the index of its actor. Actors with events at the same
+    // time go in tie-break order, but every actor executes at most event_budget
+    // events of one jiffy before the other tied actors get their turn. Once all
+    // of them spent their budgets, the budgets are renewed.
+    fn peek_closest(&mut self) -> Option<(Jiffies, usize)> {
+        let mut candidates = std::mem::take(&mut self.candidates);
+        candidates.clear();
+        for (index, actor) in self.actors.iter().enumerate() {
+            if let Some(time) = actor.borrow().peek_closest() {
+                candidates.push((time, self.tie_breaker.next(), index));
+            }
+        }
+        candidates.sort_unstable(); // Indices are unique
+
+        let closest = candidates.first().map(|(time, ..)| *time);
+        let chosen = closest.map(|time| {
+            let tied = candidates.iter().take_while(|(at, ..)| *at == time);
+            let mut available = tied.clone().filter(|(.., index)| {
+                self.spent_at != time || self.spent[*index] < self.event_budget
+            });
+            match available.next() {
+                Some((.., index)) => *index,
+                None => {
+                    tied.for_each(|(.., index)| self.spent[*index] = 0);
+                    candidates[0].2
+                }
+            }
+        });
 
-        Some((min_time, sha?))
+        self.candidates = candidates;
+        Some((closest?, chosen?))
     }
 }
 
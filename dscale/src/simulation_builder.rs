@@ -6,18 +6,46 @@
 //! settings in a fluent, type-safe manner.
 
 use std::{
+    any::TypeId,
     cell::RefCell,
     collections::{BTreeMap, HashMap},
+    fmt::Write,
+    path::PathBuf,
     rc::Rc,
+    time::Duration,
 };
 
+use log::Level;
+
 use crate::{
-    ProcessHandle, ProcessId, Simulation,
-    network::BandwidthDescription,
-    process_handle::MutableProcessHandle,
-    random::Seed,
-    time::Jiffies,
-    topology::{GLOBAL_POOL, LatencyDescription, LatencyTopology},
+    Distributions, Message, ProcessHandle, ProcessId, Simulation, SimulationActor,
+    actor::SharedActor,
+    artifacts::{self, Bundle},
+    crypto::{SIZE_MODEL_KEY, SizeModel},
+    global::{
+        anykv, configuration,
+        disk::DiskDescription,
+        errors::{self, ErrorPolicy},
+        memory::MemoryLimit,
+    },
+    helpers::{LeaderSchedule, Leadership, leader_schedule::LEADER_SCHEDULE_KEY, log_capture},
+    network::{
+        BackgroundTraffic, BandwidthDescription, ChannelOrdering, ChannelOrderings, Flow,
+        InboxDescription, NetworkSchedule, RegionLink, RegionLinks, RelayTree, RelayTrees,
+        ScheduleMode, links,
+    },
+    process_handle::{ProcessFactory, spawn},
+    profile::Profile,
+    random::{Randomizer, Seed, TieBreak},
+    scenario::{Scenario, ScenarioEvent},
+    time::{
+        Jiffies,
+        clock_sync::{ClockSync, ClockSyncActor},
+    },
+    topology::{
+        GLOBAL_POOL, LatencyDescription, LatencyTopology, MessageLatency, RegionListing, Topology,
+    },
+    window::DebugWindow,
 };
 
 fn init_logger() {
@@ -73,22 +101,84 @@ fn init_logger() {
 /// ```
 pub struct SimulationBuilder {
     seed: Seed,
+    tie_break: TieBreak,
     time_budget: Jiffies,
+    event_budget: usize,
     proc_id: usize,
-    pools: HashMap<String, Vec<(ProcessId, MutableProcessHandle)>>,
+    pools: HashMap<String, Vec<(ProcessId, ProcessFactory)>>,
     latency_topology: LatencyTopology,
+    regions: RegionListing,
+    message_latency: MessageLatency,
+    hosts: Vec<Vec<ProcessId>>,
+    channel_orderings: ChannelOrderings,
+    relay_trees: RelayTrees,
+    cpu_speeds: BTreeMap<ProcessId, f64>,
+    clock_drifts: BTreeMap<ProcessId, f64>,
+    clock_syncs: BTreeMap<ProcessId, ClockSync>,
+    startup_jitter: Option<Distributions>,
+    error_policy: ErrorPolicy,
     bandwidth: BandwidthDescription,
+    region_links: RegionLinks,
+    jiffy_duration: Option<Duration>,
+    inbox: InboxDescription,
+    network_schedule: ScheduleMode,
+    congestion_threshold: Option<usize>,
+    prune_undeliverable: bool,
+    reachability_staleness: Jiffies,
+    disk: DiskDescription,
+    memory_limits: BTreeMap<ProcessId, MemoryLimit>,
+    leader_schedule: Option<Vec<(Jiffies, Leadership)>>,
+    size_model: Option<SizeModel>,
+    scenario: Scenario,
+    background_traffic: Vec<BackgroundTraffic>,
+    actors: Vec<SharedActor>,
+    trace_messages: bool,
+    record_trails: bool,
+    debug_window: Option<DebugWindow>,
+    log_capture: Option<(usize, Level)>,
+    artifacts_dir: Option<PathBuf>,
 }
 
 impl Default for SimulationBuilder {
     fn default() -> Self {
         SimulationBuilder {
             seed: 69,
+            tie_break: TieBreak::Fifo,
             time_budget: Jiffies(1_000_000),
+            event_budget: 1024,
             proc_id: 1,
             pools: HashMap::new(),
             bandwidth: BandwidthDescription::Unbounded,
+            region_links: HashMap::new(),
+            jiffy_duration: None,
+            inbox: InboxDescription::Unbounded,
+            network_schedule: ScheduleMode::Off,
+            congestion_threshold: None,
+            prune_undeliverable: false,
+            reachability_staleness: Jiffies(0),
+            disk: DiskDescription::default(),
+            memory_limits: BTreeMap::new(),
+            leader_schedule: None,
+            size_model: None,
+            scenario: Scenario::default(),
+            background_traffic: Vec::new(),
+            actors: Vec::new(),
             latency_topology: HashMap::new(),
+            regions: HashMap::new(),
+            message_latency: HashMap::new(),
+            hosts: Vec::new(),
+            channel_orderings: HashMap::new(),
+            relay_trees: HashMap::new(),
+            cpu_speeds: BTreeMap::new(),
+            clock_drifts: BTreeMap::new(),
+            clock_syncs: BTreeMap::new(),
+            startup_jitter: None,
+            error_policy: ErrorPolicy::default(),
+            trace_messages: false,
+            record_trails: false,
+            debug_window: None,
+            log_capture: None,
+            artifacts_dir: None,
         }
     }
 }
@@ -155,22 +245,108 @@ impl SimulationBuilder {
         (0..size).for_each(|_| {
             let id = self.proc_id;
             self.proc_id += 1;
-            let handle = Rc::new(RefCell::new(P::default()));
-            self.add_to_pool::<P>(name, id, handle.clone());
-            self.add_to_pool::<P>(GLOBAL_POOL, id, handle.clone());
+            self.add_to_pool(name, id, spawn::<P>);
+            self.add_to_pool(GLOBAL_POOL, id, spawn::<P>);
         });
 
         self
     }
 
-    fn add_to_pool<P: ProcessHandle + Default + 'static>(
-        &mut self,
+    /// Adds a pool of processes located in the given region.
+    ///
+    /// Works like [`add_pool`], additionally tagging every added process with
+    /// `region`. Calling it several times with the same pool name and different
+    /// regions spreads one pool across regions. Latency between regions is
+    /// configured with [`LatencyDescription::WithinRegion`] and
+    /// [`LatencyDescription::BetweenRegions`]; processes can then route to the
+    /// closest member of a pool with [`choose_nearest_from_pool`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dscale::{SimulationBuilder, LatencyDescription, Distributions, Jiffies};
+    ///
+    /// let builder = SimulationBuilder::default()
+    ///     .add_pool_in_region::<MyProcess>("replicas", "eu", 2)
+    ///     .add_pool_in_region::<MyProcess>("replicas", "us", 2)
+    ///     .add_pool_in_region::<MyProcess>("clients", "us", 5)
+    ///     .latency_topology(&[
+    ///         LatencyDescription::WithinRegion("eu", Distributions::Uniform(Jiffies(1), Jiffies(2))),
+    ///         LatencyDescription::WithinRegion("us", Distributions::Uniform(Jiffies(1), Jiffies(2))),
+    ///         LatencyDescription::BetweenRegions("eu", "us", Distributions::Uniform(Jiffies(40), Jiffies(45))),
+    ///     ]);
+    /// # #[derive(Default)]
+    /// # struct MyProcess;
+    /// # impl dscale::ProcessHandle for MyProcess {
+    /// #     fn start(&mut self) {}
+    /// #     fn on_message(&mut self, from: dscale::ProcessId, message: dscale::MessagePtr) {}
+    /// #     fn on_timer(&mut self, id: dscale::TimerId) {}
+    /// # }
+    /// ```
+    ///
+    /// # Returns
+    ///
+    /// The `SimulationBuilder` instance for method chaining.
+    ///
+    /// [`add_pool`]: Self::add_pool
+    /// [`LatencyDescription::WithinRegion`]: crate::LatencyDescription::WithinRegion
+    /// [`LatencyDescription::BetweenRegions`]: crate::LatencyDescription::BetweenRegions
+    /// [`choose_nearest_from_pool`]: crate::choose_nearest_from_pool
+    pub fn add_pool_in_region<P: ProcessHandle + Default + 'static>(
+        mut self,
         name: &str,
-        id: usize,
-        handle: MutableProcessHandle,
-    ) {
+        region: &'static str,
+        size: usize,
+    ) -> SimulationBuilder {
+        let first = self.proc_id;
+        self = self.add_pool::<P>(name, size);
+        (first..self.proc_id).for_each(|id| {
+            self.regions.insert(id, region);
+        });
+        self
+    }
+
+    /// Adds a pool of processes in a preset environment.
+    ///
+    /// The [`Profile`] decides the size of the pool and bundles latencies,
+    /// bandwidth, channel ordering and faults of a common kind of experiment,
+    /// see its variants. Builder methods called after the profile override its
+    /// settings; [`scenario`] replaces the faults of the profile.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dscale::{SimulationBuilder, Profile};
+    ///
+    /// let builder = SimulationBuilder::default()
+    ///     .profile::<MyProcess>("replicas", Profile::LanCluster(4))
+    ///     .seed(42);
+    /// # #[derive(Default)]
+    /// # struct MyProcess;
+    /// # impl dscale::ProcessHandle for MyProcess {
+    /// #     fn start(&mut self) {}
+    /// #     fn on_message(&mut self, from: dscale::ProcessId, message: dscale::MessagePtr) {}
+    /// #     fn on_timer(&mut self, id: dscale::TimerId) {}
+    /// # }
+    /// ```
+    ///
+    /// # Returns
+    ///
+    /// The `SimulationBuilder` instance for method chaining.
+    ///
+    /// [`Profile`]: crate::Profile
+    /// [`scenario`]: SimulationBuilder::scenario
+    pub fn profile<P: ProcessHandle + Default + 'static>(
+        self,
+        pool: &'static str,
+        profile: Profile,
+    ) -> SimulationBuilder {
+        profile.apply::<P>(self, pool)
+    }
+
+    fn add_to_pool(&mut self, name: &str, id: usize, factory: ProcessFactory) {
         let pool = self.pools.entry(name.to_string()).or_default();
-        pool.push((id, handle));
+        pool.push((id, factory));
     }
 
     /// Sets the random seed for deterministic simulation execution.
@@ -204,6 +380,72 @@ impl SimulationBuilder {
         self
     }
 
+    /// Randomizes the order of events scheduled for the same jiffy.
+    ///
+    /// Message deliveries, timers and scheduled sends that fall on the same jiffy
+    /// are executed in a fixed order (see [`tie_break`]), so a protocol may
+    /// silently depend on it. With a salt the order is shuffled instead, while everything
+    /// controlled by [`seed`] (latencies, random choices) stays the same. Runs
+    /// with different salts should produce the same results; use
+    /// [`helpers::TieBreakAudit`] to check it.
+    ///
+    /// # Arguments
+    ///
+    /// * `salt` - Seed of the tie-break order
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dscale::SimulationBuilder;
+    ///
+    /// let builder = SimulationBuilder::default()
+    ///     .seed(42)
+    ///     .shuffle_ties(7); // Same latencies, different tie-break order
+    /// ```
+    ///
+    /// # Returns
+    ///
+    /// The `SimulationBuilder` instance for method chaining.
+    ///
+    /// [`seed`]: SimulationBuilder::seed
+    /// [`tie_break`]: SimulationBuilder::tie_break
+    /// [`helpers::TieBreakAudit`]: crate::helpers::TieBreakAudit
+    pub fn shuffle_ties(self, salt: Seed) -> Self {
+        self.tie_break(TieBreak::Shuffled(salt))
+    }
+
+    /// Sets the order of events scheduled for the same jiffy.
+    ///
+    /// Every message and timer gets a sequence number when it is sent or
+    /// scheduled, which orders events of the same jiffy after the policy. By
+    /// default ([`TieBreak::Fifo`]) messages tied in time are delivered in the
+    /// order they were sent and timers fire in the order they were scheduled,
+    /// independently of how the engine stores them, so a run
+    /// reproduces across versions of the engine. [`TieBreak::Lifo`] reverses
+    /// the order, which is a cheap check that a protocol does not depend on it.
+    ///
+    /// # Arguments
+    ///
+    /// * `policy` - Tie-break policy
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dscale::{SimulationBuilder, TieBreak};
+    ///
+    /// let builder = SimulationBuilder::default()
+    ///     .seed(42)
+    ///     .tie_break(TieBreak::Lifo);
+    /// ```
+    ///
+    /// # Returns
+    ///
+    /// The `SimulationBuilder` instance for method chaining.
+    pub fn tie_break(mut self, policy: TieBreak) -> Self {
+        self.tie_break = policy;
+        self
+    }
+
     /// Sets the maximum duration for the simulation.
     ///
     /// The simulation will run until either the specified time budget is reached
@@ -233,6 +475,123 @@ impl SimulationBuilder {
         self
     }
 
+    /// Sets how many events of one jiffy an actor executes before other actors get their turn.
+    ///
+    /// Events of different actors (network, timers, scenario, custom actors added
+    /// with [`add_actor`]) scheduled for the same jiffy are executed in tie-break
+    /// order: the fixed order of actors, or a seeded random one with
+    /// [`shuffle_ties`]. An actor which keeps producing events at the current
+    /// jiffy would starve all actors after it, so every actor executes at most
+    /// `budget` events of a jiffy while other actors wait at it. Once every
+    /// waiting actor spent its budget, the budgets are renewed. Defaults to 1024.
+    ///
+    /// # Arguments
+    ///
+    /// * `budget` - Events an actor executes in a row while others wait at the same jiffy
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::{cell::RefCell, rc::Rc};
+    /// use dscale::{SimulationBuilder, SimulationActor, Jiffies, global::anykv};
+    ///
+    /// // Keeps producing events at jiffy 10, e.g. a chaos agent gone wild
+    /// struct Busy;
+    ///
+    /// impl SimulationActor for Busy {
+    ///     fn start(&mut self) {}
+    ///     fn step(&mut self) {
+    ///         anykv::modify::<usize>("busy", |steps| *steps += 1);
+    ///     }
+    ///     fn peek_closest(&self) -> Option<Jiffies> {
+    ///         Some(Jiffies(10))
+    ///     }
+    /// }
+    ///
+    /// // Checks something once at jiffy 10
+    /// struct Checker {
+    ///     done: bool,
+    /// }
+    ///
+    /// impl SimulationActor for Checker {
+    ///     fn start(&mut self) {}
+    ///     fn step(&mut self) {
+    ///         self.done = true;
+    ///         anykv::set::<usize>("checked_after", anykv::get::<usize>("busy"));
+    ///     }
+    ///     fn peek_closest(&self) -> Option<Jiffies> {
+    ///         (!self.done).then_some(Jiffies(10))
+    ///     }
+    /// }
+    ///
+    /// anykv::set::<usize>("busy", 0);
+    ///
+    /// let mut simulation = SimulationBuilder::default()
+    ///     .add_actor(Rc::new(RefCell::new(Busy)))
+    ///     .add_actor(Rc::new(RefCell::new(Checker { done: false })))
+    ///     .actor_event_budget(100)
+    ///     .build();
+    ///
+    /// simulation.step_n(1_000);
+    /// assert_eq!(anykv::get::<usize>("checked_after"), 100);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `budget` is zero.
+    ///
+    /// # Returns
+    ///
+    /// The `SimulationBuilder` instance for method chaining.
+    ///
+    /// [`add_actor`]: SimulationBuilder::add_actor
+    /// [`shuffle_ties`]: SimulationBuilder::shuffle_ties
+    pub fn actor_event_budget(mut self, budget: usize) -> Self {
+        assert!(budget > 0, "Actor event budget should be positive");
+        self.event_budget = budget;
+        self
+    }
+
+    /// Sets the real-world time represented by one jiffy.
+    ///
+    /// Jiffies are abstract: the simulation itself never needs their real-world
+    /// duration. Configuring it allows to give bandwidth in real-world units (see
+    /// [`BandwidthDescription::Rate`]) and makes the duration available to
+    /// processes through [`configuration::jiffy_duration`]. Outputs of the run
+    /// give times in real-world units next to jiffies: reports annotate them
+    /// (see [`Jiffies::display`]), `metrics.csv` of the [artifacts] has a
+    /// `seconds` column and saved traces record the duration.
+    ///
+    /// # Arguments
+    ///
+    /// * `duration` - Real-world duration of one jiffy
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::time::Duration;
+    /// use dscale::{SimulationBuilder, Bandwidth, BandwidthDescription, Jiffies};
+    ///
+    /// let builder = SimulationBuilder::default()
+    ///     .jiffy_duration(Duration::from_millis(1))
+    ///     .time_budget(Jiffies(60_000)) // One minute
+    ///     .nic_bandwidth(BandwidthDescription::Rate(Bandwidth::gbps(1)));
+    /// ```
+    ///
+    /// # Returns
+    ///
+    /// The `SimulationBuilder` instance for method chaining.
+    ///
+    /// [`BandwidthDescription::Rate`]: crate::BandwidthDescription::Rate
+    /// [`configuration::jiffy_duration`]: crate::global::configuration::jiffy_duration
+    /// [`Jiffies::display`]: crate::Jiffies::display
+    /// [artifacts]: crate::artifacts
+    pub fn jiffy_duration(mut self, duration: Duration) -> Self {
+        assert!(!duration.is_zero(), "Jiffy duration should be positive");
+        self.jiffy_duration = Some(duration);
+        self
+    }
+
     /// Configures network latency between and within process pools.
     ///
     /// This method sets up the network topology by defining latency characteristics
@@ -251,6 +610,10 @@ impl SimulationBuilder {
     ///
     /// - [`LatencyDescription::WithinPool`] - Latency for messages between processes in the same pool
     /// - [`LatencyDescription::BetweenPools`] - Latency for messages between processes in different pools
+    /// - [`LatencyDescription::OneWayBetweenPools`] - Latency for messages from one pool to another only
+    ///
+    /// Descriptions are applied in order: a later description overrides latency
+    /// of the process pairs it covers.
     ///
     /// # Distribution Types
     ///
@@ -300,34 +663,51 @@ impl SimulationBuilder {
     /// [`LatencyDescription`]: crate::LatencyDescription
     /// [`LatencyDescription::WithinPool`]: crate::LatencyDescription::WithinPool
     /// [`LatencyDescription::BetweenPools`]: crate::LatencyDescription::BetweenPools
+    /// [`LatencyDescription::OneWayBetweenPools`]: crate::LatencyDescription::OneWayBetweenPools
     /// [`Distributions::Uniform`]: crate::Distributions::Uniform
     /// [`Distributions::Normal`]: crate::Distributions::Normal
     /// [`Distributions::Bernoulli`]: crate::Distributions::Bernoulli
     pub fn latency_topology(mut self, descriptions: &[LatencyDescription]) -> Self {
         descriptions.iter().for_each(|d| {
-            let (from, to, distr) = match d {
-                LatencyDescription::WithinPool(name, distr) => (*name, *name, distr),
-                LatencyDescription::BetweenPools(pool_from, pool_to, distr) => {
-                    (*pool_from, *pool_to, distr)
-                }
+            let (from_vec, to_vec, distr, one_way) = match d {
+                LatencyDescription::WithinPool(name, distr) => (
+                    self.pool_members(name),
+                    self.pool_members(name),
+                    distr,
+                    false,
+                ),
+                LatencyDescription::BetweenPools(pool_from, pool_to, distr) => (
+                    self.pool_members(pool_from),
+                    self.pool_members(pool_to),
+                    distr,
+                    false,
+                ),
+                LatencyDescription::WithinRegion(region, distr) => (
+                    self.region_members(region),
+                    self.region_members(region),
+                    distr,
+                    false,
+                ),
+                LatencyDescription::BetweenRegions(region_from, region_to, distr) => (
+                    self.region_members(region_from),
+                    self.region_members(region_to),
+                    distr,
+                    false,
+                ),
+                LatencyDescription::OneWayBetweenPools(pool_from, pool_to, distr) => (
+                    self.pool_members(pool_from),
+                    self.pool_members(pool_to),
+                    distr,
+                    true,
+                ),
+                LatencyDescription::OneWayBetweenRegions(region_from, region_to, distr) => (
+                    self.region_members(region_from),
+                    self.region_members(region_to),
+                    distr,
+                    true,
+                ),
             };
 
-            let from_vec: Vec<ProcessId> = self
-                .pools
-                .get(from)
-                .expect("No pool found")
-                .iter()
-                .map(|(id, _)| *id)
-                .collect();
-
-            let to_vec: Vec<ProcessId> = self
-                .pools
-                .get(to)
-                .expect("No pool found")
-                .iter()
-                .map(|(id, _)| *id)
-                .collect();
-
             let cartesian_product = from_vec
                 .iter()
                 .flat_map(|x| to_vec.iter().map(move |y| (*x, *y)));
@@ -340,87 +720,179 @@ impl SimulationBuilder {
                 self.latency_topology.insert(key, distr.clone());
             });
 
-            cartesian_product_backwards.for_each(|key| {
-                self.latency_topology.insert(key, distr.clone());
-            });
+            if !one_way {
+                cartesian_product_backwards.for_each(|key| {
+                    self.latency_topology.insert(key, distr.clone());
+                });
+            }
         });
         self
     }
 
-    /// Configures network bandwidth limitations for each process.
-    ///
-    /// This method sets the network interface bandwidth constraints that apply
-    /// to each process in the simulation. Bandwidth limits affect how quickly
-    /// messages can be transmitted and received, creating realistic network
-    /// bottlenecks.
+    // Process owning the NIC of the host for every process, every process is a host by default
+    fn place_on_hosts(&self) -> Vec<ProcessId> {
+        let mut nics: Vec<ProcessId> = (0..self.proc_id).collect();
+        let mut placed = vec![false; self.proc_id];
+        for host in &self.hosts {
+            for member in host {
+                assert!(
+                    (1..self.proc_id).contains(member),
+                    "No process P{member} to colocate"
+                );
+                assert!(!placed[*member], "P{member} is placed on several hosts");
+                placed[*member] = true;
+                nics[*member] = host[0];
+            }
+        }
+        nics
+    }
+
+    pub(crate) fn pool_members(&self, name: &str) -> Vec<ProcessId> {
+        self.pools
+            .get(name)
+            .expect("No pool found")
+            .iter()
+            .map(|(id, _)| *id)
+            .collect()
+    }
+
+    fn resolve_leaders(&self, leadership: &Leadership) -> Vec<ProcessId> {
+        match leadership {
+            Leadership::Pinned(id) => {
+                assert!(*id > 0 && *id < self.proc_id, "Unknown leader P{id}");
+                vec![*id]
+            }
+            Leadership::Rotating(ids) => ids.clone(),
+            Leadership::RotatingInRegion(region) => self.region_members(region),
+        }
+    }
+
+    fn region_members(&self, region: &str) -> Vec<ProcessId> {
+        let mut members: Vec<ProcessId> = self
+            .regions
+            .iter()
+            .filter(|(_, r)| **r == region)
+            .map(|(id, _)| *id)
+            .collect();
+        assert!(!members.is_empty(), "No region found");
+        members.sort();
+        members
+    }
+
+    /// Adds extra latency to every message of type `M`.
     ///
-    /// The bandwidth limit is applied per process (not globally), simulating
-    /// individual network interface constraints.
+    /// The extra latency is drawn from `extra` for each delivery and added on
+    /// top of the latency configured with [`latency_topology`], so a single
+    /// message type can be slowed down without touching the rest of the
+    /// protocol. Rules for the same type add up.
     ///
-    /// # Arguments
+    /// # Type Parameters
     ///
-    /// * `bandwidth` - A [`BandwidthDescription`] specifying the bandwidth constraints
+    /// * `M` - The message type to delay
     ///
-    /// # Bandwidth Types
+    /// # Arguments
     ///
-    /// - [`BandwidthDescription::Unbounded`] - No bandwidth limitations
-    /// - [`BandwidthDescription::Bounded(bytes_per_jiffy)`] - Limited to specified bytes per time unit
+    /// * `extra` - Distribution of the additional latency
     ///
     /// # Examples
     ///
     /// ```rust
-    /// use dscale::{SimulationBuilder, BandwidthDescription};
+    /// use dscale::{SimulationBuilder, Distributions, Jiffies, Message};
     ///
-    /// // Unlimited bandwidth
-    /// let builder1 = SimulationBuilder::default()
-    ///     .nic_bandwidth(BandwidthDescription::Unbounded);
+    /// struct Vote;
+    /// impl Message for Vote {}
     ///
-    /// // Limited to 1000 bytes per jiffy
-    /// let builder2 = SimulationBuilder::default()
-    ///     .nic_bandwidth(BandwidthDescription::Bounded(1000));
+    /// // Every vote takes 50 more jiffies to arrive
+    /// let builder = SimulationBuilder::default()
+    ///     .message_latency::<Vote>(Distributions::Uniform(Jiffies(50), Jiffies(50)));
     /// ```
     ///
-    /// # Message Sizing
+    /// # Returns
     ///
-    /// The bandwidth constraint uses the [`virtual_size()`] method of messages
-    /// to determine transmission time. This allows you to simulate large payloads
-    /// without actually storing large amounts of data in memory.
+    /// The `SimulationBuilder` instance for method chaining.
+    ///
+    /// [`latency_topology`]: Self::latency_topology
+    pub fn message_latency<M: Message>(self, extra: Distributions) -> Self {
+        self.message_latency_if::<M>(|_| true, extra)
+    }
+
+    /// Adds extra latency to messages of type `M` accepted by `filter`.
+    ///
+    /// Same as [`message_latency`], but only messages for which `filter`
+    /// returns `true` are delayed. Useful when message kinds are variants of
+    /// a single enum, for example to slow down only the certificates of a
+    /// broadcast protocol.
+    ///
+    /// # Type Parameters
+    ///
+    /// * `M` - The message type to delay
+    ///
+    /// # Arguments
+    ///
+    /// * `filter` - Selects the messages to delay
+    /// * `extra` - Distribution of the additional latency
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dscale::{SimulationBuilder, Distributions, Jiffies, Message};
+    ///
+    /// enum Consensus {
+    ///     Proposal,
+    ///     Certificate,
+    /// }
+    /// impl Message for Consensus {}
+    ///
+    /// // Only the slow path is degraded
+    /// let builder = SimulationBuilder::default().message_latency_if::<Consensus>(
+    ///     |m| matches!(m, Consensus::Certificate),
+    ///     Distributions::Uniform(Jiffies(20), Jiffies(40)),
+    /// );
+    /// ```
     ///
     /// # Returns
     ///
     /// The `SimulationBuilder` instance for method chaining.
     ///
-    /// [`BandwidthDescription`]: crate::BandwidthDescription
-    /// [`BandwidthDescription::Unbounded`]: crate::BandwidthDescription::Unbounded
-    /// [`BandwidthDescription::Bounded`]: crate::BandwidthDescription::Bounded
-    /// [`virtual_size()`]: crate::Message::virtual_size
-    pub fn nic_bandwidth(mut self, bandwidth: BandwidthDescription) -> Self {
-        self.bandwidth = bandwidth;
+    /// [`message_latency`]: Self::message_latency
+    pub fn message_latency_if<M: Message>(
+        mut self,
+        filter: fn(&M) -> bool,
+        extra: Distributions,
+    ) -> Self {
+        self.message_latency
+            .entry(TypeId::of::<M>())
+            .or_default()
+            .push((
+                Box::new(move |m| m.downcast_ref::<M>().is_some_and(filter)),
+                extra,
+            ));
         self
     }
 
-    /// Finalizes the configuration and builds the simulation.
+    /// Sets ordering guarantees of channels between two pools.
     ///
-    /// This method consumes the `SimulationBuilder` and creates a [`Simulation`]
-    /// instance ready to run. It performs final setup including:
+    /// Applies in both directions; use the same pool twice to configure
+    /// channels within a pool. Channels are [`ChannelOrdering::Random`] unless
+    /// configured otherwise. Later calls override earlier ones for the same
+    /// pairs of processes.
     ///
-    /// - Initializing the logging system
-    /// - Creating the process registry
-    /// - Setting up the network topology
-    /// - Configuring the simulation engine
+    /// # Arguments
+    ///
+    /// * `pool_a` - Name of the first pool
+    /// * `pool_b` - Name of the second pool
+    /// * `ordering` - Ordering of channels between members of the pools
     ///
     /// # Examples
     ///
     /// ```rust
-    /// use dscale::SimulationBuilder;
-    ///
-    /// let simulation = SimulationBuilder::default()
-    ///     .add_pool::<MyProcess>("nodes", 5)
-    ///     .build();
+    /// use dscale::{SimulationBuilder, ChannelOrdering};
     ///
-    /// // simulation.run();
+    /// let builder = SimulationBuilder::default()
+    ///     .add_pool::<MyProcess>("replicas", 3)
+    ///     .channel_ordering("replicas", "replicas", ChannelOrdering::Fifo);
+    /// # #[derive(Default)]
     /// # struct MyProcess;
-    /// # impl Default for MyProcess { fn default() -> Self { MyProcess } }
     /// # impl dscale::ProcessHandle for MyProcess {
     /// #     fn start(&mut self) {}
     /// #     fn on_message(&mut self, from: dscale::ProcessId, message: dscale::MessagePtr) {}
@@ -428,33 +900,1489 @@ impl SimulationBuilder {
     /// # }
     /// ```
     ///
+    /// # Panics
+    ///
+    /// Panics if a referenced pool name does not exist.
+    ///
     /// # Returns
     ///
-    /// A configured [`Simulation`] ready to run.
+    /// The `SimulationBuilder` instance for method chaining.
     ///
-    /// [`Simulation`]: crate::Simulation
-    pub fn build(self) -> Simulation {
-        init_logger();
-
-        let mut pool_listing = HashMap::new();
-        let mut procs = BTreeMap::new();
-
-        for (name, pool) in self.pools {
-            let mut ids = Vec::new();
-            for (id, handle) in pool {
-                ids.push(id);
-                procs.insert(id, handle);
+    /// [`ChannelOrdering::Random`]: crate::ChannelOrdering::Random
+    pub fn channel_ordering(
+        mut self,
+        pool_a: &str,
+        pool_b: &str,
+        ordering: ChannelOrdering,
+    ) -> Self {
+        let members_a = self.pool_members(pool_a);
+        let members_b = self.pool_members(pool_b);
+        for a in &members_a {
+            for b in &members_b {
+                self.channel_orderings.insert((*a, *b), ordering);
+                self.channel_orderings.insert((*b, *a), ordering);
             }
-            pool_listing.insert(name, ids);
+        }
+        self
+    }
+
+    /// Realizes broadcasts within a pool through a relay tree of its members.
+    ///
+    /// Applies to [`broadcast_within_pool`] (and [`broadcast`] for
+    /// [`GLOBAL_POOL`]) from members and outsiders alike: the sender sends to
+    /// its children in the tree, which forward the message further. Copies sent
+    /// along the tree are serialized by the bandwidth of their sender, see
+    /// [`RelayTree`]. Other sends are unaffected. Later calls override
+    /// earlier ones for the same pool.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - Name of the pool
+    /// * `tree` - Fanout or depth of the tree
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dscale::{SimulationBuilder, RelayTree};
+    ///
+    /// let builder = SimulationBuilder::default()
+    ///     .add_pool::<MyProcess>("committee", 100)
+    ///     .broadcast_tree("committee", RelayTree::Depth(2));
+    /// # #[derive(Default)]
+    /// # struct MyProcess;
+    /// # impl dscale::ProcessHandle for MyProcess {
+    /// #     fn start(&mut self) {}
+    /// #     fn on_message(&mut self, from: dscale::ProcessId, message: dscale::MessagePtr) {}
+    /// #     fn on_timer(&mut self, id: dscale::TimerId) {}
+    /// # }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pool does not exist or the fanout or depth is zero.
+    ///
+    /// # Returns
+    ///
+    /// The `SimulationBuilder` instance for method chaining.
+    ///
+    /// [`broadcast_within_pool`]: crate::broadcast_within_pool
+    /// [`broadcast`]: crate::broadcast
+    pub fn broadcast_tree(mut self, pool: &str, tree: RelayTree) -> Self {
+        tree.validate();
+        self.pool_members(pool);
+        self.relay_trees.insert(pool.to_string(), tree);
+        self
+    }
+
+    /// Places processes on one simulated host.
+    ///
+    /// Colocated processes share the NIC of the host: the bandwidth configured
+    /// with [`nic_bandwidth`] is split between all of them. Messages between
+    /// them never leave the host, so they do not consume bandwidth and arrive
+    /// on the next jiffy regardless of [`latency_topology`]. This models, for
+    /// example, a validator running a primary and several workers, or a client
+    /// deployed next to its replica.
+    ///
+    /// # Arguments
+    ///
+    /// * `members` - Ids of processes running on the host
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dscale::{SimulationBuilder, BandwidthDescription};
+    ///
+    /// // 4 validators, each with a primary and 2 workers on one machine
+    /// let mut builder = SimulationBuilder::default()
+    ///     .add_pool::<MyProcess>("primaries", 4)
+    ///     .add_pool::<MyProcess>("workers", 8)
+    ///     .nic_bandwidth(BandwidthDescription::Bounded(1000));
+    ///
+    /// for validator in 0..4 {
+    ///     let primary = 1 + validator;
+    ///     let workers = 5 + 2 * validator;
+    ///     builder = builder.colocate(&[primary, workers, workers + 1]);
+    /// }
+    /// # #[derive(Default)]
+    /// # struct MyProcess;
+    /// # impl dscale::ProcessHandle for MyProcess {
+    /// #     fn start(&mut self) {}
+    /// #     fn on_message(&mut self, from: dscale::ProcessId, message: dscale::MessagePtr) {}
+    /// #     fn on_timer(&mut self, id: dscale::TimerId) {}
+    /// # }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// [`build`] panics if a process does not exist or is placed on several hosts.
+    ///
+    /// # Returns
+    ///
+    /// The `SimulationBuilder` instance for method chaining.
+    ///
+    /// [`nic_bandwidth`]: Self::nic_bandwidth
+    /// [`latency_topology`]: Self::latency_topology
+    /// [`build`]: Self::build
+    pub fn colocate(mut self, members: &[ProcessId]) -> Self {
+        self.hosts.push(members.to_vec());
+        self
+    }
+
+    /// Spreads the start of processes over time.
+    ///
+    /// By default every process executes `start` at jiffy 0, before any message
+    /// is delivered, which masks bootstrap races: messages (a genesis vertex, a
+    /// first heartbeat) reaching a process which is not up yet. With a jitter the
+    /// start of every process is delayed by a value drawn from `jitter`. Until it
+    /// starts, a process is down as if crashed: messages to it are lost. The
+    /// [`await_all_started`] barrier still fires once the last process started.
+    /// Crashing or restarting a process with a scenario before its start
+    /// replaces the start.
+    ///
+    /// # Arguments
+    ///
+    /// * `jitter` - Distribution of start delays, in jiffies
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dscale::{Distributions, Jiffies, SimulationBuilder};
+    ///
+    /// let builder = SimulationBuilder::default()
+    ///     .add_pool::<MyProcess>("replicas", 4)
+    ///     .startup_jitter(Distributions::Uniform(Jiffies(0), Jiffies(500)));
+    /// # #[derive(Default)]
+    /// # struct MyProcess;
+    /// # impl dscale::ProcessHandle for MyProcess {
+    /// #     fn start(&mut self) {}
+    /// #     fn on_message(&mut self, from: dscale::ProcessId, message: dscale::MessagePtr) {}
+    /// #     fn on_timer(&mut self, id: dscale::TimerId) {}
+    /// # }
+    /// ```
+    ///
+    /// # Returns
+    ///
+    /// The `SimulationBuilder` instance for method chaining.
+    ///
+    /// [`await_all_started`]: crate::global::bootstrap::await_all_started
+    pub fn startup_jitter(mut self, jitter: Distributions) -> Self {
+        self.startup_jitter = Some(jitter);
+        self
+    }
+
+    /// Sets the CPU speed factor of every process in the pool.
+    ///
+    /// The factor scales processing costs that processes simulate through
+    /// [`configuration::cpu_time`]: a process with factor `0.5` is twice as slow,
+    /// with factor `2.0` twice as fast as a process with the default factor `1.0`.
+    /// Mixed fleets (for example, one slow replica among fast ones) are configured
+    /// by combining it with [`process_cpu_speed`]; later calls override earlier ones.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - Name of the pool
+    /// * `factor` - Speed factor, must be positive
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dscale::SimulationBuilder;
+    ///
+    /// let builder = SimulationBuilder::default()
+    ///     .add_pool::<MyProcess>("replicas", 4)
+    ///     .cpu_speed("replicas", 2.0)    // Fast fleet
+    ///     .process_cpu_speed(1, 0.25);   // With one slow replica
+    /// # #[derive(Default)]
+    /// # struct MyProcess;
+    /// # impl dscale::ProcessHandle for MyProcess {
+    /// #     fn start(&mut self) {}
+    /// #     fn on_message(&mut self, from: dscale::ProcessId, message: dscale::MessagePtr) {}
+    /// #     fn on_timer(&mut self, id: dscale::TimerId) {}
+    /// # }
+    /// ```
+    ///
+    /// # Returns
+    ///
+    /// The `SimulationBuilder` instance for method chaining.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pool does not exist or the factor is not positive.
+    ///
+    /// [`configuration::cpu_time`]: crate::global::configuration::cpu_time
+    /// [`process_cpu_speed`]: SimulationBuilder::process_cpu_speed
+    pub fn cpu_speed(mut self, pool: &str, factor: f64) -> Self {
+        assert!(factor > 0.0, "CPU speed factor should be positive");
+        for id in self.pool_members(pool) {
+            self.cpu_speeds.insert(id, factor);
+        }
+        self
+    }
+
+    /// Sets the CPU speed factor of a single process, see [`cpu_speed`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the process does not exist or the factor is not positive.
+    ///
+    /// [`cpu_speed`]: SimulationBuilder::cpu_speed
+    pub fn process_cpu_speed(mut self, id: ProcessId, factor: f64) -> Self {
+        assert!(factor > 0.0, "CPU speed factor should be positive");
+        assert!(id > 0 && id < self.proc_id, "Unknown process P{id}");
+        self.cpu_speeds.insert(id, factor);
+        self
+    }
+
+    /// Sets the clock drift of every process in the pool.
+    ///
+    /// Drift is the rate at which the local clock of a process (see
+    /// [`configuration::local_now`]) deviates from simulation time: with drift
+    /// `0.01` the local clock runs 1% fast, with `-0.01` 1% slow. Drift only
+    /// affects protocols measuring time with local clocks, message delivery and
+    /// timers are always scheduled in simulation time. Combine it with
+    /// [`process_clock_drift`] to skew single processes; later calls override
+    /// earlier ones.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - Name of the pool
+    /// * `drift` - Relative clock rate deviation, must be greater than `-1.0`
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dscale::SimulationBuilder;
+    ///
+    /// let builder = SimulationBuilder::default()
+    ///     .add_pool::<MyProcess>("replicas", 4)
+    ///     .clock_drift("replicas", 0.001)   // Slightly fast clocks
+    ///     .process_clock_drift(1, -0.2);    // With one badly slow clock
+    /// # #[derive(Default)]
+    /// # struct MyProcess;
+    /// # impl dscale::ProcessHandle for MyProcess {
+    /// #     fn start(&mut self) {}
+    /// #     fn on_message(&mut self, from: dscale::ProcessId, message: dscale::MessagePtr) {}
+    /// #     fn on_timer(&mut self, id: dscale::TimerId) {}
+    /// # }
+    /// ```
+    ///
+    /// # Returns
+    ///
+    /// The `SimulationBuilder` instance for method chaining.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pool does not exist or the drift is not greater than `-1.0`.
+    ///
+    /// [`configuration::local_now`]: crate::global::configuration::local_now
+    /// [`process_clock_drift`]: SimulationBuilder::process_clock_drift
+    pub fn clock_drift(mut self, pool: &str, drift: f64) -> Self {
+        assert!(drift > -1.0, "Clock drift should be greater than -1.0");
+        for id in self.pool_members(pool) {
+            self.clock_drifts.insert(id, drift);
+        }
+        self
+    }
+
+    /// Sets the clock drift of a single process, see [`clock_drift`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the process does not exist or the drift is not greater than `-1.0`.
+    ///
+    /// [`clock_drift`]: SimulationBuilder::clock_drift
+    pub fn process_clock_drift(mut self, id: ProcessId, drift: f64) -> Self {
+        assert!(drift > -1.0, "Clock drift should be greater than -1.0");
+        assert!(id > 0 && id < self.proc_id, "Unknown process P{id}");
+        self.clock_drifts.insert(id, drift);
+        self
+    }
+
+    /// Synchronizes local clocks of every process in the pool in the background.
+    ///
+    /// Without synchronization local clocks drift apart forever (see
+    /// [`clock_drift`]). With it, processes periodically step their clocks back
+    /// close to simulation time, like NTP clients, see [`ClockSync`]. Later calls
+    /// override earlier ones for the same processes.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - Name of the pool
+    /// * `sync` - Poll interval and accuracy of the synchronization
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dscale::{ClockSync, Jiffies, SimulationBuilder};
+    ///
+    /// let builder = SimulationBuilder::default()
+    ///     .add_pool::<MyProcess>("replicas", 4)
+    ///     .clock_drift("replicas", 0.0001)
+    ///     .clock_sync("replicas", ClockSync::new(Jiffies(10_000), Jiffies(1)));
+    /// # #[derive(Default)]
+    /// # struct MyProcess;
+    /// # impl dscale::ProcessHandle for MyProcess {
+    /// #     fn start(&mut self) {}
+    /// #     fn on_message(&mut self, from: dscale::ProcessId, message: dscale::MessagePtr) {}
+    /// #     fn on_timer(&mut self, id: dscale::TimerId) {}
+    /// # }
+    /// ```
+    ///
+    /// # Returns
+    ///
+    /// The `SimulationBuilder` instance for method chaining.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pool does not exist.
+    ///
+    /// [`clock_drift`]: SimulationBuilder::clock_drift
+    pub fn clock_sync(mut self, pool: &str, sync: ClockSync) -> Self {
+        for id in self.pool_members(pool) {
+            self.clock_syncs.insert(id, sync);
+        }
+        self
+    }
+
+    /// Configures network bandwidth limitations for each process.
+    ///
+    /// This method sets the network interface bandwidth constraints that apply
+    /// to each process in the simulation. Bandwidth limits affect how quickly
+    /// messages can be transmitted and received, creating realistic network
+    /// bottlenecks.
+    ///
+    /// The bandwidth limit is applied per process (not globally), simulating
+    /// individual network interface constraints.
+    ///
+    /// # Arguments
+    ///
+    /// * `bandwidth` - A [`BandwidthDescription`] specifying the bandwidth constraints
+    ///
+    /// # Bandwidth Types
+    ///
+    /// - [`BandwidthDescription::Unbounded`] - No bandwidth limitations
+    /// - [`BandwidthDescription::Bounded(bytes_per_jiffy)`] - Limited to specified bytes per time unit
+    /// - [`BandwidthDescription::Rate(bandwidth)`] - Limited to a real-world rate, requires [`jiffy_duration`]
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dscale::{SimulationBuilder, BandwidthDescription};
+    ///
+    /// // Unlimited bandwidth
+    /// let builder1 = SimulationBuilder::default()
+    ///     .nic_bandwidth(BandwidthDescription::Unbounded);
+    ///
+    /// // Limited to 1000 bytes per jiffy
+    /// let builder2 = SimulationBuilder::default()
+    ///     .nic_bandwidth(BandwidthDescription::Bounded(1000));
+    /// ```
+    ///
+    /// # Message Sizing
+    ///
+    /// The bandwidth constraint uses the [`virtual_size()`] method of messages
+    /// to determine transmission time. This allows you to simulate large payloads
+    /// without actually storing large amounts of data in memory.
+    ///
+    /// # Returns
+    ///
+    /// The `SimulationBuilder` instance for method chaining.
+    ///
+    /// [`BandwidthDescription`]: crate::BandwidthDescription
+    /// [`BandwidthDescription::Unbounded`]: crate::BandwidthDescription::Unbounded
+    /// [`BandwidthDescription::Bounded`]: crate::BandwidthDescription::Bounded
+    /// [`BandwidthDescription::Rate(bandwidth)`]: crate::BandwidthDescription::Rate
+    /// [`jiffy_duration`]: SimulationBuilder::jiffy_duration
+    /// [`virtual_size()`]: crate::Message::virtual_size
+    pub fn nic_bandwidth(mut self, bandwidth: BandwidthDescription) -> Self {
+        self.bandwidth = bandwidth;
+        self
+    }
+
+    /// Caps and prices the link between two regions.
+    ///
+    /// Every direction of the link gets `link.bandwidth`, shared by all
+    /// processes of the regions, on top of their [`nic_bandwidth`]; every byte
+    /// crossing it is billed at `link.cost_per_gb`. Traffic of all links is
+    /// accounted in [`Simulation::link_stats`], see [`RegionLink`] for details.
+    /// The same region on both sides configures the links within it. Later
+    /// calls override earlier ones for the same regions.
+    ///
+    /// # Arguments
+    ///
+    /// * `a`, `b` - Regions at the ends of the link
+    /// * `link` - A [`RegionLink`] with capacity and price of the link
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dscale::{BandwidthDescription, RegionLink, SimulationBuilder};
+    ///
+    /// let builder = SimulationBuilder::default()
+    ///     .add_pool_in_region::<MyProcess>("replicas", "eu", 3)
+    ///     .add_pool_in_region::<MyProcess>("replicas", "asia", 3)
+    ///     // Free within a region, 10KB per jiffy at $0.08/GB across
+    ///     .region_link("eu", "asia", RegionLink {
+    ///         bandwidth: BandwidthDescription::Bounded(10_000),
+    ///         cost_per_gb: 0.08,
+    ///     });
+    /// # #[derive(Default)]
+    /// # struct MyProcess;
+    /// # impl dscale::ProcessHandle for MyProcess {
+    /// #     fn start(&mut self) {}
+    /// #     fn on_message(&mut self, from: dscale::ProcessId, message: dscale::MessagePtr) {}
+    /// #     fn on_timer(&mut self, id: dscale::TimerId) {}
+    /// # }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if a region has no processes, the bandwidth is zero or the cost is
+    /// negative. [`build`] panics if the bandwidth is a rate without
+    /// [`jiffy_duration`].
+    ///
+    /// # Returns
+    ///
+    /// The `SimulationBuilder` instance for method chaining.
+    ///
+    /// [`nic_bandwidth`]: SimulationBuilder::nic_bandwidth
+    /// [`Simulation::link_stats`]: crate::Simulation::link_stats
+    /// [`RegionLink`]: crate::RegionLink
+    /// [`build`]: SimulationBuilder::build
+    /// [`jiffy_duration`]: SimulationBuilder::jiffy_duration
+    pub fn region_link(mut self, a: &'static str, b: &'static str, link: RegionLink) -> Self {
+        self.region_members(a);
+        self.region_members(b);
+        assert!(
+            !matches!(link.bandwidth, BandwidthDescription::Bounded(0)),
+            "Link bandwidth should be positive"
+        );
+        assert!(link.cost_per_gb >= 0.0, "Link cost should not be negative");
+        self.region_links.insert((a, b), link);
+        self.region_links.insert((b, a), link);
+        self
+    }
+
+    /// Limits the number of messages waiting in the inbox of every process.
+    ///
+    /// Messages which already traveled through the network but can not be
+    /// delivered yet because of the receiver's [`nic_bandwidth`] wait in its
+    /// inbox. A bounded inbox applies the given [`OverflowPolicy`] once it is
+    /// full, which allows modeling overloaded receivers. Every dropped message
+    /// is accounted in [`Simulation::inbox_stats`].
+    ///
+    /// Inboxes only fill up with [`BandwidthDescription::Bounded`] bandwidth.
+    ///
+    /// # Arguments
+    ///
+    /// * `inbox` - An [`InboxDescription`] specifying the inbox limit
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dscale::{SimulationBuilder, BandwidthDescription, InboxDescription, OverflowPolicy};
+    ///
+    /// let builder = SimulationBuilder::default()
+    ///     .nic_bandwidth(BandwidthDescription::Bounded(1000))
+    ///     .inbox(InboxDescription::Bounded(128, OverflowPolicy::DropNewest));
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// [`build`] panics if the inbox capacity is zero.
+    ///
+    /// # Returns
+    ///
+    /// The `SimulationBuilder` instance for method chaining.
+    ///
+    /// [`nic_bandwidth`]: SimulationBuilder::nic_bandwidth
+    /// [`build`]: SimulationBuilder::build
+    /// [`OverflowPolicy`]: crate::OverflowPolicy
+    /// [`InboxDescription`]: crate::InboxDescription
+    /// [`BandwidthDescription::Bounded`]: crate::BandwidthDescription::Bounded
+    /// [`Simulation::inbox_stats`]: crate::Simulation::inbox_stats
+    pub fn inbox(mut self, inbox: InboxDescription) -> Self {
+        self.inbox = inbox;
+        self
+    }
+
+    /// Notifies senders of messages arriving at congested NICs.
+    ///
+    /// Once `threshold` messages wait for the [`nic_bandwidth`] of a receiving
+    /// host, the sender of every further message gets a [`CongestionNotification`]
+    /// (at most once until the buffer drains below the threshold). Disabled by
+    /// default; only matters with bounded bandwidth.
+    ///
+    /// # Arguments
+    ///
+    /// * `threshold` - Buffered messages at which a NIC counts as congested
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dscale::{SimulationBuilder, BandwidthDescription};
+    ///
+    /// let builder = SimulationBuilder::default()
+    ///     .nic_bandwidth(BandwidthDescription::Bounded(1000))
+    ///     .congestion_notification(32);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `threshold` is zero.
+    ///
+    /// # Returns
+    ///
+    /// The `SimulationBuilder` instance for method chaining.
+    ///
+    /// [`nic_bandwidth`]: SimulationBuilder::nic_bandwidth
+    /// [`CongestionNotification`]: crate::CongestionNotification
+    pub fn congestion_notification(mut self, threshold: usize) -> Self {
+        assert!(threshold > 0, "Congestion threshold should be positive");
+        self.congestion_threshold = Some(threshold);
+        self
+    }
+
+    /// Records latencies drawn for every link into a [`NetworkSchedule`].
+    ///
+    /// The schedule is read with [`Simulation::network_schedule`] and can be
+    /// replayed by another run with [`replay_network_schedule`]. Disabled by
+    /// default, because a sample of every sent message is kept until the
+    /// simulation is dropped. Replaces a schedule set for replay.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - Whether link latencies should be recorded
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dscale::SimulationBuilder;
+    ///
+    /// let builder = SimulationBuilder::default().record_network_schedule(true);
+    /// ```
+    ///
+    /// # Returns
+    ///
+    /// The `SimulationBuilder` instance for method chaining.
+    ///
+    /// [`NetworkSchedule`]: crate::NetworkSchedule
+    /// [`Simulation::network_schedule`]: crate::Simulation::network_schedule
+    /// [`replay_network_schedule`]: SimulationBuilder::replay_network_schedule
+    pub fn record_network_schedule(mut self, enabled: bool) -> Self {
+        self.network_schedule = match enabled {
+            true => ScheduleMode::Record(NetworkSchedule::default()),
+            false => ScheduleMode::Off,
+        };
+        self
+    }
+
+    /// Takes link latencies from `schedule` instead of drawing them.
+    ///
+    /// Links missing in the schedule still draw latencies from the latency
+    /// topology. Replaces recording of the schedule.
+    ///
+    /// # Arguments
+    ///
+    /// * `schedule` - A [`NetworkSchedule`], recorded by another run or loaded from a file
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dscale::{SimulationBuilder, Jiffies, NetworkSchedule};
+    ///
+    /// let mut schedule = NetworkSchedule::default();
+    /// schedule.insert(Jiffies(0), 1, 2, Jiffies(10));
+    /// schedule.insert(Jiffies(500), 1, 2, Jiffies(200)); // Link degrades
+    ///
+    /// let builder = SimulationBuilder::default().replay_network_schedule(schedule);
+    /// ```
+    ///
+    /// # Returns
+    ///
+    /// The `SimulationBuilder` instance for method chaining.
+    ///
+    /// [`NetworkSchedule`]: crate::NetworkSchedule
+    pub fn replay_network_schedule(mut self, schedule: NetworkSchedule) -> Self {
+        self.network_schedule = ScheduleMode::Replay(schedule);
+        self
+    }
+
+    /// Configures the disk of every process.
+    ///
+    /// Processes persist data with [`disk::write`] and [`disk::fsync`]; the
+    /// description determines how much simulated time flushes take. By default
+    /// disks are instant.
+    ///
+    /// # Arguments
+    ///
+    /// * `disk` - A [`DiskDescription`] specifying flush latency and throughput
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dscale::{SimulationBuilder, DiskDescription, Jiffies};
+    ///
+    /// let builder = SimulationBuilder::default()
+    ///     .disk(DiskDescription {
+    ///         fsync_latency: Jiffies(100),
+    ///         throughput: Some(1000),
+    ///         ..Default::default()
+    ///     });
+    /// ```
+    ///
+    /// # Returns
+    ///
+    /// The `SimulationBuilder` instance for method chaining.
+    ///
+    /// [`disk::write`]: crate::global::disk::write
+    /// [`disk::fsync`]: crate::global::disk::fsync
+    /// [`DiskDescription`]: crate::DiskDescription
+    pub fn disk(mut self, disk: DiskDescription) -> Self {
+        self.disk = disk;
+        self
+    }
+
+    /// Sets the memory budget of every process in the pool.
+    ///
+    /// Processes report the footprint of their state with [`memory::report`].
+    /// Once the footprint of a process exceeds the budget, the [`MemoryPolicy`]
+    /// of the limit applies: the process crashes, or it refuses messages until
+    /// its footprint drops under the budget again. Processes without a limit
+    /// never run out of memory. Combine it with [`process_memory_limit`] for a
+    /// single process; later calls override earlier ones.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - Name of the pool
+    /// * `limit` - A [`MemoryLimit`] specifying the budget and the policy
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dscale::{SimulationBuilder, MemoryLimit, MemoryPolicy};
+    ///
+    /// let builder = SimulationBuilder::default()
+    ///     .add_pool::<MyProcess>("validators", 4)
+    ///     .memory_limit("validators", MemoryLimit {
+    ///         budget: 1 << 30,
+    ///         policy: MemoryPolicy::Backpressure,
+    ///     })
+    ///     .process_memory_limit(1, MemoryLimit {
+    ///         budget: 1 << 20, // Underprovisioned validator
+    ///         policy: MemoryPolicy::Crash,
+    ///     });
+    /// # #[derive(Default)]
+    /// # struct MyProcess;
+    /// # impl dscale::ProcessHandle for MyProcess {
+    /// #     fn start(&mut self) {}
+    /// #     fn on_message(&mut self, from: dscale::ProcessId, message: dscale::MessagePtr) {}
+    /// #     fn on_timer(&mut self, id: dscale::TimerId) {}
+    /// # }
+    /// ```
+    ///
+    /// # Returns
+    ///
+    /// The `SimulationBuilder` instance for method chaining.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pool does not exist or the budget is zero.
+    ///
+    /// [`memory::report`]: crate::global::memory::report
+    /// [`MemoryPolicy`]: crate::MemoryPolicy
+    /// [`MemoryLimit`]: crate::MemoryLimit
+    /// [`process_memory_limit`]: SimulationBuilder::process_memory_limit
+    pub fn memory_limit(mut self, pool: &str, limit: MemoryLimit) -> Self {
+        assert!(limit.budget > 0, "Memory budget should be positive");
+        for id in self.pool_members(pool) {
+            self.memory_limits.insert(id, limit);
+        }
+        self
+    }
+
+    /// Sets the memory budget of a single process, see [`memory_limit`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the process does not exist or the budget is zero.
+    ///
+    /// [`memory_limit`]: SimulationBuilder::memory_limit
+    pub fn process_memory_limit(mut self, id: ProcessId, limit: MemoryLimit) -> Self {
+        assert!(limit.budget > 0, "Memory budget should be positive");
+        assert!(id > 0 && id < self.proc_id, "Unknown process P{id}");
+        self.memory_limits.insert(id, limit);
+        self
+    }
+
+    /// Sets what happens when a process reports a protocol error.
+    ///
+    /// Protocol errors are reported with [`protocol_error!`] by handlers reaching
+    /// states their protocol rules out, and by the framework for messages sent to
+    /// processes which do not exist (such messages are dropped). By default the
+    /// run aborts on the first one. With [`ErrorPolicy::Record`] fault-injected
+    /// runs survive incidental errors, which are listed afterwards by
+    /// [`Simulation::protocol_errors`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dscale::{ErrorPolicy, SimulationBuilder};
+    ///
+    /// let builder = SimulationBuilder::default()
+    ///     .add_pool::<MyProcess>("replicas", 5)
+    ///     .protocol_errors(ErrorPolicy::Record);
+    /// # #[derive(Default)]
+    /// # struct MyProcess;
+    /// # impl dscale::ProcessHandle for MyProcess {
+    /// #     fn start(&mut self) {}
+    /// #     fn on_message(&mut self, from: dscale::ProcessId, message: dscale::MessagePtr) {}
+    /// #     fn on_timer(&mut self, id: dscale::TimerId) {}
+    /// # }
+    /// ```
+    ///
+    /// # Returns
+    ///
+    /// The `SimulationBuilder` instance for method chaining.
+    ///
+    /// [`protocol_error!`]: crate::protocol_error
+    /// [`ErrorPolicy::Record`]: crate::ErrorPolicy::Record
+    /// [`Simulation::protocol_errors`]: crate::Simulation::protocol_errors
+    pub fn protocol_errors(mut self, policy: ErrorPolicy) -> Self {
+        self.error_policy = policy;
+        self
+    }
+
+    /// Configures which processes lead consensus over time.
+    ///
+    /// Each entry starts a period of [`Leadership`]: a pinned process, rotation
+    /// among a list of processes or rotation among processes of a region. Plain
+    /// [`ProcessId`]s are accepted as pinned leaders. Protocols consume the
+    /// schedule through [`LeaderSchedule::configured`] and fall back to their own
+    /// leader election if no schedule is configured.
+    ///
+    /// # Arguments
+    ///
+    /// * `periods` - Start times and leadership of periods, sorted by time
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dscale::{SimulationBuilder, Jiffies, helpers::Leadership};
+    ///
+    /// // Leader moves from P1 to P7 after 50k jiffies
+    /// let builder = SimulationBuilder::default()
+    ///     .leader_schedule(&[(Jiffies(0), 1), (Jiffies(50_000), 7)]);
+    ///
+    /// // Leadership rotates within a far region
+    /// let builder = SimulationBuilder::default()
+    ///     .leader_schedule(&[(Jiffies(0), Leadership::RotatingInRegion("asia"))]);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// [`build`] panics if periods are not sorted by time, if the schedule is empty,
+    /// or if a period has no leaders (for example, an unknown region).
+    ///
+    /// # Returns
+    ///
+    /// The `SimulationBuilder` instance for method chaining.
+    ///
+    /// [`Leadership`]: crate::helpers::Leadership
+    /// [`ProcessId`]: crate::ProcessId
+    /// [`LeaderSchedule::configured`]: crate::helpers::LeaderSchedule::configured
+    /// [`build`]: SimulationBuilder::build
+    pub fn leader_schedule<L: Into<Leadership> + Clone>(
+        mut self,
+        periods: &[(Jiffies, L)],
+    ) -> Self {
+        self.leader_schedule = Some(
+            periods
+                .iter()
+                .map(|(start, leadership)| (*start, leadership.clone().into()))
+                .collect(),
+        );
+        self
+    }
+
+    /// Sets sizes of signatures, digests and certificates for the simulation.
+    ///
+    /// Protocols compute sizes of cryptographic material in their messages with
+    /// [`crypto::size_model`], which returns the model set here or the default one.
+    ///
+    /// # Arguments
+    ///
+    /// * `model` - A [`SizeModel`] describing the signature scheme
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dscale::SimulationBuilder;
+    /// use dscale::crypto::{Aggregation, SizeModel};
+    ///
+    /// let builder = SimulationBuilder::default().size_model(SizeModel {
+    ///     aggregation: Aggregation::Concatenated,
+    ///     ..SizeModel::default()
+    /// });
+    /// ```
+    ///
+    /// # Returns
+    ///
+    /// The `SimulationBuilder` instance for method chaining.
+    ///
+    /// [`crypto::size_model`]: crate::crypto::size_model
+    /// [`SizeModel`]: crate::crypto::SizeModel
+    pub fn size_model(mut self, model: SizeModel) -> Self {
+        self.size_model = Some(model);
+        self
+    }
+
+    /// Sets the timeline of environmental events of the run.
+    ///
+    /// Partitions, crashes and restarts declared in the [`Scenario`] are executed
+    /// by the simulation at their times, so the run does not have to be stepped
+    /// manually. A new scenario replaces the previous one.
+    ///
+    /// # Arguments
+    ///
+    /// * `scenario` - Events to execute
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dscale::{SimulationBuilder, Jiffies, Scenario};
+    /// use dscale::scenario::{crash, restart};
+    ///
+    /// let builder = SimulationBuilder::default()
+    ///     .scenario(Scenario::new()
+    ///         .at(Jiffies(5_000), crash(3))
+    ///         .at(Jiffies(8_000), restart(3)));
+    /// ```
+    ///
+    /// # Returns
+    ///
+    /// The `SimulationBuilder` instance for method chaining.
+    ///
+    /// # Panics
+    ///
+    /// [`build`] panics if the scenario refers to a process which does not exist.
+    ///
+    /// [`Scenario`]: crate::Scenario
+    /// [`build`]: SimulationBuilder::build
+    pub fn scenario(mut self, scenario: Scenario) -> Self {
+        self.scenario = scenario;
+        self
+    }
+
+    /// Removes messages which can never be delivered from the network.
+    ///
+    /// Messages to a crashed process and across a partition stay queued until
+    /// their arrival and are dropped only then, so long fault windows fill the
+    /// queues (and, with bounded bandwidth, NIC buffers of crashed hosts) with
+    /// dead traffic. With pruning, a crash or a partition removes the messages
+    /// held back by it which arrive before the fault may end, and sends to a
+    /// process crashed for good are dropped right away. Faults end with the
+    /// [`restart`] and [`heal`] (or next [`inject_partition`]) events of
+    /// the [`scenario`]; without one they last forever. Counts are reported by
+    /// [`Simulation::pruned_events`].
+    ///
+    /// Pruned messages no longer take receiver bandwidth, and a message waiting
+    /// for bandwidth when its fault ends is lost anyway. Processes restarted
+    /// with [`Simulation::restart`] instead of the scenario do not get messages
+    /// pruned while they were down. Disabled by default; timers and scheduled
+    /// sends of crashed processes are pruned regardless.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - Whether undeliverable messages are pruned
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dscale::{SimulationBuilder, Jiffies, Scenario};
+    /// use dscale::scenario::{crash, restart};
+    ///
+    /// // Messages to P3 arriving before 80_000 are dropped at the crash
+    /// let builder = SimulationBuilder::default()
+    ///     .scenario(Scenario::new()
+    ///         .at(Jiffies(5_000), crash(3))
+    ///         .at(Jiffies(80_000), restart(3)))
+    ///     .prune_undeliverable(true);
+    /// ```
+    ///
+    /// # Returns
+    ///
+    /// The `SimulationBuilder` instance for method chaining.
+    ///
+    /// [`restart`]: crate::scenario::restart
+    /// [`heal`]: crate::scenario::heal
+    /// [`inject_partition`]: crate::scenario::inject_partition
+    /// [`scenario`]: SimulationBuilder::scenario
+    /// [`Simulation::pruned_events`]: crate::Simulation::pruned_events
+    /// [`Simulation::restart`]: crate::Simulation::restart
+    pub fn prune_undeliverable(mut self, enabled: bool) -> Self {
+        self.prune_undeliverable = enabled;
+        self
+    }
+
+    /// Sets how outdated the connectivity seen by [`list_reachable_pool`] is.
+    ///
+    /// [`list_pool`] lists every member of a pool, whatever happened to them,
+    /// while real processes learn about crashes and partitions only after a
+    /// while, e.g. once a failure detector times out. [`list_reachable_pool`]
+    /// lists the members the calling process could reach `staleness` ago: a
+    /// member crashed more recently is still listed, one restarted or healed
+    /// more recently is still missing. Defaults to zero, i.e. the current
+    /// connectivity.
+    ///
+    /// # Arguments
+    ///
+    /// * `staleness` - Delay until a change of connectivity is seen
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dscale::{SimulationBuilder, Jiffies, Scenario};
+    /// use dscale::scenario::crash;
+    ///
+    /// // Processes stop listing P3 at 5_500
+    /// let builder = SimulationBuilder::default()
+    ///     .scenario(Scenario::new().at(Jiffies(5_000), crash(3)))
+    ///     .reachability_staleness(Jiffies(500));
+    /// ```
+    ///
+    /// # Returns
+    ///
+    /// The `SimulationBuilder` instance for method chaining.
+    ///
+    /// [`list_reachable_pool`]: crate::list_reachable_pool
+    /// [`list_pool`]: crate::list_pool
+    pub fn reachability_staleness(mut self, staleness: Jiffies) -> Self {
+        self.reachability_staleness = staleness;
+        self
+    }
+
+    // Adds one event to the scenario instead of replacing it
+    pub(crate) fn schedule(mut self, at: Jiffies, event: ScenarioEvent) -> Self {
+        self.scenario = std::mem::take(&mut self.scenario).at(at, event);
+        self
+    }
+
+    /// Adds a custom actor to the event loop of the simulation.
+    ///
+    /// Actors are global entities living outside of processes: an oracle, a feed
+    /// of external events, a background chaos agent. The simulation steps them
+    /// together with the network, timers and scenario in time order, see
+    /// [`SimulationActor`]. Can be called several times. The caller may keep a
+    /// clone of `actor` to inspect or feed it between steps of the simulation.
+    ///
+    /// # Arguments
+    ///
+    /// * `actor` - Shared handle to the actor
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::{cell::RefCell, rc::Rc};
+    /// use dscale::{SimulationBuilder, SimulationActor, Jiffies};
+    ///
+    /// // Wakes up once
+    /// struct Alarm {
+    ///     at: Option<Jiffies>,
+    /// }
+    ///
+    /// impl SimulationActor for Alarm {
+    ///     fn start(&mut self) {}
+    ///     fn step(&mut self) {
+    ///         self.at = None;
+    ///     }
+    ///     fn peek_closest(&self) -> Option<Jiffies> {
+    ///         self.at
+    ///     }
+    /// }
+    ///
+    /// let alarm = Rc::new(RefCell::new(Alarm { at: Some(Jiffies(500)) }));
+    /// let mut simulation = SimulationBuilder::default()
+    ///     .add_actor(alarm.clone())
+    ///     .build();
+    ///
+    /// simulation.step_until(Jiffies(1_000));
+    /// assert!(alarm.borrow().at.is_none());
+    /// ```
+    ///
+    /// # Returns
+    ///
+    /// The `SimulationBuilder` instance for method chaining.
+    ///
+    /// [`SimulationActor`]: crate::SimulationActor
+    pub fn add_actor<A: SimulationActor + 'static>(mut self, actor: Rc<RefCell<A>>) -> Self {
+        self.actors.push(actor);
+        self
+    }
+
+    /// Adds a flow of background cross-traffic to the simulation.
+    ///
+    /// Can be called several times, flows are independent. See
+    /// [`BackgroundTraffic`] for the burst model and how cross-traffic interacts
+    /// with the network.
+    ///
+    /// # Arguments
+    ///
+    /// * `traffic` - A [`BackgroundTraffic`] flow between two pools
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dscale::{SimulationBuilder, BackgroundTraffic, Distributions, Jiffies};
+    ///
+    /// let builder = SimulationBuilder::default()
+    ///     .add_pool::<Replica>("replicas", 4)
+    ///     .add_pool::<Replica>("clients", 8)
+    ///     .background_traffic(BackgroundTraffic {
+    ///         from: "clients",
+    ///         to: "replicas",
+    ///         message_size: 1_000,
+    ///         interval: Jiffies(1),
+    ///         on: Distributions::Uniform(Jiffies(10), Jiffies(20)),
+    ///         off: Distributions::Uniform(Jiffies(100), Jiffies(300)),
+    ///     });
+    /// # #[derive(Default)]
+    /// # struct Replica;
+    /// # impl dscale::ProcessHandle for Replica {
+    /// #     fn start(&mut self) {}
+    /// #     fn on_message(&mut self, from: dscale::ProcessId, message: dscale::MessagePtr) {}
+    /// #     fn on_timer(&mut self, id: dscale::TimerId) {}
+    /// # }
+    /// ```
+    ///
+    /// # Returns
+    ///
+    /// The `SimulationBuilder` instance for method chaining.
+    ///
+    /// # Panics
+    ///
+    /// [`build`] panics if a referenced pool does not exist or the interval is zero.
+    ///
+    /// [`BackgroundTraffic`]: crate::BackgroundTraffic
+    /// [`build`]: Self::build
+    pub fn background_traffic(mut self, traffic: BackgroundTraffic) -> Self {
+        self.background_traffic.push(traffic);
+        self
+    }
+
+    /// Enables causal message tracing.
+    ///
+    /// With tracing enabled every sent message is recorded together with its
+    /// deliveries, and messages can be linked into causal trees using
+    /// [`tracing::in_reply_to`] and [`tracing::caused_by`]. Random draws of the
+    /// simulation are logged as well, see [`TraceDiff::cause`]. Disabled by
+    /// default, because records of all messages are kept until the simulation
+    /// is dropped.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - Whether messages should be traced
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dscale::SimulationBuilder;
+    ///
+    /// let builder = SimulationBuilder::default()
+    ///     .trace_messages(true);
+    /// ```
+    ///
+    /// # Returns
+    ///
+    /// The `SimulationBuilder` instance for method chaining.
+    ///
+    /// [`tracing::in_reply_to`]: crate::global::tracing::in_reply_to
+    /// [`tracing::caused_by`]: crate::global::tracing::caused_by
+    /// [`TraceDiff::cause`]: crate::helpers::TraceDiff::cause
+    pub fn trace_messages(mut self, enabled: bool) -> Self {
+        self.trace_messages = enabled;
+        self
+    }
+
+    /// Records recent events and pending timers of every process for [`sim_assert!`].
+    ///
+    /// With trails recorded, a failed [`sim_assert!`] (and a protocol error under
+    /// [`ErrorPolicy::Abort`]) also prints the last [`RECENT_EVENTS`] events
+    /// delivered to the failing process and its pending timers. Disabled by
+    /// default, because bookkeeping runs on every delivery and every timer.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - Whether trails should be recorded
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dscale::SimulationBuilder;
+    ///
+    /// let builder = SimulationBuilder::default()
+    ///     .record_trails(true);
+    /// ```
+    ///
+    /// # Returns
+    ///
+    /// The `SimulationBuilder` instance for method chaining.
+    ///
+    /// [`sim_assert!`]: crate::sim_assert
+    /// [`ErrorPolicy::Abort`]: crate::ErrorPolicy::Abort
+    /// [`RECENT_EVENTS`]: crate::helpers::assertion::RECENT_EVENTS
+    pub fn record_trails(mut self, enabled: bool) -> Self {
+        self.record_trails = enabled;
+        self
+    }
+
+    /// Logs every event within a window of simulated time.
+    ///
+    /// Process starts, message deliveries, timers, crashes, restarts, scenario
+    /// events and dropped messages happening within the window are printed to
+    /// stderr regardless of the log level, optionally pacing the run in
+    /// wall-clock time. Events outside of the window are not affected.
+    ///
+    /// # Arguments
+    ///
+    /// * `window` - A [`DebugWindow`] with the time range and pace
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::time::Duration;
+    /// use dscale::{SimulationBuilder, DebugWindow, Jiffies};
+    ///
+    /// let builder = SimulationBuilder::default()
+    ///     .debug_window(DebugWindow {
+    ///         start: Jiffies(4_000),
+    ///         end: Jiffies(4_100),
+    ///         pace: Duration::ZERO,
+    ///     });
+    /// ```
+    ///
+    /// # Returns
+    ///
+    /// The `SimulationBuilder` instance for method chaining.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the window is empty.
+    ///
+    /// [`DebugWindow`]: crate::DebugWindow
+    pub fn debug_window(mut self, window: DebugWindow) -> Self {
+        assert!(window.start < window.end, "Debug window is empty");
+        self.debug_window = Some(window);
+        self
+    }
+
+    /// Keeps the last log lines of every process for failure reports.
+    ///
+    /// Lines written with [`debug_process!`], [`warn_process!`] and
+    /// [`error_process!`] at `level` or more severe are captured into a ring
+    /// buffer of `lines` lines per process, regardless of `RUST_LOG`. When a
+    /// process step panics, lines of the panicking process are printed after the
+    /// panic message. Captured lines can also be read with
+    /// [`log_capture::recent_logs`].
+    ///
+    /// # Arguments
+    ///
+    /// * `lines` - Number of last lines kept per process
+    /// * `level` - Least severe level captured, e.g. `Level::Warn` for warnings and errors only
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dscale::SimulationBuilder;
+    ///
+    /// let builder = SimulationBuilder::default().capture_logs(32, log::Level::Debug);
+    /// ```
+    ///
+    /// # Returns
+    ///
+    /// The `SimulationBuilder` instance for method chaining.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `lines` is zero.
+    ///
+    /// [`debug_process!`]: crate::debug_process
+    /// [`warn_process!`]: crate::warn_process
+    /// [`error_process!`]: crate::error_process
+    /// [`log_capture::recent_logs`]: crate::helpers::log_capture::recent_logs
+    pub fn capture_logs(mut self, lines: usize, level: Level) -> Self {
+        assert!(lines > 0, "Log capture should keep at least one line");
+        self.log_capture = Some((lines, level));
+        self
+    }
+
+    /// Writes a bundle of run artifacts into `dir`.
+    ///
+    /// Once [`Simulation::run`] finishes, panics or deadlocks, the configuration,
+    /// the seed, per-process statistics, recorded metrics, the message trace and
+    /// a failure report go into `<dir>/seed-<seed>/`, see [`artifacts`] for the
+    /// layout. Without this call, the directory given with the
+    /// `--artifacts-dir <dir>` command line argument is used, so every binary
+    /// building a simulation supports the mode.
+    ///
+    /// # Arguments
+    ///
+    /// * `dir` - Root directory of the bundles
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dscale::{SimulationBuilder, Jiffies};
+    ///
+    /// let dir = std::env::temp_dir().join("dscale-artifacts-example");
+    /// let mut simulation = SimulationBuilder::default()
+    ///     .seed(7)
+    ///     .add_pool::<MyProcess>("nodes", 2)
+    ///     .time_budget(Jiffies(100))
+    ///     .artifacts_dir(&dir)
+    ///     .build();
+    /// simulation.run();
+    ///
+    /// let bundle = dir.join("seed-7");
+    /// assert!(bundle.join("config.txt").exists());
+    /// assert!(bundle.join("processes.csv").exists());
+    /// assert!(!bundle.join("failure.txt").exists());
+    /// # #[derive(Default)]
+    /// # struct MyProcess;
+    /// # impl dscale::ProcessHandle for MyProcess {
+    /// #     fn start(&mut self) { dscale::schedule_timer_after(Jiffies(10)); }
+    /// #     fn on_message(&mut self, from: dscale::ProcessId, message: dscale::MessagePtr) {}
+    /// #     fn on_timer(&mut self, id: dscale::TimerId) { dscale::schedule_timer_after(Jiffies(10)); }
+    /// # }
+    /// ```
+    ///
+    /// # Returns
+    ///
+    /// The `SimulationBuilder` instance for method chaining.
+    ///
+    /// [`Simulation::run`]: crate::Simulation::run
+    /// [`artifacts`]: crate::artifacts
+    pub fn artifacts_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.artifacts_dir = Some(dir.into());
+        self
+    }
+
+    /// Finalizes the configuration and builds the simulation.
+    ///
+    /// This method consumes the `SimulationBuilder` and creates a [`Simulation`]
+    /// instance ready to run. It performs final setup including:
+    ///
+    /// - Initializing the logging system
+    /// - Creating the process registry
+    /// - Setting up the network topology
+    /// - Configuring the simulation engine
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dscale::SimulationBuilder;
+    ///
+    /// let simulation = SimulationBuilder::default()
+    ///     .add_pool::<MyProcess>("nodes", 5)
+    ///     .build();
+    ///
+    /// // simulation.run();
+    /// # struct MyProcess;
+    /// # impl Default for MyProcess { fn default() -> Self { MyProcess } }
+    /// # impl dscale::ProcessHandle for MyProcess {
+    /// #     fn start(&mut self) {}
+    /// #     fn on_message(&mut self, from: dscale::ProcessId, message: dscale::MessagePtr) {}
+    /// #     fn on_timer(&mut self, id: dscale::TimerId) {}
+    /// # }
+    /// ```
+    ///
+    /// # Returns
+    ///
+    /// A configured [`Simulation`] ready to run.
+    ///
+    /// [`Simulation`]: crate::Simulation
+    pub fn build(self) -> Simulation {
+        init_logger();
+
+        let artifacts = self
+            .artifacts_dir
+            .clone()
+            .or_else(artifacts::dir_from_args)
+            .map(|dir| Bundle::new(dir, self.seed, self.snapshot()));
+
+        if let Some(periods) = &self.leader_schedule {
+            let periods = periods
+                .iter()
+                .map(|(start, leadership)| (*start, self.resolve_leaders(leadership)))
+                .collect();
+            anykv::set(LEADER_SCHEDULE_KEY, LeaderSchedule::new(periods));
+        }
+
+        if let Some(model) = self.size_model {
+            anykv::set(SIZE_MODEL_KEY, model);
+        }
+
+        self.cpu_speeds
+            .iter()
+            .for_each(|(id, speed)| configuration::setup_cpu_speed(*id, *speed));
+        self.clock_drifts
+            .iter()
+            .for_each(|(id, drift)| configuration::setup_clock_drift(*id, *drift));
+
+        if let Some(duration) = self.jiffy_duration {
+            configuration::setup_jiffy_duration(duration);
+        }
+
+        if let Some((lines, level)) = self.log_capture {
+            log_capture::setup_capture(lines, level);
+        }
+
+        let traffic = self
+            .background_traffic
+            .iter()
+            .map(|traffic| {
+                Flow::new(
+                    *traffic,
+                    self.pool_members(traffic.from),
+                    self.pool_members(traffic.to),
+                )
+            })
+            .collect();
+
+        let nics = self.place_on_hosts();
+        let mut latency_topology = self.latency_topology;
+        for host in &self.hosts {
+            for from in host {
+                for to in host.iter().filter(|to| *to != from) {
+                    latency_topology
+                        .insert((*from, *to), Distributions::Uniform(Jiffies(0), Jiffies(0)));
+                }
+            }
+        }
+
+        let mut pool_listing = HashMap::new();
+        let mut procs = BTreeMap::new();
+
+        for (name, pool) in self.pools {
+            let mut ids = Vec::new();
+            for (id, factory) in pool {
+                ids.push(id);
+                procs.insert(id, factory);
+            }
+            pool_listing.insert(name, ids);
+        }
+
+        let mut start_delays = BTreeMap::new();
+        if let Some(jitter) = self.startup_jitter {
+            let mut randomizer = Randomizer::new(self.seed);
+            for id in procs.keys() {
+                start_delays.insert(*id, Jiffies(randomizer.random_usize(jitter)));
+            }
+        }
+        let last_start = start_delays.values().max().copied().unwrap_or_default();
+        configuration::setup_last_start(last_start);
+        errors::setup_errors(self.error_policy);
+
+        // Clock synchronization precedes custom actors of the same jiffy
+        let mut actors = self.actors;
+        if !self.clock_syncs.is_empty() {
+            let sync: SharedActor = Rc::new(RefCell::new(ClockSyncActor::new(
+                self.clock_syncs,
+                self.seed,
+            )));
+            actors.insert(0, sync);
         }
 
         Simulation::new(
             self.seed,
+            self.tie_break,
             self.time_budget,
-            self.bandwidth,
-            self.latency_topology,
-            pool_listing,
+            self.event_budget,
+            self.bandwidth.resolve(self.jiffy_duration),
+            links::resolve(self.region_links, self.jiffy_duration),
+            self.inbox,
+            self.network_schedule,
+            self.congestion_threshold,
+            self.prune_undeliverable,
+            self.reachability_staleness,
+            self.disk,
+            self.memory_limits,
+            Topology::new_shared(
+                pool_listing,
+                latency_topology,
+                self.regions,
+                self.message_latency,
+                nics,
+                self.channel_orderings,
+                self.relay_trees,
+            ),
             procs,
+            start_delays,
+            self.scenario,
+            traffic,
+            actors,
+            self.trace_messages,
+            self.record_trails,
+            self.debug_window,
+            artifacts,
         )
     }
 }
+
+impl SimulationBuilder {
+    // Human readable configuration for the artifacts bundle
+    fn snapshot(&self) -> String {
+        let mut pools: Vec<(&String, Vec<ProcessId>)> = self
+            .pools
+            .iter()
+            .filter(|(name, _)| name.as_str() != GLOBAL_POOL)
+            .map(|(name, pool)| (name, pool.iter().map(|(id, _)| *id).collect()))
+            .collect();
+        pools.sort_by_key(|(_, ids)| ids.first().copied());
+
+        let mut out = String::new();
+        let _ = writeln!(out, "seed: {}", self.seed);
+        let _ = writeln!(out, "tie break: {:?}", self.tie_break);
+        let _ = writeln!(out, "time budget: {}", self.time_budget);
+        let _ = writeln!(out, "actor event budget: {}", self.event_budget);
+        let _ = writeln!(out, "jiffy duration: {:?}", self.jiffy_duration);
+        let _ = writeln!(out, "pools:");
+        pools.iter().for_each(|(name, ids)| {
+            let _ = writeln!(out, "  {name}: {ids:?}");
+        });
+        let _ = writeln!(out, "latency links: {}", self.latency_topology.len());
+        let _ = writeln!(out, "message latency rules: {}", self.message_latency.len());
+        let _ = writeln!(out, "regions: {}", self.regions.len());
+        let _ = writeln!(out, "hosts: {:?}", self.hosts);
+        let _ = writeln!(out, "channel orderings: {}", self.channel_orderings.len());
+        let _ = writeln!(out, "relay trees: {:?}", self.relay_trees);
+        let _ = writeln!(out, "bandwidth: {:?}", self.bandwidth);
+        let region_links: BTreeMap<_, _> = self.region_links.iter().collect();
+        let _ = writeln!(out, "region links: {region_links:?}");
+        let _ = writeln!(out, "inbox: {:?}", self.inbox);
+        let _ = writeln!(out, "congestion threshold: {:?}", self.congestion_threshold);
+        let _ = writeln!(out, "disk: {:?}", self.disk);
+        let _ = writeln!(out, "memory limits: {:?}", self.memory_limits);
+        let _ = writeln!(out, "protocol errors: {:?}", self.error_policy);
+        let _ = writeln!(out, "cpu speeds: {:?}", self.cpu_speeds);
+        let _ = writeln!(out, "clock drifts: {:?}", self.clock_drifts);
+        let _ = writeln!(out, "clock syncs: {:?}", self.clock_syncs);
+        let _ = writeln!(out, "startup jitter: {:?}", self.startup_jitter);
+        let _ = writeln!(
+            out,
+            "background traffic flows: {}",
+            self.background_traffic.len()
+        );
+        let _ = writeln!(out, "custom actors: {}", self.actors.len());
+        let _ = writeln!(out, "trace messages: {}", self.trace_messages);
+        let _ = writeln!(out, "record trails: {}", self.record_trails);
+        let _ = writeln!(out, "scenario: {:?}", self.scenario);
+        let _ = writeln!(out, "prune undeliverable: {}", self.prune_undeliverable);
+        let _ = writeln!(
+            out,
+            "reachability staleness: {}",
+            self.reachability_staleness
+        );
+        out
+    }
+}
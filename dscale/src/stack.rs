@@ -0,0 +1,303 @@
+//! Protocol stacks composed of layers.
+//!
+//! Protocols are often built on top of each other: a consensus engine on top of
+//! a broadcast primitive, a broadcast on top of the network. Instead of embedding
+//! the lower protocol into every upper one, each of them can implement [`Layer`]
+//! and a [`Stack`] wires them together. Messages from the network enter the
+//! bottom layer and climb the stack through deliveries, messages of upper layers
+//! descend through submissions until a layer sends them to the network.
+//!
+//! A stack becomes a process through [`Stacked`], which instantiates the stack
+//! described by a [`StackDefinition`].
+
+use std::{any::Any, collections::VecDeque, marker::PhantomData};
+
+use crate::{Message, MessagePtr, ProcessHandle, ProcessId, TimerId, message::Shared};
+
+enum Event {
+    Deliver(usize, ProcessId, MessagePtr), // To the layer above
+    Submit(usize, MessagePtr),             // To the layer below
+}
+
+/// Events emitted by a layer while it handles an event.
+///
+/// Passed to every method of [`Layer`]. Deliveries go to the layer above,
+/// submissions go to the layer below. They are routed once the current handler
+/// returns, in the order they were made.
+pub struct LayerContext {
+    layer: usize,
+    events: VecDeque<Event>,
+}
+
+impl LayerContext {
+    /// Hands `message` received from `from` to the layer above.
+    ///
+    /// Deliveries of the top layer are dropped.
+    pub fn deliver(&mut self, from: ProcessId, message: MessagePtr) {
+        self.events
+            .push_back(Event::Deliver(self.layer, from, message));
+    }
+
+    /// Hands `message` to the layer below, e.g. to broadcast it.
+    ///
+    /// # Panics
+    ///
+    /// Routing panics if called by the bottom layer, which should talk to the
+    /// network directly.
+    pub fn submit(&mut self, message: impl Message + 'static) {
+        self.events
+            .push_back(Event::Submit(self.layer, MessagePtr(Shared::new(message))));
+    }
+}
+
+/// A protocol which is a part of a [`Stack`].
+///
+/// Mirrors [`ProcessHandle`], with one more entry point for messages coming from
+/// the layer above. All global functions ([`send_to`], [`schedule_timer_after`],
+/// [`rank`], ...) are available within its methods.
+///
+/// # Examples
+///
+/// ```rust
+/// use dscale::{MessagePtr, ProcessId, Message, broadcast};
+/// use dscale::stack::{Layer, LayerContext};
+///
+/// // Bottom layer: wraps messages of the layer above into envelopes
+/// #[derive(Default)]
+/// struct Envelopes;
+///
+/// struct Envelope(MessagePtr);
+///
+/// impl Message for Envelope {
+///     fn virtual_size(&self) -> usize {
+///         8 + self.0.0.virtual_size()
+///     }
+/// }
+///
+/// impl Layer for Envelopes {
+///     fn on_deliver(&mut self, from: ProcessId, message: MessagePtr, context: &mut LayerContext) {
+///         let envelope = message.as_type::<Envelope>();
+///         context.deliver(from, MessagePtr(envelope.0.0.clone()));
+///     }
+///
+///     fn on_submit(&mut self, message: MessagePtr, context: &mut LayerContext) {
+///         broadcast(Envelope(message));
+///     }
+/// }
+/// ```
+///
+/// [`ProcessHandle`]: crate::ProcessHandle
+/// [`send_to`]: crate::send_to
+/// [`schedule_timer_after`]: crate::schedule_timer_after
+/// [`rank`]: crate::rank
+pub trait Layer {
+    /// Called once when the process starts, layers start from the bottom.
+    fn start(&mut self, _context: &mut LayerContext) {}
+
+    /// Handles a message of the network (for the bottom layer) or a delivery
+    /// of the layer below.
+    fn on_deliver(&mut self, from: ProcessId, message: MessagePtr, context: &mut LayerContext);
+
+    /// Handles a message submitted by the layer above.
+    fn on_submit(&mut self, _message: MessagePtr, _context: &mut LayerContext) {
+        panic!("Layer does not accept messages from above");
+    }
+
+    /// Handles a timer of the process. Every layer sees every timer and ignores
+    /// timers it did not schedule.
+    fn on_timer(&mut self, _id: TimerId, _context: &mut LayerContext) {}
+}
+
+/// Layers of a process, from the bottom (closest to the network) to the top.
+///
+/// # Examples
+///
+/// ```rust
+/// use dscale::{MessagePtr, ProcessId};
+/// use dscale::stack::{Layer, LayerContext, Stack, StackDefinition, Stacked};
+///
+/// #[derive(Default)]
+/// struct Network;
+/// impl Layer for Network {
+///     fn on_deliver(&mut self, from: ProcessId, message: MessagePtr, context: &mut LayerContext) {
+///         context.deliver(from, message);
+///     }
+/// }
+///
+/// #[derive(Default)]
+/// struct Application;
+/// impl Layer for Application {
+///     fn on_deliver(&mut self, from: ProcessId, message: MessagePtr, context: &mut LayerContext) {}
+/// }
+///
+/// struct Node;
+/// impl StackDefinition for Node {
+///     fn stack() -> Stack {
+///         Stack::new().layer(Network).layer(Application)
+///     }
+/// }
+///
+/// // Stacked<Node> is a process, e.g. SimulationBuilder::add_pool::<Stacked<Node>>
+/// let process = Stacked::<Node>::default();
+/// ```
+#[derive(Default)]
+pub struct Stack {
+    layers: Vec<Box<dyn AnyLayer>>,
+}
+
+// Layer which can be seen as its concrete type, see Stacked::layer
+trait AnyLayer: Layer {
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl<L: Layer + 'static> AnyLayer for L {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl Stack {
+    /// Creates an empty stack.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Puts `layer` on top of the stack.
+    pub fn layer(mut self, layer: impl Layer + 'static) -> Self {
+        self.layers.push(Box::new(layer));
+        self
+    }
+
+    /// Number of layers.
+    pub fn depth(&self) -> usize {
+        self.layers.len()
+    }
+
+    fn start(&mut self) {
+        (0..self.layers.len()).for_each(|layer| {
+            self.handle(layer, |layer, context| layer.start(context));
+        });
+    }
+
+    fn on_message(&mut self, from: ProcessId, message: MessagePtr) {
+        self.handle(0, |layer, context| layer.on_deliver(from, message, context));
+    }
+
+    fn on_timer(&mut self, id: TimerId) {
+        (0..self.layers.len()).for_each(|layer| {
+            self.handle(layer, |layer, context| layer.on_timer(id, context));
+        });
+    }
+
+    // Runs the handler and routes everything it emitted, transitively
+    fn handle(&mut self, layer: usize, f: impl FnOnce(&mut dyn Layer, &mut LayerContext)) {
+        let mut context = LayerContext {
+            layer,
+            events: VecDeque::new(),
+        };
+        f(self.layers[layer].as_mut(), &mut context);
+
+        while let Some(event) = context.events.pop_front() {
+            match event {
+                Event::Deliver(below, from, message) => {
+                    if below + 1 < self.layers.len() {
+                        context.layer = below + 1;
+                        self.layers[below + 1].on_deliver(from, message, &mut context);
+                    }
+                }
+                Event::Submit(above, message) => {
+                    assert!(above > 0, "Bottom layer should send to the network");
+                    context.layer = above - 1;
+                    self.layers[above - 1].on_submit(message, &mut context);
+                }
+            }
+        }
+    }
+}
+
+/// Describes the layers of a stacked process.
+pub trait StackDefinition {
+    /// Creates the layers of a fresh process instance.
+    fn stack() -> Stack;
+}
+
+/// Process running the stack of `D`.
+///
+/// Messages of the network go to the bottom layer, timers go to every layer.
+pub struct Stacked<D> {
+    stack: Stack,
+    _definition: PhantomData<D>,
+}
+
+impl<D: StackDefinition> Default for Stacked<D> {
+    fn default() -> Self {
+        Self {
+            stack: D::stack(),
+            _definition: PhantomData,
+        }
+    }
+}
+
+impl<D> Stacked<D> {
+    /// Returns the lowest layer of type `L`, e.g. to inspect the state of a
+    /// protocol after a run with [`Simulation::process`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dscale::{Jiffies, MessagePtr, ProcessId, SimulationBuilder};
+    /// use dscale::stack::{Layer, LayerContext, Stack, StackDefinition, Stacked};
+    ///
+    /// #[derive(Default)]
+    /// struct Counter {
+    ///     started: bool,
+    /// }
+    /// impl Layer for Counter {
+    ///     fn start(&mut self, context: &mut LayerContext) {
+    ///         self.started = true;
+    ///     }
+    ///     fn on_deliver(&mut self, from: ProcessId, message: MessagePtr, context: &mut LayerContext) {}
+    /// }
+    ///
+    /// struct Node;
+    /// impl StackDefinition for Node {
+    ///     fn stack() -> Stack {
+    ///         Stack::new().layer(Counter::default())
+    ///     }
+    /// }
+    ///
+    /// let mut simulation = SimulationBuilder::default()
+    ///     .add_pool::<Stacked<Node>>("nodes", 1)
+    ///     .build();
+    /// simulation.step_until(Jiffies(10));
+    ///
+    /// assert!(simulation.process::<Stacked<Node>>(1).borrow().layer::<Counter>().started);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the stack has no layer of type `L`.
+    ///
+    /// [`Simulation::process`]: crate::Simulation::process
+    pub fn layer<L: Layer + 'static>(&self) -> &L {
+        self.stack
+            .layers
+            .iter()
+            .find_map(|layer| layer.as_any().downcast_ref::<L>())
+            .unwrap_or_else(|| panic!("No layer {} in the stack", std::any::type_name::<L>()))
+    }
+}
+
+impl<D: StackDefinition> ProcessHandle for Stacked<D> {
+    fn start(&mut self) {
+        self.stack.start();
+    }
+
+    fn on_message(&mut self, from: ProcessId, message: MessagePtr) {
+        self.stack.on_message(from, message);
+    }
+
+    fn on_timer(&mut self, id: TimerId) {
+        self.stack.on_timer(id);
+    }
+}
@@ -0,0 +1,140 @@
+//! Background synchronization of local clocks.
+//!
+//! Local clocks (see [`configuration::local_now`]) either agree with simulation
+//! time or drift away from it forever. Real deployments sit in between: NTP
+//! brings clocks back close to the reference every now and then, and they drift
+//! apart again until the next poll. [`ClockSync`] configured with
+//! [`SimulationBuilder::clock_sync`] reproduces that, so protocols relying on
+//! loosely synchronized clocks (leases, timestamps ordering transactions) can be
+//! studied with bounded but non-zero skew.
+//!
+//! [`configuration::local_now`]: crate::global::configuration::local_now
+//! [`SimulationBuilder::clock_sync`]: crate::SimulationBuilder::clock_sync
+
+use std::collections::BTreeMap;
+
+use log::debug;
+
+use crate::{
+    Distributions, ProcessId, SimulationActor,
+    global::{self, configuration, tracing},
+    now,
+    random::{Randomizer, Seed},
+    time::Jiffies,
+};
+
+/// NTP-like synchronization of the local clocks of a pool.
+///
+/// Every `interval` jiffies of simulation time a process polls the reference
+/// and steps its local clock to simulation time, missing it by an error drawn
+/// uniformly from `-accuracy..=accuracy` (for NTP: half the asymmetry of the
+/// round trip to the server). Between polls the clock drifts again at the rate
+/// configured with [`SimulationBuilder::clock_drift`], so the skew of a process
+/// stays within `accuracy + interval * |drift|`. Steps may move a local clock
+/// backwards. First polls are spread over the first interval, crashed processes
+/// do not poll.
+///
+/// Synchronization runs in the background without messages, so it neither
+/// consumes bandwidth nor shows up in traces. It keeps the simulation busy until
+/// the time budget is exhausted.
+///
+/// # Examples
+///
+/// ```rust
+/// use dscale::{ClockSync, Jiffies, SimulationBuilder};
+///
+/// let builder = SimulationBuilder::default()
+///     .add_pool::<MyProcess>("replicas", 4)
+///     .clock_drift("replicas", 0.001)
+///     .clock_sync("replicas", ClockSync::new(Jiffies(1_000), Jiffies(2))); // Skew within 3 jiffies
+/// # #[derive(Default)]
+/// # struct MyProcess;
+/// # impl dscale::ProcessHandle for MyProcess {
+/// #     fn start(&mut self) {}
+/// #     fn on_message(&mut self, from: dscale::ProcessId, message: dscale::MessagePtr) {}
+/// #     fn on_timer(&mut self, id: dscale::TimerId) {}
+/// # }
+/// ```
+///
+/// [`SimulationBuilder::clock_drift`]: crate::SimulationBuilder::clock_drift
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ClockSync {
+    interval: Jiffies,
+    accuracy: Jiffies,
+}
+
+impl ClockSync {
+    /// Polls every `interval` jiffies, leaving local clocks off by at most
+    /// `accuracy` jiffies right after a poll.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `interval` is zero.
+    pub fn new(interval: Jiffies, accuracy: Jiffies) -> Self {
+        assert!(interval.0 > 0, "Clock sync interval should be positive");
+        Self { interval, accuracy }
+    }
+
+    /// Simulation time between polls of a process.
+    pub fn interval(&self) -> Jiffies {
+        self.interval
+    }
+
+    /// Largest error of a local clock right after a poll.
+    pub fn accuracy(&self) -> Jiffies {
+        self.accuracy
+    }
+}
+
+pub(crate) struct ClockSyncActor {
+    processes: BTreeMap<ProcessId, ClockSync>,
+    polls: BTreeMap<(Jiffies, ProcessId), ClockSync>, // Next poll of every process
+    randomizer: Randomizer,
+}
+
+impl ClockSyncActor {
+    pub(crate) fn new(processes: BTreeMap<ProcessId, ClockSync>, seed: Seed) -> Self {
+        Self {
+            processes,
+            polls: BTreeMap::new(),
+            randomizer: Randomizer::new(seed),
+        }
+    }
+
+    fn draw(&mut self, id: ProcessId, context: &str, upper: Jiffies) -> usize {
+        let value = self
+            .randomizer
+            .random_usize(Distributions::Uniform(Jiffies(0), upper));
+        tracing::on_draw("clock_sync", Some(id), || context.to_string(), &[value]);
+        value
+    }
+}
+
+impl SimulationActor for ClockSyncActor {
+    fn start(&mut self) {
+        let processes: Vec<(ProcessId, ClockSync)> = self
+            .processes
+            .iter()
+            .map(|(id, sync)| (*id, *sync))
+            .collect();
+        for (id, sync) in processes {
+            let first = self.draw(id, "first poll", Jiffies(sync.interval.0 - 1));
+            self.polls.insert((now() + Jiffies(first), id), sync);
+        }
+    }
+
+    fn step(&mut self) {
+        let ((_, id), sync) = self.polls.pop_first().expect("Poll is scheduled");
+        if !global::is_crashed(id) {
+            let error = self.draw(id, "clock error", Jiffies(2 * sync.accuracy.0)) as f64
+                - sync.accuracy.0 as f64;
+            debug!("P{id} synchronizes its clock, error: {error}");
+            configuration::set_clock_error(id, error);
+        }
+        self.polls.insert((now() + sync.interval, id), sync);
+    }
+
+    fn peek_closest(&self) -> Option<Jiffies> {
+        self.polls.keys().next().map(|(at, _)| *at)
+    }
+}
@@ -7,8 +7,11 @@
 use std::{
     fmt::{Debug, Display},
     ops::{Add, AddAssign, Mul, Sub},
+    time::Duration,
 };
 
+use crate::global::configuration;
+
 /// A discrete unit of simulation time in DScale.
 ///
 /// `Jiffies` represents time as discrete, integer-based units rather than
@@ -72,9 +75,86 @@ use std::{
 /// println!("{}", time);    // Prints: "Jiffies(12345)"
 /// println!("{:?}", time);  // Prints: "12345"
 /// ```
+///
+/// [`Jiffies::display`] adds the real-world time once the duration of a jiffy
+/// is configured with [`SimulationBuilder::jiffy_duration`].
+///
+/// [`SimulationBuilder::jiffy_duration`]: crate::SimulationBuilder::jiffy_duration
 #[derive(PartialEq, PartialOrd, Ord, Eq, Copy, Clone, Default)]
 pub struct Jiffies(pub usize);
 
+impl Jiffies {
+    /// Returns the real-world time represented by these jiffies.
+    ///
+    /// `None` unless the duration of a jiffy is configured with
+    /// [`SimulationBuilder::jiffy_duration`] for the current simulation.
+    ///
+    /// [`SimulationBuilder::jiffy_duration`]: crate::SimulationBuilder::jiffy_duration
+    pub fn to_duration(self) -> Option<Duration> {
+        configuration::jiffy_duration().map(|jiffy| self.scale(jiffy))
+    }
+
+    /// Displays the jiffies followed by the real-world time they represent, if
+    /// the duration of a jiffy is configured. Used by failure reports, so they
+    /// read without a conversion note.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dscale::{Jiffies, SimulationBuilder};
+    /// use std::time::Duration;
+    ///
+    /// let simulation = SimulationBuilder::default()
+    ///     .add_pool::<MyProcess>("replicas", 3)
+    ///     .jiffy_duration(Duration::from_micros(1))
+    ///     .build();
+    ///
+    /// assert_eq!(Jiffies(1500).display().to_string(), "Jiffies(1500) [1.5ms]");
+    /// # #[derive(Default)]
+    /// # struct MyProcess;
+    /// # impl dscale::ProcessHandle for MyProcess {
+    /// #     fn start(&mut self) {}
+    /// #     fn on_message(&mut self, from: dscale::ProcessId, message: dscale::MessagePtr) {}
+    /// #     fn on_timer(&mut self, id: dscale::TimerId) {}
+    /// # }
+    /// ```
+    pub fn display(self) -> JiffiesDisplay {
+        self.display_in(configuration::jiffy_duration())
+    }
+
+    // For times of recordings which outlive their simulation
+    pub(crate) fn display_in(self, jiffy_duration: Option<Duration>) -> JiffiesDisplay {
+        JiffiesDisplay {
+            jiffies: self,
+            duration: jiffy_duration.map(|jiffy| self.scale(jiffy)),
+        }
+    }
+
+    fn scale(self, jiffy: Duration) -> Duration {
+        let nanos = jiffy.as_nanos() * self.0 as u128;
+        Duration::new(
+            (nanos / 1_000_000_000) as u64,
+            (nanos % 1_000_000_000) as u32,
+        )
+    }
+}
+
+/// Jiffies with their real-world time, see [`Jiffies::display`].
+pub struct JiffiesDisplay {
+    jiffies: Jiffies,
+    duration: Option<Duration>,
+}
+
+impl Display for JiffiesDisplay {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&self.jiffies, f)?;
+        match self.duration {
+            Some(duration) => write!(f, " [{duration:?}]"),
+            None => Ok(()),
+        }
+    }
+}
+
 impl Add for Jiffies {
     type Output = Jiffies;
 
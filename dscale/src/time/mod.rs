@@ -1,5 +1,8 @@
+pub mod clock_sync;
 pub mod jiffy;
 pub mod timer_manager;
 
+pub use clock_sync::ClockSync;
 pub use jiffy::Jiffies;
+pub use jiffy::JiffiesDisplay;
 pub use timer_manager::TimerId;
@@ -12,8 +12,12 @@ use crate::{
     ProcessId,
     actor::{EventSubmitter, SimulationActor},
     dscale_message::DScaleMessage,
-    global, now,
+    global::{self, named_timer},
+    helpers::assertion,
+    now,
     nursery::Nursery,
+    pending::PendingEvents,
+    random::{TieBreak, TieBreaker},
     time::Jiffies,
 };
 
@@ -89,20 +93,65 @@ pub(crate) fn next_timer_id() -> TimerId {
 
 pub(crate) type TimerManagerActor = Rc<RefCell<TimerManager>>;
 
+type ScheduledTimer = (Jiffies, u64, u64, (ProcessId, TimerId, usize)); // u64 - tie and seq, usize - incarnation of the process
+
 pub(crate) struct TimerManager {
-    working_timers: BinaryHeap<Reverse<(Jiffies, (ProcessId, TimerId))>>,
+    working_timers: BinaryHeap<Reverse<ScheduledTimer>>,
+    pruned: usize,
+    tie_breaker: TieBreaker,
     nursery: Rc<Nursery>,
 }
 
 impl TimerManager {
-    pub(crate) fn new(nursery: Rc<Nursery>) -> Self {
+    pub(crate) fn new(nursery: Rc<Nursery>, tie_break: TieBreak) -> Self {
         Self {
             working_timers: BinaryHeap::new(),
+            pruned: 0,
+            tie_breaker: TieBreaker::new(tie_break),
             nursery,
         }
     }
 }
 
+impl TimerManager {
+    // Indexed by process id, timers of previous incarnations are skipped
+    pub(crate) fn count_pending(&self, pending: &mut [PendingEvents]) {
+        self.working_timers
+            .iter()
+            .map(|entry| entry.0.3)
+            .filter(|(process_id, _, incarnation)| {
+                *incarnation == self.nursery.incarnation(*process_id)
+            })
+            .for_each(|(process_id, ..)| pending[process_id].timers += 1);
+    }
+
+    // Timers of crashed processes and of previous incarnations never fire
+    pub(crate) fn prune(&mut self, retired: &[ProcessId]) {
+        let nursery = &self.nursery;
+        let before = self.working_timers.len();
+        self.working_timers.retain(|entry| {
+            let (process_id, timer_id, incarnation) = entry.0.3;
+            let dead = retired.contains(&process_id)
+                && (nursery.is_crashed(process_id)
+                    || incarnation != nursery.incarnation(process_id));
+            if dead {
+                named_timer::forget(timer_id);
+                assertion::record_timer_dropped(process_id, timer_id);
+            }
+            !dead
+        });
+        let pruned = before - self.working_timers.len();
+        if pruned > 0 {
+            debug!("Pruned {pruned} timers of {retired:?}");
+        }
+        self.pruned += pruned;
+    }
+
+    pub(crate) fn pruned(&self) -> usize {
+        self.pruned
+    }
+}
+
 impl SimulationActor for TimerManager {
     fn start(&mut self) {
         // Do nothing
@@ -113,10 +162,20 @@ impl SimulationActor for TimerManager {
     }
 
     fn step(&mut self) {
-        let (_, (process_id, timer_id)) = self.working_timers.pop().expect("Should not be empty").0;
+        let (_, _, _, (process_id, timer_id, incarnation)) =
+            self.working_timers.pop().expect("Should not be empty").0;
+        if incarnation != self.nursery.incarnation(process_id) {
+            debug!("Dropping timer with TimerId {timer_id} of restarted P{process_id}");
+            self.nursery
+                .observe(|| format!("P{process_id}: timer {timer_id} dropped, process restarted"));
+            named_timer::forget(timer_id);
+            assertion::record_timer_dropped(process_id, timer_id);
+            return;
+        }
         debug!("Firing timer with TimerId {timer_id} for P{process_id}");
         self.nursery
             .deliver(process_id, process_id, DScaleMessage::Timer(timer_id));
+        named_timer::forget(timer_id);
     }
 }
 
@@ -125,8 +184,15 @@ impl EventSubmitter for TimerManager {
 
     fn submit(&mut self, events: &mut Vec<Self::Event>) {
         events.drain(..).for_each(|(source, timer_id, after)| {
-            self.working_timers
-                .push(Reverse((now() + after, (source, timer_id))));
+            assertion::record_timer_scheduled(source, timer_id, now() + after);
+            let incarnation = self.nursery.incarnation(source);
+            let tie = self.tie_breaker.next();
+            self.working_timers.push(Reverse((
+                now() + after,
+                tie,
+                self.tie_breaker.seq(),
+                (source, timer_id, incarnation),
+            )));
         });
     }
 }
@@ -5,12 +5,24 @@
 //! modeling different latency patterns within process pools and between
 //! different pools to create realistic network topologies.
 
-use std::{collections::HashMap, rc::Rc};
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    rc::Rc,
+};
 
-use crate::{ProcessId, random::Distributions};
+use crate::{
+    ProcessId,
+    message::ProcessStep,
+    network::{ChannelOrderings, RelayTree, RelayTrees},
+    random::Distributions,
+};
 
 pub(crate) type LatencyTopology = HashMap<(ProcessId, ProcessId), Distributions>;
 pub(crate) type PoolListing = HashMap<String, Vec<ProcessId>>;
+pub(crate) type RegionListing = HashMap<ProcessId, &'static str>;
+pub(crate) type MessageFilter = Box<dyn Fn(&dyn Any) -> bool>;
+pub(crate) type MessageLatency = HashMap<TypeId, Vec<(MessageFilter, Distributions)>>;
 
 /// Default pool for all processes within simulation.
 /// Broadcasts by default use this pool.
@@ -28,6 +40,7 @@ pub const GLOBAL_POOL: &str = "global_pool";
 /// The latency system supports two primary relationship types:
 /// - **Within Pool**: Latency between processes in the same named pool
 /// - **Between Pools**: Latency between processes in different named pools
+/// - **One Way**: Latency in a single direction, for asymmetric links
 ///
 /// Each relationship can be configured with different probability distributions
 /// to model various network characteristics like jitter, packet loss, and
@@ -187,24 +200,128 @@ pub enum LatencyDescription {
     ///
     /// [`Distributions`]: crate::Distributions
     BetweenPools(&'static str, &'static str, Distributions),
+
+    /// Configures latency for messages between processes of the same region.
+    ///
+    /// Regions are assigned with [`add_pool_in_region`] and may span several
+    /// pools, which allows one pool (for example, all replicas) to be spread
+    /// across regions with different latencies.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dscale::{LatencyDescription, Distributions, Jiffies};
+    ///
+    /// let local = LatencyDescription::WithinRegion("eu",
+    ///     Distributions::Uniform(Jiffies(1), Jiffies(2))
+    /// );
+    /// ```
+    ///
+    /// [`add_pool_in_region`]: crate::SimulationBuilder::add_pool_in_region
+    WithinRegion(&'static str, Distributions),
+
+    /// Configures latency for messages between processes of two regions.
+    ///
+    /// Applied in both directions, same as [`LatencyDescription::BetweenPools`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dscale::{LatencyDescription, Distributions, Jiffies};
+    ///
+    /// let transatlantic = LatencyDescription::BetweenRegions("eu", "us",
+    ///     Distributions::Normal(Jiffies(80), Jiffies(5))
+    /// );
+    /// ```
+    BetweenRegions(&'static str, &'static str, Distributions),
+
+    /// Configures latency for messages from the first pool to the second one only.
+    ///
+    /// Unlike [`LatencyDescription::BetweenPools`], the opposite direction is not
+    /// affected, which models asymmetric one-way delays: satellite links,
+    /// consumer uplinks and alike. Descriptions are applied in order, so a
+    /// one-way description can override one direction of a symmetric one.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dscale::{LatencyDescription, Distributions, Jiffies};
+    ///
+    /// let links = [
+    ///     // Fast downlink from servers to clients...
+    ///     LatencyDescription::BetweenPools("servers", "clients",
+    ///         Distributions::Uniform(Jiffies(5), Jiffies(10))),
+    ///     // ...but slow uplink from clients to servers
+    ///     LatencyDescription::OneWayBetweenPools("clients", "servers",
+    ///         Distributions::Uniform(Jiffies(50), Jiffies(80))),
+    /// ];
+    /// ```
+    OneWayBetweenPools(&'static str, &'static str, Distributions),
+
+    /// Configures latency for messages from the first region to the second one only.
+    ///
+    /// The regional counterpart of [`LatencyDescription::OneWayBetweenPools`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dscale::{LatencyDescription, Distributions, Jiffies};
+    ///
+    /// let uplink = LatencyDescription::OneWayBetweenRegions("ground", "orbit",
+    ///     Distributions::Normal(Jiffies(300), Jiffies(20))
+    /// );
+    /// ```
+    OneWayBetweenRegions(&'static str, &'static str, Distributions),
 }
 
 pub(crate) struct Topology {
     pool_listing: PoolListing,
     latency_topology: LatencyTopology,
+    regions: RegionListing,
+    message_latency: MessageLatency,
+    nics: Vec<ProcessId>, // Index - process, value - process owning NIC of its host
+    channel_orderings: ChannelOrderings,
+    relay_trees: RelayTrees,
 }
 
 impl Topology {
     pub(crate) fn new_shared(
         pool_listing: PoolListing,
         latency_topology: LatencyTopology,
+        regions: RegionListing,
+        message_latency: MessageLatency,
+        nics: Vec<ProcessId>,
+        channel_orderings: ChannelOrderings,
+        relay_trees: RelayTrees,
     ) -> Rc<Self> {
         Rc::new(Self {
             pool_listing,
             latency_topology,
+            regions,
+            message_latency,
+            nics,
+            channel_orderings,
+            relay_trees,
         })
     }
 
+    pub(crate) fn channel_orderings(&self) -> ChannelOrderings {
+        self.channel_orderings.clone()
+    }
+
+    pub(crate) fn relay_tree(&self, pool_name: &str) -> Option<RelayTree> {
+        self.relay_trees.get(pool_name).copied()
+    }
+
+    // Colocated processes share NIC of the host
+    pub(crate) fn nic_of(&self, id: ProcessId) -> ProcessId {
+        self.nics[id]
+    }
+
+    pub(crate) fn same_host(&self, a: ProcessId, b: ProcessId) -> bool {
+        self.nics[a] == self.nics[b]
+    }
+
     pub(crate) fn get_distribution(&self, from: ProcessId, to: ProcessId) -> Distributions {
         self.latency_topology
             .get(&(from, to))
@@ -212,7 +329,58 @@ impl Topology {
             .expect("No distr found")
     }
 
+    // Extra latencies of all rules matching the message
+    pub(crate) fn message_latency(
+        &self,
+        step: &ProcessStep,
+    ) -> impl Iterator<Item = Distributions> {
+        let message = step.message.as_ref() as &dyn Any;
+        self.message_latency
+            .get(&step.message_type.id)
+            .into_iter()
+            .flatten()
+            .filter(move |(filter, _)| filter(message))
+            .map(|(_, distr)| *distr)
+    }
+
     pub(crate) fn list_pool(&self, pool_name: &str) -> &[usize] {
         self.pool_listing.get(pool_name).expect("Invalid pool name")
     }
+
+    pub(crate) fn pool_member(&self, pool_name: &str, index: usize) -> Option<ProcessId> {
+        self.pool_listing.get(pool_name)?.get(index).copied()
+    }
+
+    // Named pool of the process (never GLOBAL_POOL) and its index within the pool
+    pub(crate) fn pool_of(&self, id: ProcessId) -> Option<(String, usize)> {
+        self.pool_listing
+            .iter()
+            .filter(|(name, _)| *name != GLOBAL_POOL)
+            .find_map(|(name, ids)| {
+                let index = ids.iter().position(|member| *member == id)?;
+                Some((name.clone(), index))
+            })
+    }
+
+    pub(crate) fn region_of(&self, id: ProcessId) -> Option<&'static str> {
+        self.regions.get(&id).copied()
+    }
+
+    // Mean of the link latency, None if the processes are not connected
+    pub(crate) fn expected_latency(&self, from: ProcessId, to: ProcessId) -> Option<f64> {
+        self.latency_topology
+            .get(&(from, to))
+            .map(|distr| distr.mean())
+    }
+
+    // Processes of the pool with the lowest expected latency from `from`, in pool order
+    pub(crate) fn nearest_in_pool(&self, from: ProcessId, pool_name: &str) -> Vec<ProcessId> {
+        let expected = |to: &ProcessId| self.expected_latency(from, *to).unwrap_or(f64::INFINITY);
+        let pool = self.list_pool(pool_name);
+        let closest = pool.iter().map(expected).fold(f64::INFINITY, f64::min);
+        pool.iter()
+            .copied()
+            .filter(|to| expected(to) == closest)
+            .collect()
+    }
 }
@@ -0,0 +1,171 @@
+//! Hosting simulated processes on real networks.
+//!
+//! Processes are written against the global communication API of DScale
+//! ([`send_to`], [`broadcast`], [`schedule_timer_after`], [`rank`], [`now`]).
+//! This module lets the same [`ProcessHandle`] implementations run outside of
+//! a [`Simulation`]: a runtime implements [`Transport`], installs it for the
+//! thread driving the process with [`host`] and forwards received messages and
+//! expired timers to the process.
+//!
+//! [`tcp::TcpNode`] is a reference runtime built on std threads and blocking
+//! sockets rather than tokio. A hosted process is driven from a single thread
+//! anyway, because the transport is installed per thread and handlers are
+//! synchronous, so an async runtime would only move socket reads to other
+//! tasks, which reader threads do here without adding a dependency to the
+//! simulator. Blocking connects happen before the process starts (peers get
+//! [`tcp::TcpNode::startup_timeout`] to come up) and on sends to peers that
+//! dropped their connection. Async runtimes plug in the same way, by
+//! implementing [`Transport`] and driving the process from one thread.
+//!
+//! Randomized pool helpers ([`choose_from_pool`], [`send_random`] and others)
+//! and simulation-only facilities (disk, wal, tracing, bootstrap) are not available
+//! outside of simulation.
+//!
+//! [`send_to`]: crate::send_to
+//! [`broadcast`]: crate::broadcast
+//! [`schedule_timer_after`]: crate::schedule_timer_after
+//! [`rank`]: crate::rank
+//! [`now`]: crate::now
+//! [`choose_from_pool`]: crate::choose_from_pool
+//! [`send_random`]: crate::global::send_random
+//! [`ProcessHandle`]: crate::ProcessHandle
+//! [`Simulation`]: crate::Simulation
+
+pub mod tcp;
+
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{
+    Jiffies, Message, ProcessId, TimerId, global::fast_forward_clock, message::SharedMessage,
+};
+
+/// Communication primitives of a process hosted outside of simulation.
+///
+/// Global functions such as [`send_to`] are routed to the transport installed
+/// with [`host`] for the current thread.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::{cell::RefCell, rc::Rc};
+/// use dscale::{Jiffies, Message, ProcessId, TimerId, rank, send_to};
+/// use dscale::message::SharedMessage;
+/// use dscale::transport::{self, Transport};
+///
+/// // Collects outgoing messages instead of sending them
+/// #[derive(Default)]
+/// struct Outbox {
+///     sent: Vec<ProcessId>,
+/// }
+///
+/// impl Transport for Outbox {
+///     fn rank(&self) -> ProcessId { 1 }
+///     fn send_to(&mut self, to: ProcessId, _message: SharedMessage) { self.sent.push(to) }
+///     fn broadcast_within_pool(&mut self, _pool: &str, _message: SharedMessage) {}
+///     fn schedule_timer_after(&mut self, _after: Jiffies) -> TimerId { 0 }
+///     fn list_pool(&self, _pool: &str) -> Vec<ProcessId> { vec![1, 2] }
+/// }
+///
+/// struct Ping;
+/// impl Message for Ping {}
+///
+/// let outbox = Rc::new(RefCell::new(Outbox::default()));
+/// transport::host(outbox.clone());
+/// assert_eq!(rank(), 1);
+/// send_to(2, Ping);
+/// transport::unhost();
+///
+/// assert_eq!(outbox.borrow().sent, vec![2]);
+/// ```
+///
+/// [`send_to`]: crate::send_to
+pub trait Transport {
+    /// Returns id of the hosted process.
+    fn rank(&self) -> ProcessId;
+
+    /// Sends a message to a process.
+    fn send_to(&mut self, to: ProcessId, message: SharedMessage);
+
+    /// Sends a message to all processes of a pool, including the sender if it
+    /// is a member.
+    fn broadcast_within_pool(&mut self, pool: &str, message: SharedMessage);
+
+    /// Arms a timer, which the runtime delivers to the process after `after`.
+    fn schedule_timer_after(&mut self, after: Jiffies) -> TimerId;
+
+    /// Lists members of a pool.
+    fn list_pool(&self, pool: &str) -> Vec<ProcessId>;
+}
+
+/// Converts messages to bytes and back for transmission over real networks.
+///
+/// Messages are type-erased, so codecs usually downcast them to the known
+/// message types of a protocol. Bytes come from the wire, so decoding returns
+/// `None` for malformed input instead of panicking; runtimes drop such frames.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::any::Any;
+/// use dscale::{Message, MessagePtr, transport::Codec};
+/// use dscale::message::{Shared, SharedMessage};
+///
+/// struct Counter(u64);
+/// impl Message for Counter {}
+///
+/// struct CounterCodec;
+///
+/// impl Codec for CounterCodec {
+///     fn encode(&self, message: &dyn Message) -> Vec<u8> {
+///         let counter = (message as &dyn Any).downcast_ref::<Counter>().expect("Unknown message");
+///         counter.0.to_le_bytes().to_vec()
+///     }
+///
+///     fn decode(&self, bytes: &[u8]) -> Option<SharedMessage> {
+///         let counter = Counter(u64::from_le_bytes(bytes.try_into().ok()?));
+///         Some(Shared::new(counter))
+///     }
+/// }
+///
+/// let codec = CounterCodec;
+/// let decoded = codec.decode(&codec.encode(&Counter(42))).unwrap();
+/// assert_eq!(MessagePtr(decoded).as_type::<Counter>().0, 42);
+/// assert!(codec.decode(&[1, 2, 3]).is_none());
+/// ```
+pub trait Codec {
+    /// Encodes a message into bytes.
+    fn encode(&self, message: &dyn Message) -> Vec<u8>;
+
+    /// Decodes a message encoded by [`Codec::encode`], `None` if `bytes` are malformed.
+    fn decode(&self, bytes: &[u8]) -> Option<SharedMessage>;
+}
+
+/// Transport shared between a runtime and the hosted process.
+pub type SharedTransport = Rc<RefCell<dyn Transport>>;
+
+thread_local! {
+    static HOSTED: RefCell<Option<SharedTransport>> = const { RefCell::new(None) };
+}
+
+/// Routes global communication functions of the current thread to `transport`.
+pub fn host(transport: SharedTransport) {
+    HOSTED.set(Some(transport));
+}
+
+/// Removes the transport installed with [`host`].
+pub fn unhost() {
+    HOSTED.take();
+}
+
+/// Moves the clock observed through [`now`] forward.
+///
+/// Runtimes call it before handing an event to the process.
+///
+/// [`now`]: crate::now
+pub fn advance_clock(to: Jiffies) {
+    fast_forward_clock(to);
+}
+
+pub(crate) fn hosted() -> Option<SharedTransport> {
+    HOSTED.with_borrow(|hosted| hosted.clone())
+}
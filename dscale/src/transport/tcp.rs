@@ -0,0 +1,422 @@
+//! Reference runtime hosting a process over TCP.
+
+use std::{
+    cell::RefCell,
+    cmp::Reverse,
+    collections::{BTreeMap, BTreeSet, BinaryHeap, HashMap, VecDeque},
+    io::{self, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    rc::Rc,
+    sync::{
+        Arc,
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use log::{debug, warn};
+
+use crate::{
+    GLOBAL_POOL, Jiffies, MessagePtr, ProcessHandle, ProcessId, TimerId,
+    global::{configuration, filter, global_unique_id, named_timer},
+    message::SharedMessage,
+    random::Seed,
+    transport::{self, Codec, Transport},
+};
+
+const HEADER_SIZE: usize = 12; // Sender id (u64) + payload length (u32)
+
+/// Default limit of payload length accepted from peers, see [`TcpNode::max_frame`].
+pub const DEFAULT_MAX_FRAME: usize = 16 * 1024 * 1024;
+
+/// Default time to wait for peers at startup, see [`TcpNode::startup_timeout`].
+pub const DEFAULT_STARTUP_TIMEOUT: Duration = Duration::from_secs(10);
+
+const CONNECT_RETRY: Duration = Duration::from_millis(10);
+
+type Frame = (ProcessId, Vec<u8>);
+
+/// Hosts a single process, exchanging messages with peers over TCP.
+///
+/// Every node listens on its own address, connects to all peers before
+/// starting the process (waiting up to [`TcpNode::startup_timeout`] for them
+/// to listen) and frames messages as sender id, payload length and payload
+/// encoded with the [`Codec`]. Timers and [`now`] are driven by wall-clock
+/// time, one jiffy lasts 1ms unless configured with [`TcpNode::jiffy`].
+/// Messages to unreachable peers and messages the codec fails to decode are
+/// dropped, as on a lossy network. A peer announcing a payload longer than
+/// [`TcpNode::max_frame`] is disconnected, and so is a peer claiming a sender
+/// id missing from `peers`. Sender ids are not authenticated otherwise.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use std::{collections::BTreeMap, net::TcpListener};
+/// use dscale::{Jiffies, transport::tcp::TcpNode};
+/// # use dscale::message::SharedMessage;
+/// # use dscale::{Message, MessagePtr, ProcessHandle, ProcessId, TimerId, transport::Codec};
+/// # #[derive(Default)]
+/// # struct Replica;
+/// # impl ProcessHandle for Replica {
+/// #     fn start(&mut self) {}
+/// #     fn on_message(&mut self, from: ProcessId, message: MessagePtr) {}
+/// #     fn on_timer(&mut self, id: TimerId) {}
+/// # }
+/// # struct ReplicaCodec;
+/// # impl Codec for ReplicaCodec {
+/// #     fn encode(&self, message: &dyn Message) -> Vec<u8> { Vec::new() }
+/// #     fn decode(&self, bytes: &[u8]) -> Option<SharedMessage> { None }
+/// # }
+///
+/// let peers = BTreeMap::from([
+///     (1, "10.0.0.1:7000".parse().unwrap()),
+///     (2, "10.0.0.2:7000".parse().unwrap()),
+///     (3, "10.0.0.3:7000".parse().unwrap()),
+/// ]);
+///
+/// // On machine 10.0.0.1
+/// let listener = TcpListener::bind(peers[&1]).unwrap();
+/// TcpNode::new(1, listener, peers, ReplicaCodec)
+///     .run(Replica::default(), Jiffies(60_000))
+///     .unwrap();
+/// ```
+///
+/// [`now`]: crate::now
+pub struct TcpNode {
+    id: ProcessId,
+    listener: TcpListener,
+    max_frame: usize,
+    startup_timeout: Duration,
+    seed: Seed,
+    state: Rc<RefCell<NodeState>>,
+}
+
+struct NodeState {
+    id: ProcessId,
+    start: Instant,
+    jiffy: Duration,
+    peers: BTreeMap<ProcessId, SocketAddr>,
+    pools: HashMap<String, Vec<ProcessId>>,
+    codec: Box<dyn Codec>,
+    connections: HashMap<ProcessId, TcpStream>,
+    timers: BinaryHeap<Reverse<(Jiffies, TimerId)>>,
+    loopback: VecDeque<SharedMessage>,
+}
+
+impl TcpNode {
+    /// Creates a node for process `id` accepting connections on `listener`.
+    ///
+    /// `peers` lists addresses of all processes including this one; all of
+    /// them form [`GLOBAL_POOL`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` is not listed in `peers`.
+    ///
+    /// [`GLOBAL_POOL`]: crate::GLOBAL_POOL
+    pub fn new(
+        id: ProcessId,
+        listener: TcpListener,
+        peers: BTreeMap<ProcessId, SocketAddr>,
+        codec: impl Codec + 'static,
+    ) -> Self {
+        assert!(peers.contains_key(&id), "P{id} is not listed in peers");
+        let pools = HashMap::from([(GLOBAL_POOL.to_string(), peers.keys().copied().collect())]);
+        Self {
+            id,
+            listener,
+            max_frame: DEFAULT_MAX_FRAME,
+            startup_timeout: DEFAULT_STARTUP_TIMEOUT,
+            seed: 0,
+            state: Rc::new(RefCell::new(NodeState {
+                id,
+                start: Instant::now(),
+                jiffy: Duration::from_millis(1),
+                peers,
+                pools,
+                codec: Box::new(codec),
+                connections: HashMap::new(),
+                timers: BinaryHeap::new(),
+                loopback: VecDeque::new(),
+            })),
+        }
+    }
+
+    /// Declares a named pool of peers for [`broadcast_within_pool`].
+    ///
+    /// [`broadcast_within_pool`]: crate::broadcast_within_pool
+    pub fn pool(self, name: &str, members: &[ProcessId]) -> Self {
+        self.state
+            .borrow_mut()
+            .pools
+            .insert(name.to_string(), members.to_vec());
+        self
+    }
+
+    /// Sets wall-clock duration of one jiffy.
+    pub fn jiffy(self, duration: Duration) -> Self {
+        self.state.borrow_mut().jiffy = duration;
+        self
+    }
+
+    /// Sets the longest payload in bytes accepted from peers, [`DEFAULT_MAX_FRAME`]
+    /// by default.
+    ///
+    /// Frames are read into memory whole, so the limit bounds the memory a
+    /// malformed or malicious frame can make the node allocate. The connection
+    /// a longer frame arrives on is closed.
+    pub fn max_frame(mut self, bytes: usize) -> Self {
+        self.max_frame = bytes;
+        self
+    }
+
+    /// Sets how long to wait for peers to accept connections before starting
+    /// the process, [`DEFAULT_STARTUP_TIMEOUT`] by default.
+    ///
+    /// Peers still unreachable by then are connected on later sends, messages
+    /// to them are dropped until they listen.
+    pub fn startup_timeout(mut self, timeout: Duration) -> Self {
+        self.startup_timeout = timeout;
+        self
+    }
+
+    /// Sets the base seed of the process randomness, 0 by default.
+    ///
+    /// As in simulation, the process gets a seed derived from the base one, see
+    /// [`SimulationBuilder::seed`].
+    ///
+    /// [`SimulationBuilder::seed`]: crate::SimulationBuilder::seed
+    pub fn seed(mut self, seed: Seed) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Starts `process` and serves it for `duration` of wall-clock jiffies.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if incoming connections can not be accepted.
+    pub fn run(self, mut process: impl ProcessHandle, duration: Jiffies) -> io::Result<()> {
+        let (sender, receiver) = mpsc::channel();
+        let listener = self.listener.try_clone()?;
+        let max_frame = self.max_frame;
+        let known = Arc::new(self.state.borrow().peers.keys().copied().collect());
+        thread::spawn(move || accept(listener, sender, known, max_frame));
+
+        let proc_num = self.state.borrow().peers.len();
+        let max_id = *self
+            .state
+            .borrow()
+            .peers
+            .keys()
+            .last()
+            .expect("Peers are not empty");
+        configuration::setup_global_configuration(proc_num);
+        configuration::setup_local_configuration(self.id, self.seed);
+        filter::setup_filters(max_id);
+        self.state.borrow_mut().start = Instant::now();
+        self.state.borrow_mut().await_peers(self.startup_timeout);
+        transport::host(self.state.clone());
+
+        process.start();
+        self.serve(&mut process, &receiver, duration);
+
+        transport::unhost();
+        filter::drop_filters();
+        Ok(())
+    }
+
+    fn serve(&self, process: &mut impl ProcessHandle, receiver: &Receiver<Frame>, until: Jiffies) {
+        loop {
+            let now = self.advance_clock();
+            if now >= until {
+                return;
+            }
+
+            let looped = self.state.borrow_mut().loopback.pop_front();
+            if let Some(message) = looped {
+                self.deliver(process, self.id, MessagePtr(message));
+                continue;
+            }
+
+            let due = self.state.borrow_mut().pop_due_timer(now);
+            if let Some(id) = due {
+                process.on_timer(id);
+                named_timer::forget(id);
+                continue;
+            }
+
+            let deadline = self.state.borrow().next_timer().unwrap_or(until).min(until);
+            let jiffies = u32::try_from(deadline.0 - now.0).unwrap_or(u32::MAX);
+            let wait = self.state.borrow().jiffy.saturating_mul(jiffies);
+            match receiver.recv_timeout(wait) {
+                Ok((from, bytes)) => {
+                    let decoded = self.state.borrow().codec.decode(&bytes);
+                    let Some(message) = decoded else {
+                        warn!("Dropping malformed {} byte message of P{from}", bytes.len());
+                        continue;
+                    };
+                    self.advance_clock();
+                    self.deliver(process, from, MessagePtr(message));
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+        }
+    }
+
+    fn deliver(&self, process: &mut impl ProcessHandle, from: ProcessId, message: MessagePtr) {
+        if filter::admits(from, self.id, &message) {
+            process.on_message(from, message);
+        }
+    }
+
+    fn advance_clock(&self) -> Jiffies {
+        let now = self.state.borrow().now();
+        transport::advance_clock(now);
+        now
+    }
+}
+
+impl NodeState {
+    fn now(&self) -> Jiffies {
+        Jiffies((self.start.elapsed().as_nanos() / self.jiffy.as_nanos()) as usize)
+    }
+
+    fn pop_due_timer(&mut self, now: Jiffies) -> Option<TimerId> {
+        match self.timers.peek() {
+            Some(Reverse((at, _))) if *at <= now => self.timers.pop().map(|Reverse((_, id))| id),
+            _ => None,
+        }
+    }
+
+    fn next_timer(&self) -> Option<Jiffies> {
+        self.timers.peek().map(|Reverse((at, _))| *at)
+    }
+
+    fn connect(&mut self, to: ProcessId) -> io::Result<()> {
+        if self.connections.contains_key(&to) {
+            return Ok(());
+        }
+        let address = self
+            .peers
+            .get(&to)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "unknown peer"))?;
+        let stream = TcpStream::connect(address)?;
+        stream.set_nodelay(true)?;
+        self.connections.insert(to, stream);
+        Ok(())
+    }
+
+    // Peers are started independently, so the first sends would be lost
+    // without waiting for them to listen
+    fn await_peers(&mut self, timeout: Duration) {
+        let deadline = Instant::now() + timeout;
+        let others: Vec<ProcessId> = self
+            .peers
+            .keys()
+            .copied()
+            .filter(|id| *id != self.id)
+            .collect();
+        for to in others {
+            while let Err(error) = self.connect(to) {
+                if Instant::now() >= deadline {
+                    warn!("P{to} is not listening yet: {error}");
+                    break;
+                }
+                thread::sleep(CONNECT_RETRY);
+            }
+        }
+    }
+
+    fn transmit(&mut self, to: ProcessId, frame: &[u8]) -> io::Result<()> {
+        self.connect(to)?;
+        let result = self.connections.get_mut(&to).unwrap().write_all(frame);
+        if result.is_err() {
+            // Reconnect on the next send
+            self.connections.remove(&to);
+        }
+        result
+    }
+}
+
+impl Transport for NodeState {
+    fn rank(&self) -> ProcessId {
+        self.id
+    }
+
+    fn send_to(&mut self, to: ProcessId, message: SharedMessage) {
+        if to == self.id {
+            self.loopback.push_back(message);
+            return;
+        }
+        let payload = self.codec.encode(message.as_ref());
+        let mut frame = Vec::with_capacity(HEADER_SIZE + payload.len());
+        frame.extend_from_slice(&(self.id as u64).to_le_bytes());
+        frame.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        frame.extend_from_slice(&payload);
+        if let Err(error) = self.transmit(to, &frame) {
+            debug!("Dropping message to P{to}: {error}");
+        }
+    }
+
+    fn broadcast_within_pool(&mut self, pool: &str, message: SharedMessage) {
+        self.list_pool(pool)
+            .into_iter()
+            .for_each(|to| self.send_to(to, message.clone()));
+    }
+
+    fn schedule_timer_after(&mut self, after: Jiffies) -> TimerId {
+        let id = global_unique_id();
+        self.timers.push(Reverse((self.now() + after, id)));
+        id
+    }
+
+    fn list_pool(&self, pool: &str) -> Vec<ProcessId> {
+        self.pools.get(pool).cloned().unwrap_or_else(|| {
+            warn!("Unknown pool {pool}");
+            Vec::new()
+        })
+    }
+}
+
+fn accept(
+    listener: TcpListener,
+    sender: Sender<Frame>,
+    known: Arc<BTreeSet<ProcessId>>,
+    max_frame: usize,
+) {
+    for stream in listener.incoming() {
+        let Ok(stream) = stream else { continue };
+        let sender = sender.clone();
+        let known = known.clone();
+        thread::spawn(move || receive(stream, sender, &known, max_frame));
+    }
+}
+
+// Returning drops the stream, which closes the connection
+fn receive(
+    mut stream: TcpStream,
+    sender: Sender<Frame>,
+    known: &BTreeSet<ProcessId>,
+    max_frame: usize,
+) {
+    let mut header = [0; HEADER_SIZE];
+    while stream.read_exact(&mut header).is_ok() {
+        let from = u64::from_le_bytes(header[..8].try_into().unwrap()) as ProcessId;
+        let len = u32::from_le_bytes(header[8..].try_into().unwrap()) as usize;
+        if !known.contains(&from) {
+            warn!("Closing connection of unknown P{from}");
+            return;
+        }
+        if len > max_frame {
+            warn!("Closing connection of P{from}: {len} byte frame exceeds {max_frame} bytes");
+            return;
+        }
+        let mut payload = vec![0; len];
+        if stream.read_exact(&mut payload).is_err() || sender.send((from, payload)).is_err() {
+            return;
+        }
+    }
+}
@@ -0,0 +1,55 @@
+//! Verbose observation of a critical time window of a run.
+
+use std::{thread, time::Duration};
+
+use crate::{Jiffies, now};
+
+/// A window of simulated time in which the simulation logs every event.
+///
+/// Long runs are usually too noisy to be debugged with `RUST_LOG=debug`. A debug
+/// window narrows verbose output down to the critical region of a failure: every
+/// process start, message delivery, timer and dropped event within
+/// `[start, end)` is printed to stderr, regardless of the log level. With a
+/// non-zero `pace` the simulation also sleeps for that long of wall-clock time
+/// after every printed event, so the region can be watched live.
+///
+/// Configured with [`SimulationBuilder::debug_window`].
+///
+/// # Examples
+///
+/// ```rust
+/// use std::time::Duration;
+/// use dscale::{SimulationBuilder, DebugWindow, Jiffies};
+///
+/// let builder = SimulationBuilder::default()
+///     .debug_window(DebugWindow {
+///         start: Jiffies(4_000),
+///         end: Jiffies(4_100),
+///         pace: Duration::from_millis(200), // Slow enough to read along
+///     });
+/// ```
+///
+/// [`SimulationBuilder::debug_window`]: crate::SimulationBuilder::debug_window
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DebugWindow {
+    /// First jiffy of the window.
+    pub start: Jiffies,
+    /// First jiffy after the window.
+    pub end: Jiffies,
+    /// Wall-clock time to sleep after every event in the window.
+    pub pace: Duration,
+}
+
+impl DebugWindow {
+    // Prints the event if the clock is within the window
+    pub(crate) fn observe(&self, event: impl FnOnce() -> String) {
+        let now = now();
+        if now < self.start || now >= self.end {
+            return;
+        }
+        eprintln!("[window {now:?}] {}", event());
+        if !self.pace.is_zero() {
+            thread::sleep(self.pace);
+        }
+    }
+}
@@ -0,0 +1,662 @@
+//! Client workloads: which keys clients touch and when requests arrive.
+//!
+//! Which keys clients touch matters as much as how often they do: contention,
+//! conflict rates and load imbalance all follow from skew. This module provides
+//! the [`KeySampler`] shared by key-value clients, transaction generators and
+//! any other system drawing keys, so workloads are described once by a
+//! [`KeyDistribution`] instead of being re-implemented per system.
+//!
+//! The client tier itself is a [`ClientDriver`]: a process issuing requests of
+//! a [`ClientWorkload`] to a target pool at the times of an [`ArrivalProcess`].
+//! Arrivals and operations may come from a production [`OperationTrace`].
+
+use std::{fs, path::Path, rc::Rc, time::Duration};
+
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use rand_distr::{Distribution, Exp, Zipf};
+
+use crate::{
+    Jiffies, Message, MessagePtr, ProcessHandle, ProcessId, TimerId, choose_from_pool,
+    global::configuration, now, random::Seed, schedule_timer_after, send_to,
+};
+
+// Separates the key stream from generators seeded with the plain process seed
+const KEY_STREAM: Seed = 0x6b65_7973;
+
+// Separates the arrival stream likewise
+const ARRIVAL_STREAM: Seed = 0x6172_7276;
+
+/// How keys of a keyspace are chosen.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum KeyDistribution {
+    /// Every key is equally likely.
+    Uniform,
+
+    /// Key `k` is chosen with probability proportional to `1 / (k + 1)^theta`, so
+    /// the lowest keys are the hottest. `theta = 0.99` is the YCSB default,
+    /// `theta = 0` is uniform.
+    Zipfian { theta: f64 },
+
+    /// `hot_probability` of the operations go to the first `hot_fraction` of
+    /// the keys, the rest go to the other keys. Both are chosen uniformly.
+    Hotspot {
+        hot_fraction: f64,
+        hot_probability: f64,
+    },
+
+    /// Keys are scanned in order: 0, 1, ..., then from 0 again.
+    Sequential,
+}
+
+/// Draws keys in `0..keys` according to a [`KeyDistribution`].
+///
+/// Samplers created with [`KeySampler::new`] are seeded from the seed of the
+/// current process (see [`configuration::seed`]): every client draws its own
+/// sequence, and the same simulation seed reproduces all of them. The sequence
+/// is independent of other generators the process seeds with its seed.
+///
+/// # Examples
+///
+/// ```rust
+/// use dscale::{ProcessHandle, ProcessId, MessagePtr, TimerId, Jiffies, schedule_timer_after};
+/// use dscale::workload::{KeyDistribution, KeySampler};
+///
+/// #[derive(Default)]
+/// struct Client {
+///     keys: Option<KeySampler>,
+/// }
+///
+/// impl ProcessHandle for Client {
+///     fn start(&mut self) {
+///         self.keys = Some(KeySampler::new(1_000, KeyDistribution::Zipfian { theta: 0.99 }));
+///         schedule_timer_after(Jiffies(10));
+///     }
+///
+///     fn on_message(&mut self, from: ProcessId, message: MessagePtr) {}
+///
+///     fn on_timer(&mut self, id: TimerId) {
+///         let key = self.keys.as_mut().unwrap().sample();
+///         assert!(key < 1_000);
+///         schedule_timer_after(Jiffies(10));
+///     }
+/// }
+/// ```
+///
+/// Outside of a process, e.g. to preview a distribution:
+///
+/// ```rust
+/// use dscale::workload::{KeyDistribution, KeySampler};
+///
+/// let hotspot = KeyDistribution::Hotspot { hot_fraction: 0.1, hot_probability: 0.9 };
+/// let mut sampler = KeySampler::seeded(100, hotspot, 42);
+/// let hot = (0..10_000).filter(|_| sampler.sample() < 10).count();
+/// assert!((8_500..9_500).contains(&hot));
+///
+/// let mut scan = KeySampler::seeded(3, KeyDistribution::Sequential, 42);
+/// assert_eq!([scan.sample(), scan.sample(), scan.sample(), scan.sample()], [0, 1, 2, 0]);
+/// ```
+///
+/// # Panics
+///
+/// [`KeySampler::new`] and [`KeySampler::seeded`] panic if `keys` is zero, if
+/// `theta` is negative, or if a hotspot fraction or probability is outside of
+/// `[0, 1]`.
+///
+/// [`configuration::seed`]: crate::global::configuration::seed
+#[derive(Clone, Debug)]
+pub struct KeySampler {
+    keys: usize,
+    distribution: KeyDistribution,
+    rng: StdRng,
+    zipf: Option<Zipf<f64>>,
+    next: usize, // Sequential scan position
+}
+
+impl KeySampler {
+    /// Creates a sampler seeded with the seed of the current process.
+    ///
+    /// Should be called from process context, e.g. in [`ProcessHandle::start`].
+    ///
+    /// [`ProcessHandle::start`]: crate::ProcessHandle::start
+    pub fn new(keys: usize, distribution: KeyDistribution) -> Self {
+        Self::seeded(keys, distribution, configuration::seed() ^ KEY_STREAM)
+    }
+
+    /// Creates a sampler with an explicit seed.
+    pub fn seeded(keys: usize, distribution: KeyDistribution, seed: Seed) -> Self {
+        assert!(keys > 0, "Keyspace should not be empty");
+        let zipf = match distribution {
+            KeyDistribution::Zipfian { theta } => {
+                Some(Zipf::new(keys as f64, theta).expect("Zipfian theta should be non-negative"))
+            }
+            KeyDistribution::Hotspot {
+                hot_fraction,
+                hot_probability,
+            } => {
+                assert!(
+                    (0.0..=1.0).contains(&hot_fraction) && (0.0..=1.0).contains(&hot_probability),
+                    "Hotspot fraction and probability should be within [0, 1]"
+                );
+                None
+            }
+            KeyDistribution::Uniform | KeyDistribution::Sequential => None,
+        };
+        Self {
+            keys,
+            distribution,
+            rng: StdRng::seed_from_u64(seed),
+            zipf,
+            next: 0,
+        }
+    }
+
+    /// Draws the next key.
+    pub fn sample(&mut self) -> usize {
+        match self.distribution {
+            KeyDistribution::Uniform => self.rng.random_range(0..self.keys),
+            KeyDistribution::Zipfian { .. } => {
+                let rank = self
+                    .rng
+                    .sample(self.zipf.expect("Created with the sampler"))
+                    as usize;
+                rank.clamp(1, self.keys) - 1
+            }
+            KeyDistribution::Hotspot {
+                hot_fraction,
+                hot_probability,
+            } => {
+                let hot_keys = ((self.keys as f64 * hot_fraction).round() as usize).min(self.keys);
+                if hot_keys == 0 || hot_keys == self.keys {
+                    self.rng.random_range(0..self.keys)
+                } else if self.rng.random_bool(hot_probability) {
+                    self.rng.random_range(0..hot_keys)
+                } else {
+                    self.rng.random_range(hot_keys..self.keys)
+                }
+            }
+            KeyDistribution::Sequential => {
+                let key = self.next;
+                self.next = (self.next + 1) % self.keys;
+                key
+            }
+        }
+    }
+
+    /// Size of the keyspace.
+    pub fn keys(&self) -> usize {
+        self.keys
+    }
+
+    /// Distribution the keys are drawn from.
+    pub fn distribution(&self) -> KeyDistribution {
+        self.distribution
+    }
+}
+
+/// When requests of a client arrive.
+///
+/// Arrivals are open-loop: requests are issued at their arrival times whether
+/// or not earlier requests were answered, so an overloaded system sees its
+/// queues grow instead of clients politely slowing down.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ArrivalProcess {
+    /// A request every `interval` jiffies.
+    Constant(Jiffies),
+
+    /// Poisson arrivals: exponentially distributed gaps with the given mean.
+    /// Several requests may arrive within the same jiffy.
+    Poisson(Jiffies),
+
+    /// Requests at the given times since the start of the simulation, e.g.
+    /// taken from a production trace. Times should be ordered; the client stops
+    /// after the last one.
+    Trace(Vec<Jiffies>),
+}
+
+// Arrival times of one client, drawn lazily
+struct Arrivals {
+    process: ArrivalProcess,
+    rng: StdRng,
+    clock: f64, // Time of the last arrival, exact for Poisson gaps
+    position: usize,
+}
+
+impl Arrivals {
+    fn new(process: ArrivalProcess) -> Self {
+        match process {
+            ArrivalProcess::Constant(interval) | ArrivalProcess::Poisson(interval) => {
+                assert!(interval > Jiffies(0), "Arrival interval should be positive")
+            }
+            ArrivalProcess::Trace(_) => {}
+        }
+        Self {
+            process,
+            rng: StdRng::seed_from_u64(configuration::seed() ^ ARRIVAL_STREAM),
+            clock: now().0 as f64,
+            position: 0,
+        }
+    }
+
+    fn next(&mut self) -> Option<Jiffies> {
+        match &self.process {
+            ArrivalProcess::Constant(interval) => {
+                self.clock += interval.0 as f64;
+                Some(Jiffies(self.clock as usize))
+            }
+            ArrivalProcess::Poisson(mean) => {
+                let gap = Exp::new(1.0 / mean.0 as f64).expect("Mean is positive");
+                self.clock += gap.sample(&mut self.rng);
+                Some(Jiffies(self.clock.round() as usize))
+            }
+            ArrivalProcess::Trace(times) => {
+                let at = times.get(self.position).copied()?;
+                self.position += 1;
+                Some(at)
+            }
+        }
+    }
+}
+
+/// Requests of a client tier, see [`ClientDriver`].
+///
+/// Only the request itself is protocol specific: when requests arrive and
+/// where they go is configuration. Responses and timers not scheduled by the
+/// driver are handed to the workload.
+pub trait ClientWorkload: Default + 'static {
+    /// Request sent to the target pool.
+    type Request: Message + 'static;
+
+    /// Pool serving the requests.
+    fn target(&self) -> &'static str;
+
+    /// Arrival times of the requests of the current client.
+    fn arrivals(&self) -> ArrivalProcess;
+
+    /// Creates the request with the given sequence number, starting from 0.
+    fn request(&mut self, sequence: usize) -> Self::Request;
+
+    /// Member of the target pool receiving the request, a random one by default.
+    fn route(&mut self, _sequence: usize) -> ProcessId {
+        choose_from_pool(self.target())
+    }
+
+    /// Called once the client starts, before the first request.
+    fn start(&mut self) {}
+
+    /// Handles a response or any other message delivered to the client.
+    fn on_response(&mut self, _from: ProcessId, _message: MessagePtr) {}
+
+    /// Handles a timer scheduled by the workload itself.
+    fn on_timer(&mut self, _id: TimerId) {}
+}
+
+/// Client process issuing the requests of `W` open-loop.
+///
+/// Every client of the pool draws its own arrivals from its process seed, so
+/// Poisson clients are independent of each other and reproducible.
+///
+/// # Examples
+///
+/// ```rust
+/// use dscale::{ProcessHandle, ProcessId, MessagePtr, TimerId, Jiffies, SimulationBuilder};
+/// use dscale::{Distributions, LatencyDescription, send_to};
+/// use dscale::global::anykv;
+/// use dscale::workload::{ArrivalProcess, ClientDriver, ClientWorkload};
+///
+/// struct Put(usize);
+/// impl dscale::Message for Put {}
+///
+/// struct Ack;
+/// impl dscale::Message for Ack {}
+///
+/// #[derive(Default)]
+/// struct Writes;
+///
+/// impl ClientWorkload for Writes {
+///     type Request = Put;
+///
+///     fn target(&self) -> &'static str {
+///         "servers"
+///     }
+///
+///     fn arrivals(&self) -> ArrivalProcess {
+///         ArrivalProcess::Poisson(Jiffies(10)) // 0.1 requests per jiffy
+///     }
+///
+///     fn request(&mut self, sequence: usize) -> Put {
+///         Put(sequence)
+///     }
+///
+///     fn on_response(&mut self, from: ProcessId, message: MessagePtr) {
+///         anykv::modify::<usize>("acked", |acked| *acked += 1);
+///     }
+/// }
+///
+/// #[derive(Default)]
+/// struct Server;
+///
+/// impl ProcessHandle for Server {
+///     fn start(&mut self) {}
+///     fn on_message(&mut self, from: ProcessId, message: MessagePtr) {
+///         send_to(from, Ack);
+///     }
+///     fn on_timer(&mut self, id: TimerId) {}
+/// }
+///
+/// let mut simulation = SimulationBuilder::default()
+///     .add_pool::<Server>("servers", 3)
+///     .add_pool::<ClientDriver<Writes>>("clients", 10)
+///     .latency_topology(&[LatencyDescription::WithinPool(
+///         dscale::GLOBAL_POOL,
+///         Distributions::Uniform(Jiffies(1), Jiffies(5)),
+///     )])
+///     .time_budget(Jiffies(10_000))
+///     .build();
+/// anykv::set::<usize>("acked", 0);
+/// simulation.run();
+///
+/// // 10 clients, 1000 requests each on average
+/// let acked = anykv::get::<usize>("acked");
+/// assert!((9_500..10_500).contains(&acked));
+/// ```
+///
+/// # Panics
+///
+/// Starting the client panics if the interval of constant or Poisson arrivals
+/// is zero.
+pub struct ClientDriver<W> {
+    workload: W,
+    arrivals: Option<Arrivals>,
+    next: Option<Jiffies>, // Arrival of the next request
+    timer: Option<TimerId>,
+    sent: usize,
+}
+
+impl<W: ClientWorkload> ClientDriver<W> {
+    /// Number of requests issued so far.
+    pub fn sent(&self) -> usize {
+        self.sent
+    }
+
+    // Issues every request due by now, then waits for the next arrival
+    fn issue_due(&mut self) {
+        let arrivals = self.arrivals.as_mut().expect("Client should be started");
+        while self.next.is_some_and(|at| at <= now()) {
+            let to = self.workload.route(self.sent);
+            send_to(to, self.workload.request(self.sent));
+            self.sent += 1;
+            self.next = arrivals.next();
+        }
+        self.timer = self
+            .next
+            .map(|at| schedule_timer_after(Jiffies(at.0 - now().0)));
+    }
+}
+
+impl<W: ClientWorkload> Default for ClientDriver<W> {
+    fn default() -> Self {
+        Self {
+            workload: W::default(),
+            arrivals: None,
+            next: None,
+            timer: None,
+            sent: 0,
+        }
+    }
+}
+
+impl<W: ClientWorkload> ProcessHandle for ClientDriver<W> {
+    fn start(&mut self) {
+        self.workload.start();
+        let mut arrivals = Arrivals::new(self.workload.arrivals());
+        self.next = arrivals.next();
+        self.arrivals = Some(arrivals);
+        self.issue_due();
+    }
+
+    fn on_message(&mut self, from: ProcessId, message: MessagePtr) {
+        self.workload.on_response(from, message);
+    }
+
+    fn on_timer(&mut self, id: TimerId) {
+        if self.timer == Some(id) {
+            self.issue_due();
+        } else {
+            self.workload.on_timer(id);
+        }
+    }
+}
+
+/// Operation of an [`OperationTrace`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TraceOperation {
+    /// Arrival time relative to the first operation of the trace.
+    pub at: Jiffies,
+    /// Type of the operation as written in the trace, e.g. `get` or `put`.
+    pub kind: String,
+    /// Key the operation touches.
+    pub key: String,
+    /// Size of the operation (payload, value) in bytes.
+    pub size: usize,
+}
+
+/// Timestamped operations of a production workload, replayed by clients.
+///
+/// Timestamps are scaled into jiffies and shifted so the first operation
+/// arrives at time 0. Clients take their [`share`] of the trace, feed its
+/// [`arrivals`] to the [`ClientDriver`] and build the request with a given
+/// sequence number from the [`operation`] with the same index, so simulated
+/// arrivals follow the trace.
+///
+/// # File Format
+///
+/// One operation per line with space-separated timestamp, operation type, key
+/// and size in bytes. Lines starting with `#` are comments. Operations may be
+/// out of order (e.g. merged logs of several hosts), they are sorted by
+/// timestamp.
+///
+/// ```text
+/// # timestamp op key size
+/// 1700000000000 get user:42 0
+/// 1700000000350 put user:42 512
+/// ```
+///
+/// # Examples
+///
+/// ```rust
+/// use std::time::Duration;
+/// use dscale::{ProcessHandle, ProcessId, MessagePtr, TimerId, Jiffies, SimulationBuilder};
+/// use dscale::{Distributions, LatencyDescription, list_pool, rank};
+/// use dscale::global::anykv;
+/// use dscale::workload::{ArrivalProcess, ClientDriver, ClientWorkload, OperationTrace};
+///
+/// struct Operation {
+///     kind: String,
+///     size: usize,
+/// }
+/// impl dscale::Message for Operation {
+///     fn virtual_size(&self) -> usize { self.size }
+/// }
+///
+/// #[derive(Default)]
+/// struct Replay {
+///     trace: OperationTrace,
+/// }
+///
+/// impl ClientWorkload for Replay {
+///     type Request = Operation;
+///
+///     fn target(&self) -> &'static str {
+///         "servers"
+///     }
+///
+///     fn start(&mut self) {
+///         // Every client replays a round-robin share of the trace
+///         let clients = list_pool("clients");
+///         let client = clients.iter().position(|id| *id == rank()).unwrap();
+///         self.trace = anykv::get::<OperationTrace>("trace").share(client, clients.len());
+///     }
+///
+///     fn arrivals(&self) -> ArrivalProcess {
+///         self.trace.arrivals()
+///     }
+///
+///     fn request(&mut self, sequence: usize) -> Operation {
+///         let operation = self.trace.operation(sequence);
+///         Operation { kind: operation.kind.clone(), size: operation.size }
+///     }
+/// }
+///
+/// #[derive(Default)]
+/// struct Server;
+///
+/// impl ProcessHandle for Server {
+///     fn start(&mut self) {}
+///     fn on_message(&mut self, from: ProcessId, message: MessagePtr) {
+///         anykv::modify::<Vec<String>>("served", |served| {
+///             served.push(message.as_type::<Operation>().kind.clone())
+///         });
+///     }
+///     fn on_timer(&mut self, id: TimerId) {}
+/// }
+///
+/// // Timestamps in milliseconds, one jiffy is 100 microseconds
+/// let trace = OperationTrace::parse(
+///     "# timestamp op key size\n\
+///      1700000000000 get user:42 0\n\
+///      1700000000005 put user:42 512\n\
+///      1700000000002 get user:7 0\n",
+///     Duration::from_millis(1),
+///     Duration::from_micros(100),
+/// );
+/// assert_eq!(trace.len(), 3);
+/// assert_eq!(trace.operation(1).at, Jiffies(20));
+///
+/// let mut simulation = SimulationBuilder::default()
+///     .add_pool::<Server>("servers", 1)
+///     .add_pool::<ClientDriver<Replay>>("clients", 2)
+///     .latency_topology(&[LatencyDescription::WithinPool(
+///         dscale::GLOBAL_POOL,
+///         Distributions::Uniform(Jiffies(1), Jiffies(5)),
+///     )])
+///     .time_budget(Jiffies(1_000))
+///     .build();
+/// anykv::set("trace", trace);
+/// anykv::set::<Vec<String>>("served", Vec::new());
+/// simulation.step_until(Jiffies(1_000)); // The trace ends, run() would report a deadlock
+/// assert_eq!(anykv::get::<Vec<String>>("served"), ["get", "get", "put"]);
+/// ```
+///
+/// # Panics
+///
+/// Parsing panics if a line is malformed or the jiffy duration is zero,
+/// [`load`] also panics if the file cannot be read.
+///
+/// [`share`]: OperationTrace::share
+/// [`arrivals`]: OperationTrace::arrivals
+/// [`operation`]: OperationTrace::operation
+/// [`load`]: OperationTrace::load
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct OperationTrace {
+    operations: Rc<[TraceOperation]>,
+}
+
+impl OperationTrace {
+    /// Parses a trace whose timestamps count `unit`s, scaling them into jiffies of `jiffy` duration.
+    ///
+    /// The jiffy duration should match [`SimulationBuilder::jiffy_duration`] if
+    /// the simulation sets it.
+    ///
+    /// [`SimulationBuilder::jiffy_duration`]: crate::SimulationBuilder::jiffy_duration
+    pub fn parse(content: &str, unit: Duration, jiffy: Duration) -> Self {
+        assert!(!jiffy.is_zero(), "Jiffy duration should be positive");
+        let mut operations: Vec<(u64, String, String, usize)> = content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                let fields: Vec<&str> = line.split_whitespace().collect();
+                assert_eq!(fields.len(), 4, "Malformed trace line: {line}");
+                (
+                    fields[0].parse().expect("Malformed trace timestamp"),
+                    fields[1].to_string(),
+                    fields[2].to_string(),
+                    fields[3].parse().expect("Malformed trace operation size"),
+                )
+            })
+            .collect();
+        operations.sort_by_key(|(timestamp, ..)| *timestamp); // Stable: ties keep file order
+
+        let first = operations.first().map_or(0, |(timestamp, ..)| *timestamp);
+        let operations = operations
+            .into_iter()
+            .map(|(timestamp, kind, key, size)| {
+                let elapsed = (timestamp - first) as u128 * unit.as_nanos();
+                TraceOperation {
+                    at: Jiffies((elapsed / jiffy.as_nanos()) as usize),
+                    kind,
+                    key,
+                    size,
+                }
+            })
+            .collect();
+        Self { operations }
+    }
+
+    /// Reads a trace file, see [`OperationTrace::parse`].
+    pub fn load(path: impl AsRef<Path>, unit: Duration, jiffy: Duration) -> Self {
+        let content = fs::read_to_string(path).expect("Unable to read operation trace");
+        Self::parse(&content, unit, jiffy)
+    }
+
+    /// Every `clients`-th operation starting from the `client`-th one (counting from 0).
+    pub fn share(&self, client: usize, clients: usize) -> Self {
+        assert!(
+            client < clients,
+            "Client index should be below the number of clients"
+        );
+        Self {
+            operations: self
+                .operations
+                .iter()
+                .skip(client)
+                .step_by(clients)
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// Arrival times of the operations, for [`ClientWorkload::arrivals`].
+    pub fn arrivals(&self) -> ArrivalProcess {
+        ArrivalProcess::Trace(
+            self.operations
+                .iter()
+                .map(|operation| operation.at)
+                .collect(),
+        )
+    }
+
+    /// Operation with the given index, i.e. the sequence number of its request.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the index is out of the trace.
+    pub fn operation(&self, index: usize) -> &TraceOperation {
+        &self.operations[index]
+    }
+
+    /// All operations ordered by arrival time.
+    pub fn operations(&self) -> &[TraceOperation] {
+        &self.operations
+    }
+
+    /// Number of operations.
+    pub fn len(&self) -> usize {
+        self.operations.len()
+    }
+
+    /// Returns `true` if the trace has no operations.
+    pub fn is_empty(&self) -> bool {
+        self.operations.is_empty()
+    }
+}
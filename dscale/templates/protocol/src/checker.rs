@@ -0,0 +1,40 @@
+// Invariants checked while the run goes and statistics reported after it.
+// Processes report to the checker through anykv, so it sees all of them.
+
+use std::collections::BTreeSet;
+
+use dscale::{Jiffies, ProcessId, global::anykv, rank, sim_assert};
+
+pub const COMMITS: &str = "{{name}}_commits";
+
+#[derive(Clone, Default)]
+pub struct Commits {
+    pub committed: BTreeSet<(ProcessId, usize)>, // Process, round
+    pub total_latency: Jiffies,
+}
+
+impl Commits {
+    pub fn avg_latency(&self) -> f64 {
+        self.total_latency.0 as f64 / self.committed.len().max(1) as f64
+    }
+
+    // Liveness: checked once the run is over
+    pub fn verify(&self, processes: &[ProcessId]) {
+        processes.iter().for_each(|id| {
+            assert!(
+                self.committed.iter().any(|(process, _)| process == id),
+                "P{id} committed nothing"
+            )
+        });
+    }
+}
+
+pub(crate) fn commit(round: usize, latency: Jiffies) {
+    let mut fresh = false;
+    anykv::modify::<Commits>(COMMITS, |commits| {
+        fresh = commits.committed.insert((rank(), round));
+        commits.total_latency += latency;
+    });
+    // Safety: checked at every commit
+    sim_assert!(fresh, "Round {round} committed twice");
+}
@@ -0,0 +1,5 @@
+pub mod checker;
+pub mod messages;
+pub mod process;
+
+pub use process::{{Name}};
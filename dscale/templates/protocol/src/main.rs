@@ -0,0 +1,31 @@
+use dscale::{global::anykv, *};
+use {{name}}::{
+    {{Name}},
+    checker::{COMMITS, Commits},
+    process::POOL,
+};
+
+fn main() {
+    anykv::set::<Commits>(COMMITS, Commits::default());
+
+    let mut sim = SimulationBuilder::default()
+        .add_pool::<{{Name}}>(POOL, 4)
+        .latency_topology(&[LatencyDescription::WithinPool(
+            POOL,
+            Distributions::Uniform(Jiffies(10), Jiffies(50)),
+        )])
+        .time_budget(Jiffies(10_000))
+        .seed(42)
+        .build();
+
+    sim.run();
+
+    let commits = anykv::get::<Commits>(COMMITS);
+    println!(
+        "{}: commits: {}, average latency: {:.1}",
+        POOL,
+        commits.committed.len(),
+        commits.avg_latency()
+    );
+    commits.verify(&list_pool(POOL));
+}
@@ -0,0 +1,12 @@
+use dscale::Message;
+
+pub enum {{Name}}Message {
+    Propose(usize), // round
+    Ack(usize),     // round
+}
+
+impl Message for {{Name}}Message {
+    fn virtual_size(&self) -> usize {
+        size_of::<usize>()
+    }
+}
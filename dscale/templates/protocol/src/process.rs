@@ -0,0 +1,62 @@
+// Every process proposes a new round each PERIOD and commits it once a
+// majority of the pool acknowledged it. Replace with the rules of the protocol.
+
+use std::collections::HashMap;
+
+use dscale::{helpers::QuorumTracker, *};
+
+use crate::{checker, messages::{{Name}}Message};
+
+pub const POOL: &str = "{{Name}}";
+const PERIOD: Jiffies = Jiffies(100);
+
+#[derive(Default)]
+pub struct {{Name}} {
+    round: usize,
+    proposals: HashMap<usize, (Jiffies, QuorumTracker)>, // Uncommitted rounds: proposal time, acks
+}
+
+impl ProcessHandle for {{Name}} {
+    fn start(&mut self) {
+        schedule_timer_after(PERIOD);
+    }
+
+    fn on_message(&mut self, from: ProcessId, message: MessagePtr) {
+        let Some(message) = message.try_as::<{{Name}}Message>() else {
+            protocol_error!("Unexpected message from P{from}");
+            return;
+        };
+        match *message {
+            {{Name}}Message::Propose(round) => send_to(from, {{Name}}Message::Ack(round)),
+            {{Name}}Message::Ack(round) => self.on_ack(from, round),
+        }
+    }
+
+    fn on_timer(&mut self, _id: TimerId) {
+        self.round += 1;
+        let members = list_pool(POOL);
+        let majority = members.len() / 2 + 1;
+        let acks = QuorumTracker::new(format!("round {}", self.round), members, majority);
+        self.proposals.insert(self.round, (now(), acks));
+        broadcast_within_pool(POOL, {{Name}}Message::Propose(self.round));
+        schedule_timer_after(PERIOD);
+    }
+}
+
+impl {{Name}} {
+    fn on_ack(&mut self, from: ProcessId, round: usize) {
+        let Some((proposed, acks)) = self.proposals.get_mut(&round) else {
+            // Acks after the majority are expected, acks of future rounds are not
+            if round > self.round {
+                protocol_error!("P{from} acknowledged round {round} which is not proposed yet");
+            }
+            return;
+        };
+        if acks.vote(from) {
+            let latency = now() - *proposed;
+            self.proposals.remove(&round);
+            artifacts::metric("commit_latency", latency.0 as f64);
+            checker::commit(round, latency);
+        }
+    }
+}
@@ -0,0 +1,8 @@
+#![no_main]
+
+use kv::abd_store::fuzzing::fuzz;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    fuzz(data);
+});
@@ -0,0 +1,8 @@
+#![no_main]
+
+use dag_based::{bullshark::Bullshark, fuzzing::fuzz};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    fuzz::<Bullshark>(data);
+});
@@ -0,0 +1,8 @@
+#![no_main]
+
+use dag_based::{fuzzing::fuzz, rider::DAGRider};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    fuzz::<DAGRider>(data);
+});
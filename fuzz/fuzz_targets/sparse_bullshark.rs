@@ -0,0 +1,8 @@
+#![no_main]
+
+use dag_based::{fuzzing::fuzz, sparse_bullshark::SparseBullshark};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    fuzz::<SparseBullshark>(data);
+});
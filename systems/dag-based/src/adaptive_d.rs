@@ -0,0 +1,133 @@
+// Feedback controller of the SparseBullshark sample size D, enabled with
+// "adaptive_D" (anykv). A round ended by its timeout means anchors miss votes
+// of sparse vertices, so D doubles. Rounds completing at the pace of the fastest
+// ones seen mean D is more than enough, and after `patience` such rounds in a
+// row D shrinks by one, saving edges: bandwidth and certificate checks. Every
+// change is recorded under "D_trajectory" (anykv) and as the "D" metric of the
+// run artifacts.
+
+use dscale::{artifacts, global::anykv, *};
+
+pub const D_TRAJECTORY: &str = "D_trajectory";
+
+const PACE_SMOOTHING: f64 = 0.3; // Weight of the last round in the pace
+
+#[derive(Clone, Copy, Debug)]
+pub struct AdaptiveD {
+    pub min: usize,
+    pub max: usize,      // Capped by the number of validators
+    pub patience: usize, // Steady rounds in a row before D shrinks
+    pub tolerance: f64,  // Rounds up to (1 + tolerance) x the fastest pace are steady
+}
+
+impl Default for AdaptiveD {
+    fn default() -> Self {
+        Self {
+            min: 1,
+            max: usize::MAX,
+            patience: 5,
+            tolerance: 0.2,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DChange {
+    pub time: Jiffies,
+    pub process: ProcessId,
+    pub round: usize, // First round using the value
+    pub D: usize,
+}
+
+pub struct DController {
+    config: AdaptiveD,
+    D: usize,
+    round_start: Option<Jiffies>,
+    pace: Option<f64>,    // Smoothed round duration
+    fastest: Option<f64>, // Fastest pace seen
+    steady: usize,        // Steady rounds in a row
+}
+
+impl DController {
+    // None unless "adaptive_D" is set: D stays static
+    pub fn configured(initial: usize, proc_num: usize) -> Option<Self> {
+        anykv::try_get::<AdaptiveD>("adaptive_D").map(|config| Self::new(config, initial, proc_num))
+    }
+
+    pub fn new(config: AdaptiveD, initial: usize, proc_num: usize) -> Self {
+        let config = AdaptiveD {
+            max: config.max.min(proc_num).max(config.min),
+            ..config
+        };
+        let controller = Self {
+            config,
+            D: initial.clamp(config.min, config.max),
+            round_start: None,
+            pace: None,
+            fastest: None,
+            steady: 0,
+        };
+        controller.report(0);
+        controller
+    }
+
+    pub fn D(&self) -> usize {
+        self.D
+    }
+
+    // Largest D any validator may use, bounds the edges of valid vertices
+    pub fn max(&self) -> usize {
+        self.config.max
+    }
+
+    // Feeds the round just entered, returns D for its vertex
+    pub fn on_round(&mut self, round: usize, timed_out: bool) -> usize {
+        let Some(start) = self.round_start.replace(now()) else {
+            return self.D; // The first round starts with bootstrap, nothing to measure
+        };
+        let duration = (now() - start).0 as f64;
+        let pace = self.pace.map_or(duration, |pace| {
+            PACE_SMOOTHING * duration + (1.0 - PACE_SMOOTHING) * pace
+        });
+        self.pace = Some(pace);
+        let fastest = self.fastest.map_or(pace, |fastest| fastest.min(pace));
+        self.fastest = Some(fastest);
+
+        let next = if timed_out {
+            self.steady = 0;
+            (self.D * 2).min(self.config.max)
+        } else if pace <= fastest * (1.0 + self.config.tolerance) {
+            self.steady += 1;
+            if self.steady < self.config.patience {
+                self.D
+            } else {
+                self.steady = 0;
+                self.D.saturating_sub(1).max(self.config.min)
+            }
+        } else {
+            self.steady = 0;
+            self.D
+        };
+
+        if next != self.D {
+            self.D = next;
+            self.report(round);
+        }
+        self.D
+    }
+
+    fn report(&self, round: usize) {
+        artifacts::metric("D", self.D as f64);
+        if anykv::try_get::<Vec<DChange>>(D_TRAJECTORY).is_none() {
+            anykv::set::<Vec<DChange>>(D_TRAJECTORY, Vec::new());
+        }
+        anykv::modify::<Vec<DChange>>(D_TRAJECTORY, |trajectory| {
+            trajectory.push(DChange {
+                time: now(),
+                process: rank(),
+                round,
+                D: self.D,
+            })
+        });
+    }
+}
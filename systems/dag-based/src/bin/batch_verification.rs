@@ -0,0 +1,52 @@
+use dag_based::{
+    bullshark::Bullshark,
+    validation::{BatchVerification, CryptoCost},
+};
+use dscale::{Distributions, Jiffies, LatencyDescription, SimulationBuilder, global::anykv};
+
+// Bullshark with expensive certificates verified one by one and in batches
+fn main() {
+    let individual = run("individually", None);
+    let batched = run(
+        "in batches",
+        Some(BatchVerification {
+            max_batch: 64,
+            marginal: 0.1,
+        }),
+    );
+
+    assert!(
+        batched < individual,
+        "Batch verification should reduce ordering latency"
+    );
+}
+
+fn run(name: &str, batch_verification: Option<BatchVerification>) -> f64 {
+    anykv::set::<(f64, usize)>("avg_latency", (0.0, 0));
+    anykv::set::<CryptoCost>(
+        "crypto_cost",
+        CryptoCost {
+            signature_verification: Jiffies(1),
+            certificate_verification: Jiffies(3),
+            batch_verification,
+            ..CryptoCost::default()
+        },
+    );
+
+    let mut sim = SimulationBuilder::default()
+        .add_pool::<Bullshark>("Validators", 10)
+        .latency_topology(&[LatencyDescription::WithinPool(
+            "Validators",
+            Distributions::Uniform(Jiffies(5), Jiffies(10)),
+        )])
+        .time_budget(Jiffies(20_000))
+        .seed(42)
+        .build();
+    sim.run();
+
+    let (latency, ordered) = anykv::get::<(f64, usize)>("avg_latency");
+    println!(
+        "Certificates verified {name}: ordered vertices: {ordered}, avg latency: {latency:.2}"
+    );
+    latency
+}
@@ -0,0 +1,55 @@
+use dag_based::bullshark::Bullshark;
+use dscale::{
+    Distributions, Jiffies, LatencyDescription, Scenario, SimulationBuilder, global::anykv,
+    helpers::Leadership, scenario,
+};
+
+const VALIDATORS: usize = 10;
+const CRASH: Jiffies = Jiffies(2_000);
+
+// Partially synchronous Bullshark and full Bullshark with the asynchronous fallback.
+// The adversary pins steady leaders to one validator and crashes it: every steady leader
+// times out, only fallback leaders elected by the common coin keep ordering.
+fn main() {
+    run("partially synchronous", false, false);
+    run("with fallback", true, false);
+    let steady = run("partially synchronous, leader crashed", false, true);
+    let fallback = run("with fallback, leader crashed", true, true);
+
+    assert!(
+        fallback > 10 * steady.max(1),
+        "Fallback should keep ordering when steady leaders time out"
+    );
+}
+
+// Vertices ordered after the crash time
+fn run(name: &str, fallback: bool, crash_leader: bool) -> usize {
+    anykv::set::<(f64, usize)>("avg_latency", (0.0, 0));
+    anykv::set::<bool>("bullshark_fallback", fallback);
+
+    let mut builder = SimulationBuilder::default()
+        .add_pool::<Bullshark>("Validators", VALIDATORS)
+        .latency_topology(&[LatencyDescription::WithinPool(
+            "Validators",
+            Distributions::Uniform(Jiffies(5), Jiffies(10)),
+        )])
+        .leader_schedule(&[(Jiffies(0), Leadership::Pinned(1))])
+        .time_budget(Jiffies(300_000))
+        .seed(42);
+
+    if crash_leader {
+        builder = builder.scenario(Scenario::new().at(CRASH, scenario::crash(1)));
+    }
+
+    let mut sim = builder.build();
+    sim.step_until(CRASH);
+    let (_, before) = anykv::get::<(f64, usize)>("avg_latency");
+    sim.run();
+
+    let (latency, ordered) = anykv::get::<(f64, usize)>("avg_latency");
+    println!(
+        "Bullshark {name}: ordered vertices: {ordered} ({} after the crash time), avg latency: {latency:.2}",
+        ordered - before
+    );
+    ordered - before
+}
@@ -0,0 +1,38 @@
+use dag_based::{
+    bullshark::Bullshark,
+    comparison::{
+        ComparisonSetup, Contender, compare, print_fairness, print_significance, print_table,
+        write_csv,
+    },
+    sparse_bullshark::SparseBullshark,
+};
+use dscale::{BandwidthDescription, Distributions, Scenario, global::anykv, time::Jiffies};
+
+fn main() {
+    let setup = ComparisonSetup {
+        validators: 50,
+        latency: Distributions::Normal(Jiffies(50), Jiffies(10)),
+        bandwidth: BandwidthDescription::Bounded(100 * 1024 * 1024 / (8 * 1000)), // 100 Mb/sec NICs
+        time_budget: Jiffies(20_000),
+        txn_rate: 10_000.0,
+        seeds: vec![4567898765, 33333, 982039],
+        faults: Scenario::new(),
+    };
+
+    // HotStuff is not implemented in this crate yet
+    let mut contenders = vec![Contender::new::<Bullshark>("Bullshark", || {})];
+    for d in [5, 10, 20] {
+        contenders.push(Contender::new::<SparseBullshark>(
+            &format!("SparseBullshark(D={d})"),
+            move || anykv::set::<usize>("D", d),
+        ));
+    }
+
+    let results = compare(&setup, &contenders);
+    print_table(&results);
+    println!();
+    print_significance(&results);
+    println!();
+    print_fairness(&results);
+    write_csv(&results, "comparison.csv");
+}
@@ -0,0 +1,59 @@
+use dag_based::{bullshark::Bullshark, workload::TxnStats};
+use dscale::{Distributions, Jiffies, LatencyDescription, SimulationBuilder, global::anykv};
+
+const VALIDATORS: usize = 10;
+const TXN_RATE: f64 = 5_000.0;
+const LAG_LIMIT: Jiffies = Jiffies(200);
+
+// Execution as the bottleneck: validators execute ordered transactions slower than clients
+// submit them. Without backpressure ordering races ahead and transactions queue up behind
+// execution, with it validators stop packing payload and transactions queue up in mempools
+// instead. Either way end-to-end latency is set by execution, not by consensus. Throttled
+// validators pack their whole mempool once they resume, so execution also idles at times and
+// commits less than it could.
+fn main() {
+    let fast = run(2.0 * TXN_RATE, None);
+    let unbounded = run(0.8 * TXN_RATE, None);
+    let bounded = run(0.8 * TXN_RATE, Some(LAG_LIMIT));
+
+    assert_eq!(fast.throttled + unbounded.throttled, 0);
+    assert!(bounded.throttled > 0, "Lagging execution should throttle");
+    assert!(
+        unbounded.avg_execution_latency() - unbounded.avg_latency()
+            > bounded.avg_execution_latency() - bounded.avg_latency(),
+        "Backpressure should bound the time ordered transactions wait for execution"
+    );
+}
+
+fn run(exec_rate: f64, lag_limit: Option<Jiffies>) -> TxnStats {
+    anykv::set::<f64>("txn_rate", TXN_RATE);
+    anykv::set::<f64>("exec_rate", exec_rate);
+    if let Some(limit) = lag_limit {
+        anykv::set::<Jiffies>("exec_lag_limit", limit);
+    }
+    anykv::set::<TxnStats>("txn_stats", TxnStats::default());
+    anykv::set::<(f64, usize)>("avg_latency", (0.0, 0));
+
+    let mut sim = SimulationBuilder::default()
+        .add_pool::<Bullshark>("Validators", VALIDATORS)
+        .latency_topology(&[LatencyDescription::WithinPool(
+            "Validators",
+            Distributions::Normal(Jiffies(50), Jiffies(10)),
+        )])
+        .time_budget(Jiffies(10_000))
+        .seed(42)
+        .build();
+
+    sim.run();
+
+    let stats = anykv::get::<TxnStats>("txn_stats");
+    println!(
+        "exec rate: {exec_rate:>6.0} txn/s, lag limit: {:>4}: committed: {}, ordering latency: {:.1}, end-to-end: {:.1}, throttled vertices: {}",
+        lag_limit.map_or("none".to_string(), |limit| limit.0.to_string()),
+        stats.committed,
+        stats.avg_latency(),
+        stats.avg_execution_latency(),
+        stats.throttled
+    );
+    stats
+}
@@ -0,0 +1,55 @@
+use dag_based::{
+    bullshark::Bullshark,
+    failover::{self, Failover, FailoverExperiment, FailoverLatency},
+    rider::DAGRider,
+    validation::CryptoCost,
+};
+use dscale::{Distributions, Jiffies, global::anykv};
+
+// Crashes the upcoming leader f times per run: time until the next anchor commits
+fn main() {
+    let experiment = FailoverExperiment {
+        validators: 10,
+        latency: Distributions::Uniform(Jiffies(5), Jiffies(10)),
+        warmup: Jiffies(5_000),
+        gap: (Jiffies(500), Jiffies(3_000)),
+        crashes: 3,
+        patience: Jiffies(60_000),
+        seeds: (1..=20).collect(),
+    };
+
+    let bullshark = failover::run::<Bullshark>(&experiment, configure);
+    let rider = failover::run::<DAGRider>(&experiment, configure);
+    report("Bullshark", &bullshark);
+    report("DAG-Rider", &rider);
+
+    assert!(
+        bullshark.iter().all(|failover| failover.latency.is_some()),
+        "Bullshark should recover from every leader crash"
+    );
+}
+
+fn configure() {
+    anykv::set::<CryptoCost>(
+        "crypto_cost",
+        CryptoCost {
+            signature_verification: Jiffies(1),
+            certificate_verification: Jiffies(1),
+            ..CryptoCost::default()
+        },
+    );
+}
+
+fn report(name: &str, failovers: &[Failover]) {
+    let latency = FailoverLatency::of(failovers);
+    println!(
+        "{name}: {} leader crashes, failover latency mean {:.1} ± {:.1}, p50 {:.0}, p99 {:.0}, max {:.0}, stalled {}",
+        failovers.len(),
+        latency.summary.mean,
+        latency.summary.ci,
+        latency.p50,
+        latency.p99,
+        latency.max,
+        latency.stalled
+    );
+}
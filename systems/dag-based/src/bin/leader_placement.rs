@@ -0,0 +1,58 @@
+use dag_based::bullshark::Bullshark;
+use dscale::{
+    Distributions, Jiffies, LatencyDescription, SimulationBuilder, global::anykv,
+    helpers::Leadership,
+};
+
+// 8 validators in "eu" and 4 in a far "asia" region.
+// Compares ordering latency with the default round robin leaders and leaders pinned to either region.
+fn main() {
+    let rotating = run("round robin", None);
+    let near = run("pinned to eu", Some(Leadership::Pinned(1)));
+    let far = run(
+        "rotating in asia",
+        Some(Leadership::RotatingInRegion("asia")),
+    );
+
+    assert!(
+        near < rotating,
+        "Near leaders should order faster than round robin"
+    );
+    assert!(
+        rotating < far,
+        "Far leaders should order slower than round robin"
+    );
+}
+
+fn run(name: &str, leadership: Option<Leadership>) -> f64 {
+    anykv::set::<(f64, usize)>("avg_latency", (0.0, 0));
+
+    let mut builder = SimulationBuilder::default()
+        .add_pool_in_region::<Bullshark>("Validators", "eu", 8)
+        .add_pool_in_region::<Bullshark>("Validators", "asia", 4)
+        .latency_topology(&[
+            LatencyDescription::WithinRegion("eu", Distributions::Uniform(Jiffies(5), Jiffies(10))),
+            LatencyDescription::WithinRegion(
+                "asia",
+                Distributions::Uniform(Jiffies(5), Jiffies(10)),
+            ),
+            LatencyDescription::BetweenRegions(
+                "eu",
+                "asia",
+                Distributions::Uniform(Jiffies(80), Jiffies(100)),
+            ),
+        ])
+        .time_budget(Jiffies(20_000))
+        .seed(42);
+
+    if let Some(leadership) = leadership {
+        builder = builder.leader_schedule(&[(Jiffies(0), leadership)]);
+    }
+
+    let mut sim = builder.build();
+    sim.run();
+
+    let (latency, ordered) = anykv::get::<(f64, usize)>("avg_latency");
+    println!("Leaders {name}: ordered vertices: {ordered}, avg latency: {latency:.2}");
+    latency
+}
@@ -0,0 +1,86 @@
+use std::{fs::File, io::Write};
+
+use dag_based::{
+    bullshark::Bullshark,
+    rider::DAGRider,
+    sparse_bullshark::SparseBullshark,
+    sweep::{LoadPoint, saturation_point},
+    workload::TxnStats,
+};
+use dscale::{
+    BandwidthDescription, Distributions, LatencyDescription, ProcessHandle, SimulationBuilder,
+    global::anykv, time::Jiffies,
+};
+use rayon::prelude::*;
+
+const VALIDATORS: usize = 50;
+const TIME_BUDGET: Jiffies = Jiffies(20_000);
+
+fn run<P: ProcessHandle + Default + 'static>(txn_rate: f64) -> LoadPoint {
+    anykv::set::<f64>("txn_rate", txn_rate);
+    anykv::set::<TxnStats>("txn_stats", TxnStats::default());
+    anykv::set::<(f64, usize)>("avg_latency", (0.0, 0));
+    anykv::set::<(f64, usize)>("avg_virtual_size", (0.0, 0));
+    anykv::set::<usize>("D", VALIDATORS / 4); // SparseBullshark sample size
+
+    let mut sim = SimulationBuilder::default()
+        .add_pool::<P>("Validators", VALIDATORS)
+        .latency_topology(&[LatencyDescription::WithinPool(
+            "Validators",
+            Distributions::Normal(Jiffies(50), Jiffies(10)),
+        )])
+        .time_budget(TIME_BUDGET)
+        .nic_bandwidth(BandwidthDescription::Bounded(
+            100 * 1024 * 1024 / (8 * 1000), // 100 Mb/sec NICs
+        ))
+        .seed(123)
+        .build();
+
+    sim.run();
+
+    let stats = anykv::get::<TxnStats>("txn_stats");
+    LoadPoint {
+        offered: txn_rate,
+        throughput: stats.committed as f64 / (TIME_BUDGET.0 as f64 / 1000.0),
+        latency: stats.avg_latency(),
+    }
+}
+
+// Usage: load_sweep [bullshark|sparse_bullshark|rider]
+fn main() {
+    let protocol = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "bullshark".to_string());
+    let runner = match protocol.as_str() {
+        "bullshark" => run::<Bullshark>,
+        "sparse_bullshark" => run::<SparseBullshark>,
+        "rider" => run::<DAGRider>,
+        other => panic!("Unknown protocol: {other}"),
+    };
+
+    // Txns per second offered to the whole system
+    let rates: Vec<f64> = (1..=12).map(|step| (step * 2500) as f64).collect();
+    let points: Vec<LoadPoint> = rates.into_par_iter().map(runner).collect();
+
+    let mut file = File::create(format!("load_sweep_{}.csv", protocol)).unwrap();
+    points.iter().for_each(|point| {
+        println!(
+            "offered: {:>8.0} txn/s, throughput: {:>8.0} txn/s, latency: {:>8.1}",
+            point.offered, point.throughput, point.latency
+        );
+        writeln!(
+            file,
+            "{} {} {}",
+            point.offered, point.throughput, point.latency
+        )
+        .unwrap();
+    });
+
+    match saturation_point(&points) {
+        Some(index) => println!(
+            "{protocol} saturates at {} txn/s offered load",
+            points[index].offered
+        ),
+        None => println!("{protocol} did not saturate, increase offered load"),
+    }
+}
@@ -0,0 +1,103 @@
+use std::time::Duration;
+
+use dag_based::{
+    bullshark::Bullshark, rider::DAGRider, shoal::Shoal, sparse_bullshark::SparseBullshark,
+    workload::TxnStats,
+};
+use dscale::{
+    BandwidthDescription, Distributions, LatencyDescription, ProcessHandle, SimulationBuilder,
+    global::anykv, helpers::BatchScheduler, time::Jiffies,
+};
+
+const TIME_BUDGET: Jiffies = Jiffies(20_000);
+const TXN_RATE: f64 = 10_000.0;
+
+#[derive(Debug)]
+#[allow(dead_code)] // Fields are reported through Debug
+struct Performance {
+    ordered_vertices: usize,
+    vertex_latency: f64,
+    throughput: f64,
+    txn_latency: f64,
+}
+
+fn run<P: ProcessHandle + Default + 'static>(validators: usize) -> impl FnOnce() -> Performance {
+    move || {
+        anykv::set::<f64>("txn_rate", TXN_RATE);
+        anykv::set::<TxnStats>("txn_stats", TxnStats::default());
+        anykv::set::<(f64, usize)>("avg_latency", (0.0, 0));
+        anykv::set::<(f64, usize)>("avg_virtual_size", (0.0, 0));
+        anykv::set::<usize>("D", validators / 4); // SparseBullshark sample size
+
+        let mut sim = SimulationBuilder::default()
+            .add_pool::<P>("Validators", validators)
+            .latency_topology(&[LatencyDescription::WithinPool(
+                "Validators",
+                Distributions::Normal(Jiffies(50), Jiffies(10)),
+            )])
+            .time_budget(TIME_BUDGET)
+            .nic_bandwidth(BandwidthDescription::Bounded(
+                100 * 1024 * 1024 / (8 * 1000), // 100 Mb/sec NICs
+            ))
+            .seed(123)
+            .build();
+
+        sim.run();
+
+        let (vertex_latency, ordered_vertices) = anykv::get::<(f64, usize)>("avg_latency");
+        let txns = anykv::get::<TxnStats>("txn_stats");
+        Performance {
+            ordered_vertices,
+            vertex_latency,
+            throughput: txns.committed as f64 / (TIME_BUDGET.0 as f64 / 1000.0),
+            txn_latency: txns.avg_latency(),
+        }
+    }
+}
+
+// Performance tracking of the protocols within the time of a CI job: the most
+// important configurations first, larger fleets only if there is time left.
+// Usage: nightly [total budget in minutes]
+fn main() {
+    let minutes: u64 = std::env::args()
+        .nth(1)
+        .map(|arg| arg.parse().expect("Budget should be a number of minutes"))
+        .unwrap_or(10);
+
+    let report = BatchScheduler::new(Duration::from_secs(minutes * 60))
+        .job(
+            "Bullshark n=50",
+            10,
+            Duration::from_secs(10),
+            run::<Bullshark>(50),
+        )
+        .job(
+            "SparseBullshark n=50",
+            10,
+            Duration::from_secs(10),
+            run::<SparseBullshark>(50),
+        )
+        .job("Shoal n=50", 5, Duration::from_secs(10), run::<Shoal>(50))
+        .job(
+            "DAGRider n=50",
+            5,
+            Duration::from_secs(10),
+            run::<DAGRider>(50),
+        )
+        .job(
+            "Bullshark n=100",
+            1,
+            Duration::from_secs(60),
+            run::<Bullshark>(100),
+        )
+        .job(
+            "SparseBullshark n=100",
+            1,
+            Duration::from_secs(60),
+            run::<SparseBullshark>(100),
+        )
+        .run();
+
+    print!("{report}");
+    report.save("nightly.csv");
+}
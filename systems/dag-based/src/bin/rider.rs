@@ -1,4 +1,7 @@
-use dag_based::rider::DAGRider;
+use dag_based::{
+    rider::DAGRider,
+    round_metrics::{ROUND_METRICS, RoundMetrics},
+};
 use dscale::{
     BandwidthDescription, Distributions, LatencyDescription, SimulationBuilder, global::anykv,
     time::Jiffies,
@@ -22,5 +25,13 @@ fn main() {
 
     let ordered = anykv::get::<(f64, usize)>("avg_latency").1;
     let avg_latency = anykv::get::<(f64, usize)>("avg_latency").0;
-    println!("ordered: {ordered}, avg_latency: {avg_latency}")
+    println!("ordered: {ordered}, avg_latency: {avg_latency}");
+
+    let rounds = anykv::get::<RoundMetrics>(ROUND_METRICS);
+    println!(
+        "round completeness: {:.3}, time to quorum: {:.1}, max buffered: {}",
+        rounds.avg_completeness(),
+        rounds.avg_time_to_quorum_overall(),
+        rounds.max_buffered()
+    );
 }
@@ -0,0 +1,46 @@
+use dag_based::{bullshark::Bullshark, shoal::Shoal};
+use dscale::{
+    Distributions, Jiffies, LatencyDescription, ProcessHandle, Scenario, SimulationBuilder,
+    global::anykv, scenario,
+};
+
+const VALIDATORS: usize = 10;
+
+// Bullshark and Shoal (pipelined anchors, reputation-based leaders) under the same network:
+// a healthy fleet and a fleet with a crashed validator, which Bullshark keeps electing.
+fn main() {
+    let bullshark = run::<Bullshark>("Bullshark", false);
+    let shoal = run::<Shoal>("Shoal", false);
+    let bullshark_crashed = run::<Bullshark>("Bullshark, crashed validator", true);
+    let shoal_crashed = run::<Shoal>("Shoal, crashed validator", true);
+
+    assert!(shoal < bullshark, "Pipelining should reduce latency");
+    assert!(
+        shoal_crashed < bullshark_crashed,
+        "Reputation should avoid crashed leaders"
+    );
+}
+
+fn run<P: ProcessHandle + Default + 'static>(name: &str, crash: bool) -> f64 {
+    anykv::set::<(f64, usize)>("avg_latency", (0.0, 0));
+
+    let mut builder = SimulationBuilder::default()
+        .add_pool::<P>("Validators", VALIDATORS)
+        .latency_topology(&[LatencyDescription::WithinPool(
+            "Validators",
+            Distributions::Uniform(Jiffies(5), Jiffies(10)),
+        )])
+        .time_budget(Jiffies(60_000))
+        .seed(42);
+
+    if crash {
+        builder = builder.scenario(Scenario::new().at(Jiffies(1_000), scenario::crash(VALIDATORS)));
+    }
+
+    let mut sim = builder.build();
+    sim.run();
+
+    let (latency, ordered) = anykv::get::<(f64, usize)>("avg_latency");
+    println!("{name}: ordered vertices: {ordered}, avg latency: {latency:.2}");
+    latency
+}
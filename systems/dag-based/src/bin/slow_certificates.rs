@@ -0,0 +1,47 @@
+use dag_based::{bullshark::Bullshark, consistent_broadcast::BCBMessage};
+use dscale::{Distributions, Jiffies, LatencyDescription, SimulationBuilder, global::anykv};
+
+const SLOWDOWN: Distributions = Distributions::Uniform(Jiffies(50), Jiffies(50));
+
+// Sensitivity of ordering latency to a slow certificate path of the consistent broadcast
+fn main() {
+    let baseline = run("nothing", |builder| builder);
+    let certificates = run("certificates", |builder| {
+        builder.message_latency_if::<BCBMessage>(
+            |m| matches!(m, BCBMessage::Certificate(..)),
+            SLOWDOWN,
+        )
+    });
+    let everything = run("all broadcast messages", |builder| {
+        builder.message_latency::<BCBMessage>(SLOWDOWN)
+    });
+
+    assert!(
+        baseline < certificates,
+        "Slow certificates should delay ordering"
+    );
+    assert!(
+        certificates < everything,
+        "Slowing every step should hurt more than slowing certificates only"
+    );
+}
+
+fn run(name: &str, slow: impl FnOnce(SimulationBuilder) -> SimulationBuilder) -> f64 {
+    anykv::set::<(f64, usize)>("avg_latency", (0.0, 0));
+
+    let builder = SimulationBuilder::default()
+        .add_pool::<Bullshark>("Validators", 10)
+        .latency_topology(&[LatencyDescription::WithinPool(
+            "Validators",
+            Distributions::Uniform(Jiffies(5), Jiffies(10)),
+        )])
+        .time_budget(Jiffies(20_000))
+        .seed(42);
+
+    let mut sim = slow(builder).build();
+    sim.run();
+
+    let (latency, ordered) = anykv::get::<(f64, usize)>("avg_latency");
+    println!("Slowed {name}: ordered vertices: {ordered}, avg latency: {latency:.2}");
+    latency
+}
@@ -0,0 +1,51 @@
+use dag_based::{bullshark::Bullshark, validation::CryptoCost};
+use dscale::{
+    Distributions, Jiffies, LatencyDescription, SimulationBuilder, global::anykv,
+    helpers::Leadership,
+};
+
+const VALIDATORS: usize = 10;
+const SLOW: f64 = 0.2;
+
+// One slow replica among fast ones: ordering latency when it is a follower and when it leads
+fn main() {
+    let uniform = run("uniform fleet", |builder| builder);
+    let follower = run("slow follower", |builder| {
+        builder.process_cpu_speed(VALIDATORS, SLOW)
+    });
+    let leader = run("slow leader", |builder| builder.process_cpu_speed(1, SLOW));
+
+    assert!(
+        uniform.max(follower) < leader,
+        "A slow leader should hurt more than a slow follower"
+    );
+}
+
+fn run(name: &str, configure: impl FnOnce(SimulationBuilder) -> SimulationBuilder) -> f64 {
+    anykv::set::<(f64, usize)>("avg_latency", (0.0, 0));
+    anykv::set::<CryptoCost>(
+        "crypto_cost",
+        CryptoCost {
+            signature_verification: Jiffies(1),
+            certificate_verification: Jiffies(1),
+            ..CryptoCost::default()
+        },
+    );
+
+    let builder = SimulationBuilder::default()
+        .add_pool::<Bullshark>("Validators", VALIDATORS)
+        .latency_topology(&[LatencyDescription::WithinPool(
+            "Validators",
+            Distributions::Uniform(Jiffies(5), Jiffies(10)),
+        )])
+        .leader_schedule(&[(Jiffies(0), Leadership::Pinned(1))])
+        .time_budget(Jiffies(20_000))
+        .seed(42);
+
+    let mut sim = configure(builder).build();
+    sim.run();
+
+    let (latency, ordered) = anykv::get::<(f64, usize)>("avg_latency");
+    println!("{name}: ordered vertices: {ordered}, avg latency: {latency:.2}");
+    latency
+}
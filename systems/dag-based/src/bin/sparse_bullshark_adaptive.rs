@@ -0,0 +1,69 @@
+use dag_based::{
+    adaptive_d::{AdaptiveD, D_TRAJECTORY, DChange},
+    sparse_bullshark::{SparseBullshark, SparseBullsharkLayer},
+};
+use dscale::{
+    BandwidthDescription, Distributions, Jiffies, LatencyDescription, Scenario, SimulationBuilder,
+    global::anykv, scenario::crash,
+};
+
+const VALIDATORS: usize = 40;
+
+// 40 validators, 10 of them crash at 5s, so rounds of crashed leaders time out.
+// Compares SparseBullshark with static D against the adaptive controller starting
+// from the smallest D, and prints how D of validator 1 moved.
+fn main() {
+    run("static D=2", 2, None);
+    run("static D=13", 13, None);
+    run("adaptive", 2, Some(AdaptiveD::default()));
+}
+
+fn run(name: &str, d: usize, adaptive: Option<AdaptiveD>) {
+    anykv::set::<(f64, usize)>("avg_latency", (0.0, 0));
+    anykv::set::<(f64, usize)>("avg_virtual_size", (0.0, 0));
+    anykv::set::<usize>("D", d);
+    anykv::set::<Vec<DChange>>(D_TRAJECTORY, Vec::new());
+    if let Some(adaptive) = adaptive {
+        anykv::set::<AdaptiveD>("adaptive_D", adaptive);
+    }
+
+    let scenario = (VALIDATORS - 9..=VALIDATORS).fold(Scenario::new(), |scenario, id| {
+        scenario.at(Jiffies(5_000), crash(id))
+    });
+    let mut sim = SimulationBuilder::default()
+        .add_pool::<SparseBullshark>("Validators", VALIDATORS)
+        .latency_topology(&[LatencyDescription::WithinPool(
+            "Validators",
+            Distributions::Normal(Jiffies(50), Jiffies(10)),
+        )])
+        .nic_bandwidth(BandwidthDescription::Bounded(
+            100 * 1024 * 1024 / (8 * 1000), // 100 Mb/sec NICs
+        ))
+        .scenario(scenario)
+        .time_budget(Jiffies(30_000))
+        .seed(7)
+        .build();
+    sim.run();
+
+    let (latency, ordered) = anykv::get::<(f64, usize)>("avg_latency");
+    let (size, _) = anykv::get::<(f64, usize)>("avg_virtual_size");
+    println!(
+        "{name}: ordered vertices: {ordered}, avg latency: {latency:.2}, avg vertex size: {size:.0}"
+    );
+
+    let last_round = sim
+        .process::<SparseBullshark>(1)
+        .borrow()
+        .layer::<SparseBullsharkLayer>()
+        .last_ordered_round();
+    println!("  last ordered round of P1: {last_round}");
+
+    let trajectory: Vec<String> = anykv::get::<Vec<DChange>>(D_TRAJECTORY)
+        .into_iter()
+        .filter(|change| change.process == 1)
+        .map(|change| format!("r{}={}", change.round, change.D))
+        .collect();
+    if !trajectory.is_empty() {
+        println!("  D of P1: {}", trajectory.join(" "));
+    }
+}
@@ -0,0 +1,62 @@
+use dag_based::{sampling::Sampling, sparse_bullshark::SparseBullshark};
+use dscale::{
+    Distributions, Jiffies, LatencyDescription, RegionLink, SimulationBuilder, global::anykv,
+};
+
+// 8 validators in "eu" and 4 in a far "asia" region, D = 4.
+// Compares ordering latency of SparseBullshark with different parent sampling strategies.
+// Stakes favour the "asia" validators, so stake-weighted sampling links far vertices more often.
+// Traffic between the regions is priced like cloud egress.
+fn main() {
+    [
+        Sampling::Uniform,
+        Sampling::LatencyProximity,
+        Sampling::StakeWeighted,
+    ]
+    .into_iter()
+    .for_each(run);
+}
+
+fn run(sampling: Sampling) {
+    anykv::set::<(f64, usize)>("avg_latency", (0.0, 0));
+    anykv::set::<(f64, usize)>("avg_virtual_size", (0.0, 0));
+    anykv::set::<usize>("D", 4);
+    anykv::set::<Sampling>("sampling", sampling);
+    anykv::set::<Vec<u64>>("stakes", [vec![1; 8], vec![4; 4]].concat());
+
+    let mut sim = SimulationBuilder::default()
+        .add_pool_in_region::<SparseBullshark>("Validators", "eu", 8)
+        .add_pool_in_region::<SparseBullshark>("Validators", "asia", 4)
+        .latency_topology(&[
+            LatencyDescription::WithinRegion("eu", Distributions::Uniform(Jiffies(5), Jiffies(10))),
+            LatencyDescription::WithinRegion(
+                "asia",
+                Distributions::Uniform(Jiffies(5), Jiffies(10)),
+            ),
+            LatencyDescription::BetweenRegions(
+                "eu",
+                "asia",
+                Distributions::Uniform(Jiffies(80), Jiffies(100)),
+            ),
+        ])
+        .region_link(
+            "eu",
+            "asia",
+            RegionLink {
+                cost_per_gb: 0.08,
+                ..Default::default()
+            },
+        )
+        .time_budget(Jiffies(20_000))
+        .seed(42)
+        .build();
+    sim.run();
+
+    let (latency, ordered) = anykv::get::<(f64, usize)>("avg_latency");
+    let links = sim.link_stats();
+    println!(
+        "Sampling {sampling:?}: ordered vertices: {ordered}, avg latency: {latency:.2}, cross-region traffic: {} bytes (${:.6})",
+        links.cross_region_bytes(),
+        links.total_cost()
+    );
+}
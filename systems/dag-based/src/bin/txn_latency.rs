@@ -0,0 +1,62 @@
+use dag_based::{
+    bullshark::Bullshark,
+    workload::{MempoolStats, SHARED_MEMPOOL, SharedMempool, TxnStats, mempool_stats},
+};
+use dscale::{Distributions, Jiffies, LatencyDescription, SimulationBuilder, global::anykv};
+
+const VALIDATORS: usize = 10;
+
+// Latency of transactions, not vertices: time in the mempool until the first vertex includes
+// the transaction, and time until that vertex is ordered. Clients submitting every transaction
+// to several validators make them include it more than once.
+fn main() {
+    let single = run(1);
+    let replicated = run(3);
+
+    assert_eq!(
+        single.duplicates, 0,
+        "Single submission should not duplicate"
+    );
+    assert!(
+        replicated.duplicates > 0,
+        "Replicated submission should duplicate"
+    );
+    assert!(single.ordered <= single.submitted && replicated.ordered <= replicated.submitted);
+}
+
+fn run(replication: usize) -> MempoolStats {
+    anykv::set::<f64>("txn_rate", 2_000.0);
+    anykv::set::<TxnStats>("txn_stats", TxnStats::default());
+    anykv::set::<usize>("txn_replication", replication);
+    anykv::set::<SharedMempool>(SHARED_MEMPOOL, SharedMempool::default());
+    anykv::set::<(f64, usize)>("avg_latency", (0.0, 0));
+
+    let mut sim = SimulationBuilder::default()
+        .add_pool::<Bullshark>("Validators", VALIDATORS)
+        .latency_topology(&[LatencyDescription::WithinPool(
+            "Validators",
+            Distributions::Uniform(Jiffies(5), Jiffies(10)),
+        )])
+        .time_budget(Jiffies(20_000))
+        .seed(42)
+        .build();
+
+    sim.run();
+
+    let (vertex_latency, _) = anykv::get::<(f64, usize)>("avg_latency");
+    let stats = mempool_stats();
+    println!(
+        "Replication {replication}: submitted: {}, ordered: {}, duplicate inclusions: {} ({:.1}%)",
+        stats.submitted,
+        stats.ordered,
+        stats.duplicates,
+        100.0 * stats.duplicates as f64 / stats.included.max(1) as f64
+    );
+    println!(
+        "  vertex latency: {vertex_latency:.2}, txn inclusion: {:.2}, ordering: {:.2}, end-to-end: {:.2}",
+        stats.avg_inclusion_latency(),
+        stats.avg_ordering_latency(),
+        stats.avg_end_to_end_latency()
+    );
+    stats
+}
@@ -0,0 +1,65 @@
+use dag_based::{bullshark::Bullshark, validation::CryptoCost};
+use dscale::{
+    Distributions, Jiffies, LatencyDescription, SimulationBuilder, crypto::VrfProof, global::anykv,
+};
+
+const VALIDATORS: usize = 10;
+
+// Bullshark with round robin leaders, known to an adversary in advance,
+// and with leaders elected by VRF: price of the proofs in vertices and their verification.
+fn main() {
+    let (round_robin, _) = run("round robin", false);
+    let (vrf, leaders) = run("VRF", true);
+
+    assert!(vrf > 0.0, "VRF leaders should order vertices");
+    assert!(
+        leaders.iter().all(|rounds| *rounds > 0),
+        "Every validator should be elected at some round"
+    );
+    assert!(
+        vrf < round_robin * 1.5,
+        "VRF election should not change latency much"
+    );
+}
+
+fn run(name: &str, vrf_leaders: bool) -> (f64, Vec<usize>) {
+    anykv::set::<(f64, usize)>("avg_latency", (0.0, 0));
+    anykv::set::<bool>("vrf_leaders", vrf_leaders);
+    anykv::set::<CryptoCost>(
+        "crypto_cost",
+        CryptoCost {
+            signature_verification: Jiffies(1),
+            certificate_verification: Jiffies(1),
+            vrf_verification: Jiffies(1),
+            ..CryptoCost::default()
+        },
+    );
+
+    let mut sim = SimulationBuilder::default()
+        .add_pool::<Bullshark>("Validators", VALIDATORS)
+        .latency_topology(&[LatencyDescription::WithinPool(
+            "Validators",
+            Distributions::Uniform(Jiffies(5), Jiffies(10)),
+        )])
+        .time_budget(Jiffies(20_000))
+        .seed(42)
+        .build();
+
+    sim.run();
+
+    // Elections of the first 200 leader rounds, as validators saw them
+    let mut leaders = vec![0; VALIDATORS];
+    (0..200).map(|slot| 2 * slot).for_each(|round| {
+        let leader = (1..=VALIDATORS)
+            .min_by_key(|validator| VrfProof::of(*validator, round).output)
+            .unwrap();
+        leaders[leader - 1] += 1;
+    });
+
+    let (latency, ordered) = anykv::get::<(f64, usize)>("avg_latency");
+    println!("Leaders {name}: ordered vertices: {ordered}, avg latency: {latency:.2}");
+    if vrf_leaders {
+        println!("VRF leader rounds per validator: {leaders:?}");
+    }
+    (latency, leaders)
+}
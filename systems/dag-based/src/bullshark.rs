@@ -1,159 +1,163 @@
 // https://arxiv.org/pdf/2201.05677
 // https://arxiv.org/pdf/2209.05633
 
-use std::{
-    collections::BTreeSet,
-    rc::{Rc, Weak},
+use std::collections::HashMap;
+
+use dscale::{
+    global::{anykv, bootstrap, configuration},
+    helpers::RoundProtocol,
+    message::{Shared, WeakShared},
+    stack::{Layer, LayerContext, Stack, StackDefinition, Stacked},
+    *,
 };
 
-use dscale::{global::configuration, *};
-
 use crate::{
-    consistent_broadcast::{BCBMessage, ByzantineConsistentBroadcast},
+    common_coin::CommonCoin,
+    consistent_broadcast::ByzantineConsistentBroadcast,
     dag_utils::{RoundBasedDAG, Vertex, VertexMessage, VertexPtr, same_vertex},
+    leaders::LeaderElection,
+    ordered_sink::{OrderedSink, OrderingEngine},
+    validation::{CryptoCost, QuorumEdgesValidator, VerificationQueue},
+    workload::Mempool,
 };
 
-pub struct Bullshark {
-    rbcast: ByzantineConsistentBroadcast,
+const ROUND_TIMEOUT: Jiffies = Jiffies(10000);
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum VoteType {
+    Steady,
+    Fallback,
+}
+
+// Asynchronous fallback of the full protocol, enabled with "bullshark_fallback" (anykv).
+// Rounds form waves of 4 (wave k: rounds 4k+2..=4k+5) with steady leaders in the first and
+// the third round and a fallback leader in the first round, elected by the common coin.
+// Every validator votes either steady or fallback in a wave, depending on whether its first
+// vertex of the wave sees a commit of the previous wave.
+struct Fallback {
+    coin: CommonCoin,
+    vote_types: HashMap<(usize, ProcessId), VoteType>, // (Wave, validator) -> vote type
+}
+
+fn wave_of(round: usize) -> Option<usize> {
+    round.checked_sub(2).map(|round| round / 4)
+}
+
+fn first_round(wave: usize) -> usize {
+    4 * wave + 2
+}
+
+fn last_round(wave: usize) -> usize {
+    4 * wave + 5
+}
+
+#[derive(Default)]
+pub struct BullsharkLayer {
+    outbox: Vec<VertexMessage>, // Submitted to the broadcast layer once the handler returns
+    validator: Option<VerificationQueue<QuorumEdgesValidator>>,
+    mempool: Mempool,
+    leaders: LeaderElection,
+    fallback: Option<Fallback>,
     self_id: ProcessId,
     proc_num: usize,
     dag: RoundBasedDAG,
-    round: usize,
-    buffer: BTreeSet<VertexPtr>,
+    rounds: Option<RoundProtocol>,
     last_ordered_round: usize,
     ordered_anchors_stack: Vec<VertexPtr>,
-    wait: bool,
-    current_timer: TimerId,
+    bootstrap: TimerId, // Fires once all validators have started
 }
 
-impl Default for Bullshark {
-    fn default() -> Self {
-        Self {
-            rbcast: ByzantineConsistentBroadcast::default(),
-            self_id: 0,
-            proc_num: 0,
-            dag: RoundBasedDAG::default(),
-            round: 0,
-            buffer: BTreeSet::new(),
-            last_ordered_round: 0,
-            ordered_anchors_stack: Vec::new(),
-            wait: true,
-            current_timer: 0,
-        }
+impl Layer for BullsharkLayer {
+    fn start(&mut self, context: &mut LayerContext) {
+        self.on_start();
+        self.flush(context);
+    }
+
+    fn on_deliver(&mut self, from: ProcessId, message: MessagePtr, context: &mut LayerContext) {
+        self.on_vertex_message(from, message);
+        self.flush(context);
+    }
+
+    fn on_timer(&mut self, id: TimerId, context: &mut LayerContext) {
+        self.on_timer_fired(id);
+        self.flush(context);
+    }
+}
+
+// Inspection after a run, see Simulation::process
+impl BullsharkLayer {
+    pub fn last_ordered_round(&self) -> usize {
+        self.last_ordered_round
     }
 }
 
-impl ProcessHandle for Bullshark {
-    fn start(&mut self) {
+// Handlers
+impl BullsharkLayer {
+    fn flush(&mut self, context: &mut LayerContext) {
+        self.outbox
+            .drain(..)
+            .for_each(|message| context.submit(message));
+    }
+
+    fn on_start(&mut self) {
         self.self_id = rank();
         self.proc_num = configuration::process_number();
         self.dag.set_round_size(configuration::process_number());
-        self.rbcast.start(configuration::process_number());
-
-        // Shared genesis vertices
-        let genesis_vertex = VertexPtr::new(Vertex {
-            round: 0,
-            source: self.self_id,
-            strong_edges: Vec::new(),
-            creation_time: now(),
-        });
+        self.rounds = Some(RoundProtocol::new(self.quorum_size()).with_timeout(ROUND_TIMEOUT));
+        self.validator = Some(VerificationQueue::new(QuorumEdgesValidator {
+            quorum_size: self.quorum_size(),
+            cost: CryptoCost::configured(),
+        }));
+        self.mempool = Mempool::configured();
+        self.leaders = LeaderElection::configured(self.proc_num);
+        if anykv::try_get::<bool>("bullshark_fallback").unwrap_or(false) {
+            self.fallback = Some(Fallback {
+                coin: CommonCoin::new(self.proc_num),
+                vote_types: HashMap::new(),
+            });
+        }
 
-        self.rbcast
-            .reliably_broadcast(VertexMessage::Genesis(genesis_vertex));
+        // Shared genesis vertices, the first round starts once everyone has them
+        for v in self.dag.add_genesis() {
+            self.rounds().record(v.round, v.source);
+        }
+        self.bootstrap = bootstrap::await_all_started();
     }
 
     // DAG construction: part 1
-    fn on_message(&mut self, from: ProcessId, message: MessagePtr) {
-        if let Some(bs_message) = self.rbcast.process(from, message.as_type::<BCBMessage>()) {
-            match bs_message.as_type::<VertexMessage>().as_ref() {
-                VertexMessage::Genesis(v) => {
-                    debug_process!("Got genesis");
-                    debug_assert!(v.round == 0);
-                    self.dag.add_vertex(v.clone());
-                    self.try_advance_round();
-                    return;
-                }
-
-                VertexMessage::Vertex(v) => {
-                    debug_process!("Got vertex from: {from}");
-
-                    // Validity check
-                    if self.bad_vertex(&v, from) {
-                        return;
-                    }
-
-                    // Try to drain stalled vertices first (in sorted order)
-                    let mut vertices_in_the_buffer =
-                        self.buffer.iter().cloned().collect::<Vec<VertexPtr>>();
-                    vertices_in_the_buffer.sort_by_key(|v| v.round);
-                    vertices_in_the_buffer.into_iter().for_each(|v| {
-                        self.try_add_to_dag(v);
-                    });
-
-                    // Then try add current received vertex
-                    if !self.try_add_to_dag(v.clone()) {
-                        self.buffer.insert(v.clone());
-                    }
-
-                    if self.round == v.round {
-                        if !self.wait {
-                            self.try_advance_round();
-                            return;
-                        }
-
-                        // Note: anchor vertices are on even rounds
-                        match self.round % 4 {
-                            0 | 2 => {
-                                // Wait for steady leader of this round
-                                if self.get_anchor(self.round).is_some() {
-                                    self.try_advance_round();
-                                }
-                            }
-                            1 | 3 => {
-                                // Wait for 2f+1 links for anchor in previous round
-                                if self.get_anchor(self.round - 1).is_none() {
-                                    return;
-                                }
-
-                                if self.dag[self.round]
-                                    .iter()
-                                    .flatten()
-                                    .map(|v| {
-                                        v.strong_edges
-                                            .iter()
-                                            .map(|weak| weak.upgrade().unwrap())
-                                            .any(|v| {
-                                                same_vertex(
-                                                    &v,
-                                                    &self.get_anchor(self.round - 1).unwrap(),
-                                                )
-                                            })
-                                    })
-                                    .count()
-                                    >= self.quorum_size()
-                                {
-                                    self.try_advance_round();
-                                }
-                            }
-                            _ => unreachable!(),
-                        }
-                    }
+    fn on_vertex_message(&mut self, from: ProcessId, message: MessagePtr) {
+        match message.as_type::<VertexMessage>().as_ref() {
+            VertexMessage::Vertex(v) => {
+                debug_process!("Got vertex from: {from}");
+
+                // Validity check
+                if let Some(v) = self.validator().submit(from, v.clone()) {
+                    self.on_valid_vertex(v);
                 }
             }
         }
     }
 
-    fn on_timer(&mut self, id: TimerId) {
-        if id == self.current_timer {
+    fn on_timer_fired(&mut self, id: TimerId) {
+        if let Some(v) = self.validator().on_timer(id) {
+            self.on_valid_vertex(v);
+            return;
+        }
+
+        if id == self.bootstrap {
+            self.try_advance_round();
+            return;
+        }
+
+        if self.rounds().on_timer(id).is_some() {
             debug_process!("Timer fired: {id}");
-            self.wait = false;
             self.try_advance_round();
         }
     }
 }
 
 // Utils
-impl Bullshark {
+impl BullsharkLayer {
     fn adversary_threshold(&self) -> usize {
         (self.proc_num - 1) / 3
     }
@@ -170,12 +174,7 @@ impl Bullshark {
         self.dag[round].iter().flatten().count()
     }
 
-    fn quorum_reached_for_round(&self, round: usize) -> bool {
-        self.non_none_vertices_count_for_round(round) >= self.quorum_size()
-    }
-
-    fn create_vertex(&self, round: usize) -> VertexPtr {
-        // Infinite source of client txns
+    fn create_vertex(&mut self, round: usize) -> VertexPtr {
         VertexPtr::new(Vertex {
             round,
             source: self.self_id,
@@ -183,18 +182,20 @@ impl Bullshark {
                 .iter()
                 .flatten() // Remove option
                 .cloned()
-                .map(|strong| Rc::downgrade(&strong))
-                .collect::<Vec<Weak<Vertex>>>(),
+                .map(|strong| Shared::downgrade(&strong))
+                .collect::<Vec<WeakShared<Vertex>>>(),
             creation_time: now(),
+            batch: self.mempool.drain(&self.dag),
+            leader_proof: self.leaders.proof(round),
         })
     }
 
-    fn bad_vertex(&self, v: &VertexPtr, from: ProcessId) -> bool {
-        v.strong_edges.len() < self.quorum_size() || from != v.source
+    fn validator(&mut self) -> &mut VerificationQueue<QuorumEdgesValidator> {
+        self.validator.as_mut().expect("Validator not initialized")
     }
 
     fn get_leader_id(&self, round: usize) -> ProcessId {
-        return round % self.proc_num + 1;
+        self.leaders.leader(round, round / 2)
     }
 
     fn get_anchor(&self, round: usize) -> Option<VertexPtr> {
@@ -202,28 +203,80 @@ impl Bullshark {
         self.dag[round][leader].clone()
     }
 
-    fn start_timer(&mut self) {
-        self.current_timer = schedule_timer_after(Jiffies(10000));
-        debug_process!("New timer scheduled: {}", self.current_timer);
-        self.wait = true;
+    fn rounds(&mut self) -> &mut RoundProtocol {
+        self.rounds.as_mut().expect("Rounds not initialized")
+    }
+
+    fn round(&self) -> usize {
+        self.rounds.as_ref().map_or(0, RoundProtocol::round)
     }
 }
 
 // DAG construction: part 2
-impl Bullshark {
+impl BullsharkLayer {
+    fn on_valid_vertex(&mut self, v: VertexPtr) {
+        // Try to drain stalled vertices first (in sorted order)
+        self.dag.buffered().into_iter().for_each(|v| {
+            self.try_add_to_dag(v);
+        });
+
+        // Then try add current received vertex
+        if !self.try_add_to_dag(v.clone()) {
+            self.dag.buffer(v.clone());
+        }
+
+        let round = self.round();
+        if round == v.round {
+            if self.rounds().timed_out() || !self.waits_for_leaders() {
+                self.try_advance_round();
+                return;
+            }
+
+            // Note: anchor vertices are on even rounds
+            match round % 4 {
+                0 | 2 => {
+                    // Wait for steady leader of this round
+                    if self.get_anchor(round).is_some() {
+                        self.try_advance_round();
+                    }
+                }
+                1 | 3 => {
+                    // Wait for 2f+1 links for anchor in previous round
+                    if self.get_anchor(round - 1).is_none() {
+                        return;
+                    }
+
+                    if self.dag[round]
+                        .iter()
+                        .flatten()
+                        .map(|v| {
+                            v.strong_edges
+                                .iter()
+                                .map(|weak| weak.upgrade().unwrap())
+                                .any(|v| same_vertex(&v, &self.get_anchor(round - 1).unwrap()))
+                        })
+                        .count()
+                        >= self.quorum_size()
+                    {
+                        self.try_advance_round();
+                    }
+                }
+                _ => unreachable!(),
+            }
+        }
+    }
+
     fn try_advance_round(&mut self) {
-        if self.quorum_reached_for_round(self.round) {
-            debug_process!("Advancing to {} round", self.round + 1);
-            self.round += 1;
-            self.start_timer();
-            self.broadcast_vertex(self.round);
+        if let Some(round) = self.rounds().advance() {
+            debug_process!("Advancing to {round} round");
+            self.broadcast_vertex(round);
         }
     }
 
     fn broadcast_vertex(&mut self, round: usize) {
         let v = self.create_vertex(round);
         self.try_add_to_dag(v.clone());
-        self.rbcast.reliably_broadcast(VertexMessage::Vertex(v));
+        self.outbox.push(VertexMessage::Vertex(v));
     }
 
     fn try_add_to_dag(&mut self, v: VertexPtr) -> bool {
@@ -246,16 +299,15 @@ impl Bullshark {
         }
 
         self.dag.add_vertex(v.clone());
+        self.rounds().record(v.round, v.source);
 
-        if self.quorum_reached_for_round(v.round) && v.round > self.round {
-            self.round = v.round;
-            self.start_timer();
+        if self.rounds().catch_up(v.round) {
             self.broadcast_vertex(v.round);
         }
 
-        self.buffer.remove(&v);
-
-        if v.source == self.get_leader_id(v.round) {
+        if self.fallback.is_some() {
+            self.try_ordering_waves(v);
+        } else if v.source == self.get_leader_id(v.round) {
             self.try_ordering(v);
         }
         return true;
@@ -263,7 +315,7 @@ impl Bullshark {
 }
 
 // Consensus logic
-impl Bullshark {
+impl BullsharkLayer {
     fn try_ordering(&mut self, v: VertexPtr) {
         // Note: leaders are on even rounds
         if v.round % 2 == 1 || v.round == 0 {
@@ -323,3 +375,185 @@ impl Bullshark {
         }
     }
 }
+
+// Asynchronous fallback
+impl BullsharkLayer {
+    fn fallback(&mut self) -> &mut Fallback {
+        self.fallback.as_mut().expect("Fallback not enabled")
+    }
+
+    fn vote_type(&self, wave: usize, source: ProcessId) -> Option<VoteType> {
+        self.fallback
+            .as_ref()
+            .and_then(|fallback| fallback.vote_types.get(&(wave, source)).copied())
+    }
+
+    // Validators voting in the fallback mode do not wait for steady leaders
+    fn waits_for_leaders(&self) -> bool {
+        match wave_of(self.round()) {
+            Some(wave) if self.fallback.is_some() => {
+                self.vote_type(wave, self.self_id) == Some(VoteType::Steady)
+            }
+            _ => true,
+        }
+    }
+
+    fn fallback_leader(&mut self, wave: usize) -> Option<VertexPtr> {
+        // Coin shares come with vertices of the last round
+        if last_round(wave) > self.dag.current_max_allocated_round() {
+            return None;
+        }
+        let shares = self.non_none_vertices_count_for_round(last_round(wave));
+        let leader = self.fallback().coin.toss(wave, shares)?;
+        self.dag[first_round(wave)][leader].clone()
+    }
+
+    fn try_ordering_waves(&mut self, v: VertexPtr) {
+        let Some(wave) = wave_of(v.round) else {
+            return;
+        };
+
+        if v.round == first_round(wave) {
+            let vote_type = self.decide_vote_type(&v, wave);
+            self.fallback()
+                .vote_types
+                .insert((wave, v.source), vote_type);
+        }
+
+        // Steady leader of the previous round: 2f+1 steady votes
+        if v.round % 2 == 1
+            && let Some(anchor) = self.get_anchor(v.round - 1)
+            && anchor.round > self.last_ordered_round
+            && self.votes(&anchor, v.round, None) >= self.quorum_size()
+        {
+            self.order_waves(anchor);
+            return;
+        }
+
+        // Fallback leader of the wave: 2f+1 fallback votes in the last round
+        if v.round == last_round(wave)
+            && let Some(anchor) = self.fallback_leader(wave)
+            && anchor.round > self.last_ordered_round
+            && self.votes(&anchor, v.round, None) >= self.quorum_size()
+        {
+            self.order_waves(anchor);
+        }
+    }
+
+    // Steady if the vertex sees the previous wave committed: its second steady leader
+    // or its fallback leader with 2f+1 votes of the matching type among the parents
+    fn decide_vote_type(&mut self, v: &VertexPtr, wave: usize) -> VoteType {
+        if wave == 0 {
+            return VoteType::Steady;
+        }
+        let previous = wave - 1;
+        let committed = [
+            self.get_anchor(first_round(previous) + 2),
+            self.fallback_leader(previous),
+        ]
+        .into_iter()
+        .flatten()
+        .any(|anchor| self.votes(&anchor, last_round(previous), Some(v)) >= self.quorum_size());
+
+        if committed {
+            VoteType::Steady
+        } else {
+            VoteType::Fallback
+        }
+    }
+
+    // Votes of the matching type for anchor among vertices of the round, or only among those
+    // in the causal history of a vertex. Steady leaders are voted for by edges (next round),
+    // fallback leaders by paths (last round of the wave).
+    fn votes(&mut self, anchor: &VertexPtr, round: usize, history: Option<&VertexPtr>) -> usize {
+        if round > self.dag.current_max_allocated_round() {
+            return 0;
+        }
+        let wave = wave_of(anchor.round).expect("Anchors belong to waves");
+        let steady = round == anchor.round + 1;
+        let expected = if steady {
+            VoteType::Steady
+        } else {
+            VoteType::Fallback
+        };
+
+        let voters: Vec<VertexPtr> = self.dag[round].iter().flatten().cloned().collect();
+        let mut votes = 0;
+        for voter in voters {
+            if self.vote_type(wave, voter.source) != Some(expected) {
+                continue;
+            }
+            if let Some(v) = history
+                && !self.dag.path_exists(v, &voter)
+            {
+                continue;
+            }
+            let linked = if steady {
+                voter
+                    .strong_edges
+                    .iter()
+                    .any(|edge| same_vertex(&edge.upgrade().unwrap(), anchor))
+            } else {
+                self.dag.path_exists(&voter, anchor)
+            };
+            if linked {
+                votes += 1;
+            }
+        }
+        votes
+    }
+
+    // Previous leaders are ordered if someone could have committed them:
+    // f+1 votes of their type in the causal history, by quorum intersection
+    fn order_waves(&mut self, v: VertexPtr) {
+        let mut anchor = v.clone();
+        self.ordered_anchors_stack.push(anchor.clone());
+        let mut r = anchor.round.saturating_sub(2);
+        while r > self.last_ordered_round {
+            // (Leader, round of its votes)
+            let mut candidates = vec![(self.get_anchor(r), r + 1)];
+            if let Some(wave) = wave_of(r)
+                && r == first_round(wave)
+            {
+                candidates.push((self.fallback_leader(wave), last_round(wave)));
+            }
+
+            for (candidate, vote_round) in candidates {
+                let Some(candidate) = candidate else {
+                    continue;
+                };
+                if self.dag.path_exists(&anchor, &candidate)
+                    && self.votes(&candidate, vote_round, Some(&anchor))
+                        > self.adversary_threshold()
+                {
+                    self.ordered_anchors_stack.push(candidate.clone());
+                    anchor = candidate;
+                    break;
+                }
+            }
+            r -= 2;
+        }
+
+        self.last_ordered_round = v.round;
+        self.order_history();
+    }
+}
+
+impl OrderingEngine for BullsharkLayer {
+    fn ordered_sink(&mut self) -> &mut OrderedSink {
+        self.dag.ordered_sink()
+    }
+}
+
+// Validator process: Bullshark on top of consistent broadcast
+pub struct BullsharkStack;
+
+impl StackDefinition for BullsharkStack {
+    fn stack() -> Stack {
+        Stack::new()
+            .layer(ByzantineConsistentBroadcast::default())
+            .layer(BullsharkLayer::default())
+    }
+}
+
+pub type Bullshark = Stacked<BullsharkStack>;
@@ -0,0 +1,103 @@
+// Chain quality: how fairly the order includes vertices of every source.
+// Sampling-based variants (SparseBullshark) reference only a part of the previous
+// round, a source left out of the samples too often gets its vertices ordered
+// late or never. Every RoundBasedDAG reports into anykv under "chain_quality" on
+// its own, the key is created by the first DAG of the run.
+
+use dscale::{ProcessId, global::anykv};
+
+pub const CHAIN_QUALITY: &str = "chain_quality";
+
+// Inclusion rate below this share of the average one is flagged
+pub const DEFAULT_TOLERANCE: f64 = 0.5;
+
+// Indexed by ProcessId, vertices are counted once: at their source
+#[derive(Clone, Default, Debug)]
+pub struct ChainQuality {
+    pub created: Vec<usize>,
+    pub ordered: Vec<usize>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Underrepresented {
+    pub source: ProcessId,
+    pub inclusion: f64, // Ordered / created vertices of the source
+    pub expected: f64,  // Average inclusion rate over all sources
+}
+
+impl ChainQuality {
+    pub fn sources(&self) -> impl Iterator<Item = ProcessId> {
+        1..self.created.len()
+    }
+
+    // Share of all ordered vertices coming from the source, 1 / n if fair
+    pub fn share(&self, source: ProcessId) -> f64 {
+        let total: usize = self.ordered.iter().sum();
+        if total == 0 {
+            return 0.0;
+        }
+        self.ordered[source] as f64 / total as f64
+    }
+
+    pub fn inclusion(&self, source: ProcessId) -> f64 {
+        if self.created[source] == 0 {
+            return 0.0;
+        }
+        self.ordered[source] as f64 / self.created[source] as f64
+    }
+
+    pub fn expected_inclusion(&self) -> f64 {
+        let rates: Vec<f64> = self
+            .sources()
+            .map(|source| self.inclusion(source))
+            .collect();
+        if rates.is_empty() {
+            return 0.0;
+        }
+        rates.iter().sum::<f64>() / rates.len() as f64
+    }
+
+    // Sources included at less than tolerance * the average inclusion rate
+    pub fn underrepresented(&self, tolerance: f64) -> Vec<Underrepresented> {
+        let expected = self.expected_inclusion();
+        self.sources()
+            .map(|source| Underrepresented {
+                source,
+                inclusion: self.inclusion(source),
+                expected,
+            })
+            .filter(|flagged| flagged.inclusion < tolerance * expected)
+            .collect()
+    }
+
+    // Lowest inclusion rate relative to the average one, 1.0 if perfectly fair
+    pub fn fairness(&self) -> f64 {
+        let expected = self.expected_inclusion();
+        if expected == 0.0 {
+            return 1.0;
+        }
+        self.sources()
+            .map(|source| self.inclusion(source) / expected)
+            .fold(f64::INFINITY, f64::min)
+    }
+}
+
+pub(crate) fn register(processes: usize) {
+    if anykv::try_get::<ChainQuality>(CHAIN_QUALITY).is_none() {
+        anykv::set::<ChainQuality>(
+            CHAIN_QUALITY,
+            ChainQuality {
+                created: vec![0; processes + 1],
+                ordered: vec![0; processes + 1],
+            },
+        );
+    }
+}
+
+pub(crate) fn record_created(source: ProcessId) {
+    anykv::modify::<ChainQuality>(CHAIN_QUALITY, |quality| quality.created[source] += 1);
+}
+
+pub(crate) fn record_ordered(source: ProcessId) {
+    anykv::modify::<ChainQuality>(CHAIN_QUALITY, |quality| quality.ordered[source] += 1);
+}
@@ -0,0 +1,36 @@
+use std::collections::HashMap;
+
+use dscale::{ProcessId, crypto::VrfProof};
+
+// Separates coin tosses from VRF leader election, which is evaluated on round numbers
+const COIN_INPUT: u64 = 1 << 63;
+
+// Threshold common coin electing fallback leaders of waves.
+// The outcome of a wave stays hidden until f+1 validators reveal their shares, which travel
+// with their vertices of the last round of the wave. Any f+1 shares give the same outcome,
+// modeled as the lowest VRF output among all validators on the wave.
+pub struct CommonCoin {
+    proc_num: usize,
+    tossed: HashMap<usize, ProcessId>, // Wave -> leader
+}
+
+impl CommonCoin {
+    pub fn new(proc_num: usize) -> Self {
+        Self {
+            proc_num,
+            tossed: HashMap::new(),
+        }
+    }
+
+    pub fn toss(&mut self, wave: usize, shares: usize) -> Option<ProcessId> {
+        if shares <= (self.proc_num - 1) / 3 {
+            return None;
+        }
+        let proc_num = self.proc_num;
+        Some(*self.tossed.entry(wave).or_insert_with(|| {
+            (1..=proc_num)
+                .min_by_key(|validator| VrfProof::of(*validator, COIN_INPUT | wave as u64).output)
+                .expect("Should be at least one validator")
+        }))
+    }
+}
@@ -0,0 +1,245 @@
+// Runs several protocols under exactly the same conditions: every contender gets
+// the same topology, bandwidth, workload, fault schedule and set of seeds.
+
+use std::{fs::File, io::Write};
+
+use dscale::{
+    BandwidthDescription, Distributions, LatencyDescription, ProcessHandle, Scenario,
+    SimulationBuilder, global::anykv, time::Jiffies,
+};
+use rayon::prelude::*;
+
+use crate::{
+    chain_quality::{CHAIN_QUALITY, ChainQuality, DEFAULT_TOLERANCE},
+    round_metrics::{ROUND_METRICS, RoundMetrics},
+    statistics::{Summary, welch_t_test},
+    workload::TxnStats,
+};
+
+const POOL_NAME: &str = "Validators";
+
+#[derive(Clone)]
+pub struct ComparisonSetup {
+    pub validators: usize,
+    pub latency: Distributions,
+    pub bandwidth: BandwidthDescription,
+    pub time_budget: Jiffies,
+    pub txn_rate: f64, // Txns per second offered to the whole system
+    pub seeds: Vec<u64>,
+    pub faults: Scenario, // Partitions, crashes and restarts of every run
+}
+
+type Runner = Box<dyn Fn(&ComparisonSetup, u64) -> RunResult + Sync>;
+
+pub struct Contender {
+    pub name: String,
+    run: Runner,
+}
+
+impl Contender {
+    // configure sets protocol specific anykv keys before processes are created
+    pub fn new<P: ProcessHandle + Default + 'static>(
+        name: &str,
+        configure: impl Fn() + Sync + 'static,
+    ) -> Self {
+        let name = name.to_string();
+        Self {
+            name: name.clone(),
+            run: Box::new(move |setup, seed| {
+                anykv::set::<f64>("txn_rate", setup.txn_rate);
+                anykv::set::<TxnStats>("txn_stats", TxnStats::default());
+                anykv::set::<(f64, usize)>("avg_latency", (0.0, 0));
+                anykv::set::<(f64, usize)>("avg_virtual_size", (0.0, 0));
+                configure();
+
+                let mut sim = SimulationBuilder::default()
+                    .add_pool::<P>(POOL_NAME, setup.validators)
+                    .latency_topology(&[LatencyDescription::WithinPool(POOL_NAME, setup.latency)])
+                    .nic_bandwidth(setup.bandwidth)
+                    .time_budget(setup.time_budget)
+                    .scenario(setup.faults.clone())
+                    .seed(seed)
+                    .build();
+
+                sim.run();
+
+                let (vertex_latency, ordered_vertices) = anykv::get::<(f64, usize)>("avg_latency");
+                let txns = anykv::get::<TxnStats>("txn_stats");
+                let rounds = anykv::get::<RoundMetrics>(ROUND_METRICS);
+                let quality = anykv::get::<ChainQuality>(CHAIN_QUALITY);
+                RunResult {
+                    contender: name.clone(),
+                    seed,
+                    ordered_vertices,
+                    vertex_latency,
+                    throughput: txns.committed as f64 / (setup.time_budget.0 as f64 / 1000.0),
+                    txn_latency: txns.avg_latency(),
+                    round_completeness: rounds.avg_completeness(),
+                    time_to_quorum: rounds.avg_time_to_quorum_overall(),
+                    max_buffered: rounds.max_buffered(),
+                    fairness: quality.fairness(),
+                    underrepresented: quality.underrepresented(DEFAULT_TOLERANCE).len(),
+                }
+            }),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct RunResult {
+    pub contender: String,
+    pub seed: u64,
+    pub ordered_vertices: usize,
+    pub vertex_latency: f64,
+    pub throughput: f64, // Committed txns per second
+    pub txn_latency: f64,
+    pub round_completeness: f64, // Share of vertices of a round present in the DAGs
+    pub time_to_quorum: f64,
+    pub max_buffered: usize,
+    pub fairness: f64, // Lowest inclusion rate of a source relative to the average one
+    pub underrepresented: usize, // Sources flagged at DEFAULT_TOLERANCE
+}
+
+// Every (contender, seed) pair is an independent simulation, results are
+// returned in contenders order, then seeds order
+pub fn compare(setup: &ComparisonSetup, contenders: &[Contender]) -> Vec<RunResult> {
+    let runs: Vec<(&Contender, u64)> = contenders
+        .iter()
+        .flat_map(|contender| setup.seeds.iter().map(move |seed| (contender, *seed)))
+        .collect();
+    runs.into_par_iter()
+        .map(|(contender, seed)| (contender.run)(setup, seed))
+        .collect()
+}
+
+pub fn write_csv(results: &[RunResult], path: &str) {
+    let mut file = File::create(path).unwrap();
+    writeln!(
+        file,
+        "contender,seed,ordered_vertices,vertex_latency,throughput,txn_latency,round_completeness,time_to_quorum,max_buffered,fairness,underrepresented"
+    )
+    .unwrap();
+    results.iter().for_each(|r| {
+        writeln!(
+            file,
+            "{},{},{},{},{},{},{},{},{},{},{}",
+            r.contender,
+            r.seed,
+            r.ordered_vertices,
+            r.vertex_latency,
+            r.throughput,
+            r.txn_latency,
+            r.round_completeness,
+            r.time_to_quorum,
+            r.max_buffered,
+            r.fairness,
+            r.underrepresented
+        )
+        .unwrap();
+    });
+}
+
+type Metric = (&'static str, fn(&RunResult) -> f64);
+
+const METRICS: [Metric; 4] = [
+    ("ORDERED", |r| r.ordered_vertices as f64),
+    ("VERTEX LATENCY", |r| r.vertex_latency),
+    ("THROUGHPUT", |r| r.throughput),
+    ("TXN LATENCY", |r| r.txn_latency),
+];
+
+// Contender names in order of first appearance
+fn contenders(results: &[RunResult]) -> Vec<&str> {
+    let mut names: Vec<&str> = Vec::new();
+    results.iter().for_each(|r| {
+        if !names.contains(&r.contender.as_str()) {
+            names.push(&r.contender);
+        }
+    });
+    names
+}
+
+fn samples(results: &[RunResult], name: &str, metric: fn(&RunResult) -> f64) -> Vec<f64> {
+    results
+        .iter()
+        .filter(|r| r.contender == name)
+        .map(metric)
+        .collect()
+}
+
+// Averages over seeds with 95% confidence intervals
+pub fn print_table(results: &[RunResult]) {
+    println!(
+        "{:<24} | {:>16} | {:>16} | {:>16} | {:>16}",
+        "CONTENDER", METRICS[0].0, METRICS[1].0, METRICS[2].0, METRICS[3].0
+    );
+    println!("{}", "-".repeat(100));
+
+    contenders(results).into_iter().for_each(|name| {
+        let cells: Vec<String> = METRICS
+            .iter()
+            .map(|(_, metric)| {
+                let summary = Summary::of(&samples(results, name, *metric));
+                format!("{:.1} ± {:.1}", summary.mean, summary.ci)
+            })
+            .collect();
+        println!(
+            "{:<24} | {:>16} | {:>16} | {:>16} | {:>16}",
+            name, cells[0], cells[1], cells[2], cells[3]
+        );
+    });
+}
+
+// Chain quality averaged over seeds: sources whose vertices get ordered much
+// less often than the others point to censorship by the protocol
+pub fn print_fairness(results: &[RunResult]) {
+    println!(
+        "{:<24} | {:>16} | {:>16}",
+        "CONTENDER", "FAIRNESS", "UNDERREPRESENTED"
+    );
+    println!("{}", "-".repeat(62));
+
+    contenders(results).into_iter().for_each(|name| {
+        let fairness = Summary::of(&samples(results, name, |r| r.fairness));
+        let flagged = Summary::of(&samples(results, name, |r| r.underrepresented as f64));
+        println!(
+            "{:<24} | {:>16} | {:>16}",
+            name,
+            format!("{:.2} ± {:.2}", fairness.mean, fairness.ci),
+            format!("{:.1} ± {:.1}", flagged.mean, flagged.ci)
+        );
+    });
+}
+
+// Welch's t-test of every metric between every pair of contenders. Seeds are
+// the samples, so at least two seeds per contender are needed.
+pub fn print_significance(results: &[RunResult]) {
+    println!(
+        "{:<24} | {:<24} | {:<14} | {:>8} | {:>8} | VERDICT",
+        "BASELINE", "CONTENDER", "METRIC", "CHANGE", "P-VALUE"
+    );
+    println!("{}", "-".repeat(104));
+
+    let names = contenders(results);
+    names.iter().enumerate().for_each(|(i, a)| {
+        names[i + 1..].iter().for_each(|b| {
+            METRICS.iter().for_each(|(metric_name, metric)| {
+                let difference =
+                    welch_t_test(&samples(results, a, *metric), &samples(results, b, *metric));
+                println!(
+                    "{:<24} | {:<24} | {:<14} | {:>+7.1}% | {:>8.3} | {}",
+                    a,
+                    b,
+                    metric_name,
+                    difference.relative * 100.0,
+                    difference.p_value,
+                    if difference.significant() {
+                        "significant"
+                    } else {
+                        "noise"
+                    }
+                );
+            });
+        });
+    });
+}
@@ -1,6 +1,7 @@
-use std::rc::Rc;
-
-use dscale::{Message, ProcessId};
+use dscale::{
+    Message, ProcessId, crypto::size_model, global::configuration::process_number,
+    message::SharedMessage,
+};
 
 #[derive(Clone, PartialEq, Eq, Hash, Copy)]
 pub struct BCBMessageId {
@@ -8,21 +9,29 @@ pub struct BCBMessageId {
     pub(super) message_id: usize,
 }
 
+#[cfg(feature = "fuzz")]
+impl BCBMessageId {
+    pub(crate) fn new(process_id: ProcessId, message_id: usize) -> Self {
+        Self {
+            process_id,
+            message_id,
+        }
+    }
+}
+
 pub enum BCBMessage {
-    Initiate((BCBMessageId, Rc<dyn Message>)),
+    Initiate((BCBMessageId, SharedMessage)),
     Signature(BCBMessageId),
-    Certificate(usize, BCBMessageId),
+    Certificate(usize, BCBMessageId), // usize -> number of signers
 }
 
-pub const ID_SIZE: usize = 128;
-pub const SIG_SIZE: usize = 64; // For example Ed25519 or Secp256k1
-
 impl Message for BCBMessage {
     fn virtual_size(&self) -> usize {
+        let model = size_model();
         match self {
-            BCBMessage::Initiate((_, m)) => ID_SIZE + m.virtual_size(),
-            BCBMessage::Signature(_) => SIG_SIZE,
-            BCBMessage::Certificate(k_validators, _) => ID_SIZE + (k_validators / 8),
+            BCBMessage::Initiate((_, m)) => model.digest + m.virtual_size(),
+            BCBMessage::Signature(_) => model.signature,
+            BCBMessage::Certificate(signers, _) => model.certificate(*signers, process_number()),
         }
     }
 }
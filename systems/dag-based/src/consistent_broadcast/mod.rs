@@ -1,27 +1,50 @@
 mod message;
-pub(crate) use message::BCBMessage;
-pub(crate) use message::ID_SIZE;
+pub use message::BCBMessage;
+pub(crate) use message::BCBMessageId;
 
-use std::{
-    collections::{HashMap, HashSet},
-    rc::Rc,
-};
+use std::collections::HashMap;
 
-use dscale::{Message, MessagePtr, ProcessId, broadcast, rank, send_to};
+use dscale::{
+    Jiffies, MessagePtr, ProcessId, broadcast,
+    global::configuration,
+    helpers::DedupCache,
+    message::{Shared, SharedMessage},
+    protocol_error, rank, send_to,
+    stack::{Layer, LayerContext},
+};
 
-use crate::consistent_broadcast::message::BCBMessageId;
+// Certificates which overtook their message are kept until the message arrives,
+// bounded in case it never does (e.g. the sender crashed)
+const WAITING_CERTIFICATES_CAPACITY: usize = 1 << 16;
+const WAITING_CERTIFICATES_TTL: Jiffies = Jiffies(600_000);
 
 // Introduction to Reliable and Secure Distributed Programming
 // Algorithm 3.17: Signed Echo Broadcast
-#[derive(Default)]
+// Bottom layer of DAG protocols: broadcasts what the layer above submits and
+// delivers messages once their certificate arrives
 pub struct ByzantineConsistentBroadcast {
-    messages: HashMap<BCBMessageId, (Rc<dyn Message>, usize)>, // usize -> signature count, once it reaches 2f+1 message pops out
-    waiting_certificates: HashSet<BCBMessageId>,
+    messages: HashMap<BCBMessageId, (SharedMessage, usize)>, // usize -> signature count, once it reaches 2f+1 message pops out
+    waiting_certificates: DedupCache<BCBMessageId>,
     process_id: ProcessId,
     message_id: usize,
     proc_num: usize,
 }
 
+impl Default for ByzantineConsistentBroadcast {
+    fn default() -> Self {
+        Self {
+            messages: HashMap::new(),
+            waiting_certificates: DedupCache::new(
+                WAITING_CERTIFICATES_CAPACITY,
+                WAITING_CERTIFICATES_TTL,
+            ),
+            process_id: 0,
+            message_id: 0,
+            proc_num: 0,
+        }
+    }
+}
+
 impl ByzantineConsistentBroadcast {
     fn adversary_threshold(&self) -> usize {
         (self.proc_num - 1) / 3
@@ -40,24 +63,35 @@ impl ByzantineConsistentBroadcast {
     }
 }
 
+impl Layer for ByzantineConsistentBroadcast {
+    fn start(&mut self, _context: &mut LayerContext) {
+        self.process_id = rank();
+        self.proc_num = configuration::process_number();
+    }
+
+    fn on_deliver(&mut self, from: ProcessId, message: MessagePtr, context: &mut LayerContext) {
+        let Some(message) = message.try_as::<BCBMessage>() else {
+            protocol_error!("Unexpected message from P{from}");
+            return;
+        };
+        if let Some(delivered) = self.process(from, message) {
+            context.deliver(from, delivered);
+        }
+    }
+
+    fn on_submit(&mut self, message: MessagePtr, _context: &mut LayerContext) {
+        self.reliably_broadcast(message.0);
+    }
+}
+
 impl ByzantineConsistentBroadcast {
-    pub(crate) fn reliably_broadcast(&mut self, message: impl Message + 'static) {
+    fn reliably_broadcast(&mut self, shared: SharedMessage) {
         let next_id = self.next_unique_message_id();
-        let shared = Rc::new(message);
         self.messages.insert(next_id, (shared.clone(), 0));
         broadcast(BCBMessage::Initiate((next_id, shared)));
     }
 
-    pub(crate) fn start(&mut self, proc_num: usize) {
-        self.process_id = rank();
-        self.proc_num = proc_num;
-    }
-
-    pub(crate) fn process(
-        &mut self,
-        from: ProcessId,
-        message: Rc<BCBMessage>,
-    ) -> Option<MessagePtr> {
+    fn process(&mut self, from: ProcessId, message: Shared<BCBMessage>) -> Option<MessagePtr> {
         match message.as_ref() {
             BCBMessage::Certificate(_, id) => {
                 match self.messages.remove(&id) {
@@ -71,8 +105,7 @@ impl ByzantineConsistentBroadcast {
             }
             BCBMessage::Initiate((id, m)) => {
                 if id.process_id != self.process_id {
-                    if self.waiting_certificates.contains(&id) {
-                        self.waiting_certificates.remove(&id);
+                    if self.waiting_certificates.remove(id) {
                         return Some(MessagePtr(m.clone()));
                     }
                     self.messages.insert(*id, (m.clone(), 0));
@@ -89,7 +122,7 @@ impl ByzantineConsistentBroadcast {
                     Some(message_state) => {
                         message_state.1 += 1;
                         if message_state.1 == self.quorum_size() {
-                            broadcast(BCBMessage::Certificate(self.proc_num, *id));
+                            broadcast(BCBMessage::Certificate(self.quorum_size(), *id));
                         }
                         return None;
                     }
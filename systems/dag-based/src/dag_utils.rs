@@ -1,38 +1,58 @@
 use std::{
-    collections::VecDeque,
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    hash::{Hash, Hasher},
     ops::Index,
-    rc::{Rc, Weak},
 };
 
 use dscale::{
     Message, ProcessId,
-    global::{anykv, configuration::process_number},
+    crypto::{VrfProof, size_model},
+    global::{anykv, bootstrap, configuration::process_number, memory},
+    message::{Shared, WeakShared},
     now, rank,
-    time::{self},
+    time::{self, Jiffies},
 };
 
-use crate::consistent_broadcast::ID_SIZE;
+use crate::{
+    chain_quality, failover,
+    ordered_sink::{OrderedSink, OrderedVertex, TieBreak},
+    round_metrics,
+    workload::{Batch, Execution, TXN_SIZE, record_commit, record_seen},
+};
 
 const GC_REMAIN: usize = usize::MAX;
 
-pub type VertexPtr = Rc<Vertex>;
+pub type VertexPtr = Shared<Vertex>;
 type Round = Vec<Option<VertexPtr>>;
 
 pub fn same_vertex(v: &VertexPtr, u: &VertexPtr) -> bool {
-    Rc::ptr_eq(v, u)
+    Shared::ptr_eq(v, u)
 }
 
 pub struct Vertex {
     pub round: usize,
     pub source: ProcessId,
     pub creation_time: time::Jiffies,
+    pub batch: Batch,
+    pub leader_proof: Option<VrfProof>, // Author's VRF proof for the round under VRF leader election
 
     // Each vertex is a pointer to real one. (Each vertex is allocated exactly-once during execution)
-    // Each party contains strong Rc references to vertices in their dags.
-    // At the same time in the real dag edges are represented with weak Rc references.
-    // Once all parties GC-ed their dags, Vertices will be deallocated because there will be no more strong Rc references.
+    // Each party contains strong references to vertices in their dags.
+    // At the same time in the real dag edges are represented with weak references.
+    // Once all parties GC-ed their dags, Vertices will be deallocated because there will be no more strong references.
     // Until GC time is is safe for the process to upgrade Weak refs traversing dag backwards.
-    pub strong_edges: Vec<Weak<Vertex>>,
+    pub strong_edges: Vec<WeakShared<Vertex>>,
+}
+
+impl Vertex {
+    // Bytes on the wire, also the approximate footprint in memory
+    pub fn size(&self) -> usize {
+        // Round, ProcessId
+        4 + 4
+            + certificate_size() * self.strong_edges.len()
+            + TXN_SIZE * self.batch.transactions
+            + self.leader_proof.map_or(0, |_| size_model().vrf_proof)
+    }
 }
 
 impl PartialEq for Vertex {
@@ -46,6 +66,12 @@ impl PartialEq for Vertex {
 
 impl Eq for Vertex {}
 
+impl Hash for Vertex {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (self.round, self.source).hash(state);
+    }
+}
+
 impl PartialOrd for Vertex {
     fn ge(&self, other: &Self) -> bool {
         (self.round, self.source).ge(&(other.round, other.source))
@@ -64,25 +90,40 @@ impl Ord for Vertex {
     }
 }
 
+// Round 0 shared by all processes
+pub(crate) fn genesis(proc_num: usize) -> Vec<VertexPtr> {
+    bootstrap::genesis("dag_genesis", || {
+        (1..=proc_num)
+            .map(|source| {
+                VertexPtr::new(Vertex {
+                    round: 0,
+                    source,
+                    strong_edges: Vec::new(),
+                    creation_time: Jiffies(0),
+                    batch: Batch::default(),
+                    leader_proof: None,
+                })
+            })
+            .collect::<Vec<_>>()
+    })
+}
+
+// Every strong edge points to a vertex certified by a quorum
 fn certificate_size() -> usize {
-    (process_number() / 8) + ID_SIZE
+    let n = process_number();
+    let quorum = 2 * ((n - 1) / 3) + 1;
+    size_model().certificate(quorum, n)
 }
 
 #[derive(Clone)]
 pub enum VertexMessage {
     Vertex(VertexPtr),
-    Genesis(VertexPtr),
 }
 
 impl Message for VertexMessage {
     fn virtual_size(&self) -> usize {
-        // Round, ProcessId
-        4 + 4
-            + certificate_size()
-                * match self {
-                    VertexMessage::Genesis(v) => v.strong_edges.len(),
-                    VertexMessage::Vertex(v) => v.strong_edges.len(),
-                }
+        let VertexMessage::Vertex(v) = self;
+        v.size()
     }
 }
 
@@ -93,52 +134,100 @@ pub struct RoundBasedDAG {
     visited: VecDeque<Vec<bool>>, // Optimized allocations & constant lookup for iterated bfs
     ordered: VecDeque<Vec<bool>>,
     gc_offset: usize,
+    sink: OrderedSink,
+    tie_break: TieBreak,
+    buffer: BTreeSet<VertexPtr>, // Received vertices waiting for their parents
+    first_seen: BTreeMap<usize, Jiffies>, // Round -> first vertex received (buffered or added)
+    last_ordered_round: usize,   // Of the last anchor ordered from
+    stored_bytes: usize,         // Reported to memory as "dag"
+    buffered_bytes: usize,       // Reported to memory as "dag_buffer"
 }
 
 impl RoundBasedDAG {
     pub fn set_round_size(&mut self, proc_num: usize) {
         self.proc_num = proc_num;
+        self.tie_break = TieBreak::configured();
+        self.sink.set_execution(Execution::configured());
+        round_metrics::register(proc_num);
+        chain_quality::register(proc_num);
+        failover::register();
+    }
+
+    // Round 0: a vertex of every validator, the same instances in all DAGs
+    pub fn add_genesis(&mut self) -> Vec<VertexPtr> {
+        let genesis = genesis(self.proc_num);
+        genesis.iter().for_each(|v| self.add_vertex(v.clone()));
+        genesis
+    }
+
+    // Vertices which are not added yet. Removed from the buffer by add_vertex
+    pub fn buffer(&mut self, v: VertexPtr) {
+        self.see(v.round);
+        let round = v.round;
+        let size = v.size();
+        if self.buffer.insert(v) {
+            self.buffered_bytes += size;
+            memory::report("dag_buffer", self.buffered_bytes);
+            let buffered = self.buffer.iter().filter(|u| u.round == round).count();
+            round_metrics::record(round, |stats| {
+                stats.peak_buffered = stats.peak_buffered.max(buffered)
+            });
+        }
+    }
+
+    // Sorted by round
+    pub fn buffered(&self) -> Vec<VertexPtr> {
+        self.buffer.iter().cloned().collect()
     }
 
     // v should be already in the DAG
-    // "in some deterministic order"
+    // "in some deterministic order": the one of the configured tie break rule
     pub fn order_from(&mut self, v: &VertexPtr) {
+        failover::record_anchor(v.round);
+        self.last_ordered_round = self.last_ordered_round.max(v.round);
+        let mut committed = Vec::new();
         let mut queue = VecDeque::new();
         queue.push_back(v.clone());
 
-        while queue.len() > 0 {
-            let curr = queue.pop_front().unwrap();
-
-            let strong_edges: Vec<VertexPtr> = curr
-                .strong_edges
-                .iter()
-                .map(|weak| weak.upgrade().unwrap())
-                .collect();
-
-            for edge in strong_edges.into_iter() {
+        while let Some(curr) = queue.pop_front() {
+            for edge in curr.strong_edges.iter().map(|weak| weak.upgrade().unwrap()) {
                 let real_round = self.round(edge.round);
-                if self.ordered[real_round][edge.source] {
-                    continue;
-                } else {
+                if !self.ordered[real_round][edge.source] {
                     self.ordered[real_round][edge.source] = true;
-                    if rank() == edge.source {
-                        anykv::modify::<(f64, usize)>(
-                            "avg_latency",
-                            |(prev_avg_latency, prev_total_ordered)| {
-                                let vertex_latency = now() - edge.creation_time;
-                                *prev_avg_latency = (vertex_latency.0 as f64
-                                    + (*prev_avg_latency * *prev_total_ordered as f64))
-                                    as f64
-                                    / (*prev_total_ordered + 1) as f64;
-
-                                *prev_total_ordered += 1;
-                            },
-                        );
-                    }
+                    committed.push(edge.clone());
                     queue.push_back(edge);
                 }
             }
         }
+
+        self.tie_break.arrange(&mut committed, |v| OrderedVertex {
+            round: v.round,
+            source: v.source,
+        });
+
+        for v in committed {
+            let ordered = OrderedVertex {
+                round: v.round,
+                source: v.source,
+            };
+            let executed_at = self.sink.push(ordered, &v.batch);
+            if rank() == v.source {
+                record_commit(&v.batch, executed_at);
+                chain_quality::record_ordered(v.source);
+                anykv::modify::<(f64, usize)>(
+                    "avg_latency",
+                    |(prev_avg_latency, prev_total_ordered)| {
+                        let vertex_latency = now() - v.creation_time;
+                        *prev_avg_latency = (vertex_latency.0 as f64
+                            + (*prev_avg_latency * *prev_total_ordered as f64))
+                            as f64
+                            / (*prev_total_ordered + 1) as f64;
+
+                        *prev_total_ordered += 1;
+                    },
+                );
+            }
+        }
         self.gc();
     }
 
@@ -189,6 +278,7 @@ impl RoundBasedDAG {
     }
 
     pub fn add_vertex(&mut self, v: VertexPtr) {
+        record_seen(&v.batch, v.source);
         if self.current_allocated_rounds() > v.round {
             self.insert(v);
         } else {
@@ -205,6 +295,20 @@ impl RoundBasedDAG {
     pub fn current_max_allocated_round(&self) -> usize {
         self.current_allocated_rounds().saturating_sub(1)
     }
+
+    pub fn ordered_sink(&mut self) -> &mut OrderedSink {
+        &mut self.sink
+    }
+
+    // Counts transactions of vertices above the last ordered anchor, which is all but
+    // vertices no anchor will ever reach
+    pub fn execution_lag(&self) -> Jiffies {
+        let unordered = (self.last_ordered_round + 1..self.current_allocated_rounds())
+            .flat_map(|round| self[round].iter().flatten())
+            .map(|v| v.batch.transactions)
+            .sum();
+        self.sink.execution().lag(unordered)
+    }
 }
 
 impl RoundBasedDAG {
@@ -231,17 +335,54 @@ impl RoundBasedDAG {
     fn gc(&mut self) {
         let to_gc = self.ordered.len().saturating_sub(GC_REMAIN);
         (0..to_gc).for_each(|_| {
-            self.matrix.pop_front();
+            let round = self.matrix.pop_front().unwrap_or_default();
+            self.stored_bytes -= round.iter().flatten().map(|v| v.size()).sum::<usize>();
             self.visited.pop_front();
             self.ordered.pop_front();
         });
         self.gc_offset += to_gc;
+        if to_gc > 0 {
+            memory::report("dag", self.stored_bytes);
+        }
     }
 
     fn insert(&mut self, v: VertexPtr) {
-        let round = self.round(v.round);
+        self.see(v.round);
+        if self.buffer.remove(&v) {
+            self.buffered_bytes -= v.size();
+            memory::report("dag_buffer", self.buffered_bytes);
+        }
+        let real_round = self.round(v.round);
         let source = v.source;
-        self.matrix[round][source] = Some(v);
+        let added = self.matrix[real_round][source].is_none();
+        self.matrix[real_round][source] = Some(v.clone());
+        if !added {
+            return;
+        }
+        self.stored_bytes += v.size();
+        memory::report("dag", self.stored_bytes);
+        if rank() == v.source {
+            chain_quality::record_created(v.source);
+        }
+
+        let vertices = self.matrix[real_round].iter().flatten().count();
+        let reached_quorum = vertices == self.quorum_size();
+        let time_to_quorum = (now() - self.first_seen[&v.round]).0 as f64;
+        round_metrics::record(v.round, |stats| {
+            stats.vertices += 1;
+            if reached_quorum {
+                stats.quorum_reached += 1;
+                stats.total_time_to_quorum += time_to_quorum;
+            }
+        });
+    }
+
+    fn see(&mut self, round: usize) {
+        self.first_seen.entry(round).or_insert(now());
+    }
+
+    fn quorum_size(&self) -> usize {
+        2 * ((self.proc_num - 1) / 3) + 1
     }
 
     fn reset_visited(&mut self) {
@@ -0,0 +1,203 @@
+// Leader failover latency: repeatedly crashes the validator which is about to
+// lead and measures the time until the next anchor is committed anywhere.
+// Crashed validators stay down (the protocols have no state transfer for a
+// restarted validator), so a run crashes at most f leaders; more samples come
+// from more seeds.
+
+use std::collections::BTreeSet;
+
+use dscale::{
+    Distributions, LatencyDescription, ProcessHandle, ProcessId, SimulationBuilder, global::anykv,
+    now, time::Jiffies,
+};
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use rayon::prelude::*;
+
+use crate::{
+    bullshark::Bullshark,
+    leaders::LeaderElection,
+    rider::DAGRider,
+    round_metrics::{ROUND_METRICS, RoundMetrics},
+    sparse_bullshark::SparseBullshark,
+    statistics::Summary,
+};
+
+// Highest anchor round committed by any validator
+pub const COMMITTED_ANCHOR: &str = "committed_anchor";
+
+const POOL_NAME: &str = "Validators";
+
+// Protocols committing anchors of leaders elected per round
+pub trait Anchored {
+    // Leader election slot of the round, None if the round has no anchor
+    fn anchor_slot(round: usize) -> Option<usize>;
+}
+
+impl Anchored for Bullshark {
+    fn anchor_slot(round: usize) -> Option<usize> {
+        round.is_multiple_of(2).then_some(round / 2)
+    }
+}
+
+impl Anchored for SparseBullshark {
+    fn anchor_slot(round: usize) -> Option<usize> {
+        round.is_multiple_of(2).then_some(round / 2)
+    }
+}
+
+impl Anchored for DAGRider {
+    fn anchor_slot(round: usize) -> Option<usize> {
+        (round % 4 == 1).then_some(round / 4)
+    }
+}
+
+#[derive(Clone)]
+pub struct FailoverExperiment {
+    pub validators: usize,
+    pub latency: Distributions,
+    pub warmup: Jiffies,         // Failure-free prefix of every run
+    pub gap: (Jiffies, Jiffies), // Uniformly random time before each crash
+    pub crashes: usize,          // Per run, at most f
+    pub patience: Jiffies,       // Slower failovers are reported as stalled
+    pub seeds: Vec<u64>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Failover {
+    pub seed: u64,
+    pub leader: ProcessId,
+    pub round: usize, // Anchor round of the crashed leader
+    pub crashed_at: Jiffies,
+    pub latency: Option<Jiffies>, // Until an anchor of this round or later is committed
+}
+
+#[derive(Clone, Debug)]
+pub struct FailoverLatency {
+    pub summary: Summary,
+    pub p50: f64,
+    pub p99: f64,
+    pub max: f64,
+    pub stalled: usize,
+}
+
+impl FailoverLatency {
+    pub fn of(failovers: &[Failover]) -> Self {
+        let mut samples: Vec<f64> = failovers
+            .iter()
+            .filter_map(|failover| failover.latency)
+            .map(|latency| latency.0 as f64)
+            .collect();
+        samples.sort_by(f64::total_cmp);
+        Self {
+            summary: Summary::of(&samples),
+            p50: percentile(&samples, 0.5),
+            p99: percentile(&samples, 0.99),
+            max: samples.last().copied().unwrap_or(0.0),
+            stalled: failovers.len() - samples.len(),
+        }
+    }
+}
+
+// Nearest rank, samples should be sorted
+fn percentile(samples: &[f64], p: f64) -> f64 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    let rank = (p * samples.len() as f64).ceil() as usize;
+    samples[rank.clamp(1, samples.len()) - 1]
+}
+
+// configure sets protocol specific anykv keys before processes are created.
+// Runs of different seeds are independent, failovers are returned in seeds order
+pub fn run<P: ProcessHandle + Anchored + Default + 'static>(
+    experiment: &FailoverExperiment,
+    configure: impl Fn() + Sync,
+) -> Vec<Failover> {
+    assert!(
+        experiment.crashes <= (experiment.validators - 1) / 3,
+        "At most f validators may crash"
+    );
+    experiment
+        .seeds
+        .par_iter()
+        .flat_map_iter(|seed| run_seed::<P>(experiment, &configure, *seed))
+        .collect()
+}
+
+fn run_seed<P: ProcessHandle + Anchored + Default + 'static>(
+    experiment: &FailoverExperiment,
+    configure: &impl Fn(),
+    seed: u64,
+) -> Vec<Failover> {
+    anykv::set::<(f64, usize)>("avg_latency", (0.0, 0));
+    anykv::set::<usize>(COMMITTED_ANCHOR, 0);
+    configure();
+
+    let time_budget = experiment.warmup
+        + Jiffies((experiment.gap.1.0 + experiment.patience.0) * (experiment.crashes + 1));
+    let mut sim = SimulationBuilder::default()
+        .add_pool::<P>(POOL_NAME, experiment.validators)
+        .latency_topology(&[LatencyDescription::WithinPool(
+            POOL_NAME,
+            experiment.latency,
+        )])
+        .time_budget(time_budget)
+        .seed(seed)
+        .build();
+
+    let leaders = LeaderElection::configured(experiment.validators);
+    let mut random = StdRng::seed_from_u64(seed);
+    let mut crashed = BTreeSet::new();
+    let mut failovers = Vec::new();
+
+    sim.step_until(experiment.warmup);
+    for _ in 0..experiment.crashes {
+        let gap = random.random_range(experiment.gap.0.0..=experiment.gap.1.0);
+        sim.step_until(now() + Jiffies(gap));
+
+        // Anchor of the highest round is not committed yet: it needs votes of the next one
+        let highest = anykv::get::<RoundMetrics>(ROUND_METRICS)
+            .rounds
+            .last_key_value()
+            .map_or(0, |(round, _)| *round);
+        let (round, leader) = (highest..)
+            .filter_map(|round| {
+                P::anchor_slot(round).map(|slot| (round, leaders.leader(round, slot)))
+            })
+            .find(|(_, leader)| !crashed.contains(leader))
+            .expect("Some leader should be alive");
+
+        sim.crash(leader);
+        crashed.insert(leader);
+        let crashed_at = now();
+
+        let deadline = crashed_at + experiment.patience;
+        while anykv::get::<usize>(COMMITTED_ANCHOR) < round && now() < deadline {
+            if sim.step_n(1) == 0 {
+                break;
+            }
+        }
+        let latency = (anykv::get::<usize>(COMMITTED_ANCHOR) >= round).then(|| now() - crashed_at);
+
+        failovers.push(Failover {
+            seed,
+            leader,
+            round,
+            crashed_at,
+            latency,
+        });
+    }
+    failovers
+}
+
+pub(crate) fn register() {
+    if anykv::try_get::<usize>(COMMITTED_ANCHOR).is_none() {
+        anykv::set::<usize>(COMMITTED_ANCHOR, 0);
+    }
+}
+
+pub(crate) fn record_anchor(round: usize) {
+    anykv::modify::<usize>(COMMITTED_ANCHOR, |committed| {
+        *committed = (*committed).max(round)
+    });
+}
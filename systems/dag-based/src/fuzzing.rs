@@ -0,0 +1,126 @@
+// Decoding of fuzzed messages for DAG protocols, enabled with the "fuzz" feature.
+// Validators get consistent broadcast messages with arbitrary rounds, sources and
+// ids. Vertices stay structurally plausible: their edges point at genesis or at
+// previously decoded vertices one round below, kept alive by the decoder like
+// they would be by other validators.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use arbitrary::Arbitrary;
+use dscale::{
+    Distributions, LatencyDescription, ProcessHandle, ProcessId, SimulationBuilder,
+    crypto::VrfProof,
+    global::anykv,
+    helpers::HandlerFuzzer,
+    message::{Shared, SharedMessage},
+    now,
+    time::Jiffies,
+};
+
+use crate::{
+    consistent_broadcast::{BCBMessage, BCBMessageId},
+    dag_utils::{self, Vertex, VertexMessage, VertexPtr},
+    workload::Batch,
+};
+
+const POOL_NAME: &str = "Validators";
+const VALIDATORS: usize = 4;
+
+#[derive(Arbitrary, Debug)]
+enum BroadcastInput {
+    Initiate(u8, VertexInput), // Message id
+    Signature(u8, u8),         // Author and message id
+    Certificate(u8, u8, u8),   // Signers, author and message id
+}
+
+#[derive(Arbitrary, Debug)]
+struct VertexInput {
+    round: u8,
+    source: u8,
+    parents: BTreeSet<u8>, // Sources of vertices one round below
+    transactions: u8,
+    leader_proof: bool,
+}
+
+struct Decoder {
+    vertices: BTreeMap<(usize, ProcessId), VertexPtr>, // Latest by round and source
+}
+
+impl Decoder {
+    fn new() -> Self {
+        Self {
+            vertices: dag_utils::genesis(VALIDATORS)
+                .into_iter()
+                .map(|v| ((v.round, v.source), v))
+                .collect(),
+        }
+    }
+
+    fn decode(&mut self, from: ProcessId, input: BroadcastInput) -> SharedMessage {
+        match input {
+            BroadcastInput::Initiate(id, vertex) => {
+                let vertex: SharedMessage =
+                    Shared::new(VertexMessage::Vertex(self.decode_vertex(vertex)));
+                Shared::new(BCBMessage::Initiate((
+                    BCBMessageId::new(from, id as usize),
+                    vertex,
+                )))
+            }
+            BroadcastInput::Signature(author, id) => Shared::new(BCBMessage::Signature(
+                BCBMessageId::new(author as ProcessId, id as usize),
+            )),
+            BroadcastInput::Certificate(signers, author, id) => {
+                Shared::new(BCBMessage::Certificate(
+                    signers as usize,
+                    BCBMessageId::new(author as ProcessId, id as usize),
+                ))
+            }
+        }
+    }
+
+    fn decode_vertex(&mut self, input: VertexInput) -> VertexPtr {
+        let round = input.round as usize;
+        let source = input.source as ProcessId;
+        let strong_edges = match round {
+            0 => Vec::new(),
+            _ => input
+                .parents
+                .iter()
+                .filter_map(|parent| self.vertices.get(&(round - 1, *parent as ProcessId)))
+                .map(Shared::downgrade)
+                .collect(),
+        };
+        let mut batch = Batch::default();
+        batch.transactions = input.transactions as usize;
+        let vertex = VertexPtr::new(Vertex {
+            round,
+            source,
+            strong_edges,
+            creation_time: now(),
+            batch,
+            // Keys exist only for validators
+            leader_proof: (input.leader_proof && (1..=VALIDATORS).contains(&source))
+                .then(|| VrfProof::of(source, round as u64)),
+        });
+        self.vertices.insert((round, source), vertex.clone());
+        vertex
+    }
+}
+
+// Feeds validators of protocol P with messages decoded from fuzzer input,
+// returns the number of delivered messages
+pub fn fuzz<P: ProcessHandle + Default + 'static>(data: &[u8]) -> usize {
+    anykv::set::<(f64, usize)>("avg_latency", (0.0, 0));
+    anykv::set::<(f64, usize)>("avg_virtual_size", (0.0, 0));
+    anykv::set::<usize>("D", VALIDATORS / 2); // Sample size of SparseBullshark
+
+    let builder = SimulationBuilder::default()
+        .add_pool::<P>(POOL_NAME, VALIDATORS)
+        .latency_topology(&[LatencyDescription::WithinPool(
+            POOL_NAME,
+            Distributions::Uniform(Jiffies(1), Jiffies(10)),
+        )]);
+    let mut fuzzer = HandlerFuzzer::new(builder);
+    let mut decoder = Decoder::new();
+    fuzzer.fuzz(data, |from, input| Some(decoder.decode(from, input)))
+}
@@ -0,0 +1,76 @@
+use std::{cell::RefCell, collections::HashMap};
+
+use dscale::{crypto::VrfProof, global::anykv, helpers::LeaderSchedule, *};
+
+// Round leaders (anchors): round robin over all validators, unless a leader schedule is configured.
+// All validators must agree on the leader of a round, so a time-dependent schedule is evaluated
+// at the nominal round time round * "round_duration" (anykv) instead of local now().
+//
+// With "vrf_leaders" (anykv) set, the leader of a round is the validator with the lowest VRF
+// output on the round, unknown in advance to anyone without the keys (adaptive adversary).
+// Vertices carry proofs of their authors, validators verify them on receive.
+pub struct LeaderElection {
+    proc_num: usize,
+    schedule: Option<LeaderSchedule>,
+    round_duration: Jiffies,
+    vrf: bool,
+    elected: RefCell<HashMap<usize, ProcessId>>, // Round -> VRF leader
+}
+
+impl Default for LeaderElection {
+    fn default() -> Self {
+        Self {
+            proc_num: 1,
+            schedule: None,
+            round_duration: Jiffies(0),
+            vrf: false,
+            elected: RefCell::default(),
+        }
+    }
+}
+
+impl LeaderElection {
+    pub fn configured(proc_num: usize) -> Self {
+        let schedule = LeaderSchedule::configured();
+        let round_duration = match &schedule {
+            Some(schedule) if schedule.is_time_dependent() => anykv::try_get::<Jiffies>(
+                "round_duration",
+            )
+            .expect("round_duration should be configured for time-dependent leader schedule"),
+            _ => Jiffies(0),
+        };
+        let vrf = anykv::try_get::<bool>("vrf_leaders").unwrap_or(false);
+        assert!(
+            !(vrf && schedule.is_some()),
+            "VRF leader election and leader schedule are exclusive"
+        );
+        Self {
+            proc_num,
+            schedule,
+            round_duration,
+            vrf,
+            elected: RefCell::default(),
+        }
+    }
+
+    // Slot numbers rounds which have leaders, so rotation covers all scheduled leaders
+    pub fn leader(&self, round: usize, slot: usize) -> ProcessId {
+        if self.vrf {
+            return *self.elected.borrow_mut().entry(round).or_insert_with(|| {
+                // Stands for the proofs of all validators, which come with their vertices
+                (1..=self.proc_num)
+                    .min_by_key(|validator| VrfProof::of(*validator, round as u64).output)
+                    .expect("Should be at least one validator")
+            });
+        }
+        match &self.schedule {
+            None => round % self.proc_num + 1,
+            Some(schedule) => schedule.leader(Jiffies(round * self.round_duration), slot),
+        }
+    }
+
+    // Proof attached to own vertex of the round
+    pub fn proof(&self, round: usize) -> Option<VrfProof> {
+        self.vrf.then(|| VrfProof::prove(round as u64))
+    }
+}
@@ -1,7 +1,23 @@
 #![allow(non_snake_case)]
 
+pub mod adaptive_d;
 pub mod bullshark;
-pub(crate) mod consistent_broadcast;
+pub mod chain_quality;
+pub(crate) mod common_coin;
+pub mod comparison;
+pub mod consistent_broadcast;
 pub(crate) mod dag_utils;
+pub mod failover;
+#[cfg(feature = "fuzz")]
+pub mod fuzzing;
+pub(crate) mod leaders;
+pub mod ordered_sink;
 pub mod rider;
+pub mod round_metrics;
+pub mod sampling;
+pub mod shoal;
 pub mod sparse_bullshark;
+pub mod statistics;
+pub mod sweep;
+pub mod validation;
+pub mod workload;
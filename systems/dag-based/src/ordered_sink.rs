@@ -0,0 +1,99 @@
+use std::{
+    collections::VecDeque,
+    hash::{DefaultHasher, Hash, Hasher},
+};
+
+use dscale::{Jiffies, ProcessId, global::anykv, stack::Layer};
+
+use crate::workload::{Batch, Execution};
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct OrderedVertex {
+    pub round: usize,
+    pub source: ProcessId,
+}
+
+// Order of the vertices committed by one anchor (its causal history not ordered before).
+// Any rule is safe as long as all validators use the same one, which is configured
+// through anykv under "tie_break", Traversal if absent.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum TieBreak {
+    #[default]
+    Traversal, // BFS from the anchor, strong edges in the order their author listed them
+    Source,          // By source, then by round
+    RoundThenSource, // Older rounds first, a round by source
+    Digest,          // By hash of the vertex identity: no source is always first
+}
+
+impl TieBreak {
+    pub fn configured() -> Self {
+        anykv::try_get::<TieBreak>("tie_break").unwrap_or_default()
+    }
+
+    // Vertices come in traversal order
+    pub fn arrange<T>(&self, vertices: &mut [T], identity: impl Fn(&T) -> OrderedVertex) {
+        match self {
+            TieBreak::Traversal => {}
+            TieBreak::Source => vertices.sort_by_key(|v| {
+                let v = identity(v);
+                (v.source, v.round)
+            }),
+            TieBreak::RoundThenSource => vertices.sort_by_key(|v| {
+                let v = identity(v);
+                (v.round, v.source)
+            }),
+            TieBreak::Digest => vertices.sort_by_cached_key(|v| {
+                let v = identity(v);
+                let mut hasher = DefaultHasher::new();
+                v.hash(&mut hasher);
+                (hasher.finish(), v.round, v.source)
+            }),
+        }
+    }
+}
+
+// Committed sequence produced by the consensus engine, executed by the validator.
+// Detached by default: large experiments never read it and should not pay for buffering.
+#[derive(Default)]
+pub struct OrderedSink {
+    attached: bool,
+    pending: VecDeque<OrderedVertex>,
+    total_ordered: usize,
+    execution: Execution,
+}
+
+impl OrderedSink {
+    pub fn attach(&mut self) {
+        self.attached = true;
+    }
+
+    pub fn drain(&mut self) -> impl Iterator<Item = OrderedVertex> + '_ {
+        self.pending.drain(..)
+    }
+
+    pub fn total_ordered(&self) -> usize {
+        self.total_ordered
+    }
+
+    pub fn execution(&self) -> &Execution {
+        &self.execution
+    }
+
+    pub(crate) fn set_execution(&mut self, execution: Execution) {
+        self.execution = execution;
+    }
+
+    // Returns when transactions of the vertex are executed
+    pub(crate) fn push(&mut self, v: OrderedVertex, batch: &Batch) -> Jiffies {
+        self.total_ordered += 1;
+        if self.attached {
+            self.pending.push_back(v);
+        }
+        self.execution.execute(batch)
+    }
+}
+
+// Any consensus layer which exposes its committed sequence
+pub trait OrderingEngine: Layer {
+    fn ordered_sink(&mut self) -> &mut OrderedSink;
+}
@@ -1,85 +1,111 @@
 // https://arxiv.org/pdf/2102.08325
 
-use std::{
-    collections::BTreeSet,
-    rc::{Rc, Weak},
+use dscale::{
+    global::configuration,
+    helpers::RoundProtocol,
+    message::{Shared, WeakShared},
+    stack::{Layer, LayerContext, Stack, StackDefinition, Stacked},
+    *,
 };
 
-use dscale::{global::configuration, *};
-
 use crate::{
-    consistent_broadcast::{BCBMessage, ByzantineConsistentBroadcast},
+    consistent_broadcast::ByzantineConsistentBroadcast,
     dag_utils::{RoundBasedDAG, Vertex, VertexMessage, VertexPtr, same_vertex},
+    leaders::LeaderElection,
+    ordered_sink::{OrderedSink, OrderingEngine},
+    validation::{CryptoCost, QuorumEdgesValidator, VerificationQueue},
+    workload::Mempool,
 };
 
 const CONSTRUCTING_ROUTINE_INTERVAL: Jiffies = Jiffies(500);
 
 #[derive(Default)]
-pub struct DAGRider {
-    rbcast: ByzantineConsistentBroadcast,
+pub struct DAGRiderLayer {
+    outbox: Vec<VertexMessage>, // Submitted to the broadcast layer once the handler returns
+    validator: Option<VerificationQueue<QuorumEdgesValidator>>,
+    mempool: Mempool,
+    leaders: LeaderElection,
     self_id: ProcessId,
     proc_num: usize,
     dag: RoundBasedDAG,
-    round: usize,
-    buffer: BTreeSet<VertexPtr>,
+    rounds: Option<RoundProtocol>,
     decided_wave: usize,
     leaders_stack: Vec<VertexPtr>,
 }
 
-impl ProcessHandle for DAGRider {
-    fn start(&mut self) {
+impl Layer for DAGRiderLayer {
+    fn start(&mut self, context: &mut LayerContext) {
+        self.on_start();
+        self.flush(context);
+    }
+
+    fn on_deliver(&mut self, from: ProcessId, message: MessagePtr, context: &mut LayerContext) {
+        self.on_vertex_message(from, message);
+        self.flush(context);
+    }
+
+    fn on_timer(&mut self, id: TimerId, context: &mut LayerContext) {
+        self.on_timer_fired(id);
+        self.flush(context);
+    }
+}
+
+// Handlers
+impl DAGRiderLayer {
+    fn flush(&mut self, context: &mut LayerContext) {
+        self.outbox
+            .drain(..)
+            .for_each(|message| context.submit(message));
+    }
+
+    fn on_start(&mut self) {
         self.self_id = rank();
         self.proc_num = configuration::process_number();
         self.dag.set_round_size(configuration::process_number());
-        self.rbcast.start(configuration::process_number());
+        self.rounds = Some(RoundProtocol::new(self.quorum_size()));
+        self.validator = Some(VerificationQueue::new(QuorumEdgesValidator {
+            quorum_size: self.quorum_size(),
+            cost: CryptoCost::configured(),
+        }));
+        self.mempool = Mempool::configured();
+        self.leaders = LeaderElection::configured(self.proc_num);
 
         schedule_timer_after(CONSTRUCTING_ROUTINE_INTERVAL);
 
         // Shared genesis vertices
-        let genesis_vertex = VertexPtr::new(Vertex {
-            round: 0,
-            source: self.self_id,
-            strong_edges: Vec::new(),
-            creation_time: now(),
-        });
-
-        self.dag.add_vertex(genesis_vertex.clone());
-
-        self.rbcast
-            .reliably_broadcast(VertexMessage::Genesis(genesis_vertex));
+        for v in self.dag.add_genesis() {
+            self.rounds().record(v.round, v.source);
+        }
     }
 
-    fn on_message(&mut self, from: ProcessId, message: MessagePtr) {
-        if let Some(bs_message) = self.rbcast.process(from, message.as_type::<BCBMessage>()) {
-            match bs_message.as_type::<VertexMessage>().as_ref() {
-                VertexMessage::Genesis(v) => {
-                    debug_assert!(v.round == 0);
-                    self.dag.add_vertex(v.clone());
-                    return;
-                }
-
-                VertexMessage::Vertex(v) => {
-                    if self.bad_vertex(&v, from) {
-                        return;
-                    }
-                    self.buffer.insert(v.clone());
+    fn on_vertex_message(&mut self, from: ProcessId, message: MessagePtr) {
+        match message.as_type::<VertexMessage>().as_ref() {
+            VertexMessage::Vertex(v) => {
+                if let Some(v) = self.validator().submit(from, v.clone()) {
+                    self.dag.buffer(v);
                 }
             }
         }
     }
 
-    fn on_timer(&mut self, _id: TimerId) {
+    fn on_timer_fired(&mut self, id: TimerId) {
+        if let Some(v) = self.validator().on_timer(id) {
+            self.dag.buffer(v);
+            return;
+        }
+
         self.construct();
     }
 }
 
-impl DAGRider {
+impl DAGRiderLayer {
     fn construct(&mut self) {
+        let round = self.rounds().round();
         let ready_to_be_added = self
-            .buffer
-            .iter()
-            .cloned()
-            .filter(|v| v.round <= self.round)
+            .dag
+            .buffered()
+            .into_iter()
+            .filter(|v| v.round <= round)
             .filter(|v| {
                 v.strong_edges
                     .iter()
@@ -91,10 +117,9 @@ impl DAGRider {
             })
             .collect::<Vec<VertexPtr>>();
 
-        self.buffer.retain(|v| !ready_to_be_added.contains(v));
-
         ready_to_be_added.into_iter().for_each(|v| {
             self.dag.add_vertex(v.clone());
+            self.rounds().record(v.round, v.source);
         });
 
         self.try_advance_round();
@@ -102,20 +127,21 @@ impl DAGRider {
     }
 
     fn try_advance_round(&mut self) {
-        if self.quorum_reached_for_round(self.round) {
-            if self.round % 4 == 0 && self.round != 0 {
-                self.wave_ready(self.round / 4);
+        let completed = self.rounds().round();
+        if let Some(round) = self.rounds().advance() {
+            if completed.is_multiple_of(4) && completed != 0 {
+                self.wave_ready(completed / 4);
             }
-            self.round += 1;
-            let v = self.create_vertex(self.round);
+            let v = self.create_vertex(round);
             self.dag.add_vertex(v.clone());
-            self.rbcast.reliably_broadcast(VertexMessage::Vertex(v));
+            self.rounds().record(v.round, v.source);
+            self.outbox.push(VertexMessage::Vertex(v));
         }
     }
 }
 
 // Utils
-impl DAGRider {
+impl DAGRiderLayer {
     fn adversary_threshold(&self) -> usize {
         (self.proc_num - 1) / 3
     }
@@ -124,15 +150,11 @@ impl DAGRider {
         2 * self.adversary_threshold() + 1
     }
 
-    fn non_none_vertices_count_for_round(&self, round: usize) -> usize {
-        self.dag[round].iter().flatten().count()
-    }
-
-    fn quorum_reached_for_round(&self, round: usize) -> bool {
-        self.non_none_vertices_count_for_round(round) >= self.quorum_size()
+    fn rounds(&mut self) -> &mut RoundProtocol {
+        self.rounds.as_mut().expect("Rounds not initialized")
     }
 
-    fn create_vertex(&self, round: usize) -> VertexPtr {
+    fn create_vertex(&mut self, round: usize) -> VertexPtr {
         VertexPtr::new(Vertex {
             round,
             source: self.self_id,
@@ -140,18 +162,20 @@ impl DAGRider {
                 .iter()
                 .flatten()
                 .cloned()
-                .map(|strong| Rc::downgrade(&strong))
-                .collect::<Vec<Weak<Vertex>>>(),
+                .map(|strong| Shared::downgrade(&strong))
+                .collect::<Vec<WeakShared<Vertex>>>(),
             creation_time: now(),
+            batch: self.mempool.drain(&self.dag),
+            leader_proof: self.leaders.proof(round),
         })
     }
 
-    fn bad_vertex(&self, v: &VertexPtr, from: ProcessId) -> bool {
-        v.strong_edges.len() < self.quorum_size() || from != v.source
+    fn validator(&mut self) -> &mut VerificationQueue<QuorumEdgesValidator> {
+        self.validator.as_mut().expect("Validator not initialized")
     }
 
     fn get_leader_id(&self, round: usize) -> ProcessId {
-        return round % self.proc_num + 1;
+        self.leaders.leader(round, round / 4)
     }
 
     fn round(&self, w: usize, k: usize) -> usize {
@@ -166,7 +190,7 @@ impl DAGRider {
 }
 
 // Consensus logic
-impl DAGRider {
+impl DAGRiderLayer {
     fn wave_ready(&mut self, w: usize) {
         let mut leader = match self.get_wave_vertex_leader(w) {
             None => return,
@@ -207,3 +231,22 @@ impl DAGRider {
         }
     }
 }
+
+impl OrderingEngine for DAGRiderLayer {
+    fn ordered_sink(&mut self) -> &mut OrderedSink {
+        self.dag.ordered_sink()
+    }
+}
+
+// Validator process: DAG-Rider on top of consistent broadcast
+pub struct DAGRiderStack;
+
+impl StackDefinition for DAGRiderStack {
+    fn stack() -> Stack {
+        Stack::new()
+            .layer(ByzantineConsistentBroadcast::default())
+            .layer(DAGRiderLayer::default())
+    }
+}
+
+pub type DAGRider = Stacked<DAGRiderStack>;
@@ -0,0 +1,87 @@
+// Per-round completeness of the DAG. Every RoundBasedDAG reports into anykv under
+// "round_metrics" on its own, protocols need no instrumentation. The key is
+// created by the first DAG of the run, a value set beforehand is kept.
+
+use std::collections::BTreeMap;
+
+use dscale::global::anykv;
+
+pub const ROUND_METRICS: &str = "round_metrics";
+
+// Summed over all processes
+#[derive(Clone, Default, Debug)]
+pub struct RoundStats {
+    pub vertices: usize,           // Vertices added to the DAGs
+    pub quorum_reached: usize,     // Processes which added a quorum of vertices
+    pub total_time_to_quorum: f64, // Since the first vertex of the round was seen by the process
+    pub peak_buffered: usize,      // Max vertices of the round buffered at a single process
+}
+
+#[derive(Clone, Default, Debug)]
+pub struct RoundMetrics {
+    pub processes: usize,
+    pub rounds: BTreeMap<usize, RoundStats>,
+}
+
+impl RoundMetrics {
+    // Share of the n * n vertices of the round present in the DAGs
+    pub fn completeness(&self, round: usize) -> f64 {
+        let vertices = self.rounds.get(&round).map_or(0, |stats| stats.vertices);
+        vertices as f64 / (self.processes * self.processes) as f64
+    }
+
+    pub fn avg_time_to_quorum(&self, round: usize) -> Option<f64> {
+        self.rounds
+            .get(&round)
+            .filter(|stats| stats.quorum_reached > 0)
+            .map(|stats| stats.total_time_to_quorum / stats.quorum_reached as f64)
+    }
+
+    // Averages over rounds, genesis excluded
+    pub fn avg_completeness(&self) -> f64 {
+        self.average(|round| Some(self.completeness(round)))
+    }
+
+    pub fn avg_time_to_quorum_overall(&self) -> f64 {
+        self.average(|round| self.avg_time_to_quorum(round))
+    }
+
+    pub fn max_buffered(&self) -> usize {
+        self.rounds
+            .values()
+            .map(|stats| stats.peak_buffered)
+            .max()
+            .unwrap_or(0)
+    }
+
+    fn average(&self, metric: impl Fn(usize) -> Option<f64>) -> f64 {
+        let values: Vec<f64> = self
+            .rounds
+            .keys()
+            .filter(|round| **round > 0)
+            .filter_map(|round| metric(*round))
+            .collect();
+        if values.is_empty() {
+            return 0.0;
+        }
+        values.iter().sum::<f64>() / values.len() as f64
+    }
+}
+
+pub(crate) fn register(processes: usize) {
+    if anykv::try_get::<RoundMetrics>(ROUND_METRICS).is_none() {
+        anykv::set::<RoundMetrics>(
+            ROUND_METRICS,
+            RoundMetrics {
+                processes,
+                rounds: BTreeMap::new(),
+            },
+        );
+    }
+}
+
+pub(crate) fn record(round: usize, f: impl FnOnce(&mut RoundStats)) {
+    anykv::modify::<RoundMetrics>(ROUND_METRICS, |metrics| {
+        f(metrics.rounds.entry(round).or_default())
+    });
+}
@@ -0,0 +1,76 @@
+use rand::{SeedableRng, prelude::IndexedRandom, rngs::StdRng};
+
+use dscale::{
+    global::{anykv, configuration},
+    *,
+};
+
+use crate::dag_utils::VertexPtr;
+
+// How SparseBullshark picks D parents out of the previous round, selected with
+// "sampling" (anykv). Weighted strategies draw without replacement, so a vertex
+// is picked at most once and every vertex has a chance.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Sampling {
+    #[default]
+    Uniform, // Every vertex is equally likely
+    LatencyProximity, // By inverse expected latency to the source: close vertices are fresher
+    StakeWeighted,    // By stake of the source, "stakes" (anykv): Vec<u64> of validators 1..=n
+}
+
+pub struct Sampler {
+    strategy: Sampling,
+    rng: StdRng,
+    weights: Vec<f64>, // Validator - 1 -> weight, empty for uniform sampling
+}
+
+impl Sampler {
+    pub fn configured(proc_num: usize) -> Self {
+        let strategy = anykv::try_get::<Sampling>("sampling").unwrap_or_default();
+        let weights = match strategy {
+            Sampling::Uniform => Vec::new(),
+            Sampling::LatencyProximity => {
+                let latencies: Vec<Option<f64>> = (1..=proc_num).map(expected_latency).collect();
+                // Unknown links count as the farthest known ones
+                let farthest = latencies.iter().flatten().copied().fold(0.0, f64::max);
+                latencies
+                    .into_iter()
+                    .map(|latency| 1.0 / (1.0 + latency.unwrap_or(farthest)))
+                    .collect()
+            }
+            Sampling::StakeWeighted => {
+                let stakes = anykv::try_get::<Vec<u64>>("stakes")
+                    .expect("stakes should be configured for stake-weighted sampling");
+                assert_eq!(
+                    stakes.len(),
+                    proc_num,
+                    "Every validator should have a stake"
+                );
+                assert!(
+                    stakes.iter().all(|stake| *stake > 0),
+                    "Stakes should be positive"
+                );
+                stakes.into_iter().map(|stake| stake as f64).collect()
+            }
+        };
+        Self {
+            strategy,
+            rng: StdRng::seed_from_u64(configuration::seed()),
+            weights,
+        }
+    }
+
+    pub fn sample(&mut self, candidates: &[VertexPtr], amount: usize) -> Vec<VertexPtr> {
+        match self.strategy {
+            Sampling::Uniform => candidates
+                .choose_multiple(&mut self.rng, amount)
+                .cloned()
+                .collect(),
+            Sampling::LatencyProximity | Sampling::StakeWeighted => candidates
+                .choose_multiple_weighted(&mut self.rng, amount, |v| self.weights[v.source - 1])
+                .expect("Sampling weights should be positive and finite")
+                .cloned()
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,386 @@
+// https://arxiv.org/pdf/2306.03058
+
+use std::collections::{BTreeSet, VecDeque};
+
+use dscale::{
+    global::{bootstrap, configuration},
+    helpers::RoundProtocol,
+    message::{Shared, WeakShared},
+    stack::{Layer, LayerContext, Stack, StackDefinition, Stacked},
+    *,
+};
+
+use crate::{
+    consistent_broadcast::ByzantineConsistentBroadcast,
+    dag_utils::{RoundBasedDAG, Vertex, VertexMessage, VertexPtr, same_vertex},
+    ordered_sink::{OrderedSink, OrderingEngine},
+    validation::{CryptoCost, QuorumEdgesValidator, VerificationQueue},
+    workload::Mempool,
+};
+
+// Committed anchors the reputation is computed over
+const REPUTATION_WINDOW: usize = 10;
+
+const ROUND_TIMEOUT: Jiffies = Jiffies(10000);
+
+// Leader reputation: validators whose vertices were linked by recently committed anchors
+// lead, the others (crashed, slow, far away) do not. All validators order the same anchors,
+// so they agree on the schedule of every instance.
+struct Reputation {
+    proc_num: usize,
+    window: VecDeque<BTreeSet<ProcessId>>, // Sources linked by the last committed anchors
+    leaders: Vec<ProcessId>,               // Rotation of the current instance
+}
+
+impl Reputation {
+    fn new(proc_num: usize) -> Self {
+        Self {
+            proc_num,
+            window: VecDeque::new(),
+            leaders: (1..=proc_num).collect(),
+        }
+    }
+
+    fn leader(&self, round: usize) -> ProcessId {
+        self.leaders[round % self.leaders.len()]
+    }
+
+    // The best 2f+1 validators by the number of anchors which linked their vertices
+    fn on_commit(&mut self, anchor: &VertexPtr) {
+        let linked = anchor
+            .strong_edges
+            .iter()
+            .map(|weak| weak.upgrade().unwrap().source)
+            .chain([anchor.source])
+            .collect();
+        self.window.push_back(linked);
+        if self.window.len() > REPUTATION_WINDOW {
+            self.window.pop_front();
+        }
+
+        let mut ranked: Vec<ProcessId> = (1..=self.proc_num).collect();
+        ranked.sort_by_key(|validator| {
+            let score = self
+                .window
+                .iter()
+                .filter(|linked| linked.contains(validator))
+                .count();
+            (usize::MAX - score, *validator)
+        });
+        ranked.truncate(2 * ((self.proc_num - 1) / 3) + 1);
+        ranked.sort();
+        self.leaders = ranked;
+    }
+}
+
+// Shoal: instances of Bullshark run back to back on the same DAG. Once an instance orders its
+// first anchor, the next one starts from the following round, so in the good case every round
+// has an anchor (pipelining). Leaders of every instance come from the reputation.
+pub struct ShoalLayer {
+    outbox: Vec<VertexMessage>, // Submitted to the broadcast layer once the handler returns
+    validator: Option<VerificationQueue<QuorumEdgesValidator>>,
+    mempool: Mempool,
+    reputation: Reputation,
+    self_id: ProcessId,
+    proc_num: usize,
+    dag: RoundBasedDAG,
+    rounds: Option<RoundProtocol>,
+    instance_start: usize, // First anchor round of the current instance
+    bootstrap: TimerId,    // Fires once all validators have started
+}
+
+impl Default for ShoalLayer {
+    fn default() -> Self {
+        Self {
+            outbox: Vec::new(),
+            validator: None,
+            mempool: Mempool::default(),
+            reputation: Reputation::new(1),
+            self_id: 0,
+            proc_num: 0,
+            dag: RoundBasedDAG::default(),
+            rounds: None,
+            instance_start: 2,
+            bootstrap: 0,
+        }
+    }
+}
+
+impl Layer for ShoalLayer {
+    fn start(&mut self, context: &mut LayerContext) {
+        self.on_start();
+        self.flush(context);
+    }
+
+    fn on_deliver(&mut self, from: ProcessId, message: MessagePtr, context: &mut LayerContext) {
+        self.on_vertex_message(from, message);
+        self.flush(context);
+    }
+
+    fn on_timer(&mut self, id: TimerId, context: &mut LayerContext) {
+        self.on_timer_fired(id);
+        self.flush(context);
+    }
+}
+
+// Handlers
+impl ShoalLayer {
+    fn flush(&mut self, context: &mut LayerContext) {
+        self.outbox
+            .drain(..)
+            .for_each(|message| context.submit(message));
+    }
+
+    fn on_start(&mut self) {
+        self.self_id = rank();
+        self.proc_num = configuration::process_number();
+        self.dag.set_round_size(configuration::process_number());
+        self.rounds = Some(RoundProtocol::new(self.quorum_size()).with_timeout(ROUND_TIMEOUT));
+        self.validator = Some(VerificationQueue::new(QuorumEdgesValidator {
+            quorum_size: self.quorum_size(),
+            cost: CryptoCost::configured(),
+        }));
+        self.mempool = Mempool::configured();
+        self.reputation = Reputation::new(self.proc_num);
+
+        // Shared genesis vertices, the first round starts once everyone has them
+        for v in self.dag.add_genesis() {
+            self.rounds().record(v.round, v.source);
+        }
+        self.bootstrap = bootstrap::await_all_started();
+    }
+
+    // DAG construction: part 1
+    fn on_vertex_message(&mut self, from: ProcessId, message: MessagePtr) {
+        match message.as_type::<VertexMessage>().as_ref() {
+            VertexMessage::Vertex(v) => {
+                debug_process!("Got vertex from: {from}");
+
+                // Validity check
+                if let Some(v) = self.validator().submit(from, v.clone()) {
+                    self.on_valid_vertex(v);
+                }
+            }
+        }
+    }
+
+    fn on_timer_fired(&mut self, id: TimerId) {
+        if let Some(v) = self.validator().on_timer(id) {
+            self.on_valid_vertex(v);
+            return;
+        }
+
+        if id == self.bootstrap {
+            self.try_advance_round();
+            return;
+        }
+
+        if self.rounds().on_timer(id).is_some() {
+            debug_process!("Timer fired: {id}");
+            self.try_advance_round();
+        }
+    }
+}
+
+// Utils
+impl ShoalLayer {
+    fn adversary_threshold(&self) -> usize {
+        (self.proc_num - 1) / 3
+    }
+
+    fn quorum_size(&self) -> usize {
+        2 * self.adversary_threshold() + 1
+    }
+
+    fn direct_commit_threshold(&self) -> usize {
+        self.adversary_threshold() + 1
+    }
+
+    fn create_vertex(&mut self, round: usize) -> VertexPtr {
+        VertexPtr::new(Vertex {
+            round,
+            source: self.self_id,
+            strong_edges: self.dag[round - 1]
+                .iter()
+                .flatten() // Remove option
+                .map(Shared::downgrade)
+                .collect::<Vec<WeakShared<Vertex>>>(),
+            creation_time: now(),
+            batch: self.mempool.drain(&self.dag),
+            leader_proof: None,
+        })
+    }
+
+    fn validator(&mut self) -> &mut VerificationQueue<QuorumEdgesValidator> {
+        self.validator.as_mut().expect("Validator not initialized")
+    }
+
+    // Anchors of an instance are on every second round from its start
+    fn is_anchor_round(&self, round: usize) -> bool {
+        round >= self.instance_start && (round - self.instance_start).is_multiple_of(2)
+    }
+
+    fn get_anchor(&self, round: usize) -> Option<VertexPtr> {
+        if !self.is_anchor_round(round) || round > self.dag.current_max_allocated_round() {
+            return None;
+        }
+        self.dag[round][self.reputation.leader(round)].clone()
+    }
+
+    fn votes(&self, anchor: &VertexPtr) -> usize {
+        if anchor.round + 1 > self.dag.current_max_allocated_round() {
+            return 0;
+        }
+        self.dag[anchor.round + 1]
+            .iter()
+            .flatten()
+            .filter(|v| {
+                v.strong_edges
+                    .iter()
+                    .any(|edge| same_vertex(&edge.upgrade().unwrap(), anchor))
+            })
+            .count()
+    }
+
+    fn rounds(&mut self) -> &mut RoundProtocol {
+        self.rounds.as_mut().expect("Rounds not initialized")
+    }
+}
+
+// DAG construction: part 2
+impl ShoalLayer {
+    fn on_valid_vertex(&mut self, v: VertexPtr) {
+        // Try to drain stalled vertices first (in sorted order)
+        self.dag.buffered().into_iter().for_each(|v| {
+            self.try_add_to_dag(v);
+        });
+
+        // Then try add current received vertex
+        if !self.try_add_to_dag(v.clone()) {
+            self.dag.buffer(v.clone());
+        }
+
+        let round = self.rounds().round();
+        if round == v.round {
+            if self.rounds().timed_out() {
+                self.try_advance_round();
+                return;
+            }
+
+            if self.is_anchor_round(round) {
+                // Wait for leader of this round
+                if self.get_anchor(round).is_some() {
+                    self.try_advance_round();
+                }
+            } else if let Some(anchor) = self.get_anchor(round - 1) {
+                // Wait for 2f+1 links for anchor in previous round
+                if self.votes(&anchor) >= self.quorum_size() {
+                    self.try_advance_round();
+                }
+            }
+        }
+    }
+
+    fn try_advance_round(&mut self) {
+        if let Some(round) = self.rounds().advance() {
+            debug_process!("Advancing to {round} round");
+            self.broadcast_vertex(round);
+        }
+    }
+
+    fn broadcast_vertex(&mut self, round: usize) {
+        let v = self.create_vertex(round);
+        self.try_add_to_dag(v.clone());
+        self.outbox.push(VertexMessage::Vertex(v));
+    }
+
+    fn try_add_to_dag(&mut self, v: VertexPtr) -> bool {
+        // Strong edges are not in the DAG yet
+        if v.round - 1 > self.dag.current_max_allocated_round() {
+            return false;
+        }
+
+        let all_strong_edges_in_the_dag = v
+            .strong_edges
+            .iter()
+            .map(|weak| weak.upgrade().unwrap())
+            .all(|edge| match self.dag[edge.round][edge.source] {
+                None => false,
+                Some(ref vertex) => same_vertex(&edge, vertex),
+            });
+
+        if !all_strong_edges_in_the_dag {
+            return false;
+        }
+
+        self.dag.add_vertex(v.clone());
+        self.rounds().record(v.round, v.source);
+
+        if self.rounds().catch_up(v.round) {
+            self.broadcast_vertex(v.round);
+        }
+
+        self.try_ordering();
+        true
+    }
+}
+
+// Consensus logic
+impl ShoalLayer {
+    // Commits anchors of the current instance, switching instances until none can be committed
+    fn try_ordering(&mut self) {
+        while let Some(anchor) = self.highest_committed_anchor() {
+            self.order_first_anchor(anchor);
+        }
+    }
+
+    fn highest_committed_anchor(&self) -> Option<VertexPtr> {
+        (self.instance_start..=self.dag.current_max_allocated_round())
+            .rev()
+            .filter_map(|round| self.get_anchor(round))
+            .find(|anchor| self.votes(anchor) >= self.direct_commit_threshold())
+    }
+
+    // As Bullshark orders anchors of the instance, but only the first of them (same for all
+    // validators) ends the instance. Later anchors are reevaluated with the next schedule.
+    fn order_first_anchor(&mut self, v: VertexPtr) {
+        let mut anchor = v;
+        let mut r = anchor.round.saturating_sub(2);
+        while r >= self.instance_start {
+            if let Some(prev_anchor) = self.get_anchor(r)
+                && self.dag.path_exists(&anchor, &prev_anchor)
+            {
+                anchor = prev_anchor;
+            }
+            r -= 2;
+        }
+
+        debug_process!(
+            "Instance of round {} ordered anchor of round {}",
+            self.instance_start,
+            anchor.round
+        );
+        self.dag.order_from(&anchor);
+        self.reputation.on_commit(&anchor);
+        self.instance_start = anchor.round + 1;
+    }
+}
+
+impl OrderingEngine for ShoalLayer {
+    fn ordered_sink(&mut self) -> &mut OrderedSink {
+        self.dag.ordered_sink()
+    }
+}
+
+// Validator process: Shoal on top of consistent broadcast
+pub struct ShoalStack;
+
+impl StackDefinition for ShoalStack {
+    fn stack() -> Stack {
+        Stack::new()
+            .layer(ByzantineConsistentBroadcast::default())
+            .layer(ShoalLayer::default())
+    }
+}
+
+pub type Shoal = Stacked<ShoalStack>;
@@ -2,160 +2,149 @@
 // https://arxiv.org/pdf/2209.05633
 // https://arxiv.org/pdf/2506.13998
 
-use std::{
-    collections::BTreeSet,
-    rc::{Rc, Weak},
-};
+use std::collections::BTreeSet;
 
+use crate::{
+    adaptive_d::DController,
+    consistent_broadcast::ByzantineConsistentBroadcast,
+    dag_utils::{RoundBasedDAG, Vertex, VertexMessage, VertexPtr, same_vertex},
+    leaders::LeaderElection,
+    ordered_sink::{OrderedSink, OrderingEngine},
+    sampling::Sampler,
+    validation::{CryptoCost, SampledEdgesValidator, VerificationQueue},
+    workload::Mempool,
+};
 use dscale::{
-    global::{anykv, configuration},
+    global::{anykv, bootstrap, configuration},
+    helpers::RoundProtocol,
+    message::{Shared, WeakShared},
+    stack::{Layer, LayerContext, Stack, StackDefinition, Stacked},
     *,
 };
-use rand::{SeedableRng, rngs::StdRng};
 
-use crate::{
-    consistent_broadcast::{BCBMessage, ByzantineConsistentBroadcast},
-    dag_utils::{RoundBasedDAG, Vertex, VertexMessage, VertexPtr, same_vertex},
-};
+const ROUND_TIMEOUT: Jiffies = Jiffies(10000);
 
-pub struct SparseBullshark {
-    rbcast: ByzantineConsistentBroadcast,
+pub struct SparseBullsharkLayer {
+    outbox: Vec<VertexMessage>, // Submitted to the broadcast layer once the handler returns
+    validator: Option<VerificationQueue<SampledEdgesValidator>>,
+    mempool: Mempool,
+    leaders: LeaderElection,
     proc_num: usize,
     dag: RoundBasedDAG,
-    round: usize,
-    buffer: BTreeSet<VertexPtr>,
+    rounds: Option<RoundProtocol>,
     last_ordered_round: usize,
     ordered_anchors_stack: Vec<VertexPtr>,
-    wait: bool,
-    current_timer: TimerId,
-    sampler: Option<StdRng>,
+    bootstrap: TimerId, // Fires once all validators have started
+    sampler: Option<Sampler>,
     D: usize,
+    controller: Option<DController>, // Adapts D per round, see adaptive_d
 }
 
-impl Default for SparseBullshark {
+impl Default for SparseBullsharkLayer {
     fn default() -> Self {
         Self {
-            rbcast: ByzantineConsistentBroadcast::default(),
+            outbox: Vec::new(),
+            validator: None,
+            mempool: Mempool::default(),
+            leaders: LeaderElection::default(),
             proc_num: 0,
             dag: RoundBasedDAG::default(),
-            round: 0,
-            buffer: BTreeSet::new(),
+            rounds: None,
             last_ordered_round: 0,
             ordered_anchors_stack: Vec::new(),
-            wait: true,
-            current_timer: 0,
+            bootstrap: 0,
             sampler: None,
             D: anykv::get::<usize>("D"),
+            controller: None,
         }
     }
 }
-impl ProcessHandle for SparseBullshark {
-    fn start(&mut self) {
-        self.proc_num = configuration::process_number();
-        self.sampler = Some(StdRng::seed_from_u64(configuration::seed()));
-        self.dag.set_round_size(configuration::process_number());
-        self.rbcast.start(configuration::process_number());
+impl Layer for SparseBullsharkLayer {
+    fn start(&mut self, context: &mut LayerContext) {
+        self.on_start();
+        self.flush(context);
+    }
 
-        // Shared genesis vertices
-        let genesis_vertex = VertexPtr::new(Vertex {
-            round: 0,
-            source: rank(),
-            strong_edges: Vec::new(),
-            creation_time: now(),
-        });
+    fn on_deliver(&mut self, from: ProcessId, message: MessagePtr, context: &mut LayerContext) {
+        self.on_vertex_message(from, message);
+        self.flush(context);
+    }
 
-        self.rbcast
-            .reliably_broadcast(VertexMessage::Genesis(genesis_vertex));
+    fn on_timer(&mut self, id: TimerId, context: &mut LayerContext) {
+        self.on_timer_fired(id);
+        self.flush(context);
     }
+}
 
-    // DAG construction: part 1
-    fn on_message(&mut self, from: ProcessId, message: MessagePtr) {
-        if let Some(bs_message) = self.rbcast.process(from, message.as_type::<BCBMessage>()) {
-            match bs_message.as_type::<VertexMessage>().as_ref() {
-                VertexMessage::Genesis(v) => {
-                    debug_assert!(v.round == 0);
-                    self.dag.add_vertex(v.clone());
-                    self.try_advance_round();
-                    return;
-                }
+// Inspection after a run, see Simulation::process
+impl SparseBullsharkLayer {
+    pub fn last_ordered_round(&self) -> usize {
+        self.last_ordered_round
+    }
+}
 
-                VertexMessage::Vertex(v) => {
-                    if self.bad_vertex(v, from) {
-                        return;
-                    }
+// Handlers
+impl SparseBullsharkLayer {
+    fn flush(&mut self, context: &mut LayerContext) {
+        self.outbox
+            .drain(..)
+            .for_each(|message| context.submit(message));
+    }
 
-                    // Try to drain stalled vertices first
-                    let mut vertices_in_the_buffer =
-                        self.buffer.iter().cloned().collect::<Vec<VertexPtr>>();
-                    vertices_in_the_buffer.sort_by_key(|v| v.round);
-                    vertices_in_the_buffer.into_iter().for_each(|v| {
-                        self.try_add_to_dag(v);
-                    });
-
-                    // Then try add current received vertex
-                    if !self.try_add_to_dag(v.clone()) {
-                        self.buffer.insert(v.clone());
-                    }
+    fn on_start(&mut self) {
+        self.proc_num = configuration::process_number();
+        self.sampler = Some(Sampler::configured(self.proc_num));
+        self.dag.set_round_size(configuration::process_number());
+        self.rounds = Some(RoundProtocol::new(self.quorum_size()).with_timeout(ROUND_TIMEOUT));
+        self.controller = DController::configured(self.D, self.proc_num);
+        if let Some(controller) = &self.controller {
+            self.D = controller.D();
+        }
+        self.validator = Some(VerificationQueue::new(SampledEdgesValidator {
+            // Others may have grown their D already
+            D: self.controller.as_ref().map_or(self.D, DController::max),
+            cost: CryptoCost::configured(),
+        }));
+        self.mempool = Mempool::configured();
+        self.leaders = LeaderElection::configured(self.proc_num);
+
+        // Shared genesis vertices, the first round starts once everyone has them
+        for v in self.dag.add_genesis() {
+            self.rounds().record(v.round, v.source);
+        }
+        self.bootstrap = bootstrap::await_all_started();
+    }
 
-                    if self.round == v.round {
-                        // Note: anchor vertices are on even rounds
-                        if !self.wait {
-                            self.try_advance_round();
-                            return;
-                        }
-
-                        // Note: anchor vertices are on even rounds
-                        match self.round % 4 {
-                            0 | 2 => {
-                                // Wait for steady leader of this round
-                                if self.get_anchor(self.round).is_some() {
-                                    self.try_advance_round();
-                                }
-                            }
-                            1 | 3 => {
-                                // Wait for 2f+1 links for anchor in previous round
-                                if self.get_anchor(self.round - 1).is_none() {
-                                    return;
-                                }
-
-                                if self.dag[self.round]
-                                    .iter()
-                                    .flatten()
-                                    .map(|v| {
-                                        v.strong_edges
-                                            .iter()
-                                            .map(|weak| weak.upgrade().unwrap())
-                                            .any(|v| {
-                                                same_vertex(
-                                                    &v,
-                                                    &self.get_anchor(self.round - 1).unwrap(),
-                                                )
-                                            })
-                                    })
-                                    .count()
-                                    >= self.quorum_size()
-                                {
-                                    self.try_advance_round();
-                                }
-                            }
-                            _ => unreachable!(),
-                        }
-                    }
+    // DAG construction: part 1
+    fn on_vertex_message(&mut self, from: ProcessId, message: MessagePtr) {
+        match message.as_type::<VertexMessage>().as_ref() {
+            VertexMessage::Vertex(v) => {
+                if let Some(v) = self.validator().submit(from, v.clone()) {
+                    self.on_valid_vertex(v);
                 }
             }
         }
     }
 
-    fn on_timer(&mut self, id: TimerId) {
-        if id == self.current_timer {
-            self.wait = false;
+    fn on_timer_fired(&mut self, id: TimerId) {
+        if let Some(v) = self.validator().on_timer(id) {
+            self.on_valid_vertex(v);
+            return;
+        }
+
+        if id == self.bootstrap {
+            self.try_advance_round();
+            return;
+        }
+
+        if self.rounds().on_timer(id).is_some() {
             self.try_advance_round();
         }
     }
 }
 
 // Utils
-impl SparseBullshark {
+impl SparseBullsharkLayer {
     fn adversary_threshold(&self) -> usize {
         (self.proc_num - 1) / 3
     }
@@ -168,31 +157,22 @@ impl SparseBullshark {
         2 * self.adversary_threshold() + 1
     }
 
-    fn non_none_vertices_count_for_round(&self, round: usize) -> usize {
-        self.dag[round].iter().flatten().count()
-    }
-
-    fn quorum_reached_for_round(&self, round: usize) -> bool {
-        self.non_none_vertices_count_for_round(round) >= self.quorum_size()
-    }
-
-    fn sample_random_candidates(&mut self, round: usize) -> Vec<Weak<Vertex>> {
+    fn sample_random_candidates(&mut self, round: usize) -> Vec<WeakShared<Vertex>> {
         let candidates: Vec<VertexPtr> = self.dag[round].iter().flatten().cloned().collect();
 
         if candidates.len() <= self.D {
             return candidates
                 .into_iter()
-                .map(|strong| Rc::downgrade(&strong))
+                .map(|strong| Shared::downgrade(&strong))
                 .collect();
         }
 
-        use rand::prelude::IndexedRandom;
-        let mut random_candidates = candidates
-            .choose_multiple(
-                self.sampler.as_mut().expect("Sampler not initialized"),
-                self.D,
-            )
-            .cloned()
+        let mut random_candidates = self
+            .sampler
+            .as_mut()
+            .expect("Sampler not initialized")
+            .sample(&candidates, self.D)
+            .into_iter()
             .collect::<BTreeSet<VertexPtr>>();
 
         // Try add myself
@@ -210,17 +190,18 @@ impl SparseBullshark {
 
         random_candidates
             .into_iter()
-            .map(|strong| Rc::downgrade(&strong))
+            .map(|strong| Shared::downgrade(&strong))
             .collect()
     }
 
     fn create_vertex(&mut self, round: usize) -> VertexPtr {
-        // Infinite source of client txns
         let vertex = VertexPtr::new(Vertex {
             round,
             source: rank(),
             strong_edges: self.sample_random_candidates(round - 1),
             creation_time: now(),
+            batch: self.mempool.drain(&self.dag),
+            leader_proof: self.leaders.proof(round),
         });
 
         let virtual_size = VertexMessage::Vertex(vertex.clone()).virtual_size();
@@ -232,12 +213,12 @@ impl SparseBullshark {
         vertex
     }
 
-    fn bad_vertex(&self, v: &VertexPtr, from: ProcessId) -> bool {
-        v.strong_edges.len() > self.D + 2 || from != v.source
+    fn validator(&mut self) -> &mut VerificationQueue<SampledEdgesValidator> {
+        self.validator.as_mut().expect("Validator not initialized")
     }
 
     fn get_leader_id(&self, round: usize) -> ProcessId {
-        return round % self.proc_num + 1;
+        self.leaders.leader(round, round / 2)
     }
 
     fn get_anchor(&self, round: usize) -> Option<VertexPtr> {
@@ -245,26 +226,83 @@ impl SparseBullshark {
         self.dag[round][leader].clone()
     }
 
-    fn start_timer(&mut self) {
-        self.current_timer = schedule_timer_after(Jiffies(10000));
-        self.wait = true;
+    fn rounds(&mut self) -> &mut RoundProtocol {
+        self.rounds.as_mut().expect("Rounds not initialized")
     }
 }
 
 // DAG construction: part 2
-impl SparseBullshark {
+impl SparseBullsharkLayer {
+    fn on_valid_vertex(&mut self, v: VertexPtr) {
+        // Try to drain stalled vertices first
+        self.dag.buffered().into_iter().for_each(|v| {
+            self.try_add_to_dag(v);
+        });
+
+        // Then try add current received vertex
+        if !self.try_add_to_dag(v.clone()) {
+            self.dag.buffer(v.clone());
+        }
+
+        let round = self.rounds().round();
+        if round == v.round {
+            if self.rounds().timed_out() {
+                self.try_advance_round();
+                return;
+            }
+
+            // Note: anchor vertices are on even rounds
+            match round % 4 {
+                0 | 2 => {
+                    // Wait for steady leader of this round
+                    if self.get_anchor(round).is_some() {
+                        self.try_advance_round();
+                    }
+                }
+                1 | 3 => {
+                    // Wait for 2f+1 links for anchor in previous round
+                    if self.get_anchor(round - 1).is_none() {
+                        return;
+                    }
+
+                    if self.dag[round]
+                        .iter()
+                        .flatten()
+                        .map(|v| {
+                            v.strong_edges
+                                .iter()
+                                .map(|weak| weak.upgrade().unwrap())
+                                .any(|v| same_vertex(&v, &self.get_anchor(round - 1).unwrap()))
+                        })
+                        .count()
+                        >= self.quorum_size()
+                    {
+                        self.try_advance_round();
+                    }
+                }
+                _ => unreachable!(),
+            }
+        }
+    }
+
     fn try_advance_round(&mut self) {
-        if self.quorum_reached_for_round(self.round) {
-            self.round += 1;
-            self.start_timer();
-            self.broadcast_vertex(self.round);
+        let timed_out = self.rounds().timed_out();
+        if let Some(round) = self.rounds().advance() {
+            self.adapt_D(round, timed_out);
+            self.broadcast_vertex(round);
+        }
+    }
+
+    fn adapt_D(&mut self, round: usize, timed_out: bool) {
+        if let Some(controller) = &mut self.controller {
+            self.D = controller.on_round(round, timed_out);
         }
     }
 
     fn broadcast_vertex(&mut self, round: usize) {
         let v = self.create_vertex(round);
         self.try_add_to_dag(v.clone());
-        self.rbcast.reliably_broadcast(VertexMessage::Vertex(v));
+        self.outbox.push(VertexMessage::Vertex(v));
     }
 
     fn try_add_to_dag(&mut self, v: VertexPtr) -> bool {
@@ -287,15 +325,13 @@ impl SparseBullshark {
         }
 
         self.dag.add_vertex(v.clone());
+        self.rounds().record(v.round, v.source);
 
-        if self.quorum_reached_for_round(v.round) && v.round > self.round {
-            self.round = v.round;
-            self.start_timer();
+        if self.rounds().catch_up(v.round) {
+            self.adapt_D(v.round, false);
             self.broadcast_vertex(v.round);
         }
 
-        self.buffer.remove(&v);
-
         if v.source == self.get_leader_id(v.round) {
             self.try_ordering(v);
         }
@@ -304,7 +340,7 @@ impl SparseBullshark {
 }
 
 // Consensus logic
-impl SparseBullshark {
+impl SparseBullsharkLayer {
     fn try_ordering(&mut self, v: VertexPtr) {
         // Note: leaders are on even rounds
         if v.round % 2 == 1 || v.round == 0 {
@@ -364,3 +400,22 @@ impl SparseBullshark {
         }
     }
 }
+
+impl OrderingEngine for SparseBullsharkLayer {
+    fn ordered_sink(&mut self) -> &mut OrderedSink {
+        self.dag.ordered_sink()
+    }
+}
+
+// Validator process: Sparse Bullshark on top of consistent broadcast
+pub struct SparseBullsharkStack;
+
+impl StackDefinition for SparseBullsharkStack {
+    fn stack() -> Stack {
+        Stack::new()
+            .layer(ByzantineConsistentBroadcast::default())
+            .layer(SparseBullsharkLayer::default())
+    }
+}
+
+pub type SparseBullshark = Stacked<SparseBullsharkStack>;
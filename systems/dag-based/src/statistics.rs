@@ -0,0 +1,163 @@
+// Cross-seed statistics: confidence intervals of a metric and Welch's t-test
+// between two configurations, so differences can be told apart from noise.
+
+// Significance level of reported intervals and tests
+pub const ALPHA: f64 = 0.05;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Summary {
+    pub runs: usize,
+    pub mean: f64,
+    pub std_dev: f64, // Sample standard deviation
+    pub ci: f64,      // Half-width of the (1 - ALPHA) confidence interval of the mean
+}
+
+impl Summary {
+    pub fn of(samples: &[f64]) -> Self {
+        let runs = samples.len();
+        let mean = samples.iter().sum::<f64>() / runs as f64;
+        if runs < 2 {
+            return Self {
+                runs,
+                mean,
+                std_dev: 0.0,
+                ci: f64::NAN,
+            };
+        }
+        let variance = samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (runs - 1) as f64;
+        let std_dev = variance.sqrt();
+        Self {
+            runs,
+            mean,
+            std_dev,
+            ci: t_critical((runs - 1) as f64) * std_dev / (runs as f64).sqrt(),
+        }
+    }
+
+    fn variance_of_mean(&self) -> f64 {
+        self.std_dev.powi(2) / self.runs as f64
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Difference {
+    pub relative: f64, // (b - a) / a
+    pub p_value: f64,  // Two-sided
+}
+
+impl Difference {
+    pub fn significant(&self) -> bool {
+        self.p_value < ALPHA
+    }
+}
+
+// Welch's t-test: does not assume equal variances of the two configurations
+pub fn welch_t_test(a: &[f64], b: &[f64]) -> Difference {
+    let (a, b) = (Summary::of(a), Summary::of(b));
+    let relative = (b.mean - a.mean) / a.mean;
+    let (va, vb) = (a.variance_of_mean(), b.variance_of_mean());
+    let se = (va + vb).sqrt();
+
+    let p_value = if a.runs < 2 || b.runs < 2 {
+        f64::NAN
+    } else if se == 0.0 {
+        // Deterministic metric: any difference is real
+        if a.mean == b.mean { 1.0 } else { 0.0 }
+    } else {
+        let t = (b.mean - a.mean) / se;
+        let df = (va + vb).powi(2)
+            / (va.powi(2) / (a.runs - 1) as f64 + vb.powi(2) / (b.runs - 1) as f64);
+        t_two_sided_p(t, df)
+    };
+
+    Difference { relative, p_value }
+}
+
+fn t_two_sided_p(t: f64, df: f64) -> f64 {
+    incomplete_beta(df / 2.0, 0.5, df / (df + t * t))
+}
+
+// t such that P(|T| > t) = ALPHA, found by bisection
+fn t_critical(df: f64) -> f64 {
+    let (mut low, mut high) = (0.0, 1e3);
+    for _ in 0..100 {
+        let mid = (low + high) / 2.0;
+        if t_two_sided_p(mid, df) > ALPHA {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    (low + high) / 2.0
+}
+
+// Regularized incomplete beta function I_x(a, b) (Numerical Recipes, 6.4)
+fn incomplete_beta(a: f64, b: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    if x >= 1.0 {
+        return 1.0;
+    }
+    let front =
+        (ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b) + a * x.ln() + b * (1.0 - x).ln()).exp();
+    if x < (a + 1.0) / (a + b + 2.0) {
+        front * beta_fraction(a, b, x) / a
+    } else {
+        1.0 - front * beta_fraction(b, a, 1.0 - x) / b
+    }
+}
+
+fn beta_fraction(a: f64, b: f64, x: f64) -> f64 {
+    const TINY: f64 = 1e-300;
+    let mut c = 1.0;
+    let mut d = 1.0 - (a + b) * x / (a + 1.0);
+    if d.abs() < TINY {
+        d = TINY;
+    }
+    d = 1.0 / d;
+    let mut h = d;
+    for m in 1..300 {
+        let m = m as f64;
+        for numerator in [
+            m * (b - m) * x / ((a + 2.0 * m - 1.0) * (a + 2.0 * m)),
+            -(a + m) * (a + b + m) * x / ((a + 2.0 * m) * (a + 2.0 * m + 1.0)),
+        ] {
+            d = 1.0 + numerator * d;
+            if d.abs() < TINY {
+                d = TINY;
+            }
+            c = 1.0 + numerator / c;
+            if c.abs() < TINY {
+                c = TINY;
+            }
+            d = 1.0 / d;
+            h *= d * c;
+        }
+        if (d * c - 1.0).abs() < 1e-12 {
+            break;
+        }
+    }
+    h
+}
+
+// Lanczos approximation
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 6] = [
+        76.18009172947146,
+        -86.50532032941677,
+        24.01409824083091,
+        -1.231739572450155,
+        0.1208650973866179e-2,
+        -0.5395239384953e-5,
+    ];
+    let tmp = x + 5.5;
+    let tmp = tmp - (x + 0.5) * tmp.ln();
+    let series = COEFFICIENTS
+        .iter()
+        .enumerate()
+        .fold(1.000000000190015, |sum, (i, c)| {
+            sum + c / (x + 1.0 + i as f64)
+        });
+    -tmp + (2.5066282746310005 * series / x).ln()
+}
@@ -0,0 +1,23 @@
+// Latency vs throughput curve of a protocol under increasing offered load
+
+#[derive(Clone, Debug)]
+pub struct LoadPoint {
+    pub offered: f64,    // Txns per second
+    pub throughput: f64, // Committed txns per second
+    pub latency: f64,    // Average txn latency in jiffies
+}
+
+// Share of offered load which should be committed for the system to keep up
+const KEEP_UP_RATIO: f64 = 0.9;
+// Latency growth relative to the lightest load which is considered a knee
+const LATENCY_KNEE: f64 = 2.0;
+
+// Index of the first point past the knee of the curve: either throughput stops
+// following offered load or latency explodes. Points should be sorted by offered load.
+pub fn saturation_point(points: &[LoadPoint]) -> Option<usize> {
+    let base_latency = points.first()?.latency;
+    points.iter().position(|point| {
+        point.throughput < KEEP_UP_RATIO * point.offered
+            || point.latency > LATENCY_KNEE * base_latency
+    })
+}
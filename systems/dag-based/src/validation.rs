@@ -0,0 +1,172 @@
+use std::collections::HashMap;
+
+use dscale::{
+    global::{anykv, configuration},
+    message::Shared,
+    *,
+};
+
+use crate::dag_utils::Vertex;
+
+// Simulated cost of cryptographic checks performed on receive.
+// Configured through anykv under "crypto_cost", zero (free crypto) if absent.
+#[derive(Clone, Copy, Default)]
+pub struct CryptoCost {
+    pub signature_verification: Jiffies,
+    pub certificate_verification: Jiffies,
+    pub vrf_verification: Jiffies,
+    pub batch_verification: Option<BatchVerification>, // Certificates of a message one by one if absent
+}
+
+// Verifying k signatures at once costs the first one in full and every other one
+// at a fraction of it (batch verification of Ed25519, multi-pairings of BLS).
+#[derive(Clone, Copy)]
+pub struct BatchVerification {
+    pub max_batch: usize,
+    pub marginal: f64, // Cost of each additional signature relative to the first
+}
+
+impl BatchVerification {
+    pub fn cost(&self, single: Jiffies, signatures: usize) -> Jiffies {
+        let full = signatures / self.max_batch;
+        let rest = signatures % self.max_batch;
+        let batch = |size: usize| {
+            if size == 0 {
+                return 0.0;
+            }
+            single.0 as f64 * (1.0 + (size - 1) as f64 * self.marginal)
+        };
+        Jiffies((full as f64 * batch(self.max_batch) + batch(rest)).ceil() as usize)
+    }
+}
+
+impl CryptoCost {
+    pub fn configured() -> Self {
+        anykv::try_get::<CryptoCost>("crypto_cost").unwrap_or_default()
+    }
+
+    pub fn certificates(&self, certificates: usize) -> Jiffies {
+        match self.batch_verification {
+            None => Jiffies(self.certificate_verification.0 * certificates),
+            Some(batch) => batch.cost(self.certificate_verification, certificates),
+        }
+    }
+}
+
+pub trait MessageValidator {
+    type Message;
+
+    // Structural and signature predicates. Rejected messages never reach the protocol.
+    fn is_valid(&self, from: ProcessId, message: &Self::Message) -> bool;
+
+    // Simulated time spent verifying the message before it can be processed,
+    // nominal: scaled by the CPU speed of the verifying process.
+    fn verification_cost(&self, message: &Self::Message) -> Jiffies;
+}
+
+// Verifies messages one at a time (single verification core per process):
+// each accepted message is released only after all previously submitted ones
+// and its own verification cost have elapsed.
+pub struct VerificationQueue<V: MessageValidator> {
+    validator: V,
+    busy_until: Jiffies,
+    in_verification: HashMap<TimerId, Shared<V::Message>>,
+    rejected: usize,
+}
+
+impl<V: MessageValidator> VerificationQueue<V> {
+    pub fn new(validator: V) -> Self {
+        Self {
+            validator,
+            busy_until: Jiffies(0),
+            in_verification: HashMap::new(),
+            rejected: 0,
+        }
+    }
+
+    // Returns message back immediately if verification is free, otherwise it pops out later from on_timer()
+    pub fn submit(
+        &mut self,
+        from: ProcessId,
+        message: Shared<V::Message>,
+    ) -> Option<Shared<V::Message>> {
+        if !self.validator.is_valid(from, &message) {
+            debug_process!("Rejected invalid message from {from}");
+            self.rejected += 1;
+            return None;
+        }
+
+        let cost = configuration::cpu_time(self.validator.verification_cost(&message));
+        if cost == Jiffies(0) && self.busy_until <= now() {
+            return Some(message);
+        }
+
+        self.busy_until = self.busy_until.max(now()) + cost;
+        let timer = schedule_timer_after(self.busy_until - now());
+        self.in_verification.insert(timer, message);
+        None
+    }
+
+    pub fn on_timer(&mut self, id: TimerId) -> Option<Shared<V::Message>> {
+        self.in_verification.remove(&id)
+    }
+
+    pub fn rejected(&self) -> usize {
+        self.rejected
+    }
+}
+
+// Vertex references at least 2f+1 certified vertices of the previous round (Bullshark, DAG-Rider)
+pub struct QuorumEdgesValidator {
+    pub quorum_size: usize,
+    pub cost: CryptoCost,
+}
+
+impl MessageValidator for QuorumEdgesValidator {
+    type Message = Vertex;
+
+    fn is_valid(&self, from: ProcessId, v: &Vertex) -> bool {
+        v.round > 0 // Genesis vertices are never sent
+            && v.strong_edges.len() >= self.quorum_size
+            && from == v.source
+            && has_valid_proof(v)
+    }
+
+    fn verification_cost(&self, v: &Vertex) -> Jiffies {
+        vertex_verification_cost(&self.cost, v)
+    }
+}
+
+// Vertex references at most D sampled vertices + own + anchor (SparseBullshark)
+pub struct SampledEdgesValidator {
+    pub D: usize,
+    pub cost: CryptoCost,
+}
+
+impl MessageValidator for SampledEdgesValidator {
+    type Message = Vertex;
+
+    fn is_valid(&self, from: ProcessId, v: &Vertex) -> bool {
+        v.round > 0 && v.strong_edges.len() <= self.D + 2 && from == v.source && has_valid_proof(v)
+    }
+
+    fn verification_cost(&self, v: &Vertex) -> Jiffies {
+        vertex_verification_cost(&self.cost, v)
+    }
+}
+
+// Leader election proof, if any, is made by the source for the round of the vertex
+fn has_valid_proof(v: &Vertex) -> bool {
+    v.leader_proof.is_none_or(|proof| {
+        proof.prover == v.source && proof.input == v.round as u64 && proof.verify()
+    })
+}
+
+// Source signature + certificate per strong edge + leader election proof
+fn vertex_verification_cost(cost: &CryptoCost, v: &Vertex) -> Jiffies {
+    Jiffies(
+        cost.signature_verification.0
+            + cost.certificates(v.strong_edges.len()).0
+            + v.leader_proof.map_or(0, |_| cost.vrf_verification.0),
+    )
+}
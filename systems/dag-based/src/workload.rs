@@ -0,0 +1,286 @@
+// Open-loop client load: transactions arrive uniformly at every validator and wait
+// in the local mempool until its next vertex. Offered load of the whole system
+// (txns per second, 1 jiffy == 1ms) is configured through anykv under "txn_rate".
+//
+// With "txn_replication" (anykv) set, every transaction is tracked individually: clients submit
+// it to that many validators, which drop it once they see it in a vertex of another validator.
+// Transactions included more than once are deduplicated on ordering.
+//
+// Every validator executes ordered transactions in order at "exec_rate" (txns per second,
+// anykv), instantly if absent. With "exec_lag_limit" (Jiffies, anykv) set, a validator whose
+// execution is behind by more than that stops packing transactions into its vertices: they
+// wait in its mempool until execution catches up. Payload of vertices which are not ordered
+// yet counts as lag already, otherwise validators would keep packing for as long as ordering
+// takes and execution would fall behind by that much.
+
+use std::collections::{BTreeSet, HashMap};
+
+use dscale::{
+    Jiffies, ProcessId,
+    global::{anykv, configuration::process_number},
+    message::Shared,
+    now, rank,
+};
+
+use crate::dag_utils::RoundBasedDAG;
+
+pub const TXN_SIZE: usize = 512; // Bytes
+
+pub type TxnId = usize;
+
+#[derive(Default, Clone)]
+pub struct Batch {
+    pub transactions: usize,
+    arrivals_sum: f64, // Latency of the whole batch is computed without keeping every txn
+    tracked: Shared<Vec<TxnId>>, // Only with "txn_replication"
+}
+
+impl Batch {
+    pub fn total_latency(&self, committed_at: Jiffies) -> f64 {
+        self.transactions as f64 * committed_at.0 as f64 - self.arrivals_sum
+    }
+}
+
+#[derive(Default)]
+pub struct Mempool {
+    rate: f64, // Txns per jiffy arriving at this validator
+    carry: f64,
+    last_drain: Jiffies,
+    replication: Option<usize>,
+    lag_limit: Option<Jiffies>,
+}
+
+impl Mempool {
+    pub fn configured() -> Self {
+        let txn_rate = anykv::try_get::<f64>("txn_rate").unwrap_or(0.0);
+        Self {
+            rate: txn_rate / 1000.0 / process_number() as f64,
+            carry: 0.0,
+            last_drain: now(),
+            replication: anykv::try_get::<usize>("txn_replication"),
+            lag_limit: anykv::try_get::<Jiffies>("exec_lag_limit"),
+        }
+    }
+
+    // Nothing while execution lags beyond the limit
+    pub fn drain(&mut self, dag: &RoundBasedDAG) -> Batch {
+        if self
+            .lag_limit
+            .is_some_and(|limit| dag.execution_lag() > limit)
+        {
+            anykv::modify::<TxnStats>("txn_stats", |stats| stats.throttled += 1);
+            return Batch::default();
+        }
+
+        if let Some(replication) = self.replication {
+            // Read before the pool borrows anykv
+            let proc_num = process_number();
+            let rate = self.rate * proc_num as f64;
+            let mut batch = Batch::default();
+            anykv::modify::<SharedMempool>(SHARED_MEMPOOL, |pool| {
+                pool.submit_until_now(rate, replication, proc_num);
+                batch = pool.include();
+            });
+            return batch;
+        }
+
+        let arrived = self.rate * (now() - self.last_drain).0 as f64 + self.carry;
+        let transactions = arrived.floor();
+        self.carry = arrived - transactions;
+        let batch = Batch {
+            transactions: transactions as usize,
+            arrivals_sum: transactions * (self.last_drain.0 + now().0) as f64 / 2.0,
+            tracked: Shared::default(),
+        };
+        self.last_drain = now();
+        batch
+    }
+}
+
+// Ordered transactions waiting for execution at a validator. Batches execute as a whole.
+#[derive(Default)]
+pub struct Execution {
+    rate: Option<f64>, // Txns per jiffy
+    busy_until: f64,
+}
+
+impl Execution {
+    pub fn configured() -> Self {
+        Self {
+            rate: anykv::try_get::<f64>("exec_rate").map(|rate| rate / 1000.0),
+            busy_until: 0.0,
+        }
+    }
+
+    // Returns when the batch is executed
+    pub(crate) fn execute(&mut self, batch: &Batch) -> Jiffies {
+        let Some(rate) = self.rate else {
+            return now();
+        };
+        self.busy_until = self.busy_until.max(now().0 as f64) + batch.transactions as f64 / rate;
+        Jiffies(self.busy_until.ceil() as usize)
+    }
+
+    // Time until everything ordered so far and `unordered` more transactions are executed
+    pub fn lag(&self, unordered: usize) -> Jiffies {
+        let Some(rate) = self.rate else {
+            return Jiffies(0);
+        };
+        let backlog = (self.busy_until - now().0 as f64).max(0.0);
+        Jiffies((backlog + unordered as f64 / rate).ceil() as usize)
+    }
+}
+
+// Stored under "shared_mempool" in anykv, required only when "txn_replication" is set
+pub const SHARED_MEMPOOL: &str = "shared_mempool";
+
+#[derive(Default, Clone)]
+pub struct SharedMempool {
+    submitted_until: Jiffies,
+    carry: f64,
+    next_id: TxnId,
+    submitted_at: HashMap<TxnId, Jiffies>, // Until ordered
+    included_at: HashMap<TxnId, Jiffies>,  // First inclusion, until ordered
+    pending: HashMap<ProcessId, BTreeSet<TxnId>>, // Not included by the validator yet, oldest first
+    pub stats: MempoolStats,
+}
+
+impl SharedMempool {
+    // Arrivals of the whole system since the last call: every transaction comes to a validator
+    // in turn and to replication - 1 more validators spread over the ring
+    fn submit_until_now(&mut self, rate: f64, replication: usize, proc_num: usize) {
+        let since = self.submitted_until;
+        let span = (now() - since).0;
+        let arrived = rate * span as f64 + self.carry;
+        let transactions = arrived.floor() as usize;
+        self.carry = arrived - transactions as f64;
+        self.submitted_until = now();
+
+        (0..transactions).for_each(|i| {
+            let id = self.next_id;
+            self.next_id += 1;
+            self.submitted_at
+                .insert(id, Jiffies(since.0 + span * i / transactions));
+            (0..replication.min(proc_num)).for_each(|k| {
+                let validator = (id + k * proc_num / replication) % proc_num + 1;
+                self.pending.entry(validator).or_default().insert(id);
+            });
+        });
+        self.stats.submitted += transactions;
+    }
+
+    fn include(&mut self) -> Batch {
+        let txns: Vec<TxnId> = std::mem::take(self.pending.entry(rank()).or_default())
+            .into_iter()
+            .collect();
+        let arrivals_sum = txns
+            .iter()
+            .filter_map(|id| self.submitted_at.get(id))
+            .map(|at| at.0 as f64)
+            .sum();
+        txns.iter().for_each(|id| {
+            self.included_at.entry(*id).or_insert(now());
+        });
+        self.stats.included += txns.len();
+        Batch {
+            transactions: txns.len(),
+            arrivals_sum,
+            tracked: Shared::new(txns),
+        }
+    }
+}
+
+#[derive(Default, Clone, Copy)]
+pub struct MempoolStats {
+    pub submitted: usize,
+    pub included: usize,        // Inclusions in vertices, duplicates too
+    pub ordered: usize,         // Distinct transactions
+    pub duplicates: usize,      // Inclusions ordered after the transaction was already ordered
+    pub inclusion_latency: f64, // Submission -> first inclusion, total over ordered txns
+    pub ordering_latency: f64,  // First inclusion -> ordered, total over ordered txns
+}
+
+impl MempoolStats {
+    pub fn avg_inclusion_latency(&self) -> f64 {
+        self.inclusion_latency / self.ordered.max(1) as f64
+    }
+
+    pub fn avg_ordering_latency(&self) -> f64 {
+        self.ordering_latency / self.ordered.max(1) as f64
+    }
+
+    pub fn avg_end_to_end_latency(&self) -> f64 {
+        self.avg_inclusion_latency() + self.avg_ordering_latency()
+    }
+}
+
+pub fn mempool_stats() -> MempoolStats {
+    let mut stats = MempoolStats::default();
+    anykv::modify::<SharedMempool>(SHARED_MEMPOOL, |pool| stats = pool.stats);
+    stats
+}
+
+// Validators do not include transactions they saw in vertices of others
+pub(crate) fn record_seen(batch: &Batch, source: ProcessId) {
+    if batch.tracked.is_empty() || source == rank() {
+        return;
+    }
+    anykv::modify::<SharedMempool>(SHARED_MEMPOOL, |pool| {
+        if let Some(pending) = pool.pending.get_mut(&rank()) {
+            batch.tracked.iter().for_each(|id| {
+                pending.remove(id);
+            });
+        }
+    });
+}
+
+// Stored under "txn_stats" in anykv, required only when "txn_rate" is set
+#[derive(Default, Clone)]
+pub struct TxnStats {
+    pub committed: usize,
+    pub total_latency: f64,           // Arrival -> ordered
+    pub total_execution_latency: f64, // Arrival -> executed
+    pub throttled: usize,             // Vertices left without transactions by "exec_lag_limit"
+}
+
+impl TxnStats {
+    pub fn avg_latency(&self) -> f64 {
+        if self.committed == 0 {
+            return 0.0;
+        }
+        self.total_latency / self.committed as f64
+    }
+
+    pub fn avg_execution_latency(&self) -> f64 {
+        if self.committed == 0 {
+            return 0.0;
+        }
+        self.total_execution_latency / self.committed as f64
+    }
+}
+
+pub(crate) fn record_commit(batch: &Batch, executed_at: Jiffies) {
+    if batch.transactions == 0 {
+        return;
+    }
+    anykv::modify::<TxnStats>("txn_stats", |stats| {
+        stats.committed += batch.transactions;
+        stats.total_latency += batch.total_latency(now());
+        stats.total_execution_latency += batch.total_latency(executed_at);
+    });
+    if batch.tracked.is_empty() {
+        return;
+    }
+    anykv::modify::<SharedMempool>(SHARED_MEMPOOL, |pool| {
+        batch.tracked.iter().for_each(|id| {
+            let Some(submitted_at) = pool.submitted_at.remove(id) else {
+                pool.stats.duplicates += 1;
+                return;
+            };
+            let included_at = pool.included_at.remove(id).expect("Should be included");
+            pool.stats.ordered += 1;
+            pool.stats.inclusion_latency += (included_at - submitted_at).0 as f64;
+            pool.stats.ordering_latency += (now() - included_at).0 as f64;
+        });
+    });
+}
@@ -0,0 +1,46 @@
+use dscale::{global::anykv, *};
+
+pub const REPLICAS: &str = "Replicas";
+pub const PRIMARY: usize = 0; // Index of the primary within REPLICAS
+
+pub enum Request {
+    Write,
+    Ack(usize), // Index of the acknowledging replica within its pool
+}
+
+impl Message for Request {}
+
+// Clients write to the primary by its position in the pool, not by a process id
+#[derive(Default)]
+pub struct Client;
+
+impl ProcessHandle for Client {
+    fn start(&mut self) {
+        let primary = pool_member(REPLICAS, PRIMARY).expect("No primary");
+        send_to(primary, Request::Write);
+    }
+
+    fn on_message(&mut self, from: ProcessId, message: MessagePtr) {
+        if let Request::Ack(index) = *message.as_type::<Request>() {
+            anykv::modify::<Vec<(ProcessId, usize)>>("acks", |acks| acks.push((from, index)));
+        }
+    }
+
+    fn on_timer(&mut self, _id: TimerId) {}
+}
+
+#[derive(Default)]
+pub struct Replica;
+
+impl ProcessHandle for Replica {
+    fn start(&mut self) {}
+
+    fn on_message(&mut self, from: ProcessId, message: MessagePtr) {
+        if let Request::Write = *message.as_type::<Request>() {
+            let (_, index) = pool_of(rank()).expect("Replica outside of any pool");
+            send_to(from, Request::Ack(index));
+        }
+    }
+
+    fn on_timer(&mut self, _id: TimerId) {}
+}
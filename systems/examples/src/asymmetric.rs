@@ -0,0 +1,51 @@
+use dscale::{global::anykv, *};
+
+pub const ROUNDS: usize = 50;
+
+pub enum Probe {
+    Ping(Jiffies),          // Sent at
+    Pong(Jiffies, Jiffies), // Ping sent at, ping received at
+}
+
+impl Message for Probe {}
+
+// Ground stations probe a satellite and split every round trip into one-way delays
+#[derive(Default)]
+pub struct GroundStation {
+    rounds: usize,
+}
+
+impl ProcessHandle for GroundStation {
+    fn start(&mut self) {
+        send_random_from_pool("Satellite", Probe::Ping(now()));
+    }
+
+    fn on_message(&mut self, from: ProcessId, message: MessagePtr) {
+        if let Probe::Pong(sent, received) = *message.as_type::<Probe>() {
+            anykv::modify::<Vec<(usize, usize)>>("delays", |delays| {
+                delays.push(((received - sent).0, (now() - received).0))
+            });
+            self.rounds += 1;
+            if self.rounds < ROUNDS {
+                send_to(from, Probe::Ping(now()));
+            }
+        }
+    }
+
+    fn on_timer(&mut self, _id: TimerId) {}
+}
+
+#[derive(Default)]
+pub struct Satellite;
+
+impl ProcessHandle for Satellite {
+    fn start(&mut self) {}
+
+    fn on_message(&mut self, from: ProcessId, message: MessagePtr) {
+        if let Probe::Ping(sent) = *message.as_type::<Probe>() {
+            send_to(from, Probe::Pong(sent, now()));
+        }
+    }
+
+    fn on_timer(&mut self, _id: TimerId) {}
+}
@@ -0,0 +1,48 @@
+use dscale::{global::anykv, *};
+use examples::addressing::{Client, PRIMARY, REPLICAS, Replica};
+
+fn run(builder: SimulationBuilder, order: &str) {
+    anykv::set::<Vec<(ProcessId, usize)>>("acks", Vec::new());
+
+    let mut sim = builder
+        .latency_topology(&[LatencyDescription::WithinPool(
+            GLOBAL_POOL,
+            Distributions::Uniform(Jiffies(1), Jiffies(10)),
+        )])
+        .time_budget(Jiffies(1_000))
+        .seed(42)
+        .build();
+
+    // Nothing happens after the acks, so don't run() into a deadlock
+    sim.step_until(Jiffies(1_000));
+
+    let primary = pool_member(REPLICAS, PRIMARY).unwrap();
+    println!("{order}: primary is P{primary}");
+    assert_eq!(pool_of(primary), Some((REPLICAS.to_string(), PRIMARY)));
+    assert_eq!(pool_member(REPLICAS, 3), None);
+    assert_eq!(pool_member("Nonexistent", 0), None);
+
+    // Every client reached the primary, wherever the builder placed it
+    let acks = anykv::get::<Vec<(ProcessId, usize)>>("acks");
+    assert_eq!(acks.len(), 2);
+    for (from, index) in acks {
+        assert_eq!((from, index), (primary, PRIMARY));
+    }
+}
+
+fn main() {
+    println!("=== Pool Addressing Example ===\n");
+
+    run(
+        SimulationBuilder::default()
+            .add_pool::<Replica>(REPLICAS, 3)
+            .add_pool::<Client>("Clients", 2),
+        "Replicas first",
+    );
+    run(
+        SimulationBuilder::default()
+            .add_pool::<Client>("Clients", 2)
+            .add_pool::<Replica>(REPLICAS, 3),
+        "Clients first",
+    );
+}
@@ -0,0 +1,48 @@
+use dscale::{global::anykv, *};
+use examples::asymmetric::{GroundStation, ROUNDS, Satellite};
+
+fn main() {
+    println!("=== Asymmetric Latency Example ===\n");
+
+    anykv::set::<Vec<(usize, usize)>>("delays", Vec::new());
+
+    let mut sim = SimulationBuilder::default()
+        .add_pool::<GroundStation>("Ground", 3)
+        .add_pool::<Satellite>("Satellite", 1)
+        .latency_topology(&[
+            // Downlink from the satellite is fast...
+            LatencyDescription::BetweenPools(
+                "Ground",
+                "Satellite",
+                Distributions::Uniform(Jiffies(10), Jiffies(20)),
+            ),
+            // ...the uplink is not: overrides one direction of the link above
+            LatencyDescription::OneWayBetweenPools(
+                "Ground",
+                "Satellite",
+                Distributions::Uniform(Jiffies(200), Jiffies(300)),
+            ),
+        ])
+        .time_budget(Jiffies(100_000))
+        .seed(42)
+        .build();
+
+    // Probing stops after ROUNDS, so don't run() into a deadlock
+    sim.step_until(Jiffies(100_000));
+
+    let delays = anykv::get::<Vec<(usize, usize)>>("delays");
+    assert_eq!(delays.len(), 3 * ROUNDS);
+
+    let mean = |delay: fn(&(usize, usize)) -> usize| {
+        delays.iter().map(delay).sum::<usize>() as f64 / delays.len() as f64
+    };
+    let (uplink, downlink) = (mean(|d| d.0), mean(|d| d.1));
+    println!("Mean uplink delay:   {uplink:.1}");
+    println!("Mean downlink delay: {downlink:.1}");
+
+    // Every hop takes one jiffy on top of its latency
+    for (up, down) in &delays {
+        assert!((201..=301).contains(up), "Uplink delay {up}");
+        assert!((11..=21).contains(down), "Downlink delay {down}");
+    }
+}
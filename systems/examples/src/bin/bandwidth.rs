@@ -1,6 +1,6 @@
 use std::time::Instant;
 
-use dscale::{global::anykv, *};
+use dscale::{global::anykv, helpers::Golden, *};
 use examples::bandwidth::{Receiver, Sender};
 
 fn main() {
@@ -49,6 +49,14 @@ fn run_unbounded() -> usize {
         elapsed, sent, received
     );
 
+    Golden::new(&sim)
+        .metric("sent", sent)
+        .metric("received", received)
+        .assert_matches(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/golden/bandwidth_unbounded.golden"
+        ));
+
     received
 }
 
@@ -81,5 +89,13 @@ fn run_bounded() -> usize {
         elapsed, sent, received
     );
 
+    Golden::new(&sim)
+        .metric("sent", sent)
+        .metric("received", received)
+        .assert_matches(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/golden/bandwidth_bounded.golden"
+        ));
+
     received
 }
@@ -0,0 +1,47 @@
+use dscale::{global::anykv, *};
+use examples::colocation::{SENDERS, Sink, Streamer};
+
+fn main() {
+    println!("=== Colocation Example ===\n");
+
+    // Streamers 1, 2 send to sinks 3, 4
+    let separate = run("every process on its own host", &[]);
+    let shared_nic = run("sinks share a host", &[&[3, 4]]);
+    let local = run("streamers next to their sinks", &[&[1, 3], &[2, 4]]);
+
+    // Intra-host messages arrive on the next jiffy
+    assert_eq!(local, 1.0);
+    // 600 bytes per jiffy fit into a NIC of 1000 bytes per jiffy: 1 jiffy to leave + 5 of latency
+    assert_eq!(separate, 6.0);
+    // 1200 bytes per jiffy do not fit into a shared NIC, queues grow
+    assert!(shared_nic > 5.0 * separate);
+}
+
+fn run(name: &str, hosts: &[&[ProcessId]]) -> f64 {
+    anykv::set::<usize>("received", 0);
+    anykv::set::<usize>("total_latency", 0);
+
+    let mut builder = SimulationBuilder::default()
+        .add_pool::<Streamer>("Streamers", SENDERS)
+        .add_pool::<Sink>("Sinks", SENDERS)
+        .nic_bandwidth(BandwidthDescription::Bounded(1000))
+        .latency_topology(&[LatencyDescription::BetweenPools(
+            "Streamers",
+            "Sinks",
+            Distributions::Uniform(Jiffies(5), Jiffies(5)),
+        )])
+        .time_budget(Jiffies(1_000))
+        .seed(42);
+
+    for host in hosts {
+        builder = builder.colocate(host);
+    }
+
+    let mut sim = builder.build();
+    sim.run();
+
+    let received = anykv::get::<usize>("received");
+    let latency = anykv::get::<usize>("total_latency") as f64 / received as f64;
+    println!("{name}: received: {received}, avg latency: {latency:.2}");
+    latency
+}
@@ -0,0 +1,49 @@
+use dscale::{global::anykv, *};
+use examples::contention::Prober;
+
+// Returns mean and max round trip time
+fn probe(background: Option<BackgroundTraffic>) -> (f64, usize) {
+    anykv::set::<Vec<usize>>("rtts", Vec::new());
+
+    let mut builder = SimulationBuilder::default()
+        .add_pool::<Prober>("Replicas", 4)
+        .latency_topology(&[LatencyDescription::WithinPool(
+            "Replicas",
+            Distributions::Uniform(Jiffies(5), Jiffies(10)),
+        )])
+        .nic_bandwidth(BandwidthDescription::Bounded(250))
+        .time_budget(Jiffies(20_000))
+        .seed(42);
+    if let Some(traffic) = background {
+        builder = builder.background_traffic(traffic);
+    }
+    let mut sim = builder.build();
+    sim.run();
+
+    let rtts = anykv::get::<Vec<usize>>("rtts");
+    let mean = rtts.iter().sum::<usize>() as f64 / rtts.len() as f64;
+    (mean, rtts.iter().copied().max().unwrap_or(0))
+}
+
+fn main() {
+    println!("=== Background Traffic Example ===\n");
+
+    let (quiet_mean, quiet_max) = probe(None);
+    let (busy_mean, busy_max) = probe(Some(BackgroundTraffic {
+        from: "Replicas",
+        to: "Replicas",
+        message_size: 1_000,
+        interval: Jiffies(1),
+        on: Distributions::Uniform(Jiffies(100), Jiffies(200)),
+        off: Distributions::Uniform(Jiffies(300), Jiffies(500)),
+    }));
+
+    println!("Idle network:   mean RTT {quiet_mean:.1}, max RTT {quiet_max}");
+    println!("Bursty network: mean RTT {busy_mean:.1}, max RTT {busy_max}");
+
+    // NIC capacity is accounted since the start of the run, so bursts delay
+    // probes only because they outweigh the silences: ~1000 bytes per jiffy
+    // for ~150 jiffies out of ~550 is more than 250 bytes per jiffy on average.
+    assert!(busy_mean > quiet_mean);
+    assert!(busy_max > 2 * quiet_max);
+}
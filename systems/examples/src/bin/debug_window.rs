@@ -0,0 +1,43 @@
+use std::time::Duration;
+
+use dscale::{
+    global::anykv,
+    scenario::{crash, inject_partition},
+    *,
+};
+use examples::partition::Beater;
+
+fn main() {
+    println!("=== Debug Window Example ===\n");
+
+    anykv::set::<Vec<(Jiffies, ProcessId)>>("heard", Vec::new());
+
+    let scenario = Scenario::new()
+        .at(Jiffies(1_000), inject_partition(&[&[1, 2], &[3, 4, 5]]))
+        .at(Jiffies(2_000), crash(3));
+
+    // Watch the crash of P3 closely: every event around it goes to stderr,
+    // each followed by a short pause
+    let mut sim = SimulationBuilder::default()
+        .add_pool::<Beater>("Beaters", 5)
+        .latency_topology(&[LatencyDescription::WithinPool(
+            "Beaters",
+            Distributions::Uniform(Jiffies(1), Jiffies(5)),
+        )])
+        .scenario(scenario)
+        .debug_window(DebugWindow {
+            start: Jiffies(1_995),
+            end: Jiffies(2_005),
+            pace: Duration::from_millis(20),
+        })
+        .time_budget(Jiffies(3_000))
+        .seed(42)
+        .build();
+
+    sim.run();
+
+    println!(
+        "\nP1 received {} heartbeats",
+        anykv::get::<Vec<(Jiffies, ProcessId)>>("heard").len()
+    );
+}
@@ -0,0 +1,50 @@
+use std::{cell::RefCell, rc::Rc};
+
+use dscale::{global::anykv, *};
+use examples::external_feed::{
+    ExchangeFeed, GATEWAY_POOL_NAME, Gateway, TRADERS_POOL_NAME, Trader,
+};
+
+const TRADERS: usize = 4;
+
+// Gateway 1, traders 2..=5
+fn main() {
+    println!("=== External Feed Example ===\n");
+
+    anykv::set::<usize>("ticks", 0);
+    anykv::set::<usize>("last_price", 0);
+
+    let feed = Rc::new(RefCell::new(ExchangeFeed::new(1)));
+
+    let mut sim = SimulationBuilder::default()
+        .add_pool::<Gateway>(GATEWAY_POOL_NAME, 1)
+        .add_pool::<Trader>(TRADERS_POOL_NAME, TRADERS)
+        .add_actor(feed.clone())
+        .latency_topology(&[LatencyDescription::BetweenPools(
+            GATEWAY_POOL_NAME,
+            TRADERS_POOL_NAME,
+            Distributions::Uniform(Jiffies(5), Jiffies(5)),
+        )])
+        .time_budget(Jiffies(2_000))
+        .seed(42)
+        .build();
+
+    // Opening session is known upfront
+    feed.borrow_mut()
+        .submit(&mut (1..=10).map(|k| (Jiffies(k * 50), 100 + k)).collect());
+    sim.step_until(Jiffies(1_000));
+    assert_eq!(anykv::get::<usize>("ticks"), TRADERS * 10);
+
+    // Late quotes arrive while the simulation is paused
+    feed.borrow_mut()
+        .submit(&mut vec![(Jiffies(1_500), 120), (Jiffies(1_200), 115)]);
+    sim.step_until(Jiffies(2_000));
+
+    let published = feed.borrow().published;
+    let ticks = anykv::get::<usize>("ticks");
+    println!("Quotes published: {published}, ticks received: {ticks}");
+
+    assert_eq!(published, 12);
+    assert_eq!(ticks, TRADERS * published);
+    assert_eq!(anykv::get::<usize>("last_price"), 120);
+}
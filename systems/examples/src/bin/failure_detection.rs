@@ -0,0 +1,79 @@
+use dscale::{
+    global::anykv,
+    helpers::failure_detector::Detection,
+    scenario::{crash, restart},
+    *,
+};
+use examples::failure_detection::{PERIOD, WATCHERS, Watcher};
+
+const CRASHED: ProcessId = 3;
+const CRASH_AT: Jiffies = Jiffies(2_000);
+const RESTART_AT: Jiffies = Jiffies(5_000);
+
+fn main() {
+    println!("=== Failure Detection Example ===\n");
+
+    for detector in ["perfect", "eventually perfect", "swim"] {
+        anykv::set::<&'static str>("detector", detector);
+        anykv::set::<Vec<(Jiffies, ProcessId, Detection)>>("detections", Vec::new());
+
+        let mut sim = SimulationBuilder::default()
+            .add_pool::<Watcher>(WATCHERS, 5)
+            .latency_topology(&[LatencyDescription::WithinPool(
+                WATCHERS,
+                Distributions::Normal(Jiffies(20), Jiffies(15)),
+            )])
+            .scenario(
+                Scenario::new()
+                    .at(CRASH_AT, crash(CRASHED))
+                    .at(RESTART_AT, restart(CRASHED)),
+            )
+            .time_budget(Jiffies(8_000))
+            .seed(42)
+            .build();
+        sim.run();
+
+        let detections = anykv::get::<Vec<(Jiffies, ProcessId, Detection)>>("detections");
+        let crashed =
+            |id: ProcessId, at: Jiffies| id == CRASHED && at >= CRASH_AT && at < RESTART_AT;
+        let false_suspicions = detections
+            .iter()
+            .filter(|(at, _, detection)| matches!(detection, Detection::Suspect(id) if !crashed(*id, *at)))
+            .count();
+
+        // Every live observer notices the crash and the restart
+        let observers: Vec<ProcessId> = (1..=5).filter(|id| *id != CRASHED).collect();
+        let first = |observer: ProcessId, expected: Detection, after: Jiffies| {
+            detections
+                .iter()
+                .find(|(at, by, detection)| {
+                    *by == observer && *detection == expected && *at >= after
+                })
+                .map(|(at, _, _)| *at - after)
+                .unwrap_or_else(|| panic!("{detector}: P{observer} missed {expected:?}"))
+        };
+        let mean = |delays: Vec<Jiffies>| {
+            delays.iter().map(|d| d.0).sum::<usize>() as f64 / delays.len() as f64
+        };
+        let detection = mean(
+            observers
+                .iter()
+                .map(|o| first(*o, Detection::Suspect(CRASHED), CRASH_AT))
+                .collect(),
+        );
+        let recovery = mean(
+            observers
+                .iter()
+                .map(|o| first(*o, Detection::Restore(CRASHED), RESTART_AT))
+                .collect(),
+        );
+
+        println!(
+            "{detector:>18}: detection {detection:.1}, recovery {recovery:.1}, false suspicions {false_suspicions}"
+        );
+        if detector == "perfect" {
+            assert_eq!(false_suspicions, 0);
+            assert!(detection <= PERIOD.0 as f64);
+        }
+    }
+}
@@ -0,0 +1,37 @@
+use dscale::{global::anykv, *};
+use examples::firewall::{EQUIVOCATOR, ROUNDS, Voter};
+
+fn main() {
+    println!("=== Message Filter Example ===\n");
+
+    anykv::set::<usize>("blacklisted", 0);
+
+    let mut sim = SimulationBuilder::default()
+        .add_pool::<Voter>("Voters", 4)
+        .latency_topology(&[LatencyDescription::WithinPool(
+            "Voters",
+            Distributions::Uniform(Jiffies(1), Jiffies(5)),
+        )])
+        .time_budget(Jiffies(1_000))
+        .seed(42)
+        .build();
+
+    // Voting stops after ROUNDS, so don't run() into a deadlock
+    sim.step_until(Jiffies(1_000));
+
+    let blacklisted = anykv::get::<usize>("blacklisted");
+    println!("Processes which blacklisted P{EQUIVOCATOR}: {blacklisted}");
+    assert_eq!(blacklisted, 3);
+
+    for id in 1..=4 {
+        let filtered = sim.filtered_messages(id);
+        println!("P{id} filtered out {filtered} messages");
+        // Both votes of every round after the first one are dropped
+        let expected = if id == EQUIVOCATOR {
+            0
+        } else {
+            2 * (ROUNDS - 1)
+        };
+        assert_eq!(filtered, expected);
+    }
+}
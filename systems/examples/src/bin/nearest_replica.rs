@@ -0,0 +1,63 @@
+use dscale::{global::anykv, *};
+use examples::nearest_replica::{Client, Replica};
+
+fn main() {
+    println!("=== Nearest Replica Example ===\n");
+
+    let random = run(false);
+    let nearest = run(true);
+
+    // Nearest replica is always in the same region: 2 hops of 1..=3 jiffies + 1 jiffy each
+    assert!((4.0..=8.0).contains(&nearest));
+    assert!(random > 2.0 * nearest);
+}
+
+fn run(route_to_nearest: bool) -> f64 {
+    anykv::set::<bool>("route_to_nearest", route_to_nearest);
+    anykv::set::<usize>("reads", 0);
+    anykv::set::<usize>("total_latency", 0);
+
+    let mut sim = SimulationBuilder::default()
+        .add_pool_in_region::<Replica>("Replicas", "eu", 2)
+        .add_pool_in_region::<Replica>("Replicas", "us", 2)
+        .add_pool_in_region::<Replica>("Replicas", "asia", 2)
+        .add_pool_in_region::<Client>("Clients", "eu", 3)
+        .add_pool_in_region::<Client>("Clients", "us", 3)
+        .add_pool_in_region::<Client>("Clients", "asia", 3)
+        .latency_topology(&[
+            LatencyDescription::WithinRegion("eu", Distributions::Uniform(Jiffies(1), Jiffies(3))),
+            LatencyDescription::WithinRegion("us", Distributions::Uniform(Jiffies(1), Jiffies(3))),
+            LatencyDescription::WithinRegion(
+                "asia",
+                Distributions::Uniform(Jiffies(1), Jiffies(3)),
+            ),
+            LatencyDescription::BetweenRegions(
+                "eu",
+                "us",
+                Distributions::Uniform(Jiffies(40), Jiffies(45)),
+            ),
+            LatencyDescription::BetweenRegions(
+                "us",
+                "asia",
+                Distributions::Uniform(Jiffies(60), Jiffies(70)),
+            ),
+            LatencyDescription::BetweenRegions(
+                "eu",
+                "asia",
+                Distributions::Uniform(Jiffies(80), Jiffies(90)),
+            ),
+        ])
+        .time_budget(Jiffies(100_000))
+        .seed(42)
+        .build();
+
+    sim.run();
+
+    let reads = anykv::get::<usize>("reads");
+    let latency = anykv::get::<usize>("total_latency") as f64 / reads as f64;
+    println!(
+        "Route to nearest: {}, reads: {}, avg latency: {:.2}\n",
+        route_to_nearest, reads, latency
+    );
+    latency
+}
@@ -0,0 +1,43 @@
+use dscale::{global::anykv, *};
+use examples::ordering::Node;
+
+fn main() {
+    println!("=== Channel Ordering Example ===\n");
+
+    let (reordered, overtaken) = run(ChannelOrdering::Random);
+    assert!(reordered > 0 && overtaken > 0);
+
+    let (reordered, overtaken) = run(ChannelOrdering::Fifo);
+    assert_eq!(reordered, 0);
+    assert!(
+        overtaken > 0,
+        "FIFO does not order messages of different senders"
+    );
+
+    let (reordered, overtaken) = run(ChannelOrdering::Causal);
+    assert_eq!(reordered, 0);
+    assert_eq!(overtaken, 0);
+}
+
+fn run(ordering: ChannelOrdering) -> (usize, usize) {
+    anykv::set::<usize>("reordered", 0);
+    anykv::set::<usize>("overtaken", 0);
+
+    let mut sim = SimulationBuilder::default()
+        .add_pool::<Node>("Nodes", 3)
+        .latency_topology(&[LatencyDescription::WithinPool(
+            "Nodes",
+            Distributions::Uniform(Jiffies(1), Jiffies(20)),
+        )])
+        .channel_ordering("Nodes", "Nodes", ordering)
+        .time_budget(Jiffies(10_000))
+        .seed(42)
+        .build();
+
+    sim.run();
+
+    let reordered = anykv::get::<usize>("reordered");
+    let overtaken = anykv::get::<usize>("overtaken");
+    println!("{ordering:?}: reordered: {reordered}, overtaken by relay: {overtaken}");
+    (reordered, overtaken)
+}
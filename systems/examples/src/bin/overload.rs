@@ -0,0 +1,64 @@
+use dscale::{global::anykv, *};
+use examples::bandwidth::{Receiver, Sender};
+
+const CAPACITY: usize = 16;
+
+fn main() {
+    println!("=== Overload Example ===\n");
+
+    let unbounded = run(InboxDescription::Unbounded);
+    assert_eq!(unbounded.total_dropped(), 0);
+
+    let newest = run(InboxDescription::Bounded(
+        CAPACITY,
+        OverflowPolicy::DropNewest,
+    ));
+    assert!(newest.dropped(2) > 0, "Receiver should be overloaded");
+    assert_eq!(newest.peak_len(2), CAPACITY);
+    assert!(newest.crashed().is_empty());
+
+    let oldest = run(InboxDescription::Bounded(
+        CAPACITY,
+        OverflowPolicy::DropOldest,
+    ));
+    assert!(oldest.dropped(2) > 0, "Receiver should be overloaded");
+    assert_eq!(oldest.peak_len(2), CAPACITY);
+
+    let crash = run(InboxDescription::Bounded(CAPACITY, OverflowPolicy::Crash));
+    assert_eq!(crash.crashed().len(), 1);
+    assert_eq!(crash.crashed()[0].0, 2);
+}
+
+fn run(inbox: InboxDescription) -> InboxStats {
+    anykv::set::<usize>("messages_sent", 0);
+    anykv::set::<usize>("messages_received", 0);
+
+    let mut sim = SimulationBuilder::default()
+        .add_pool::<Sender>("Senders", 1)
+        .add_pool::<Receiver>("Receivers", 1)
+        // Receiver drains one message per 10 jiffies, sender sends one per jiffy
+        .nic_bandwidth(BandwidthDescription::Bounded(100))
+        .inbox(inbox)
+        .latency_topology(&[LatencyDescription::BetweenPools(
+            "Senders",
+            "Receivers",
+            Distributions::Uniform(Jiffies(10), Jiffies(10)),
+        )])
+        .time_budget(Jiffies(1_000))
+        .seed(42)
+        .build();
+
+    sim.run();
+
+    let stats = sim.inbox_stats();
+    println!(
+        "{:?}: sent: {}, received: {}, dropped: {}, peak inbox: {}, crashed: {:?}\n",
+        inbox,
+        anykv::get::<usize>("messages_sent"),
+        anykv::get::<usize>("messages_received"),
+        stats.total_dropped(),
+        stats.peak_len(2),
+        stats.crashed(),
+    );
+    stats
+}
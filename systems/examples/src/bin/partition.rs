@@ -0,0 +1,62 @@
+use dscale::{
+    global::anykv,
+    scenario::{crash, heal, inject_partition, restart},
+    *,
+};
+use examples::partition::Beater;
+
+fn main() {
+    println!("=== Partition Scenario Example ===\n");
+
+    anykv::set::<Vec<(Jiffies, ProcessId)>>("heard", Vec::new());
+
+    let scenario = Scenario::new()
+        .at(Jiffies(1_000), inject_partition(&[&[1, 2], &[3, 4, 5]]))
+        .at(Jiffies(2_000), crash(3))
+        .at(Jiffies(3_000), heal())
+        .at(Jiffies(4_000), restart(3));
+
+    let mut sim = SimulationBuilder::default()
+        .add_pool::<Beater>("Beaters", 5)
+        .latency_topology(&[LatencyDescription::WithinPool(
+            "Beaters",
+            Distributions::Uniform(Jiffies(1), Jiffies(5)),
+        )])
+        .scenario(scenario)
+        .time_budget(Jiffies(5_000))
+        .seed(42)
+        .build();
+
+    sim.run();
+
+    let heard = anykv::get::<Vec<(Jiffies, ProcessId)>>("heard");
+    let heard_from = |from: usize, to: usize| -> Vec<ProcessId> {
+        let mut peers: Vec<ProcessId> = heard
+            .iter()
+            .filter(|(at, _)| (Jiffies(from)..Jiffies(to)).contains(at))
+            .map(|(_, peer)| *peer)
+            .collect();
+        peers.sort();
+        peers.dedup();
+        peers
+    };
+
+    let phases = [
+        ("connected", heard_from(0, 1_000), vec![1, 2, 3, 4, 5]),
+        ("partitioned", heard_from(1_000, 3_000), vec![1, 2]),
+        (
+            "healed, P3 crashed",
+            heard_from(3_000, 4_000),
+            vec![1, 2, 4, 5],
+        ),
+        (
+            "P3 restarted",
+            heard_from(4_000, 5_000),
+            vec![1, 2, 3, 4, 5],
+        ),
+    ];
+    for (phase, peers, expected) in phases {
+        println!("{phase:<20} P1 heard from {peers:?}");
+        assert_eq!(peers, expected);
+    }
+}
@@ -0,0 +1,57 @@
+use dscale::{
+    global::{anykv, disk},
+    *,
+};
+use examples::persistence::{Replica, Writer};
+
+fn main() {
+    println!("=== Persistence Example ===\n");
+
+    let instant = run(DiskDescription::default());
+    let ssd = run(DiskDescription {
+        fsync_latency: Jiffies(2),
+        throughput: Some(1000),
+        ..Default::default()
+    });
+    let hdd = run(DiskDescription {
+        fsync_latency: Jiffies(8),
+        throughput: Some(500),
+        ..Default::default()
+    });
+
+    // Network round trip is 12 jiffies
+    assert_eq!(instant, 12.0);
+    // 2 jiffies of latency + 1 jiffy to write an entry
+    assert_eq!(ssd, 15.0);
+    // 10 jiffies per flush, so flushes still keep up with appends
+    assert_eq!(hdd, 22.0);
+}
+
+fn run(description: DiskDescription) -> f64 {
+    anykv::set::<usize>("acked", 0);
+    anykv::set::<usize>("total_latency", 0);
+
+    let mut sim = SimulationBuilder::default()
+        .add_pool::<Writer>("Writers", 1)
+        .add_pool::<Replica>("Replicas", 1)
+        .disk(description)
+        .latency_topology(&[LatencyDescription::BetweenPools(
+            "Writers",
+            "Replicas",
+            Distributions::Uniform(Jiffies(5), Jiffies(5)),
+        )])
+        .time_budget(Jiffies(10_000))
+        .seed(42)
+        .build();
+
+    sim.run();
+
+    let acked = anykv::get::<usize>("acked");
+    let latency = anykv::get::<usize>("total_latency") as f64 / acked as f64;
+    let (written, fsyncs) = disk::usage(2);
+    println!(
+        "{:?}: acked: {}, avg latency: {:.2}, written: {}, fsyncs: {}\n",
+        description, acked, latency, written, fsyncs
+    );
+    latency
+}
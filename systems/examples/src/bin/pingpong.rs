@@ -1,6 +1,6 @@
 use std::time::Instant;
 
-use dscale::{global::anykv, *};
+use dscale::{global::anykv, helpers::Golden, *};
 use examples::pingpong::PingPongProcess;
 
 fn main() {
@@ -34,4 +34,12 @@ fn main() {
         (anykv::get::<usize>("pings") + anykv::get::<usize>("pongs")) as f64
             / elapsed.as_secs_f64()
     );
+
+    Golden::new(&sim)
+        .metric("pings", anykv::get::<usize>("pings"))
+        .metric("pongs", anykv::get::<usize>("pongs"))
+        .assert_matches(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/golden/pingpong.golden"
+        ));
 }
@@ -0,0 +1,63 @@
+use std::{any::Any, collections::BTreeMap, net::TcpListener, thread};
+
+use dscale::{
+    global::anykv,
+    message::{Shared, SharedMessage},
+    transport::{Codec, tcp::TcpNode},
+    *,
+};
+use examples::pingpong::{PingPongMessage, PingPongProcess};
+
+struct PingPongCodec;
+
+impl Codec for PingPongCodec {
+    fn encode(&self, message: &dyn Message) -> Vec<u8> {
+        match (message as &dyn Any).downcast_ref::<PingPongMessage>() {
+            Some(PingPongMessage::Ping) => vec![0],
+            Some(PingPongMessage::Pong) => vec![1],
+            None => panic!("Unknown message"),
+        }
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Option<SharedMessage> {
+        match bytes {
+            [0] => Some(Shared::new(PingPongMessage::Ping)),
+            [1] => Some(Shared::new(PingPongMessage::Pong)),
+            _ => None,
+        }
+    }
+}
+
+// The same process as in the pingpong simulation, hosted on two threads talking over localhost
+fn main() {
+    println!("=== PingPong over TCP Example ===\n");
+
+    let listeners: BTreeMap<ProcessId, TcpListener> = (1..=2)
+        .map(|id| (id, TcpListener::bind("127.0.0.1:0").unwrap()))
+        .collect();
+    let peers: BTreeMap<_, _> = listeners
+        .iter()
+        .map(|(id, listener)| (*id, listener.local_addr().unwrap()))
+        .collect();
+
+    let nodes: Vec<_> = listeners
+        .into_iter()
+        .map(|(id, listener)| {
+            let peers = peers.clone();
+            thread::spawn(move || {
+                anykv::set::<usize>("pings", 0);
+                anykv::set::<usize>("pongs", 0);
+                TcpNode::new(id, listener, peers, PingPongCodec)
+                    .run(PingPongProcess::default(), Jiffies(500))
+                    .unwrap();
+                anykv::get::<usize>("pings") + anykv::get::<usize>("pongs")
+            })
+        })
+        .collect();
+
+    let sent: Vec<usize> = nodes.into_iter().map(|n| n.join().unwrap()).collect();
+    println!("Pings sent: {}, Pongs sent: {}", sent[0], sent[1]);
+
+    assert!(sent[0] > 0 && sent[1] > 0);
+    assert!(sent[0].abs_diff(sent[1]) <= 1);
+}
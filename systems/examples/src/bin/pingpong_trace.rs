@@ -0,0 +1,30 @@
+use dscale::{
+    global::{anykv, tracing},
+    *,
+};
+use examples::pingpong::PingPongProcess;
+
+fn main() {
+    let mut sim = SimulationBuilder::default()
+        .add_pool::<PingPongProcess>("ExamplePool", 2)
+        .latency_topology(&[LatencyDescription::WithinPool(
+            "ExamplePool",
+            Distributions::Uniform(Jiffies(0), Jiffies(10)),
+        )])
+        .time_budget(Jiffies(100))
+        .trace_messages(true)
+        .seed(5)
+        .build();
+
+    anykv::set::<usize>("pings", 0);
+    anykv::set::<usize>("pongs", 0);
+
+    sim.run();
+
+    // Every hop replies to the previous one, so the whole exchange is a single trace
+    let trace = tracing::trace_of(1).expect("First Ping is not traced");
+    print!("{}", tracing::dump(trace));
+
+    let hops = anykv::get::<usize>("pings") + anykv::get::<usize>("pongs");
+    assert_eq!(tracing::dump(trace).matches("sent at").count(), hops + 1);
+}
@@ -0,0 +1,35 @@
+use dscale::{helpers::log_capture, *};
+use examples::postmortem::{PEERS_POOL_NAME, Peer};
+use log::Level;
+
+const LINES: usize = 4;
+
+fn main() {
+    println!("=== Postmortem Example ===\n");
+
+    // Only warnings are kept: a panicking peer would print its last ones
+    let mut sim = SimulationBuilder::default()
+        .add_pool::<Peer>(PEERS_POOL_NAME, 5)
+        .latency_topology(&[LatencyDescription::WithinPool(
+            PEERS_POOL_NAME,
+            Distributions::Uniform(Jiffies(1), Jiffies(30)),
+        )])
+        .capture_logs(LINES, Level::Warn)
+        .time_budget(Jiffies(1_000))
+        .seed(42)
+        .build();
+
+    sim.run();
+
+    for peer in list_pool(PEERS_POOL_NAME) {
+        let lines = log_capture::recent_logs(peer);
+        println!("Last warnings of P{peer}:");
+        lines
+            .iter()
+            .for_each(|line| println!("  {}: {}", line.at.0, line.message));
+
+        assert_eq!(lines.len(), LINES);
+        assert!(lines.iter().all(|line| line.level == Level::Warn));
+        assert!(lines.windows(2).all(|pair| pair[0].at <= pair[1].at));
+    }
+}
@@ -0,0 +1,47 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use dscale::{global::anykv, *};
+use examples::quorum::{QUORUM, QuorumClient, READS, REPLICAS, Replica};
+
+fn main() {
+    println!("=== Quorum Reads Example ===\n");
+
+    anykv::set::<usize>("hellos", 0);
+    anykv::set::<Vec<(ProcessId, usize, ProcessId)>>("replies", Vec::new());
+
+    let mut sim = SimulationBuilder::default()
+        .add_pool::<Replica>(REPLICAS, 5)
+        .add_pool::<QuorumClient>("Clients", 2)
+        .latency_topology(&[LatencyDescription::WithinPool(
+            GLOBAL_POOL,
+            Distributions::Uniform(Jiffies(1), Jiffies(10)),
+        )])
+        .time_budget(Jiffies(2_000))
+        .seed(42)
+        .build();
+
+    // Clients stop after READS, so don't run() into a deadlock
+    sim.step_until(Jiffies(2_000));
+
+    // Every replica got a hello from every client, clients got none
+    let hellos = anykv::get::<usize>("hellos");
+    println!("Hellos received by replicas: {hellos}");
+    assert_eq!(hellos, 5 * 2);
+
+    let mut quorums: BTreeMap<(ProcessId, usize), BTreeSet<ProcessId>> = BTreeMap::new();
+    for (client, read, replica) in anykv::get::<Vec<(ProcessId, usize, ProcessId)>>("replies") {
+        assert!(list_pool(REPLICAS).contains(&replica));
+        assert!(quorums.entry((client, read)).or_default().insert(replica));
+    }
+    assert_eq!(quorums.len(), 2 * READS);
+
+    let mut load: BTreeMap<ProcessId, usize> = BTreeMap::new();
+    for ((client, read), replicas) in &quorums {
+        assert_eq!(replicas.len(), QUORUM, "Read {read} of P{client}");
+        replicas
+            .iter()
+            .for_each(|replica| *load.entry(*replica).or_default() += 1);
+    }
+    println!("Reads served by replicas: {load:?}");
+    assert_eq!(load.len(), 5); // Quorums are spread over the whole pool
+}
@@ -0,0 +1,65 @@
+use dscale::{global::anykv, *};
+use examples::ramp::{CLIENT_POOL_NAME, Client, SERVER_POOL_NAME, Server, Stop};
+
+const CLIENTS: usize = 4;
+const PHASE: Jiffies = Jiffies(1_000);
+
+// Server 1, clients 2..=5
+fn main() {
+    println!("=== Workload Ramp Example ===\n");
+
+    anykv::set::<Jiffies>("ramp_interval", Jiffies(20));
+    anykv::set::<usize>("served", 0);
+    anykv::set::<Vec<usize>>("served_by_phase", Vec::new());
+
+    let mut sim = SimulationBuilder::default()
+        .add_pool::<Server>(SERVER_POOL_NAME, 1)
+        .add_pool::<Client>(CLIENT_POOL_NAME, CLIENTS)
+        .latency_topology(&[LatencyDescription::BetweenPools(
+            SERVER_POOL_NAME,
+            CLIENT_POOL_NAME,
+            Distributions::Uniform(Jiffies(1), Jiffies(5)),
+        )])
+        .time_budget(Jiffies(5_000))
+        .seed(42)
+        .build();
+
+    // The driver owns the ramp: processes only read the current interval
+    let injector = sim.injector();
+    for (phase, interval) in [(1, Jiffies(10)), (2, Jiffies(5))] {
+        injector.at(Jiffies(PHASE.0 * phase), move || {
+            anykv::set::<Jiffies>("ramp_interval", interval)
+        });
+    }
+    for phase in 1..=4 {
+        injector.at(Jiffies(PHASE.0 * phase), || {
+            let served = anykv::get::<usize>("served");
+            anykv::modify::<Vec<usize>>("served_by_phase", |phases| phases.push(served));
+        });
+    }
+    sim.step_until(Jiffies(2_500));
+
+    // Decided while the simulation is paused: the server stops every client
+    list_pool(CLIENT_POOL_NAME)
+        .into_iter()
+        .for_each(|client| injector.send_at(Jiffies(3_000), 1, client, Stop));
+    assert_eq!(injector.pending(), 2 + CLIENTS);
+    sim.step_until(Jiffies(5_000));
+
+    let mut phases = anykv::get::<Vec<usize>>("served_by_phase");
+    phases.push(anykv::get::<usize>("served"));
+    let served: Vec<usize> = phases
+        .iter()
+        .scan(0, |previous, total| {
+            let served = total - *previous;
+            *previous = *total;
+            Some(served)
+        })
+        .collect();
+    println!("Requests served by phase: {served:?}");
+
+    // Every client doubles its rate in the second and the third phases
+    assert!((served[1] as f64 / served[0] as f64 - 2.0).abs() < 0.1);
+    assert!((served[2] as f64 / served[1] as f64 - 2.0).abs() < 0.1);
+    assert_eq!(served[4], 0);
+}
@@ -0,0 +1,62 @@
+use dscale::{
+    global::anykv,
+    scenario::{crash, heal, inject_partition},
+    *,
+};
+use examples::reachability::Observer;
+
+const STALENESS: Jiffies = Jiffies(250);
+
+fn main() {
+    println!("=== Reachable Pool Example ===\n");
+
+    let fresh = run(Jiffies(0));
+    let stale = run(STALENESS);
+
+    let views = [
+        (Jiffies(900), vec![1, 2, 3, 4, 5], vec![1, 2, 3, 4, 5]),
+        (Jiffies(1_100), vec![1, 2], vec![1, 2, 3, 4, 5]),
+        (Jiffies(1_300), vec![1, 2], vec![1, 2]),
+        (Jiffies(2_100), vec![1], vec![1, 2]),
+        (Jiffies(3_100), vec![1, 3, 4, 5], vec![1]),
+        (Jiffies(3_300), vec![1, 3, 4, 5], vec![1, 3, 4, 5]),
+    ];
+    for (at, expected_fresh, expected_stale) in views {
+        let (fresh, stale) = (view(&fresh, at), view(&stale, at));
+        println!(
+            "at {:>5}: reachable {fresh:?}, {} jiffies stale {stale:?}",
+            at.0, STALENESS.0
+        );
+        assert_eq!(fresh, expected_fresh);
+        assert_eq!(stale, expected_stale);
+    }
+}
+
+fn view(log: &[(Jiffies, Vec<ProcessId>)], at: Jiffies) -> Vec<ProcessId> {
+    log.iter()
+        .find(|(logged, _)| *logged == at)
+        .map(|(_, reachable)| reachable.clone())
+        .expect("P1 logs every period")
+}
+
+// P1 ends up alone on its side: P2 crashes, then the partition heals
+fn run(staleness: Jiffies) -> Vec<(Jiffies, Vec<ProcessId>)> {
+    anykv::set::<Vec<(Jiffies, Vec<ProcessId>)>>("reachable", Vec::new());
+
+    let scenario = Scenario::new()
+        .at(Jiffies(1_000), inject_partition(&[&[1, 2], &[3, 4, 5]]))
+        .at(Jiffies(2_000), crash(2))
+        .at(Jiffies(3_000), heal());
+
+    let mut sim = SimulationBuilder::default()
+        .add_pool::<Observer>("Members", 5)
+        .scenario(scenario)
+        .reachability_staleness(staleness)
+        .time_budget(Jiffies(4_000))
+        .seed(42)
+        .build();
+
+    sim.run();
+
+    anykv::get::<Vec<(Jiffies, Vec<ProcessId>)>>("reachable")
+}
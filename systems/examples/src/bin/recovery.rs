@@ -0,0 +1,42 @@
+use dscale::{global::anykv, *};
+use examples::recovery::Appender;
+
+fn main() {
+    println!("=== Recovery Example ===\n");
+
+    // 100 entries appended before the crash, sync of the last 5 is still in flight
+    let unsynced = run(CrashTruncation::DropUnsynced);
+    assert_eq!(unsynced, 95);
+
+    let all = run(CrashTruncation::KeepAll);
+    assert_eq!(all, 100);
+
+    let torn = run(CrashTruncation::TornTail);
+    assert!((95..=100).contains(&torn));
+}
+
+fn run(crash_truncation: CrashTruncation) -> usize {
+    let mut sim = SimulationBuilder::default()
+        .add_pool::<Appender>("Appenders", 1)
+        .disk(DiskDescription {
+            fsync_latency: Jiffies(20),
+            throughput: Some(100),
+            crash_truncation,
+        })
+        .time_budget(Jiffies(2_000))
+        .seed(42)
+        .build();
+
+    sim.step_until(Jiffies(1005));
+    sim.restart(1);
+    let recovered = anykv::get::<usize>("recovered");
+
+    sim.run(); // Recovered instance keeps appending after the surviving prefix
+    println!(
+        "{:?}: recovered: {}, entries at the end: {}\n",
+        crash_truncation,
+        recovered,
+        anykv::get::<usize>("log_len"),
+    );
+    recovered
+}
@@ -0,0 +1,50 @@
+use dscale::{global::anykv, *};
+use examples::relay_tree::Member;
+
+const COMMITTEE: usize = 150;
+
+// A proposer broadcasts a 1 MB block to a committee of 150 over 1 Gbit/s NICs.
+// A plain broadcast assumes the proposer sends all 149 copies for free. A relay
+// tree of depth one makes the proposer's NIC send them one after another, deeper
+// trees spread the copies over the members at the cost of extra hops.
+fn main() {
+    println!("=== Relay Tree Broadcast Example ===\n");
+
+    let free = run("free", None);
+    let flat = run("Depth(1)", Some(RelayTree::Depth(1)));
+    assert!(free < flat);
+    for tree in [
+        RelayTree::Fanout(2),
+        RelayTree::Fanout(8),
+        RelayTree::Depth(2),
+    ] {
+        let relayed = run(&format!("{tree:?}"), Some(tree));
+        assert!(free < relayed && relayed < flat);
+    }
+}
+
+// Returns the time the last member got the block
+fn run(name: &str, tree: Option<RelayTree>) -> Jiffies {
+    anykv::set::<Vec<Jiffies>>("received", Vec::new());
+
+    let mut builder = SimulationBuilder::default()
+        .add_pool::<Member>("Committee", COMMITTEE)
+        .latency_topology(&[LatencyDescription::WithinPool(
+            "Committee",
+            Distributions::Uniform(Jiffies(5), Jiffies(10)),
+        )])
+        .nic_bandwidth(BandwidthDescription::Bounded(125_000)) // 1 Gbit/s in bytes per millisecond
+        .seed(11);
+    if let Some(tree) = tree {
+        builder = builder.broadcast_tree("Committee", tree);
+    }
+    let mut sim = builder.build();
+    sim.step_until(Jiffies(10_000));
+
+    let received = anykv::get::<Vec<Jiffies>>("received");
+    assert_eq!(received.len(), COMMITTEE);
+    let last = received.iter().max().copied().expect("Block was received");
+    let average = received.iter().map(|at| at.0).sum::<usize>() as f64 / received.len() as f64;
+    println!("{name:<10} last member: {last}, average: {average:.1}");
+    last
+}
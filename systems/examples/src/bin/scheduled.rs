@@ -0,0 +1,32 @@
+use dscale::{global::anykv, *};
+use examples::scheduled::{BEATS, Beacon};
+
+fn main() {
+    println!("=== Scheduled Sends Example ===\n");
+
+    anykv::set::<usize>("beats", 0);
+    anykv::set::<usize>("reminders", 0);
+
+    let mut sim = SimulationBuilder::default()
+        .add_pool::<Beacon>("Beacons", 4)
+        .latency_topology(&[LatencyDescription::WithinPool(
+            "Beacons",
+            Distributions::Uniform(Jiffies(5), Jiffies(5)),
+        )])
+        .time_budget(Jiffies(2_000))
+        .seed(42)
+        .build();
+
+    // First incarnation sends 4 beats, the rest of its schedule dies with it
+    sim.step_until(Jiffies(450));
+    sim.restart(1);
+    // Nothing is left to do once the schedule is over, so don't run() into a deadlock
+    sim.step_until(Jiffies(2_000));
+
+    let beats = anykv::get::<usize>("beats");
+    let reminders = anykv::get::<usize>("reminders");
+    println!("Beats received: {beats}, reminders received: {reminders}");
+
+    assert_eq!(beats, 4 * (4 + BEATS));
+    assert_eq!(reminders, 1);
+}
@@ -0,0 +1,40 @@
+use dscale::{global::anykv, *};
+use examples::startup::{Eager, Patient};
+
+const PROCESSES: usize = 10;
+
+// Processes greeting everyone from start() hear all greetings only if they all
+// start at once. Spread over 100 jiffies, early greetings reach processes which
+// are not up yet and get lost; waiting for the start barrier fixes it.
+fn main() {
+    println!("=== Startup Jitter Example ===\n");
+
+    let all = PROCESSES * PROCESSES;
+    assert_eq!(run::<Eager>("eager, simultaneous", None), all);
+    assert!(run::<Eager>("eager, jittered", Some(Jiffies(100))) < all);
+    assert_eq!(run::<Patient>("patient, jittered", Some(Jiffies(100))), all);
+}
+
+fn run<P: ProcessHandle + Default + 'static>(name: &str, jitter: Option<Jiffies>) -> usize {
+    anykv::set::<usize>("hellos", 0);
+
+    let mut builder = SimulationBuilder::default()
+        .add_pool::<P>("Nodes", PROCESSES)
+        .latency_topology(&[LatencyDescription::WithinPool(
+            "Nodes",
+            Distributions::Uniform(Jiffies(1), Jiffies(5)),
+        )])
+        .seed(8);
+    if let Some(jitter) = jitter {
+        builder = builder.startup_jitter(Distributions::Uniform(Jiffies(0), jitter));
+    }
+    let mut sim = builder.build();
+    sim.step_until(Jiffies(1_000));
+
+    let hellos = anykv::get::<usize>("hellos");
+    println!(
+        "{name:<20} greetings heard: {hellos} of {}",
+        PROCESSES * PROCESSES
+    );
+    hellos
+}
@@ -0,0 +1,44 @@
+use dscale::{global::anykv, helpers::TieBreakAudit, *};
+use examples::tie_breaks::{FirstWriterWins, HighestWriterWins, Writer};
+
+const WRITERS: usize = 4;
+
+fn main() {
+    println!("=== Tie-Break Audit Example ===\n");
+
+    let first = TieBreakAudit::run(8, experiment::<FirstWriterWins>);
+    println!(
+        "First writer wins: baseline winner P{}, {} of 8 shuffled runs diverged",
+        first.baseline(),
+        first.divergent().count()
+    );
+    assert!(!first.is_stable());
+
+    let highest = TieBreakAudit::run(8, experiment::<HighestWriterWins>);
+    println!(
+        "Highest writer wins: baseline winner P{}, {} of 8 shuffled runs diverged",
+        highest.baseline(),
+        highest.divergent().count()
+    );
+    highest.assert_stable();
+}
+
+fn experiment<R: ProcessHandle + Default + 'static>(builder: SimulationBuilder) -> ProcessId {
+    anykv::set::<ProcessId>("winner", 0);
+
+    let mut sim = builder
+        .add_pool::<R>("Register", 1)
+        .add_pool::<Writer>("Writers", WRITERS)
+        .latency_topology(&[LatencyDescription::BetweenPools(
+            "Writers",
+            "Register",
+            Distributions::Uniform(Jiffies(5), Jiffies(5)),
+        )])
+        .time_budget(Jiffies(100))
+        .seed(42)
+        .build();
+
+    // Writers send once, so don't run() into a deadlock
+    sim.step_until(Jiffies(100));
+    anykv::get::<ProcessId>("winner")
+}
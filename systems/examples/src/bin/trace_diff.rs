@@ -0,0 +1,73 @@
+use dscale::{
+    global::{
+        anykv,
+        tracing::{self, RecordedTrace},
+    },
+    helpers::{
+        TraceDiff,
+        trace_diff::{Divergence, DivergenceCause},
+    },
+    *,
+};
+use examples::pingpong::{PingPongMessage, PingPongProcess};
+
+fn run(slow_pongs: bool, seed: u64) -> RecordedTrace {
+    anykv::set::<usize>("pings", 0);
+    anykv::set::<usize>("pongs", 0);
+
+    let mut builder = SimulationBuilder::default()
+        .add_pool::<PingPongProcess>("ExamplePool", 2)
+        .latency_topology(&[LatencyDescription::WithinPool(
+            "ExamplePool",
+            Distributions::Uniform(Jiffies(0), Jiffies(10)),
+        )])
+        .time_budget(Jiffies(100))
+        .trace_messages(true)
+        .seed(seed);
+    if slow_pongs {
+        builder = builder.message_latency_if::<PingPongMessage>(
+            |message| *message == PingPongMessage::Pong,
+            Distributions::Uniform(Jiffies(5), Jiffies(5)),
+        );
+    }
+
+    let mut sim = builder.build();
+    sim.run();
+    tracing::record()
+}
+
+fn main() {
+    println!("=== Trace Diff Example ===\n");
+
+    let baseline = run(false, 5);
+    let slow_pongs = run(true, 5);
+    let reseeded = run(false, 6);
+
+    // Recordings outlive their simulations and can be stored
+    let path = std::env::temp_dir().join("dscale_trace_diff_baseline.trace");
+    baseline.save(&path);
+    assert_eq!(RecordedTrace::load(&path), baseline);
+
+    assert!(TraceDiff::between(&baseline, &baseline).is_identical());
+
+    let diff = TraceDiff::between(&baseline, &slow_pongs);
+    print!("{diff}");
+
+    // Runs agree up to the first Pong, which is the first message slowed down
+    let first = baseline
+        .messages
+        .iter()
+        .find(|message| message.from == 2)
+        .unwrap();
+    assert!(matches!(
+        diff.first_divergence,
+        Some(Divergence::Changed { ref baseline, .. }) if baseline == first
+    ));
+    assert!(diff.missing > 0, "Slower exchange fits fewer hops");
+    assert_eq!(diff.cause(), Some(DivergenceCause::Logic));
+
+    // Same exchange with another seed: processes behave the same, latencies differ
+    let diff = TraceDiff::between(&baseline, &reseeded);
+    print!("\n{diff}");
+    assert_eq!(diff.cause(), Some(DivergenceCause::Randomness));
+}
@@ -0,0 +1,21 @@
+use dscale::{global::anykv, global::configuration, *};
+
+pub const PERIOD: Jiffies = Jiffies(100);
+
+// Every process samples the skew of its local clock, the largest one is kept
+#[derive(Default)]
+pub struct Sampler;
+
+impl ProcessHandle for Sampler {
+    fn start(&mut self) {
+        schedule_timer_after(PERIOD);
+    }
+
+    fn on_message(&mut self, _from: ProcessId, _message: MessagePtr) {}
+
+    fn on_timer(&mut self, _id: TimerId) {
+        let skew = configuration::clock_skew().abs();
+        anykv::modify::<f64>("max_skew", |max| *max = max.max(skew));
+        schedule_timer_after(PERIOD);
+    }
+}
@@ -0,0 +1,46 @@
+use dscale::{global::anykv, *};
+
+pub const CHUNK_SIZE: usize = 600;
+pub const SENDERS: usize = 2;
+
+pub struct Chunk {
+    pub sent_at: Jiffies,
+}
+
+impl Message for Chunk {
+    fn virtual_size(&self) -> usize {
+        CHUNK_SIZE
+    }
+}
+
+// Streams a chunk to its receiver (rank + SENDERS) every jiffy
+#[derive(Default)]
+pub struct Streamer {}
+
+impl ProcessHandle for Streamer {
+    fn start(&mut self) {
+        schedule_timer_after(Jiffies(1));
+    }
+
+    fn on_message(&mut self, _from: ProcessId, _message: MessagePtr) {}
+
+    fn on_timer(&mut self, _id: TimerId) {
+        send_to(rank() + SENDERS, Chunk { sent_at: now() });
+        schedule_timer_after(Jiffies(1));
+    }
+}
+
+#[derive(Default)]
+pub struct Sink {}
+
+impl ProcessHandle for Sink {
+    fn start(&mut self) {}
+
+    fn on_message(&mut self, _from: ProcessId, message: MessagePtr) {
+        let chunk = message.as_type::<Chunk>();
+        anykv::modify::<usize>("received", |x| *x += 1);
+        anykv::modify::<usize>("total_latency", |x| *x += (now() - chunk.sent_at).0);
+    }
+
+    fn on_timer(&mut self, _id: TimerId) {}
+}
@@ -0,0 +1,46 @@
+use dscale::{global::anykv, *};
+
+pub const PERIOD: Jiffies = Jiffies(20);
+
+pub enum Probe {
+    Ping(Jiffies), // Sent at
+    Pong(Jiffies),
+}
+
+impl Message for Probe {
+    fn virtual_size(&self) -> usize {
+        100
+    }
+}
+
+// P1 periodically pings random peers and records round trip times
+#[derive(Default)]
+pub struct Prober;
+
+impl ProcessHandle for Prober {
+    fn start(&mut self) {
+        if rank() == 1 {
+            schedule_timer_after(PERIOD);
+        }
+    }
+
+    fn on_message(&mut self, from: ProcessId, message: MessagePtr) {
+        match *message.as_type::<Probe>() {
+            Probe::Ping(sent) => send_to(from, Probe::Pong(sent)),
+            Probe::Pong(sent) => {
+                anykv::modify::<Vec<usize>>("rtts", |rtts| rtts.push((now() - sent).0))
+            }
+        }
+    }
+
+    fn on_timer(&mut self, _id: TimerId) {
+        let peer = loop {
+            let peer = choose_from_pool("Replicas");
+            if peer != rank() {
+                break peer;
+            }
+        };
+        send_to(peer, Probe::Ping(now()));
+        schedule_timer_after(PERIOD);
+    }
+}
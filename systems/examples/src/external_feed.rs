@@ -0,0 +1,84 @@
+use std::collections::VecDeque;
+
+use dscale::{actor::as_process, global::anykv, *};
+
+pub const GATEWAY_POOL_NAME: &str = "Gateway";
+pub const TRADERS_POOL_NAME: &str = "Traders";
+
+pub struct Tick {
+    pub price: usize,
+}
+
+impl Message for Tick {}
+
+// Quotes of an exchange outside of the simulated system. The feed is not a
+// process: it publishes quotes at their times through the gateway process
+pub struct ExchangeFeed {
+    gateway: ProcessId,
+    quotes: VecDeque<(Jiffies, usize)>,
+    pub published: usize,
+}
+
+impl ExchangeFeed {
+    pub fn new(gateway: ProcessId) -> Self {
+        Self {
+            gateway,
+            quotes: VecDeque::new(),
+            published: 0,
+        }
+    }
+}
+
+impl SimulationActor for ExchangeFeed {
+    fn start(&mut self) {}
+
+    fn step(&mut self) {
+        let (_, price) = self.quotes.pop_front().expect("No quotes");
+        as_process(self.gateway, || {
+            broadcast_within_pool(TRADERS_POOL_NAME, Tick { price })
+        });
+        self.published += 1;
+    }
+
+    fn peek_closest(&self) -> Option<Jiffies> {
+        self.quotes.front().map(|(at, _)| *at)
+    }
+}
+
+// Quotes may be fed at any time, also between steps of the simulation
+impl EventSubmitter for ExchangeFeed {
+    type Event = (Jiffies, usize);
+
+    fn submit(&mut self, events: &mut Vec<Self::Event>) {
+        self.quotes.extend(events.drain(..));
+        self.quotes.make_contiguous().sort();
+    }
+}
+
+#[derive(Default)]
+pub struct Gateway {}
+
+impl ProcessHandle for Gateway {
+    fn start(&mut self) {}
+
+    fn on_message(&mut self, _from: ProcessId, _message: MessagePtr) {}
+
+    fn on_timer(&mut self, _id: TimerId) {}
+}
+
+#[derive(Default)]
+pub struct Trader {}
+
+impl ProcessHandle for Trader {
+    fn start(&mut self) {}
+
+    fn on_message(&mut self, from: ProcessId, message: MessagePtr) {
+        let price = message.as_type::<Tick>().price;
+        debug_process!("Quote {price} from P{from}");
+        assert_eq!(pool_member(GATEWAY_POOL_NAME, 0), Some(from));
+        anykv::modify::<usize>("ticks", |ticks| *ticks += 1);
+        anykv::set::<usize>("last_price", price);
+    }
+
+    fn on_timer(&mut self, _id: TimerId) {}
+}
@@ -0,0 +1,66 @@
+use dscale::{
+    global::anykv,
+    helpers::failure_detector::{
+        Detection, EventuallyPerfectDetector, FailureDetector, PerfectDetector, SwimDetector,
+    },
+    *,
+};
+
+pub const WATCHERS: &str = "Watchers";
+pub const PERIOD: Jiffies = Jiffies(50);
+
+// Detector under evaluation, chosen with anykv "detector"
+fn configured_detector() -> Box<dyn FailureDetector> {
+    match anykv::get::<&'static str>("detector") {
+        "perfect" => Box::new(PerfectDetector::new(WATCHERS, PERIOD)),
+        "eventually perfect" => Box::new(EventuallyPerfectDetector::new(
+            WATCHERS,
+            PERIOD,
+            Jiffies(2 * PERIOD.0),
+        )),
+        // Indirect probes need about 4 one-way delays before the period ends
+        "swim" => Box::new(SwimDetector::new(
+            WATCHERS,
+            Jiffies(4 * PERIOD.0),
+            Jiffies(3 * PERIOD.0 / 2),
+            2,
+        )),
+        other => panic!("Unknown detector {other}"),
+    }
+}
+
+// Logs every detection as (time, observer, detection)
+#[derive(Default)]
+pub struct Watcher {
+    detector: Option<Box<dyn FailureDetector>>,
+}
+
+impl Watcher {
+    fn log(&self, detections: Vec<Detection>) {
+        anykv::modify::<Vec<(Jiffies, ProcessId, Detection)>>("detections", |log| {
+            detections
+                .into_iter()
+                .for_each(|detection| log.push((now(), rank(), detection)))
+        });
+    }
+}
+
+impl ProcessHandle for Watcher {
+    fn start(&mut self) {
+        let mut detector = configured_detector();
+        detector.start();
+        self.detector = Some(detector);
+    }
+
+    fn on_message(&mut self, from: ProcessId, message: MessagePtr) {
+        if let Some(detections) = self.detector.as_mut().unwrap().on_message(from, &message) {
+            self.log(detections);
+        }
+    }
+
+    fn on_timer(&mut self, id: TimerId) {
+        if let Some(detections) = self.detector.as_mut().unwrap().on_timer(id) {
+            self.log(detections);
+        }
+    }
+}
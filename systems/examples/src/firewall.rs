@@ -0,0 +1,72 @@
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    rc::Rc,
+};
+
+use dscale::{global::anykv, *};
+
+pub struct Vote {
+    round: usize,
+    value: usize,
+}
+
+impl Message for Vote {}
+
+pub const ROUNDS: usize = 10;
+pub const EQUIVOCATOR: ProcessId = 4;
+const PERIOD: Jiffies = Jiffies(10);
+
+// Every round processes broadcast a vote, the equivocator votes twice with different values.
+// Honest processes blacklist peers caught equivocating and filter out their messages.
+#[derive(Default)]
+pub struct Voter {
+    round: usize,
+    votes: HashMap<(ProcessId, usize), usize>,
+    blacklist: Rc<RefCell<HashSet<ProcessId>>>,
+}
+
+impl ProcessHandle for Voter {
+    fn start(&mut self) {
+        let blacklist = self.blacklist.clone();
+        set_message_filter(move |from, _message| !blacklist.borrow().contains(&from));
+        schedule_timer_after(PERIOD);
+    }
+
+    fn on_message(&mut self, from: ProcessId, message: MessagePtr) {
+        if from == rank() {
+            return;
+        }
+        let vote = message.as_type::<Vote>();
+        match self.votes.insert((from, vote.round), vote.value) {
+            Some(previous) if previous != vote.value => {
+                debug_process!("P{from} equivocated in round {}", vote.round);
+                self.blacklist.borrow_mut().insert(from);
+                anykv::modify::<usize>("blacklisted", |x| *x += 1);
+            }
+            _ => {}
+        }
+    }
+
+    fn on_timer(&mut self, _id: TimerId) {
+        self.round += 1;
+        if rank() == EQUIVOCATOR {
+            broadcast(Vote {
+                round: self.round,
+                value: 0,
+            });
+            broadcast(Vote {
+                round: self.round,
+                value: 1,
+            });
+        } else {
+            broadcast(Vote {
+                round: self.round,
+                value: rank(),
+            });
+        }
+        if self.round < ROUNDS {
+            schedule_timer_after(PERIOD);
+        }
+    }
+}
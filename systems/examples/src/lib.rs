@@ -1,7 +1,29 @@
 #![allow(non_snake_case)]
 
+pub mod addressing;
+pub mod asymmetric;
 pub mod bandwidth;
 pub mod broadcast;
+pub mod clock_sync;
+pub mod colocation;
+pub mod contention;
+pub mod external_feed;
+pub mod failure_detection;
+pub mod firewall;
 pub mod multidc_pingpong;
+pub mod nearest_replica;
+pub mod ordering;
+pub mod partition;
+pub mod persistence;
 pub mod pingpong;
+pub mod postmortem;
+pub mod quorum;
+pub mod ramp;
+pub mod reachability;
+pub mod recovery;
+pub mod relay_tree;
+pub mod reliable;
+pub mod scheduled;
+pub mod startup;
+pub mod tie_breaks;
 pub mod timers;
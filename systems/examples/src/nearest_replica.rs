@@ -0,0 +1,69 @@
+use dscale::{global::anykv, *};
+
+// Clients spread across regions read from a replica pool which is spread too.
+// Configured through anykv "route_to_nearest": either the nearest or a random replica serves the read.
+
+pub struct Read {
+    pub sent_at: Jiffies,
+}
+
+impl Message for Read {}
+
+pub struct ReadReply {
+    pub sent_at: Jiffies,
+}
+
+impl Message for ReadReply {}
+
+#[derive(Default)]
+pub struct Client {}
+
+impl Client {
+    fn read(&self) {
+        let replica = if anykv::get::<bool>("route_to_nearest") {
+            choose_nearest_from_pool("Replicas")
+        } else {
+            choose_from_pool("Replicas")
+        };
+        send_to(replica, Read { sent_at: now() });
+    }
+}
+
+impl ProcessHandle for Client {
+    fn start(&mut self) {
+        self.read();
+    }
+
+    fn on_message(&mut self, _from: ProcessId, message: MessagePtr) {
+        let reply = message.as_type::<ReadReply>();
+        anykv::modify::<usize>("reads", |r| *r += 1);
+        anykv::modify::<usize>("total_latency", |l| *l += (now() - reply.sent_at).0);
+        self.read();
+    }
+
+    fn on_timer(&mut self, _id: TimerId) {}
+}
+
+#[derive(Default)]
+pub struct Replica {}
+
+impl ProcessHandle for Replica {
+    fn start(&mut self) {}
+
+    fn on_message(&mut self, from: ProcessId, message: MessagePtr) {
+        let read = message.as_type::<Read>();
+        debug_process!(
+            "Serving read from {:?} in {:?}",
+            region_of(from),
+            region_of(rank())
+        );
+        send_to(
+            from,
+            ReadReply {
+                sent_at: read.sent_at,
+            },
+        );
+    }
+
+    fn on_timer(&mut self, _id: TimerId) {}
+}
@@ -0,0 +1,54 @@
+use dscale::{global::anykv, *};
+
+pub enum Hop {
+    Direct(usize),
+    Relay(usize),
+    Forwarded(usize),
+}
+
+impl Message for Hop {}
+
+const BURST: usize = 3;
+const PERIOD: Jiffies = Jiffies(30);
+
+// P1 periodically sends a burst of sequence numbers to P3 and relays the last one through P2.
+// P3 counts reordered direct messages and forwarded ones overtaking their direct copy.
+#[derive(Default)]
+pub struct Node {
+    seq: usize,
+    last_direct: usize,
+}
+
+impl ProcessHandle for Node {
+    fn start(&mut self) {
+        if rank() == 1 {
+            schedule_timer_after(PERIOD);
+        }
+    }
+
+    fn on_message(&mut self, _from: ProcessId, message: MessagePtr) {
+        match *message.as_type::<Hop>() {
+            Hop::Relay(seq) => send_to(3, Hop::Forwarded(seq)),
+            Hop::Direct(seq) => {
+                if seq < self.last_direct {
+                    anykv::modify::<usize>("reordered", |x| *x += 1);
+                }
+                self.last_direct = self.last_direct.max(seq);
+            }
+            Hop::Forwarded(seq) => {
+                if self.last_direct < seq {
+                    anykv::modify::<usize>("overtaken", |x| *x += 1);
+                }
+            }
+        }
+    }
+
+    fn on_timer(&mut self, _id: TimerId) {
+        for _ in 0..BURST {
+            self.seq += 1;
+            send_to(3, Hop::Direct(self.seq));
+        }
+        send_to(2, Hop::Relay(self.seq));
+        schedule_timer_after(PERIOD);
+    }
+}
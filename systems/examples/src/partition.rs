@@ -0,0 +1,28 @@
+use dscale::{global::anykv, *};
+
+pub struct Heartbeat;
+
+impl Message for Heartbeat {}
+
+pub const PERIOD: Jiffies = Jiffies(10);
+
+// Every process broadcasts heartbeats, P1 logs whom it hears from and when
+#[derive(Default)]
+pub struct Beater;
+
+impl ProcessHandle for Beater {
+    fn start(&mut self) {
+        schedule_timer_after(PERIOD);
+    }
+
+    fn on_message(&mut self, from: ProcessId, _message: MessagePtr) {
+        if rank() == 1 {
+            anykv::modify::<Vec<(Jiffies, ProcessId)>>("heard", |heard| heard.push((now(), from)));
+        }
+    }
+
+    fn on_timer(&mut self, _id: TimerId) {
+        broadcast(Heartbeat);
+        schedule_timer_after(PERIOD);
+    }
+}
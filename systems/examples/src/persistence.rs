@@ -0,0 +1,69 @@
+use std::collections::HashMap;
+
+use dscale::{
+    global::{anykv, disk},
+    *,
+};
+
+pub const ENTRY_SIZE: usize = 1000;
+pub const APPEND_INTERVAL: Jiffies = Jiffies(10);
+
+pub struct Append {
+    pub sent_at: Jiffies,
+}
+
+impl Message for Append {
+    fn virtual_size(&self) -> usize {
+        ENTRY_SIZE
+    }
+}
+
+pub struct Ack {
+    pub sent_at: Jiffies,
+}
+
+impl Message for Ack {}
+
+// Appends an entry to the replica every APPEND_INTERVAL and measures commit latency
+#[derive(Default)]
+pub struct Writer {}
+
+impl ProcessHandle for Writer {
+    fn start(&mut self) {
+        schedule_timer_after(APPEND_INTERVAL);
+    }
+
+    fn on_message(&mut self, _from: ProcessId, message: MessagePtr) {
+        let ack = message.as_type::<Ack>();
+        anykv::modify::<usize>("acked", |a| *a += 1);
+        anykv::modify::<usize>("total_latency", |l| *l += (now() - ack.sent_at).0);
+    }
+
+    fn on_timer(&mut self, _id: TimerId) {
+        send_to(2, Append { sent_at: now() });
+        schedule_timer_after(APPEND_INTERVAL);
+    }
+}
+
+// Acknowledges appends only after they are durable
+#[derive(Default)]
+pub struct Replica {
+    persisting: HashMap<TimerId, (ProcessId, Jiffies)>,
+}
+
+impl ProcessHandle for Replica {
+    fn start(&mut self) {}
+
+    fn on_message(&mut self, from: ProcessId, message: MessagePtr) {
+        let append = message.as_type::<Append>();
+        disk::write(ENTRY_SIZE);
+        self.persisting
+            .insert(disk::fsync(), (from, append.sent_at));
+    }
+
+    fn on_timer(&mut self, id: TimerId) {
+        if let Some((writer, sent_at)) = self.persisting.remove(&id) {
+            send_to(writer, Ack { sent_at });
+        }
+    }
+}
@@ -1,4 +1,7 @@
-use dscale::{global::anykv, *};
+use dscale::{
+    global::{anykv, tracing},
+    *,
+};
 
 #[derive(Clone, Eq, PartialEq, PartialOrd, Ord)]
 pub enum PingPongMessage {
@@ -22,17 +25,19 @@ impl ProcessHandle for PingPongProcess {
         let m = message.as_type::<PingPongMessage>();
 
         if from == 1 && rank() == 2 {
-            assert!(*m == PingPongMessage::Ping);
+            sim_assert!(*m == PingPongMessage::Ping);
             debug_process!("Sending Pong");
             anykv::modify::<usize>("pongs", |p| *p += 1);
+            tracing::in_reply_to();
             send_to(1, PingPongMessage::Pong);
             return;
         }
 
         if from == 2 && rank() == 1 {
-            assert!(*m == PingPongMessage::Pong);
+            sim_assert!(*m == PingPongMessage::Pong);
             debug_process!("Sending Ping");
             anykv::modify::<usize>("pings", |p| *p += 1);
+            tracing::in_reply_to();
             send_to(2, PingPongMessage::Ping);
             return;
         }
@@ -0,0 +1,40 @@
+use std::collections::HashMap;
+
+use dscale::*;
+
+pub const PEERS_POOL_NAME: &str = "Peers";
+pub const PERIOD: Jiffies = Jiffies(10);
+
+pub struct Tick(pub usize);
+
+impl Message for Tick {}
+
+// Peers broadcast numbered ticks and complain about reordered ones
+#[derive(Default)]
+pub struct Peer {
+    sent: usize,
+    last_seen: HashMap<ProcessId, usize>,
+}
+
+impl ProcessHandle for Peer {
+    fn start(&mut self) {
+        schedule_timer_after(PERIOD);
+    }
+
+    fn on_message(&mut self, from: ProcessId, message: MessagePtr) {
+        let tick = message.as_type::<Tick>().0;
+        let last = self.last_seen.entry(from).or_default();
+        if tick < *last {
+            warn_process!("Tick {tick} of P{from} arrived after tick {last}");
+        } else {
+            debug_process!("Tick {tick} of P{from}");
+        }
+        *last = (*last).max(tick);
+    }
+
+    fn on_timer(&mut self, _id: TimerId) {
+        self.sent += 1;
+        broadcast_within_pool(PEERS_POOL_NAME, Tick(self.sent));
+        schedule_timer_after(PERIOD);
+    }
+}
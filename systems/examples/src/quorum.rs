@@ -0,0 +1,62 @@
+use dscale::{global::anykv, *};
+
+pub const REPLICAS: &str = "Replicas";
+pub const QUORUM: usize = 3;
+pub const READS: usize = 20;
+const PERIOD: Jiffies = Jiffies(50);
+
+pub enum KvMessage {
+    Hello,
+    Read(usize),
+    ReadReply(usize),
+}
+
+impl Message for KvMessage {}
+
+// Clients are not members of the replica pool: they announce themselves to all
+// replicas, then read from a random quorum of them instead of broadcasting.
+#[derive(Default)]
+pub struct QuorumClient {
+    reads: usize,
+}
+
+impl ProcessHandle for QuorumClient {
+    fn start(&mut self) {
+        broadcast_within_pool(REPLICAS, KvMessage::Hello);
+        schedule_timer_after(PERIOD);
+    }
+
+    fn on_message(&mut self, from: ProcessId, message: MessagePtr) {
+        if let KvMessage::ReadReply(read) = *message.as_type::<KvMessage>() {
+            anykv::modify::<Vec<(ProcessId, usize, ProcessId)>>("replies", |replies| {
+                replies.push((rank(), read, from))
+            });
+        }
+    }
+
+    fn on_timer(&mut self, _id: TimerId) {
+        if self.reads == READS {
+            return;
+        }
+        self.reads += 1;
+        send_to_pool_quorum(REPLICAS, KvMessage::Read(self.reads), QUORUM);
+        schedule_timer_after(PERIOD);
+    }
+}
+
+#[derive(Default)]
+pub struct Replica;
+
+impl ProcessHandle for Replica {
+    fn start(&mut self) {}
+
+    fn on_message(&mut self, from: ProcessId, message: MessagePtr) {
+        match *message.as_type::<KvMessage>() {
+            KvMessage::Hello => anykv::modify::<usize>("hellos", |hellos| *hellos += 1),
+            KvMessage::Read(read) => send_to(from, KvMessage::ReadReply(read)),
+            KvMessage::ReadReply(_) => unreachable!("Replicas do not read"),
+        }
+    }
+
+    fn on_timer(&mut self, _id: TimerId) {}
+}
@@ -0,0 +1,55 @@
+use dscale::{global::anykv, *};
+
+pub const SERVER_POOL_NAME: &str = "Server";
+pub const CLIENT_POOL_NAME: &str = "Clients";
+
+pub struct Request;
+
+impl Message for Request {}
+
+pub struct Stop;
+
+impl Message for Stop {}
+
+// Issues requests with the interval currently set by the experiment driver
+#[derive(Default)]
+pub struct Client {
+    stopped: bool,
+}
+
+impl ProcessHandle for Client {
+    fn start(&mut self) {
+        schedule_timer_after(anykv::get::<Jiffies>("ramp_interval"));
+    }
+
+    fn on_message(&mut self, _from: ProcessId, message: MessagePtr) {
+        if message.is::<Stop>() {
+            debug_process!("Stopped");
+            self.stopped = true;
+        }
+    }
+
+    fn on_timer(&mut self, _id: TimerId) {
+        if self.stopped {
+            return;
+        }
+        send_to(
+            pool_member(SERVER_POOL_NAME, 0).expect("No server"),
+            Request,
+        );
+        schedule_timer_after(anykv::get::<Jiffies>("ramp_interval"));
+    }
+}
+
+#[derive(Default)]
+pub struct Server {}
+
+impl ProcessHandle for Server {
+    fn start(&mut self) {}
+
+    fn on_message(&mut self, _from: ProcessId, _message: MessagePtr) {
+        anykv::modify::<usize>("served", |served| *served += 1);
+    }
+
+    fn on_timer(&mut self, _id: TimerId) {}
+}
@@ -0,0 +1,25 @@
+use dscale::{global::anykv, *};
+
+pub const PERIOD: Jiffies = Jiffies(100);
+
+// P1 periodically logs whom it considers reachable
+#[derive(Default)]
+pub struct Observer;
+
+impl ProcessHandle for Observer {
+    fn start(&mut self) {
+        if rank() == 1 {
+            schedule_timer_after(PERIOD);
+        }
+    }
+
+    fn on_message(&mut self, _from: ProcessId, _message: MessagePtr) {}
+
+    fn on_timer(&mut self, _id: TimerId) {
+        let reachable = list_reachable_pool("Members");
+        anykv::modify::<Vec<(Jiffies, Vec<ProcessId>)>>("reachable", |log| {
+            log.push((now(), reachable))
+        });
+        schedule_timer_after(PERIOD);
+    }
+}
@@ -0,0 +1,54 @@
+use dscale::{
+    global::{anykv, wal},
+    *,
+};
+
+pub const APPEND_INTERVAL: Jiffies = Jiffies(10);
+pub const SYNC_EVERY: usize = 5;
+
+pub struct Entry {
+    pub seq: usize,
+}
+
+impl_virtual_size!(struct Entry { seq });
+
+impl Message for Entry {
+    fn virtual_size(&self) -> usize {
+        helpers::virtual_size_of(self)
+    }
+}
+
+// Appends numbered entries to its log, syncing every SYNC_EVERY entries
+#[derive(Default)]
+pub struct Appender {
+    append_timer: Option<TimerId>,
+}
+
+impl ProcessHandle for Appender {
+    fn start(&mut self) {
+        // Recovery: surviving log must be a gapless prefix
+        let entries = wal::entries();
+        for (lsn, entry) in entries.into_iter().enumerate() {
+            sim_assert!(
+                entry.as_type::<Entry>().seq == lsn,
+                "Log has a gap at {lsn}"
+            );
+        }
+        anykv::set::<usize>("recovered", wal::len());
+        self.append_timer = Some(schedule_timer_after(APPEND_INTERVAL));
+    }
+
+    fn on_message(&mut self, _from: ProcessId, _message: MessagePtr) {}
+
+    fn on_timer(&mut self, id: TimerId) {
+        if self.append_timer != Some(id) {
+            return; // Sync completion
+        }
+        let lsn = wal::append(Entry { seq: wal::len() });
+        anykv::set::<usize>("log_len", wal::len());
+        if (lsn + 1).is_multiple_of(SYNC_EVERY) {
+            wal::sync();
+        }
+        self.append_timer = Some(schedule_timer_after(APPEND_INTERVAL));
+    }
+}
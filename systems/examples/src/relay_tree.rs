@@ -0,0 +1,30 @@
+use dscale::{global::anykv, *};
+
+pub const BLOCK_SIZE: usize = 1024 * 1024;
+
+pub struct Block;
+
+impl Message for Block {
+    fn virtual_size(&self) -> usize {
+        BLOCK_SIZE
+    }
+}
+
+// P1 proposes one block, every member logs when the block reaches it
+#[derive(Default)]
+pub struct Member;
+
+impl ProcessHandle for Member {
+    fn start(&mut self) {
+        if rank() == 1 {
+            broadcast_within_pool("Committee", Block);
+        }
+    }
+
+    fn on_message(&mut self, from: ProcessId, _message: MessagePtr) {
+        assert_eq!(from, 1, "Relays are transparent");
+        anykv::modify::<Vec<Jiffies>>("received", |received| received.push(now()));
+    }
+
+    fn on_timer(&mut self, _id: TimerId) {}
+}
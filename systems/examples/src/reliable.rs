@@ -0,0 +1,66 @@
+use dscale::{
+    global::anykv,
+    helpers::reliable::{Outgoing, ReliableLayer},
+    stack::{Layer, LayerContext, Stack, StackDefinition},
+    *,
+};
+
+pub struct Update(pub usize);
+
+impl Message for Update {}
+
+pub const UPDATES: usize = 100;
+pub const PERIOD: Jiffies = Jiffies(10);
+
+// The writer sends numbered updates to the reader, which counts distinct ones
+#[derive(Default)]
+pub struct Replication {
+    next: usize,
+    timer: Option<TimerId>,
+}
+
+impl Layer for Replication {
+    fn start(&mut self, _context: &mut LayerContext) {
+        if rank() == 1 {
+            self.timer = Some(schedule_timer_after(PERIOD));
+        }
+    }
+
+    fn on_deliver(&mut self, _from: ProcessId, message: MessagePtr, _context: &mut LayerContext) {
+        let update = message.as_type::<Update>().0;
+        anykv::modify::<Vec<usize>>("received", |received| received.push(update));
+    }
+
+    fn on_timer(&mut self, id: TimerId, context: &mut LayerContext) {
+        if self.timer != Some(id) {
+            return; // Retransmission timer of the layer below
+        }
+        context.submit(Outgoing::pool("Readers", Update(self.next)));
+        self.next += 1;
+        if self.next < UPDATES {
+            self.timer = Some(schedule_timer_after(PERIOD));
+        }
+    }
+}
+
+// Acknowledges messages between writers and readers
+pub struct Reliable;
+
+impl StackDefinition for Reliable {
+    fn stack() -> Stack {
+        Stack::new()
+            .layer(ReliableLayer::new(Jiffies(20)).between("Writers", "Readers"))
+            .layer(Replication::default())
+    }
+}
+
+// Same layer, reliable among writers only: updates to readers are sent as they are
+pub struct Unreliable;
+
+impl StackDefinition for Unreliable {
+    fn stack() -> Stack {
+        Stack::new()
+            .layer(ReliableLayer::new(Jiffies(20)).between("Writers", "Writers"))
+            .layer(Replication::default())
+    }
+}
@@ -0,0 +1,55 @@
+use dscale::{global::anykv, *};
+
+pub const BEATS: usize = 10;
+pub const BEAT_INTERVAL: Jiffies = Jiffies(100);
+
+pub struct Beat {
+    pub departure: Jiffies,
+}
+
+impl Message for Beat {}
+
+pub struct Reminder {
+    pub departure: Jiffies,
+}
+
+impl Message for Reminder {}
+
+// Rank 1 schedules all of its beats upfront instead of keeping them in timers
+#[derive(Default)]
+pub struct Beacon {}
+
+impl ProcessHandle for Beacon {
+    fn start(&mut self) {
+        if rank() != 1 {
+            return;
+        }
+        for k in 1..=BEATS {
+            let delay = Jiffies(BEAT_INTERVAL.0 * k);
+            broadcast_after(
+                Beat {
+                    departure: now() + delay,
+                },
+                delay,
+            );
+        }
+        let at = now() + Jiffies(BEAT_INTERVAL.0 * BEATS + 50);
+        send_to_at(2, Reminder { departure: at }, at);
+    }
+
+    fn on_message(&mut self, _from: ProcessId, message: MessagePtr) {
+        let departure = if let Some(beat) = message.try_as::<Beat>() {
+            anykv::modify::<usize>("beats", |x| *x += 1);
+            beat.departure
+        } else {
+            anykv::modify::<usize>("reminders", |x| *x += 1);
+            message.as_type::<Reminder>().departure
+        };
+        debug_process!("Message departed at {departure}");
+
+        // 1 jiffy to leave + 5 jiffies of latency
+        assert_eq!(now(), departure + Jiffies(6));
+    }
+
+    fn on_timer(&mut self, _id: TimerId) {}
+}
@@ -0,0 +1,39 @@
+use dscale::{global::anykv, global::bootstrap, *};
+
+pub struct Hello;
+
+impl Message for Hello {}
+
+// Greets everyone right at start, as a genesis announcement would
+#[derive(Default)]
+pub struct Eager;
+
+impl ProcessHandle for Eager {
+    fn start(&mut self) {
+        broadcast(Hello);
+    }
+
+    fn on_message(&mut self, _from: ProcessId, _message: MessagePtr) {
+        anykv::modify::<usize>("hellos", |hellos| *hellos += 1);
+    }
+
+    fn on_timer(&mut self, _id: TimerId) {}
+}
+
+// Greets everyone once all processes are up
+#[derive(Default)]
+pub struct Patient;
+
+impl ProcessHandle for Patient {
+    fn start(&mut self) {
+        bootstrap::await_all_started();
+    }
+
+    fn on_message(&mut self, _from: ProcessId, _message: MessagePtr) {
+        anykv::modify::<usize>("hellos", |hellos| *hellos += 1);
+    }
+
+    fn on_timer(&mut self, _id: TimerId) {
+        broadcast(Hello);
+    }
+}
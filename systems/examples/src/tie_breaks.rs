@@ -0,0 +1,53 @@
+use dscale::{global::anykv, *};
+
+pub struct Write(pub ProcessId);
+
+impl Message for Write {}
+
+// Every writer sends its id to the register (P1) once, all writes arrive together
+#[derive(Default)]
+pub struct Writer;
+
+impl ProcessHandle for Writer {
+    fn start(&mut self) {
+        send_to(1, Write(rank()));
+    }
+
+    fn on_message(&mut self, _from: ProcessId, _message: MessagePtr) {}
+
+    fn on_timer(&mut self, _id: TimerId) {}
+}
+
+// Keeps the write delivered first: depends on the order of simultaneous deliveries
+#[derive(Default)]
+pub struct FirstWriterWins;
+
+impl ProcessHandle for FirstWriterWins {
+    fn start(&mut self) {}
+
+    fn on_message(&mut self, _from: ProcessId, message: MessagePtr) {
+        let writer = message.as_type::<Write>().0;
+        if anykv::get::<ProcessId>("winner") == 0 {
+            anykv::set::<ProcessId>("winner", writer);
+        }
+    }
+
+    fn on_timer(&mut self, _id: TimerId) {}
+}
+
+// Keeps the write of the highest id: does not depend on delivery order
+#[derive(Default)]
+pub struct HighestWriterWins;
+
+impl ProcessHandle for HighestWriterWins {
+    fn start(&mut self) {}
+
+    fn on_message(&mut self, _from: ProcessId, message: MessagePtr) {
+        let writer = message.as_type::<Write>().0;
+        if anykv::get::<ProcessId>("winner") < writer {
+            anykv::set::<ProcessId>("winner", writer);
+        }
+    }
+
+    fn on_timer(&mut self, _id: TimerId) {}
+}
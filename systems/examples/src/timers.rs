@@ -8,9 +8,14 @@ pub enum LazyPingPongMessage {
 
 impl Message for LazyPingPongMessage {}
 
+#[derive(Clone)]
+enum DemoTimer {
+    Heartbeat,
+    DelayedPong,
+}
+
 #[derive(Default)]
 pub struct LazyPingPong {
-    heartbeat_timer: Option<TimerId>,
     ping_count: usize,
 }
 
@@ -19,8 +24,7 @@ impl ProcessHandle for LazyPingPong {
         debug_process!("Starting timer demo process");
 
         // Schedule a heartbeat timer to fire every 1000 jiffies
-        let timer_id = schedule_timer_after(Jiffies(1000));
-        self.heartbeat_timer = Some(timer_id);
+        let timer_id = schedule_named_timer(Jiffies(1000), DemoTimer::Heartbeat);
         debug_process!(
             "Scheduled heartbeat timer {} to fire in 1000 jiffies",
             timer_id
@@ -41,7 +45,7 @@ impl ProcessHandle for LazyPingPong {
                 anykv::modify::<usize>("pings_received", |count| *count += 1);
 
                 // Schedule a delayed response using a timer
-                let timer_id = schedule_timer_after(Jiffies(500));
+                let timer_id = schedule_named_timer(Jiffies(500), DemoTimer::DelayedPong);
                 debug_process!("Scheduling delayed pong response with timer {}", timer_id);
             }
 
@@ -61,23 +65,22 @@ impl ProcessHandle for LazyPingPong {
     fn on_timer(&mut self, timer_id: TimerId) {
         debug_process!("Timer {} fired", timer_id);
 
-        // Check if this is the heartbeat timer
-        if let Some(heartbeat_id) = self.heartbeat_timer {
-            if timer_id == heartbeat_id {
+        // The purpose of the timer comes with it, no need to remember ids
+        match timer_kind::<DemoTimer>(timer_id) {
+            Some(DemoTimer::Heartbeat) => {
                 debug_process!("Heartbeat timer fired");
                 anykv::modify::<usize>("heartbeats", |count| *count += 1);
 
                 // Reschedule the heartbeat timer for continuous operation
-                let new_timer_id = schedule_timer_after(Jiffies(1000));
-                self.heartbeat_timer = Some(new_timer_id);
-                return;
+                schedule_named_timer(Jiffies(1000), DemoTimer::Heartbeat);
             }
-        }
-
-        // This must be a delayed response timer
-        debug_process!("Delayed response timer fired - sending DelayedPong");
-        if rank() == 2 {
-            send_to(1, LazyPingPongMessage::DelayedPong);
+            Some(DemoTimer::DelayedPong) => {
+                debug_process!("Delayed response timer fired - sending DelayedPong");
+                if rank() == 2 {
+                    send_to(1, LazyPingPongMessage::DelayedPong);
+                }
+            }
+            None => unreachable!("Only named timers are scheduled"),
         }
     }
 }
@@ -0,0 +1,140 @@
+// Client-perceived availability: a client operation (all attempts of one request
+// together) is available if it succeeded within the SLA latency since its first
+// attempt. Operations are bucketed by the time they were issued, so partition and
+// crash scenarios produce an availability timeline instead of a single aggregate.
+
+use std::collections::HashMap;
+
+use dscale::Jiffies;
+
+use crate::abd_store::{
+    session::{OpId, SessionEvent, SessionLog},
+    types::ClientId,
+};
+
+#[derive(Clone, Copy, Debug)]
+pub struct ClientOperation {
+    pub client: ClientId,
+    pub issued: Jiffies,
+    pub completed: Option<Jiffies>, // None if every attempt failed or is still in flight
+    pub attempts: usize,
+}
+
+impl ClientOperation {
+    pub fn latency(&self) -> Option<Jiffies> {
+        self.completed.map(|completed| completed - self.issued)
+    }
+
+    pub fn within(&self, sla: Jiffies) -> bool {
+        self.latency().is_some_and(|latency| latency <= sla)
+    }
+}
+
+// Groups attempts into client operations, in the order operations were issued
+pub fn client_operations(log: &SessionLog) -> Vec<ClientOperation> {
+    let mut operations: Vec<ClientOperation> = Vec::new();
+    let mut current: HashMap<ClientId, usize> = HashMap::new();
+    let mut attempts: HashMap<OpId, usize> = HashMap::new();
+
+    for event in log {
+        match *event {
+            SessionEvent::Invoke {
+                id, attempt, at, ..
+            } => {
+                if attempt == 1 {
+                    current.insert(id.0, operations.len());
+                    operations.push(ClientOperation {
+                        client: id.0,
+                        issued: at,
+                        completed: None,
+                        attempts: 0,
+                    });
+                }
+                let operation = current[&id.0];
+                operations[operation].attempts += 1;
+                attempts.insert(id, operation);
+            }
+            SessionEvent::Complete { id, at, .. } => {
+                operations[attempts[&id]].completed = Some(at);
+            }
+            SessionEvent::Fail { .. } => {}
+        }
+    }
+    operations
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AvailabilityBucket {
+    pub start: Jiffies,
+    pub issued: usize,     // Operations issued in the bucket and judged
+    pub within_sla: usize, // Of them, succeeded within the SLA
+    pub goodput: usize,    // Operations which succeeded within the SLA, by completion time
+}
+
+impl AvailabilityBucket {
+    // None if no operation was issued in the bucket
+    pub fn availability(&self) -> Option<f64> {
+        (self.issued > 0).then(|| self.within_sla as f64 / self.issued as f64)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct AvailabilityTimeline {
+    pub sla: Jiffies,
+    pub bucket: Jiffies,
+    pub buckets: Vec<AvailabilityBucket>,
+}
+
+impl AvailabilityTimeline {
+    // Operations issued less than sla before the end of the run are only judged
+    // if they already succeeded: the rest still had time left
+    pub fn of(log: &SessionLog, sla: Jiffies, bucket: Jiffies, end: Jiffies) -> Self {
+        assert!(bucket.0 > 0, "Bucket should be positive");
+        let mut buckets: Vec<AvailabilityBucket> = (0..end.0.div_ceil(bucket.0))
+            .map(|index| AvailabilityBucket {
+                start: Jiffies(index * bucket.0),
+                ..Default::default()
+            })
+            .collect();
+
+        // Events at the very end of the run fall into the last bucket
+        let last = buckets.len().saturating_sub(1);
+        let index = |at: Jiffies| (at.0 / bucket.0).min(last);
+
+        client_operations(log).iter().for_each(|operation| {
+            let available = operation.within(sla);
+            if !available && operation.issued + sla > end {
+                return;
+            }
+            let issued = &mut buckets[index(operation.issued)];
+            issued.issued += 1;
+            if available {
+                issued.within_sla += 1;
+                buckets[index(operation.completed.expect("Succeeded"))].goodput += 1;
+            }
+        });
+
+        Self {
+            sla,
+            bucket,
+            buckets,
+        }
+    }
+
+    pub fn availability(&self) -> f64 {
+        let issued: usize = self.buckets.iter().map(|bucket| bucket.issued).sum();
+        if issued == 0 {
+            return 1.0;
+        }
+        let within_sla: usize = self.buckets.iter().map(|bucket| bucket.within_sla).sum();
+        within_sla as f64 / issued as f64
+    }
+
+    // Lowest availability of a bucket with judged operations
+    pub fn worst(&self) -> Option<f64> {
+        self.buckets
+            .iter()
+            .filter_map(|bucket| bucket.availability())
+            .min_by(f64::total_cmp)
+    }
+}
@@ -1,39 +1,140 @@
 use dscale::{
     global::{anykv, configuration},
+    helpers::Backoff,
+    workload::{KeyDistribution, KeySampler},
     *,
 };
 
 use rand::{Rng, SeedableRng, rngs::StdRng, seq::IndexedRandom};
 
-use crate::abd_store::types::{Key, Value};
+use crate::abd_store::{
+    reconfiguration::{Configuration, ReconfigurationMessage, initial_configuration},
+    session::Session,
+    types::{Key, RequestId, Value},
+};
+
+// Object (key) touched by an operation, with the written value for writes
+pub type ObjectAccess = (Key, Option<Value>);
 
 #[derive(Default, Clone)]
 pub struct ExecutionHistoryEntry {
     pub client: ProcessId,
-    pub operation: String,
+    pub operation: String,          // Human readable, e.g. "MultiPut(1=5,3=7)"
+    pub objects: Vec<ObjectAccess>, // Tags the entry with every object it touches
     pub result: Option<Value>,
+    pub reads: Vec<(Key, Value)>, // Values returned by MultiGet
     pub start: Jiffies,
     pub end: Jiffies,
+    // Attempt timed out or was aborted: it may take effect at any moment after start, or never
+    pub ambiguous: bool,
 }
 pub type ExecutionHistory = Vec<ExecutionHistoryEntry>;
 
+// Keys of a batch are distinct. Every key of a batch is linearizable on its own,
+// the batch as a whole is not atomic
+#[derive(Clone)]
+#[cfg_attr(feature = "fuzz", derive(arbitrary::Arbitrary))]
+#[allow(clippy::enum_variant_names)]
 pub(crate) enum ClientReq {
-    PutRequest(Key, Value),
-    GetRequest(Key),
+    PutRequest(RequestId, Key, Value),
+    GetRequest(RequestId, Key),
+    MultiPutRequest(RequestId, Vec<(Key, Value)>),
+    MultiGetRequest(RequestId, Vec<Key>),
+}
+
+impl ClientReq {
+    pub(crate) fn request(&self) -> RequestId {
+        match *self {
+            ClientReq::PutRequest(request, _, _) => request,
+            ClientReq::GetRequest(request, _) => request,
+            ClientReq::MultiPutRequest(request, _) => request,
+            ClientReq::MultiGetRequest(request, _) => request,
+        }
+    }
+
+    pub(crate) fn objects(&self) -> Vec<ObjectAccess> {
+        match self {
+            ClientReq::PutRequest(_, key, value) => vec![(*key, Some(*value))],
+            ClientReq::GetRequest(_, key) => vec![(*key, None)],
+            ClientReq::MultiPutRequest(_, writes) => writes
+                .iter()
+                .map(|(key, value)| (*key, Some(*value)))
+                .collect(),
+            ClientReq::MultiGetRequest(_, keys) => keys.iter().map(|key| (*key, None)).collect(),
+        }
+    }
 }
 
 pub(crate) enum ClientResponse {
-    GetResponse(Value),
-    PutAck,
+    GetResponse(RequestId, Value),
+    MultiGetResponse(RequestId, Vec<(Key, Value)>),
+    PutAck(RequestId), // Also acknowledges MultiPut
+    Retry(RequestId),  // Replica does not serve current configuration
+}
+
+impl ClientResponse {
+    fn request(&self) -> RequestId {
+        match *self {
+            ClientResponse::GetResponse(request, _) => request,
+            ClientResponse::MultiGetResponse(request, _) => request,
+            ClientResponse::PutAck(request) => request,
+            ClientResponse::Retry(request) => request,
+        }
+    }
+}
+
+impl_virtual_size!(
+    enum ClientReq {
+        PutRequest(request, key, value),
+        GetRequest(request, key),
+        MultiPutRequest(request, writes),
+        MultiGetRequest(request, keys),
+    }
+);
+impl_virtual_size!(
+    enum ClientResponse {
+        GetResponse(request, value),
+        MultiGetResponse(request, values),
+        PutAck(request),
+        Retry(request),
+    }
+);
+
+impl Message for ClientReq {
+    fn virtual_size(&self) -> usize {
+        helpers::virtual_size_of(self)
+    }
+}
+impl Message for ClientResponse {
+    fn virtual_size(&self) -> usize {
+        helpers::virtual_size_of(self)
+    }
 }
 
-impl Message for ClientReq {}
-impl Message for ClientResponse {}
+const THINK_TIME: Jiffies = Jiffies(100);
+const RETRY_BACKOFF: Jiffies = Jiffies(10);
+const DEFAULT_OPERATION_TIMEOUT: Jiffies = Jiffies(10_000);
+const DEFAULT_MAX_ATTEMPTS: usize = 3;
 
+// Every attempt is recorded as a separate operation of the client session:
+// attempts which did not get a response are ambiguous. Retried Put writes a
+// fresh value, because resending the old one could apply it twice (before and
+// after concurrent writes).
 pub struct Client {
     rng: Option<StdRng>,
     keypool: Vec<Key>,
-    current_op: ExecutionHistoryEntry,
+    keys: Option<KeySampler>, // Index into keypool, anykv "key_distribution" (uniform by default)
+    session: Option<Session>,
+    config: Option<Configuration>,
+    operation_timeout: Jiffies, // anykv "client_timeout"
+    max_attempts: usize,        // anykv "client_max_attempts"
+    backoff: Option<Backoff>,   // Retries of the pending operation
+    batch_size: usize,          // anykv "client_batch_size", keys per operation
+    pending_request: Option<ClientReq>,
+    next_request: RequestId,
+    last_replica: Option<ProcessId>,
+    timeout_timer: Option<TimerId>,
+    next_timer: Option<TimerId>,
 }
 
 impl Default for Client {
@@ -41,7 +142,18 @@ impl Default for Client {
         Self {
             rng: None,
             keypool: vec![1, 3, 4, 6, 10],
-            current_op: ExecutionHistoryEntry::default(),
+            keys: None,
+            session: None,
+            config: None,
+            operation_timeout: DEFAULT_OPERATION_TIMEOUT,
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            backoff: None,
+            batch_size: 1,
+            pending_request: None,
+            next_request: 0,
+            last_replica: None,
+            timeout_timer: None,
+            next_timer: None,
         }
     }
 }
@@ -49,70 +161,257 @@ impl Default for Client {
 impl ProcessHandle for Client {
     fn start(&mut self) {
         self.rng = Some(StdRng::seed_from_u64(configuration::seed()));
-        schedule_timer_after(Jiffies(100));
+        self.config = Some(initial_configuration());
+        let distribution = anykv::try_get::<KeyDistribution>("key_distribution")
+            .unwrap_or(KeyDistribution::Uniform);
+        self.keys = Some(KeySampler::new(self.keypool.len(), distribution));
+        self.session = Some(Session::new(rank()));
+        if let Some(timeout) = anykv::try_get::<Jiffies>("client_timeout") {
+            self.operation_timeout = timeout;
+        }
+        if let Some(max_attempts) = anykv::try_get::<usize>("client_max_attempts") {
+            self.max_attempts = max_attempts;
+        }
+        self.backoff = Some(
+            Backoff::new(RETRY_BACKOFF, RETRY_BACKOFF)
+                .with_budget(self.max_attempts.saturating_sub(1)),
+        );
+        if let Some(batch_size) = anykv::try_get::<usize>("client_batch_size") {
+            assert!(
+                (1..=self.keypool.len()).contains(&batch_size),
+                "Batch keys should be distinct keys of the keypool"
+            );
+            self.batch_size = batch_size;
+        }
+        self.next_timer = Some(schedule_timer_after(THINK_TIME));
     }
 
     fn on_message(&mut self, from: dscale::ProcessId, message: dscale::MessagePtr) {
-        let response = message.as_type::<ClientResponse>();
-        self.current_op.client = rank();
-        self.current_op.end = now();
-        match *response {
-            ClientResponse::GetResponse(value) => {
+        if let Some(reconfiguration) = message.try_as::<ReconfigurationMessage>() {
+            if let ReconfigurationMessage::NewConfiguration(config) = reconfiguration.as_ref() {
+                debug_process!("Switching to epoch {}", config.epoch);
+                self.config = Some(config.clone());
+            }
+            return;
+        }
+
+        let Some(response) = message.try_as::<ClientResponse>() else {
+            protocol_error!("Unexpected message from {from}");
+            return;
+        };
+        let in_flight = self.timeout_timer.is_some()
+            && self
+                .pending_request
+                .as_ref()
+                .is_some_and(|request| request.request() == response.request());
+        if !in_flight {
+            debug_process!("Ignoring late response from {from}");
+            return;
+        }
+        self.timeout_timer = None;
+
+        match response.as_ref() {
+            ClientResponse::GetResponse(_, value) => {
                 debug_process!("Got get response from {from}. Value: {value}");
-                self.current_op.result = Some(value);
+                self.complete(Some(*value), Vec::new());
             }
-            ClientResponse::PutAck => {
+            ClientResponse::MultiGetResponse(_, values) => {
+                debug_process!("Got multi get response from {from}. Values: {values:?}");
+                self.complete(None, values.clone());
+            }
+            ClientResponse::PutAck(_) => {
                 debug_process!("Got PutAck from {from}");
-                self.current_op.result = None;
+                self.complete(None, Vec::new());
+            }
+            ClientResponse::Retry(_) => {
+                debug_process!("Replica {from} asked to retry");
+                self.fail_attempt();
             }
         }
-
-        anykv::modify::<ExecutionHistory>("linearizable_history", |h| {
-            h.push(self.current_op.clone());
-        });
-
-        schedule_timer_after(Jiffies(100));
     }
 
-    fn on_timer(&mut self, _id: dscale::TimerId) {
-        self.do_random_operation();
+    fn on_timer(&mut self, id: dscale::TimerId) {
+        if self.timeout_timer == Some(id) {
+            debug_process!("Operation timed out");
+            self.timeout_timer = None;
+            self.fail_attempt();
+            return;
+        }
+
+        // Timeouts of already finished attempts are ignored
+        if self.next_timer == Some(id) {
+            self.next_timer = None;
+            match self.pending_request.clone() {
+                Some(request) => self.retry(request),
+                None => self.do_random_operation(),
+            }
+        }
     }
 }
 
 impl Client {
     fn choose_key(&mut self) -> Key {
-        self.keypool
-            .choose(self.rng.as_mut().unwrap())
-            .copied()
-            .unwrap()
+        self.keypool[self.keys.as_mut().expect("Not started").sample()]
     }
 
     fn choose_value(&self) -> Value {
         global_unique_id() // Make values monotonous
     }
 
+    fn next_request(&mut self) -> RequestId {
+        self.next_request += 1;
+        self.next_request
+    }
+
+    fn choose_keys(&mut self) -> Vec<Key> {
+        let mut keys = Vec::new();
+        while keys.len() < self.batch_size {
+            let key = self.choose_key();
+            if !keys.contains(&key) {
+                keys.push(key);
+            }
+        }
+        keys
+    }
+
     fn choose_operation(&mut self) -> ClientReq {
         let random_bool = self.rng.as_mut().unwrap().random::<bool>();
+        if self.batch_size > 1 {
+            return self.choose_batch(random_bool);
+        }
         let random_key = self.choose_key();
-
-        self.current_op.start = now();
+        let request = self.next_request();
 
         if random_bool {
             debug_process!("Choosed operation: Get({random_key})");
-            self.current_op.operation = String::from(format!("Get({random_key})"));
-            ClientReq::GetRequest(random_key)
+            ClientReq::GetRequest(request, random_key)
         } else {
             let value = self.choose_value();
             debug_process!("Choosed operation: Put({random_key},{value})");
-            self.current_op.operation = String::from(format!("Put({random_key},{value})"));
-            ClientReq::PutRequest(random_key, value)
+            ClientReq::PutRequest(request, random_key, value)
+        }
+    }
+
+    fn choose_batch(&mut self, read: bool) -> ClientReq {
+        let keys = self.choose_keys();
+        let request = self.next_request();
+
+        if read {
+            debug_process!("Choosed operation: MultiGet({keys:?})");
+            ClientReq::MultiGetRequest(request, keys)
+        } else {
+            let writes: Vec<(Key, Value)> = keys
+                .into_iter()
+                .map(|key| (key, self.choose_value()))
+                .collect();
+            debug_process!("Choosed operation: MultiPut({writes:?})");
+            ClientReq::MultiPutRequest(request, writes)
         }
     }
 
     fn do_random_operation(&mut self) {
-        let target = choose_from_pool("Replicas");
         let operation = self.choose_operation();
-        send_to(target, operation);
+        self.attempt(operation);
+    }
+
+    fn retry(&mut self, failed: ClientReq) {
+        let request = self.next_request();
+        let operation = match failed {
+            ClientReq::GetRequest(_, key) => ClientReq::GetRequest(request, key),
+            ClientReq::PutRequest(_, key, _) => {
+                ClientReq::PutRequest(request, key, self.choose_value())
+            }
+            ClientReq::MultiGetRequest(_, keys) => ClientReq::MultiGetRequest(request, keys),
+            ClientReq::MultiPutRequest(_, writes) => ClientReq::MultiPutRequest(
+                request,
+                writes
+                    .into_iter()
+                    .map(|(key, _)| (key, self.choose_value()))
+                    .collect(),
+            ),
+        };
+        debug_process!("Retrying, attempt {}", self.attempt_number());
+        self.attempt(operation);
+    }
+
+    fn backoff_mut(&mut self) -> &mut Backoff {
+        self.backoff.as_mut().expect("Not started")
+    }
+
+    fn attempt_number(&self) -> usize {
+        self.backoff.as_ref().expect("Not started").retries() + 1
+    }
+
+    fn session(&self) -> &Session {
+        self.session.as_ref().expect("Not started")
+    }
+
+    fn attempt(&mut self, operation: ClientReq) {
+        let description = match &operation {
+            ClientReq::GetRequest(_, key) => format!("Get({key})"),
+            ClientReq::PutRequest(_, key, value) => format!("Put({key},{value})"),
+            ClientReq::MultiGetRequest(_, keys) => {
+                let keys: Vec<String> = keys.iter().map(|key| key.to_string()).collect();
+                format!("MultiGet({})", keys.join(","))
+            }
+            ClientReq::MultiPutRequest(_, writes) => {
+                let writes: Vec<String> = writes
+                    .iter()
+                    .map(|(key, value)| format!("{key}={value}"))
+                    .collect();
+                format!("MultiPut({})", writes.join(","))
+            }
+        };
+        self.session().invoke(
+            operation.request(),
+            description,
+            operation.objects(),
+            self.attempt_number(),
+        );
+        self.pending_request = Some(operation.clone());
+        self.send_to_replica(operation);
+        self.timeout_timer = Some(schedule_timer_after(self.operation_timeout));
+    }
+
+    fn complete(&mut self, result: Option<Value>, reads: Vec<(Key, Value)>) {
+        let request = self.pending_request.take().expect("No pending request");
+        self.session().complete(request.request(), result, reads);
+        self.backoff_mut().reset();
+        self.next_timer = Some(schedule_timer_after(THINK_TIME));
+    }
+
+    fn fail_attempt(&mut self) {
+        let request = self
+            .pending_request
+            .as_ref()
+            .expect("No pending request")
+            .request();
+        self.session().fail(request);
+        let attempts = self.attempt_number();
+        match self.backoff_mut().schedule() {
+            Some(timer) => self.next_timer = Some(timer),
+            None => {
+                debug_process!("Giving up after {attempts} attempts");
+                self.pending_request = None;
+                self.next_timer = Some(schedule_timer_after(THINK_TIME));
+            }
+        }
+    }
+
+    // Retries go to a different replica whenever configuration allows it
+    fn send_to_replica(&mut self, request: ClientReq) {
+        let members = &self.config.as_ref().expect("Not started").members;
+        let candidates: Vec<ProcessId> = members
+            .iter()
+            .copied()
+            .filter(|member| members.len() == 1 || Some(*member) != self.last_replica)
+            .collect();
+        let target = candidates
+            .choose(self.rng.as_mut().unwrap())
+            .copied()
+            .unwrap();
+        self.last_replica = Some(target);
+        send_to(target, request);
         debug_process!("Sent operation to {target}");
     }
 }
@@ -0,0 +1,120 @@
+// Decoding of fuzzed messages for ABD replicas, enabled with the "fuzz" feature.
+// Replicas get client requests, register operations, reconfiguration and lease
+// messages with arbitrary keys, timestamps, epochs and sequence numbers. Replica 1
+// holds a lease, so lease messages are expected. Clients are sinks: they only
+// absorb replies.
+
+use std::collections::BTreeSet;
+
+use arbitrary::Arbitrary;
+use dscale::{
+    helpers::HandlerFuzzer,
+    message::{Shared, SharedMessage},
+    *,
+};
+
+use crate::abd_store::{
+    Replica,
+    client::ClientReq,
+    lease::LeaseMessage,
+    reconfiguration::ReconfigurationMessage,
+    register::RoutedRegisterOps,
+    types::{CLIENT_POOL_NAME, REPLICA_POOL_NAME, RequestId},
+};
+
+const REPLICAS: usize = 3;
+const CLIENTS: usize = 1;
+
+#[derive(Arbitrary)]
+enum ReplicaInput {
+    Client(ClientReq),
+    Register(RoutedRegisterOps),
+    Reconfiguration(ReconfigurationMessage),
+    Lease(LeaseMessage),
+}
+
+#[derive(Default)]
+struct Sink;
+
+impl ProcessHandle for Sink {
+    fn start(&mut self) {}
+    fn on_message(&mut self, _from: ProcessId, _message: MessagePtr) {}
+    fn on_timer(&mut self, _id: TimerId) {}
+}
+
+// Drops messages the sender would never send: clients send requests with fresh
+// ids, the reconfigurer (played by a client) stops and installs replicas,
+// replicas talk among themselves. Configurations are made of replicas.
+fn decode(
+    from: ProcessId,
+    input: ReplicaInput,
+    requests: &mut BTreeSet<RequestId>,
+) -> Option<SharedMessage> {
+    let replica = (1..=REPLICAS).contains(&from);
+    match input {
+        ReplicaInput::Client(request) if !replica => requests
+            .insert(request.request())
+            .then(|| Shared::new(distinct_keys(request)) as SharedMessage),
+        ReplicaInput::Register(ops) if replica => Some(Shared::new(ops)),
+        ReplicaInput::Lease(message) if replica => Some(Shared::new(message)),
+        ReplicaInput::Reconfiguration(message) if !replica => match message {
+            ReconfigurationMessage::Stop(_) => Some(Shared::new(message)),
+            ReconfigurationMessage::Install(mut config, state) => {
+                config
+                    .members
+                    .retain(|member| (1..=REPLICAS).contains(member));
+                Some(Shared::new(ReconfigurationMessage::Install(config, state)))
+            }
+            ReconfigurationMessage::NewConfiguration(mut config) => {
+                config
+                    .members
+                    .retain(|member| (1..=REPLICAS).contains(member));
+                Some(Shared::new(ReconfigurationMessage::NewConfiguration(
+                    config,
+                )))
+            }
+            ReconfigurationMessage::StopAck(..) | ReconfigurationMessage::InstallAck(_) => None,
+        },
+        _ => None,
+    }
+}
+
+// Keys of a batch are distinct, see ClientReq
+fn distinct_keys(request: ClientReq) -> ClientReq {
+    match request {
+        ClientReq::MultiPutRequest(request, mut writes) => {
+            writes.sort_by_key(|(key, _)| *key);
+            writes.dedup_by_key(|(key, _)| *key);
+            ClientReq::MultiPutRequest(request, writes)
+        }
+        ClientReq::MultiGetRequest(request, mut keys) => {
+            keys.sort();
+            keys.dedup();
+            ClientReq::MultiGetRequest(request, keys)
+        }
+        request => request,
+    }
+}
+
+// Feeds replicas with messages decoded from fuzzer input, returns the number of
+// delivered messages
+pub fn fuzz(data: &[u8]) -> usize {
+    global::anykv::set::<Vec<ProcessId>>("abd_lease_holders", vec![1]);
+
+    let builder = SimulationBuilder::default()
+        .add_pool::<Replica>(REPLICA_POOL_NAME, REPLICAS)
+        .add_pool::<Sink>(CLIENT_POOL_NAME, CLIENTS)
+        .latency_topology(&[
+            LatencyDescription::WithinPool(
+                REPLICA_POOL_NAME,
+                Distributions::Uniform(Jiffies(0), Jiffies(10)),
+            ),
+            LatencyDescription::BetweenPools(
+                CLIENT_POOL_NAME,
+                REPLICA_POOL_NAME,
+                Distributions::Uniform(Jiffies(10), Jiffies(50)),
+            ),
+        ]);
+    let mut requests = BTreeSet::new();
+    HandlerFuzzer::new(builder).fuzz(data, |from, input| decode(from, input, &mut requests))
+}
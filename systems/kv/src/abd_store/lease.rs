@@ -0,0 +1,285 @@
+// Quorum leases (Moraru, Andersen, Kaminsky. Paxos Quorum Leases, SoCC 2014) on top of ABD.
+//
+// A lease holder serves Get locally, without a quorum round trip, while it holds
+// a lease granted by every member of the configuration. A grantor promises for the
+// lease duration (measured on its local clock) to inform the holder of every
+// completed write: the client is answered once the holder acknowledged the
+// committed value, or once the promise expired. The holder measures its lease from
+// the moment it asked for it, so with perfect clocks it expires before any promise.
+// Clock drift breaks this assumption: a slow holder clock outlives fast grantor
+// clocks and serves stale values.
+//
+// Configured through anykv: lease holders under "abd_lease_holders" (leases are
+// disabled if absent), lease duration under "abd_lease_duration".
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use dscale::{
+    global::{anykv, configuration},
+    *,
+};
+
+use crate::abd_store::{
+    reconfiguration::Configuration,
+    register::Completion,
+    types::{Key, Timestamp, Value},
+};
+
+// Gets served locally by lease holders, counted in anykv
+pub const LOCAL_READS: &str = "abd_local_reads";
+
+const DEFAULT_LEASE_DURATION: Jiffies = Jiffies(1000);
+
+type LeaseSequence = usize;
+type CommitId = usize;
+
+#[cfg_attr(feature = "fuzz", derive(arbitrary::Arbitrary))]
+pub(crate) enum LeaseMessage {
+    Request(LeaseSequence),
+    Grant(LeaseSequence),
+    Commit(CommitId, LeaseSequence, Key, Value, Timestamp),
+    CommitAck(CommitId),
+}
+
+impl_virtual_size!(
+    enum LeaseMessage {
+        Request(sequence),
+        Grant(sequence),
+        Commit(id, sequence, key, value, timestamp),
+        CommitAck(id),
+    }
+);
+
+impl Message for LeaseMessage {
+    fn virtual_size(&self) -> usize {
+        helpers::virtual_size_of(self)
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Promise {
+    sequence: LeaseSequence,
+    expiry: Jiffies, // Grantor local time
+}
+
+struct PendingRenewal {
+    sent_at: Jiffies, // Holder local time
+    needed: usize,
+    granted: HashSet<ProcessId>,
+}
+
+struct PendingCommit {
+    completion: Completion,
+    waiting: HashSet<ProcessId>,
+}
+
+pub(crate) struct Leases {
+    holders: Vec<ProcessId>,
+    duration: Jiffies,
+
+    // Holder side
+    sequence: LeaseSequence,
+    renewals: BTreeMap<LeaseSequence, PendingRenewal>,
+    lease_start: LeaseSequence, // Commits of earlier leases may have missed writes
+    lease_until: Option<Jiffies>, // Holder local time
+    known: HashMap<Key, (Value, Timestamp)>,
+    renew_timer: Option<TimerId>,
+
+    // Grantor side
+    promises: BTreeMap<ProcessId, Promise>,
+    next_commit: CommitId,
+    commits: HashMap<CommitId, PendingCommit>,
+    expiry_timers: HashMap<TimerId, CommitId>,
+}
+
+impl Leases {
+    pub(crate) fn configured() -> Option<Self> {
+        let holders = anykv::try_get::<Vec<ProcessId>>("abd_lease_holders")?;
+        let duration =
+            anykv::try_get::<Jiffies>("abd_lease_duration").unwrap_or(DEFAULT_LEASE_DURATION);
+        if anykv::try_get::<usize>(LOCAL_READS).is_none() {
+            anykv::set::<usize>(LOCAL_READS, 0);
+        }
+        Some(Self {
+            holders,
+            duration,
+            sequence: 0,
+            renewals: BTreeMap::new(),
+            lease_start: 0,
+            lease_until: None,
+            known: HashMap::new(),
+            renew_timer: None,
+            promises: BTreeMap::new(),
+            next_commit: 0,
+            commits: HashMap::new(),
+            expiry_timers: HashMap::new(),
+        })
+    }
+
+    pub(crate) fn start(&mut self, config: &Configuration) {
+        if self.holders.contains(&rank()) {
+            self.renew(config);
+        }
+    }
+
+    // Leases are renewed three times per duration, so a single slow grant does not
+    // interrupt local reads
+    fn renew(&mut self, config: &Configuration) {
+        self.sequence += 1;
+        self.renewals.insert(
+            self.sequence,
+            PendingRenewal {
+                sent_at: configuration::local_now(),
+                needed: config.members.len(),
+                granted: HashSet::new(),
+            },
+        );
+        config
+            .members
+            .iter()
+            .for_each(|member| send_to(*member, LeaseMessage::Request(self.sequence)));
+        self.renew_timer = Some(schedule_timer_after(configuration::local_duration(
+            Jiffies(self.duration.0 / 3),
+        )));
+    }
+
+    fn valid(&self) -> bool {
+        self.lease_until
+            .is_some_and(|until| configuration::local_now() < until)
+    }
+
+    // Value of the last write the holder was informed of, None if the key has to be read from a quorum
+    pub(crate) fn read(&mut self, key: Key) -> Option<Value> {
+        if !self.valid() {
+            return None;
+        }
+        let (value, _) = self.known.get(&key)?;
+        debug_process!("Serving Get({key}) locally under lease");
+        anykv::modify::<usize>(LOCAL_READS, |reads| *reads += 1);
+        Some(*value)
+    }
+
+    // Configurations have different grantors
+    pub(crate) fn drop_lease(&mut self) {
+        self.lease_until = None;
+        self.renewals.clear();
+        self.known.clear();
+    }
+
+    // Returns the completion if no lease holder has to be informed of it first
+    pub(crate) fn hold(&mut self, completion: Completion) -> Option<Completion> {
+        let now = configuration::local_now();
+        let active: Vec<(ProcessId, Promise)> = self
+            .promises
+            .iter()
+            .filter(|(_, promise)| promise.expiry > now)
+            .map(|(holder, promise)| (*holder, *promise))
+            .collect();
+        if active.is_empty() {
+            return Some(completion);
+        }
+
+        self.next_commit += 1;
+        let (value, ts) = completion.committed;
+        active.iter().for_each(|(holder, promise)| {
+            send_to(
+                *holder,
+                LeaseMessage::Commit(
+                    self.next_commit,
+                    promise.sequence,
+                    completion.key,
+                    value,
+                    ts,
+                ),
+            )
+        });
+        let last_expiry = active
+            .iter()
+            .map(|(_, promise)| promise.expiry)
+            .max()
+            .expect("Not empty");
+        let timer = schedule_timer_after(configuration::local_duration(last_expiry - now));
+        self.expiry_timers.insert(timer, self.next_commit);
+        self.commits.insert(
+            self.next_commit,
+            PendingCommit {
+                completion,
+                waiting: active.iter().map(|(holder, _)| *holder).collect(),
+            },
+        );
+        None
+    }
+
+    // Returns completions released by the message
+    pub(crate) fn on_message(
+        &mut self,
+        from: ProcessId,
+        message: &LeaseMessage,
+    ) -> Vec<Completion> {
+        match *message {
+            LeaseMessage::Request(sequence) => {
+                self.promises.insert(
+                    from,
+                    Promise {
+                        sequence,
+                        expiry: configuration::local_now() + self.duration,
+                    },
+                );
+                send_to(from, LeaseMessage::Grant(sequence));
+            }
+            LeaseMessage::Grant(sequence) => {
+                let Some(renewal) = self.renewals.get_mut(&sequence) else {
+                    return Vec::new(); // Superseded or dropped
+                };
+                renewal.granted.insert(from);
+                if renewal.granted.len() < renewal.needed {
+                    return Vec::new();
+                }
+                let renewal = self.renewals.remove(&sequence).expect("Checked above");
+                self.renewals.retain(|pending, _| *pending > sequence);
+                if !self.valid() {
+                    debug_process!("Acquired lease {sequence}");
+                    self.lease_start = sequence;
+                    self.known.clear();
+                }
+                self.lease_until = Some(renewal.sent_at + self.duration);
+            }
+            LeaseMessage::Commit(id, sequence, key, value, ts) => {
+                if sequence >= self.lease_start {
+                    let known = self.known.entry(key).or_insert((value, ts));
+                    if (ts, value) > (known.1, known.0) {
+                        *known = (value, ts);
+                    }
+                }
+                send_to(from, LeaseMessage::CommitAck(id));
+            }
+            LeaseMessage::CommitAck(id) => {
+                let Some(commit) = self.commits.get_mut(&id) else {
+                    return Vec::new(); // Promises already expired
+                };
+                commit.waiting.remove(&from);
+                if commit.waiting.is_empty() {
+                    let commit = self.commits.remove(&id).expect("Checked above");
+                    return vec![commit.completion];
+                }
+            }
+        }
+        Vec::new()
+    }
+
+    // Returns completions released by promise expiry
+    pub(crate) fn on_timer(&mut self, id: TimerId, config: &Configuration) -> Vec<Completion> {
+        if self.renew_timer == Some(id) {
+            self.renew(config);
+            return Vec::new();
+        }
+        let Some(commit) = self.expiry_timers.remove(&id) else {
+            return Vec::new();
+        };
+        self.commits
+            .remove(&commit)
+            .map(|commit| commit.completion)
+            .into_iter()
+            .collect()
+    }
+}
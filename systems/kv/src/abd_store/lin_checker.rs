@@ -1,6 +1,7 @@
-use crate::abd_store::client::ExecutionHistory;
+use crate::abd_store::client::{ExecutionHistory, ExecutionHistoryEntry};
 use crate::abd_store::types::{Key, Value};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::ops::Range;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Operation {
@@ -14,130 +15,196 @@ pub struct Call {
     pub op: Operation,
     pub start: usize,
     pub end: usize,
+    // May be linearized anywhere after start or left out
+    pub ambiguous: bool,
 }
 
-// Wing-Gong like checker
-pub fn check_linearizable(history: &ExecutionHistory) -> bool {
-    let mut keys_history: HashMap<Key, Vec<Call>> = HashMap::new();
-    let mut max_time = 0;
+// Value of an object between segments of its sub-history, together with the
+// ambiguous writes which have not taken effect yet: they still may, or never
+type State = (Value, BTreeSet<usize>);
 
-    for entry in history {
-        if let Some(call) = parse_entry(entry) {
-            max_time = max_time.max(call.end);
-            keys_history.entry(call.key).or_default().push(call);
+// Wing-Gong like checker exploiting locality: linearizability is compositional
+// (Herlihy & Wing), so the history is linearizable iff the sub-history of every
+// object is, and every object is checked on its own
+pub fn check_linearizable(history: &ExecutionHistory) -> bool {
+    for (key, calls) in sub_histories(history) {
+        if let Err(window) = check_object(calls) {
+            println!(
+                "Linearizability violation for key {key} between {} and {}!",
+                window.start, window.end
+            );
+            return false;
         }
     }
 
-    if keys_history.is_empty() {
-        return true;
+    println!("Checker: History is linearizable!");
+    true
+}
+
+// Splits the history by object tags. Batches are linearizable per key, not
+// atomic: every key of a batch is a separate call spanning the whole batch
+pub fn sub_histories(history: &ExecutionHistory) -> BTreeMap<Key, Vec<Call>> {
+    let mut objects: BTreeMap<Key, Vec<Call>> = BTreeMap::new();
+    history
+        .iter()
+        .flat_map(project)
+        .for_each(|call| objects.entry(call.key).or_default().push(call));
+    objects
+}
+
+fn project(entry: &ExecutionHistoryEntry) -> Vec<Call> {
+    entry
+        .objects
+        .iter()
+        .filter_map(|(key, written)| {
+            let op = match written {
+                Some(value) => Operation::Write(*value),
+                // Reads which returned nothing constrain nothing
+                None => Operation::Read(read_value(entry, *key)?),
+            };
+            Some(Call {
+                key: *key,
+                op,
+                start: entry.start.0,
+                // Timed out write could still be applied by some replica
+                end: if entry.ambiguous {
+                    usize::MAX
+                } else {
+                    entry.end.0
+                },
+                ambiguous: entry.ambiguous,
+            })
+        })
+        .collect()
+}
+
+fn read_value(entry: &ExecutionHistoryEntry, key: Key) -> Option<Value> {
+    match entry.reads.iter().find(|(read, _)| *read == key) {
+        Some((_, value)) => Some(*value),
+        None if entry.objects.len() == 1 => entry.result,
+        None => None,
     }
+}
 
-    for (key, mut ops) in keys_history {
-        // Fix for Incomplete Operations
-        // Identify values that were read but never logged as a finished 'Put'
-        let mut written_values = HashSet::new();
-        let mut read_values = HashSet::new();
-        for op in &ops {
-            match op.op {
-                Operation::Write(v) => {
-                    written_values.insert(v);
-                }
-                Operation::Read(v) => {
-                    if v != 0 {
-                        read_values.insert(v);
-                    }
-                }
-            }
+// Checks the sub-history segment by segment. A segment ends once every call in it
+// has completed before the next one starts, so no linearization can interleave
+// calls of different segments: only the possible states are carried over, which
+// keeps the search exponential in the size of a segment, not of the history.
+// Returns the time window of the first segment which cannot be linearized.
+fn check_object(mut calls: Vec<Call>) -> Result<(), Range<usize>> {
+    calls.sort_by_key(|call| call.start);
+    let mut states: HashSet<State> = HashSet::from([(0, BTreeSet::new())]);
+
+    for segment in segments(&calls) {
+        let (ambiguous, mut definite): (Vec<usize>, Vec<usize>) =
+            segment.clone().partition(|index| calls[*index].ambiguous);
+        definite.sort_by_key(|index| calls[*index].end);
+
+        let mut next = HashSet::new();
+        for (value, pending) in &states {
+            let mut ops = definite.clone();
+            ops.extend(pending.iter().chain(&ambiguous));
+            let mut search = Search {
+                calls: &calls,
+                ops: &ops,
+                definite: definite.len(),
+                visited: HashSet::new(),
+                reached: &mut next,
+            };
+            search.run(&mut vec![false; ops.len()], *value);
         }
 
-        ops.sort_by_key(|op| op.end);
-        if !check_single_key(&ops) {
-            println!("Linearizability violation for key {}!", key);
-            return false;
+        if next.is_empty() {
+            let start = calls[segment.start].start;
+            let end = definite
+                .iter()
+                .map(|index| calls[*index].end)
+                .max()
+                .unwrap_or(start);
+            return Err(start..end);
         }
+        states = next;
     }
 
-    println!("Checker: History is linearizable!");
-    true
+    Ok(())
 }
 
-fn parse_entry(entry: &crate::abd_store::client::ExecutionHistoryEntry) -> Option<Call> {
-    let op_str = entry.operation.replace(" ", "");
-
-    if op_str.starts_with("Get") {
-        let key_str = op_str.strip_prefix("Get(")?.strip_suffix(")")?;
-        let key: Key = key_str.parse().ok()?;
-        let value = entry.result?;
-        Some(Call {
-            key,
-            op: Operation::Read(value),
-            start: entry.start.0,
-            end: entry.end.0,
-        })
-    } else if op_str.starts_with("Put") {
-        let inner = op_str.strip_prefix("Put(")?.strip_suffix(")")?;
-        let parts: Vec<&str> = inner.split(',').collect();
-        if parts.len() != 2 {
-            return None;
+// Ranges of calls (sorted by start) which overlap each other transitively.
+// Ambiguous calls never complete, so they do not hold a segment open
+fn segments(calls: &[Call]) -> Vec<Range<usize>> {
+    let mut segments = Vec::new();
+    let mut from = 0;
+    let mut horizon = None;
+    for (index, call) in calls.iter().enumerate() {
+        if horizon.is_some_and(|horizon| call.start > horizon) {
+            segments.push(from..index);
+            from = index;
+            horizon = None;
         }
-        let key: Key = parts[0].parse().ok()?;
-        let value: Value = parts[1].parse().ok()?;
-        Some(Call {
-            key,
-            op: Operation::Write(value),
-            start: entry.start.0,
-            end: entry.end.0,
-        })
-    } else {
-        None
+        if !call.ambiguous {
+            horizon = Some(horizon.unwrap_or(0).max(call.end));
+        }
+    }
+    if from < calls.len() {
+        segments.push(from..calls.len());
     }
+    segments
 }
 
-fn check_single_key(ops: &[Call]) -> bool {
-    let mut used = vec![false; ops.len()];
-    search(ops, &mut used, 0, 0)
+struct Search<'a> {
+    calls: &'a [Call],
+    ops: &'a [usize], // Definite calls first, then ambiguous ones
+    definite: usize,
+    visited: HashSet<(Vec<bool>, Value)>, // Explored partial linearizations
+    reached: &'a mut HashSet<State>,
 }
 
-fn search(ops: &[Call], used: &mut [bool], count: usize, current_value: Value) -> bool {
-    if count == ops.len() {
-        return true;
-    }
-
-    let mut min_end = usize::MAX;
-    for i in 0..ops.len() {
-        if !used[i] && ops[i].end < min_end {
-            min_end = ops[i].end;
+impl Search<'_> {
+    fn run(&mut self, used: &mut [bool], current_value: Value) {
+        if !self.visited.insert((used.to_vec(), current_value)) {
+            return;
         }
-    }
 
-    for i in 0..ops.len() {
-        if used[i] {
-            continue;
+        // Ambiguous writes left out may still take effect in later segments
+        if used[..self.definite].iter().all(|used| *used) {
+            let pending = self.ops[self.definite..]
+                .iter()
+                .zip(&used[self.definite..])
+                .filter(|(_, used)| !**used)
+                .map(|(index, _)| *index)
+                .collect();
+            self.reached.insert((current_value, pending));
+            return;
         }
-        let op = &ops[i];
 
-        if op.start > min_end {
-            continue;
-        }
+        let min_end = self
+            .ops
+            .iter()
+            .zip(used.iter())
+            .filter(|(_, used)| !**used)
+            .map(|(index, _)| self.calls[*index].end)
+            .min()
+            .unwrap_or(usize::MAX);
+
+        for i in 0..self.ops.len() {
+            if used[i] {
+                continue;
+            }
+            let op = &self.calls[self.ops[i]];
 
-        let consistent = match op.op {
-            Operation::Read(v) => v == current_value,
-            Operation::Write(_) => true,
-        };
+            if op.start > min_end {
+                continue;
+            }
 
-        if consistent {
-            used[i] = true;
             let next_value = match op.op {
+                Operation::Read(v) if v != current_value => continue,
                 Operation::Read(_) => current_value,
                 Operation::Write(v) => v,
             };
 
-            if search(ops, used, count + 1, next_value) {
-                return true;
-            }
+            used[i] = true;
+            self.run(used, next_value);
             used[i] = false;
         }
     }
-
-    false
 }
@@ -1,27 +1,51 @@
+pub mod availability;
 pub mod client;
+#[cfg(feature = "fuzz")]
+pub mod fuzzing;
+pub mod lease;
 pub mod lin_checker;
+pub mod reconfiguration;
 pub mod register;
+pub mod session;
 pub mod types;
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
-use dscale::{global::configuration::process_number, *};
+use dscale::*;
 
 use crate::abd_store::{
-    client::ClientReq,
-    register::{MWMRAtomicRegister, RoutedRegisterOp},
-    types::Key,
+    client::{ClientReq, ClientResponse},
+    lease::{LeaseMessage, Leases},
+    reconfiguration::{
+        Configuration, ReconfigurationMessage, RegistersState, initial_configuration,
+    },
+    register::{Completion, MWMRAtomicRegister, Outbox, RoutedRegisterOps},
+    types::{ClientId, Key, RequestId, Value},
 };
 
+// Keys of a batch are executed as independent register operations sharing
+// messages, the client is answered once all of them complete
+struct PendingBatch {
+    remaining: usize,
+    read: BTreeMap<Key, Value>, // Empty for MultiPut
+}
+
 #[derive(Default)]
 pub struct Replica {
-    proc_num: usize,
+    config: Option<Configuration>,
+    stopped: bool,
     registers: HashMap<Key, MWMRAtomicRegister>,
+    batches: HashMap<(ClientId, RequestId), PendingBatch>,
+    leases: Option<Leases>,
 }
 
 impl Replica {
-    fn quorum_size(&self) -> usize {
-        self.proc_num / 2 + 1
+    fn config(&self) -> &Configuration {
+        self.config.as_ref().expect("Not started")
+    }
+
+    fn serving(&self) -> bool {
+        !self.stopped && self.config().contains(rank())
     }
 
     fn find_register(&mut self, key: Key) -> &mut MWMRAtomicRegister {
@@ -33,29 +57,238 @@ impl Replica {
 
 impl ProcessHandle for Replica {
     fn start(&mut self) {
-        self.proc_num = process_number()
+        self.config = Some(initial_configuration());
+        self.leases = Leases::configured();
+        let config = self.config().clone();
+        if let Some(leases) = self.leases.as_mut() {
+            leases.start(&config);
+        }
     }
 
     fn on_message(&mut self, from: dscale::ProcessId, message: dscale::MessagePtr) {
         if let Some(client_op) = message.try_as::<ClientReq>() {
-            match *client_op {
-                ClientReq::GetRequest(key) => {
+            if !self.serving() {
+                debug_process!(
+                    "Not serving epoch {}, client {from} should retry",
+                    self.config().epoch
+                );
+                send_to(from, ClientResponse::Retry(client_op.request()));
+                return;
+            }
+
+            let config = self.config().clone();
+            let mut out = Outbox::default();
+            match client_op.as_ref() {
+                ClientReq::GetRequest(request, key) => {
                     debug_process!("Client {from} requested Get({key})");
-                    self.find_register(key).read(from);
+                    match self.leases.as_mut().and_then(|leases| leases.read(*key)) {
+                        Some(value) => send_to(from, ClientResponse::GetResponse(*request, value)),
+                        None => self
+                            .find_register(*key)
+                            .read(from, *request, &config, &mut out),
+                    }
                 }
-                ClientReq::PutRequest(key, value) => {
+                ClientReq::PutRequest(request, key, value) => {
                     debug_process!("Client {from} requested Put({key},{value})");
-                    self.find_register(key).write(from, value);
+                    self.find_register(*key)
+                        .write(from, *request, *value, &config, &mut out);
                 }
+                ClientReq::MultiGetRequest(request, keys) => {
+                    debug_process!("Client {from} requested MultiGet({keys:?})");
+                    self.start_batch(from, *request, keys.len());
+                    keys.iter().for_each(|key| {
+                        self.find_register(*key)
+                            .read(from, *request, &config, &mut out)
+                    });
+                }
+                ClientReq::MultiPutRequest(request, writes) => {
+                    debug_process!("Client {from} requested MultiPut({writes:?})");
+                    self.start_batch(from, *request, writes.len());
+                    writes.iter().for_each(|(key, value)| {
+                        self.find_register(*key)
+                            .write(from, *request, *value, &config, &mut out)
+                    });
+                }
+            }
+            self.flush(out);
+            return;
+        }
+
+        if let Some(reconfiguration) = message.try_as::<ReconfigurationMessage>() {
+            self.reconfigure(from, reconfiguration.as_ref());
+            return;
+        }
+
+        if let Some(lease) = message.try_as::<LeaseMessage>() {
+            let Some(leases) = self.leases.as_mut() else {
+                protocol_error!("Lease message from {from}, but leases are not configured");
+                return;
+            };
+            let released = leases.on_message(from, lease.as_ref());
+            released
+                .into_iter()
+                .for_each(|completion| self.complete(completion));
+            return;
+        }
+
+        let Some(register_ops) = message.try_as::<RoutedRegisterOps>() else {
+            protocol_error!("Unexpected message from {from}");
+            return;
+        };
+        if !self.serving() || register_ops.epoch != self.config().epoch {
+            return; // Operations of other configurations are aborted
+        }
+        let config = self.config().clone();
+        let mut out = Outbox::default();
+        register_ops.ops.iter().for_each(|(key, op)| {
+            self.find_register(*key)
+                .serve(op, from, *key, &config, &mut out)
+        });
+        self.flush(out);
+    }
+
+    fn on_timer(&mut self, id: TimerId) {
+        let config = self.config().clone();
+        let released = match self.leases.as_mut() {
+            Some(leases) => leases.on_timer(id, &config),
+            None => Vec::new(),
+        };
+        released
+            .into_iter()
+            .for_each(|completion| self.complete(completion));
+    }
+}
+
+// Batches
+impl Replica {
+    fn start_batch(&mut self, client: ClientId, request: RequestId, keys: usize) {
+        self.batches.insert(
+            (client, request),
+            PendingBatch {
+                remaining: keys,
+                read: BTreeMap::new(),
+            },
+        );
+    }
+
+    fn flush(&mut self, out: Outbox) {
+        let completions = out.flush(self.config().epoch);
+        completions.into_iter().for_each(|completion| {
+            // Lease holders learn of the write before the client does
+            let completion = match self.leases.as_mut() {
+                Some(leases) => leases.hold(completion),
+                None => Some(completion),
+            };
+            if let Some(completion) = completion {
+                self.complete(completion);
+            }
+        });
+    }
+
+    fn complete(&mut self, completion: Completion) {
+        let Completion {
+            client,
+            request,
+            key,
+            result,
+            ..
+        } = completion;
+        let Some(batch) = self.batches.get_mut(&(client, request)) else {
+            match result {
+                Some(value) => send_to(client, ClientResponse::GetResponse(request, value)),
+                None => send_to(client, ClientResponse::PutAck(request)),
             }
             return;
+        };
+
+        let Some(remaining) = batch.remaining.checked_sub(1) else {
+            protocol_error!("Request {request} of client {client} completed more keys than it has");
+            return;
+        };
+        batch.remaining = remaining;
+        if let Some(value) = result {
+            batch.read.insert(key, value);
+        }
+        if batch.remaining > 0 {
+            return;
+        }
+        let batch = self
+            .batches
+            .remove(&(client, request))
+            .expect("Checked above");
+        match result {
+            Some(_) => send_to(
+                client,
+                ClientResponse::MultiGetResponse(request, batch.read.into_iter().collect()),
+            ),
+            None => send_to(client, ClientResponse::PutAck(request)),
         }
+    }
+}
+
+// Reconfiguration
+impl Replica {
+    fn reconfigure(&mut self, from: ProcessId, message: &ReconfigurationMessage) {
+        match message {
+            ReconfigurationMessage::Stop(next_epoch) => {
+                debug_process!("Stopping epoch {}", self.config().epoch);
+                self.stopped = true;
+                let mut aborted: Vec<_> = self
+                    .registers
+                    .values_mut()
+                    .flat_map(|register| register.abort_pending())
+                    .collect();
+                aborted.sort();
+                aborted.dedup(); // Keys of a batch share the request
+                self.batches.clear();
+                aborted
+                    .into_iter()
+                    .for_each(|(client, request)| send_to(client, ClientResponse::Retry(request)));
+                send_to(
+                    from,
+                    ReconfigurationMessage::StopAck(*next_epoch, self.snapshot()),
+                );
+            }
+            ReconfigurationMessage::Install(config, _) if config.epoch < self.config().epoch => {}
+            ReconfigurationMessage::Install(config, state) => {
+                debug_process!("Installing epoch {}", config.epoch);
+                state.iter().for_each(|(key, value, ts)| {
+                    self.find_register(*key).install(*value, *ts);
+                });
+                self.config = Some(config.clone());
+                self.stopped = false;
+                self.drop_lease();
+                send_to(from, ReconfigurationMessage::InstallAck(config.epoch));
+            }
+            ReconfigurationMessage::NewConfiguration(config) => {
+                if config.epoch > self.config().epoch {
+                    self.config = Some(config.clone());
+                    self.stopped = !config.contains(rank());
+                    self.drop_lease();
+                }
+            }
+            ReconfigurationMessage::StopAck(..) | ReconfigurationMessage::InstallAck(_) => {
+                protocol_error!("Acks are addressed to Reconfigurer, got one from {from}")
+            }
+        }
+    }
 
-        let register_op = message.as_type::<RoutedRegisterOp>();
-        let quorum_size = self.quorum_size();
-        let register = self.find_register(register_op.key);
-        register.serve(&register_op.op, from, register_op.key, quorum_size);
+    fn drop_lease(&mut self) {
+        if let Some(leases) = self.leases.as_mut() {
+            leases.drop_lease();
+        }
     }
 
-    fn on_timer(&mut self, _id: TimerId) {}
+    fn snapshot(&self) -> RegistersState {
+        let mut state: RegistersState = self
+            .registers
+            .iter()
+            .map(|(key, register)| {
+                let (value, ts) = register.snapshot();
+                (*key, value, ts)
+            })
+            .collect();
+        state.sort();
+        state
+    }
 }
@@ -0,0 +1,202 @@
+// Stoppable configurations: https://arxiv.org/pdf/1010.2232
+//
+// All replicas are allocated upfront in REPLICA_POOL_NAME, a configuration selects
+// the subset of them serving operations. Changing configuration:
+//   1. Stop old configuration: members abort pending operations and stop serving
+//   2. Gather state from a majority of stopped members (intersects every completed quorum)
+//   3. Install merged state into new configuration and wait for majority of acks
+//   4. Announce new configuration to clients and replicas
+
+use std::collections::{BTreeMap, VecDeque};
+
+use dscale::{global::anykv, *};
+
+use crate::abd_store::types::{Key, REPLICA_POOL_NAME, Timestamp, Value};
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "fuzz", derive(arbitrary::Arbitrary))]
+pub struct Configuration {
+    pub epoch: usize,
+    pub members: Vec<ProcessId>,
+}
+
+impl Configuration {
+    pub fn quorum_size(&self) -> usize {
+        self.members.len() / 2 + 1
+    }
+
+    pub fn contains(&self, id: ProcessId) -> bool {
+        self.members.contains(&id)
+    }
+}
+
+// Sequence of (time, members) changes, configured through anykv under "abd_reconfigurations".
+// Members of the initial configuration are under "abd_initial_members" (whole replica pool if absent).
+pub type ReconfigurationPlan = Vec<(Jiffies, Vec<ProcessId>)>;
+
+pub fn initial_configuration() -> Configuration {
+    Configuration {
+        epoch: 0,
+        members: anykv::try_get::<Vec<ProcessId>>("abd_initial_members")
+            .unwrap_or_else(|| list_pool(REPLICA_POOL_NAME)),
+    }
+}
+
+pub(crate) type RegistersState = Vec<(Key, Value, Timestamp)>;
+
+#[cfg_attr(feature = "fuzz", derive(arbitrary::Arbitrary))]
+pub(crate) enum ReconfigurationMessage {
+    Stop(usize),
+    StopAck(usize, RegistersState),
+    Install(Configuration, RegistersState),
+    InstallAck(usize),
+    NewConfiguration(Configuration),
+}
+
+impl_virtual_size!(struct Configuration { epoch, members });
+impl_virtual_size!(
+    enum ReconfigurationMessage {
+        Stop(epoch),
+        StopAck(epoch, registers),
+        Install(configuration, registers),
+        InstallAck(epoch),
+        NewConfiguration(configuration),
+    }
+);
+
+impl Message for ReconfigurationMessage {
+    fn virtual_size(&self) -> usize {
+        helpers::virtual_size_of(self)
+    }
+}
+
+enum Phase {
+    Idle,
+    Stopping(Configuration, Vec<RegistersState>),
+    Installing(Configuration, usize),
+}
+
+pub struct Reconfigurer {
+    current: Option<Configuration>,
+    scheduled: VecDeque<Vec<ProcessId>>,
+    requested: VecDeque<Vec<ProcessId>>,
+    phase: Phase,
+}
+
+impl Default for Reconfigurer {
+    fn default() -> Self {
+        Self {
+            current: None,
+            scheduled: VecDeque::new(),
+            requested: VecDeque::new(),
+            phase: Phase::Idle,
+        }
+    }
+}
+
+impl ProcessHandle for Reconfigurer {
+    fn start(&mut self) {
+        self.current = Some(initial_configuration());
+        anykv::set::<Vec<(Jiffies, usize)>>("abd_epoch_changes", Vec::new());
+
+        let mut plan =
+            anykv::try_get::<ReconfigurationPlan>("abd_reconfigurations").unwrap_or_default();
+        plan.sort_by_key(|(at, _)| *at);
+        plan.into_iter().for_each(|(at, members)| {
+            schedule_timer_after(at);
+            self.scheduled.push_back(members);
+        });
+    }
+
+    fn on_message(&mut self, from: ProcessId, message: MessagePtr) {
+        let Some(message) = message.try_as::<ReconfigurationMessage>() else {
+            protocol_error!("Unexpected message from P{from}");
+            return;
+        };
+        let stop_quorum = self.current().quorum_size();
+        match (&mut self.phase, message.as_ref()) {
+            (Phase::Stopping(next, states), ReconfigurationMessage::StopAck(epoch, state))
+                if *epoch == next.epoch =>
+            {
+                debug_process!("P{from} stopped epoch {}", epoch - 1);
+                states.push(state.clone());
+                if states.len() == stop_quorum {
+                    let merged = merge(states);
+                    let next = next.clone();
+                    debug_process!("Installing epoch {} at {:?}", next.epoch, next.members);
+                    next.members.iter().for_each(|member| {
+                        send_to(
+                            *member,
+                            ReconfigurationMessage::Install(next.clone(), merged.clone()),
+                        );
+                    });
+                    self.phase = Phase::Installing(next, 0);
+                }
+            }
+            (Phase::Installing(next, acks), ReconfigurationMessage::InstallAck(epoch))
+                if *epoch == next.epoch =>
+            {
+                *acks += 1;
+                if *acks == next.quorum_size() {
+                    let next = next.clone();
+                    debug_process!("Epoch {} is active", next.epoch);
+                    anykv::modify::<Vec<(Jiffies, usize)>>("abd_epoch_changes", |changes| {
+                        changes.push((now(), next.epoch))
+                    });
+                    broadcast(ReconfigurationMessage::NewConfiguration(next.clone()));
+                    self.current = Some(next);
+                    self.phase = Phase::Idle;
+                    self.try_reconfigure();
+                }
+            }
+            _ => {} // Late acks from already finished phases
+        }
+    }
+
+    fn on_timer(&mut self, _id: TimerId) {
+        let members = self.scheduled.pop_front().expect("Unplanned timer");
+        self.requested.push_back(members);
+        self.try_reconfigure();
+    }
+}
+
+impl Reconfigurer {
+    fn current(&self) -> &Configuration {
+        self.current.as_ref().expect("Not started")
+    }
+
+    // Changes are applied one at a time, overlapping requests wait for the previous one
+    fn try_reconfigure(&mut self) {
+        if !matches!(self.phase, Phase::Idle) {
+            return;
+        }
+
+        let Some(members) = self.requested.pop_front() else {
+            return;
+        };
+
+        let next = Configuration {
+            epoch: self.current().epoch + 1,
+            members,
+        };
+        debug_process!("Stopping epoch {}", self.current().epoch);
+        self.current().members.iter().for_each(|member| {
+            send_to(*member, ReconfigurationMessage::Stop(next.epoch));
+        });
+        self.phase = Phase::Stopping(next, Vec::new());
+    }
+}
+
+fn merge(states: &[RegistersState]) -> RegistersState {
+    let mut merged: BTreeMap<Key, (Value, Timestamp)> = BTreeMap::new();
+    states.iter().flatten().for_each(|(key, value, ts)| {
+        let entry = merged.entry(*key).or_insert((*value, *ts));
+        if (*ts, *value) > (entry.1, entry.0) {
+            *entry = (*value, *ts);
+        }
+    });
+    merged
+        .into_iter()
+        .map(|(key, (value, ts))| (key, value, ts))
+        .collect()
+}
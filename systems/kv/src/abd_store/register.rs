@@ -5,15 +5,18 @@ use std::collections::HashMap;
 use dscale::*;
 
 use crate::abd_store::{
-    client::ClientResponse,
-    types::{ClientId, Key, REPLICA_POOL_NAME, ReadSequence, Timestamp, Value},
+    reconfiguration::Configuration,
+    types::{ClientId, Key, ReadSequence, RequestId, Timestamp, Value},
 };
 
-pub(crate) struct RoutedRegisterOp {
-    pub(crate) key: Key,
-    pub(crate) op: RegisterOps,
+#[cfg_attr(feature = "fuzz", derive(arbitrary::Arbitrary))]
+pub(crate) struct RoutedRegisterOps {
+    pub(crate) epoch: usize,
+    pub(crate) ops: Vec<(Key, RegisterOps)>, // Ops of several registers travel together
 }
 
+#[derive(Clone, Copy)]
+#[cfg_attr(feature = "fuzz", derive(arbitrary::Arbitrary))]
 pub(crate) enum RegisterOps {
     RegisterReadRequest(ReadSequence),
     RegisterReadResponse(Value, Timestamp, ReadSequence),
@@ -21,18 +24,102 @@ pub(crate) enum RegisterOps {
     RegisterWriteAck(Value, Timestamp),
 }
 
-impl Message for RoutedRegisterOp {}
+impl_virtual_size!(struct RoutedRegisterOps { epoch, ops });
+impl_virtual_size!(
+    enum RegisterOps {
+        RegisterReadRequest(sequence),
+        RegisterReadResponse(value, timestamp, sequence),
+        RegisterWriteRequest(value, timestamp),
+        RegisterWriteAck(value, timestamp),
+    }
+);
+
+impl Message for RoutedRegisterOps {
+    fn virtual_size(&self) -> usize {
+        helpers::virtual_size_of(self)
+    }
+}
+
+// Client operation finished by a register, Put has no result
+pub(crate) struct Completion {
+    pub(crate) client: ClientId,
+    pub(crate) request: RequestId,
+    pub(crate) key: Key,
+    pub(crate) result: Option<Value>,
+    pub(crate) committed: (Value, Timestamp), // Written to a quorum by the operation
+}
+
+// Collects ops and completions of all registers touched while handling one message,
+// ops addressed to the same replica are sent as one message
+#[derive(Default)]
+pub(crate) struct Outbox {
+    ops: Vec<(ProcessId, Key, RegisterOps)>,
+    completions: Vec<Completion>,
+}
+
+impl Outbox {
+    fn broadcast_within_configuration(
+        &mut self,
+        config: &Configuration,
+        key: Key,
+        op: RegisterOps,
+    ) {
+        config
+            .members
+            .iter()
+            .for_each(|member| self.ops.push((*member, key, op)));
+    }
+
+    fn send(&mut self, to: ProcessId, key: Key, op: RegisterOps) {
+        self.ops.push((to, key, op));
+    }
+
+    fn complete(
+        &mut self,
+        client: ClientId,
+        request: RequestId,
+        key: Key,
+        result: Option<Value>,
+        committed: (Value, Timestamp),
+    ) {
+        self.completions.push(Completion {
+            client,
+            request,
+            key,
+            result,
+            committed,
+        });
+    }
+
+    // Destinations are served in order of their first op
+    pub(crate) fn flush(self, epoch: usize) -> Vec<Completion> {
+        let mut destinations = Vec::new();
+        let mut grouped: HashMap<ProcessId, Vec<(Key, RegisterOps)>> = HashMap::new();
+        self.ops.into_iter().for_each(|(to, key, op)| {
+            let ops = grouped.entry(to).or_insert_with(|| {
+                destinations.push(to);
+                Vec::new()
+            });
+            ops.push((key, op));
+        });
+        destinations.into_iter().for_each(|to| {
+            let ops = grouped.remove(&to).expect("Grouped above");
+            send_to(to, RoutedRegisterOps { epoch, ops });
+        });
+        self.completions
+    }
+}
 
 // Manual coroutines
 enum CoroResumeAfterReadQuorum {
-    Write(ClientId, Value),
-    Read(ClientId),
+    Write(ClientId, RequestId, Value),
+    Read(ClientId, RequestId),
 }
 
 // Manual coroutines
 enum CoroResumeAfterWriteQuorum {
-    Write(ClientId),
-    Read(ClientId, Value),
+    Write(ClientId, RequestId),
+    Read(ClientId, RequestId, Value),
 }
 
 struct PendingReadQuorum {
@@ -68,42 +155,51 @@ impl MWMRAtomicRegister {
         }
     }
 
-    pub(crate) fn write(&mut self, client: ClientId, value: Value) {
+    pub(crate) fn write(
+        &mut self,
+        client: ClientId,
+        request: RequestId,
+        value: Value,
+        config: &Configuration,
+        out: &mut Outbox,
+    ) {
         self.r += 1;
         debug_process!("[r == {}] Gathering read quorum for Write...", self.r);
         self.pending_read_quorums.insert(
             self.r,
             PendingReadQuorum {
-                resume: CoroResumeAfterReadQuorum::Write(client, value),
+                resume: CoroResumeAfterReadQuorum::Write(client, request, value),
                 read_quorum: Vec::new(),
             },
         );
-        broadcast_within_pool(
-            REPLICA_POOL_NAME,
-            RoutedRegisterOp {
-                key: self.key,
-                op: RegisterOps::RegisterReadRequest(self.r),
-            },
+        out.broadcast_within_configuration(
+            config,
+            self.key,
+            RegisterOps::RegisterReadRequest(self.r),
         );
         return;
     }
 
-    pub(crate) fn read(&mut self, client: ClientId) {
+    pub(crate) fn read(
+        &mut self,
+        client: ClientId,
+        request: RequestId,
+        config: &Configuration,
+        out: &mut Outbox,
+    ) {
         self.r += 1;
         debug_process!("[r == {}]. Gathering read quorum for Read...", self.r);
         self.pending_read_quorums.insert(
             self.r,
             PendingReadQuorum {
-                resume: CoroResumeAfterReadQuorum::Read(client),
+                resume: CoroResumeAfterReadQuorum::Read(client, request),
                 read_quorum: Vec::new(),
             },
         );
-        broadcast_within_pool(
-            REPLICA_POOL_NAME,
-            RoutedRegisterOp {
-                key: self.key,
-                op: RegisterOps::RegisterReadRequest(self.r),
-            },
+        out.broadcast_within_configuration(
+            config,
+            self.key,
+            RegisterOps::RegisterReadRequest(self.r),
         );
     }
 
@@ -112,16 +208,16 @@ impl MWMRAtomicRegister {
         op: &RegisterOps,
         from: ProcessId,
         key: Key,
-        quorum_size: usize,
+        config: &Configuration,
+        out: &mut Outbox,
     ) {
+        let quorum_size = config.quorum_size();
         match *op {
             RegisterOps::RegisterReadRequest(r_) => {
-                send_to(
+                out.send(
                     from,
-                    RoutedRegisterOp {
-                        key,
-                        op: RegisterOps::RegisterReadResponse(self.local_value, self.local_ts, r_),
-                    },
+                    key,
+                    RegisterOps::RegisterReadResponse(self.local_value, self.local_ts, r_),
                 );
                 return;
             }
@@ -131,23 +227,22 @@ impl MWMRAtomicRegister {
                     self.local_value = v_;
                     self.local_ts = t_;
                 }
-                send_to(
-                    from,
-                    RoutedRegisterOp {
-                        key,
-                        op: RegisterOps::RegisterWriteAck(v_, t_),
-                    },
-                );
+                out.send(from, key, RegisterOps::RegisterWriteAck(v_, t_));
                 return;
             }
 
             RegisterOps::RegisterReadResponse(v_, t_, r) => {
-                let qourum_info = self.pending_read_quorums.get_mut(&r).unwrap();
+                // Operation was aborted by reconfiguration
+                let Some(qourum_info) = self.pending_read_quorums.get_mut(&r) else {
+                    return;
+                };
                 qourum_info.read_quorum.push((v_, t_, r));
 
                 if qourum_info.read_quorum.len() == quorum_size {
+                    // Completed quorums must not be aborted by reconfiguration
+                    let qourum_info = self.pending_read_quorums.remove(&r).unwrap();
                     match qourum_info.resume {
-                        CoroResumeAfterReadQuorum::Write(client, saved_value) => {
+                        CoroResumeAfterReadQuorum::Write(client, request, saved_value) => {
                             debug_process!("Gathered read quorum for Write");
                             debug_process!("Resuming Write...");
                             let t_ = qourum_info
@@ -161,21 +256,19 @@ impl MWMRAtomicRegister {
                             self.pending_write_quorums.insert(
                                 self.t,
                                 PendingWriteQuorum {
-                                    resume: CoroResumeAfterWriteQuorum::Write(client),
+                                    resume: CoroResumeAfterWriteQuorum::Write(client, request),
                                     write_quorum: Vec::new(),
                                 },
                             );
 
                             debug_process!("Gathering write quorum for Write...");
-                            broadcast_within_pool(
-                                REPLICA_POOL_NAME,
-                                RoutedRegisterOp {
-                                    key,
-                                    op: RegisterOps::RegisterWriteRequest(saved_value, self.t),
-                                },
+                            out.broadcast_within_configuration(
+                                config,
+                                key,
+                                RegisterOps::RegisterWriteRequest(saved_value, self.t),
                             );
                         }
-                        CoroResumeAfterReadQuorum::Read(client) => {
+                        CoroResumeAfterReadQuorum::Read(client, request) => {
                             debug_process!("Gathered read quorum for Read");
                             debug_process!("Resuming Read...");
                             // let v_m be the largest value with the highest timestamp t_m
@@ -189,18 +282,16 @@ impl MWMRAtomicRegister {
                             self.pending_write_quorums.insert(
                                 t_m,
                                 PendingWriteQuorum {
-                                    resume: CoroResumeAfterWriteQuorum::Read(client, v_m),
+                                    resume: CoroResumeAfterWriteQuorum::Read(client, request, v_m),
                                     write_quorum: Vec::new(),
                                 },
                             );
 
                             debug_process!("Gathering write quorum for Read...");
-                            broadcast_within_pool(
-                                REPLICA_POOL_NAME,
-                                RoutedRegisterOp {
-                                    key,
-                                    op: RegisterOps::RegisterWriteRequest(v_m, t_m),
-                                },
+                            out.broadcast_within_configuration(
+                                config,
+                                key,
+                                RegisterOps::RegisterWriteRequest(v_m, t_m),
                             );
                         }
                     }
@@ -208,20 +299,24 @@ impl MWMRAtomicRegister {
             }
 
             RegisterOps::RegisterWriteAck(v, t) => {
-                let qourum_info = self.pending_write_quorums.get_mut(&t).unwrap();
+                // Operation was aborted by reconfiguration
+                let Some(qourum_info) = self.pending_write_quorums.get_mut(&t) else {
+                    return;
+                };
                 qourum_info.write_quorum.push((v, t));
 
                 if qourum_info.write_quorum.len() == quorum_size {
+                    let qourum_info = self.pending_write_quorums.remove(&t).unwrap();
                     match qourum_info.resume {
-                        CoroResumeAfterWriteQuorum::Write(client) => {
+                        CoroResumeAfterWriteQuorum::Write(client, request) => {
                             debug_process!("Gathered write quorum for Write");
                             debug_process!("Resuming Write...");
-                            send_to(client, ClientResponse::PutAck);
+                            out.complete(client, request, key, None, (v, t));
                         }
-                        CoroResumeAfterWriteQuorum::Read(client, saved_value) => {
+                        CoroResumeAfterWriteQuorum::Read(client, request, saved_value) => {
                             debug_process!("Gathered write quorum for Read");
                             debug_process!("Resuming Read...");
-                            send_to(client, ClientResponse::GetResponse(saved_value));
+                            out.complete(client, request, key, Some(saved_value), (v, t));
                         }
                     }
                 }
@@ -229,3 +324,38 @@ impl MWMRAtomicRegister {
         }
     }
 }
+
+// Reconfiguration
+impl MWMRAtomicRegister {
+    pub(crate) fn snapshot(&self) -> (Value, Timestamp) {
+        (self.local_value, self.local_ts)
+    }
+
+    pub(crate) fn install(&mut self, value: Value, ts: Timestamp) {
+        if ts > self.local_ts || (ts == self.local_ts && value > self.local_value) {
+            self.local_value = value;
+            self.local_ts = ts;
+        }
+    }
+
+    // Returns client requests which will never complete in the stopped configuration
+    pub(crate) fn abort_pending(&mut self) -> Vec<(ClientId, RequestId)> {
+        let readers = self
+            .pending_read_quorums
+            .drain()
+            .map(|(_, pending)| match pending.resume {
+                CoroResumeAfterReadQuorum::Write(client, request, _) => (client, request),
+                CoroResumeAfterReadQuorum::Read(client, request) => (client, request),
+            });
+        let writers = self
+            .pending_write_quorums
+            .drain()
+            .map(|(_, pending)| match pending.resume {
+                CoroResumeAfterWriteQuorum::Write(client, request) => (client, request),
+                CoroResumeAfterWriteQuorum::Read(client, request, _) => (client, request),
+            });
+        let mut clients: Vec<(ClientId, RequestId)> = readers.chain(writers).collect();
+        clients.sort(); // HashMap drain order is not deterministic
+        clients
+    }
+}
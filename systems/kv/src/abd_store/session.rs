@@ -0,0 +1,163 @@
+// Clients never mutate shared history entries: every client appends invoke/complete
+// events of its own operations, history is assembled from the whole log afterwards.
+
+use std::collections::BTreeMap;
+
+use dscale::{global::anykv, *};
+
+use crate::abd_store::{
+    client::{ExecutionHistory, ExecutionHistoryEntry, ObjectAccess},
+    types::{ClientId, Key, RequestId, Value},
+};
+
+pub type OpId = (ClientId, RequestId);
+
+#[derive(Clone, Debug)]
+pub enum SessionEvent {
+    Invoke {
+        id: OpId,
+        operation: String,
+        objects: Vec<ObjectAccess>,
+        attempt: usize, // Attempts of one client operation are numbered from 1
+        at: Jiffies,
+    },
+    Complete {
+        id: OpId,
+        result: Option<Value>,
+        reads: Vec<(Key, Value)>, // MultiGet only
+        at: Jiffies,
+    },
+    // Timed out or aborted, operation may take effect later or never
+    Fail {
+        id: OpId,
+        at: Jiffies,
+    },
+}
+
+impl SessionEvent {
+    fn id(&self) -> OpId {
+        match *self {
+            SessionEvent::Invoke { id, .. } => id,
+            SessionEvent::Complete { id, .. } => id,
+            SessionEvent::Fail { id, .. } => id,
+        }
+    }
+}
+
+// Stored under "session_log" in anykv
+pub type SessionLog = Vec<SessionEvent>;
+
+pub(crate) struct Session {
+    client: ClientId,
+}
+
+impl Session {
+    pub(crate) fn new(client: ClientId) -> Self {
+        Self { client }
+    }
+
+    pub(crate) fn invoke(
+        &self,
+        request: RequestId,
+        operation: String,
+        objects: Vec<ObjectAccess>,
+        attempt: usize,
+    ) {
+        self.append(SessionEvent::Invoke {
+            id: (self.client, request),
+            operation,
+            objects,
+            attempt,
+            at: now(),
+        });
+    }
+
+    pub(crate) fn complete(
+        &self,
+        request: RequestId,
+        result: Option<Value>,
+        reads: Vec<(Key, Value)>,
+    ) {
+        self.append(SessionEvent::Complete {
+            id: (self.client, request),
+            result,
+            reads,
+            at: now(),
+        });
+    }
+
+    pub(crate) fn fail(&self, request: RequestId) {
+        self.append(SessionEvent::Fail {
+            id: (self.client, request),
+            at: now(),
+        });
+    }
+
+    fn append(&self, event: SessionEvent) {
+        anykv::modify::<SessionLog>("session_log", |log| log.push(event));
+    }
+}
+
+// Pairs invocations with their outcomes. Operations still in flight
+// when simulation ended are ambiguous.
+pub fn build_history(log: &SessionLog) -> ExecutionHistory {
+    let last_event = log
+        .iter()
+        .map(|event| match *event {
+            SessionEvent::Invoke { at, .. } => at,
+            SessionEvent::Complete { at, .. } => at,
+            SessionEvent::Fail { at, .. } => at,
+        })
+        .max()
+        .unwrap_or_default();
+
+    let mut operations: BTreeMap<OpId, (ExecutionHistoryEntry, bool)> = BTreeMap::new();
+    for event in log {
+        let id = event.id();
+        match event {
+            SessionEvent::Invoke {
+                operation,
+                objects,
+                at,
+                ..
+            } => {
+                let entry = ExecutionHistoryEntry {
+                    client: id.0,
+                    operation: operation.clone(),
+                    objects: objects.clone(),
+                    result: None,
+                    reads: Vec::new(),
+                    start: *at,
+                    end: last_event,
+                    ambiguous: true,
+                };
+                let previous = operations.insert(id, (entry, false));
+                assert!(previous.is_none(), "Operation {id:?} invoked twice");
+            }
+            SessionEvent::Complete {
+                result, reads, at, ..
+            } => {
+                let (entry, finished) = operations
+                    .get_mut(&id)
+                    .expect("Completion without invocation");
+                assert!(!*finished, "Operation {id:?} finished twice");
+                *finished = true;
+                entry.result = *result;
+                entry.reads = reads.clone();
+                entry.end = *at;
+                entry.ambiguous = false;
+            }
+            SessionEvent::Fail { at, .. } => {
+                let (entry, finished) =
+                    operations.get_mut(&id).expect("Failure without invocation");
+                assert!(!*finished, "Operation {id:?} finished twice");
+                *finished = true;
+                entry.end = *at;
+            }
+        }
+    }
+
+    let mut history: ExecutionHistory = operations.into_values().map(|(entry, _)| entry).collect();
+    history.sort_by_key(|entry| (entry.end, entry.start, entry.client));
+    history
+}
@@ -5,6 +5,7 @@ pub type Key = usize;
 pub type Timestamp = usize;
 pub type ReadSequence = usize;
 pub type ClientId = ProcessId;
+pub type RequestId = usize;
 
 pub const REPLICA_POOL_NAME: &str = "Replicas";
 pub const CLIENT_POOL_NAME: &str = "Clients";
@@ -0,0 +1,94 @@
+use dscale::{
+    global::anykv,
+    scenario::{heal, inject_partition},
+    *,
+};
+use kv::abd_store::{
+    Replica,
+    availability::AvailabilityTimeline,
+    client::Client,
+    session::SessionLog,
+    types::{CLIENT_POOL_NAME, REPLICA_POOL_NAME},
+};
+
+const SLA: Jiffies = Jiffies(300);
+const BUCKET: Jiffies = Jiffies(1000);
+const TIME_BUDGET: Jiffies = Jiffies(15000);
+
+fn main() {
+    anykv::set::<Jiffies>("client_timeout", Jiffies(500));
+    anykv::set::<usize>("client_max_attempts", 3);
+
+    // Replicas 1..=5, clients 6..=9: clients reach only a minority of replicas
+    // between 5000 and 10000
+    let scenario = Scenario::new()
+        .at(
+            Jiffies(5000),
+            inject_partition(&[&[1, 2, 3], &[4, 5, 6, 7, 8, 9]]),
+        )
+        .at(Jiffies(10000), heal());
+
+    // 1 jiffy == 1ms
+    let mut sim = SimulationBuilder::default()
+        .add_pool::<Replica>(REPLICA_POOL_NAME, 5)
+        .add_pool::<Client>(CLIENT_POOL_NAME, 4)
+        .time_budget(TIME_BUDGET)
+        .latency_topology(&[
+            LatencyDescription::WithinPool(
+                REPLICA_POOL_NAME,
+                Distributions::Uniform(Jiffies(0), Jiffies(10)),
+            ),
+            LatencyDescription::BetweenPools(
+                CLIENT_POOL_NAME,
+                REPLICA_POOL_NAME,
+                Distributions::Uniform(Jiffies(10), Jiffies(50)),
+            ),
+        ])
+        .scenario(scenario)
+        .seed(5444)
+        .build();
+
+    anykv::set::<SessionLog>("session_log", SessionLog::new());
+
+    sim.run();
+
+    let timeline = AvailabilityTimeline::of(
+        &anykv::get::<SessionLog>("session_log"),
+        SLA,
+        BUCKET,
+        TIME_BUDGET,
+    );
+    timeline.buckets.iter().for_each(|bucket| {
+        let availability = bucket
+            .availability()
+            .map_or("-".to_string(), |availability| {
+                format!("{:.1}%", 100.0 * availability)
+            });
+        println!(
+            "{:>6}: availability {availability:>6} ({}/{} ops), goodput {}/s",
+            bucket.start.0,
+            bucket.within_sla,
+            bucket.issued,
+            bucket.goodput * 1000 / BUCKET.0
+        );
+    });
+    println!(
+        "Overall availability: {:.1}%, worst bucket: {:.1}%",
+        100.0 * timeline.availability(),
+        100.0 * timeline.worst().unwrap_or(1.0)
+    );
+
+    let partitioned = |start: Jiffies| (5000..10000).contains(&start.0);
+    timeline.buckets.iter().for_each(|bucket| {
+        if partitioned(bucket.start) {
+            assert_eq!(bucket.within_sla, 0, "Minority should not serve clients");
+        } else if bucket.start.0 < 4000 || bucket.start.0 >= 11000 {
+            assert!(
+                bucket
+                    .availability()
+                    .is_some_and(|availability| availability > 0.9),
+                "Healthy replicas should meet the SLA"
+            );
+        }
+    });
+}
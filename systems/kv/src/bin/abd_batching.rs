@@ -0,0 +1,71 @@
+use dscale::{
+    global::{anykv, tracing},
+    *,
+};
+use kv::abd_store::{
+    Replica,
+    client::Client,
+    lin_checker::check_linearizable,
+    session::{SessionLog, build_history},
+    types::{CLIENT_POOL_NAME, REPLICA_POOL_NAME},
+};
+
+// Register messages per key operation: keys of a batch share quorum round trips
+fn main() {
+    let single = run(1);
+    let pairs = run(2);
+    let batched = run(4);
+
+    assert!(
+        batched < pairs && pairs < single,
+        "Batching should reduce register messages per key"
+    );
+}
+
+fn run(batch_size: usize) -> f64 {
+    anykv::set::<usize>("client_batch_size", batch_size);
+
+    // 1 jiffy == 1ms
+    let mut sim = SimulationBuilder::default()
+        .add_pool::<Replica>(REPLICA_POOL_NAME, 10)
+        .add_pool::<Client>(CLIENT_POOL_NAME, 4)
+        .time_budget(Jiffies(10000))
+        .latency_topology(&[
+            LatencyDescription::WithinPool(
+                REPLICA_POOL_NAME,
+                Distributions::Uniform(Jiffies(0), Jiffies(10)),
+            ),
+            LatencyDescription::BetweenPools(
+                CLIENT_POOL_NAME,
+                REPLICA_POOL_NAME,
+                Distributions::Uniform(Jiffies(10), Jiffies(50)),
+            ),
+        ])
+        .trace_messages(true)
+        .seed(5444)
+        .build();
+
+    anykv::set::<SessionLog>("session_log", SessionLog::new());
+
+    sim.run();
+
+    let trace = tracing::record();
+    let count = |type_name: &str| {
+        trace
+            .messages
+            .iter()
+            .filter(|message| message.type_name == type_name)
+            .count()
+    };
+    let key_operations = count("ClientReq") * batch_size;
+    let register_messages = count("RoutedRegisterOps");
+    let per_key = register_messages as f64 / key_operations as f64;
+    println!(
+        "batch size {batch_size}: {key_operations} key operations, {register_messages} register messages, {per_key:.1} per key"
+    );
+
+    assert!(check_linearizable(&build_history(
+        &anykv::get::<SessionLog>("session_log")
+    )));
+    per_key
+}
@@ -0,0 +1,68 @@
+use dscale::{global::anykv, *};
+use kv::abd_store::{
+    Replica,
+    client::Client,
+    lease::LOCAL_READS,
+    lin_checker::check_linearizable,
+    session::{SessionLog, build_history},
+    types::{CLIENT_POOL_NAME, REPLICA_POOL_NAME},
+};
+
+const LEASE_HOLDER: ProcessId = 1;
+
+// Replica 1 holds a quorum lease and serves Gets locally. Safe with synchronized
+// clocks, stale reads once its clock runs slower than clocks of the grantors.
+// Leases last 300 local jiffies and are renewed every 100. With -0.5/+0.5 drift
+// the holder renews every 200 real jiffies, exactly when promises of the grantors
+// lapse, so they are missing only while renewals are in flight and a stale read
+// depends on the order of events tied in time. With -0.8/+0.8 renewals go out
+// every 500 real jiffies and promises lapse after 167, leaving a wide window
+fn main() {
+    let (synchronized, local_reads) = run(0.0, 0.0);
+    println!("Synchronized clocks: {local_reads} local reads, linearizable: {synchronized}");
+    assert!(local_reads > 0, "Lease holder should serve reads locally");
+    assert!(
+        synchronized,
+        "Leases should be safe with synchronized clocks"
+    );
+
+    let (drifted, local_reads) = run(-0.8, 0.8);
+    println!("Drifted clocks: {local_reads} local reads, linearizable: {drifted}");
+    assert!(
+        !drifted,
+        "Slow lease holder should serve stale reads after promises expired"
+    );
+}
+
+fn run(holder_drift: f64, grantor_drift: f64) -> (bool, usize) {
+    anykv::set::<Vec<ProcessId>>("abd_lease_holders", vec![LEASE_HOLDER]);
+    anykv::set::<Jiffies>("abd_lease_duration", Jiffies(300));
+
+    // 1 jiffy == 1ms
+    let mut sim = SimulationBuilder::default()
+        .add_pool::<Replica>(REPLICA_POOL_NAME, 5)
+        .add_pool::<Client>(CLIENT_POOL_NAME, 10)
+        .time_budget(Jiffies(20000))
+        .latency_topology(&[
+            LatencyDescription::WithinPool(
+                REPLICA_POOL_NAME,
+                Distributions::Uniform(Jiffies(0), Jiffies(10)),
+            ),
+            LatencyDescription::BetweenPools(
+                CLIENT_POOL_NAME,
+                REPLICA_POOL_NAME,
+                Distributions::Uniform(Jiffies(10), Jiffies(50)),
+            ),
+        ])
+        .clock_drift(REPLICA_POOL_NAME, grantor_drift)
+        .process_clock_drift(LEASE_HOLDER, holder_drift)
+        .seed(5444)
+        .build();
+
+    anykv::set::<SessionLog>("session_log", SessionLog::new());
+
+    sim.run();
+
+    let linearizable = check_linearizable(&build_history(&anykv::get::<SessionLog>("session_log")));
+    (linearizable, anykv::get::<usize>(LOCAL_READS))
+}
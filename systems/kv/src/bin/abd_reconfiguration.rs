@@ -0,0 +1,79 @@
+use dscale::{global::anykv, *};
+use kv::abd_store::{
+    Replica,
+    client::Client,
+    lin_checker::check_linearizable,
+    reconfiguration::{ReconfigurationPlan, Reconfigurer},
+    session::{SessionLog, build_history},
+    types::{CLIENT_POOL_NAME, REPLICA_POOL_NAME},
+};
+
+const RECONFIGURER_POOL_NAME: &str = "Reconfigurer";
+
+fn main() {
+    // Replicas: P1..=P10, Clients: P11..=P14, Reconfigurer: P15
+    anykv::set::<Vec<ProcessId>>("abd_initial_members", vec![1, 2, 3, 4, 5]);
+    anykv::set::<ReconfigurationPlan>(
+        "abd_reconfigurations",
+        vec![
+            (Jiffies(1500), vec![4, 5, 6, 7, 8]), // Overlapping configuration
+            (Jiffies(3000), vec![9, 10, 1]),      // Disjoint with the previous one
+        ],
+    );
+
+    // 1 jiffy == 1ms
+    let mut sim = SimulationBuilder::default()
+        .add_pool::<Replica>(REPLICA_POOL_NAME, 10)
+        .add_pool::<Client>(CLIENT_POOL_NAME, 4)
+        .add_pool::<Reconfigurer>(RECONFIGURER_POOL_NAME, 1)
+        .time_budget(Jiffies(5000))
+        .latency_topology(&[
+            LatencyDescription::WithinPool(
+                REPLICA_POOL_NAME,
+                Distributions::Uniform(Jiffies(0), Jiffies(10)),
+            ),
+            LatencyDescription::WithinPool(
+                CLIENT_POOL_NAME,
+                Distributions::Uniform(Jiffies(0), Jiffies(545)),
+            ),
+            LatencyDescription::WithinPool(
+                RECONFIGURER_POOL_NAME,
+                Distributions::Uniform(Jiffies(0), Jiffies(0)),
+            ),
+            LatencyDescription::BetweenPools(
+                CLIENT_POOL_NAME,
+                REPLICA_POOL_NAME,
+                Distributions::Uniform(Jiffies(0), Jiffies(300)),
+            ),
+            LatencyDescription::BetweenPools(
+                RECONFIGURER_POOL_NAME,
+                REPLICA_POOL_NAME,
+                Distributions::Uniform(Jiffies(0), Jiffies(10)),
+            ),
+            LatencyDescription::BetweenPools(
+                RECONFIGURER_POOL_NAME,
+                CLIENT_POOL_NAME,
+                Distributions::Uniform(Jiffies(0), Jiffies(300)),
+            ),
+        ])
+        .seed(5444)
+        .build();
+
+    anykv::set::<SessionLog>("session_log", SessionLog::new());
+
+    sim.run();
+
+    let history = build_history(&anykv::get::<SessionLog>("session_log"));
+    let epoch_changes = anykv::get::<Vec<(Jiffies, usize)>>("abd_epoch_changes");
+
+    for (at, epoch) in epoch_changes.iter() {
+        let spanning = history
+            .iter()
+            .filter(|op| op.start <= *at && *at <= op.end)
+            .count();
+        println!("Epoch {epoch} activated at {at}, operations spanning the change: {spanning}");
+    }
+
+    assert_eq!(epoch_changes.len(), 2, "Not all reconfigurations finished");
+    assert!(check_linearizable(&history));
+}
@@ -1,8 +1,9 @@
 use dscale::{global::anykv, *};
 use kv::abd_store::{
     Replica,
-    client::{Client, ExecutionHistory},
+    client::Client,
     lin_checker::check_linearizable,
+    session::{SessionLog, build_history},
     types::{CLIENT_POOL_NAME, REPLICA_POOL_NAME},
 };
 
@@ -30,7 +31,7 @@ fn main() {
         .seed(5444)
         .build();
 
-    anykv::set::<ExecutionHistory>("linearizable_history", ExecutionHistory::new());
+    anykv::set::<SessionLog>("session_log", SessionLog::new());
 
     sim.run();
 
@@ -40,13 +41,14 @@ fn main() {
     );
     println!("{}", "-".repeat(75));
 
-    let history = anykv::get::<ExecutionHistory>("linearizable_history");
+    let history = build_history(&anykv::get::<SessionLog>("session_log"));
 
-    for el in anykv::get::<ExecutionHistory>("linearizable_history") {
-        let result = el
-            .result
-            .map(|v| v.to_string())
-            .unwrap_or_else(|| "Ack".to_string());
+    for el in history.iter() {
+        let result = match (el.result, el.ambiguous) {
+            (Some(v), _) => v.to_string(),
+            (None, false) => "Ack".to_string(),
+            (None, true) => "?".to_string(),
+        };
         println!(
             "{:<8} | {:<12} | {:<8} | {:<12} | {:<12}",
             el.client, el.operation, result, el.start, el.end
@@ -0,0 +1,48 @@
+use dscale::{global::anykv, *};
+use kv::abd_store::{
+    Replica,
+    client::Client,
+    lin_checker::check_linearizable,
+    session::{SessionLog, build_history},
+    types::{CLIENT_POOL_NAME, REPLICA_POOL_NAME},
+};
+
+fn main() {
+    // Timeout is shorter than the slowest round trip, so some attempts are ambiguous
+    anykv::set::<Jiffies>("client_timeout", Jiffies(1500));
+    anykv::set::<usize>("client_max_attempts", 3);
+
+    // 1 jiffy == 1ms
+    let mut sim = SimulationBuilder::default()
+        .add_pool::<Replica>(REPLICA_POOL_NAME, 10)
+        .add_pool::<Client>(CLIENT_POOL_NAME, 4)
+        .time_budget(Jiffies(10000))
+        .latency_topology(&[
+            LatencyDescription::WithinPool(
+                REPLICA_POOL_NAME,
+                Distributions::Uniform(Jiffies(0), Jiffies(10)),
+            ),
+            LatencyDescription::WithinPool(
+                CLIENT_POOL_NAME,
+                Distributions::Uniform(Jiffies(0), Jiffies(545)),
+            ),
+            LatencyDescription::BetweenPools(
+                CLIENT_POOL_NAME,
+                REPLICA_POOL_NAME,
+                Distributions::Uniform(Jiffies(0), Jiffies(1212)),
+            ),
+        ])
+        .seed(5444)
+        .build();
+
+    anykv::set::<SessionLog>("session_log", SessionLog::new());
+
+    sim.run();
+
+    let history = build_history(&anykv::get::<SessionLog>("session_log"));
+    let ambiguous = history.iter().filter(|op| op.ambiguous).count();
+    println!("Attempts: {}, ambiguous: {}", history.len(), ambiguous);
+
+    assert!(ambiguous > 0, "No attempt timed out");
+    assert!(check_linearizable(&history));
+}
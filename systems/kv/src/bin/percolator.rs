@@ -0,0 +1,89 @@
+use dscale::{
+    global::anykv,
+    scenario::{crash, restart},
+    *,
+};
+use kv::percolator::{
+    PercolatorStats,
+    client::TxnClient,
+    history::{Outcome, TxnLog, build_history},
+    oracle::{Oracle, TsoMetrics},
+    si_checker::check_snapshot_isolation,
+    storage::Shard,
+    types::{ORACLE_POOL_NAME, SHARD_POOL_NAME, TXN_CLIENT_POOL_NAME},
+};
+
+// Shards 1..=3, oracle 4, clients 5..=10
+fn main() {
+    anykv::set::<usize>("percolator_keys", 8);
+
+    // A shard loses its memory and recovers from WAL, clients crash in the middle
+    // of transactions and leave their locks behind
+    let scenario = Scenario::new()
+        .at(Jiffies(3000), crash(2))
+        .at(Jiffies(3500), restart(2))
+        .at(Jiffies(5000), crash(5))
+        .at(Jiffies(5200), crash(6))
+        .at(Jiffies(7000), restart(5));
+
+    // 1 jiffy == 1ms
+    let mut sim = SimulationBuilder::default()
+        .add_pool::<Shard>(SHARD_POOL_NAME, 3)
+        .add_pool::<Oracle>(ORACLE_POOL_NAME, 1)
+        .add_pool::<TxnClient>(TXN_CLIENT_POOL_NAME, 6)
+        .time_budget(Jiffies(10000))
+        .latency_topology(&[
+            LatencyDescription::BetweenPools(
+                TXN_CLIENT_POOL_NAME,
+                SHARD_POOL_NAME,
+                Distributions::Uniform(Jiffies(5), Jiffies(15)),
+            ),
+            LatencyDescription::BetweenPools(
+                TXN_CLIENT_POOL_NAME,
+                ORACLE_POOL_NAME,
+                Distributions::Uniform(Jiffies(1), Jiffies(5)),
+            ),
+        ])
+        .disk(DiskDescription {
+            fsync_latency: Jiffies(2),
+            throughput: Some(1000),
+            ..Default::default()
+        })
+        .scenario(scenario)
+        .seed(5444)
+        .build();
+
+    anykv::set::<TxnLog>("txn_log", TxnLog::new());
+
+    sim.run();
+
+    let history = build_history(&anykv::get::<TxnLog>("txn_log"));
+    let count = |outcome: Outcome| {
+        history
+            .iter()
+            .filter(|transaction| transaction.outcome == outcome)
+            .count()
+    };
+    let stats = PercolatorStats::get();
+    println!(
+        "Transactions: {} committed, {} aborted, {} unknown",
+        count(Outcome::Committed),
+        count(Outcome::Aborted),
+        count(Outcome::Unknown)
+    );
+    println!(
+        "Lazy cleanup: {} locks rolled forward, {} rolled back. Recovered {} mutations from WAL",
+        stats.rolled_forward, stats.rolled_back, stats.recovered_mutations
+    );
+
+    let tso = TsoMetrics::get();
+    println!(
+        "Timestamps: {} allocated, mean allocation latency {:.1}, p99 {}",
+        tso.timestamps,
+        tso.mean_allocation(),
+        tso.allocation_percentile(99.0).0
+    );
+
+    assert!(count(Outcome::Committed) > 0);
+    assert!(check_snapshot_isolation(&history));
+}
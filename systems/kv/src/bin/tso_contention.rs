@@ -0,0 +1,97 @@
+use dscale::{global::anykv, *};
+use kv::percolator::{
+    oracle::{Oracle, TimestampLoad, TsoMetrics},
+    types::ORACLE_POOL_NAME,
+};
+
+const LOAD_POOL_NAME: &str = "Load";
+const TIME_BUDGET: Jiffies = Jiffies(100_000);
+
+struct Batching {
+    name: &'static str,
+    round_limit: usize,
+    range: usize,
+}
+
+const BATCHING: [Batching; 3] = [
+    Batching {
+        name: "no batching",
+        round_limit: 1,
+        range: 1,
+    },
+    Batching {
+        name: "batched rounds",
+        round_limit: usize::MAX,
+        range: 1,
+    },
+    Batching {
+        name: "ranges of 8",
+        round_limit: usize::MAX,
+        range: 8,
+    },
+];
+
+// Oracle throughput and allocation latency under growing closed-loop load
+fn main() {
+    println!(
+        "{:<16} | {:>8} | {:>12} | {:>8} | {:>8} | {:>8} | {:>8}",
+        "BATCHING", "CLIENTS", "TIMESTAMPS/S", "ROUNDS", "PERSISTS", "MEAN MS", "P99 MS"
+    );
+    println!("{}", "-".repeat(86));
+
+    let mut saturated = Vec::new();
+    for batching in &BATCHING {
+        for clients in [4, 16, 64] {
+            let metrics = run(batching, clients);
+            let throughput = metrics.timestamps as f64 / (TIME_BUDGET.0 as f64 / 100_000.0);
+            println!(
+                "{:<16} | {:>8} | {:>12.0} | {:>8} | {:>8} | {:>8.1} | {:>8.1}",
+                batching.name,
+                clients,
+                throughput,
+                metrics.rounds,
+                metrics.persists,
+                metrics.mean_allocation() / 100.0,
+                metrics.allocation_percentile(99.0).0 as f64 / 100.0
+            );
+            if clients == 64 {
+                saturated.push(throughput);
+            }
+        }
+    }
+
+    assert!(
+        saturated[0] < saturated[1] && saturated[1] < saturated[2],
+        "Batching should raise throughput of a saturated oracle"
+    );
+}
+
+fn run(batching: &Batching, clients: usize) -> TsoMetrics {
+    anykv::set::<Jiffies>("tso_round_cost", Jiffies(5));
+    anykv::set::<Jiffies>("tso_request_cost", Jiffies(1));
+    anykv::set::<usize>("tso_round_limit", batching.round_limit);
+    anykv::set::<usize>("tso_reservation", 10_000);
+    anykv::set::<usize>("tso_range", batching.range);
+
+    // 1 jiffy == 10us
+    let mut sim = SimulationBuilder::default()
+        .add_pool::<Oracle>(ORACLE_POOL_NAME, 1)
+        .add_pool::<TimestampLoad>(LOAD_POOL_NAME, clients)
+        .time_budget(TIME_BUDGET)
+        .latency_topology(&[LatencyDescription::BetweenPools(
+            ORACLE_POOL_NAME,
+            LOAD_POOL_NAME,
+            Distributions::Uniform(Jiffies(20), Jiffies(30)),
+        )])
+        .disk(DiskDescription {
+            fsync_latency: Jiffies(100),
+            throughput: None,
+            ..Default::default()
+        })
+        .seed(5444)
+        .build();
+
+    sim.run();
+
+    TsoMetrics::get()
+}
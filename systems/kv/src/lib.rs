@@ -1,3 +1,4 @@
 #![allow(non_snake_case)]
 
 pub mod abd_store;
+pub mod percolator;
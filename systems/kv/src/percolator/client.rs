@@ -0,0 +1,388 @@
+// Read-modify-write transactions over a few random keys. Requests are retried on
+// timeout until the shard answers: every storage request is idempotent, so a
+// shard may well have executed the lost attempt already.
+
+use std::collections::{BTreeMap, HashMap};
+
+use dscale::{
+    global::{anykv, configuration},
+    workload::{KeyDistribution, KeySampler},
+    *,
+};
+
+use crate::percolator::{
+    PercolatorStats,
+    history::{self, Outcome, Transaction, TxnEvent},
+    oracle::{TimestampRequest, TimestampResponse, TsoMetrics},
+    storage::{StorageReply, StorageRequest, StorageResponse, StorageRpc},
+    types::{Key, ORACLE_POOL_NAME, RpcId, Timestamp, Value, shard_of},
+};
+
+const THINK_TIME: Jiffies = Jiffies(50);
+const RPC_TIMEOUT: Jiffies = Jiffies(200);
+const LOCK_BACKOFF: Jiffies = Jiffies(50);
+const DEFAULT_KEYS: usize = 10;
+const KEYS_PER_TRANSACTION: usize = 2;
+
+#[derive(Clone, Copy)]
+enum Purpose {
+    StartTs,
+    Read(Key),
+    CheckPrimary(Key, Timestamp), // Locked key, lock start_ts
+    Resolve(Key),
+    Prewrite,
+    CommitTs,
+    CommitPrimary,
+}
+
+#[derive(Clone, Copy)]
+enum Request {
+    Timestamp,
+    Storage(Key, StorageRequest), // Routed to the shard of the key
+}
+
+struct Rpc {
+    request: Request,
+    purpose: Purpose,
+    timer: TimerId,
+    sent: Jiffies,
+}
+
+struct ActiveTransaction {
+    keys: Vec<Key>, // First one is the primary
+    start_ts: Timestamp,
+    reads: BTreeMap<Key, Value>,
+    writes: Vec<(Key, Value)>,
+    commit_ts: Option<Timestamp>,
+    pending: usize,
+}
+
+impl ActiveTransaction {
+    fn primary(&self) -> Key {
+        self.keys[0]
+    }
+
+    fn record(&self, commit_ts: Option<Timestamp>, outcome: Outcome) -> Transaction {
+        Transaction {
+            client: rank(),
+            start_ts: self.start_ts,
+            commit_ts,
+            reads: self
+                .reads
+                .iter()
+                .map(|(key, value)| (*key, *value))
+                .collect(),
+            writes: self.writes.clone(),
+            outcome,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct TxnClient {
+    keys: Option<KeySampler>, // anykv "percolator_keys" keys, "key_distribution" (uniform by default)
+    next_rpc: RpcId,
+    rpcs: HashMap<RpcId, Rpc>,
+    timeouts: HashMap<TimerId, RpcId>,
+    backoffs: HashMap<TimerId, Key>, // Reads waiting for a live lock to go away
+    transaction: Option<ActiveTransaction>,
+    think_timer: Option<TimerId>,
+}
+
+impl ProcessHandle for TxnClient {
+    fn start(&mut self) {
+        PercolatorStats::register();
+        let keys = anykv::try_get::<usize>("percolator_keys").unwrap_or(DEFAULT_KEYS);
+        let distribution = anykv::try_get::<KeyDistribution>("key_distribution")
+            .unwrap_or(KeyDistribution::Uniform);
+        // Restarted client draws a different sequence of keys
+        self.keys = Some(KeySampler::seeded(
+            keys,
+            distribution,
+            configuration::seed() ^ now().0 as u64,
+        ));
+        self.think_timer = Some(schedule_timer_after(THINK_TIME));
+    }
+
+    fn on_message(&mut self, from: ProcessId, message: MessagePtr) {
+        if let Some(response) = message.try_as::<TimestampResponse>() {
+            if let Some(rpc) = self.rpcs.get(&response.0) {
+                TsoMetrics::record_allocation(now() - rpc.sent);
+            }
+            match self.complete_rpc(response.0) {
+                Some(Purpose::StartTs) => self.on_start_ts(response.1),
+                Some(Purpose::CommitTs) => self.on_commit_ts(response.1),
+                Some(_) => protocol_error!("Timestamp response does not match the request"),
+                None => {}
+            }
+            return;
+        }
+
+        let Some(reply) = message.try_as::<StorageReply>() else {
+            protocol_error!("Unexpected message from P{from}");
+            return;
+        };
+        match self.complete_rpc(reply.0) {
+            Some(Purpose::Read(key)) => self.on_read(key, reply.1),
+            Some(Purpose::CheckPrimary(key, lock_ts)) => {
+                self.on_primary_checked(key, lock_ts, reply.1)
+            }
+            Some(Purpose::Resolve(key)) => self.read(key),
+            Some(Purpose::Prewrite) => self.on_prewritten(reply.1),
+            Some(Purpose::CommitPrimary) => self.on_primary_committed(reply.1),
+            Some(_) => protocol_error!("Storage reply does not match the request"),
+            None => {}
+        }
+    }
+
+    fn on_timer(&mut self, id: TimerId) {
+        if self.think_timer == Some(id) {
+            self.think_timer = None;
+            self.begin();
+            return;
+        }
+        if let Some(key) = self.backoffs.remove(&id) {
+            self.read(key);
+            return;
+        }
+        if let Some(rpc) = self.timeouts.remove(&id) {
+            let rpc = self.rpcs.remove(&rpc).expect("Timer of a pending request");
+            debug_process!("Request timed out, retrying");
+            self.call(rpc.request, rpc.purpose);
+        }
+    }
+}
+
+// Requests
+impl TxnClient {
+    fn call(&mut self, request: Request, purpose: Purpose) {
+        self.next_rpc += 1;
+        let id = self.next_rpc;
+        match request {
+            Request::Timestamp => send_to(
+                pool_member(ORACLE_POOL_NAME, 0).expect("No oracle"),
+                TimestampRequest(id, 1),
+            ),
+            Request::Storage(key, request) => send_to(shard_of(key), StorageRpc(id, request)),
+        }
+        let timer = schedule_timer_after(RPC_TIMEOUT);
+        self.timeouts.insert(timer, id);
+        self.rpcs.insert(
+            id,
+            Rpc {
+                request,
+                purpose,
+                timer,
+                sent: now(),
+            },
+        );
+    }
+
+    // None for answers to retried or abandoned requests
+    fn complete_rpc(&mut self, id: RpcId) -> Option<Purpose> {
+        let rpc = self.rpcs.remove(&id)?;
+        self.timeouts.remove(&rpc.timer);
+        Some(rpc.purpose)
+    }
+
+    // Best effort: lost requests are covered by lazy cleanup
+    fn send(&self, key: Key, request: StorageRequest) {
+        send_to(shard_of(key), StorageRpc(0, request));
+    }
+
+    fn abandon_requests(&mut self) {
+        self.rpcs.clear();
+        self.timeouts.clear();
+        self.backoffs.clear();
+    }
+
+    fn transaction(&mut self) -> &mut ActiveTransaction {
+        self.transaction.as_mut().expect("No active transaction")
+    }
+}
+
+// Transaction steps
+impl TxnClient {
+    fn begin(&mut self) {
+        let sampler = self.keys.as_mut().expect("Not started");
+        let mut keys = Vec::new();
+        while keys.len() < KEYS_PER_TRANSACTION.min(sampler.keys()) {
+            let key = sampler.sample();
+            if !keys.contains(&key) {
+                keys.push(key);
+            }
+        }
+        self.transaction = Some(ActiveTransaction {
+            keys,
+            start_ts: 0,
+            reads: BTreeMap::new(),
+            writes: Vec::new(),
+            commit_ts: None,
+            pending: 0,
+        });
+        self.call(Request::Timestamp, Purpose::StartTs);
+    }
+
+    fn on_start_ts(&mut self, start_ts: Timestamp) {
+        let transaction = self.transaction();
+        transaction.start_ts = start_ts;
+        transaction.pending = transaction.keys.len();
+        let keys = transaction.keys.clone();
+        debug_process!("Transaction {start_ts} reads {keys:?}");
+        keys.into_iter().for_each(|key| self.read(key));
+    }
+
+    fn read(&mut self, key: Key) {
+        let start_ts = self.transaction().start_ts;
+        self.call(
+            Request::Storage(key, StorageRequest::Get(key, start_ts)),
+            Purpose::Read(key),
+        );
+    }
+
+    fn on_read(&mut self, key: Key, response: StorageResponse) {
+        match response {
+            StorageResponse::Value(value) => {
+                let transaction = self.transaction();
+                transaction.reads.insert(key, value.unwrap_or(0));
+                transaction.pending -= 1;
+                if transaction.pending == 0 {
+                    self.prewrite();
+                }
+            }
+            StorageResponse::Locked(lock_ts, primary, expired) => {
+                debug_process!("{key} is locked by {lock_ts}, checking primary {primary}");
+                self.call(
+                    Request::Storage(
+                        primary,
+                        StorageRequest::CheckPrimary(primary, lock_ts, expired),
+                    ),
+                    Purpose::CheckPrimary(key, lock_ts),
+                );
+            }
+            _ => unreachable!("Not a response to Get"),
+        }
+    }
+
+    fn on_primary_checked(&mut self, key: Key, lock_ts: Timestamp, response: StorageResponse) {
+        let commit_ts = match response {
+            StorageResponse::PrimaryLocked => {
+                let timer = schedule_timer_after(LOCK_BACKOFF);
+                self.backoffs.insert(timer, key);
+                return;
+            }
+            StorageResponse::PrimaryCommitted(commit_ts) => {
+                PercolatorStats::modify(|stats| stats.rolled_forward += 1);
+                Some(commit_ts)
+            }
+            StorageResponse::PrimaryRolledBack => {
+                PercolatorStats::modify(|stats| stats.rolled_back += 1);
+                None
+            }
+            _ => unreachable!("Not a response to CheckPrimary"),
+        };
+        debug_process!("Resolving lock {lock_ts} of {key}, committed at {commit_ts:?}");
+        self.call(
+            Request::Storage(key, StorageRequest::ResolveLock(key, lock_ts, commit_ts)),
+            Purpose::Resolve(key),
+        );
+    }
+
+    fn prewrite(&mut self) {
+        let transaction = self.transaction();
+        let (start_ts, primary) = (transaction.start_ts, transaction.primary());
+        transaction.writes = transaction
+            .keys
+            .iter()
+            .map(|key| (*key, global_unique_id() + 1)) // 0 is the initial value
+            .collect();
+        transaction.pending = transaction.writes.len();
+        let writes = transaction.writes.clone();
+        writes.into_iter().for_each(|(key, value)| {
+            self.call(
+                Request::Storage(key, StorageRequest::Prewrite(key, value, start_ts, primary)),
+                Purpose::Prewrite,
+            )
+        });
+    }
+
+    fn on_prewritten(&mut self, response: StorageResponse) {
+        match response {
+            StorageResponse::Ok => {
+                let transaction = self.transaction();
+                transaction.pending -= 1;
+                if transaction.pending == 0 {
+                    self.call(Request::Timestamp, Purpose::CommitTs);
+                }
+            }
+            StorageResponse::Conflict => {
+                let transaction = self.transaction.take().expect("No active transaction");
+                debug_process!("Transaction {} aborted on conflict", transaction.start_ts);
+                history::append(TxnEvent::Finished(
+                    transaction.record(None, Outcome::Aborted),
+                ));
+                self.roll_back(&transaction);
+                self.finish();
+            }
+            _ => unreachable!("Not a response to Prewrite"),
+        }
+    }
+
+    fn on_commit_ts(&mut self, commit_ts: Timestamp) {
+        let transaction = self.transaction();
+        let (start_ts, primary) = (transaction.start_ts, transaction.primary());
+        // Recorded before the commit point: the client may crash right after it
+        history::append(TxnEvent::Finished(
+            transaction.record(Some(commit_ts), Outcome::Unknown),
+        ));
+        self.call(
+            Request::Storage(
+                primary,
+                StorageRequest::Commit(primary, start_ts, commit_ts),
+            ),
+            Purpose::CommitPrimary,
+        );
+        self.transaction().commit_ts = Some(commit_ts);
+    }
+
+    fn on_primary_committed(&mut self, response: StorageResponse) {
+        let transaction = self.transaction.take().expect("No active transaction");
+        match response {
+            StorageResponse::Ok => {
+                debug_process!("Transaction {} committed", transaction.start_ts);
+                history::append(TxnEvent::Decided(transaction.start_ts, Outcome::Committed));
+                let commit_ts = transaction.commit_ts.expect("Asked before the commit");
+                transaction.keys[1..].iter().for_each(|key| {
+                    self.send(
+                        *key,
+                        StorageRequest::Commit(*key, transaction.start_ts, commit_ts),
+                    )
+                });
+            }
+            StorageResponse::Conflict => {
+                debug_process!(
+                    "Transaction {} was rolled back by a reader",
+                    transaction.start_ts
+                );
+                history::append(TxnEvent::Decided(transaction.start_ts, Outcome::Aborted));
+                self.roll_back(&transaction);
+            }
+            _ => unreachable!("Not a response to Commit"),
+        }
+        self.finish();
+    }
+
+    fn roll_back(&self, transaction: &ActiveTransaction) {
+        transaction.keys.iter().for_each(|key| {
+            self.send(
+                *key,
+                StorageRequest::ResolveLock(*key, transaction.start_ts, None),
+            )
+        });
+    }
+
+    fn finish(&mut self) {
+        self.abandon_requests();
+        self.think_timer = Some(schedule_timer_after(THINK_TIME));
+    }
+}
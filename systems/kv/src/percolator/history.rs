@@ -0,0 +1,59 @@
+// Like session log of abd_store: clients only append events of their own
+// transactions, history is assembled from the whole log afterwards.
+
+use std::collections::BTreeMap;
+
+use dscale::{ProcessId, global::anykv};
+
+use crate::percolator::types::{Key, Timestamp, Value};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Committed,
+    Aborted,
+    Unknown, // Client crashed or gave up while committing the primary
+}
+
+#[derive(Clone, Debug)]
+pub struct Transaction {
+    pub client: ProcessId,
+    pub start_ts: Timestamp,
+    pub commit_ts: Option<Timestamp>, // None if aborted before asking for it
+    pub reads: Vec<(Key, Value)>,     // 0 is the initial value of every key
+    pub writes: Vec<(Key, Value)>,
+    pub outcome: Outcome,
+}
+
+#[derive(Clone, Debug)]
+pub enum TxnEvent {
+    Finished(Transaction),       // Outcome may still be unknown
+    Decided(Timestamp, Outcome), // start_ts
+}
+
+// Stored under "txn_log" in anykv
+pub type TxnLog = Vec<TxnEvent>;
+pub type TxnHistory = Vec<Transaction>;
+
+pub(crate) fn append(event: TxnEvent) {
+    if anykv::try_get::<TxnLog>("txn_log").is_none() {
+        anykv::set::<TxnLog>("txn_log", TxnLog::new());
+    }
+    anykv::modify::<TxnLog>("txn_log", |log| log.push(event));
+}
+
+// Transactions ordered by start_ts
+pub fn build_history(log: &TxnLog) -> TxnHistory {
+    let mut transactions: BTreeMap<Timestamp, Transaction> = BTreeMap::new();
+    log.iter().for_each(|event| match event {
+        TxnEvent::Finished(transaction) => {
+            transactions.insert(transaction.start_ts, transaction.clone());
+        }
+        TxnEvent::Decided(start_ts, outcome) => {
+            transactions
+                .get_mut(start_ts)
+                .expect("Decided after finished")
+                .outcome = *outcome;
+        }
+    });
+    transactions.into_values().collect()
+}
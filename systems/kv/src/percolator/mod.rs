@@ -0,0 +1,44 @@
+// Percolator (Peng, Dabek. Large-scale Incremental Processing Using Distributed
+// Transactions and Notifications, OSDI 2010): snapshot isolation transactions on
+// top of single-row atomic storage.
+//
+//   1. Transaction reads at start_ts obtained from the timestamp oracle
+//   2. Prewrite: every written row gets data at start_ts and a lock pointing to the primary row
+//   3. Commit: commit_ts from the oracle, the primary lock is replaced with a write
+//      record (commit point), then secondaries are committed asynchronously
+//
+// Clients may crash at any step, so locks are cleaned up lazily: a reader stuck on
+// an expired lock checks the primary and rolls the transaction forward or back.
+
+pub mod client;
+pub mod history;
+pub mod oracle;
+pub mod si_checker;
+pub mod storage;
+pub mod types;
+
+use dscale::global::anykv;
+
+// Stored under "percolator_stats" in anykv
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PercolatorStats {
+    pub recovered_mutations: usize, // Replayed from WAL by restarted shards
+    pub rolled_forward: usize,      // Locks of committed transactions resolved by readers
+    pub rolled_back: usize,         // Locks of failed transactions resolved by readers
+}
+
+impl PercolatorStats {
+    pub fn get() -> Self {
+        anykv::try_get::<PercolatorStats>("percolator_stats").unwrap_or_default()
+    }
+
+    pub(crate) fn register() {
+        if anykv::try_get::<PercolatorStats>("percolator_stats").is_none() {
+            anykv::set::<PercolatorStats>("percolator_stats", PercolatorStats::default());
+        }
+    }
+
+    pub(crate) fn modify(f: impl FnOnce(&mut PercolatorStats)) {
+        anykv::modify::<PercolatorStats>("percolator_stats", f);
+    }
+}
@@ -0,0 +1,246 @@
+// Timestamp oracle. Requests queue up and are served in rounds, one round at a
+// time: the oracle spends CPU on the round and its requests, grants every request
+// a range of consecutive timestamps, and answers once the granted timestamps are
+// covered by a persisted reservation. Reservations are extended in windows, so
+// most rounds do not touch the disk, and a restarted oracle resumes above
+// everything it could have granted.
+//
+// Configured through anykv:
+//   "tso_round_cost", "tso_request_cost": nominal CPU work per round and per request (0 by default)
+//   "tso_round_limit": requests served per round (unlimited by default, 1 disables batching)
+//   "tso_reservation": timestamps reserved per persist (1000 by default)
+
+use std::collections::VecDeque;
+
+use dscale::{
+    global::{anykv, configuration, wal},
+    *,
+};
+
+use crate::percolator::types::{ORACLE_POOL_NAME, RpcId, Timestamp};
+
+const DEFAULT_RESERVATION: usize = 1000;
+
+pub(crate) struct TimestampRequest(pub(crate) RpcId, pub(crate) usize); // Range size
+pub(crate) struct TimestampResponse(pub(crate) RpcId, pub(crate) Timestamp); // First of the range
+
+// WAL entry: timestamps below are possibly granted
+struct Reservation(Timestamp);
+
+impl_virtual_size!(struct TimestampRequest { 0, 1 });
+impl_virtual_size!(struct TimestampResponse { 0, 1 });
+impl_virtual_size!(struct Reservation { 0 });
+
+impl Message for TimestampRequest {
+    fn virtual_size(&self) -> usize {
+        helpers::virtual_size_of(self)
+    }
+}
+impl Message for TimestampResponse {
+    fn virtual_size(&self) -> usize {
+        helpers::virtual_size_of(self)
+    }
+}
+impl Message for Reservation {
+    fn virtual_size(&self) -> usize {
+        helpers::virtual_size_of(self)
+    }
+}
+
+// Stored under "tso_metrics" in anykv
+#[derive(Clone, Debug, Default)]
+pub struct TsoMetrics {
+    pub rounds: usize,
+    pub persists: usize,
+    pub timestamps: usize,
+    pub allocations: Vec<Jiffies>, // Latency of every request, as seen by the requester
+}
+
+impl TsoMetrics {
+    pub fn get() -> Self {
+        anykv::try_get::<TsoMetrics>("tso_metrics").unwrap_or_default()
+    }
+
+    fn modify(f: impl FnOnce(&mut TsoMetrics)) {
+        if anykv::try_get::<TsoMetrics>("tso_metrics").is_none() {
+            anykv::set::<TsoMetrics>("tso_metrics", TsoMetrics::default());
+        }
+        anykv::modify::<TsoMetrics>("tso_metrics", f);
+    }
+
+    pub(crate) fn record_allocation(latency: Jiffies) {
+        Self::modify(|metrics| metrics.allocations.push(latency));
+    }
+
+    pub fn mean_allocation(&self) -> f64 {
+        let total: usize = self.allocations.iter().map(|latency| latency.0).sum();
+        total as f64 / self.allocations.len().max(1) as f64
+    }
+
+    // Nearest rank, zero without allocations
+    pub fn allocation_percentile(&self, p: f64) -> Jiffies {
+        let mut sorted = self.allocations.clone();
+        sorted.sort();
+        let rank = ((p / 100.0 * sorted.len() as f64).ceil() as usize).max(1);
+        sorted.get(rank - 1).copied().unwrap_or_default()
+    }
+}
+
+struct Grant {
+    to: ProcessId,
+    request: RpcId,
+    first: Timestamp,
+}
+
+enum Phase {
+    Idle,
+    Computing(TimerId, Vec<(ProcessId, TimestampRequest)>),
+    Persisting(TimerId, Vec<Grant>),
+}
+
+pub struct Oracle {
+    next: Timestamp,
+    reserved: Timestamp,
+    queue: VecDeque<(ProcessId, TimestampRequest)>,
+    phase: Phase,
+    round_cost: Jiffies,
+    request_cost: Jiffies,
+    round_limit: usize,
+    reservation: usize,
+}
+
+impl Default for Oracle {
+    fn default() -> Self {
+        Self {
+            next: 1,
+            reserved: 1,
+            queue: VecDeque::new(),
+            phase: Phase::Idle,
+            round_cost: Jiffies(0),
+            request_cost: Jiffies(0),
+            round_limit: usize::MAX,
+            reservation: DEFAULT_RESERVATION,
+        }
+    }
+}
+
+impl ProcessHandle for Oracle {
+    fn start(&mut self) {
+        if let Some(last) = wal::entries().pop() {
+            self.reserved = last.as_type::<Reservation>().0;
+            self.next = self.reserved;
+            debug_process!("Recovered, granting from {}", self.next);
+        }
+        self.round_cost = anykv::try_get::<Jiffies>("tso_round_cost").unwrap_or_default();
+        self.request_cost = anykv::try_get::<Jiffies>("tso_request_cost").unwrap_or_default();
+        if let Some(limit) = anykv::try_get::<usize>("tso_round_limit") {
+            assert!(limit > 0, "Round should serve at least one request");
+            self.round_limit = limit;
+        }
+        if let Some(reservation) = anykv::try_get::<usize>("tso_reservation") {
+            self.reservation = reservation;
+        }
+    }
+
+    fn on_message(&mut self, from: ProcessId, message: MessagePtr) {
+        let Some(request) = message.try_as::<TimestampRequest>() else {
+            protocol_error!("Unexpected message from P{from}");
+            return;
+        };
+        self.queue
+            .push_back((from, TimestampRequest(request.0, request.1)));
+        self.next_round();
+    }
+
+    fn on_timer(&mut self, id: TimerId) {
+        match std::mem::replace(&mut self.phase, Phase::Idle) {
+            Phase::Computing(timer, requests) if timer == id => self.grant(requests),
+            Phase::Persisting(timer, grants) if timer == id => {
+                grants.into_iter().for_each(|grant| {
+                    send_to(grant.to, TimestampResponse(grant.request, grant.first))
+                });
+                self.next_round();
+            }
+            phase => self.phase = phase,
+        }
+    }
+}
+
+impl Oracle {
+    fn next_round(&mut self) {
+        if !matches!(self.phase, Phase::Idle) || self.queue.is_empty() {
+            return;
+        }
+        let size = self.round_limit.min(self.queue.len());
+        let requests: Vec<_> = self.queue.drain(..size).collect();
+        let cost = configuration::cpu_time(Jiffies(
+            self.round_cost.0 + self.request_cost.0 * requests.len(),
+        ));
+        self.phase = Phase::Computing(schedule_timer_after(cost), requests);
+    }
+
+    fn grant(&mut self, requests: Vec<(ProcessId, TimestampRequest)>) {
+        let granted: usize = requests.iter().map(|(_, request)| request.1).sum();
+        let grants: Vec<Grant> = requests
+            .into_iter()
+            .map(|(to, request)| {
+                let first = self.next;
+                self.next += request.1;
+                Grant {
+                    to,
+                    request: request.0,
+                    first,
+                }
+            })
+            .collect();
+
+        let persist = self.next > self.reserved;
+        if persist {
+            self.reserved = self.next + self.reservation;
+            wal::append(Reservation(self.reserved));
+        }
+        TsoMetrics::modify(|metrics| {
+            metrics.rounds += 1;
+            metrics.persists += persist as usize;
+            metrics.timestamps += granted;
+        });
+        // Answers never get ahead of the reservation covering them
+        let timer = match persist {
+            true => wal::sync(),
+            false => schedule_timer_after(Jiffies(0)),
+        };
+        self.phase = Phase::Persisting(timer, grants);
+    }
+}
+
+// Closed-loop load: keeps one request for "tso_range" timestamps (1 by default)
+// in flight, e.g. a proxy batching requests of many transactions
+#[derive(Default)]
+pub struct TimestampLoad {
+    range: usize,
+    sent: Jiffies,
+}
+
+impl TimestampLoad {
+    fn request(&mut self) {
+        self.sent = now();
+        send_to(
+            pool_member(ORACLE_POOL_NAME, 0).expect("No oracle"),
+            TimestampRequest(0, self.range),
+        );
+    }
+}
+
+impl ProcessHandle for TimestampLoad {
+    fn start(&mut self) {
+        self.range = anykv::try_get::<usize>("tso_range").unwrap_or(1);
+        self.request();
+    }
+
+    fn on_message(&mut self, _from: ProcessId, _message: MessagePtr) {
+        TsoMetrics::record_allocation(now() - self.sent);
+        self.request();
+    }
+
+    fn on_timer(&mut self, _id: TimerId) {}
+}
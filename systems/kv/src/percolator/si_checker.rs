@@ -0,0 +1,105 @@
+// Snapshot isolation checker for timestamped histories. Percolator exposes the
+// snapshot (start_ts) and the commit point (commit_ts) of every transaction, so
+// instead of searching for them the checker verifies the history against them:
+//   - Every read returns the latest committed write before start_ts
+//   - Committed transactions writing the same key do not overlap (first committer wins)
+// Transactions with unknown outcome may be committed or not, observing their
+// writes proves they were.
+
+use std::collections::HashMap;
+
+use crate::percolator::{
+    history::{Outcome, Transaction, TxnHistory},
+    types::{Key, Timestamp, Value},
+};
+
+pub fn check_snapshot_isolation(history: &TxnHistory) -> bool {
+    let mut writers: HashMap<(Key, Value), &Transaction> = HashMap::new();
+    history.iter().for_each(|transaction| {
+        transaction.writes.iter().for_each(|write| {
+            writers.insert(*write, transaction);
+        });
+    });
+
+    // Writers of unknown outcome whose values were observed
+    let observed: Vec<Timestamp> = history
+        .iter()
+        .flat_map(|reader| reader.reads.iter())
+        .filter_map(|read| writers.get(read))
+        .filter(|writer| writer.outcome == Outcome::Unknown)
+        .map(|writer| writer.start_ts)
+        .collect();
+    let committed: Vec<&Transaction> = history
+        .iter()
+        .filter(|transaction| {
+            transaction.outcome == Outcome::Committed || observed.contains(&transaction.start_ts)
+        })
+        .collect();
+
+    for reader in history {
+        for (key, value) in &reader.reads {
+            // Value is unique to the writer, unless it is the initial one
+            let writer = match *value {
+                0 => None,
+                value => match writers.get(&(*key, value)) {
+                    Some(writer) => Some(*writer),
+                    None => {
+                        println!("SI violation: {key}={value} was never written");
+                        return false;
+                    }
+                },
+            };
+            let Some(visible_from) = visible_from(writer, reader.start_ts) else {
+                println!(
+                    "SI violation: transaction {} read uncommitted {key}={value}",
+                    reader.start_ts
+                );
+                return false;
+            };
+            let overwritten = committed.iter().any(|other| {
+                other.writes.iter().any(|(written, _)| written == key)
+                    && other.commit_ts.is_some_and(|commit_ts| {
+                        visible_from < commit_ts && commit_ts < reader.start_ts
+                    })
+            });
+            if overwritten {
+                println!(
+                    "SI violation: transaction {} read stale {key}={value}",
+                    reader.start_ts
+                );
+                return false;
+            }
+        }
+    }
+
+    for (index, first) in committed.iter().enumerate() {
+        for second in &committed[index + 1..] {
+            let overlap = second.start_ts < first.commit_ts.expect("Committed");
+            let conflict = first
+                .writes
+                .iter()
+                .any(|(key, _)| second.writes.iter().any(|(other, _)| other == key));
+            if overlap && conflict {
+                println!(
+                    "SI violation: concurrent transactions {} and {} both committed a write to the same key",
+                    first.start_ts, second.start_ts
+                );
+                return false;
+            }
+        }
+    }
+
+    println!("Checker: History satisfies snapshot isolation!");
+    true
+}
+
+// Commit timestamp of the observed write, None if it can not be visible at start_ts
+fn visible_from(writer: Option<&Transaction>, start_ts: Timestamp) -> Option<Timestamp> {
+    let Some(writer) = writer else {
+        return Some(0);
+    };
+    if writer.outcome == Outcome::Aborted {
+        return None;
+    }
+    writer.commit_ts.filter(|commit_ts| *commit_ts < start_ts)
+}
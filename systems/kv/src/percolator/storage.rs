@@ -0,0 +1,319 @@
+// Bigtable-like storage shard: every row has data, lock and write columns, and
+// requests touching a single row execute atomically. Mutations are appended to the
+// WAL and requests are answered only once every preceding mutation is durable, so a
+// restarted shard never forgets what it acknowledged.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+use dscale::{global::wal, *};
+
+use crate::percolator::{
+    PercolatorStats,
+    types::{Key, RpcId, Timestamp, Value},
+};
+
+// Locks older than this are presumed to be left by a crashed client
+pub const LOCK_TTL: Jiffies = Jiffies(300);
+
+#[derive(Clone, Copy)]
+pub(crate) enum StorageRequest {
+    Get(Key, Timestamp),                            // key, start_ts
+    Prewrite(Key, Value, Timestamp, Key),           // key, value, start_ts, primary
+    Commit(Key, Timestamp, Timestamp),              // key, start_ts, commit_ts
+    CheckPrimary(Key, Timestamp, bool),             // primary, lock start_ts, roll back if locked
+    ResolveLock(Key, Timestamp, Option<Timestamp>), // key, lock start_ts, primary commit_ts
+}
+
+#[derive(Clone, Copy)]
+pub(crate) enum StorageResponse {
+    Value(Option<Value>),
+    Locked(Timestamp, Key, bool), // lock start_ts, primary, expired
+    Ok,
+    Conflict,
+    PrimaryCommitted(Timestamp),
+    PrimaryRolledBack,
+    PrimaryLocked,
+}
+
+pub(crate) struct StorageRpc(pub(crate) RpcId, pub(crate) StorageRequest);
+pub(crate) struct StorageReply(pub(crate) RpcId, pub(crate) StorageResponse);
+
+// WAL entries
+enum Mutation {
+    Prewrite(Key, Value, Timestamp, Key, Jiffies),
+    Commit(Key, Timestamp, Timestamp),
+    Rollback(Key, Timestamp),
+}
+
+impl_virtual_size!(
+    enum StorageRequest {
+        Get(key, start_ts),
+        Prewrite(key, value, start_ts, primary),
+        Commit(key, start_ts, commit_ts),
+        CheckPrimary(key, start_ts, rollback),
+        ResolveLock(key, start_ts, commit_ts),
+    }
+);
+impl_virtual_size!(
+    enum StorageResponse {
+        Value(value),
+        Locked(start_ts, primary, expired),
+        Ok,
+        Conflict,
+        PrimaryCommitted(commit_ts),
+        PrimaryRolledBack,
+        PrimaryLocked,
+    }
+);
+impl_virtual_size!(struct StorageRpc { 0, 1 });
+impl_virtual_size!(struct StorageReply { 0, 1 });
+impl_virtual_size!(
+    enum Mutation {
+        Prewrite(key, value, start_ts, primary, at),
+        Commit(key, start_ts, commit_ts),
+        Rollback(key, start_ts),
+    }
+);
+
+impl Message for StorageRpc {
+    fn virtual_size(&self) -> usize {
+        helpers::virtual_size_of(self)
+    }
+}
+impl Message for StorageReply {
+    fn virtual_size(&self) -> usize {
+        helpers::virtual_size_of(self)
+    }
+}
+impl Message for Mutation {
+    fn virtual_size(&self) -> usize {
+        helpers::virtual_size_of(self)
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Lock {
+    start_ts: Timestamp,
+    primary: Key,
+    at: Jiffies,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Write {
+    Put(Timestamp), // start_ts of the data
+    Rollback,       // Recorded at start_ts, rejects late prewrites
+}
+
+#[derive(Default)]
+struct Row {
+    data: BTreeMap<Timestamp, Value>,
+    lock: Option<Lock>,
+    writes: BTreeMap<Timestamp, Write>, // By commit_ts
+}
+
+impl Row {
+    fn locked_by(&self, start_ts: Timestamp) -> bool {
+        self.lock.is_some_and(|lock| lock.start_ts == start_ts)
+    }
+
+    fn commit_ts_of(&self, start_ts: Timestamp) -> Option<Timestamp> {
+        self.writes
+            .iter()
+            .find(|(_, write)| **write == Write::Put(start_ts))
+            .map(|(commit_ts, _)| *commit_ts)
+    }
+
+    fn rolled_back(&self, start_ts: Timestamp) -> bool {
+        self.writes.get(&start_ts) == Some(&Write::Rollback)
+    }
+}
+
+#[derive(Default)]
+pub struct Shard {
+    rows: HashMap<Key, Row>,
+    syncs: VecDeque<(TimerId, Vec<(ProcessId, StorageReply)>)>, // Replies wait for their sync
+}
+
+impl ProcessHandle for Shard {
+    fn start(&mut self) {
+        PercolatorStats::register();
+        let entries = wal::entries();
+        debug_process!("Recovering {} mutations", entries.len());
+        PercolatorStats::modify(|stats| stats.recovered_mutations += entries.len());
+        entries
+            .into_iter()
+            .for_each(|entry| self.apply(entry.as_type::<Mutation>().as_ref()));
+    }
+
+    fn on_message(&mut self, from: ProcessId, message: MessagePtr) {
+        let Some(rpc) = message.try_as::<StorageRpc>() else {
+            protocol_error!("Unexpected message from P{from}");
+            return;
+        };
+        let response = match rpc.1 {
+            StorageRequest::Get(key, start_ts) => self.get(key, start_ts),
+            StorageRequest::Prewrite(key, value, start_ts, primary) => {
+                self.prewrite(key, value, start_ts, primary)
+            }
+            StorageRequest::Commit(key, start_ts, commit_ts) => {
+                self.commit(key, start_ts, commit_ts)
+            }
+            StorageRequest::CheckPrimary(primary, start_ts, rollback) => {
+                self.check_primary(primary, start_ts, rollback)
+            }
+            StorageRequest::ResolveLock(key, start_ts, commit_ts) => {
+                self.resolve_lock(key, start_ts, commit_ts)
+            }
+        };
+        self.reply(from, StorageReply(rpc.0, response));
+    }
+
+    fn on_timer(&mut self, id: TimerId) {
+        // Disk serves flushes in order
+        while let Some((sync, replies)) = self.syncs.pop_front() {
+            replies
+                .into_iter()
+                .for_each(|(to, reply)| send_to(to, reply));
+            if sync == id {
+                break;
+            }
+        }
+    }
+}
+
+impl Shard {
+    fn row(&mut self, key: Key) -> &mut Row {
+        self.rows.entry(key).or_default()
+    }
+
+    fn get(&mut self, key: Key, start_ts: Timestamp) -> StorageResponse {
+        let row = self.row(key);
+        if let Some(lock) = row.lock.filter(|lock| lock.start_ts <= start_ts) {
+            // Lock may belong to a transaction committed before start_ts
+            let expired = now() - lock.at > LOCK_TTL;
+            return StorageResponse::Locked(lock.start_ts, lock.primary, expired);
+        }
+        let value = row
+            .writes
+            .range(..=start_ts)
+            .rev()
+            .find_map(|(_, write)| match write {
+                Write::Put(data_ts) => Some(row.data[data_ts]),
+                Write::Rollback => None,
+            });
+        StorageResponse::Value(value)
+    }
+
+    fn prewrite(
+        &mut self,
+        key: Key,
+        value: Value,
+        start_ts: Timestamp,
+        primary: Key,
+    ) -> StorageResponse {
+        let row = self.row(key);
+        if let Some(lock) = row.lock {
+            return match lock.start_ts == start_ts {
+                true => StorageResponse::Ok, // Retried prewrite
+                false => StorageResponse::Conflict,
+            };
+        }
+        let written_after = row
+            .writes
+            .range(start_ts..)
+            .any(|(_, write)| matches!(write, Write::Put(_)));
+        if written_after || row.rolled_back(start_ts) {
+            return StorageResponse::Conflict;
+        }
+        self.persist(Mutation::Prewrite(key, value, start_ts, primary, now()));
+        StorageResponse::Ok
+    }
+
+    fn commit(&mut self, key: Key, start_ts: Timestamp, commit_ts: Timestamp) -> StorageResponse {
+        let row = self.row(key);
+        if row.locked_by(start_ts) {
+            self.persist(Mutation::Commit(key, start_ts, commit_ts));
+            return StorageResponse::Ok;
+        }
+        match row.commit_ts_of(start_ts) {
+            Some(_) => StorageResponse::Ok, // Retried commit or rolled forward
+            None => StorageResponse::Conflict,
+        }
+    }
+
+    // Decides the fate of a transaction: its primary lock is the commit point
+    fn check_primary(
+        &mut self,
+        primary: Key,
+        start_ts: Timestamp,
+        rollback: bool,
+    ) -> StorageResponse {
+        let row = self.row(primary);
+        if row.locked_by(start_ts) && !rollback {
+            return StorageResponse::PrimaryLocked;
+        }
+        if let Some(commit_ts) = row.commit_ts_of(start_ts) {
+            return StorageResponse::PrimaryCommitted(commit_ts);
+        }
+        if !row.rolled_back(start_ts) {
+            self.persist(Mutation::Rollback(primary, start_ts));
+        }
+        StorageResponse::PrimaryRolledBack
+    }
+
+    fn resolve_lock(
+        &mut self,
+        key: Key,
+        start_ts: Timestamp,
+        commit_ts: Option<Timestamp>,
+    ) -> StorageResponse {
+        if self.row(key).locked_by(start_ts) {
+            match commit_ts {
+                Some(commit_ts) => self.persist(Mutation::Commit(key, start_ts, commit_ts)),
+                None => self.persist(Mutation::Rollback(key, start_ts)),
+            }
+        }
+        StorageResponse::Ok
+    }
+
+    fn persist(&mut self, mutation: Mutation) {
+        self.apply(&mutation);
+        wal::append(mutation);
+        self.syncs.push_back((wal::sync(), Vec::new()));
+    }
+
+    fn apply(&mut self, mutation: &Mutation) {
+        match *mutation {
+            Mutation::Prewrite(key, value, start_ts, primary, at) => {
+                let row = self.row(key);
+                row.data.insert(start_ts, value);
+                row.lock = Some(Lock {
+                    start_ts,
+                    primary,
+                    at,
+                });
+            }
+            Mutation::Commit(key, start_ts, commit_ts) => {
+                let row = self.row(key);
+                row.writes.insert(commit_ts, Write::Put(start_ts));
+                row.lock = None;
+            }
+            Mutation::Rollback(key, start_ts) => {
+                let row = self.row(key);
+                if row.locked_by(start_ts) {
+                    row.lock = None;
+                    row.data.remove(&start_ts);
+                }
+                row.writes.insert(start_ts, Write::Rollback);
+            }
+        }
+    }
+
+    // Replies never overtake mutations they may depend on
+    fn reply(&mut self, to: ProcessId, reply: StorageReply) {
+        match self.syncs.back_mut() {
+            Some((_, replies)) => replies.push((to, reply)),
+            None => send_to(to, reply),
+        }
+    }
+}
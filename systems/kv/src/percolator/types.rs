@@ -0,0 +1,16 @@
+use dscale::{ProcessId, list_pool};
+
+pub type Key = usize;
+pub type Value = usize;
+pub type Timestamp = usize;
+pub type RpcId = usize;
+
+pub const SHARD_POOL_NAME: &str = "Shards";
+pub const ORACLE_POOL_NAME: &str = "Oracle";
+pub const TXN_CLIENT_POOL_NAME: &str = "TxnClients";
+
+// Rows are hash partitioned between shards
+pub fn shard_of(key: Key) -> ProcessId {
+    let shards = list_pool(SHARD_POOL_NAME);
+    shards[key % shards.len()]
+}
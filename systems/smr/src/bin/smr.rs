@@ -0,0 +1,65 @@
+use dag_based::{
+    bullshark::BullsharkLayer, ordered_sink::TieBreak, rider::DAGRiderLayer,
+    sparse_bullshark::SparseBullsharkLayer,
+};
+use dscale::{global::anykv, *};
+use smr::{Replica, checker::Checkpoints, state_machine::KvStateMachine};
+
+fn run<P: ProcessHandle + Default + 'static>(name: &str, tie_break: TieBreak) {
+    anykv::set::<(f64, usize)>("avg_latency", (0.0, 0));
+    anykv::set::<(f64, usize)>("avg_virtual_size", (0.0, 0));
+    anykv::set::<usize>("D", 4); // SparseBullshark sample size
+    anykv::set::<Checkpoints>("smr_checkpoints", Checkpoints::default());
+    anykv::set::<TieBreak>("tie_break", tie_break);
+
+    let mut sim = SimulationBuilder::default()
+        .add_pool::<P>("Validators", 10)
+        .latency_topology(&[LatencyDescription::WithinPool(
+            "Validators",
+            Distributions::Normal(Jiffies(50), Jiffies(10)),
+        )])
+        .time_budget(Jiffies(60_000))
+        .seed(123)
+        .build();
+
+    sim.run();
+
+    let checkpoints = anykv::get::<Checkpoints>("smr_checkpoints");
+    println!(
+        "{name}: checkpoints: {}, verified: {}, divergences: {}",
+        checkpoints.checkpoints_taken(),
+        checkpoints.verified,
+        checkpoints.divergences.len()
+    );
+
+    if let Some(d) = checkpoints.first_divergence() {
+        panic!(
+            "{name}: P{} diverged from P{} in {} after {} applied commands (detected at {})",
+            d.diverged, d.reference, d.field, d.applied, d.detected_at
+        );
+    }
+    assert!(
+        checkpoints.verified > 0,
+        "{name}: nothing was cross-checked"
+    );
+}
+
+fn main() {
+    run::<Replica<BullsharkLayer, KvStateMachine>>("Bullshark", TieBreak::Traversal);
+    run::<Replica<SparseBullsharkLayer, KvStateMachine>>("SparseBullshark", TieBreak::Traversal);
+    run::<Replica<DAGRiderLayer, KvStateMachine>>("DAGRider", TieBreak::Traversal);
+
+    // Every rule of ordering within an anchor must keep replicas identical
+    [
+        TieBreak::Source,
+        TieBreak::RoundThenSource,
+        TieBreak::Digest,
+    ]
+    .into_iter()
+    .for_each(|tie_break| {
+        run::<Replica<BullsharkLayer, KvStateMachine>>(
+            &format!("Bullshark, tie break by {tie_break:?}"),
+            tie_break,
+        )
+    });
+}
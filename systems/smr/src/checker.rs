@@ -0,0 +1,94 @@
+use std::collections::HashMap;
+
+use dscale::{Jiffies, ProcessId, global::anykv, now, rank};
+
+use crate::state_machine::StateMachine;
+
+// Replicas report state digest every CHECKPOINT_INTERVAL applied commands
+pub const CHECKPOINT_INTERVAL: usize = 100;
+
+#[derive(Clone, Debug)]
+pub struct Divergence {
+    pub applied: usize,
+    pub reference: ProcessId,
+    pub diverged: ProcessId,
+    pub detected_at: Jiffies,
+    pub field: String, // First field of StateMachine::inspect which differs
+}
+
+// Reference state of a checkpoint: reported first, by some replica
+#[derive(Clone)]
+struct Snapshot {
+    replica: ProcessId,
+    digest: u64,
+    fields: Vec<(String, u64)>,
+}
+
+// Digests are compared at identical log positions, so replicas progressing
+// at different speeds are still comparable.
+#[derive(Clone, Default)]
+pub struct Checkpoints {
+    snapshots: HashMap<usize, Snapshot>,
+    pub verified: usize,
+    pub divergences: Vec<Divergence>,
+}
+
+impl Checkpoints {
+    pub fn checkpoints_taken(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    // Earliest in the log; ties broken by simulated time of detection
+    pub fn first_divergence(&self) -> Option<&Divergence> {
+        self.divergences
+            .iter()
+            .min_by_key(|d| (d.applied, d.detected_at))
+    }
+}
+
+pub(crate) fn report_digest<S: StateMachine>(applied: usize, state: &S) {
+    let digest = state.digest();
+    anykv::modify::<Checkpoints>("smr_checkpoints", |checkpoints| {
+        match checkpoints.snapshots.get(&applied) {
+            None => {
+                checkpoints.snapshots.insert(
+                    applied,
+                    Snapshot {
+                        replica: rank(),
+                        digest,
+                        fields: state.inspect(),
+                    },
+                );
+            }
+            Some(reference) if reference.digest == digest => {
+                checkpoints.verified += 1;
+            }
+            Some(reference) => {
+                let field = first_differing_field(&reference.fields, &state.inspect());
+                log::error!(
+                    "State divergence at applied index {applied}: P{} differs from P{} in {field}",
+                    rank(),
+                    reference.replica
+                );
+                checkpoints.divergences.push(Divergence {
+                    applied,
+                    reference: reference.replica,
+                    diverged: rank(),
+                    detected_at: now(),
+                    field,
+                });
+            }
+        }
+    });
+}
+
+// Digests differ, yet fields may not if inspect() does not cover the whole state
+fn first_differing_field(reference: &[(String, u64)], actual: &[(String, u64)]) -> String {
+    reference
+        .iter()
+        .zip(actual)
+        .find(|(expected, actual)| expected != actual)
+        .map_or("<not inspected>".to_string(), |(expected, _)| {
+            expected.0.clone()
+        })
+}
@@ -0,0 +1,5 @@
+pub mod checker;
+pub mod replica;
+pub mod state_machine;
+
+pub use replica::Replica;
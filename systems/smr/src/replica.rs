@@ -0,0 +1,69 @@
+use std::marker::PhantomData;
+
+use dag_based::{consistent_broadcast::ByzantineConsistentBroadcast, ordered_sink::OrderingEngine};
+use dscale::{
+    MessagePtr, ProcessId, TimerId,
+    stack::{Layer, LayerContext, Stack, StackDefinition, Stacked},
+};
+
+use crate::{
+    checker::{CHECKPOINT_INTERVAL, report_digest},
+    state_machine::StateMachine,
+};
+
+// Runs consensus layer as is and applies its committed sequence
+// to a deterministic state machine after every step of the engine.
+#[derive(Default)]
+pub struct ReplicaLayer<C: OrderingEngine + Default, S: StateMachine> {
+    engine: C,
+    state: S,
+    applied: usize,
+}
+
+impl<C: OrderingEngine + Default, S: StateMachine> ReplicaLayer<C, S> {
+    fn apply_committed(&mut self) {
+        let committed: Vec<_> = self.engine.ordered_sink().drain().collect();
+        for entry in committed {
+            self.state.apply(S::command(&entry));
+            self.applied += 1;
+            if self.applied.is_multiple_of(CHECKPOINT_INTERVAL) {
+                report_digest(self.applied, &self.state);
+            }
+        }
+    }
+}
+
+impl<C: OrderingEngine + Default, S: StateMachine> Layer for ReplicaLayer<C, S> {
+    fn start(&mut self, context: &mut LayerContext) {
+        self.engine.ordered_sink().attach();
+        self.engine.start(context);
+        self.apply_committed();
+    }
+
+    fn on_deliver(&mut self, from: ProcessId, message: MessagePtr, context: &mut LayerContext) {
+        self.engine.on_deliver(from, message, context);
+        self.apply_committed();
+    }
+
+    fn on_timer(&mut self, id: TimerId, context: &mut LayerContext) {
+        self.engine.on_timer(id, context);
+        self.apply_committed();
+    }
+}
+
+// Replica process: engine of C wrapped by the replica on top of consistent broadcast
+pub struct ReplicaStack<C, S>(PhantomData<(C, S)>);
+
+impl<C, S> StackDefinition for ReplicaStack<C, S>
+where
+    C: OrderingEngine + Default + 'static,
+    S: StateMachine + 'static,
+{
+    fn stack() -> Stack {
+        Stack::new()
+            .layer(ByzantineConsistentBroadcast::default())
+            .layer(ReplicaLayer::<C, S>::default())
+    }
+}
+
+pub type Replica<C, S> = Stacked<ReplicaStack<C, S>>;
@@ -0,0 +1,86 @@
+use std::{
+    collections::BTreeMap,
+    hash::{DefaultHasher, Hash, Hasher},
+};
+
+use dag_based::ordered_sink::OrderedVertex;
+
+pub trait StateMachine: Default {
+    type Command;
+
+    // Vertices carry no payload (infinite source of client txns),
+    // so commands are derived deterministically from the committed vertex identity.
+    fn command(entry: &OrderedVertex) -> Self::Command;
+
+    fn apply(&mut self, command: Self::Command);
+
+    fn digest(&self) -> u64;
+
+    // Digests of individual fields, so a divergence can be narrowed down to the
+    // first differing one. Fields must be listed in the same order by every replica.
+    fn inspect(&self) -> Vec<(String, u64)> {
+        vec![("state".to_string(), self.digest())]
+    }
+}
+
+pub type Key = usize;
+pub type Value = usize;
+
+const KEYSPACE: usize = 64;
+
+pub enum KvCommand {
+    Put(Key, Value),
+    Add(Key, Value),
+    Delete(Key),
+}
+
+// Commands do not commute: any divergence in the committed order changes the digest
+#[derive(Default)]
+pub struct KvStateMachine {
+    store: BTreeMap<Key, Value>,
+}
+
+impl StateMachine for KvStateMachine {
+    type Command = KvCommand;
+
+    fn command(entry: &OrderedVertex) -> KvCommand {
+        let key = (entry.round * 31 + entry.source) % KEYSPACE;
+        match (entry.round + entry.source) % 3 {
+            0 => KvCommand::Put(key, entry.round),
+            1 => KvCommand::Add(key, entry.source),
+            _ => KvCommand::Delete(key),
+        }
+    }
+
+    fn apply(&mut self, command: KvCommand) {
+        match command {
+            KvCommand::Put(key, value) => {
+                self.store.insert(key, value);
+            }
+            KvCommand::Add(key, value) => {
+                *self.store.entry(key).or_default() += value;
+            }
+            KvCommand::Delete(key) => {
+                self.store.remove(&key);
+            }
+        }
+    }
+
+    fn digest(&self) -> u64 {
+        hash(&self.store)
+    }
+
+    // Every key of the keyspace is a field, absent keys included
+    fn inspect(&self) -> Vec<(String, u64)> {
+        (0..KEYSPACE)
+            .map(|key| (format!("key {key}"), hash(&self.store.get(&key))))
+            .collect()
+    }
+}
+
+// DefaultHasher::new() uses fixed keys -> identical digests across replicas
+fn hash(value: &impl Hash) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}